@@ -568,9 +568,10 @@ def external_crates_repository(name, annotations):
                 version = "^0.11.0",
             ),
             "p256": crate.spec(
-                version = "^0.10",
+                version = "^0.11.1",
                 features = [
                     "arithmetic",
+                    "ecdsa",
                 ],
                 default_features = False,
             ),
@@ -660,6 +661,13 @@ def external_crates_repository(name, annotations):
             "quickcheck": crate.spec(
                 version = "^1.0.3",
             ),
+            "quinn": crate.spec(
+                version = "^0.7.2",
+                default_features = False,
+                features = [
+                    "tls-rustls",
+                ],
+            ),
             "quote": crate.spec(
                 version = "^1.0",
             ),
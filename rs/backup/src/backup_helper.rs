@@ -1,3 +1,4 @@
+use crate::content_store::{ContentStore, RetentionPolicy};
 use crate::notification_client::NotificationClient;
 use crate::util::{block_on, sleep_secs};
 use ic_recovery::command_helper::exec_cmd;
@@ -5,7 +6,7 @@ use ic_recovery::file_sync_helper::download_binary;
 use ic_registry_client::client::{RegistryClient, RegistryClientImpl};
 use ic_registry_client_helpers::node::NodeRegistry;
 use ic_registry_client_helpers::subnet::SubnetRegistry;
-use ic_types::{ReplicaVersion, SubnetId};
+use ic_types::{Height, ReplicaVersion, SubnetId};
 
 use rand::seq::SliceRandom;
 use rand::thread_rng;
@@ -32,6 +33,7 @@ pub struct BackupHelper {
     pub notification_client: NotificationClient,
     pub downloads: Arc<Mutex<bool>>,
     pub disk_threshold_warn: u32,
+    pub content_store: Option<(Arc<ContentStore>, RetentionPolicy)>,
     pub log: Logger,
 }
 
@@ -433,7 +435,10 @@ impl BackupHelper {
             DiskStats::Inodes => "-i",
             DiskStats::Space => "-k",
         });
-        cmd.arg(self.archive_root_dir());
+        match &self.content_store {
+            Some((store, _)) => cmd.arg(store.root()),
+            None => cmd.arg(self.archive_root_dir()),
+        };
         match exec_cmd(&mut cmd) {
             Ok(str) => {
                 if let Some(val) = str
@@ -469,6 +474,70 @@ impl BackupHelper {
     }
 
     fn archive_state(&self, last_height: u64) -> Result<(), String> {
+        let result = match &self.content_store {
+            Some((store, retention_policy)) => {
+                self.store_checkpoint(store, retention_policy, last_height)
+            }
+            None => self.copy_state(last_height),
+        };
+        if let Err(e) = result {
+            error!(self.log, "Error: {}", e);
+            self.notification_client
+                .report_failure_slack("Couldn't backup the recovered state!".to_string());
+            return Err(e);
+        }
+        info!(self.log, "State archived!");
+
+        match (
+            self.get_disk_stats(DiskStats::Space),
+            self.get_disk_stats(DiskStats::Inodes),
+        ) {
+            (Ok(space), Ok(inodes)) => {
+                info!(self.log, "Space: {} Inodes: {}", space, inodes);
+                self.notification_client
+                    .push_metrics_disk_stats(space, inodes);
+                Ok(())
+            }
+            (Err(err), Ok(_)) => Err(err),
+            (_, Err(err)) => Err(err),
+        }
+    }
+
+    fn store_checkpoint(
+        &self,
+        store: &ContentStore,
+        retention_policy: &RetentionPolicy,
+        last_height: u64,
+    ) -> Result<(), String> {
+        let checkpoint_dir = self
+            .state_dir()
+            .join(format!("checkpoints/{:016x}", last_height));
+        info!(
+            self.log,
+            "Storing checkpoint: {} in the content store",
+            checkpoint_dir.to_string_lossy()
+        );
+        let stats =
+            store.store_checkpoint(&self.subnet_id, Height::from(last_height), &checkpoint_dir)?;
+        info!(
+            self.log,
+            "Stored {} new chunks ({} bytes) out of {}",
+            stats.new_chunks,
+            stats.new_bytes,
+            stats.chunks
+        );
+        let stats = store.gc(retention_policy)?;
+        info!(
+            self.log,
+            "Removed {} checkpoints and {} chunks ({} bytes) from the content store",
+            stats.removed_checkpoints,
+            stats.removed_chunks,
+            stats.removed_bytes
+        );
+        Ok(())
+    }
+
+    fn copy_state(&self, last_height: u64) -> Result<(), String> {
         let state_dir = self.data_dir().join(".");
         let archive_dir = self.archive_dir(last_height);
         info!(
@@ -490,27 +559,6 @@ impl BackupHelper {
         }
         cmd.arg(state_dir).arg(&archive_dir);
         info!(self.log, "Will execute: {:?}", cmd);
-        if let Err(e) = exec_cmd(&mut cmd) {
-            error!(self.log, "Error: {}", e);
-            self.notification_client
-                .report_failure_slack("Couldn't backup the recovered state!".to_string());
-            Err(e.to_string())
-        } else {
-            info!(self.log, "State archived!");
-
-            match (
-                self.get_disk_stats(DiskStats::Space),
-                self.get_disk_stats(DiskStats::Inodes),
-            ) {
-                (Ok(space), Ok(inodes)) => {
-                    info!(self.log, "Space: {} Inodes: {}", space, inodes);
-                    self.notification_client
-                        .push_metrics_disk_stats(space, inodes);
-                    Ok(())
-                }
-                (Err(err), Ok(_)) => Err(err),
-                (_, Err(err)) => Err(err),
-            }
-        }
+        exec_cmd(&mut cmd).map(|_| ()).map_err(|e| e.to_string())
     }
 }
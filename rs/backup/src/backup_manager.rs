@@ -20,6 +20,7 @@ use slog::{error, info, Logger};
 use tokio::runtime::Handle;
 
 use crate::config::Config;
+use crate::content_store::ContentStore;
 use crate::util::{block_on, sleep_secs};
 use crate::{backup_helper::BackupHelper, notification_client::NotificationClient};
 
@@ -129,6 +130,12 @@ impl BackupManager {
 
         let downloads = Arc::new(Mutex::new(true));
         let disk_threshold_warn = config.disk_threshold_warn;
+        let content_store = config.content_store.clone().map(|retention_policy| {
+            (
+                Arc::new(ContentStore::new(config.content_store_dir())),
+                retention_policy,
+            )
+        });
 
         for s in config.subnets {
             let replica_version = fetch_value_or_default(
@@ -153,6 +160,7 @@ impl BackupManager {
                 notification_client,
                 downloads: downloads.clone(),
                 disk_threshold_warn,
+                content_store: content_store.clone(),
                 log: log.clone(),
             };
             let sync_period = std::time::Duration::from_secs(s.sync_period_secs);
@@ -1,4 +1,5 @@
 use clap::Parser;
+use ic_types::PrincipalId;
 
 use std::path::PathBuf;
 
@@ -8,4 +9,30 @@ pub struct BackupArgs {
     /// Path to the config file
     #[clap(long)]
     pub config_file: PathBuf,
+
+    /// Operation on the content store; if omitted, the backups are run
+    #[clap(subcommand)]
+    pub subcmd: Option<SubCommand>,
+}
+
+#[derive(Parser)]
+pub enum SubCommand {
+    /// Reassemble a checkpoint from the content store and verify its root hash
+    Restore(RestoreCmd),
+    /// Remove the checkpoints and chunks from the content store that are not
+    /// retained by the configured retention policy
+    Gc,
+}
+
+#[derive(Parser)]
+pub struct RestoreCmd {
+    /// Subnet of the checkpoint
+    #[clap(long)]
+    pub subnet_id: PrincipalId,
+    /// Height of the checkpoint
+    #[clap(long)]
+    pub height: u64,
+    /// Directory to restore the checkpoint to; must not exist
+    #[clap(long)]
+    pub output: PathBuf,
 }
@@ -1,3 +1,4 @@
+use crate::content_store::RetentionPolicy;
 use ic_config::{ConfigSource, ConfigValidate};
 use ic_types::{ReplicaVersion, SubnetId};
 use serde::{Deserialize, Serialize};
@@ -28,6 +29,11 @@ pub struct Config {
     pub disk_threshold_warn: u32,
     pub slack_token: String,
     pub subnets: Vec<SubnetConfig>,
+    /// If set, restored states are archived as checkpoints in a deduplicated
+    /// content-addressed store and pruned according to this policy, instead
+    /// of being copied in full for every height.
+    #[serde(default)]
+    pub content_store: Option<RetentionPolicy>,
 }
 
 impl ConfigValidate for Config {
@@ -44,6 +50,11 @@ impl ConfigValidate for Config {
         if self.disk_threshold_warn > 100 {
             return Err("Disk threshhold warning value is > 100".to_string());
         }
+        if let Some(policy) = &self.content_store {
+            if policy.keep_last == 0 {
+                return Err("The content store has to keep at least one checkpoint!".to_string());
+            }
+        }
         if self.subnets.is_empty() {
             return Err("No subnet configured for backup!".to_string());
         }
@@ -52,6 +63,10 @@ impl ConfigValidate for Config {
 }
 
 impl Config {
+    pub fn content_store_dir(&self) -> PathBuf {
+        self.root_dir.join("content_store")
+    }
+
     pub fn load_config(config_path: PathBuf) -> Result<Config, String> {
         let config: Config = ConfigSource::File(config_path)
             .load()
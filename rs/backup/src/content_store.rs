@@ -0,0 +1,576 @@
+//! A deduplicated archive of checkpoints in a local directory.
+//!
+//! Checkpoints are split into the chunks of their state sync manifest and
+//! every chunk is stored once under its hash, so checkpoints (of any subnet)
+//! that share files or chunks don't use additional disk space for them:
+//!
+//! ```text
+//! <root>/
+//! ├── chunks
+//! │   └── <first two hex digits of the hash>
+//! │       └── <hex encoded chunk hash>
+//! └── checkpoints
+//!     └── <subnet id>
+//!         └── <height>    The protobuf encoded manifest of the checkpoint
+//! ```
+//!
+//! A checkpoint's manifest is written only after all of its chunks, and
+//! garbage collection removes manifests before the chunks they reference, so
+//! the store stays consistent if the process is interrupted.
+//!
+//! Several processes can share a store: writing chunks and manifests, garbage
+//! collection and restoring take an advisory lock on `<root>/lock`, so that a
+//! chunk that is about to be referenced by a new checkpoint or is being read
+//! is never collected. Manifests are computed before taking the lock.
+
+use ic_logger::replica_logger::no_op_logger;
+use ic_metrics::MetricsRegistry;
+use ic_state_layout::{CheckpointLayout, ReadOnly};
+use ic_state_manager::{
+    manifest::{compute_manifest, manifest_hash, DEFAULT_CHUNK_SIZE},
+    ManifestMetrics,
+};
+use ic_types::{
+    state_sync::{decode_manifest, encode_manifest, Manifest},
+    Height, SubnetId,
+};
+use nix::fcntl::{flock, FlockArg};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::os::unix::{fs::FileExt, io::AsRawFd};
+use std::path::{Path, PathBuf};
+
+const CHUNKS_DIR: &str = "chunks";
+const CHECKPOINTS_DIR: &str = "checkpoints";
+const LOCK_FILE: &str = "lock";
+
+/// Determines which archived checkpoints survive garbage collection.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    /// Number of the most recent checkpoints kept per subnet.
+    pub keep_last: usize,
+    /// If set, checkpoints at heights that are multiples of this value are
+    /// kept forever.
+    #[serde(default)]
+    pub keep_every_nth_height: Option<u64>,
+}
+
+impl RetentionPolicy {
+    /// Returns the heights among `heights` (sorted in ascending order) that
+    /// are not retained.
+    fn expired(&self, heights: &[Height]) -> Vec<Height> {
+        let recent = heights.len().saturating_sub(self.keep_last);
+        heights[..recent]
+            .iter()
+            .filter(|h| match self.keep_every_nth_height {
+                Some(n) if n > 0 => h.get() % n != 0,
+                _ => true,
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+/// Summary of storing a single checkpoint.
+#[derive(Debug, Default)]
+pub struct StoreStats {
+    pub chunks: usize,
+    pub new_chunks: usize,
+    pub new_bytes: u64,
+}
+
+/// Summary of a garbage collection.
+#[derive(Debug, Default)]
+pub struct GcStats {
+    pub removed_checkpoints: usize,
+    pub removed_chunks: usize,
+    pub removed_bytes: u64,
+}
+
+pub struct ContentStore {
+    root: PathBuf,
+}
+
+impl ContentStore {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Takes the lock of the store with `arg`, blocking until it is available.
+    /// The lock is held by the returned file until it is dropped. As every
+    /// call opens the lock file anew, the lock also excludes other threads of
+    /// the same process.
+    fn lock(&self, arg: FlockArg) -> Result<File, String> {
+        fs::create_dir_all(&self.root).map_err(|err| {
+            format!(
+                "Failed to create directory {}: {}",
+                self.root.display(),
+                err
+            )
+        })?;
+        let path = self.root.join(LOCK_FILE);
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .open(&path)
+            .map_err(|err| format!("Failed to open {}: {}", path.display(), err))?;
+        flock(file.as_raw_fd(), arg)
+            .map_err(|err| format!("Failed to lock {}: {}", path.display(), err))?;
+        Ok(file)
+    }
+
+    fn chunk_file(&self, hash: &[u8; 32]) -> PathBuf {
+        let hash = hex::encode(hash);
+        self.root.join(CHUNKS_DIR).join(&hash[..2]).join(hash)
+    }
+
+    fn subnet_dir(&self, subnet_id: &SubnetId) -> PathBuf {
+        self.root.join(CHECKPOINTS_DIR).join(subnet_id.to_string())
+    }
+
+    fn manifest_file(&self, subnet_id: &SubnetId, height: Height) -> PathBuf {
+        self.subnet_dir(subnet_id).join(height.to_string())
+    }
+
+    /// Archives the checkpoint at `checkpoint_dir` as the checkpoint of the
+    /// given subnet at `height`, storing only the chunks the store doesn't
+    /// contain yet.
+    pub fn store_checkpoint(
+        &self,
+        subnet_id: &SubnetId,
+        height: Height,
+        checkpoint_dir: &Path,
+    ) -> Result<StoreStats, String> {
+        let state_sync_version =
+            CheckpointLayout::<ReadOnly>::new(checkpoint_dir.to_path_buf(), height)
+                .and_then(|layout| layout.system_metadata().deserialize())
+                .map_err(|err| {
+                    format!(
+                        "Failed to read the state sync version of checkpoint {}: {}",
+                        checkpoint_dir.display(),
+                        err
+                    )
+                })?
+                .state_sync_version;
+        let manifest = compute_checkpoint_manifest(checkpoint_dir, state_sync_version)?;
+
+        let _lock = self.lock(FlockArg::LockExclusive)?;
+        let mut stats = StoreStats {
+            chunks: manifest.chunk_table.len(),
+            ..Default::default()
+        };
+        let mut open_file: Option<(u32, File)> = None;
+        for chunk in manifest.chunk_table.iter() {
+            let chunk_file = self.chunk_file(&chunk.hash);
+            if chunk_file.exists() {
+                continue;
+            }
+            if open_file.as_ref().map(|(index, _)| *index) != Some(chunk.file_index) {
+                let path = checkpoint_dir
+                    .join(&manifest.file_table[chunk.file_index as usize].relative_path);
+                let file = File::open(&path)
+                    .map_err(|err| format!("Failed to open {}: {}", path.display(), err))?;
+                open_file = Some((chunk.file_index, file));
+            }
+            let (_, file) = open_file.as_ref().unwrap();
+            let mut buf = vec![0; chunk.size_bytes as usize];
+            file.read_exact_at(&mut buf, chunk.offset)
+                .map_err(|err| format!("Failed to read chunk {:?}: {}", chunk, err))?;
+            write_atomically(&chunk_file, &buf)?;
+            stats.new_chunks += 1;
+            stats.new_bytes += chunk.size_bytes as u64;
+        }
+
+        write_atomically(
+            &self.manifest_file(subnet_id, height),
+            &encode_manifest(&manifest),
+        )?;
+        Ok(stats)
+    }
+
+    /// Returns the heights of all archived checkpoints of the subnet in
+    /// ascending order.
+    pub fn heights(&self, subnet_id: &SubnetId) -> Result<Vec<Height>, String> {
+        let dir = self.subnet_dir(subnet_id);
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut heights = Vec::new();
+        for entry in read_dir(&dir)? {
+            let name = entry.file_name();
+            // Skips leftovers of interrupted writes.
+            if let Some(height) = name.to_str().and_then(|name| name.parse().ok()) {
+                heights.push(Height::new(height));
+            }
+        }
+        heights.sort();
+        Ok(heights)
+    }
+
+    fn read_manifest(&self, subnet_id: &SubnetId, height: Height) -> Result<Manifest, String> {
+        let path = self.manifest_file(subnet_id, height);
+        let bytes = fs::read(&path).map_err(|err| {
+            format!(
+                "No checkpoint of subnet {} at height {} in the store: {}",
+                subnet_id, height, err
+            )
+        })?;
+        decode_manifest(&bytes)
+    }
+
+    /// Reassembles the archived checkpoint of the subnet at `height` in the
+    /// new directory `out` and verifies that its root hash matches the one of
+    /// the archived checkpoint. Returns the root hash.
+    pub fn restore_checkpoint(
+        &self,
+        subnet_id: &SubnetId,
+        height: Height,
+        out: &Path,
+    ) -> Result<[u8; 32], String> {
+        let lock = self.lock(FlockArg::LockShared)?;
+        let manifest = self.read_manifest(subnet_id, height)?;
+        if out.exists() {
+            return Err(format!("{} already exists", out.display()));
+        }
+
+        let mut files = Vec::with_capacity(manifest.file_table.len());
+        for file_info in manifest.file_table.iter() {
+            let path = out.join(&file_info.relative_path);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).map_err(|err| {
+                    format!("Failed to create directory {}: {}", parent.display(), err)
+                })?;
+            }
+            let file = OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+                .map_err(|err| format!("Failed to create {}: {}", path.display(), err))?;
+            file.set_len(file_info.size_bytes)
+                .map_err(|err| format!("Failed to resize {}: {}", path.display(), err))?;
+            files.push(file);
+        }
+        for chunk in manifest.chunk_table.iter() {
+            let chunk_file = self.chunk_file(&chunk.hash);
+            let bytes = fs::read(&chunk_file)
+                .map_err(|err| format!("Failed to read chunk {}: {}", chunk_file.display(), err))?;
+            files[chunk.file_index as usize]
+                .write_all_at(&bytes, chunk.offset)
+                .map_err(|err| format!("Failed to write chunk {:?}: {}", chunk, err))?;
+        }
+        for file in files {
+            file.sync_all()
+                .map_err(|err| format!("Failed to sync restored file: {}", err))?;
+        }
+        drop(lock);
+
+        let expected = manifest_hash(&manifest);
+        let actual = manifest_hash(&compute_checkpoint_manifest(out, manifest.version)?);
+        if actual != expected {
+            return Err(format!(
+                "Root hash mismatch of the restored checkpoint: expected {}, got {}",
+                hex::encode(expected),
+                hex::encode(actual)
+            ));
+        }
+        Ok(actual)
+    }
+
+    /// Removes the checkpoints of all subnets that are not retained by the
+    /// policy and all chunks that are no longer referenced.
+    pub fn gc(&self, policy: &RetentionPolicy) -> Result<GcStats, String> {
+        let _lock = self.lock(FlockArg::LockExclusive)?;
+        let mut stats = GcStats::default();
+        let checkpoints_dir = self.root.join(CHECKPOINTS_DIR);
+        let subnet_dirs = if checkpoints_dir.exists() {
+            read_dir(&checkpoints_dir)?
+        } else {
+            Vec::new()
+        };
+
+        let mut referenced = HashSet::new();
+        for subnet_dir in subnet_dirs {
+            let subnet_dir = subnet_dir.path();
+            let mut heights = BTreeSet::new();
+            for entry in read_dir(&subnet_dir)? {
+                match entry
+                    .file_name()
+                    .to_str()
+                    .and_then(|name| name.parse().ok())
+                {
+                    Some(height) => {
+                        heights.insert(Height::new(height));
+                    }
+                    None => remove_file(&entry.path())?,
+                }
+            }
+            let heights: Vec<_> = heights.into_iter().collect();
+            let expired = policy.expired(&heights);
+            for height in heights {
+                let path = subnet_dir.join(height.to_string());
+                if expired.contains(&height) {
+                    remove_file(&path)?;
+                    stats.removed_checkpoints += 1;
+                } else {
+                    let bytes = fs::read(&path)
+                        .map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+                    let manifest = decode_manifest(&bytes)?;
+                    referenced.extend(manifest.chunk_table.iter().map(|chunk| chunk.hash));
+                }
+            }
+        }
+
+        let chunks_dir = self.root.join(CHUNKS_DIR);
+        if !chunks_dir.exists() {
+            return Ok(stats);
+        }
+        for prefix_dir in read_dir(&chunks_dir)? {
+            for entry in read_dir(&prefix_dir.path())? {
+                let is_referenced = hex::decode(entry.file_name().to_string_lossy().as_ref())
+                    .ok()
+                    .and_then(|hash| <[u8; 32]>::try_from(hash).ok())
+                    .map_or(false, |hash| referenced.contains(&hash));
+                if !is_referenced {
+                    stats.removed_bytes += entry.metadata().map(|m| m.len()).unwrap_or(0);
+                    stats.removed_chunks += 1;
+                    remove_file(&entry.path())?;
+                }
+            }
+        }
+        Ok(stats)
+    }
+}
+
+fn compute_checkpoint_manifest(
+    checkpoint_dir: &Path,
+    state_sync_version: u32,
+) -> Result<Manifest, String> {
+    let mut thread_pool =
+        scoped_threadpool::Pool::new(ic_state_manager::NUMBER_OF_CHECKPOINT_THREADS);
+    let metrics_registry = MetricsRegistry::new();
+    compute_manifest(
+        &mut thread_pool,
+        &ManifestMetrics::new(&metrics_registry),
+        &no_op_logger(),
+        state_sync_version,
+        checkpoint_dir,
+        DEFAULT_CHUNK_SIZE,
+        None,
+    )
+    .map_err(|err| {
+        format!(
+            "Failed to compute the manifest of {}: {}",
+            checkpoint_dir.display(),
+            err
+        )
+    })
+}
+
+/// Writes `content` to a temporary file next to `path` and renames it, so
+/// that `path` either doesn't exist or is complete.
+fn write_atomically(path: &Path, content: &[u8]) -> Result<(), String> {
+    let parent = path.parent().expect("path has a parent");
+    fs::create_dir_all(parent)
+        .map_err(|err| format!("Failed to create directory {}: {}", parent.display(), err))?;
+    let tmp_path = path.with_extension("tmp");
+    let file = File::create(&tmp_path)
+        .map_err(|err| format!("Failed to create {}: {}", tmp_path.display(), err))?;
+    file.write_all_at(content, 0)
+        .and_then(|_| file.sync_all())
+        .map_err(|err| format!("Failed to write {}: {}", tmp_path.display(), err))?;
+    fs::rename(&tmp_path, path).map_err(|err| {
+        format!(
+            "Failed to rename {} to {}: {}",
+            tmp_path.display(),
+            path.display(),
+            err
+        )
+    })
+}
+
+fn read_dir(path: &Path) -> Result<Vec<fs::DirEntry>, String> {
+    fs::read_dir(path)
+        .and_then(|entries| entries.collect())
+        .map_err(|err| format!("Failed to read directory {}: {}", path.display(), err))
+}
+
+fn remove_file(path: &Path) -> Result<(), String> {
+    fs::remove_file(path).map_err(|err| format!("Failed to remove {}: {}", path.display(), err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_types::PrincipalId;
+
+    fn subnet_test_id(id: u64) -> SubnetId {
+        SubnetId::from(PrincipalId::new_subnet_test_id(id))
+    }
+
+    fn make_checkpoint(root: &Path, height: u64, heap: &[u8]) -> PathBuf {
+        let checkpoint = root.join(format!("{:016x}", height));
+        let layout =
+            CheckpointLayout::<ic_state_layout::RwPolicy>::new(checkpoint.clone(), Height::new(0))
+                .unwrap();
+        layout
+            .system_metadata()
+            .serialize(ic_protobuf::state::system_metadata::v1::SystemMetadata {
+                state_sync_version: ic_state_manager::manifest::CURRENT_STATE_SYNC_VERSION,
+                ..Default::default()
+            })
+            .unwrap();
+        let canister_dir = checkpoint
+            .join("canister_states")
+            .join("00000000001000000101");
+        fs::create_dir_all(&canister_dir).unwrap();
+        fs::write(canister_dir.join("vmemory_0.bin"), heap).unwrap();
+        fs::write(canister_dir.join("stable_memory.bin"), vec![7; 3 << 20]).unwrap();
+        fs::write(canister_dir.join("queues.pbuf"), b"").unwrap();
+        checkpoint
+    }
+
+    #[test]
+    fn shared_chunks_are_stored_once_and_checkpoints_can_be_restored() {
+        let tmp = tempfile::tempdir().unwrap();
+        let store = ContentStore::new(tmp.path().join("store"));
+        let subnet_id = subnet_test_id(1);
+        let first = make_checkpoint(&tmp.path().join("state"), 100, &[1; 1 << 20]);
+        let second = make_checkpoint(&tmp.path().join("state"), 200, &[2; 1 << 20]);
+
+        let stats = store
+            .store_checkpoint(&subnet_id, Height::new(100), &first)
+            .unwrap();
+        assert_eq!(stats.new_chunks, stats.chunks);
+        let stats = store
+            .store_checkpoint(&subnet_id, Height::new(200), &second)
+            .unwrap();
+        // Only the changed heap chunk is new.
+        assert_eq!(stats.new_chunks, 1);
+        assert_eq!(stats.new_bytes, 1 << 20);
+        assert_eq!(
+            store.heights(&subnet_id).unwrap(),
+            vec![Height::new(100), Height::new(200)]
+        );
+
+        let restored = tmp.path().join("restored");
+        store
+            .restore_checkpoint(&subnet_id, Height::new(100), &restored)
+            .unwrap();
+        let heap = Path::new("canister_states/00000000001000000101/vmemory_0.bin");
+        assert_eq!(
+            fs::read(restored.join(heap)).unwrap(),
+            fs::read(first.join(heap)).unwrap()
+        );
+        assert!(restored
+            .join("canister_states/00000000001000000101/queues.pbuf")
+            .exists());
+    }
+
+    #[test]
+    fn gc_removes_expired_checkpoints_and_unreferenced_chunks() {
+        let tmp = tempfile::tempdir().unwrap();
+        let store = ContentStore::new(tmp.path().join("store"));
+        let subnet_id = subnet_test_id(1);
+        for height in [100, 200, 300, 400] {
+            let checkpoint = make_checkpoint(
+                &tmp.path().join("state"),
+                height,
+                &vec![height as u8; 1 << 20],
+            );
+            store
+                .store_checkpoint(&subnet_id, Height::new(height), &checkpoint)
+                .unwrap();
+        }
+
+        let stats = store
+            .gc(&RetentionPolicy {
+                keep_last: 1,
+                keep_every_nth_height: Some(200),
+            })
+            .unwrap();
+        assert_eq!(stats.removed_checkpoints, 2);
+        assert_eq!(stats.removed_chunks, 2);
+        assert_eq!(
+            store.heights(&subnet_id).unwrap(),
+            vec![Height::new(200), Height::new(400)]
+        );
+        for height in [200, 400] {
+            store
+                .restore_checkpoint(
+                    &subnet_id,
+                    Height::new(height),
+                    &tmp.path().join(format!("restored_{}", height)),
+                )
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn gc_waits_for_the_lock_held_by_another_process() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().join("store");
+        let store = ContentStore::new(root.clone());
+        let subnet_id = subnet_test_id(1);
+        for height in [100, 200] {
+            let checkpoint = make_checkpoint(
+                &tmp.path().join("state"),
+                height,
+                &vec![height as u8; 1 << 20],
+            );
+            store
+                .store_checkpoint(&subnet_id, Height::new(height), &checkpoint)
+                .unwrap();
+        }
+
+        // Every call opens the lock file anew, so the lock taken here behaves
+        // like the lock of another process.
+        let other_process_lock = store.lock(FlockArg::LockExclusive).unwrap();
+        let (tx, rx) = std::sync::mpsc::channel();
+        let gc = std::thread::spawn(move || {
+            let result = ContentStore::new(root).gc(&RetentionPolicy {
+                keep_last: 1,
+                keep_every_nth_height: None,
+            });
+            tx.send(result).unwrap();
+        });
+        assert!(rx
+            .recv_timeout(std::time::Duration::from_millis(500))
+            .is_err());
+        assert_eq!(
+            store.heights(&subnet_id).unwrap(),
+            vec![Height::new(100), Height::new(200)]
+        );
+
+        drop(other_process_lock);
+        let stats = rx
+            .recv_timeout(std::time::Duration::from_secs(60))
+            .unwrap()
+            .unwrap();
+        gc.join().unwrap();
+        assert_eq!(stats.removed_checkpoints, 1);
+        assert_eq!(store.heights(&subnet_id).unwrap(), vec![Height::new(200)]);
+    }
+
+    #[test]
+    fn corrupted_chunk_fails_restore() {
+        let tmp = tempfile::tempdir().unwrap();
+        let store = ContentStore::new(tmp.path().join("store"));
+        let subnet_id = subnet_test_id(1);
+        let checkpoint = make_checkpoint(&tmp.path().join("state"), 100, &[1; 1 << 20]);
+        store
+            .store_checkpoint(&subnet_id, Height::new(100), &checkpoint)
+            .unwrap();
+        let manifest = store.read_manifest(&subnet_id, Height::new(100)).unwrap();
+        fs::write(store.chunk_file(&manifest.chunk_table[0].hash), vec![0; 16]).unwrap();
+
+        let err = store
+            .restore_checkpoint(&subnet_id, Height::new(100), &tmp.path().join("restored"))
+            .unwrap_err();
+        assert!(err.contains("Root hash mismatch"), "{}", err);
+    }
+}
@@ -2,5 +2,6 @@ pub mod backup_helper;
 pub mod backup_manager;
 pub mod cmd;
 pub mod config;
+pub mod content_store;
 pub mod notification_client;
 pub mod util;
@@ -1,5 +1,11 @@
 use clap::Parser;
-use ic_backup::{backup_manager::BackupManager, cmd::BackupArgs};
+use ic_backup::{
+    backup_manager::BackupManager,
+    cmd::{BackupArgs, SubCommand},
+    config::Config,
+    content_store::ContentStore,
+};
+use ic_types::{Height, SubnetId};
 use slog::{o, Drain};
 use std::sync::Arc;
 use tokio::runtime::Handle;
@@ -23,6 +29,10 @@ use tokio::task::spawn_blocking;
 //     "ssh_private_key": "/home/my_user/.ssh/id_ed25519_backup",
 //     "disk_threshold_warn": 75,
 //     "slack_token": "ABCD1234"
+//     "content_store": {
+//         "keep_last": 10,
+//         "keep_every_nth_height": 1000000
+//     },
 //     "subnets": [
 //       {
 //         "subnet_id": "ziu2q-il6zl-3654z-zcdg2-nbtx3-u2ba3-7yzey-flpky-aam7n-x53ip-uqe",
@@ -50,6 +60,37 @@ async fn main() {
     let log = slog::Logger::root(drain, o!());
 
     let args = BackupArgs::parse();
+    if let Some(subcmd) = args.subcmd {
+        let config = Config::load_config(args.config_file).expect("Config file can't be loaded");
+        let store = ContentStore::new(config.content_store_dir());
+        match subcmd {
+            SubCommand::Restore(cmd) => {
+                let root_hash = store
+                    .restore_checkpoint(
+                        &SubnetId::from(cmd.subnet_id),
+                        Height::from(cmd.height),
+                        &cmd.output,
+                    )
+                    .unwrap_or_else(|err| panic!("Restore failed: {}", err));
+                println!(
+                    "Restored checkpoint at height {} to {} with root hash {}",
+                    cmd.height,
+                    cmd.output.display(),
+                    hex::encode(root_hash)
+                );
+            }
+            SubCommand::Gc => {
+                let retention_policy = config
+                    .content_store
+                    .expect("No retention policy for the content store configured");
+                let stats = store
+                    .gc(&retention_policy)
+                    .unwrap_or_else(|err| panic!("Garbage collection failed: {}", err));
+                println!("{:?}", stats);
+            }
+        }
+        return;
+    }
     let rt = Handle::current();
     spawn_blocking(move || {
         let bm = BackupManager::new(args.config_file, &rt, log);
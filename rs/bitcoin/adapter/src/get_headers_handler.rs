@@ -0,0 +1,167 @@
+use std::sync::Arc;
+
+use bitcoin::{BlockHash, BlockHeader};
+use tokio::sync::Mutex;
+
+use crate::BlockchainState;
+
+// Max number of block headers that can be returned in the `GetHeadersResponse`.
+// Matches the maximum number of headers in a Bitcoin `headers` message.
+const MAX_HEADERS_LENGTH: usize = 2_000;
+
+#[derive(Debug)]
+pub struct GetHeadersRequest {
+    /// Hash of the block whose header is requested first.
+    pub tip: BlockHash,
+}
+
+#[derive(Debug)]
+pub struct GetHeadersResponse {
+    /// The header of the requested block followed by the headers of its
+    /// ancestors, in descending order of height.
+    pub headers: Vec<BlockHeader>,
+}
+
+/// Contains the functionality to respond to GetHeadersRequests via the RPC
+/// server. These are used by the Bitcoin canister to backfill the headers of
+/// its stable blocks.
+pub struct GetHeadersHandler {
+    state: Arc<Mutex<BlockchainState>>,
+}
+
+impl GetHeadersHandler {
+    /// Creates a GetHeadersHandler to be used to access the blockchain state
+    /// inside of the adapter when a `GetHeadersRequest` is received.
+    pub fn new(state: Arc<Mutex<BlockchainState>>) -> Self {
+        Self { state }
+    }
+
+    /// Handles a request for headers. The response contains the header of the
+    /// requested block followed by the headers of its ancestors, up to
+    /// `MAX_HEADERS_LENGTH` headers. The response is empty if the adapter doesn't
+    /// know the requested block (yet).
+    pub async fn get_headers(&self, request: GetHeadersRequest) -> GetHeadersResponse {
+        let state = self.state.lock().await;
+        GetHeadersResponse {
+            headers: get_ancestor_headers(&state, &request.tip),
+        }
+    }
+}
+
+// Walks the header cache from `tip` towards the genesis block.
+fn get_ancestor_headers(state: &BlockchainState, tip: &BlockHash) -> Vec<BlockHeader> {
+    let mut headers = vec![];
+    let mut block_hash = *tip;
+    while headers.len() < MAX_HEADERS_LENGTH {
+        match state.get_cached_header(&block_hash) {
+            Some(cached_header) => {
+                headers.push(cached_header.header);
+                block_hash = cached_header.header.prev_blockhash;
+            }
+            // The parent of the genesis block is not in the cache.
+            None => break,
+        }
+    }
+    headers
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use bitcoin::Network;
+    use ic_metrics::MetricsRegistry;
+
+    use crate::{common::test_common::generate_headers, config::test::ConfigBuilder};
+
+    /// This test ensures that `GetHeadersHandler::get_headers(...)` returns the requested
+    /// header followed by its ancestors down to the genesis block.
+    #[tokio::test]
+    async fn test_get_headers() {
+        let config = ConfigBuilder::new().with_network(Network::Regtest).build();
+        let mut blockchain_state = BlockchainState::new(&config, &MetricsRegistry::default());
+        let genesis = blockchain_state.genesis().clone();
+        let genesis_hash = genesis.header.block_hash();
+        // Set up the following chain:
+        // 0 -> 1 -> 2 -> 3 -> 4
+        // |--> 1'
+        let main_chain = generate_headers(genesis_hash, genesis.header.time, 4, &[]);
+        let side_chain = generate_headers(
+            genesis_hash,
+            genesis.header.time,
+            1,
+            &main_chain
+                .iter()
+                .map(|h| h.block_hash())
+                .collect::<Vec<_>>(),
+        );
+        blockchain_state.add_headers(&main_chain);
+        blockchain_state.add_headers(&side_chain);
+        let handler = GetHeadersHandler::new(Arc::new(Mutex::new(blockchain_state)));
+
+        let response = handler
+            .get_headers(GetHeadersRequest {
+                tip: main_chain[2].block_hash(),
+            })
+            .await;
+        assert_eq!(
+            response.headers,
+            vec![main_chain[2], main_chain[1], main_chain[0], genesis.header]
+        );
+
+        let response = handler
+            .get_headers(GetHeadersRequest {
+                tip: side_chain[0].block_hash(),
+            })
+            .await;
+        assert_eq!(response.headers, vec![side_chain[0], genesis.header]);
+    }
+
+    /// This test ensures that `GetHeadersHandler::get_headers(...)` returns an empty response
+    /// for an unknown block.
+    #[tokio::test]
+    async fn test_get_headers_of_unknown_block() {
+        let config = ConfigBuilder::new().with_network(Network::Regtest).build();
+        let blockchain_state = BlockchainState::new(&config, &MetricsRegistry::default());
+        let genesis = blockchain_state.genesis().clone();
+        let unknown_header =
+            generate_headers(genesis.header.block_hash(), genesis.header.time, 1, &[])[0];
+        let handler = GetHeadersHandler::new(Arc::new(Mutex::new(blockchain_state)));
+
+        let response = handler
+            .get_headers(GetHeadersRequest {
+                tip: unknown_header.block_hash(),
+            })
+            .await;
+        assert!(response.headers.is_empty());
+    }
+
+    /// This test ensures that at most `MAX_HEADERS_LENGTH` headers are returned.
+    #[tokio::test]
+    async fn test_get_headers_is_capped() {
+        let config = ConfigBuilder::new().with_network(Network::Regtest).build();
+        let mut blockchain_state = BlockchainState::new(&config, &MetricsRegistry::default());
+        let genesis = blockchain_state.genesis().clone();
+        let chain = generate_headers(
+            genesis.header.block_hash(),
+            genesis.header.time,
+            MAX_HEADERS_LENGTH as u32 + 10,
+            &[],
+        );
+        blockchain_state.add_headers(&chain);
+        let handler = GetHeadersHandler::new(Arc::new(Mutex::new(blockchain_state)));
+
+        let tip = chain.last().unwrap();
+        let response = handler
+            .get_headers(GetHeadersRequest {
+                tip: tip.block_hash(),
+            })
+            .await;
+        assert_eq!(response.headers.len(), MAX_HEADERS_LENGTH);
+        assert_eq!(response.headers[0], *tip);
+        assert_eq!(
+            response.headers.last().unwrap().block_hash(),
+            chain[chain.len() - MAX_HEADERS_LENGTH].block_hash()
+        );
+    }
+}
@@ -41,11 +41,13 @@ mod rpc_server;
 mod stream;
 mod transaction_manager;
 
+mod get_headers_handler;
 mod get_successors_handler;
 
 pub use blockchainmanager::BlockchainManager;
 pub use blockchainstate::BlockchainState;
 use common::BlockHeight;
+pub use get_headers_handler::GetHeadersHandler;
 pub use get_successors_handler::GetSuccessorsHandler;
 pub use router::start_router;
 pub use rpc_server::spawn_grpc_server;
@@ -3,7 +3,7 @@ use ic_adapter_metrics_server::start_metrics_grpc;
 use ic_async_utils::{abort_on_panic, shutdown_signal};
 use ic_btc_adapter::{
     cli::Cli, config::IncomingSource, spawn_grpc_server, start_router, AdapterState,
-    BlockchainState, GetSuccessorsHandler,
+    BlockchainState, GetHeadersHandler, GetSuccessorsHandler,
 };
 use ic_logger::{info, new_replica_logger_from_config};
 use ic_metrics::MetricsRegistry;
@@ -54,6 +54,7 @@ pub async fn main() {
     let blockchain_state = Arc::new(Mutex::new(BlockchainState::new(&config, &metrics_registry)));
     let get_successors_handler =
         GetSuccessorsHandler::new(&config, blockchain_state.clone(), blockchain_manager_tx);
+    let get_headers_handler = GetHeadersHandler::new(blockchain_state.clone());
 
     // TODO: we should NOT have an unbounded channel for buffering TransactionManagerRequests.
     let (transaction_manager_tx, transaction_manager_rx) = channel(10);
@@ -63,6 +64,7 @@ pub async fn main() {
         logger.clone(),
         adapter_state.clone(),
         get_successors_handler,
+        get_headers_handler,
         transaction_manager_tx,
         &metrics_registry,
     );
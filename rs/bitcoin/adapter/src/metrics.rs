@@ -1,6 +1,7 @@
 use ic_metrics::MetricsRegistry;
 use prometheus::{IntCounter, IntCounterVec, IntGauge};
 
+pub(crate) const LABEL_GET_HEADERS: &str = "get_headers";
 pub(crate) const LABEL_GET_SUCCESSOR: &str = "get_successor";
 pub(crate) const LABEL_REQUEST_TYPE: &str = "type";
 pub(crate) const LABEL_SEND_TRANSACTION: &str = "send_transaction";
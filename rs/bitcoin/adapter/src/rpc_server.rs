@@ -1,15 +1,17 @@
 use crate::{
     config::{Config, IncomingSource},
+    get_headers_handler::{GetHeadersRequest, GetHeadersResponse},
     get_successors_handler::{GetSuccessorsRequest, GetSuccessorsResponse},
-    metrics::{ServiceMetrics, LABEL_GET_SUCCESSOR, LABEL_SEND_TRANSACTION},
-    AdapterState, GetSuccessorsHandler, TransactionManagerRequest,
+    metrics::{ServiceMetrics, LABEL_GET_HEADERS, LABEL_GET_SUCCESSOR, LABEL_SEND_TRANSACTION},
+    AdapterState, GetHeadersHandler, GetSuccessorsHandler, TransactionManagerRequest,
 };
 use bitcoin::{consensus::Encodable, hashes::Hash, BlockHash};
 use ic_async_utils::{incoming_from_first_systemd_socket, incoming_from_path};
 use ic_btc_service::{
     btc_service_server::{BtcService, BtcServiceServer},
-    BtcServiceGetSuccessorsRequest, BtcServiceGetSuccessorsResponse,
-    BtcServiceSendTransactionRequest, BtcServiceSendTransactionResponse,
+    BtcServiceGetHeadersRequest, BtcServiceGetHeadersResponse, BtcServiceGetSuccessorsRequest,
+    BtcServiceGetSuccessorsResponse, BtcServiceSendTransactionRequest,
+    BtcServiceSendTransactionResponse,
 };
 use ic_logger::{debug, ReplicaLogger};
 use ic_metrics::MetricsRegistry;
@@ -20,6 +22,7 @@ use tonic::{transport::Server, Request, Response, Status};
 struct BtcServiceImpl {
     adapter_state: AdapterState,
     get_successors_handler: GetSuccessorsHandler,
+    get_headers_handler: GetHeadersHandler,
     transaction_manager_tx: Sender<TransactionManagerRequest>,
     logger: ReplicaLogger,
     metrics: ServiceMetrics,
@@ -72,6 +75,31 @@ impl TryFrom<GetSuccessorsResponse> for BtcServiceGetSuccessorsResponse {
     }
 }
 
+impl TryFrom<BtcServiceGetHeadersRequest> for GetHeadersRequest {
+    type Error = Status;
+
+    fn try_from(request: BtcServiceGetHeadersRequest) -> Result<Self, Self::Error> {
+        let tip = BlockHash::from_slice(request.tip.as_slice())
+            .map_err(|_| Status::unknown("Failed to parse tip hash!"))?;
+        Ok(GetHeadersRequest { tip })
+    }
+}
+
+impl TryFrom<GetHeadersResponse> for BtcServiceGetHeadersResponse {
+    type Error = Status;
+    fn try_from(response: GetHeadersResponse) -> Result<Self, Self::Error> {
+        let mut headers = vec![];
+        for block_header in response.headers.iter() {
+            let mut encoded_block_header = vec![];
+            block_header
+                .consensus_encode(&mut encoded_block_header)
+                .map_err(|_| Status::unknown("Failed to encode block header!"))?;
+            headers.push(encoded_block_header);
+        }
+        Ok(BtcServiceGetHeadersResponse { headers })
+    }
+}
+
 #[tonic::async_trait]
 impl BtcService for BtcServiceImpl {
     async fn get_successors(
@@ -117,6 +145,31 @@ impl BtcService for BtcServiceImpl {
             );
         Ok(Response::new(BtcServiceSendTransactionResponse {}))
     }
+
+    async fn get_headers(
+        &self,
+        request: Request<BtcServiceGetHeadersRequest>,
+    ) -> Result<Response<BtcServiceGetHeadersResponse>, Status> {
+        self.adapter_state.received_now();
+        let inner = request.into_inner();
+        debug!(self.logger, "Received GetHeadersRequest: {:?}", inner);
+        let request = inner.try_into()?;
+
+        self.metrics
+            .requests
+            .with_label_values(&[LABEL_GET_HEADERS])
+            .inc();
+
+        let response = BtcServiceGetHeadersResponse::try_from(
+            self.get_headers_handler.get_headers(request).await,
+        )?;
+        debug!(
+            self.logger,
+            "Sending GetHeadersResponse with {} headers",
+            response.headers.len()
+        );
+        Ok(Response::new(response))
+    }
 }
 
 /// Spawns in a separate Tokio task the BTC adapter gRPC service.
@@ -125,12 +178,14 @@ pub fn spawn_grpc_server(
     logger: ReplicaLogger,
     adapter_state: AdapterState,
     get_successors_handler: GetSuccessorsHandler,
+    get_headers_handler: GetHeadersHandler,
     transaction_manager_tx: Sender<TransactionManagerRequest>,
     metrics_registry: &MetricsRegistry,
 ) {
     let btc_adapter_impl = BtcServiceImpl {
         adapter_state,
         get_successors_handler,
+        get_headers_handler,
         transaction_manager_tx,
         logger,
         metrics: ServiceMetrics::new(metrics_registry),
@@ -2,8 +2,8 @@ pub use crate::fees::get_current_fee_percentiles;
 use crate::{metrics::BitcoinCanisterMetrics, state::State, store};
 use bitcoin::{util::psbt::serialize::Deserialize, Transaction};
 use ic_btc_types::{
-    GetBalanceError, GetUtxosError, GetUtxosResponse, SendTransactionError, SendTransactionRequest,
-    UtxosFilter,
+    GetBalanceError, GetBlockHeadersError, GetBlockHeadersResponse, GetUtxosError,
+    GetUtxosResponse, Height, SendTransactionError, SendTransactionRequest, UtxosFilter,
 };
 use ic_btc_types_internal::{
     BitcoinAdapterRequestWrapper, SendTransactionRequest as InternalSendTransactionRequest,
@@ -24,6 +24,13 @@ use ic_replicated_state::bitcoin_state::BitcoinStateError;
 // than 100_000 `Utxo`s are returned in a single response.
 const MAX_UTXOS_PER_RESPONSE: usize = 10_000;
 
+/// The maximum number of block headers that are included in a single
+/// `GetBlockHeadersResponse`.
+///
+/// A block header is 80 bytes, so a full response stays well below the max
+/// response payload size of 2MiB.
+pub const MAX_BLOCK_HEADERS_PER_RESPONSE: usize = 1_000;
+
 /// The Bitcoin Canister component.
 ///
 /// Maintains information that is needed to be accessed at the bitcoin canister's
@@ -83,6 +90,29 @@ pub fn get_utxos(
     }
 }
 
+/// Retrieves the headers of the main chain blocks in the range
+/// `[start_height, end_height]`.
+///
+/// If `end_height` is not set, headers up to the tip are returned. At most
+/// `MAX_BLOCK_HEADERS_PER_RESPONSE` headers are returned, starting at
+/// `start_height`.
+///
+/// The headers of blocks that became stable before headers were recorded are
+/// fetched from the adapter. Until all of them are recorded, requests fail
+/// with `GetBlockHeadersError::BlockHeadersNotSynced`.
+pub fn get_block_headers(
+    state: &State,
+    start_height: Height,
+    end_height: Option<Height>,
+) -> Result<GetBlockHeadersResponse, GetBlockHeadersError> {
+    store::get_block_headers(
+        state,
+        start_height,
+        end_height,
+        MAX_BLOCK_HEADERS_PER_RESPONSE,
+    )
+}
+
 pub fn send_transaction(
     state: &mut State,
     request: SendTransactionRequest,
@@ -117,9 +147,12 @@ mod test {
     use bitcoin::secp256k1::rand::rngs::OsRng;
     use bitcoin::secp256k1::Secp256k1;
     use bitcoin::util::psbt::serialize::Serialize;
-    use bitcoin::{blockdata::constants::genesis_block, Address, Block, Network, PublicKey};
+    use bitcoin::{
+        blockdata::constants::genesis_block, Address, Block, BlockHeader, Network, PublicKey,
+    };
     use ic_btc_test_utils::{random_p2tr_address, BlockBuilder, TransactionBuilder};
     use ic_btc_types::{NetworkInRequest as BtcTypesNetwork, OutPoint, Utxo};
+    use ic_replicated_state::{bitcoin_state::BitcoinState as ReplicatedBitcoinState, PageMap};
 
     // A default state to use for tests.
     fn default_state() -> State {
@@ -509,6 +542,185 @@ mod test {
         }
     }
 
+    #[test]
+    fn get_block_headers_from_stable_and_unstable_blocks() {
+        let block_0 = genesis_block(Network::Regtest);
+        let mut blocks = vec![block_0.clone()];
+        for _ in 0..4 {
+            let block = BlockBuilder::with_prev_header(blocks.last().unwrap().header).build();
+            blocks.push(block);
+        }
+
+        // With a stability threshold of 2, the first blocks become stable and
+        // their headers are served from the stable state.
+        let mut state = State::new(2, Network::Regtest, block_0);
+        for block in blocks.iter().skip(1) {
+            store::insert_block(&mut state, block.clone()).unwrap();
+        }
+        assert!(state.height > 0);
+
+        let expected: Vec<Vec<u8>> = blocks
+            .iter()
+            .map(|block| bitcoin::consensus::serialize(&block.header))
+            .collect();
+
+        let response = get_block_headers(&state, 0, None).unwrap();
+        assert_eq!(response.tip_height, 4);
+        assert_eq!(
+            response
+                .block_headers
+                .into_iter()
+                .map(|header| header.into_vec())
+                .collect::<Vec<_>>(),
+            expected
+        );
+
+        let response = get_block_headers(&state, 1, Some(2)).unwrap();
+        assert_eq!(
+            response
+                .block_headers
+                .into_iter()
+                .map(|header| header.into_vec())
+                .collect::<Vec<_>>(),
+            expected[1..=2].to_vec()
+        );
+
+        // The number of headers is capped by the given limit.
+        let response = store::get_block_headers(&state, 1, None, 2).unwrap();
+        assert_eq!(response.block_headers.len(), 2);
+    }
+
+    // Returns a chain of `num_blocks` blocks along with a state in which the
+    // headers of the stable blocks aren't recorded, as is the case for a state
+    // written before block headers were recorded.
+    fn state_without_stable_block_headers(num_blocks: usize) -> (Vec<Block>, State) {
+        let block_0 = genesis_block(Network::Regtest);
+        let mut blocks = vec![block_0.clone()];
+        for _ in 1..num_blocks {
+            let block = BlockBuilder::with_prev_header(blocks.last().unwrap().header).build();
+            blocks.push(block);
+        }
+        let mut state = State::new(2, Network::Regtest, block_0);
+        for block in blocks.iter().skip(1) {
+            store::insert_block(&mut state, block.clone()).unwrap();
+        }
+
+        let mut replicated_state = ReplicatedBitcoinState::from(state);
+        replicated_state.utxo_set.block_headers = PageMap::default();
+        (blocks, State::from(replicated_state))
+    }
+
+    #[test]
+    fn get_block_headers_of_blocks_stable_before_headers_were_recorded() {
+        let (blocks, mut state) = state_without_stable_block_headers(10);
+        let stable_height = state.height;
+        assert!(stable_height > 1);
+
+        // No headers are served until the headers of all stable blocks are
+        // backfilled.
+        assert_eq!(
+            get_block_headers(&state, stable_height, None),
+            Err(GetBlockHeadersError::BlockHeadersNotSynced)
+        );
+        assert_eq!(
+            store::block_headers_backfill_tip(&state),
+            Some(blocks[stable_height as usize - 1].block_hash())
+        );
+
+        // Headers are backfilled in descending order of height, possibly
+        // across several responses from the adapter.
+        let stable_headers: Vec<BlockHeader> = blocks[..stable_height as usize]
+            .iter()
+            .rev()
+            .map(|block| block.header)
+            .collect();
+        store::insert_backfilled_block_headers(&mut state, &stable_headers[..1]).unwrap();
+        assert_eq!(
+            store::block_headers_backfill_tip(&state),
+            Some(blocks[stable_height as usize - 2].block_hash())
+        );
+        assert_eq!(
+            get_block_headers(&state, stable_height, None),
+            Err(GetBlockHeadersError::BlockHeadersNotSynced)
+        );
+
+        store::insert_backfilled_block_headers(&mut state, &stable_headers[1..]).unwrap();
+        assert_eq!(store::block_headers_backfill_tip(&state), None);
+
+        let response = get_block_headers(&state, 0, None).unwrap();
+        assert_eq!(
+            response
+                .block_headers
+                .into_iter()
+                .map(|header| header.into_vec())
+                .collect::<Vec<_>>(),
+            blocks
+                .iter()
+                .map(|block| bitcoin::consensus::serialize(&block.header))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn backfilled_block_headers_must_extend_recorded_headers() {
+        let (blocks, mut state) = state_without_stable_block_headers(10);
+        let stable_height = state.height;
+        assert!(stable_height > 1);
+        let backfill_tip = store::block_headers_backfill_tip(&state);
+
+        // The headers skip the highest stable block.
+        let headers: Vec<BlockHeader> = blocks[..stable_height as usize - 1]
+            .iter()
+            .rev()
+            .map(|block| block.header)
+            .collect();
+        assert_eq!(
+            store::insert_backfilled_block_headers(&mut state, &headers),
+            Err(store::BlockHeaderDoesNotExtendHeaders(
+                headers[0].block_hash()
+            ))
+        );
+
+        // Nothing is recorded.
+        assert_eq!(store::block_headers_backfill_tip(&state), backfill_tip);
+        assert_eq!(
+            get_block_headers(&state, stable_height, None),
+            Err(GetBlockHeadersError::BlockHeadersNotSynced)
+        );
+    }
+
+    #[test]
+    fn get_block_headers_invalid_range() {
+        let state = default_state();
+
+        assert_eq!(
+            get_block_headers(&state, 1, None),
+            Err(GetBlockHeadersError::StartHeightDoesNotExist {
+                requested: 1,
+                chain_height: 0,
+            })
+        );
+        assert_eq!(
+            get_block_headers(&state, 0, Some(1)),
+            Err(GetBlockHeadersError::EndHeightDoesNotExist {
+                requested: 1,
+                chain_height: 0,
+            })
+        );
+
+        let mut state = default_state();
+        let block_1 =
+            BlockBuilder::with_prev_header(genesis_block(Network::Regtest).header).build();
+        store::insert_block(&mut state, block_1).unwrap();
+        assert_eq!(
+            get_block_headers(&state, 1, Some(0)),
+            Err(GetBlockHeadersError::StartHeightLargerThanEndHeight {
+                start_height: 1,
+                end_height: 0,
+            })
+        );
+    }
+
     #[test]
     fn send_transaction_malformed_transaction() {
         assert_eq!(
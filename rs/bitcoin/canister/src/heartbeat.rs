@@ -1,4 +1,7 @@
-use crate::{blocktree::BlockDoesNotExtendTree, state::State, store, BitcoinCanister};
+use crate::{
+    blocktree::BlockDoesNotExtendTree, state::State, store, store::BlockHeaderDoesNotExtendHeaders,
+    BitcoinCanister,
+};
 use bitcoin::{
     hash_types::{BlockHash, TxMerkleNode},
     hashes::Hash,
@@ -6,8 +9,8 @@ use bitcoin::{
 };
 use ic_btc_types::Network as BitcoinNetwork;
 use ic_btc_types_internal::{
-    BitcoinAdapterRequestWrapper, BitcoinAdapterResponseWrapper, Block, GetSuccessorsRequest,
-    Transaction,
+    BitcoinAdapterRequestWrapper, BitcoinAdapterResponseWrapper, Block, BlockHeader,
+    GetHeadersRequest, GetSuccessorsRequest, Transaction,
 };
 use ic_logger::{debug, error, trace, ReplicaLogger};
 use ic_registry_subnet_features::{BitcoinFeature, BitcoinFeatureStatus};
@@ -19,7 +22,9 @@ impl BitcoinCanister {
     /// The heartbeat of the Bitcoin canister.
     ///
     /// The heartbeat sends and processes `GetSuccessor` requests/responses, which
-    /// is needed to fetch new blocks from the network.
+    /// is needed to fetch new blocks from the network. It also sends and processes
+    /// `GetHeaders` requests/responses to fetch the headers of blocks that became
+    /// stable before block headers were recorded.
     pub fn heartbeat(
         &self,
         bitcoin_state: ReplicatedBitcoinState,
@@ -56,19 +61,22 @@ impl BitcoinCanister {
 
                 if !state.adapter_queues.has_in_flight_get_successors_requests() {
                     let request = get_successors_request(&mut state);
+                    push_request(
+                        &mut state,
+                        BitcoinAdapterRequestWrapper::GetSuccessorsRequest(request),
+                        &self.log,
+                    );
+                }
 
-                    match state
-                        .adapter_queues
-                        .push_request(BitcoinAdapterRequestWrapper::GetSuccessorsRequest(request))
-                    {
-                        Ok(()) => {}
-                        Err(err @ BitcoinStateError::QueueFull { .. }) => {
-                            error!(self.log, "Could not push GetSuccessorsRequest because the adapter queues are full. Error: {:?}", err);
-                        }
-                        // TODO(EXC-1098): Refactor the `push_request` method to not return these
-                        // errors to avoid this `unreachable` statement.
-                        Err(BitcoinStateError::FeatureNotEnabled)
-                        | Err(BitcoinStateError::NonMatchingResponse { .. }) => unreachable!(),
+                if !state.adapter_queues.has_in_flight_get_headers_requests() {
+                    if let Some(tip) = store::block_headers_backfill_tip(&state) {
+                        push_request(
+                            &mut state,
+                            BitcoinAdapterRequestWrapper::GetHeadersRequest(GetHeadersRequest {
+                                tip: tip.to_vec(),
+                            }),
+                            &self.log,
+                        );
                     }
                 }
             }
@@ -81,6 +89,26 @@ impl BitcoinCanister {
     }
 }
 
+// Pushes a request to the adapter queues, logging an error if they are full.
+fn push_request(state: &mut State, request: BitcoinAdapterRequestWrapper, log: &ReplicaLogger) {
+    let request_type = request.to_request_type_label().to_string();
+    match state.adapter_queues.push_request(request) {
+        Ok(()) => {}
+        Err(err @ BitcoinStateError::QueueFull { .. }) => {
+            error!(
+                log,
+                "Could not push {} request because the adapter queues are full. Error: {:?}",
+                request_type,
+                err
+            );
+        }
+        // TODO(EXC-1098): Refactor the `push_request` method to not return these
+        // errors to avoid this `unreachable` statement.
+        Err(BitcoinStateError::FeatureNotEnabled)
+        | Err(BitcoinStateError::NonMatchingResponse { .. }) => unreachable!(),
+    }
+}
+
 // Retrieves a `GetSuccessorsRequest` to send to the adapter.
 fn get_successors_request(state: &mut State) -> GetSuccessorsRequest {
     let mut processed_block_hashes: Vec<Vec<u8>> = store::get_unstable_blocks(state)
@@ -129,6 +157,26 @@ fn process_adapter_responses(state: &mut State, log: &ReplicaLogger) -> u32 {
                     }
                 }
             }
+            BitcoinAdapterResponseWrapper::GetHeadersResponse(r) => {
+                let headers: Result<Vec<_>, _> =
+                    r.headers.iter().map(to_btc_block_header).collect();
+                match headers {
+                    Ok(headers) => {
+                        if let Err(BlockHeaderDoesNotExtendHeaders(block_hash)) =
+                            store::insert_backfilled_block_headers(state, &headers)
+                        {
+                            error!(
+                                log,
+                                "Received block header that doesn't extend the recorded headers: {}",
+                                block_hash
+                            );
+                        }
+                    }
+                    Err(err) => {
+                        error!(log, "Received malformed block header: {:?}", err);
+                    }
+                }
+            }
             BitcoinAdapterResponseWrapper::SendTransactionResponse(_) => {
                 // TODO(EXC-911): Handle these responses too.
             }
@@ -193,6 +241,19 @@ fn to_btc_transaction(transaction: &Transaction) -> bitcoin::Transaction {
     }
 }
 
+fn to_btc_block_header(
+    header: &BlockHeader,
+) -> Result<bitcoin::BlockHeader, bitcoin::hashes::Error> {
+    Ok(bitcoin::BlockHeader {
+        version: header.version,
+        prev_blockhash: BlockHash::from_hash(Hash::from_slice(&header.prev_blockhash)?),
+        merkle_root: TxMerkleNode::from_hash(Hash::from_slice(&header.merkle_root)?),
+        time: header.time,
+        bits: header.bits,
+        nonce: header.nonce,
+    })
+}
+
 fn to_btc_block(block: &Block) -> bitcoin::Block {
     bitcoin::Block {
         header: bitcoin::BlockHeader {
@@ -214,8 +275,11 @@ fn to_btc_block(block: &Block) -> bitcoin::Block {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use bitcoin::blockdata::constants::genesis_block;
+    use ic_btc_test_utils::BlockBuilder;
     use ic_logger::replica_logger::no_op_logger;
     use ic_metrics::MetricsRegistry;
+    use ic_replicated_state::PageMap;
 
     #[test]
     fn does_not_push_requests_to_adapter_if_feature_is_disabled() {
@@ -280,6 +344,53 @@ mod tests {
         assert_eq!(state.adapter_queues.num_requests(), 1);
     }
 
+    #[test]
+    fn pushes_get_headers_request_if_stable_block_headers_are_missing() {
+        let block_0 = genesis_block(Network::Regtest);
+        let mut blocks = vec![block_0.clone()];
+        for _ in 0..5 {
+            let block = BlockBuilder::with_prev_header(blocks.last().unwrap().header).build();
+            blocks.push(block);
+        }
+        let mut state = State::new(2, Network::Regtest, block_0);
+        for block in blocks.iter().skip(1) {
+            store::insert_block(&mut state, block.clone()).unwrap();
+        }
+        let stable_height = state.height;
+        assert!(stable_height > 0);
+
+        // Drop the recorded headers of the stable blocks.
+        let mut state = ReplicatedBitcoinState::from(state);
+        state.utxo_set.block_headers = PageMap::default();
+
+        let bitcoin_canister = BitcoinCanister::new(&MetricsRegistry::new(), no_op_logger());
+        let state = bitcoin_canister.heartbeat(
+            state,
+            BitcoinFeature {
+                network: BitcoinNetwork::Regtest,
+                status: BitcoinFeatureStatus::Enabled,
+            },
+        );
+        assert_eq!(state.adapter_queues.num_requests(), 2);
+        assert!(state.adapter_queues.has_in_flight_get_headers_requests());
+        assert!(state
+            .adapter_requests_iter()
+            .any(|(_, request)| request.request
+                == BitcoinAdapterRequestWrapper::GetHeadersRequest(GetHeadersRequest {
+                    tip: blocks[stable_height as usize - 1].block_hash().to_vec(),
+                })));
+
+        // No further request is pushed while one is in flight.
+        let state = bitcoin_canister.heartbeat(
+            state,
+            BitcoinFeature {
+                network: BitcoinNetwork::Regtest,
+                status: BitcoinFeatureStatus::Enabled,
+            },
+        );
+        assert_eq!(state.adapter_queues.num_requests(), 2);
+    }
+
     #[test]
     fn state_is_reset_if_feature_is_disabled() {
         let mut state = ReplicatedBitcoinState::new(BitcoinNetwork::Mainnet);
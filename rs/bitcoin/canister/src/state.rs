@@ -48,6 +48,7 @@ impl From<ReplicatedBitcoinState> for State {
         let utxos_small = state.utxo_set.utxos_small;
         let utxos_medium = state.utxo_set.utxos_medium;
         let address_outpoints = state.utxo_set.address_outpoints;
+        let block_headers = state.utxo_set.block_headers;
 
         Self {
             adapter_queues: state.adapter_queues,
@@ -73,6 +74,11 @@ impl From<ReplicatedBitcoinState> for State {
                     MAX_ADDRESS_OUTPOINT_SIZE,
                     0,
                 ),
+                block_headers: StableBTreeMap::init(
+                    PageMapMemory::new(block_headers),
+                    HEIGHT_SIZE,
+                    BLOCK_HEADER_SIZE,
+                ),
             },
             fee_percentiles_cache: state.fee_percentiles_cache,
         }
@@ -94,6 +100,7 @@ impl From<State> for ReplicatedBitcoinState {
                     .address_to_outpoints
                     .get_memory()
                     .into_page_map(),
+                block_headers: state.utxos.block_headers.get_memory().into_page_map(),
                 network: state.utxos.network,
             },
             fee_percentiles_cache: state.fee_percentiles_cache,
@@ -182,6 +189,9 @@ pub const UTXO_VALUE_MAX_SIZE_MEDIUM: u32 = TX_OUT_MAX_SIZE_MEDIUM + HEIGHT_SIZE
 const MAX_ADDRESS_SIZE: u32 = 90;
 const MAX_ADDRESS_OUTPOINT_SIZE: u32 = MAX_ADDRESS_SIZE + OUTPOINT_SIZE;
 
+// A serialized block header is always 80 bytes.
+const BLOCK_HEADER_SIZE: u32 = 80;
+
 impl Default for Utxos {
     fn default() -> Self {
         Self {
@@ -215,6 +225,8 @@ pub struct UtxoSet {
     pub network: Network,
     // An index for fast retrievals of an address's UTXOs.
     pub address_to_outpoints: StableBTreeMap<PageMapMemory, Vec<u8>, Vec<u8>>,
+    // The headers of all stable blocks, keyed by their big-endian encoded height.
+    pub block_headers: StableBTreeMap<PageMapMemory, Vec<u8>, Vec<u8>>,
 }
 
 impl UtxoSet {
@@ -226,6 +238,11 @@ impl UtxoSet {
                 MAX_ADDRESS_OUTPOINT_SIZE,
                 0, // No values are stored in the map.
             ),
+            block_headers: StableBTreeMap::new(
+                PageMapMemory::default(),
+                HEIGHT_SIZE,
+                BLOCK_HEADER_SIZE,
+            ),
             network,
         }
     }
@@ -4,8 +4,15 @@ use crate::{
     types::Page,
     unstable_blocks, utxoset,
 };
-use bitcoin::{hashes::Hash, Address, Block, OutPoint, Txid};
-use ic_btc_types::{GetBalanceError, GetUtxosError, GetUtxosResponse, Height, Satoshi};
+use bitcoin::{
+    consensus::{deserialize, serialize},
+    hashes::Hash,
+    Address, Block, BlockHash, BlockHeader, OutPoint, Txid,
+};
+use ic_btc_types::{
+    GetBalanceError, GetBlockHeadersError, GetBlockHeadersResponse, GetUtxosError,
+    GetUtxosResponse, Height, Satoshi,
+};
 use lazy_static::lazy_static;
 use serde_bytes::ByteBuf;
 use std::str::FromStr;
@@ -17,6 +24,11 @@ lazy_static! {
     ];
 }
 
+/// An error returned when a backfilled block header isn't the parent of the
+/// lowest recorded block header.
+#[derive(Debug, PartialEq)]
+pub struct BlockHeaderDoesNotExtendHeaders(pub BlockHash);
+
 /// Returns the balance of a bitcoin address.
 pub fn get_balance(
     state: &State,
@@ -191,6 +203,7 @@ pub fn insert_block(state: &mut State, block: Block) -> Result<(), BlockDoesNotE
         for tx in &new_stable_block.txdata {
             utxoset::insert_tx(&mut state.utxos, tx, state.height);
         }
+        utxoset::insert_block_header(&mut state.utxos, &new_stable_block.header, state.height);
 
         state.height += 1;
     }
@@ -198,6 +211,143 @@ pub fn insert_block(state: &mut State, block: Block) -> Result<(), BlockDoesNotE
     Ok(())
 }
 
+/// Returns the hash of the highest stable block whose header isn't recorded,
+/// or `None` if the headers of all stable blocks are recorded.
+///
+/// Headers are missing for blocks that became stable before block headers
+/// were recorded. They are fetched from the adapter, starting at this block
+/// and going down to the genesis block.
+pub fn block_headers_backfill_tip(state: &State) -> Option<BlockHash> {
+    match lowest_known_block_header(state) {
+        (0, _) => None,
+        (_, header) => Some(header.prev_blockhash),
+    }
+}
+
+/// Returns true iff the headers of all stable blocks are recorded.
+pub fn block_headers_synced(state: &State) -> bool {
+    block_headers_backfill_tip(state).is_none()
+}
+
+/// Records the headers of blocks that became stable before block headers were
+/// recorded.
+///
+/// `headers` are expected in descending order of height, starting with the
+/// header of the block returned by `block_headers_backfill_tip`. Headers are
+/// recorded until the header of the genesis block is recorded. An error is
+/// returned for the first header that isn't the parent of the lowest recorded
+/// header; the headers before it are kept.
+pub fn insert_backfilled_block_headers(
+    state: &mut State,
+    headers: &[BlockHeader],
+) -> Result<(), BlockHeaderDoesNotExtendHeaders> {
+    let (mut height, lowest_header) = lowest_known_block_header(state);
+    let mut expected_hash = lowest_header.prev_blockhash;
+    for header in headers {
+        if height == 0 {
+            break;
+        }
+
+        let block_hash = header.block_hash();
+        if block_hash != expected_hash {
+            return Err(BlockHeaderDoesNotExtendHeaders(block_hash));
+        }
+
+        height -= 1;
+        utxoset::insert_block_header(&mut state.utxos, header, height);
+        expected_hash = header.prev_blockhash;
+    }
+
+    Ok(())
+}
+
+// Returns the lowest height from which on the headers of all blocks are
+// known, along with the header at that height.
+fn lowest_known_block_header(state: &State) -> (Height, BlockHeader) {
+    match utxoset::get_lowest_block_header_height(&state.utxos) {
+        Some(height) => {
+            let header = utxoset::get_block_header(&state.utxos, height)
+                .expect("The header at the lowest recorded height must exist.");
+            (
+                height,
+                deserialize(&header).expect("Recorded block headers must be valid."),
+            )
+        }
+        // No headers are recorded yet, so the anchor of the unstable blocks
+        // has the lowest known header.
+        None => (
+            state.height,
+            unstable_blocks::get_main_chain(&state.unstable_blocks)
+                .first()
+                .header,
+        ),
+    }
+}
+
+/// Returns the headers of the main chain blocks in the range
+/// `[start_height, end_height]`, or up to the tip if `end_height` is not set.
+///
+/// At most `limit` headers are returned, starting at `start_height`.
+///
+/// Returns `GetBlockHeadersError::BlockHeadersNotSynced` as long as the
+/// headers of some stable blocks aren't recorded.
+pub fn get_block_headers(
+    state: &State,
+    start_height: Height,
+    end_height: Option<Height>,
+    limit: usize,
+) -> Result<GetBlockHeadersResponse, GetBlockHeadersError> {
+    if !block_headers_synced(state) {
+        return Err(GetBlockHeadersError::BlockHeadersNotSynced);
+    }
+
+    let main_chain = unstable_blocks::get_main_chain(&state.unstable_blocks);
+    let tip_height = state.height + main_chain.len() as u32 - 1;
+
+    if start_height > tip_height {
+        return Err(GetBlockHeadersError::StartHeightDoesNotExist {
+            requested: start_height,
+            chain_height: tip_height,
+        });
+    }
+
+    let end_height = match end_height {
+        Some(end_height) if end_height > tip_height => {
+            return Err(GetBlockHeadersError::EndHeightDoesNotExist {
+                requested: end_height,
+                chain_height: tip_height,
+            });
+        }
+        Some(end_height) if end_height < start_height => {
+            return Err(GetBlockHeadersError::StartHeightLargerThanEndHeight {
+                start_height,
+                end_height,
+            });
+        }
+        Some(end_height) => end_height,
+        None => tip_height,
+    };
+
+    // Headers below the anchor of the unstable blocks are served from the
+    // stable state, the remaining ones from the main chain.
+    let unstable_blocks = main_chain.into_chain();
+    let mut block_headers = vec![];
+    for height in (start_height..=end_height).take(limit) {
+        let header = if height < state.height {
+            utxoset::get_block_header(&state.utxos, height)
+                .expect("The headers of all stable blocks must be recorded.")
+        } else {
+            serialize(&unstable_blocks[(height - state.height) as usize].header)
+        };
+        block_headers.push(ByteBuf::from(header));
+    }
+
+    Ok(GetBlockHeadersResponse {
+        tip_height,
+        block_headers,
+    })
+}
+
 pub fn main_chain_height(state: &State) -> Height {
     unstable_blocks::get_main_chain(&state.unstable_blocks).len() as u32 + state.height - 1
 }
@@ -1,6 +1,6 @@
 use crate::address_utxoset::AddressUtxoSet;
 use crate::{state::UtxoSet, types::Storable, utxos::UtxosTrait};
-use bitcoin::{Address, OutPoint, Transaction, TxOut, Txid};
+use bitcoin::{consensus::serialize, Address, BlockHeader, OutPoint, Transaction, TxOut, Txid};
 use std::str::FromStr;
 
 type Height = u32;
@@ -23,6 +23,32 @@ pub fn insert_tx(utxo_set: &mut UtxoSet, tx: &Transaction, height: Height) {
     insert_unspent_txs(utxo_set, tx, height);
 }
 
+/// Records the header of the stable block at the given height.
+pub fn insert_block_header(utxo_set: &mut UtxoSet, header: &BlockHeader, height: Height) {
+    utxo_set
+        .block_headers
+        .insert(height.to_be_bytes().to_vec(), serialize(header))
+        .expect("Inserting a block header must succeed.");
+}
+
+/// Returns the serialized header of the stable block at the given height.
+///
+/// Returns `None` if no header was recorded for that height, which is the case
+/// for blocks that became stable before block headers were recorded.
+pub fn get_block_header(utxo_set: &UtxoSet, height: Height) -> Option<Vec<u8>> {
+    utxo_set.block_headers.get(&height.to_be_bytes().to_vec())
+}
+
+/// Returns the lowest height for which a block header is recorded, if any.
+pub fn get_lowest_block_header_height(utxo_set: &UtxoSet) -> Option<Height> {
+    // Heights are stored in big-endian, so the first key is the lowest height.
+    utxo_set.block_headers.iter().next().map(|(height, _)| {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(&height);
+        Height::from_be_bytes(bytes)
+    })
+}
+
 // Iterates over transaction inputs and removes spent outputs.
 fn remove_spent_txs(utxo_set: &mut UtxoSet, tx: &Transaction) {
     if tx.is_coin_base() {
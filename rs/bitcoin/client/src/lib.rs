@@ -1,20 +1,21 @@
 mod metrics;
 
 use crate::metrics::{
-    Metrics, LABEL_GET_SUCCESSORS, LABEL_REQUEST_TYPE, LABEL_SEND_TRANSACTION, LABEL_STATUS,
-    OK_LABEL, REQUESTS_LABEL_NAMES, UNKNOWN_LABEL,
+    Metrics, LABEL_GET_HEADERS, LABEL_GET_SUCCESSORS, LABEL_REQUEST_TYPE, LABEL_SEND_TRANSACTION,
+    LABEL_STATUS, OK_LABEL, REQUESTS_LABEL_NAMES, UNKNOWN_LABEL,
 };
 use bitcoin::consensus::Decodable;
 use ic_adapter_metrics::AdapterMetrics;
 use ic_async_utils::ExecuteOnTokioRuntime;
 use ic_btc_service::{
-    btc_service_client::BtcServiceClient, BtcServiceGetSuccessorsRequest,
-    BtcServiceSendTransactionRequest,
+    btc_service_client::BtcServiceClient, BtcServiceGetHeadersRequest,
+    BtcServiceGetSuccessorsRequest, BtcServiceSendTransactionRequest,
 };
 use ic_btc_types_internal::{
     BitcoinAdapterRequestWrapper, BitcoinAdapterResponseWrapper, Block as InternalBlock,
     BlockHeader as InternalBlockHeader, CanisterGetSuccessorsRequestInitial,
     CanisterGetSuccessorsResponseComplete, CanisterSendTransactionRequest,
+    GetHeadersRequest as InternalGetHeadersRequest, GetHeadersResponse,
     GetSuccessorsRequest as InternalGetSuccessorsRequest, GetSuccessorsResponse,
     OutPoint as InternalOutPoint, SendTransactionRequest as InternalSendTransactionRequest,
     SendTransactionResponse, Transaction as InternalTransaction, TxIn as InternalTxIn,
@@ -188,6 +189,36 @@ impl BitcoinAdapterClient for BitcoinAdapterClientImpl {
                         })
                         .map_err(convert_tonic_error)
                 }
+                BitcoinAdapterRequestWrapper::GetHeadersRequest(InternalGetHeadersRequest {
+                    tip,
+                }) => {
+                    request_timer.set_label(LABEL_REQUEST_TYPE, LABEL_GET_HEADERS);
+                    let get_headers_request = BtcServiceGetHeadersRequest { tip };
+                    let mut tonic_request = tonic::Request::new(get_headers_request);
+                    tonic_request.set_timeout(opts.timeout);
+
+                    client
+                        .get_headers(tonic_request)
+                        .await
+                        .map(|tonic_response| {
+                            let mut headers = vec![];
+                            for h in tonic_response.into_inner().headers.into_iter() {
+                                let bitcoin_block_header =
+                                    bitcoin::BlockHeader::consensus_decode(&*h).map_err(|e| {
+                                        tonic::Status::internal(format!(
+                                            "Deserialization of response failed: {}",
+                                            e
+                                        ))
+                                    })?;
+                                headers.push(to_internal_block_header(&bitcoin_block_header));
+                            }
+                            Ok(BitcoinAdapterResponseWrapper::GetHeadersResponse(
+                                GetHeadersResponse { headers },
+                            ))
+                        })
+                        .map_err(convert_tonic_error)?
+                        .map_err(convert_tonic_error)
+                }
                 BitcoinAdapterRequestWrapper::CanisterSendTransactionRequest(
                     CanisterSendTransactionRequest { transaction, .. },
                 ) => {
@@ -281,6 +312,9 @@ impl BitcoinAdapterClient for BrokenConnectionBitcoinClient {
             | BitcoinAdapterRequestWrapper::SendTransactionRequest(_) => {
                 request_timer.set_label(LABEL_REQUEST_TYPE, LABEL_SEND_TRANSACTION)
             }
+            BitcoinAdapterRequestWrapper::GetHeadersRequest(_) => {
+                request_timer.set_label(LABEL_REQUEST_TYPE, LABEL_GET_HEADERS)
+            }
         }
         request_timer.set_label(
             LABEL_STATUS,
@@ -5,6 +5,7 @@ pub const LABEL_REQUEST_TYPE: &str = "request_type";
 pub const LABEL_STATUS: &str = "status";
 pub const LABEL_GET_SUCCESSORS: &str = "get_successors";
 pub const LABEL_SEND_TRANSACTION: &str = "send_transaction";
+pub const LABEL_GET_HEADERS: &str = "get_headers";
 pub const OK_LABEL: &str = "OK";
 pub const UNKNOWN_LABEL: &str = "unknown";
 
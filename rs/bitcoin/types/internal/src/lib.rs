@@ -98,10 +98,32 @@ impl From<v1::SendTransactionRequest> for SendTransactionRequest {
     }
 }
 
+/// A request for the header of the block with hash `tip` and the headers of
+/// its ancestors. Used to backfill the headers of stable blocks.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GetHeadersRequest {
+    pub tip: Vec<u8>,
+}
+
+impl From<&GetHeadersRequest> for v1::GetHeadersRequest {
+    fn from(request: &GetHeadersRequest) -> Self {
+        v1::GetHeadersRequest {
+            tip: request.tip.clone(),
+        }
+    }
+}
+
+impl From<v1::GetHeadersRequest> for GetHeadersRequest {
+    fn from(request: v1::GetHeadersRequest) -> Self {
+        GetHeadersRequest { tip: request.tip }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum BitcoinAdapterRequestWrapper {
     GetSuccessorsRequest(GetSuccessorsRequest),
     SendTransactionRequest(SendTransactionRequest),
+    GetHeadersRequest(GetHeadersRequest),
     // A request from the bitcoin wasm canister.
     // This supersedes `GetSuccessorsRequest`, which will be deleted
     // once the Bitcoin replica canister is phased out.
@@ -114,6 +136,7 @@ impl BitcoinAdapterRequestWrapper {
         match self {
             BitcoinAdapterRequestWrapper::GetSuccessorsRequest(_) => "get_successors",
             BitcoinAdapterRequestWrapper::SendTransactionRequest(_) => "send_transaction",
+            BitcoinAdapterRequestWrapper::GetHeadersRequest(_) => "get_headers",
             BitcoinAdapterRequestWrapper::CanisterGetSuccessorsRequest(_) => "get_successors",
             BitcoinAdapterRequestWrapper::CanisterSendTransactionRequest(_) => "send_transaction",
         }
@@ -123,7 +146,8 @@ impl BitcoinAdapterRequestWrapper {
     pub fn network(&self) -> Network {
         match self {
             BitcoinAdapterRequestWrapper::GetSuccessorsRequest(_)
-            | BitcoinAdapterRequestWrapper::SendTransactionRequest(_) => {
+            | BitcoinAdapterRequestWrapper::SendTransactionRequest(_)
+            | BitcoinAdapterRequestWrapper::GetHeadersRequest(_) => {
                 // NOTE: These are the deprecated replica requests that do not contain a network
                 // parameter. These requests do not trigger this code path and they'll be
                 // deleted in EXC-1239. For now, return a stub response.
@@ -160,6 +184,13 @@ impl From<&BitcoinAdapterRequestWrapper> for v1::BitcoinAdapterRequestWrapper {
                     ),
                 }
             }
+            BitcoinAdapterRequestWrapper::GetHeadersRequest(request) => {
+                v1::BitcoinAdapterRequestWrapper {
+                    r: Some(v1::bitcoin_adapter_request_wrapper::R::GetHeadersRequest(
+                        request.into(),
+                    )),
+                }
+            }
             BitcoinAdapterRequestWrapper::CanisterGetSuccessorsRequest(request) => {
                 v1::BitcoinAdapterRequestWrapper {
                     r: Some(
@@ -194,6 +225,9 @@ impl TryFrom<v1::BitcoinAdapterRequestWrapper> for BitcoinAdapterRequestWrapper
             v1::bitcoin_adapter_request_wrapper::R::SendTransactionRequest(r) => Ok(
                 BitcoinAdapterRequestWrapper::SendTransactionRequest(r.try_into()?),
             ),
+            v1::bitcoin_adapter_request_wrapper::R::GetHeadersRequest(r) => {
+                Ok(BitcoinAdapterRequestWrapper::GetHeadersRequest(r.into()))
+            }
             v1::bitcoin_adapter_request_wrapper::R::CanisterGetSuccessorsRequest(r) => Ok(
                 BitcoinAdapterRequestWrapper::CanisterGetSuccessorsRequest(r.try_into()?),
             ),
@@ -512,6 +546,38 @@ impl TryFrom<v1::GetSuccessorsResponse> for GetSuccessorsResponse {
     }
 }
 
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Hash, PartialEq, Eq)]
+pub struct GetHeadersResponse {
+    pub headers: Vec<BlockHeader>,
+}
+
+impl GetHeadersResponse {
+    /// Returns the size of this `GetHeadersResponse` in bytes.
+    pub fn count_bytes(&self) -> usize {
+        self.headers.iter().map(|x| x.count_bytes()).sum::<usize>()
+    }
+}
+
+impl From<&GetHeadersResponse> for v1::GetHeadersResponse {
+    fn from(response: &GetHeadersResponse) -> Self {
+        v1::GetHeadersResponse {
+            headers: response.headers.iter().map(v1::BlockHeader::from).collect(),
+        }
+    }
+}
+
+impl From<v1::GetHeadersResponse> for GetHeadersResponse {
+    fn from(response: v1::GetHeadersResponse) -> Self {
+        GetHeadersResponse {
+            headers: response
+                .headers
+                .into_iter()
+                .map(BlockHeader::from)
+                .collect(),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SendTransactionResponse {}
 
@@ -538,6 +604,7 @@ impl SendTransactionResponse {
 pub enum BitcoinAdapterResponseWrapper {
     GetSuccessorsResponse(GetSuccessorsResponse),
     SendTransactionResponse(SendTransactionResponse),
+    GetHeadersResponse(GetHeadersResponse),
     // Responses for the bitcoin wasm canister.
     // These supersede the above responses, which will be deleted
     // once the Bitcoin replica canister is phased out.
@@ -551,6 +618,7 @@ impl BitcoinAdapterResponseWrapper {
         match self {
             BitcoinAdapterResponseWrapper::GetSuccessorsResponse(r) => r.count_bytes(),
             BitcoinAdapterResponseWrapper::SendTransactionResponse(r) => r.count_bytes(),
+            BitcoinAdapterResponseWrapper::GetHeadersResponse(r) => r.count_bytes(),
             BitcoinAdapterResponseWrapper::CanisterGetSuccessorsResponse(r) => r.count_bytes(),
             BitcoinAdapterResponseWrapper::CanisterSendTransactionResponse(r) => r.count_bytes(),
         }
@@ -578,6 +646,13 @@ impl From<&BitcoinAdapterResponseWrapper> for v1::BitcoinAdapterResponseWrapper
                     ),
                 }
             }
+            BitcoinAdapterResponseWrapper::GetHeadersResponse(response) => {
+                v1::BitcoinAdapterResponseWrapper {
+                    r: Some(v1::bitcoin_adapter_response_wrapper::R::GetHeadersResponse(
+                        response.into(),
+                    )),
+                }
+            }
             BitcoinAdapterResponseWrapper::CanisterGetSuccessorsResponse(response) => {
                 v1::BitcoinAdapterResponseWrapper {
                     r: Some(
@@ -612,6 +687,9 @@ impl TryFrom<v1::BitcoinAdapterResponseWrapper> for BitcoinAdapterResponseWrappe
             v1::bitcoin_adapter_response_wrapper::R::SendTransactionResponse(r) => Ok(
                 BitcoinAdapterResponseWrapper::SendTransactionResponse(r.try_into()?),
             ),
+            v1::bitcoin_adapter_response_wrapper::R::GetHeadersResponse(r) => {
+                Ok(BitcoinAdapterResponseWrapper::GetHeadersResponse(r.into()))
+            }
             v1::bitcoin_adapter_response_wrapper::R::CanisterGetSuccessorsResponse(r) => {
                 Ok(BitcoinAdapterResponseWrapper::CanisterGetSuccessorsResponse(r.try_into()?))
             }
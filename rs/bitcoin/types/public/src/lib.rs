@@ -10,6 +10,7 @@ pub type MillisatoshiPerByte = u64;
 pub type BlockHash = Vec<u8>;
 pub type Height = u32;
 pub type Page = ByteBuf;
+pub type BlockHeader = ByteBuf;
 
 #[derive(CandidType, Clone, Copy, Deserialize, Debug, Eq, PartialEq, Serialize, Hash)]
 pub enum Network {
@@ -248,6 +249,92 @@ impl std::fmt::Display for GetBalanceError {
     }
 }
 
+/// A request for getting the headers of the blocks in the range
+/// `[start_height, end_height]` of the main chain.
+///
+/// If `end_height` is not set, the range ends at the tip of the main chain.
+#[derive(CandidType, Debug, Deserialize, PartialEq)]
+pub struct GetBlockHeadersRequest {
+    pub start_height: Height,
+    pub end_height: Option<Height>,
+    pub network: NetworkInRequest,
+}
+
+/// The response returned for a request to get block headers.
+///
+/// The number of headers in a single response is limited. If fewer headers
+/// than requested are returned, the remaining ones can be requested starting
+/// from `start_height + block_headers.len()`.
+#[derive(CandidType, Debug, Deserialize, PartialEq, Clone)]
+pub struct GetBlockHeadersResponse {
+    pub tip_height: Height,
+    pub block_headers: Vec<BlockHeader>,
+}
+
+/// Errors when processing a `get_block_headers` request.
+#[derive(CandidType, Debug, Deserialize, PartialEq, Clone)]
+pub enum GetBlockHeadersError {
+    StartHeightDoesNotExist {
+        requested: Height,
+        chain_height: Height,
+    },
+    EndHeightDoesNotExist {
+        requested: Height,
+        chain_height: Height,
+    },
+    StartHeightLargerThanEndHeight {
+        start_height: Height,
+        end_height: Height,
+    },
+    /// The headers of stable blocks that were stored before headers were
+    /// recorded are still being fetched from the adapter. No headers are
+    /// served until all of them are available.
+    BlockHeadersNotSynced,
+}
+
+impl std::fmt::Display for GetBlockHeadersError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::StartHeightDoesNotExist {
+                requested,
+                chain_height,
+            } => {
+                write!(
+                    f,
+                    "The requested start_height is larger than the height of the chain. Given: {}, height of chain: {}",
+                    requested, chain_height
+                )
+            }
+            Self::EndHeightDoesNotExist {
+                requested,
+                chain_height,
+            } => {
+                write!(
+                    f,
+                    "The requested end_height is larger than the height of the chain. Given: {}, height of chain: {}",
+                    requested, chain_height
+                )
+            }
+            Self::StartHeightLargerThanEndHeight {
+                start_height,
+                end_height,
+            } => {
+                write!(
+                    f,
+                    "The requested start_height is larger than the requested end_height. start_height: {}, end_height: {}",
+                    start_height, end_height
+                )
+            }
+            Self::BlockHeadersNotSynced => {
+                write!(
+                    f,
+                    "The block headers are still being synced. Please try again later."
+                )
+            }
+        }
+    }
+}
+
 #[derive(CandidType, Debug, Deserialize, PartialEq)]
 pub struct SendTransactionRequest {
     #[serde(with = "serde_bytes")]
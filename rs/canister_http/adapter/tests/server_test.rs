@@ -111,6 +111,11 @@ MIGHAgEAMBMGByqGSM49AgEGCCqGSM49AwEHBG0wawIBAQQgob29X4H4m2XOkSZE
                 Ok::<_, warp::Rejection>(warp::reply::reply())
             });
 
+        // Echoes the method the request was made with.
+        let any_method = warp::method()
+            .and(warp::path("method"))
+            .map(|method: http::Method| Response::builder().body(method.to_string()));
+
         let basic_head = warp::head()
             .and(warp::path("head"))
             .map(|| warp::reply::reply());
@@ -120,6 +125,7 @@ MIGHAgEAMBMGByqGSM49AgEGCCqGSM49AwEHBG0wawIBAQQgob29X4H4m2XOkSZE
             .or(basic_head)
             .or(get_response_size)
             .or(get_delay)
+            .or(any_method)
             .or(invalid_header);
 
         let (addr, fut) = warp::serve(routes)
@@ -225,6 +231,35 @@ MIGHAgEAMBMGByqGSM49AgEGCCqGSM49AwEHBG0wawIBAQQgob29X4H4m2XOkSZE
         assert_eq!(http_response.status, StatusCode::OK.as_u16() as u32);
     }
 
+    #[tokio::test]
+    async fn test_canister_http_server_put_delete_patch() {
+        let server_config = Config {
+            ..Default::default()
+        };
+
+        let url = start_server(CERT_INIT.get_or_init(generate_certs));
+        let mut client = spawn_grpc_server(server_config);
+
+        for (method, expected) in [
+            (HttpMethod::Put, "PUT"),
+            (HttpMethod::Delete, "DELETE"),
+            (HttpMethod::Patch, "PATCH"),
+        ] {
+            let request = tonic::Request::new(CanisterHttpSendRequest {
+                url: format!("https://{}/method", &url),
+                headers: Vec::new(),
+                method: method as i32,
+                body: "420".to_string().as_bytes().to_vec(),
+                max_response_size_bytes: 512,
+            });
+
+            let response = client.canister_http_send(request).await;
+            let http_response = response.unwrap().into_inner();
+            assert_eq!(http_response.status, StatusCode::OK.as_u16() as u32);
+            assert_eq!(http_response.content, expected.as_bytes());
+        }
+    }
+
     #[tokio::test]
     async fn test_response_limit_exceeded() {
         // Check if response with higher than allowed response limit is rejected.
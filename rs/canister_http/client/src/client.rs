@@ -148,6 +148,9 @@ impl NonBlockingChannel<CanisterHttpRequest> for CanisterHttpAdapterClientImpl {
                         CanisterHttpMethod::GET => HttpMethod::Get.into(),
                         CanisterHttpMethod::POST => HttpMethod::Post.into(),
                         CanisterHttpMethod::HEAD => HttpMethod::Head.into(),
+                        CanisterHttpMethod::PUT => HttpMethod::Put.into(),
+                        CanisterHttpMethod::DELETE => HttpMethod::Delete.into(),
+                        CanisterHttpMethod::PATCH => HttpMethod::Patch.into(),
                     },
                     max_response_size_bytes: request_max_response_bytes.unwrap_or(CANISTER_HTTP_ADAPTER_MAX_RESPONSE_SIZE).get(),
                     headers: request_headers
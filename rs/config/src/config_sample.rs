@@ -337,8 +337,11 @@ pub const SAMPLE_CONFIG: &str = r#"
         ipv6_rule_template: "",
         ipv4_user_output_rule_template: "",
         ipv6_user_output_rule_template: "",
+        ipv4_udp_rule_template: "",
+        ipv6_udp_rule_template: "",
         default_rules: [],
         ports_for_node_whitelist: [],
+        udp_ports_for_node_whitelist: [],
         ports_for_http_adapter_blacklist: [],
     },
 
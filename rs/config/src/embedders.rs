@@ -2,6 +2,7 @@ use ic_base_types::NumBytes;
 use ic_sys::PAGE_SIZE;
 use ic_types::{NumInstructions, NumPages};
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 
 use crate::flag_status::FlagStatus;
 
@@ -37,6 +38,14 @@ const GiB: u64 = KiB * KiB * KiB;
 // is allowed to produce.
 const STABLE_MEMORY_DIRTY_PAGE_LIMIT: u64 = 8 * GiB / (PAGE_SIZE as u64);
 
+/// The maximum total size of the compiled modules kept in memory by the
+/// compilation cache.
+pub(crate) const MAX_COMPILATION_CACHE_SIZE: NumBytes = NumBytes::new(10 * GiB);
+
+/// The maximum total size of the compiled modules kept on disk by the
+/// compilation cache.
+pub(crate) const MAX_COMPILATION_CACHE_DISK_SIZE: NumBytes = NumBytes::new(100 * GiB);
+
 #[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
 pub struct FeatureFlags {
     pub rate_limiting_of_debug_prints: FlagStatus,
@@ -87,6 +96,18 @@ pub struct Config {
     // Maximum number of stable memory dirty pages that a single message execution
     // is allowed to produce.
     pub stable_memory_dirty_page_limit: NumPages,
+
+    /// Maximum total size of the compiled modules kept in memory by the
+    /// compilation cache. The least recently used modules are evicted first.
+    pub max_compilation_cache_size: NumBytes,
+
+    /// Maximum total size of the compiled modules kept on disk by the
+    /// compilation cache.
+    pub max_compilation_cache_disk_size: NumBytes,
+
+    /// The directory where modules evicted from the in-memory compilation
+    /// cache are stored. If not set, evicted modules are dropped.
+    pub compilation_cache_dir: Option<PathBuf>,
 }
 
 impl Config {
@@ -102,6 +123,9 @@ impl Config {
             num_rayon_compilation_threads: DEFAULT_WASMTIME_RAYON_COMPILATION_THREADS,
             feature_flags: FeatureFlags::default(),
             stable_memory_dirty_page_limit: NumPages::from(STABLE_MEMORY_DIRTY_PAGE_LIMIT),
+            max_compilation_cache_size: MAX_COMPILATION_CACHE_SIZE,
+            max_compilation_cache_disk_size: MAX_COMPILATION_CACHE_DISK_SIZE,
+            compilation_cache_dir: None,
         }
     }
 }
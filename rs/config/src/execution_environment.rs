@@ -8,7 +8,7 @@ use ic_types::{
     Cycles, NumBytes, NumInstructions, MAX_STABLE_MEMORY_IN_BYTES, MAX_WASM_MEMORY_IN_BYTES,
 };
 use serde::{Deserialize, Serialize};
-use std::str::FromStr;
+use std::{path::PathBuf, str::FromStr, time::Duration};
 
 const GB: u64 = 1024 * 1024 * 1024;
 
@@ -52,6 +52,13 @@ const INGRESS_HISTORY_MEMORY_CAPACITY: NumBytes = NumBytes::new(10 * GB);
 /// memory can succeed.
 pub(crate) const SUBNET_HEAP_DELTA_CAPACITY: NumBytes = NumBytes::new(140 * GB);
 
+/// The maximum total size of the query results kept in the query cache.
+const QUERY_CACHE_CAPACITY: NumBytes = NumBytes::new(200 * 1024 * 1024);
+
+/// The maximum difference in batch time for which a cached query result is
+/// reused.
+const QUERY_CACHE_MAX_EXPIRY_TIME: Duration = Duration::from_secs(300);
+
 /// The maximum depth of call graphs allowed for ICQC
 pub(crate) const MAX_QUERY_CALL_DEPTH: usize = 6;
 /// Equivalent to MAX_INSTRUCTIONS_PER_MESSAGE_WITHOUT_DTS for now
@@ -155,6 +162,29 @@ pub struct Config {
 
     /// Indicates whether composite queries are available or not.
     pub composite_queries: FlagStatus,
+
+    /// Maximum total size of the compiled modules kept in memory by the
+    /// compilation cache.
+    pub max_compilation_cache_size: NumBytes,
+
+    /// Maximum total size of the compiled modules kept on disk by the
+    /// compilation cache.
+    pub max_compilation_cache_disk_size: NumBytes,
+
+    /// The directory where the compilation cache stores modules evicted from
+    /// memory. The replica places it under the state root if not set.
+    pub compilation_cache_dir: Option<PathBuf>,
+
+    /// Indicates whether the results of user queries are cached.
+    pub query_caching: FlagStatus,
+
+    /// The maximum total size of the query results kept in the query cache.
+    pub query_cache_capacity: NumBytes,
+
+    /// A cached query result is only reused while the batch time of the
+    /// queried state is at most this much later than the batch time of the
+    /// state it was computed against.
+    pub query_cache_max_expiry_time: Duration,
 }
 
 impl Default for Config {
@@ -211,6 +241,12 @@ impl Default for Config {
                 mainnet_canister_id: Some(bitcoin_mainnet_canister_id),
             },
             composite_queries: FlagStatus::Disabled,
+            max_compilation_cache_size: embedders::MAX_COMPILATION_CACHE_SIZE,
+            max_compilation_cache_disk_size: embedders::MAX_COMPILATION_CACHE_DISK_SIZE,
+            compilation_cache_dir: None,
+            query_caching: FlagStatus::Enabled,
+            query_cache_capacity: QUERY_CACHE_CAPACITY,
+            query_cache_max_expiry_time: QUERY_CACHE_MAX_EXPIRY_TIME,
         }
     }
 }
@@ -28,9 +28,16 @@ pub struct Config {
     pub ipv6_rule_template: String,
     pub ipv4_user_output_rule_template: String,
     pub ipv6_user_output_rule_template: String,
+    /// Templates for inbound UDP rules. They are only used to whitelist the
+    /// nodes on `udp_ports_for_node_whitelist`.
+    pub ipv4_udp_rule_template: String,
+    pub ipv6_udp_rule_template: String,
     #[cfg_attr(test, proptest(strategy = "any::<String>().prop_map(|_x| vec![])"))]
     pub default_rules: Vec<FirewallRule>,
     pub ports_for_node_whitelist: Vec<u32>,
+    /// UDP ports on which all nodes in the registry are whitelisted, e.g. the
+    /// transport port when the transport runs over QUIC.
+    pub udp_ports_for_node_whitelist: Vec<u32>,
     pub ports_for_http_adapter_blacklist: Vec<u32>,
 }
 
@@ -43,8 +50,11 @@ impl Default for Config {
             ipv6_rule_template: "".to_string(),
             ipv4_user_output_rule_template: "".to_string(),
             ipv6_user_output_rule_template: "".to_string(),
+            ipv4_udp_rule_template: "".to_string(),
+            ipv6_udp_rule_template: "".to_string(),
             default_rules: vec![],
             ports_for_node_whitelist: vec![],
+            udp_ports_for_node_whitelist: vec![],
             ports_for_http_adapter_blacklist: vec![],
         }
     }
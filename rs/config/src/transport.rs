@@ -14,6 +14,18 @@ pub struct TransportConfig {
     pub legacy_flow_tag: u32,
 }
 
+/// The protocol that transport uses to connect to the peers. All nodes of a
+/// subnet must use the same protocol, so it is not part of the node's
+/// `TransportConfig`, but selected by the `quic_transport` subnet feature in
+/// the registry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TransportProtocol {
+    /// One TLS over TCP connection per peer, shared by all flows
+    Tcp,
+    /// One QUIC connection per peer, with an independent stream per flow
+    Quic,
+}
+
 impl Default for TransportConfig {
     fn default() -> Self {
         Self {
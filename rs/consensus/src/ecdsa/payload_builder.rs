@@ -4,7 +4,7 @@
 
 use super::pre_signer::{EcdsaTranscriptBuilder, EcdsaTranscriptBuilderImpl};
 use super::signer::{EcdsaSignatureBuilder, EcdsaSignatureBuilderImpl};
-use super::utils::EcdsaBlockReaderImpl;
+use super::utils::{algorithm_for_key_id, EcdsaBlockReaderImpl};
 use crate::consensus::{
     crypto::ConsensusCrypto, metrics::EcdsaPayloadMetrics, pool_reader::PoolReader,
 };
@@ -699,6 +699,7 @@ pub(crate) fn create_data_payload_helper_2(
         next_interval_registry_version,
         current_key_transcript.as_ref(),
         &mut ecdsa_payload.key_transcript.next_in_creation,
+        &ecdsa_payload.key_transcript.key_id,
         &mut ecdsa_payload.uid_generator,
         transcript_builder,
         height,
@@ -739,6 +740,7 @@ fn new_random_config(
     subnet_nodes: &[NodeId],
     summary_registry_version: RegistryVersion,
     uid_generator: &mut ecdsa::EcdsaUIDGenerator,
+    algorithm_id: AlgorithmId,
 ) -> Result<ecdsa::RandomTranscriptParams, EcdsaPayloadError> {
     let transcript_id = uid_generator.next_transcript_id();
     let dealers = subnet_nodes.iter().copied().collect::<BTreeSet<_>>();
@@ -748,7 +750,7 @@ fn new_random_config(
         dealers,
         receivers,
         summary_registry_version,
-        AlgorithmId::ThresholdEcdsaSecp256k1,
+        algorithm_id,
     ))
 }
 
@@ -782,11 +784,14 @@ fn make_new_quadruples_if_needed_helper(
     let unassigned_quadruples = ecdsa_payload.unassigned_quadruple_ids().count();
     let quadruples_to_create = ecdsa_config.quadruples_to_create_in_advance as usize;
     if quadruples_to_create > unassigned_quadruples {
+        let algorithm_id = algorithm_for_key_id(&ecdsa_payload.key_transcript.key_id);
         let quadruples_in_creation = &mut ecdsa_payload.quadruples_in_creation;
         let uid_generator = &mut ecdsa_payload.uid_generator;
         for _ in 0..(quadruples_to_create - unassigned_quadruples) {
-            let kappa_config = new_random_config(subnet_nodes, registry_version, uid_generator)?;
-            let lambda_config = new_random_config(subnet_nodes, registry_version, uid_generator)?;
+            let kappa_config =
+                new_random_config(subnet_nodes, registry_version, uid_generator, algorithm_id)?;
+            let lambda_config =
+                new_random_config(subnet_nodes, registry_version, uid_generator, algorithm_id)?;
             quadruples_in_creation.insert(
                 uid_generator.next_quadruple_id(),
                 ecdsa::QuadrupleInCreation::new(kappa_config, lambda_config),
@@ -1110,6 +1115,7 @@ fn update_next_key_transcript(
     registry_version: RegistryVersion,
     current_key_transcript: Option<&ecdsa::UnmaskedTranscriptWithAttributes>,
     next_key_transcript_creation: &mut ecdsa::KeyTranscriptCreation,
+    key_id: &EcdsaKeyId,
     uid_generator: &mut ecdsa::EcdsaUIDGenerator,
     transcript_cache: &dyn EcdsaTranscriptBuilder,
     height: Height,
@@ -1169,7 +1175,7 @@ fn update_next_key_transcript(
                     dealers_set,
                     receivers_set,
                     registry_version,
-                    AlgorithmId::ThresholdEcdsaSecp256k1,
+                    algorithm_for_key_id(key_id),
                 ),
             );
         }
@@ -1782,10 +1788,11 @@ mod tests {
         uid_generator: &mut ecdsa::EcdsaUIDGenerator,
         quadruples_in_creation: &mut BTreeMap<ecdsa::QuadrupleId, ecdsa::QuadrupleInCreation>,
     ) -> (ecdsa::RandomTranscriptParams, ecdsa::RandomTranscriptParams) {
+        let algorithm_id = AlgorithmId::ThresholdEcdsaSecp256k1;
         let kappa_config_ref =
-            new_random_config(subnet_nodes, registry_version, uid_generator).unwrap();
+            new_random_config(subnet_nodes, registry_version, uid_generator, algorithm_id).unwrap();
         let lambda_config_ref =
-            new_random_config(subnet_nodes, registry_version, uid_generator).unwrap();
+            new_random_config(subnet_nodes, registry_version, uid_generator, algorithm_id).unwrap();
         quadruples_in_creation.insert(
             uid_generator.next_quadruple_id(),
             ecdsa::QuadrupleInCreation::new(kappa_config_ref.clone(), lambda_config_ref.clone()),
@@ -2257,6 +2264,7 @@ mod tests {
             registry_version,
             None,
             &mut payload.key_transcript.next_in_creation,
+            &payload.key_transcript.key_id,
             &mut payload.uid_generator,
             &transcript_builder,
             cur_height,
@@ -2290,6 +2298,7 @@ mod tests {
             registry_version,
             None,
             &mut payload.key_transcript.next_in_creation,
+            &payload.key_transcript.key_id,
             &mut payload.uid_generator,
             &transcript_builder,
             cur_height,
@@ -2329,6 +2338,7 @@ mod tests {
             registry_version,
             None,
             &mut payload.key_transcript.next_in_creation,
+            &payload.key_transcript.key_id,
             &mut payload.uid_generator,
             &transcript_builder,
             cur_height,
@@ -2366,6 +2376,7 @@ mod tests {
             registry_version,
             Some(&current_key_transcript),
             &mut payload.key_transcript.next_in_creation,
+            &payload.key_transcript.key_id,
             &mut payload.uid_generator,
             &transcript_builder,
             cur_height,
@@ -2400,6 +2411,7 @@ mod tests {
             registry_version,
             Some(&current_key_transcript),
             &mut payload.key_transcript.next_in_creation,
+            &payload.key_transcript.key_id,
             &mut payload.uid_generator,
             &transcript_builder,
             cur_height,
@@ -2450,6 +2462,7 @@ mod tests {
             registry_version,
             None,
             &mut payload.key_transcript.next_in_creation,
+            &payload.key_transcript.key_id,
             &mut payload.uid_generator,
             &transcript_builder,
             cur_height,
@@ -2483,6 +2496,7 @@ mod tests {
             registry_version,
             None,
             &mut payload.key_transcript.next_in_creation,
+            &payload.key_transcript.key_id,
             &mut payload.uid_generator,
             &transcript_builder,
             cur_height,
@@ -2522,6 +2536,7 @@ mod tests {
             registry_version,
             None,
             &mut payload.key_transcript.next_in_creation,
+            &payload.key_transcript.key_id,
             &mut payload.uid_generator,
             &transcript_builder,
             cur_height,
@@ -2571,6 +2586,7 @@ mod tests {
             registry_version,
             None,
             &mut payload.key_transcript.next_in_creation,
+            &payload.key_transcript.key_id,
             &mut payload.uid_generator,
             &transcript_builder,
             cur_height,
@@ -2609,6 +2625,7 @@ mod tests {
             registry_version,
             None,
             &mut payload.key_transcript.next_in_creation,
+            &payload.key_transcript.key_id,
             &mut payload.uid_generator,
             &transcript_builder,
             cur_height,
@@ -1,7 +1,7 @@
 //! Common utils for the ECDSA implementation.
 
 use crate::ecdsa::complaints::{EcdsaTranscriptLoader, TranscriptLoadStatus};
-use ic_ic00_types::EcdsaKeyId;
+use ic_ic00_types::{EcdsaCurve, EcdsaKeyId};
 use ic_interfaces::consensus_pool::ConsensusBlockChain;
 use ic_interfaces::ecdsa::{EcdsaChangeAction, EcdsaChangeSet, EcdsaPool};
 use ic_protobuf::registry::subnet::v1 as pb;
@@ -13,6 +13,7 @@ use ic_types::consensus::ecdsa::{
 use ic_types::crypto::canister_threshold_sig::idkg::{
     IDkgTranscript, IDkgTranscriptOperation, InitialIDkgDealings,
 };
+use ic_types::crypto::AlgorithmId;
 use ic_types::Height;
 use std::collections::BTreeSet;
 use std::convert::TryInto;
@@ -158,6 +159,14 @@ pub(crate) fn transcript_op_summary(op: &IDkgTranscriptOperation) -> String {
     }
 }
 
+/// The algorithm of the transcripts created for the given key.
+pub(crate) fn algorithm_for_key_id(key_id: &EcdsaKeyId) -> AlgorithmId {
+    match key_id.curve {
+        EcdsaCurve::Secp256k1 => AlgorithmId::ThresholdEcdsaSecp256k1,
+        EcdsaCurve::Secp256r1 => AlgorithmId::ThresholdEcdsaSecp256r1,
+    }
+}
+
 /// Inspect ecdsa_initializations field in the CUPContent.
 /// Return key_id and dealings.
 pub(crate) fn inspect_ecdsa_initializations(
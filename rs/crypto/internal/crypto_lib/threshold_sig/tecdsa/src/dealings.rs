@@ -190,7 +190,7 @@ impl IDkgDealingInternal {
         }
 
         for recipient in recipients {
-            if recipient.curve_type() != curve {
+            if recipient.curve_type() != mega_key_curve(curve) {
                 return Err(ThresholdEcdsaError::InvalidRecipients);
             }
         }
@@ -426,7 +426,8 @@ impl IDkgDealingInternal {
         dealer_index: NodeIndex,
         recipient_index: NodeIndex,
     ) -> ThresholdEcdsaResult<()> {
-        if private_key.curve_type() != curve_type || public_key.curve_type() != curve_type {
+        let key_curve = mega_key_curve(curve_type);
+        if private_key.curve_type() != key_curve || public_key.curve_type() != key_curve {
             return Err(ThresholdEcdsaError::CurveMismatch);
         }
 
@@ -76,6 +76,11 @@
 //! Implements the MEGa encryption/decryption scheme, including key
 //! generation.
 //!
+//! MEGa keys are always on secp256k1, see [`mega_key_curve`]. Shares of
+//! secrets on secp256r1, used for threshold ECDSA with
+//! [`AlgorithmId::ThresholdEcdsaSecp256r1`], are encrypted under the same
+//! secp256k1 keys.
+//!
 //! [`RandomOracle`](#utility-functions-random-oracle) is used to
 //! generate the additive masking values.
 //!
@@ -317,6 +322,7 @@ pub fn create_dealing(
 ) -> Result<IDkgDealingInternal, IdkgCreateDealingInternalError> {
     let curve = match algorithm_id {
         AlgorithmId::ThresholdEcdsaSecp256k1 => Ok(EccCurveType::K256),
+        AlgorithmId::ThresholdEcdsaSecp256r1 => Ok(EccCurveType::P256),
         _ => Err(IdkgCreateDealingInternalError::UnsupportedAlgorithm),
     }?;
 
@@ -368,6 +374,7 @@ pub fn create_transcript(
 ) -> Result<IDkgTranscriptInternal, IDkgCreateTranscriptInternalError> {
     let curve = match algorithm_id {
         AlgorithmId::ThresholdEcdsaSecp256k1 => Ok(EccCurveType::K256),
+        AlgorithmId::ThresholdEcdsaSecp256r1 => Ok(EccCurveType::P256),
         _ => Err(IDkgCreateTranscriptInternalError::UnsupportedAlgorithm),
     }?;
 
@@ -565,6 +572,7 @@ pub fn publicly_verify_dealing(
 ) -> Result<(), IDkgVerifyDealingInternalError> {
     let curve = match algorithm_id {
         AlgorithmId::ThresholdEcdsaSecp256k1 => Ok(EccCurveType::K256),
+        AlgorithmId::ThresholdEcdsaSecp256r1 => Ok(EccCurveType::P256),
         _ => Err(IDkgVerifyDealingInternalError::UnsupportedAlgorithm),
     }?;
 
@@ -597,6 +605,7 @@ pub fn privately_verify_dealing(
 ) -> Result<(), IDkgVerifyDealingInternalError> {
     let curve = match algorithm_id {
         AlgorithmId::ThresholdEcdsaSecp256k1 => Ok(EccCurveType::K256),
+        AlgorithmId::ThresholdEcdsaSecp256r1 => Ok(EccCurveType::P256),
         _ => Err(IDkgVerifyDealingInternalError::UnsupportedAlgorithm),
     }?;
 
@@ -660,6 +669,9 @@ fn signature_parameters(algorithm_id: AlgorithmId) -> Option<(EccCurveType, usiz
         AlgorithmId::ThresholdEcdsaSecp256k1 => {
             Some((EccCurveType::K256, EccCurveType::K256.scalar_bytes()))
         }
+        AlgorithmId::ThresholdEcdsaSecp256r1 => {
+            Some((EccCurveType::P256, EccCurveType::P256.scalar_bytes()))
+        }
         _ => None,
     }
 }
@@ -808,6 +820,7 @@ pub fn combine_sig_shares(
 ) -> Result<ThresholdEcdsaCombinedSigInternal, ThresholdEcdsaCombineSigSharesInternalError> {
     let curve_type = match algorithm_id {
         AlgorithmId::ThresholdEcdsaSecp256k1 => EccCurveType::K256,
+        AlgorithmId::ThresholdEcdsaSecp256r1 => EccCurveType::P256,
         _ => return Err(ThresholdEcdsaCombineSigSharesInternalError::UnsupportedAlgorithm),
     };
 
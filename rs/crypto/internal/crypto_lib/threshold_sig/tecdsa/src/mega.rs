@@ -44,6 +44,17 @@ impl MEGaCiphertextType {
     }
 }
 
+/// Return the curve of the MEGa keys used to encrypt shares on `curve`
+///
+/// Nodes only hold secp256k1 MEGa keys, so shares of secp256r1 secrets
+/// are also encrypted under secp256k1 keys.
+pub fn mega_key_curve(curve: EccCurveType) -> EccCurveType {
+    match curve {
+        EccCurveType::K256 => EccCurveType::K256,
+        EccCurveType::P256 => EccCurveType::K256,
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct MEGaPublicKey {
     point: EccPoint,
@@ -206,20 +217,22 @@ impl MEGaCiphertext {
     /// Simple type verification for MEGa ciphertexts
     ///
     /// Verifies that the ciphertext is of the expected type (single or pairs)
-    /// and is for the expected curve.
+    /// and encrypts values on the expected curve.
     pub fn verify_is(
         &self,
         ctype: MEGaCiphertextType,
         curve: EccCurveType,
     ) -> ThresholdEcdsaResult<()> {
-        if self.ephemeral_key().curve_type() != curve {
+        let key_curve = mega_key_curve(curve);
+
+        if self.ephemeral_key().curve_type() != key_curve {
             return Err(ThresholdEcdsaError::CurveMismatch);
         }
 
-        if self.pop_public_key().curve_type() != curve {
+        if self.pop_public_key().curve_type() != key_curve {
             return Err(ThresholdEcdsaError::CurveMismatch);
         }
-        if self.pop_proof().curve_type()? != curve {
+        if self.pop_proof().curve_type()? != key_curve {
             return Err(ThresholdEcdsaError::CurveMismatch);
         }
 
@@ -311,7 +324,7 @@ impl From<MEGaCiphertextPair> for MEGaCiphertext {
 fn check_plaintexts(
     plaintexts: &[EccScalar],
     recipients: &[MEGaPublicKey],
-) -> ThresholdEcdsaResult<EccCurveType> {
+) -> ThresholdEcdsaResult<(EccCurveType, EccCurveType)> {
     if plaintexts.len() != recipients.len() {
         return Err(ThresholdEcdsaError::InvalidArguments(
             "Must be as many plaintexts as recipients".to_string(),
@@ -325,6 +338,7 @@ fn check_plaintexts(
     }
 
     let curve_type = plaintexts[0].curve_type();
+    let key_curve = mega_key_curve(curve_type);
 
     for pt in plaintexts {
         if pt.curve_type() != curve_type {
@@ -333,18 +347,18 @@ fn check_plaintexts(
     }
 
     for recipient in recipients {
-        if recipient.curve_type() != curve_type {
+        if recipient.curve_type() != key_curve {
             return Err(ThresholdEcdsaError::CurveMismatch);
         }
     }
 
-    Ok(curve_type)
+    Ok((key_curve, curve_type))
 }
 
 fn check_plaintexts_pair(
     plaintexts: &[(EccScalar, EccScalar)],
     recipients: &[MEGaPublicKey],
-) -> ThresholdEcdsaResult<EccCurveType> {
+) -> ThresholdEcdsaResult<(EccCurveType, EccCurveType)> {
     if plaintexts.len() != recipients.len() {
         return Err(ThresholdEcdsaError::InvalidArguments(
             "Must be as many plaintexts as recipients".to_string(),
@@ -358,6 +372,7 @@ fn check_plaintexts_pair(
     }
 
     let curve_type = plaintexts[0].0.curve_type();
+    let key_curve = mega_key_curve(curve_type);
 
     for pt in plaintexts {
         if pt.0.curve_type() != curve_type || pt.1.curve_type() != curve_type {
@@ -366,16 +381,17 @@ fn check_plaintexts_pair(
     }
 
     for recipient in recipients {
-        if recipient.curve_type() != curve_type {
+        if recipient.curve_type() != key_curve {
             return Err(ThresholdEcdsaError::CurveMismatch);
         }
     }
 
-    Ok(curve_type)
+    Ok((key_curve, curve_type))
 }
 
 fn mega_hash_to_scalars(
     ctype: MEGaCiphertextType,
+    curve_type: EccCurveType,
     dealer_index: NodeIndex,
     recipient_index: NodeIndex,
     associated_data: &[u8],
@@ -383,8 +399,6 @@ fn mega_hash_to_scalars(
     ephemeral_key: &EccPoint,
     shared_secret: &EccPoint,
 ) -> ThresholdEcdsaResult<Vec<EccScalar>> {
-    let curve_type = public_key.curve_type();
-
     let count = match ctype {
         MEGaCiphertextType::Single => 1,
         MEGaCiphertextType::Pairs => 2,
@@ -489,12 +503,12 @@ impl MEGaCiphertextSingle {
         dealer_index: NodeIndex,
         associated_data: &[u8],
     ) -> ThresholdEcdsaResult<Self> {
-        let curve_type = check_plaintexts(plaintexts, recipients)?;
+        let (key_curve, curve_type) = check_plaintexts(plaintexts, recipients)?;
 
         let ctype = MEGaCiphertextType::Single;
 
         let (beta, v, pop_public_key, pop_proof) =
-            compute_eph_key_and_pop(ctype, curve_type, seed, associated_data, dealer_index)?;
+            compute_eph_key_and_pop(ctype, key_curve, seed, associated_data, dealer_index)?;
 
         let mut ctexts = Vec::with_capacity(recipients.len());
 
@@ -503,6 +517,7 @@ impl MEGaCiphertextSingle {
 
             let hm = mega_hash_to_scalars(
                 ctype,
+                curve_type,
                 dealer_index,
                 index as NodeIndex,
                 associated_data,
@@ -555,6 +570,7 @@ impl MEGaCiphertextSingle {
 
         let hm = mega_hash_to_scalars(
             MEGaCiphertextType::Single,
+            self.ctexts[recipient_index as usize].curve_type(),
             dealer_index,
             recipient_index,
             associated_data,
@@ -600,12 +616,12 @@ impl MEGaCiphertextPair {
         dealer_index: NodeIndex,
         associated_data: &[u8],
     ) -> ThresholdEcdsaResult<Self> {
-        let curve_type = check_plaintexts_pair(plaintexts, recipients)?;
+        let (key_curve, curve_type) = check_plaintexts_pair(plaintexts, recipients)?;
 
         let ctype = MEGaCiphertextType::Pairs;
 
         let (beta, v, pop_public_key, pop_proof) =
-            compute_eph_key_and_pop(ctype, curve_type, seed, associated_data, dealer_index)?;
+            compute_eph_key_and_pop(ctype, key_curve, seed, associated_data, dealer_index)?;
 
         let mut ctexts = Vec::with_capacity(recipients.len());
 
@@ -614,6 +630,7 @@ impl MEGaCiphertextPair {
 
             let hm = mega_hash_to_scalars(
                 ctype,
+                curve_type,
                 dealer_index,
                 index as NodeIndex,
                 associated_data,
@@ -667,6 +684,7 @@ impl MEGaCiphertextPair {
 
         let hm = mega_hash_to_scalars(
             MEGaCiphertextType::Pairs,
+            self.ctexts[recipient_index as usize].0.curve_type(),
             dealer_index,
             recipient_index,
             associated_data,
@@ -1,6 +1,7 @@
 use ic_crypto_internal_threshold_sig_ecdsa::*;
 use ic_crypto_test_utils_reproducible_rng::reproducible_rng;
-use rand::Rng;
+use ic_types::crypto::canister_threshold_sig::MasterEcdsaPublicKey;
+use ic_types::crypto::AlgorithmId;
 use std::convert::{TryFrom, TryInto};
 
 #[allow(dead_code)]
@@ -24,20 +25,54 @@ fn test_index_next_behavior() {
 }
 
 #[test]
-fn test_that_key_derivation_on_secp256r1_currently_fails() -> Result<(), ThresholdEcdsaError> {
-    let mut rng = reproducible_rng();
-    let path = DerivationPath::new_bip32(&[1, 2, 3]);
-    let master_key = EccPoint::hash_to_point(
+fn verify_secp256r1_extended_key_derivation() -> Result<(), ThresholdEcdsaError> {
+    // The secret key of the P-256 test vectors in RFC 6979 section A.2.5
+    let master_secret = EccScalar::deserialize(
         EccCurveType::P256,
-        &rng.gen::<[u8; 32]>(),
-        "public_key".as_bytes(),
+        &hex::decode("c9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721").unwrap(),
     )?;
 
+    let master_key = MasterEcdsaPublicKey {
+        algorithm_id: AlgorithmId::EcdsaP256,
+        public_key: EccPoint::mul_by_g(&master_secret)?.serialize(),
+    };
+
+    let derive = |path: Vec<DerivationIndex>| -> Result<(String, String), ThresholdEcdsaError> {
+        let key = sign::derive_public_key(&master_key, &DerivationPath::new(path))?;
+        assert_eq!(key.algorithm_id, AlgorithmId::EcdsaP256);
+        Ok((hex::encode(key.public_key), hex::encode(key.chain_key)))
+    };
+
+    let index1 = DerivationIndex(vec![1, 2, 3, 4, 5]);
+    let index2 = DerivationIndex(vec![8, 0, 2, 8, 0, 2]);
+
+    assert_eq!(
+        derive(vec![])?,
+        (
+            "0360fed4ba255a9d31c961eb74c6356d68c049b8923b61fa6ce669622e60f29fb6".to_string(),
+            "0000000000000000000000000000000000000000000000000000000000000000".to_string()
+        )
+    );
+    assert_eq!(
+        derive(vec![index1.clone()])?,
+        (
+            "02b15d8bfa6dcc3cecea23cc7fb8fad182085bca82d5998d94dbcce1c550b821d2".to_string(),
+            "3f1c68110df0de68f3fb005d48f1fca8aec13f2d2cae3041cbd5e6267f89d7cd".to_string()
+        )
+    );
+    assert_eq!(
+        derive(vec![index2.clone()])?,
+        (
+            "03a8e1449bef5901d05a1e3ad1848142d019f04abf4c18bf3bc6a64281ce655dc1".to_string(),
+            "24d9cdf69a938df3edadeadcedf330ce64b3f8677b71b076f68426627feb07e1".to_string()
+        )
+    );
     assert_eq!(
-        path.derive_tweak(&master_key),
-        Err(ThresholdEcdsaError::InvalidArguments(
-            "Currently key derivation not defined for secp256r1".to_string()
-        ))
+        derive(vec![index1, index2])?,
+        (
+            "03b118aa41daf04f9eeb1b0ceb6aea30ab24d3c5a4ecf2f45df0720434658a3c76".to_string(),
+            "f3d7362f43e5d34369c835b288fb688f0f761961e0baecd878e7f0d86b5ecd5f".to_string()
+        )
     );
 
     Ok(())
@@ -115,6 +115,69 @@ fn mega_single_smoke_test() -> Result<(), ThresholdEcdsaError> {
     Ok(())
 }
 
+#[test]
+fn mega_should_encrypt_secp256r1_plaintexts_under_secp256k1_keys() -> Result<(), ThresholdEcdsaError>
+{
+    let curve = EccCurveType::P256;
+    let key_curve = mega_key_curve(curve);
+    assert_eq!(key_curve, EccCurveType::K256);
+
+    let mut rng = reproducible_rng();
+
+    let a_sk = MEGaPrivateKey::generate(key_curve, &mut rng)?;
+    let b_sk = MEGaPrivateKey::generate(key_curve, &mut rng)?;
+
+    let a_pk = a_sk.public_key()?;
+    let b_pk = b_sk.public_key()?;
+
+    let associated_data = b"assoc_data_test";
+
+    let ptext_for_a = EccScalar::random(curve, &mut rng);
+    let ptext_for_b = EccScalar::random(curve, &mut rng);
+
+    let dealer_index = 0;
+
+    let ctext = MEGaCiphertextSingle::encrypt(
+        Seed::from_rng(&mut rng),
+        &[ptext_for_a.clone(), ptext_for_b.clone()],
+        &[a_pk.clone(), b_pk.clone()],
+        dealer_index,
+        associated_data,
+    )?;
+
+    assert!(MEGaCiphertext::from(ctext.clone())
+        .verify_is(MEGaCiphertextType::Single, curve)
+        .is_ok());
+    assert_eq!(
+        MEGaCiphertext::from(ctext.clone()).verify_is(MEGaCiphertextType::Single, key_curve),
+        Err(ThresholdEcdsaError::CurveMismatch)
+    );
+
+    assert_eq!(
+        ctext.decrypt(associated_data, dealer_index, 0, &a_sk, &a_pk)?,
+        ptext_for_a
+    );
+    assert_eq!(
+        ctext.decrypt(associated_data, dealer_index, 1, &b_sk, &b_pk)?,
+        ptext_for_b
+    );
+
+    // Recipient keys on the plaintext curve are rejected
+    let p256_pk = MEGaPrivateKey::generate(curve, &mut rng)?.public_key()?;
+    assert_eq!(
+        MEGaCiphertextSingle::encrypt(
+            Seed::from_rng(&mut rng),
+            &[ptext_for_a],
+            &[p256_pk],
+            dealer_index,
+            associated_data,
+        ),
+        Err(ThresholdEcdsaError::CurveMismatch)
+    );
+
+    Ok(())
+}
+
 #[test]
 fn mega_pair_smoke_test() -> Result<(), ThresholdEcdsaError> {
     let curve = EccCurveType::K256;
@@ -207,8 +207,7 @@ fn random_subset(
     result
 }
 
-#[test]
-fn should_basic_signing_protocol_work() -> Result<(), ThresholdEcdsaError> {
+fn check_basic_signing_protocol(curve: EccCurveType) -> Result<(), ThresholdEcdsaError> {
     fn test_sig_serialization(
         alg: ic_types::crypto::AlgorithmId,
         sig: &ThresholdEcdsaCombinedSigInternal,
@@ -227,7 +226,7 @@ fn should_basic_signing_protocol_work() -> Result<(), ThresholdEcdsaError> {
     let random_seed = Seed::from_rng(&mut rng);
 
     let setup = SignatureProtocolSetup::new(
-        EccCurveType::K256,
+        curve,
         nodes,
         threshold,
         number_of_dealings_corrupted,
@@ -276,3 +275,13 @@ fn should_basic_signing_protocol_work() -> Result<(), ThresholdEcdsaError> {
 
     Ok(())
 }
+
+#[test]
+fn should_basic_signing_protocol_work() -> Result<(), ThresholdEcdsaError> {
+    check_basic_signing_protocol(EccCurveType::K256)
+}
+
+#[test]
+fn should_basic_signing_protocol_work_on_secp256r1() -> Result<(), ThresholdEcdsaError> {
+    check_basic_signing_protocol(EccCurveType::P256)
+}
@@ -28,11 +28,7 @@ impl ProtocolSetup {
     ) -> Result<Self, ThresholdEcdsaError> {
         let alg = match curve {
             EccCurveType::K256 => AlgorithmId::ThresholdEcdsaSecp256k1,
-            _ => {
-                return Err(ThresholdEcdsaError::InvalidArguments(
-                    "Unsupported curve".to_string(),
-                ))
-            }
+            EccCurveType::P256 => AlgorithmId::ThresholdEcdsaSecp256r1,
         };
 
         let mut rng = seed.into_rng();
@@ -42,7 +38,7 @@ impl ProtocolSetup {
         let mut pk = Vec::with_capacity(receivers);
 
         for _i in 0..receivers {
-            let k = MEGaPrivateKey::generate(curve, &mut rng)?;
+            let k = MEGaPrivateKey::generate(mega_key_curve(curve), &mut rng)?;
             pk.push(k.public_key()?);
             sk.push(k);
         }
@@ -737,9 +733,14 @@ impl SignatureProtocolSetup {
     }
 
     pub fn public_key(&self, path: &DerivationPath) -> Result<EcdsaPublicKey, ThresholdEcdsaError> {
+        let constant_term = self.key.transcript.constant_term();
+        let algorithm_id = match constant_term.curve_type() {
+            EccCurveType::K256 => AlgorithmId::EcdsaSecp256k1,
+            EccCurveType::P256 => AlgorithmId::EcdsaP256,
+        };
         let master_public_key = MasterEcdsaPublicKey {
-            algorithm_id: AlgorithmId::EcdsaSecp256k1,
-            public_key: self.key.transcript.constant_term().serialize(),
+            algorithm_id,
+            public_key: constant_term.serialize(),
         };
         ic_crypto_internal_threshold_sig_ecdsa::sign::derive_public_key(&master_public_key, path)
     }
@@ -847,18 +848,36 @@ impl SignatureProtocolExecution {
             self.setup.setup.alg,
         )?;
 
-        // If verification succeeded, check with RustCrypto's ECDSA also
+        // If verification succeeded, check with another ECDSA implementation also
         let pk = self.setup.public_key(&self.derivation_path)?;
 
-        use k256::ecdsa::signature::{Signature, Verifier};
+        match pk.algorithm_id {
+            AlgorithmId::EcdsaSecp256k1 => {
+                use k256::ecdsa::signature::{Signature, Verifier};
+
+                let vk = k256::ecdsa::VerifyingKey::from_sec1_bytes(&pk.public_key)
+                    .expect("Failed to parse public key");
+
+                let sig = k256::ecdsa::Signature::from_bytes(&sig.serialize())
+                    .expect("Failed to parse signature");
 
-        let vk = k256::ecdsa::VerifyingKey::from_sec1_bytes(&pk.public_key)
-            .expect("Failed to parse public key");
+                assert!(vk.verify(&self.signed_message, &sig).is_ok());
+            }
+            AlgorithmId::EcdsaP256 => {
+                use ic_crypto_internal_basic_sig_ecdsa_secp256r1::{api, types};
 
-        let sig = k256::ecdsa::Signature::from_bytes(&sig.serialize())
-            .expect("Failed to parse signature");
+                let mut sig_bytes = [0u8; types::SignatureBytes::SIZE];
+                sig_bytes.copy_from_slice(&sig.serialize());
 
-        assert!(vk.verify(&self.signed_message, &sig).is_ok());
+                assert!(api::verify(
+                    &types::SignatureBytes(sig_bytes),
+                    &self.hashed_message,
+                    &types::PublicKeyBytes(pk.public_key),
+                )
+                .is_ok());
+            }
+            alg => panic!("Unexpected algorithm {:?}", alg),
+        }
 
         Ok(())
     }
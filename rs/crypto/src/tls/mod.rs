@@ -1,6 +1,7 @@
 use super::*;
 use async_trait::async_trait;
 use ic_crypto_internal_logmon::metrics::{MetricsDomain, MetricsResult, MetricsScope};
+use ic_crypto_tls_interfaces::rustls::{Certificate, ClientConfig, ServerConfig};
 use ic_crypto_tls_interfaces::{
     AllowedClients, AuthenticatedPeer, MalformedPeerCertificateError, TlsClientHandshakeError,
     TlsHandshake, TlsPublicKeyCert, TlsServerHandshakeError, TlsStream,
@@ -144,6 +145,84 @@ where
         );
         result
     }
+
+    fn quic_server_config(&self) -> Result<ServerConfig, TlsServerHandshakeError> {
+        let log_id = get_log_id(&self.logger, module_path!());
+        let logger = new_logger!(&self.logger;
+            crypto.log_id => log_id,
+            crypto.trait_name => "TlsHandshake",
+            crypto.method_name => "quic_server_config",
+        );
+        debug!(logger;
+            crypto.description => "start",
+            crypto.allowed_tls_clients => "all nodes",
+        );
+        let start_time = self.metrics.now();
+        let result = rustls::server_handshake::quic_server_config(
+            &self.csp,
+            self.node_id,
+            &self.registry_client,
+        );
+        self.metrics.observe_duration_seconds(
+            MetricsDomain::TlsHandshake,
+            MetricsScope::Full,
+            "quic_server_config",
+            MetricsResult::from(&result),
+            start_time,
+        );
+        debug!(logger;
+            crypto.description => "end",
+            crypto.is_ok => result.is_ok(),
+            crypto.error => log_err(result.as_ref().err()),
+        );
+        result
+    }
+
+    fn quic_authenticated_peer(
+        &self,
+        peer_certificates: &[Certificate],
+    ) -> Result<AuthenticatedPeer, TlsServerHandshakeError> {
+        rustls::server_handshake::quic_authenticated_peer(peer_certificates)
+    }
+
+    fn quic_client_config(
+        &self,
+        server: NodeId,
+        registry_version: RegistryVersion,
+    ) -> Result<ClientConfig, TlsClientHandshakeError> {
+        let log_id = get_log_id(&self.logger, module_path!());
+        let logger = new_logger!(&self.logger;
+            crypto.log_id => log_id,
+            crypto.trait_name => "TlsHandshake",
+            crypto.method_name => "quic_client_config",
+        );
+        debug!(logger;
+            crypto.description => "start",
+            crypto.registry_version => registry_version.get(),
+            crypto.tls_server => format!("{}", server),
+        );
+        let start_time = self.metrics.now();
+        let result = rustls::client_handshake::client_config(
+            &self.csp,
+            self.node_id,
+            &self.registry_client,
+            server,
+            registry_version,
+        );
+        self.metrics.observe_duration_seconds(
+            MetricsDomain::TlsHandshake,
+            MetricsScope::Full,
+            "quic_client_config",
+            MetricsResult::from(&result),
+            start_time,
+        );
+        debug!(logger;
+            crypto.description => "end",
+            crypto.is_ok => result.is_ok(),
+            crypto.error => log_err(result.as_ref().err()),
+        );
+        result
+    }
 }
 
 fn node_id_from_cert_subject_common_name(
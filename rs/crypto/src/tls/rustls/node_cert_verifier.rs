@@ -64,7 +64,9 @@ impl NodeServerCertVerifier {
 /// * The presented certificate equals the node's certificate fetched from the
 ///   `registry_client` at version `registry_version` for the `NodeId` parsed
 ///   from the presented certificate. (The `registry_client` and
-///   `registry_version` are passed to the constructors.)
+///   `registry_version` are passed to the constructors. If no
+///   `registry_version` is passed, the latest version of the registry at the
+///   time of the verification is used.)
 ///
 /// If any of these conditions does not hold, a `TLSError` is returned.
 ///
@@ -72,7 +74,7 @@ impl NodeServerCertVerifier {
 pub struct NodeClientCertVerifier {
     allowed_nodes: SomeOrAllNodes,
     registry_client: Arc<dyn RegistryClient>,
-    registry_version: RegistryVersion,
+    registry_version: Option<RegistryVersion>,
 }
 
 impl NodeClientCertVerifier {
@@ -89,7 +91,23 @@ impl NodeClientCertVerifier {
         Self {
             allowed_nodes,
             registry_client,
-            registry_version,
+            registry_version: Some(registry_version),
+        }
+    }
+
+    /// Creates a verifier that considers only certificates for the
+    /// `allowed_nodes` fetched from the `registry_client` at the latest
+    /// registry version as trusted.
+    ///
+    /// Client authentication is mandatory.
+    pub fn new_with_mandatory_client_auth_at_latest_version(
+        allowed_nodes: SomeOrAllNodes,
+        registry_client: Arc<dyn RegistryClient>,
+    ) -> Self {
+        Self {
+            allowed_nodes,
+            registry_client,
+            registry_version: None,
         }
     }
 }
@@ -135,11 +153,14 @@ impl ClientCertVerifier for NodeClientCertVerifier {
         presented_certs: &[Certificate],
         _sni: Option<&webpki::DNSName>,
     ) -> Result<ClientCertVerified, TLSError> {
+        let registry_version = self
+            .registry_version
+            .unwrap_or_else(|| self.registry_client.get_latest_version());
         verify_node_cert(
             presented_certs,
             &self.allowed_nodes,
             &self.registry_client,
-            self.registry_version,
+            registry_version,
         )
         .map(|_| ClientCertVerified::assertion())
     }
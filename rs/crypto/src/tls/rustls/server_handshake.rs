@@ -7,7 +7,8 @@ use crate::tls::{
 };
 use ic_crypto_internal_csp::api::CspTlsHandshakeSignerProvider;
 use ic_crypto_tls_interfaces::{
-    AllowedClients, AuthenticatedPeer, TlsPublicKeyCert, TlsServerHandshakeError, TlsStream,
+    AllowedClients, AuthenticatedPeer, SomeOrAllNodes, TlsPublicKeyCert, TlsServerHandshakeError,
+    TlsStream,
 };
 use ic_interfaces_registry::RegistryClient;
 use ic_types::{NodeId, RegistryVersion};
@@ -16,8 +17,8 @@ use tokio::net::TcpStream;
 use tokio_rustls::rustls::ciphersuite::{TLS13_AES_128_GCM_SHA256, TLS13_AES_256_GCM_SHA384};
 use tokio_rustls::rustls::sign::CertifiedKey;
 use tokio_rustls::rustls::{
-    ClientCertVerifier, NoClientAuth, ProtocolVersion, ResolvesServerCert, ServerConfig, Session,
-    SignatureScheme,
+    Certificate, ClientCertVerifier, NoClientAuth, ProtocolVersion, ResolvesServerCert,
+    ServerConfig, Session, SignatureScheme,
 };
 use tokio_rustls::TlsAcceptor;
 
@@ -113,6 +114,45 @@ fn static_cert_resolver(key: CertifiedKey, scheme: SignatureScheme) -> Arc<dyn R
     ))
 }
 
+/// Returns the TLS configuration of a QUIC endpoint that accepts connections
+/// from other nodes.
+///
+/// Since a QUIC endpoint outlives the individual connections it accepts, the
+/// clients are authenticated against the certificates of all nodes in the
+/// latest version of the registry at the time of each handshake. Callers must
+/// therefore check if the node returned by `quic_authenticated_peer` is an
+/// allowed client.
+pub fn quic_server_config<P: CspTlsHandshakeSignerProvider>(
+    signer_provider: &P,
+    self_node_id: NodeId,
+    registry_client: &Arc<dyn RegistryClient>,
+) -> Result<ServerConfig, TlsServerHandshakeError> {
+    let registry_version = registry_client.get_latest_version();
+    let self_tls_cert = tls_cert_from_registry(registry_client, self_node_id, registry_version)?;
+    let client_cert_verifier =
+        NodeClientCertVerifier::new_with_mandatory_client_auth_at_latest_version(
+            SomeOrAllNodes::All,
+            Arc::clone(registry_client),
+        );
+    Ok(
+        server_config_with_tls13_and_aes_ciphersuites_and_ed25519_signing_key(
+            Arc::new(client_cert_verifier),
+            self_tls_cert,
+            signer_provider,
+        ),
+    )
+}
+
+/// Determines the node that authenticated with the given certificates in a
+/// QUIC handshake with a server configured by `quic_server_config`.
+pub fn quic_authenticated_peer(
+    peer_certificates: &[Certificate],
+) -> Result<AuthenticatedPeer, TlsServerHandshakeError> {
+    let client_cert = single_client_cert(peer_certificates)?;
+    let node_id = node_id_from_cert_subject_common_name(&client_cert)?;
+    Ok(AuthenticatedPeer::Node(node_id))
+}
+
 fn single_client_cert_from_handshake(
     tls_stream: &tokio_rustls::server::TlsStream<TcpStream>,
 ) -> Result<TlsPublicKeyCert, TlsServerHandshakeError> {
@@ -121,6 +161,12 @@ fn single_client_cert_from_handshake(
             internal_error: "missing peer certificates in session".to_string(),
         },
     )?;
+    single_client_cert(&peer_certs)
+}
+
+fn single_client_cert(
+    peer_certs: &[Certificate],
+) -> Result<TlsPublicKeyCert, TlsServerHandshakeError> {
     if peer_certs.len() > 1 {
         return Err(TlsServerHandshakeError::HandshakeError {
             internal_error: "peer sent more than one certificate, but expected only a single one"
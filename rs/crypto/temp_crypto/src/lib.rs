@@ -9,6 +9,7 @@ use ic_crypto_node_key_generation::{
     derive_node_id, generate_committee_signing_keys, generate_dkg_dealing_encryption_keys,
     generate_idkg_dealing_encryption_keys, generate_node_signing_keys, generate_tls_keys,
 };
+use ic_crypto_tls_interfaces::rustls::{Certificate, ClientConfig, ServerConfig};
 use ic_crypto_tls_interfaces::{
     AllowedClients, AuthenticatedPeer, TlsClientHandshakeError, TlsHandshake, TlsPublicKeyCert,
     TlsServerHandshakeError, TlsStream,
@@ -877,6 +878,27 @@ impl<C: CryptoServiceProvider + Send + Sync> TlsHandshake for TempCryptoComponen
             .perform_tls_client_handshake(tcp_stream, server, registry_version)
             .await
     }
+
+    fn quic_server_config(&self) -> Result<ServerConfig, TlsServerHandshakeError> {
+        self.crypto_component.quic_server_config()
+    }
+
+    fn quic_authenticated_peer(
+        &self,
+        peer_certificates: &[Certificate],
+    ) -> Result<AuthenticatedPeer, TlsServerHandshakeError> {
+        self.crypto_component
+            .quic_authenticated_peer(peer_certificates)
+    }
+
+    fn quic_client_config(
+        &self,
+        server: NodeId,
+        registry_version: RegistryVersion,
+    ) -> Result<ClientConfig, TlsClientHandshakeError> {
+        self.crypto_component
+            .quic_client_config(server, registry_version)
+    }
 }
 
 impl<C: CryptoServiceProvider, T: Signable> BasicSigVerifier<T> for TempCryptoComponentGeneric<C> {
@@ -1,5 +1,6 @@
 use async_trait::async_trait;
 use ic_base_types::{NodeId, RegistryVersion};
+use ic_crypto_tls_interfaces::rustls::{Certificate, ClientConfig, ServerConfig};
 use ic_crypto_tls_interfaces::{
     AllowedClients, AuthenticatedPeer, TlsClientHandshakeError, TlsHandshake,
     TlsServerHandshakeError, TlsStream,
@@ -31,5 +32,18 @@ mock! {
             server: NodeId,
             registry_version: RegistryVersion,
         ) -> Result<Box<dyn TlsStream>, TlsClientHandshakeError>;
+
+        fn quic_server_config(&self) -> Result<ServerConfig, TlsServerHandshakeError>;
+
+        fn quic_authenticated_peer(
+            &self,
+            peer_certificates: &[Certificate],
+        ) -> Result<AuthenticatedPeer, TlsServerHandshakeError>;
+
+        fn quic_client_config(
+            &self,
+            server: NodeId,
+            registry_version: RegistryVersion,
+        ) -> Result<ClientConfig, TlsClientHandshakeError>;
     }
 }
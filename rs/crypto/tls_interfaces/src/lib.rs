@@ -16,6 +16,11 @@ use std::fmt::{Display, Formatter};
 use std::hash::{Hash, Hasher};
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net::TcpStream;
+use tokio_rustls::rustls::{Certificate, ClientConfig, ServerConfig};
+
+/// The rustls version used by the TLS configurations for QUIC, see
+/// `TlsHandshake::quic_server_config` and `TlsHandshake::quic_client_config`.
+pub use tokio_rustls::rustls;
 
 #[cfg(test)]
 mod tests;
@@ -388,6 +393,64 @@ pub trait TlsHandshake {
         server: NodeId,
         registry_version: RegistryVersion,
     ) -> Result<Box<dyn TlsStream>, TlsClientHandshakeError>;
+
+    /// Returns the TLS configuration of a QUIC endpoint that accepts
+    /// connections from other nodes.
+    ///
+    /// The configuration is the same as the one used in
+    /// `perform_tls_server_handshake`, except that a client is authenticated
+    /// against the certificates of _all_ nodes in the registry at the latest
+    /// registry version at the time of the handshake. This is because a QUIC
+    /// endpoint outlives the connections it accepts. Callers must thus use
+    /// `quic_authenticated_peer` to determine the authenticated peer of an
+    /// accepted connection and verify that it is an allowed client.
+    ///
+    /// The node's signing key never leaves the crypto component: signatures in
+    /// the handshake are computed by the crypto component on demand.
+    ///
+    /// # Errors
+    /// * TlsServerHandshakeError::RegistryError if the registry cannot be
+    ///   accessed.
+    /// * TlsServerHandshakeError::CertificateNotInRegistry if the node's own
+    ///   certificate is not found in the registry.
+    /// * TlsServerHandshakeError::MalformedSelfCertificate if the node's own
+    ///   server certificate is malformed.
+    fn quic_server_config(&self) -> Result<ServerConfig, TlsServerHandshakeError>;
+
+    /// Determines the node that authenticated with the `peer_certificates`
+    /// during a QUIC handshake with a server that uses the configuration
+    /// returned by `quic_server_config`.
+    ///
+    /// # Errors
+    /// * TlsServerHandshakeError::MalformedClientCertificate if the node ID
+    ///   cannot be determined from the client's certificate.
+    /// * TlsServerHandshakeError::HandshakeError if the peer did not present
+    ///   exactly one certificate.
+    fn quic_authenticated_peer(
+        &self,
+        peer_certificates: &[Certificate],
+    ) -> Result<AuthenticatedPeer, TlsServerHandshakeError>;
+
+    /// Returns the TLS configuration for a QUIC connection to the given
+    /// `server`.
+    ///
+    /// The configuration is the same as the one used in
+    /// `perform_tls_client_handshake`. In particular, the handshake fails
+    /// unless the peer authenticates as `server` with its certificate in the
+    /// registry at version `registry_version`.
+    ///
+    /// # Errors
+    /// * TlsClientHandshakeError::RegistryError if the registry cannot be
+    ///   accessed.
+    /// * TlsClientHandshakeError::CertificateNotInRegistry if the node's own
+    ///   certificate is not found in the registry.
+    /// * TlsClientHandshakeError::MalformedSelfCertificate if the node's own
+    ///   client certificate is malformed.
+    fn quic_client_config(
+        &self,
+        server: NodeId,
+        registry_version: RegistryVersion,
+    ) -> Result<ClientConfig, TlsClientHandshakeError>;
 }
 
 #[derive(Clone, Debug)]
@@ -836,6 +836,12 @@ impl CyclesAccountManager {
         Ok(())
     }
 
+    /// Returns the fee for a canister http request.
+    ///
+    /// The fee covers the variable size of the request (url, headers, body and
+    /// transform) and the response size limit, so it is the same for all HTTP
+    /// methods: the body of a `PUT` or `PATCH` request is charged like that of a
+    /// `POST` request. `DELETE` requests cannot carry a body.
     pub fn http_request_fee(
         &self,
         request_size: NumBytes,
@@ -1,5 +1,5 @@
 use ic_base_types::NumSeconds;
-use ic_config::subnet_config::SubnetConfigs;
+use ic_config::subnet_config::{CyclesAccountManagerConfig, SubnetConfigs};
 use ic_constants::SMALL_APP_SUBNET_MAX_SIZE;
 use ic_cycles_account_manager::IngressInductionCost;
 use ic_ic00_types::{CanisterIdRecord, Payload, IC_00};
@@ -17,7 +17,10 @@ use ic_test_utilities::{
 };
 use ic_test_utilities_logger::with_test_replica_logger;
 use ic_types::{
-    messages::{extract_effective_canister_id, SignedIngressContent},
+    messages::{
+        extract_effective_canister_id, SignedIngressContent,
+        MAX_INTER_CANISTER_PAYLOAD_IN_BYTES_U64,
+    },
     nominal_cycles::NominalCycles,
     CanisterId, ComputeAllocation, Cycles, MemoryAllocation, NumBytes, NumInstructions,
 };
@@ -376,6 +379,37 @@ fn ingress_induction_cost_valid_subnet_message() {
     }
 }
 
+#[test]
+fn http_request_fee_charges_request_and_response_bytes() {
+    let cycles_account_manager = CyclesAccountManagerBuilder::new().build();
+    let config = CyclesAccountManagerConfig::application_subnet();
+    let request_size = NumBytes::from(100);
+
+    assert_eq!(
+        cycles_account_manager.http_request_fee(
+            request_size,
+            Some(NumBytes::from(1_000)),
+            SMALL_APP_SUBNET_MAX_SIZE
+        ),
+        config.http_request_baseline_fee + config.http_request_per_byte_fee * 1_100u64
+    );
+    // Without a limit, the maximum response size is charged.
+    assert_eq!(
+        cycles_account_manager.http_request_fee(request_size, None, SMALL_APP_SUBNET_MAX_SIZE),
+        config.http_request_baseline_fee
+            + config.http_request_per_byte_fee * (100 + MAX_INTER_CANISTER_PAYLOAD_IN_BYTES_U64)
+    );
+    // The fee scales with the subnet size.
+    assert_eq!(
+        cycles_account_manager.http_request_fee(
+            request_size,
+            Some(NumBytes::from(1_000)),
+            2 * SMALL_APP_SUBNET_MAX_SIZE
+        ),
+        (config.http_request_baseline_fee + config.http_request_per_byte_fee * 1_100u64) * 2u64
+    );
+}
+
 #[test]
 fn charging_removes_canisters_with_insufficient_balance() {
     with_test_replica_logger(|log| {
@@ -1,13 +1,17 @@
 //! Standalone interface for testing application canisters.
 
 use crate::message::{msg_stream_from_file, Message};
+use crate::script::{is_script_file, script_from_file};
 use hex::encode;
 use ic_config::{subnet_config::SubnetConfigs, Config};
 use ic_cycles_account_manager::CyclesAccountManager;
 use ic_error_types::{ErrorCode, UserError};
 use ic_execution_environment::ExecutionServices;
 use ic_http_endpoints_metrics::MetricsHttpEndpoint;
-use ic_interfaces::{execution_environment::IngressHistoryReader, messaging::MessageRouting};
+use ic_interfaces::{
+    execution_environment::{IngressHistoryReader, QueryHandler},
+    messaging::MessageRouting,
+};
 use ic_interfaces_state_manager::StateReader;
 use ic_messaging::MessageRoutingImpl;
 use ic_metrics::MetricsRegistry;
@@ -25,6 +29,7 @@ use ic_registry_proto_data_provider::ProtoRegistryDataProvider;
 use ic_registry_provisional_whitelist::ProvisionalWhitelist;
 use ic_registry_routing_table::{routing_table_insert_subnet, RoutingTable};
 use ic_registry_subnet_type::SubnetType;
+use ic_replicated_state::ReplicatedState;
 use ic_state_manager::StateManagerImpl;
 use ic_test_utilities::consensus::fake::FakeVerifier;
 use ic_test_utilities_registry::{
@@ -35,7 +40,7 @@ use ic_types::{
     ingress::{IngressState, IngressStatus, WasmResult},
     messages::{MessageId, SignedIngress},
     replica_config::ReplicaConfig,
-    time, CanisterId, NodeId, PrincipalId, Randomness, RegistryVersion, SubnetId,
+    time, CanisterId, NodeId, PrincipalId, Randomness, RegistryVersion, SubnetId, Time,
 };
 use slog::{Drain, Logger};
 use std::collections::BTreeMap;
@@ -45,6 +50,7 @@ use std::sync::{Arc, Mutex};
 use std::{thread::sleep, time::Duration};
 
 mod message;
+mod script;
 
 // drun will panic if it takes more than this many batches
 // until a response for a message is received
@@ -59,20 +65,84 @@ pub struct DrunOptions {
     pub log_file: Option<PathBuf>,
 }
 
-/// Deliver a single message to the Message Routing layer
-fn deliver_message(
-    msg: SignedIngress,
-    message_routing: &dyn MessageRouting,
-    ingress_hist_reader: &dyn IngressHistoryReader,
+/// The components of the single-node subnet that messages are executed on.
+pub(crate) struct Subnet {
+    message_routing: MessageRoutingImpl,
+    ingress_hist_reader: Box<dyn IngressHistoryReader>,
+    query_handler: Arc<dyn QueryHandler<State = ReplicatedState>>,
+    state_manager: Arc<StateManagerImpl>,
     extra_batches: u64,
-) {
-    let message_id = msg.id();
+    // How far the time of the batches is ahead of the wall-clock time.
+    time_offset: Duration,
+}
+
+impl Subnet {
+    /// Executes the given message, prints its result and returns it.
+    ///
+    /// Returns `None` for messages that do not produce a reply.
+    pub(crate) fn execute(&self, msg: Message) -> Option<Result<WasmResult, UserError>> {
+        match msg {
+            Message::Install(msg) | Message::Ingress(msg) | Message::Create(msg) => {
+                Some(self.deliver_message(msg))
+            }
 
-    let _ = execute_ingress_message(message_routing, msg, &message_id, ingress_hist_reader);
-    // print result after waiting, to not interleave the result
-    // with debug.print messages from subsequent calls. revise after DFN-1269.
-    wait_extra_batches(message_routing, extra_batches);
-    print_ingress_result(&message_id, ingress_hist_reader);
+            Message::Query(q) => {
+                // NOTE: Data certificates aren't supported in drun yet.
+                // To support them, we'd need to do something similar to
+                // http_handler::get_latest_certified_state_and_data_certificate
+                let result = self.query_handler.query(
+                    q,
+                    self.state_manager.get_latest_state().take(),
+                    Vec::new(),
+                );
+                print_query_result(&result);
+                Some(result)
+            }
+
+            Message::Logs(canister_id) => {
+                print_canister_logs(
+                    self.state_manager.get_latest_state().take().as_ref(),
+                    canister_id,
+                );
+                None
+            }
+        }
+    }
+
+    /// Returns an expiry time for ingress messages that is valid with
+    /// respect to the time of the next batch.
+    pub(crate) fn ingress_expiry_time(&self) -> Time {
+        time::current_time_and_expiry_time().1 + self.time_offset
+    }
+
+    /// Moves the time of all subsequent batches forward by `duration`.
+    pub(crate) fn advance_time(&mut self, duration: Duration) {
+        self.time_offset += duration;
+    }
+
+    /// Executes the given number of empty batches, e.g. to run heartbeats
+    /// and timers.
+    pub(crate) fn tick(&self, batches: u64) {
+        wait_extra_batches(&self.message_routing, batches, self.time_offset);
+    }
+
+    /// Deliver a single message to the Message Routing layer
+    fn deliver_message(&self, msg: SignedIngress) -> Result<WasmResult, UserError> {
+        let message_id = msg.id();
+
+        let result = execute_ingress_message(
+            &self.message_routing,
+            msg,
+            &message_id,
+            self.ingress_hist_reader.as_ref(),
+            self.time_offset,
+        );
+        // print result after waiting, to not interleave the result
+        // with debug.print messages from subsequent calls. revise after DFN-1269.
+        wait_extra_batches(&self.message_routing, self.extra_batches, self.time_offset);
+        print_ingress_result(&message_id, self.ingress_hist_reader.as_ref());
+        result
+    }
 }
 
 fn setup_logger(log_file: PathBuf) -> Logger {
@@ -165,7 +235,13 @@ pub fn run_drun(uo: DrunOptions) -> Result<(), String> {
         subnet_id,
     };
 
-    let mut msg_stream = msg_stream_from_file(&msg_filename)?;
+    // Scripts are parsed upfront, so that malformed scripts are reported
+    // before any message is executed.
+    let script = if is_script_file(&msg_filename) {
+        Some(script_from_file(&msg_filename)?)
+    } else {
+        None
+    };
     let log = match log_file {
         Some(log_file) => setup_logger(log_file),
         None => slog::Logger::root(slog::Discard, slog::o!()),
@@ -230,49 +306,26 @@ pub fn run_drun(uo: DrunOptions) -> Result<(), String> {
         Arc::clone(&registry) as _,
     );
 
-    msg_stream.try_for_each(|parse_result| {
-        parse_result.map(|msg| match msg {
-            Message::Install(msg) => {
-                deliver_message(
-                    msg,
-                    &message_routing,
-                    ingress_hist_reader.as_ref(),
-                    extra_batches,
-                );
-            }
-
-            Message::Query(q) => {
-                // NOTE: Data certificates aren't supported in drun yet.
-                // To support them, we'd need to do something similar to
-                // http_handler::get_latest_certified_state_and_data_certificate
-                print_query_result(query_handler.query(
-                    q,
-                    state_manager.get_latest_state().take(),
-                    Vec::new(),
-                ));
-            }
+    let mut subnet = Subnet {
+        message_routing,
+        ingress_hist_reader,
+        query_handler,
+        state_manager,
+        extra_batches,
+        time_offset: Duration::ZERO,
+    };
 
-            Message::Ingress(msg) => {
-                deliver_message(
-                    msg,
-                    &message_routing,
-                    ingress_hist_reader.as_ref(),
-                    extra_batches,
-                );
-            }
-            Message::Create(msg) => {
-                deliver_message(
-                    msg,
-                    &message_routing,
-                    ingress_hist_reader.as_ref(),
-                    extra_batches,
-                );
-            }
-        })
-    })
+    match script {
+        Some(script) => script.run(&mut subnet),
+        None => msg_stream_from_file(&msg_filename)?.try_for_each(|parse_result| {
+            parse_result.map(|msg| {
+                subnet.execute(msg);
+            })
+        }),
+    }
 }
 
-fn print_query_result(res: Result<WasmResult, UserError>) {
+fn print_query_result(res: &Result<WasmResult, UserError>) {
     match res {
         Ok(payload) => {
             print!("Ok: ");
@@ -291,7 +344,7 @@ fn print_ingress_result(message_id: &MessageId, ingress_hist_reader: &dyn Ingres
             ..
         } => {
             print!("Completed: ");
-            print_wasm_result(result)
+            print_wasm_result(&result)
         }
         IngressStatus::Known {
             state: IngressState::Failed(error),
@@ -301,14 +354,34 @@ fn print_ingress_result(message_id: &MessageId, ingress_hist_reader: &dyn Ingres
     };
 }
 
-fn print_wasm_result(wasm_result: WasmResult) {
+fn print_canister_logs(state: &ReplicatedState, canister_id: CanisterId) {
+    match state.canister_state(&canister_id) {
+        Some(canister) => {
+            for record in canister.system_state.canister_log.records() {
+                println!(
+                    "[{}. {}]: {}",
+                    record.idx,
+                    record.timestamp_nanos,
+                    String::from_utf8_lossy(&record.content)
+                );
+            }
+        }
+        None => println!("Err: Canister {} not found", canister_id),
+    }
+}
+
+fn print_wasm_result(wasm_result: &WasmResult) {
     match wasm_result {
         WasmResult::Reply(v) => println!("Reply: 0x{}", encode(v)),
         WasmResult::Reject(e) => println!("Reject: {}", e),
     }
 }
 
-fn build_batch(message_routing: &dyn MessageRouting, msgs: Vec<SignedIngress>) -> Batch {
+fn build_batch(
+    message_routing: &dyn MessageRouting,
+    msgs: Vec<SignedIngress>,
+    time_offset: Duration,
+) -> Batch {
     Batch {
         batch_number: message_routing.expected_batch_height(),
         requires_full_state_hash: !msgs.is_empty(),
@@ -319,7 +392,7 @@ fn build_batch(message_routing: &dyn MessageRouting, msgs: Vec<SignedIngress>) -
         randomness: Randomness::from([0; 32]),
         ecdsa_subnet_public_keys: BTreeMap::new(),
         registry_version: RegistryVersion::from(1),
-        time: time::current_time(),
+        time: time::current_time() + time_offset,
         consensus_responses: vec![],
     }
 }
@@ -332,8 +405,9 @@ fn execute_ingress_message(
     msg: SignedIngress,
     msg_id: &MessageId,
     ingress_history: &dyn IngressHistoryReader,
+    time_offset: Duration,
 ) -> Result<WasmResult, UserError> {
-    let mut batch = build_batch(message_routing, vec![msg]);
+    let mut batch = build_batch(message_routing, vec![msg], time_offset);
     for _ in 0..MAX_BATCHES_UNTIL_RESPONSE {
         // In the first batch we try to send the ingress message itself. If it fails, we
         // repeat with the same batch.
@@ -344,7 +418,7 @@ fn execute_ingress_message(
         // potential inter-canister messages that the ingress message may have
         // triggered.
         if message_routing.deliver_batch(batch.clone()).is_ok() {
-            batch = build_batch(message_routing, vec![])
+            batch = build_batch(message_routing, vec![], time_offset)
         }
         sleep(WAIT_PER_BATCH);
 
@@ -390,10 +464,14 @@ fn execute_ingress_message(
 ///
 /// This is a temporary measure until DFN-1269 is resolved. In that ticket, we
 /// will actually try to wait until all messages have been executed.
-fn wait_extra_batches(message_routing: &dyn MessageRouting, extra_batches: u64) {
+fn wait_extra_batches(
+    message_routing: &dyn MessageRouting,
+    extra_batches: u64,
+    time_offset: Duration,
+) {
     for _ in 0..extra_batches {
         loop {
-            let batch = build_batch(message_routing, vec![]);
+            let batch = build_batch(message_routing, vec![], time_offset);
             let ok = message_routing.deliver_batch(batch).is_ok();
             sleep(WAIT_PER_BATCH);
             if ok {
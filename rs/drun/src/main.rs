@@ -105,7 +105,10 @@ fn get_arg_matches() -> ArgMatches {
             Arg::new(ARG_MESSAGES)
                 .required(true)
                 .value_name("Query/Ingress Messages")
-                .help("Text file containing one message per line."),
+                .help(
+                    "Text file containing one message per line, or a JSON/YAML script \
+                    (.json, .yaml, .yml).",
+                ),
         )
         .arg(
             Arg::new(ARG_LOG_FILE)
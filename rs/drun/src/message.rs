@@ -23,6 +23,7 @@ pub(crate) enum Message {
     Query(UserQuery),
     Install(SignedIngress),
     Create(SignedIngress),
+    Logs(CanisterId),
 }
 
 #[derive(Debug)]
@@ -176,6 +177,7 @@ fn parse_message(s: &str, nonce: u64) -> Result<Message, String> {
             nonce: Some(nonce.to_le_bytes().to_vec()),
         })),
         ["create"] => parse_create(nonce),
+        ["logs", canister_id] => Ok(Message::Logs(parse_canister_id(canister_id)?)),
         ["install", canister_id, wasm_file, payload] => {
             parse_install(nonce, canister_id, payload, wasm_file, "install")
         }
@@ -192,7 +194,7 @@ fn parse_message(s: &str, nonce: u64) -> Result<Message, String> {
     }
 }
 
-fn parse_canister_id(canister_id: &str) -> Result<CanisterId, String> {
+pub(crate) fn parse_canister_id(canister_id: &str) -> Result<CanisterId, String> {
     use std::str::FromStr;
     match PrincipalId::from_str(canister_id) {
         Ok(id) => match CanisterId::new(id) {
@@ -263,7 +265,7 @@ fn parse_install(
     Ok(Message::Install(signed_ingress))
 }
 
-fn validate_method_name(method_name: &str) -> Result<String, String> {
+pub(crate) fn validate_method_name(method_name: &str) -> Result<String, String> {
     fn is_ident_start(c: char) -> bool {
         c.is_ascii() && (c.is_alphabetic() || c == '_')
     }
@@ -377,6 +379,15 @@ mod tests {
     const APP_CANISTER_URL: &str = "ryjl3-tyaaa-aaaaa-aaaba-cai";
     const APP_CANISTER_ID: u64 = 2;
 
+    #[test]
+    fn test_parse_logs_message_succeeds() {
+        let s = &format!("logs {}", APP_CANISTER_URL);
+        assert_eq!(
+            parse_message(s, 0).unwrap(),
+            Message::Logs(canister_test_id(APP_CANISTER_ID))
+        );
+    }
+
     #[test]
     fn test_parse_message_quoted_payload_succeeds() {
         let s = &format!(
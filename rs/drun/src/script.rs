@@ -0,0 +1,509 @@
+//! Structured message scripts with Candid arguments and assertions.
+//!
+//! In addition to the line-based format parsed in [`crate::message`], `drun`
+//! accepts scripts in JSON (`.json`) or YAML (`.yaml`, `.yml`) format. A
+//! script is a list of steps that refer to canisters by alias, encode
+//! arguments from Candid text and may assert on the replies of calls:
+//!
+//! ```yaml
+//! steps:
+//!   - create: counter
+//!   - install:
+//!       canister: counter
+//!       wasm: counter.wasm
+//!   - ingress:
+//!       canister: counter
+//!       method: inc
+//!   - query:
+//!       canister: counter
+//!       method: read
+//!       expect_reply: "(1 : nat)"
+//!   - advance_time: 60
+//!   - tick: 1
+//! ```
+
+use crate::{
+    message::{parse_canister_id, validate_method_name, Message},
+    Subnet,
+};
+use candid::{IDLArgs, TypeEnv};
+use ic_error_types::UserError;
+use ic_ic00_types::{self as ic00, CanisterIdRecord, CanisterInstallMode, Payload};
+use ic_test_utilities::types::messages::SignedIngressBuilder;
+use ic_types::{ingress::WasmResult, messages::UserQuery, CanisterId, PrincipalId, UserId};
+use serde::Deserialize;
+use std::{collections::BTreeMap, fs::File, path::Path, path::PathBuf, time::Duration};
+
+/// A script as read from a JSON or YAML file.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Script {
+    steps: Vec<Step>,
+}
+
+/// A single step of a [`Script`].
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum Step {
+    /// Creates a canister and binds its id to the given alias.
+    Create(String),
+    /// Installs, reinstalls or upgrades the code of a canister.
+    Install(InstallStep),
+    /// Sends an ingress message and waits for its completion.
+    Ingress(CallStep),
+    /// Executes a query.
+    Query(CallStep),
+    /// Prints the logs of a canister.
+    Logs(String),
+    /// Moves the time of all subsequent batches forward by the given number
+    /// of seconds.
+    AdvanceTime(u64),
+    /// Executes the given number of empty batches.
+    Tick(u64),
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct InstallStep {
+    /// Alias or textual id of the canister.
+    canister: String,
+    /// Path to the Wasm module, relative to the script.
+    wasm: PathBuf,
+    #[serde(default)]
+    mode: CanisterInstallMode,
+    /// Init or post-upgrade argument in Candid text format.
+    arg: Option<String>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct CallStep {
+    /// Alias or textual id of the canister.
+    canister: String,
+    method: String,
+    /// Argument in Candid text format. Defaults to `()`.
+    arg: Option<String>,
+    /// The expected reply in Candid text format.
+    expect_reply: Option<String>,
+    /// A substring of the expected reject message.
+    expect_reject: Option<String>,
+}
+
+/// Returns `true` if the given file is a JSON or YAML script rather than a
+/// line-based message file.
+pub(crate) fn is_script_file(filename: &str) -> bool {
+    matches!(
+        Path::new(filename).extension().and_then(|ext| ext.to_str()),
+        Some("json") | Some("yaml") | Some("yml")
+    )
+}
+
+/// Reads a script from a JSON or YAML file.
+///
+/// Relative Wasm paths are resolved against the directory of the script.
+pub(crate) fn script_from_file(filename: &str) -> Result<Script, String> {
+    let f = File::open(filename).map_err(|e| e.to_string())?;
+    let mut script: Script = match Path::new(filename).extension().and_then(|ext| ext.to_str()) {
+        Some("json") => serde_json::from_reader(f).map_err(|e| e.to_string())?,
+        _ => serde_yaml::from_reader(f).map_err(|e| e.to_string())?,
+    };
+    script.validate()?;
+
+    let base_dir = Path::new(filename)
+        .parent()
+        .unwrap_or_else(|| Path::new(""));
+    for step in script.steps.iter_mut() {
+        if let Step::Install(install) = step {
+            install.wasm = base_dir.join(&install.wasm);
+        }
+    }
+    Ok(script)
+}
+
+impl Script {
+    /// Checks everything that can be checked without executing the script.
+    fn validate(&self) -> Result<(), String> {
+        self.steps.iter().enumerate().try_for_each(|(i, step)| {
+            match step {
+                Step::Install(InstallStep { arg, .. }) => parse_args(arg).map(|_| ()),
+                Step::Ingress(call) | Step::Query(call) => validate_call(call),
+                Step::Create(_) | Step::Logs(_) | Step::AdvanceTime(_) | Step::Tick(_) => Ok(()),
+            }
+            .map_err(|e| format!("Step {}: {}", i + 1, e))
+        })
+    }
+
+    /// Executes all steps on the given subnet.
+    ///
+    /// Failing to set up a canister aborts the script. Failed assertions are
+    /// reported as they occur and make the script fail once all steps have
+    /// been executed.
+    pub(crate) fn run(self, subnet: &mut Subnet) -> Result<(), String> {
+        let mut aliases = BTreeMap::new();
+        let mut assertions = 0;
+        let mut failures = 0;
+
+        for (i, step) in self.steps.into_iter().enumerate() {
+            let nonce = i as u64;
+            let step_error = |e: String| format!("Step {}: {}", i + 1, e);
+            match step {
+                Step::Create(alias) => {
+                    let msg = SignedIngressBuilder::new()
+                        .method_name(ic00::Method::ProvisionalCreateCanisterWithCycles)
+                        .canister_id(ic00::IC_00)
+                        .method_payload(
+                            ic00::ProvisionalCreateCanisterWithCyclesArgs::new(None, None).encode(),
+                        )
+                        .nonce(nonce)
+                        .expiry_time(subnet.ingress_expiry_time())
+                        .build();
+                    let reply = expect_setup_reply(subnet.execute(Message::Create(msg)))
+                        .map_err(step_error)?;
+                    let canister_id = CanisterIdRecord::decode(&reply)
+                        .map_err(|e| step_error(e.to_string()))?
+                        .get_canister_id();
+                    aliases.insert(alias, canister_id);
+                }
+                Step::Install(install) => {
+                    let canister_id =
+                        resolve_canister(&aliases, &install.canister).map_err(step_error)?;
+                    let wasm_module = std::fs::read(&install.wasm).map_err(|e| {
+                        step_error(format!(
+                            "Could not read wasm file: {} - Error: {}",
+                            install.wasm.display(),
+                            e
+                        ))
+                    })?;
+                    let msg = SignedIngressBuilder::new()
+                        .canister_id(ic00::IC_00)
+                        .method_name(ic00::Method::InstallCode)
+                        .method_payload(
+                            ic00::InstallCodeArgs::new(
+                                install.mode,
+                                canister_id,
+                                wasm_module,
+                                parse_args(&install.arg).map_err(step_error)?,
+                                None,
+                                Some(8 * 1024 * 1024 * 1024), // drun users dont care about memory limits
+                                None,
+                            )
+                            .encode(),
+                        )
+                        .nonce(nonce)
+                        .expiry_time(subnet.ingress_expiry_time())
+                        .build();
+                    expect_setup_reply(subnet.execute(Message::Install(msg)))
+                        .map_err(step_error)?;
+                }
+                Step::Ingress(call) => {
+                    let msg = SignedIngressBuilder::new()
+                        .canister_id(
+                            resolve_canister(&aliases, &call.canister).map_err(step_error)?,
+                        )
+                        .method_name(&call.method)
+                        .method_payload(parse_args(&call.arg).map_err(step_error)?)
+                        .nonce(nonce)
+                        .expiry_time(subnet.ingress_expiry_time())
+                        .build();
+                    let result = subnet.execute(Message::Ingress(msg));
+                    if let Some(outcome) = check_expectations(&call, result) {
+                        assertions += 1;
+                        if let Err(e) = outcome {
+                            failures += 1;
+                            println!("FAILED: {}", step_error(e));
+                        }
+                    }
+                }
+                Step::Query(call) => {
+                    let query = UserQuery {
+                        source: UserId::from(PrincipalId::new_anonymous()),
+                        receiver: resolve_canister(&aliases, &call.canister).map_err(step_error)?,
+                        method_name: call.method.clone(),
+                        method_payload: parse_args(&call.arg).map_err(step_error)?,
+                        ingress_expiry: subnet.ingress_expiry_time().as_nanos_since_unix_epoch(),
+                        nonce: Some(nonce.to_le_bytes().to_vec()),
+                    };
+                    let result = subnet.execute(Message::Query(query));
+                    if let Some(outcome) = check_expectations(&call, result) {
+                        assertions += 1;
+                        if let Err(e) = outcome {
+                            failures += 1;
+                            println!("FAILED: {}", step_error(e));
+                        }
+                    }
+                }
+                Step::Logs(canister) => {
+                    let canister_id = resolve_canister(&aliases, &canister).map_err(step_error)?;
+                    subnet.execute(Message::Logs(canister_id));
+                }
+                Step::AdvanceTime(seconds) => subnet.advance_time(Duration::from_secs(seconds)),
+                Step::Tick(batches) => subnet.tick(batches),
+            }
+        }
+
+        if failures > 0 {
+            Err(format!("{} of {} assertions failed.", failures, assertions))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+fn validate_call(call: &CallStep) -> Result<(), String> {
+    validate_method_name(&call.method)?;
+    parse_args(&call.arg)?;
+    if let Some(expected) = &call.expect_reply {
+        parse_candid(expected)?;
+    }
+    if call.expect_reply.is_some() && call.expect_reject.is_some() {
+        return Err("Only one of expect_reply and expect_reject may be set.".to_string());
+    }
+    Ok(())
+}
+
+/// Resolves an alias bound by a `create` step or a textual canister id.
+fn resolve_canister(
+    aliases: &BTreeMap<String, CanisterId>,
+    canister: &str,
+) -> Result<CanisterId, String> {
+    match aliases.get(canister) {
+        Some(canister_id) => Ok(*canister_id),
+        None => parse_canister_id(canister),
+    }
+}
+
+fn parse_candid(text: &str) -> Result<IDLArgs, String> {
+    text.parse::<IDLArgs>()
+        .map_err(|e| format!("Failed to parse Candid arguments {}: {}", text, e))
+}
+
+/// Encodes the given Candid text arguments, `()` if none are given.
+fn parse_args(arg: &Option<String>) -> Result<Vec<u8>, String> {
+    parse_candid(arg.as_deref().unwrap_or("()"))?
+        .to_bytes()
+        .map_err(|e| format!("Failed to encode Candid arguments: {}", e))
+}
+
+/// Returns the reply of a message that sets up a canister, or an error if it
+/// did not succeed.
+fn expect_setup_reply(result: Option<Result<WasmResult, UserError>>) -> Result<Vec<u8>, String> {
+    match result {
+        Some(Ok(WasmResult::Reply(reply))) => Ok(reply),
+        Some(Ok(WasmResult::Reject(reject))) => Err(format!("Rejected: {}", reject)),
+        Some(Err(e)) => Err(e.to_string()),
+        None => unreachable!("Ingress messages always produce a result."),
+    }
+}
+
+/// Checks the result of a call against the expectations of the step.
+///
+/// Returns `None` if the step does not have any expectations.
+fn check_expectations(
+    call: &CallStep,
+    result: Option<Result<WasmResult, UserError>>,
+) -> Option<Result<(), String>> {
+    let result = result.expect("Calls always produce a result.");
+    if let Some(expected) = &call.expect_reply {
+        return Some(match result {
+            Ok(WasmResult::Reply(reply)) => check_reply(expected, &reply),
+            Ok(WasmResult::Reject(reject)) => Err(format!(
+                "Expected reply {}, got reject: {}",
+                expected, reject
+            )),
+            Err(e) => Err(format!("Expected reply {}, got error: {}", expected, e)),
+        });
+    }
+    if let Some(expected) = &call.expect_reject {
+        let message = match result {
+            Ok(WasmResult::Reply(reply)) => {
+                return Some(Err(format!(
+                    "Expected reject containing \"{}\", got reply: 0x{}",
+                    expected,
+                    hex::encode(reply)
+                )))
+            }
+            Ok(WasmResult::Reject(reject)) => reject,
+            Err(e) => e.to_string(),
+        };
+        return Some(if message.contains(expected.as_str()) {
+            Ok(())
+        } else {
+            Err(format!(
+                "Expected reject containing \"{}\", got: {}",
+                expected, message
+            ))
+        });
+    }
+    None
+}
+
+/// Compares a Candid-encoded reply with the expected Candid text.
+///
+/// The expected values are annotated with the types of the reply, so that
+/// e.g. `(1)` matches a reply of type `nat`.
+fn check_reply(expected: &str, reply: &[u8]) -> Result<(), String> {
+    let actual = IDLArgs::from_bytes(reply)
+        .map_err(|e| format!("Failed to decode reply 0x{}: {}", hex::encode(reply), e))?;
+    let expected = parse_candid(expected)?
+        .annotate_types(true, &TypeEnv::new(), &actual.get_types())
+        .map_err(|e| format!("Expected {}, got {}: {}", expected, actual, e))?;
+    if expected.args == actual.args {
+        Ok(())
+    } else {
+        Err(format!("Expected reply {}, got {}", expected, actual))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candid::Encode;
+    use std::io::Write;
+
+    const APP_CANISTER_URL: &str = "ryjl3-tyaaa-aaaaa-aaaba-cai";
+
+    fn call(expect_reply: Option<&str>, expect_reject: Option<&str>) -> CallStep {
+        CallStep {
+            canister: "counter".to_string(),
+            method: "read".to_string(),
+            arg: None,
+            expect_reply: expect_reply.map(String::from),
+            expect_reject: expect_reject.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_is_script_file() {
+        assert!(is_script_file("test.json"));
+        assert!(is_script_file("dir/test.yaml"));
+        assert!(is_script_file("test.yml"));
+        assert!(!is_script_file("test.txt"));
+        assert!(!is_script_file("test"));
+    }
+
+    #[test]
+    fn test_parse_yaml_and_json_scripts() {
+        let yaml = r#"
+steps:
+  - create: counter
+  - install:
+      canister: counter
+      wasm: counter.wasm
+      arg: "(42 : nat)"
+  - ingress:
+      canister: counter
+      method: inc
+  - query:
+      canister: counter
+      method: read
+      expect_reply: "(43 : nat)"
+  - advance_time: 60
+  - tick: 2
+"#;
+        let json = r#"{"steps": [
+            {"create": "counter"},
+            {"install": {"canister": "counter", "wasm": "counter.wasm", "arg": "(42 : nat)"}},
+            {"ingress": {"canister": "counter", "method": "inc"}},
+            {"query": {"canister": "counter", "method": "read", "expect_reply": "(43 : nat)"}},
+            {"advance_time": 60},
+            {"tick": 2}
+        ]}"#;
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut scripts = vec![];
+        for (name, content) in [("script.yaml", yaml), ("script.json", json)] {
+            let path = dir.path().join(name);
+            File::create(&path)
+                .unwrap()
+                .write_all(content.as_bytes())
+                .unwrap();
+            scripts.push(script_from_file(path.to_str().unwrap()).unwrap());
+        }
+
+        assert_eq!(scripts[0], scripts[1]);
+        assert_eq!(scripts[0].steps.len(), 6);
+        assert_eq!(
+            scripts[0].steps[1],
+            Step::Install(InstallStep {
+                canister: "counter".to_string(),
+                wasm: dir.path().join("counter.wasm"),
+                mode: CanisterInstallMode::Install,
+                arg: Some("(42 : nat)".to_string()),
+            })
+        );
+        assert_eq!(scripts[0].steps[4], Step::AdvanceTime(60));
+    }
+
+    #[test]
+    fn test_validate_rejects_malformed_steps() {
+        let script = Script {
+            steps: vec![Step::Query(CallStep {
+                arg: Some("(1".to_string()),
+                ..call(None, None)
+            })],
+        };
+        assert!(script.validate().unwrap_err().starts_with("Step 1:"));
+
+        let script = Script {
+            steps: vec![Step::Query(CallStep {
+                method: "0read".to_string(),
+                ..call(None, None)
+            })],
+        };
+        assert!(script.validate().is_err());
+
+        let script = Script {
+            steps: vec![Step::Query(call(Some("()"), Some("")))],
+        };
+        assert!(script.validate().is_err());
+    }
+
+    #[test]
+    fn test_resolve_canister() {
+        let mut aliases = BTreeMap::new();
+        aliases.insert("counter".to_string(), CanisterId::from_u64(7));
+        assert_eq!(
+            resolve_canister(&aliases, "counter"),
+            Ok(CanisterId::from_u64(7))
+        );
+        assert_eq!(
+            resolve_canister(&aliases, APP_CANISTER_URL),
+            Ok(CanisterId::from_u64(2))
+        );
+        assert!(resolve_canister(&aliases, "unknown").is_err());
+    }
+
+    #[test]
+    fn test_check_reply_compares_decoded_values() {
+        let reply = Encode!(&candid::Nat::from(43_u64), &"hello").unwrap();
+        assert_eq!(check_reply("(43, \"hello\")", &reply), Ok(()));
+        assert_eq!(check_reply("(43 : nat, \"hello\" : text)", &reply), Ok(()));
+        assert!(check_reply("(42, \"hello\")", &reply).is_err());
+        assert!(check_reply("(43)", &reply).is_err());
+    }
+
+    #[test]
+    fn test_check_expectations() {
+        let reply = || Some(Ok(WasmResult::Reply(Encode!(&true).unwrap())));
+        let reject = || Some(Ok(WasmResult::Reject("counter is locked".to_string())));
+
+        assert_eq!(check_expectations(&call(None, None), reply()), None);
+        assert_eq!(
+            check_expectations(&call(Some("(true)"), None), reply()),
+            Some(Ok(()))
+        );
+        assert!(check_expectations(&call(Some("(true)"), None), reject())
+            .unwrap()
+            .is_err());
+        assert_eq!(
+            check_expectations(&call(None, Some("locked")), reject()),
+            Some(Ok(()))
+        );
+        assert!(check_expectations(&call(None, Some("unlocked")), reject())
+            .unwrap()
+            .is_err());
+        assert!(check_expectations(&call(None, Some("")), reply())
+            .unwrap()
+            .is_err());
+    }
+}
@@ -1,22 +1,492 @@
+//! A size-bounded cache of compiled wasm modules.
+//!
+//! The most recently used modules are kept in memory. Modules evicted from
+//! memory are spilled to a directory on disk (if configured), which has its
+//! own byte budget. Files on disk are keyed by the hash of the wasm binary and
+//! by a hash of the replica version, the wasmtime version and the embedder
+//! configuration, so that modules compiled by a different replica or with a
+//! different configuration are never reused. Every file is checked against a
+//! header and a checksum when it is loaded, and its module must deserialize.
+//! Files that don't pass these checks are discarded, so that the module is
+//! compiled again and the file overwritten.
 use std::{
-    collections::HashMap,
-    sync::{Arc, RwLock},
+    collections::{BTreeMap, HashMap},
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
 };
 
-use crate::SerializedModule;
+use crate::{SerializedModule, WasmtimeEmbedder};
+use ic_config::{
+    embedders::{Config as EmbeddersConfig, FeatureFlags},
+    flag_status::FlagStatus,
+};
+use ic_crypto_sha::Sha256;
 use ic_interfaces::execution_environment::HypervisorResult;
+use ic_logger::{warn, ReplicaLogger};
+use ic_metrics::MetricsRegistry;
+use ic_types::{replica_version::REPLICA_BINARY_HASH, ReplicaVersion};
 use ic_wasm_types::{CanisterModule, WasmHash};
+use prometheus::{IntCounterVec, IntGauge};
+
+/// Identifies files written by the compilation cache.
+const FILE_MAGIC: &[u8; 8] = b"ICWASMCC";
+
+/// Must be incremented whenever the on-disk format or the way the
+/// configuration hash is computed changes.
+const FILE_FORMAT_VERSION: u32 = 3;
+
+/// Magic + file format version + wasm hash + config hash + payload checksum.
+const FILE_HEADER_SIZE: usize = FILE_MAGIC.len() + 4 + 32 + 32 + 32;
+
+/// The number of bytes accounted for a cached compilation error.
+const ERROR_ENTRY_SIZE: u64 = 1024;
+
+/// Lookup results reported by the `compilation_cache_lookups` metric.
+const LOOKUP_MEMORY_HIT: &str = "memory_hit";
+const LOOKUP_DISK_HIT: &str = "disk_hit";
+const LOOKUP_MISS: &str = "miss";
+
+const TIER_MEMORY: &str = "memory";
+const TIER_DISK: &str = "disk";
+
+struct CompilationCacheMetrics {
+    lookups: IntCounterVec,
+    evictions: IntCounterVec,
+    invalid_files: IntCounterVec,
+    memory_bytes: IntGauge,
+    disk_bytes: IntGauge,
+}
+
+impl CompilationCacheMetrics {
+    fn new(metrics_registry: &MetricsRegistry) -> Self {
+        Self {
+            lookups: metrics_registry.int_counter_vec(
+                "compilation_cache_lookups_total",
+                "Number of compilation cache lookups by result.",
+                &["result"],
+            ),
+            evictions: metrics_registry.int_counter_vec(
+                "compilation_cache_evictions_total",
+                "Number of modules evicted from the compilation cache by tier.",
+                &["tier"],
+            ),
+            invalid_files: metrics_registry.int_counter_vec(
+                "compilation_cache_invalid_files_total",
+                "Number of compilation cache files discarded because they failed validation.",
+                &["reason"],
+            ),
+            memory_bytes: metrics_registry.int_gauge(
+                "compilation_cache_memory_bytes",
+                "Total size of the modules kept in memory by the compilation cache.",
+            ),
+            disk_bytes: metrics_registry.int_gauge(
+                "compilation_cache_disk_bytes",
+                "Total size of the module files kept on disk by the compilation cache.",
+            ),
+        }
+    }
+}
+
+/// A least-recently-used index of entries with a total byte budget.
+struct LruIndex<V> {
+    entries: HashMap<WasmHash, (V, u64, u64)>,
+    /// Maps the access tick of every entry to its key.
+    order: BTreeMap<u64, WasmHash>,
+    next_tick: u64,
+    size: u64,
+    capacity: u64,
+}
+
+impl<V> LruIndex<V> {
+    fn new(capacity: u64) -> Self {
+        Self {
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            next_tick: 0,
+            size: 0,
+            capacity,
+        }
+    }
+
+    fn get(&mut self, key: &WasmHash) -> Option<&V> {
+        let tick = self.next_tick;
+        let (_, _, last_used) = self.entries.get_mut(key)?;
+        self.order.remove(last_used);
+        self.order.insert(tick, key.clone());
+        *last_used = tick;
+        self.next_tick += 1;
+        self.entries.get(key).map(|(value, _, _)| value)
+    }
+
+    /// Inserts the entry and returns the entries that had to be evicted to
+    /// stay within the capacity. An entry larger than the capacity is
+    /// returned immediately.
+    fn insert(&mut self, key: WasmHash, value: V, size: u64) -> Vec<(WasmHash, V, u64)> {
+        self.remove(&key);
+        if size > self.capacity {
+            return vec![(key, value, size)];
+        }
+        let mut evicted = vec![];
+        while self.size + size > self.capacity {
+            let oldest = match self.order.values().next() {
+                Some(oldest) => oldest.clone(),
+                None => break,
+            };
+            if let Some((value, size)) = self.remove(&oldest) {
+                evicted.push((oldest, value, size));
+            }
+        }
+        self.order.insert(self.next_tick, key.clone());
+        self.entries.insert(key, (value, size, self.next_tick));
+        self.next_tick += 1;
+        self.size += size;
+        evicted
+    }
+
+    fn remove(&mut self, key: &WasmHash) -> Option<(V, u64)> {
+        let (value, size, last_used) = self.entries.remove(key)?;
+        self.order.remove(&last_used);
+        self.size -= size;
+        Some((value, size))
+    }
+
+    fn clear(&mut self) -> Vec<WasmHash> {
+        self.order.clear();
+        self.size = 0;
+        self.entries.drain().map(|(key, _)| key).collect()
+    }
+}
+
+/// Stores serialized modules on disk in the directory
+/// `<root>/<hex(config_hash)>/<hex(wasm_hash)>`.
+struct DiskCache {
+    dir: PathBuf,
+    config_hash: [u8; 32],
+    index: Mutex<LruIndex<()>>,
+    /// Checks that the modules loaded from disk can be deserialized.
+    embedder: WasmtimeEmbedder,
+    log: ReplicaLogger,
+}
+
+impl DiskCache {
+    /// Opens the cache directory for the current replica and the given
+    /// configuration. Directories belonging to other replicas or
+    /// configurations are removed, and the files that are already present
+    /// are added to the index in modification order.
+    fn open(
+        root: &Path,
+        config: &EmbeddersConfig,
+        config_hash: [u8; 32],
+        log: ReplicaLogger,
+    ) -> io::Result<Self> {
+        let dir = root.join(hex::encode(config_hash));
+        fs::create_dir_all(&dir)?;
+        for entry in fs::read_dir(root)? {
+            let path = entry?.path();
+            if path != dir {
+                let result = if path.is_dir() {
+                    fs::remove_dir_all(&path)
+                } else {
+                    fs::remove_file(&path)
+                };
+                if let Err(err) = result {
+                    warn!(
+                        log,
+                        "Failed to remove stale compilation cache entry {}: {}",
+                        path.display(),
+                        err
+                    );
+                }
+            }
+        }
+
+        let mut files = vec![];
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            let wasm_hash = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| hex::decode(name).ok())
+                .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok());
+            match wasm_hash {
+                Some(wasm_hash) if metadata.is_file() => files.push((
+                    metadata.modified()?,
+                    WasmHash::from(wasm_hash),
+                    metadata.len(),
+                )),
+                _ => fs::remove_file(entry.path())?,
+            }
+        }
+        files.sort_by_key(|(modified, _, _)| *modified);
+
+        let mut index = LruIndex::new(config.max_compilation_cache_disk_size.get());
+        for (_, wasm_hash, size) in files {
+            for (evicted, _, _) in index.insert(wasm_hash, (), size) {
+                let _ = fs::remove_file(dir.join(hex::encode(evicted.to_vec())));
+            }
+        }
+
+        Ok(Self {
+            dir,
+            config_hash,
+            index: Mutex::new(index),
+            embedder: WasmtimeEmbedder::new(config.clone(), log.clone()),
+            log,
+        })
+    }
+
+    fn path(&self, wasm_hash: &WasmHash) -> PathBuf {
+        self.dir.join(hex::encode(wasm_hash.to_vec()))
+    }
+
+    fn size(&self) -> u64 {
+        self.index.lock().unwrap().size
+    }
+
+    /// Writes the module to disk and returns the number of evicted files.
+    fn store(&self, wasm_hash: &WasmHash, module: &SerializedModule) -> io::Result<usize> {
+        let payload = bincode::serialize(module)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        let path = self.path(wasm_hash);
+        let tmp_path = path.with_extension("tmp");
+        {
+            let mut file = fs::File::create(&tmp_path)?;
+            file.write_all(FILE_MAGIC)?;
+            file.write_all(&FILE_FORMAT_VERSION.to_le_bytes())?;
+            file.write_all(&wasm_hash.to_vec())?;
+            file.write_all(&self.config_hash)?;
+            file.write_all(&Sha256::hash(&payload))?;
+            file.write_all(&payload)?;
+        }
+        fs::rename(&tmp_path, &path)?;
+
+        let size = (FILE_HEADER_SIZE + payload.len()) as u64;
+        let evicted = self
+            .index
+            .lock()
+            .unwrap()
+            .insert(wasm_hash.clone(), (), size);
+        for (evicted_hash, _, _) in evicted.iter() {
+            let _ = fs::remove_file(self.path(evicted_hash));
+        }
+        Ok(evicted.len())
+    }
+
+    /// Loads the module from disk. Returns `Err` with the reason if the file
+    /// exists but doesn't contain a valid module, in which case it is removed
+    /// and the module is compiled again by the caller.
+    ///
+    /// The module is deserialized here only to check that wasmtime accepts
+    /// it. The caller of [`CompilationCache::get`] deserializes it again for
+    /// execution, which happens rarely for modules loaded from disk.
+    fn load(&self, wasm_hash: &WasmHash) -> Result<Option<SerializedModule>, &'static str> {
+        if self.index.lock().unwrap().get(wasm_hash).is_none() {
+            return Ok(None);
+        }
+        let path = self.path(wasm_hash);
+        let result = fs::read(&path)
+            .map_err(|_| "io_error")
+            .and_then(|contents| self.validate(wasm_hash, &contents));
+        match result {
+            Ok(module) => Ok(Some(module)),
+            Err(reason) => {
+                warn!(
+                    self.log,
+                    "Discarding invalid compilation cache file {}: {}",
+                    path.display(),
+                    reason
+                );
+                self.index.lock().unwrap().remove(wasm_hash);
+                let _ = fs::remove_file(&path);
+                Err(reason)
+            }
+        }
+    }
+
+    fn validate(
+        &self,
+        wasm_hash: &WasmHash,
+        contents: &[u8],
+    ) -> Result<SerializedModule, &'static str> {
+        if contents.len() < FILE_HEADER_SIZE {
+            return Err("truncated");
+        }
+        let (header, payload) = contents.split_at(FILE_HEADER_SIZE);
+        let (magic, header) = header.split_at(FILE_MAGIC.len());
+        let (version, header) = header.split_at(4);
+        let (file_wasm_hash, header) = header.split_at(32);
+        let (file_config_hash, checksum) = header.split_at(32);
+        if magic != FILE_MAGIC || version != FILE_FORMAT_VERSION.to_le_bytes() {
+            return Err("bad_header");
+        }
+        if file_wasm_hash != wasm_hash.to_vec() || file_config_hash != self.config_hash {
+            return Err("key_mismatch");
+        }
+        if checksum != Sha256::hash(payload) {
+            return Err("checksum_mismatch");
+        }
+        let module: SerializedModule =
+            bincode::deserialize(payload).map_err(|_| "decoding_failed")?;
+        self.embedder
+            .deserialize_module(&module.bytes)
+            .map_err(|_| "deserialization_failed")?;
+        Ok(module)
+    }
+
+    fn clear(&self) {
+        for wasm_hash in self.index.lock().unwrap().clear() {
+            let _ = fs::remove_file(self.path(&wasm_hash));
+        }
+    }
+}
+
+/// Returns the hash of the code that validates, instruments and compiles
+/// modules: the version and binary of the replica and the version of wasmtime.
+/// Dev builds may share a version, so the hash of the replica binary is
+/// included when it is known.
+fn compiler_version_hash() -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for part in [
+        wasmtime_environ::VERSION,
+        ReplicaVersion::default().as_ref(),
+        REPLICA_BINARY_HASH
+            .get()
+            .map(String::as_str)
+            .unwrap_or_default(),
+    ] {
+        hasher.write(&(part.len() as u64).to_le_bytes());
+        hasher.write(part.as_bytes());
+    }
+    hasher.finish()
+}
+
+/// Returns the hash of the code and the configuration options that affect
+/// the validation, instrumentation or compiled code of a module.
+///
+/// The config is destructured so that adding a field to it fails to compile
+/// until the field is either hashed or explicitly ignored here.
+fn config_hash(config: &EmbeddersConfig) -> [u8; 32] {
+    let EmbeddersConfig {
+        max_wasm_stack_size,
+        query_execution_threads: _,
+        max_globals,
+        max_functions,
+        max_custom_sections,
+        max_custom_sections_size,
+        cost_to_compile_wasm_instruction,
+        num_rayon_compilation_threads: _,
+        feature_flags,
+        stable_memory_dirty_page_limit,
+        max_compilation_cache_size: _,
+        max_compilation_cache_disk_size: _,
+        compilation_cache_dir: _,
+    } = config;
+    let FeatureFlags {
+        rate_limiting_of_debug_prints,
+        new_wasm_transform_lib,
+        write_barrier,
+    } = feature_flags;
+
+    let mut hasher = Sha256::new();
+    hasher.write(&FILE_FORMAT_VERSION.to_le_bytes());
+    hasher.write(&compiler_version_hash());
+    for value in [
+        *max_wasm_stack_size as u64,
+        *max_globals as u64,
+        *max_functions as u64,
+        *max_custom_sections as u64,
+        max_custom_sections_size.get(),
+        cost_to_compile_wasm_instruction.get(),
+        stable_memory_dirty_page_limit.get(),
+    ] {
+        hasher.write(&value.to_le_bytes());
+    }
+    for flag in [
+        rate_limiting_of_debug_prints,
+        new_wasm_transform_lib,
+        write_barrier,
+    ] {
+        hasher.write(&[flag_byte(flag)]);
+    }
+    hasher.finish()
+}
+
+fn flag_byte(flag: &FlagStatus) -> u8 {
+    match flag {
+        FlagStatus::Enabled => 1,
+        FlagStatus::Disabled => 0,
+    }
+}
+
+fn entry_size(entry: &HypervisorResult<Arc<SerializedModule>>) -> u64 {
+    match entry {
+        Ok(module) => module.bytes.as_slice().len() as u64,
+        Err(_) => ERROR_ENTRY_SIZE,
+    }
+}
 
 /// Stores the serialized modules of wasm code that has already been compiled so
 /// that it can be used again without recompiling.
-#[derive(Default)]
+///
+/// Compilation errors are only cached in memory.
 pub struct CompilationCache {
-    cache: RwLock<HashMap<WasmHash, HypervisorResult<Arc<SerializedModule>>>>,
+    memory: Mutex<LruIndex<HypervisorResult<Arc<SerializedModule>>>>,
+    disk: Option<DiskCache>,
+    /// Not set for caches created with `default()`, which are not exported.
+    metrics: Option<CompilationCacheMetrics>,
+}
+
+impl Default for CompilationCache {
+    /// Returns an in-memory cache without a size limit.
+    fn default() -> Self {
+        Self {
+            memory: Mutex::new(LruIndex::new(u64::MAX)),
+            disk: None,
+            metrics: None,
+        }
+    }
 }
 
 impl CompilationCache {
-    pub fn new() -> Self {
-        Self::default()
+    /// Creates a cache with the byte budgets of the given config. If
+    /// `compilation_cache_dir` is set, modules evicted from memory are kept
+    /// in that directory. If the directory cannot be used, the cache falls
+    /// back to memory only.
+    pub fn new(
+        config: &EmbeddersConfig,
+        metrics_registry: &MetricsRegistry,
+        log: ReplicaLogger,
+    ) -> Self {
+        let disk = config.compilation_cache_dir.as_ref().and_then(|dir| {
+            DiskCache::open(dir, config, config_hash(config), log.clone())
+                .map_err(|err| {
+                    warn!(
+                        log,
+                        "Failed to open compilation cache directory {}: {}",
+                        dir.display(),
+                        err
+                    )
+                })
+                .ok()
+        });
+        let metrics = CompilationCacheMetrics::new(metrics_registry);
+        if let Some(disk) = &disk {
+            metrics.disk_bytes.set(disk.size() as i64);
+        }
+        Self {
+            memory: Mutex::new(LruIndex::new(config.max_compilation_cache_size.get())),
+            disk,
+            metrics: Some(metrics),
+        }
+    }
+
+    fn observe(&self, f: impl FnOnce(&CompilationCacheMetrics)) {
+        if let Some(metrics) = &self.metrics {
+            f(metrics)
+        }
     }
 
     pub fn insert(
@@ -24,25 +494,341 @@ impl CompilationCache {
         canister_module: &CanisterModule,
         serialized_module: HypervisorResult<Arc<SerializedModule>>,
     ) {
-        self.cache
-            .write()
-            .unwrap()
-            .insert(WasmHash::from(canister_module), serialized_module);
+        let size = entry_size(&serialized_module);
+        let evicted = {
+            let mut memory = self.memory.lock().unwrap();
+            let evicted = memory.insert(WasmHash::from(canister_module), serialized_module, size);
+            self.observe(|metrics| metrics.memory_bytes.set(memory.size as i64));
+            evicted
+        };
+        self.spill(evicted);
     }
 
     pub fn get(
         &self,
         canister_module: &CanisterModule,
     ) -> Option<HypervisorResult<Arc<SerializedModule>>> {
-        self.cache
-            .read()
+        let wasm_hash = WasmHash::from(canister_module);
+        let cached = self
+            .memory
+            .lock()
             .unwrap()
-            .get(&WasmHash::from(canister_module))
-            .map(|o| o.as_ref().map(Arc::clone).map_err(|e| e.clone()))
+            .get(&wasm_hash)
+            .map(|o| o.as_ref().map(Arc::clone).map_err(|e| e.clone()));
+        if cached.is_some() {
+            self.observe(|metrics| {
+                metrics
+                    .lookups
+                    .with_label_values(&[LOOKUP_MEMORY_HIT])
+                    .inc()
+            });
+            return cached;
+        }
+
+        let loaded = self.disk.as_ref().and_then(|disk| {
+            let loaded = disk.load(&wasm_hash).unwrap_or_else(|reason| {
+                self.observe(|metrics| metrics.invalid_files.with_label_values(&[reason]).inc());
+                None
+            });
+            self.observe(|metrics| metrics.disk_bytes.set(disk.size() as i64));
+            loaded
+        });
+        match loaded {
+            Some(module) => {
+                self.observe(|metrics| metrics.lookups.with_label_values(&[LOOKUP_DISK_HIT]).inc());
+                let module = Arc::new(module);
+                self.insert(canister_module, Ok(Arc::clone(&module)));
+                Some(Ok(module))
+            }
+            None => {
+                self.observe(|metrics| metrics.lookups.with_label_values(&[LOOKUP_MISS]).inc());
+                None
+            }
+        }
+    }
+
+    /// Moves the modules evicted from memory to disk.
+    fn spill(&self, evicted: Vec<(WasmHash, HypervisorResult<Arc<SerializedModule>>, u64)>) {
+        if evicted.is_empty() {
+            return;
+        }
+        self.observe(|metrics| {
+            metrics
+                .evictions
+                .with_label_values(&[TIER_MEMORY])
+                .inc_by(evicted.len() as u64)
+        });
+        let disk = match &self.disk {
+            Some(disk) => disk,
+            None => return,
+        };
+        for (wasm_hash, entry, _) in evicted {
+            if let Ok(module) = entry {
+                match disk.store(&wasm_hash, &module) {
+                    Ok(evicted_files) => self.observe(|metrics| {
+                        metrics
+                            .evictions
+                            .with_label_values(&[TIER_DISK])
+                            .inc_by(evicted_files as u64)
+                    }),
+                    Err(err) => warn!(
+                        disk.log,
+                        "Failed to write compilation cache file {}: {}",
+                        disk.path(&wasm_hash).display(),
+                        err
+                    ),
+                }
+            }
+        }
+        self.observe(|metrics| metrics.disk_bytes.set(disk.size() as i64));
     }
 
     #[doc(hidden)]
     pub fn clear_for_testing(&self) {
-        self.cache.write().unwrap().clear()
+        self.memory.lock().unwrap().clear();
+        self.observe(|metrics| metrics.memory_bytes.set(0));
+        if let Some(disk) = &self.disk {
+            disk.clear();
+            self.observe(|metrics| metrics.disk_bytes.set(0));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{wasm_utils::compile, WasmtimeEmbedder};
+    use ic_logger::replica_logger::no_op_logger;
+    use ic_types::NumBytes;
+    use ic_wasm_types::BinaryEncodedWasm;
+
+    fn module(i: u32) -> (CanisterModule, Arc<SerializedModule>) {
+        let wasm = wabt::wat2wasm(format!("(module (global i32 (i32.const {})))", i)).unwrap();
+        let embedder = WasmtimeEmbedder::new(EmbeddersConfig::default(), no_op_logger());
+        let (_, result) = compile(&embedder, &BinaryEncodedWasm::new(wasm.clone()));
+        let (_, serialized_module) = result.unwrap();
+        (CanisterModule::new(wasm), Arc::new(serialized_module))
+    }
+
+    fn config(memory_size: u64, dir: Option<&Path>) -> EmbeddersConfig {
+        EmbeddersConfig {
+            max_compilation_cache_size: NumBytes::new(memory_size),
+            compilation_cache_dir: dir.map(Path::to_path_buf),
+            ..EmbeddersConfig::default()
+        }
+    }
+
+    fn cached_bytes(cache: &CompilationCache, canister_module: &CanisterModule) -> Option<Vec<u8>> {
+        cache
+            .get(canister_module)
+            .map(|module| module.unwrap().bytes.as_slice().to_vec())
+    }
+
+    #[test]
+    fn least_recently_used_module_is_evicted() {
+        let (wasm_a, module_a) = module(1);
+        let (wasm_b, module_b) = module(2);
+        let (wasm_c, module_c) = module(3);
+        let capacity = entry_size(&Ok(Arc::clone(&module_a))) * 5 / 2;
+        let cache = CompilationCache::new(
+            &config(capacity, None),
+            &MetricsRegistry::new(),
+            no_op_logger(),
+        );
+
+        cache.insert(&wasm_a, Ok(Arc::clone(&module_a)));
+        cache.insert(&wasm_b, Ok(module_b));
+        assert!(cache.get(&wasm_a).is_some());
+        cache.insert(&wasm_c, Ok(module_c));
+
+        assert!(cache.get(&wasm_a).is_some());
+        assert!(cache.get(&wasm_b).is_none());
+        assert!(cache.get(&wasm_c).is_some());
+        assert_eq!(
+            cache
+                .metrics
+                .as_ref()
+                .unwrap()
+                .lookups
+                .with_label_values(&[LOOKUP_MISS])
+                .get(),
+            1
+        );
+        assert_eq!(
+            cache
+                .metrics
+                .as_ref()
+                .unwrap()
+                .evictions
+                .with_label_values(&[TIER_MEMORY])
+                .get(),
+            1
+        );
+    }
+
+    #[test]
+    fn evicted_module_is_loaded_from_disk_after_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let (wasm, module) = module(1);
+        let expected = module.bytes.as_slice().to_vec();
+
+        // A memory budget of one byte spills every module to disk.
+        let cache = CompilationCache::new(
+            &config(1, Some(dir.path())),
+            &MetricsRegistry::new(),
+            no_op_logger(),
+        );
+        cache.insert(&wasm, Ok(module));
+        assert_eq!(cached_bytes(&cache, &wasm), Some(expected.clone()));
+        assert_eq!(
+            cache
+                .metrics
+                .as_ref()
+                .unwrap()
+                .lookups
+                .with_label_values(&[LOOKUP_DISK_HIT])
+                .get(),
+            1
+        );
+        drop(cache);
+
+        let cache = CompilationCache::new(
+            &config(1 << 30, Some(dir.path())),
+            &MetricsRegistry::new(),
+            no_op_logger(),
+        );
+        assert_eq!(cached_bytes(&cache, &wasm), Some(expected));
+    }
+
+    #[test]
+    fn corrupted_file_is_discarded() {
+        let dir = tempfile::tempdir().unwrap();
+        let (wasm, module) = module(1);
+        let config = config(1, Some(dir.path()));
+        let cache = CompilationCache::new(&config, &MetricsRegistry::new(), no_op_logger());
+        cache.insert(&wasm, Ok(module));
+
+        let path = cache.disk.as_ref().unwrap().path(&WasmHash::from(&wasm));
+        let mut contents = fs::read(&path).unwrap();
+        let last = contents.len() - 1;
+        contents[last] ^= 0xff;
+        fs::write(&path, contents).unwrap();
+
+        assert!(cache.get(&wasm).is_none());
+        assert!(!path.exists());
+        assert_eq!(
+            cache
+                .metrics
+                .as_ref()
+                .unwrap()
+                .invalid_files
+                .with_label_values(&["checksum_mismatch"])
+                .get(),
+            1
+        );
+    }
+
+    #[test]
+    fn modules_compiled_with_different_config_are_not_reused() {
+        let dir = tempfile::tempdir().unwrap();
+        let (wasm, module) = module(1);
+        let cache = CompilationCache::new(
+            &config(1, Some(dir.path())),
+            &MetricsRegistry::new(),
+            no_op_logger(),
+        );
+        cache.insert(&wasm, Ok(module));
+        drop(cache);
+
+        let mut other_config = config(1, Some(dir.path()));
+        other_config.max_wasm_stack_size /= 2;
+        let cache = CompilationCache::new(&other_config, &MetricsRegistry::new(), no_op_logger());
+        assert!(cache.get(&wasm).is_none());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn modules_of_other_versions_are_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = config(1, Some(dir.path()));
+        // The directory of a replica with a different version.
+        let stale_dir = dir.path().join(hex::encode([0; 32]));
+        fs::create_dir_all(&stale_dir).unwrap();
+        fs::write(stale_dir.join("module"), b"module").unwrap();
+
+        let (wasm, module) = module(1);
+        let cache = CompilationCache::new(&config, &MetricsRegistry::new(), no_op_logger());
+        assert!(!stale_dir.exists());
+        cache.insert(&wasm, Ok(module));
+        assert!(cache.get(&wasm).is_some());
+    }
+
+    #[test]
+    fn module_that_fails_to_deserialize_is_compiled_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let (wasm, module) = module(1);
+        let config = config(1, Some(dir.path()));
+        let cache = CompilationCache::new(&config, &MetricsRegistry::new(), no_op_logger());
+        cache.insert(&wasm, Ok(Arc::clone(&module)));
+
+        // Replace the file with a well-formed file whose module wasmtime
+        // rejects, as if it had been written by an incompatible compiler.
+        let disk = cache.disk.as_ref().unwrap();
+        let wasm_hash = WasmHash::from(&wasm);
+        let mut stale_module = (*module).clone();
+        stale_module.bytes =
+            Arc::new(bincode::deserialize(&bincode::serialize(&vec![0_u8; 16]).unwrap()).unwrap());
+        disk.store(&wasm_hash, &stale_module).unwrap();
+
+        assert!(cache.get(&wasm).is_none());
+        assert!(!disk.path(&wasm_hash).exists());
+        assert_eq!(
+            cache
+                .metrics
+                .as_ref()
+                .unwrap()
+                .invalid_files
+                .with_label_values(&["deserialization_failed"])
+                .get(),
+            1
+        );
+
+        // The recompiled module overwrites the entry.
+        let (_, recompiled) = module(1);
+        cache.insert(&wasm, Ok(Arc::clone(&recompiled)));
+        assert!(disk.path(&wasm_hash).exists());
+        assert_eq!(
+            cached_bytes(&cache, &wasm),
+            Some(recompiled.bytes.as_slice().to_vec())
+        );
+    }
+
+    #[test]
+    fn cache_settings_do_not_change_the_config_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut other_config = config(1 << 30, Some(dir.path()));
+        other_config.max_compilation_cache_disk_size = NumBytes::new(1);
+        assert_eq!(config_hash(&config(1, None)), config_hash(&other_config));
+    }
+
+    #[test]
+    fn compilation_errors_are_not_written_to_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let (wasm, _) = module(1);
+        let cache = CompilationCache::new(
+            &config(1, Some(dir.path())),
+            &MetricsRegistry::new(),
+            no_op_logger(),
+        );
+        cache.insert(
+            &wasm,
+            Err(
+                ic_interfaces::execution_environment::HypervisorError::ContractViolation(
+                    "error".to_string(),
+                ),
+            ),
+        );
+        assert!(cache.get(&wasm).is_none());
+        assert_eq!(cache.disk.as_ref().unwrap().size(), 0);
     }
 }
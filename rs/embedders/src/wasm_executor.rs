@@ -27,7 +27,7 @@ use ic_sys::{page_bytes_from_ptr, PageBytes, PageIndex, PAGE_SIZE};
 use ic_system_api::{
     system_api_empty::SystemApiEmpty, ExecutionParameters, ModificationTracking, SystemApiImpl,
 };
-use ic_types::{CanisterId, NumBytes, NumInstructions};
+use ic_types::{canister_log::CanisterLog, CanisterId, NumBytes, NumInstructions};
 use ic_wasm_types::{BinaryEncodedWasm, CanisterModule};
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
@@ -472,6 +472,8 @@ pub fn wasm_execution_error(
                 accessed_pages: 0,
                 dirty_pages: 0,
             },
+            canister_log: CanisterLog::default(),
+            data_certificate_accessed: false,
         },
         None,
     )
@@ -634,6 +636,8 @@ pub fn process(
                         accessed_pages: 0,
                         dirty_pages: 0,
                     },
+                    canister_log: CanisterLog::default(),
+                    data_certificate_accessed: false,
                 },
                 None,
                 Err(system_api),
@@ -723,6 +727,9 @@ pub fn process(
         Err(_) => None,
     };
 
+    let canister_log = instance.store_data_mut().system_api.take_canister_log();
+    let data_certificate_accessed = instance.store_data().system_api.data_certificate_accessed();
+
     (
         SliceExecutionOutput {
             executed_instructions: slice_instructions_executed,
@@ -733,6 +740,8 @@ pub fn process(
             allocated_bytes,
             allocated_message_bytes,
             instance_stats,
+            canister_log,
+            data_certificate_accessed,
         },
         wasm_state_changes,
         Ok(instance),
@@ -80,6 +80,39 @@ fn wasmtime_error_to_hypervisor_error(err: anyhow::Error) -> HypervisorError {
     }
 }
 
+/// Returns the Wasm backtrace of the trap that caused the error, if any, as it
+/// is recorded in the canister log: one line per frame, innermost frame first.
+fn wasmtime_error_backtrace(err: &anyhow::Error) -> Option<String> {
+    let trace = err.downcast_ref::<wasmtime::Trap>()?.trace()?;
+    if trace.is_empty() {
+        return None;
+    }
+    let frames: Vec<String> = trace
+        .iter()
+        .map(|frame| match frame.func_name() {
+            Some(name) => name.to_string(),
+            None => format!("<function {}>", frame.func_index()),
+        })
+        .collect();
+    Some(format!("Canister Backtrace:\n{}", frames.join("\n")))
+}
+
+/// Returns the canister log record describing a trap, or `None` if the error
+/// is not a trap.
+fn trap_log_message(err: &HypervisorError, backtrace: Option<String>) -> Option<Vec<u8>> {
+    let message = match err {
+        HypervisorError::CalledTrap(message) => message.clone(),
+        HypervisorError::Trapped(trap_code) => trap_code.to_string(),
+        _ => return None,
+    };
+    let mut record = format!("[TRAP]: {}", message);
+    if let Some(backtrace) = backtrace {
+        record.push('\n');
+        record.push_str(&backtrace);
+    }
+    Some(record.into_bytes())
+}
+
 fn trap_code_to_hypervisor_error(trap_code: wasmtime::TrapCode) -> HypervisorError {
     match trap_code {
         wasmtime::TrapCode::StackOverflow => HypervisorError::Trapped(TrapCode::StackOverflow),
@@ -528,7 +561,14 @@ impl<S: SystemApi> WasmtimeInstance<S> {
         self.store.data()
     }
 
-    fn invoke_export(&mut self, export: &str, args: &[Val]) -> HypervisorResult<()> {
+    /// Invokes the given export. If the export traps, its backtrace is stored
+    /// in `backtrace`.
+    fn invoke_export(
+        &mut self,
+        export: &str,
+        args: &[Val],
+        backtrace: &mut Option<String>,
+    ) -> HypervisorResult<()> {
         self.instance
             .get_export(&mut self.store, export)
             .ok_or_else(|| {
@@ -539,7 +579,10 @@ impl<S: SystemApi> WasmtimeInstance<S> {
                 HypervisorError::ContractViolation("export is not a function".to_string())
             })?
             .call(&mut self.store, args, &mut [])
-            .map_err(wasmtime_error_to_hypervisor_error)
+            .map_err(|err| {
+                *backtrace = wasmtime_error_backtrace(&err);
+                wasmtime_error_to_hypervisor_error(err)
+            })
     }
 
     fn dirty_pages(&self) -> HashMap<CanisterMemoryType, PageAccessResults> {
@@ -595,8 +638,11 @@ impl<S: SystemApi> WasmtimeInstance<S> {
     pub fn run(&mut self, func_ref: FuncRef) -> HypervisorResult<InstanceRunResult> {
         let _alt_sig_stack = unsafe { self.signal_stack.register() };
 
+        let mut backtrace = None;
         let result = match &func_ref {
-            FuncRef::Method(wasm_method) => self.invoke_export(&wasm_method.to_string(), &[]),
+            FuncRef::Method(wasm_method) => {
+                self.invoke_export(&wasm_method.to_string(), &[], &mut backtrace)
+            }
             FuncRef::QueryClosure(closure) | FuncRef::UpdateClosure(closure) => self
                 .instance
                 .get_export(&mut self.store, "table")
@@ -617,7 +663,10 @@ impl<S: SystemApi> WasmtimeInstance<S> {
                     )
                 })?
                 .call(&mut self.store, &[Val::I32(closure.env as i32)], &mut [])
-                .map_err(wasmtime_error_to_hypervisor_error),
+                .map_err(|err| {
+                    backtrace = wasmtime_error_backtrace(&err);
+                    wasmtime_error_to_hypervisor_error(err)
+                }),
         }
         .map_err(|e| {
             self.store
@@ -628,6 +677,12 @@ impl<S: SystemApi> WasmtimeInstance<S> {
                 .unwrap_or(e)
         });
 
+        if let Err(err) = &result {
+            if let Some(record) = trap_log_message(err, backtrace) {
+                self.store.data_mut().system_api.append_canister_log(record);
+            }
+        }
+
         let mut accesses = self.dirty_pages();
         let dirty_pages = if let Some(PageAccessResults {
             dirty_pages,
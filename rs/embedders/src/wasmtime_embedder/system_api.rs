@@ -696,9 +696,15 @@ pub(crate) fn syscalls<S: SystemApi>(
                     caller.data().system_api.subnet_type(),
                     rate_limiting_of_debug_prints,
                 ) {
-                    // Debug print is a no-op on non-system subnets with rate limiting.
-                    (SubnetType::Application, FlagStatus::Enabled) => Ok(()),
-                    (SubnetType::VerifiedApplication, FlagStatus::Enabled) => Ok(()),
+                    // Debug print only goes to the canister log on non-system subnets
+                    // with rate limiting.
+                    (SubnetType::Application, FlagStatus::Enabled)
+                    | (SubnetType::VerifiedApplication, FlagStatus::Enabled) => {
+                        with_memory_and_system_api(&mut caller, |system_api, memory| {
+                            system_api.save_log_message(offset as u32, length as u32, memory);
+                            Ok(())
+                        })
+                    }
                     // If rate limiting is disabled or the subnet is a system subnet, then
                     // debug print produces output.
                     (_, FlagStatus::Disabled) | (SubnetType::System, FlagStatus::Enabled) => {
@@ -61,7 +61,7 @@ pub fn bench_execute_query(c: &mut Criterion) {
                 compute_allocation_used: 0,
             };
             let instructions_before = round_limits.instructions;
-            let (_, _, result) = execute_non_replicated_query(
+            let (_, _, result, _) = execute_non_replicated_query(
                 NonReplicatedQueryKind::Pure { caller: sender },
                 WasmMethod::Query("test".to_string()),
                 &[],
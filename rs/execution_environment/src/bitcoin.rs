@@ -3,9 +3,10 @@ use candid::Encode;
 use ic_btc_canister::state::State as BitcoinCanisterState;
 use ic_error_types::{ErrorCode, UserError};
 use ic_ic00_types::{
-    BitcoinGetBalanceArgs, BitcoinGetCurrentFeePercentilesArgs, BitcoinGetSuccessorsArgs,
-    BitcoinGetSuccessorsResponse, BitcoinGetUtxosArgs, BitcoinNetwork, BitcoinSendTransactionArgs,
-    BitcoinSendTransactionInternalArgs, EmptyBlob, Method as Ic00Method, Payload,
+    BitcoinGetBalanceArgs, BitcoinGetBlockHeadersArgs, BitcoinGetCurrentFeePercentilesArgs,
+    BitcoinGetSuccessorsArgs, BitcoinGetSuccessorsResponse, BitcoinGetUtxosArgs, BitcoinNetwork,
+    BitcoinSendTransactionArgs, BitcoinSendTransactionInternalArgs, EmptyBlob,
+    Method as Ic00Method, Payload,
 };
 use ic_registry_subnet_features::BitcoinFeatureStatus;
 use ic_replicated_state::{
@@ -28,6 +29,8 @@ const GET_UTXOS_FEE: Cycles = Cycles::new(100_000_000);
 const GET_CURRENT_FEE_PERCENTILES_FEE: Cycles = Cycles::new(100_000_000);
 const SEND_TRANSACTION_FEE_BASE: Cycles = Cycles::new(5_000_000_000);
 const SEND_TRANSACTION_FEE_PER_BYTE: Cycles = Cycles::new(20_000_000);
+const GET_BLOCK_HEADERS_FEE_BASE: Cycles = Cycles::new(50_000_000);
+const GET_BLOCK_HEADERS_FEE_PER_HEADER: Cycles = Cycles::new(50_000);
 
 /// Handles a `bitcoin_get_balance` request.
 pub fn get_balance(
@@ -143,6 +146,63 @@ pub fn get_current_fee_percentiles(
     )
 }
 
+/// Handles a `bitcoin_get_block_headers` request.
+pub fn get_block_headers(
+    payload: &[u8],
+    state: &mut ReplicatedState,
+    payment: Cycles,
+) -> (Result<Vec<u8>, UserError>, Cycles) {
+    let args = match BitcoinGetBlockHeadersArgs::decode(payload) {
+        Err(err) => {
+            // Failed to parse payload. Charge the base fee and return.
+            return (
+                Err(candid_error_to_user_error(err)),
+                payment - GET_BLOCK_HEADERS_FEE_BASE,
+            );
+        }
+        Ok(args) => args,
+    };
+
+    // The fee covers the maximum number of headers that can be returned for
+    // the requested range, independently of how many are actually available.
+    let max_headers = ic_btc_canister::MAX_BLOCK_HEADERS_PER_RESPONSE as u64;
+    let num_headers = match args.end_height {
+        Some(end_height) => {
+            (end_height.saturating_sub(args.start_height) as u64 + 1).min(max_headers)
+        }
+        None => max_headers,
+    };
+    let fee = GET_BLOCK_HEADERS_FEE_BASE + GET_BLOCK_HEADERS_FEE_PER_HEADER * num_headers;
+
+    execute_bitcoin_endpoint(
+        payload,
+        state,
+        payment,
+        fee,
+        move |_payload: &[u8], state: &mut ReplicatedState| -> Result<Vec<u8>, UserError> {
+            // Verify that the request is for the expected network.
+            verify_network(args.network.into(), state.bitcoin().network())?;
+
+            let btc_canister_state = BitcoinCanisterState::from(state.take_bitcoin_state());
+            let response = ic_btc_canister::get_block_headers(
+                &btc_canister_state,
+                args.start_height,
+                args.end_height,
+            );
+            state.put_bitcoin_state(btc_canister_state.into());
+
+            response
+                .map(|response| Encode!(&response).unwrap())
+                .map_err(|err| {
+                    UserError::new(
+                        ErrorCode::CanisterRejectedMessage,
+                        format!("{} failed: {}", Ic00Method::BitcoinGetBlockHeaders, err),
+                    )
+                })
+        },
+    )
+}
+
 /// Handles a `bitcoin_send_transaction` request.
 // TODO(EXC-1239): Remove this endpoint once the migration to a canister is complete.
 pub fn send_transaction(
@@ -5,13 +5,13 @@ use bitcoin::{
 use candid::Encode;
 use ic_btc_test_utils::{random_p2pkh_address, BlockBuilder, TransactionBuilder};
 use ic_btc_types::{
-    GetUtxosResponse, NetworkInRequest as BitcoinNetwork, OutPoint, Satoshi, Utxo,
-    UtxosFilterInRequest,
+    BlockHeader, GetBlockHeadersResponse, GetUtxosResponse, NetworkInRequest as BitcoinNetwork,
+    OutPoint, Satoshi, Utxo, UtxosFilterInRequest,
 };
 use ic_ic00_types::{
-    BitcoinGetBalanceArgs, BitcoinGetCurrentFeePercentilesArgs, BitcoinGetSuccessorsArgs,
-    BitcoinGetUtxosArgs, BitcoinSendTransactionArgs, EmptyBlob, Method, Payload as Ic00Payload,
-    IC_00,
+    BitcoinGetBalanceArgs, BitcoinGetBlockHeadersArgs, BitcoinGetCurrentFeePercentilesArgs,
+    BitcoinGetSuccessorsArgs, BitcoinGetUtxosArgs, BitcoinSendTransactionArgs, EmptyBlob, Method,
+    Payload as Ic00Payload, IC_00,
 };
 use ic_interfaces::execution_environment::SubnetAvailableMemory;
 use ic_replicated_state::bitcoin_state::BitcoinState;
@@ -28,6 +28,8 @@ use std::sync::Arc;
 // TODO(EXC-1153): Refactor to avoid copying these constants from bitcoin.rs
 const SEND_TRANSACTION_FEE_BASE: Cycles = Cycles::new(5_000_000_000);
 const SEND_TRANSACTION_FEE_PER_BYTE: Cycles = Cycles::new(20_000_000);
+const GET_BLOCK_HEADERS_FEE_BASE: Cycles = Cycles::new(50_000_000);
+const GET_BLOCK_HEADERS_FEE_PER_HEADER: Cycles = Cycles::new(50_000);
 
 lazy_static! {
     static ref MAX_SUBNET_AVAILABLE_MEMORY: SubnetAvailableMemory =
@@ -754,6 +756,105 @@ fn get_current_fee_percentiles_cache() {
     test.state_mut().put_bitcoin_state(bitcoin_state);
 }
 
+fn fake_get_block_headers_args() -> BitcoinGetBlockHeadersArgs {
+    BitcoinGetBlockHeadersArgs {
+        start_height: 0,
+        end_height: Some(1),
+        network: BitcoinNetwork::Testnet,
+    }
+}
+
+#[test]
+fn get_block_headers_rejects_feature_not_enabled() {
+    reject_feature_not_enabled(
+        Method::BitcoinGetBlockHeaders,
+        fake_get_block_headers_args().encode(),
+    );
+}
+
+#[test]
+fn get_block_headers_not_enough_cycles() {
+    // Two headers are requested.
+    let fee = GET_BLOCK_HEADERS_FEE_BASE + GET_BLOCK_HEADERS_FEE_PER_HEADER * 2_u64;
+    reject_and_check_refund(
+        fake_state(),
+        Method::BitcoinGetBlockHeaders,
+        fake_get_block_headers_args().encode(),
+        fee - Cycles::new(1), // Not enough cycles given.
+        fee - Cycles::new(1), // Refund all.
+        "Received 50_099_999 cycles. 50_100_000 cycles are required.",
+    );
+}
+
+#[test]
+fn get_block_headers_charges_for_max_headers_without_end_height() {
+    let fee = GET_BLOCK_HEADERS_FEE_BASE
+        + GET_BLOCK_HEADERS_FEE_PER_HEADER * ic_btc_canister::MAX_BLOCK_HEADERS_PER_RESPONSE as u64;
+    let expected_refund = Cycles::new(123);
+    execute_and_check_refund(
+        Method::BitcoinGetBlockHeaders,
+        BitcoinGetBlockHeadersArgs {
+            start_height: 0,
+            end_height: None,
+            network: BitcoinNetwork::Testnet,
+        }
+        .encode(),
+        fee + expected_refund,
+        expected_refund,
+    );
+}
+
+#[test]
+fn get_block_headers_succeeds() {
+    let network = Network::Testnet;
+    let block_0 = BlockBuilder::genesis().build();
+    let block_1 = BlockBuilder::with_prev_header(block_0.header).build();
+    let block_2 = BlockBuilder::with_prev_header(block_1.header).build();
+
+    // With a stability threshold of 1, `block_0` is stable and its header is
+    // served from the stable state.
+    let mut state = ic_btc_canister::state::State::new(1, network, block_0.clone());
+    ic_btc_canister::store::insert_block(&mut state, block_1.clone()).unwrap();
+    ic_btc_canister::store::insert_block(&mut state, block_2).unwrap();
+
+    let fee = GET_BLOCK_HEADERS_FEE_BASE + GET_BLOCK_HEADERS_FEE_PER_HEADER * 2_u64;
+    execute_check_payload_and_refund(
+        BitcoinState::from(state),
+        Method::BitcoinGetBlockHeaders,
+        fake_get_block_headers_args().encode(),
+        fee,
+        Cycles::zero(),
+        Payload::Data(
+            Encode!(&GetBlockHeadersResponse {
+                tip_height: 2,
+                block_headers: vec![
+                    BlockHeader::from(bitcoin::consensus::serialize(&block_0.header)),
+                    BlockHeader::from(bitcoin::consensus::serialize(&block_1.header)),
+                ],
+            })
+            .unwrap(),
+        ),
+    );
+}
+
+#[test]
+fn get_block_headers_rejects_invalid_range() {
+    let fee = GET_BLOCK_HEADERS_FEE_BASE + GET_BLOCK_HEADERS_FEE_PER_HEADER * 1_u64;
+    reject_and_check_refund(
+        fake_state(),
+        Method::BitcoinGetBlockHeaders,
+        BitcoinGetBlockHeadersArgs {
+            start_height: 5,
+            end_height: Some(5),
+            network: BitcoinNetwork::Testnet,
+        }
+        .encode(),
+        fee,
+        Cycles::zero(),
+        "bitcoin_get_block_headers failed: The requested start_height is larger than the height of the chain. Given: 5, height of chain: 1",
+    );
+}
+
 #[test]
 fn send_transaction_rejects_if_feature_not_enabled() {
     reject_feature_not_enabled(
@@ -1,8 +1,11 @@
 use crate::execution::install_code::{
-    validate_compute_allocation, validate_controller, validate_memory_allocation, OriginalContext,
+    canister_layout, validate_compute_allocation, validate_controller, validate_memory_allocation,
+    OriginalContext,
 };
 use crate::execution::{install::execute_install, upgrade::execute_upgrade};
-use crate::execution_environment::{CompilationCostHandling, RoundContext, RoundLimits};
+use crate::execution_environment::{
+    as_round_instructions, CompilationCostHandling, RoundContext, RoundLimits,
+};
 use crate::{
     canister_settings::CanisterSettings,
     hypervisor::Hypervisor,
@@ -14,8 +17,8 @@ use ic_config::flag_status::FlagStatus;
 use ic_cycles_account_manager::CyclesAccountManager;
 use ic_error_types::{ErrorCode, RejectCode, UserError};
 use ic_ic00_types::{
-    CanisterInstallMode, CanisterStatusResultV2, CanisterStatusType, InstallCodeArgs,
-    Method as Ic00Method,
+    CanisterInstallMode, CanisterSnapshotResponse, CanisterStatusResultV2, CanisterStatusType,
+    InstallCodeArgs, LogVisibility, Method as Ic00Method,
 };
 use ic_interfaces::execution_environment::{
     CanisterOutOfCyclesError, HypervisorError, IngressHistoryWriter, SubnetAvailableMemory,
@@ -25,8 +28,8 @@ use ic_logger::{error, fatal, info, ReplicaLogger};
 use ic_registry_provisional_whitelist::ProvisionalWhitelist;
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::{
-    CallOrigin, CanisterState, CanisterStatus, NetworkTopology, ReplicatedState, SchedulerState,
-    SystemState,
+    canister_snapshots::memory_copy_size, CallOrigin, CanisterSnapshot, CanisterState,
+    CanisterStatus, NetworkTopology, ReplicatedState, SchedulerState, SnapshotId, SystemState,
 };
 use ic_system_api::ExecutionParameters;
 use ic_types::messages::{MessageId, SignedIngressContent};
@@ -35,9 +38,9 @@ use ic_types::NumInstructions;
 use ic_types::{
     ingress::{IngressState, IngressStatus},
     messages::{Payload, RejectContext, Response as CanisterResponse, StopCanisterContext},
-    CanisterId, CanisterTimer, ComputeAllocation, Cycles, InvalidComputeAllocationError,
-    InvalidMemoryAllocationError, InvalidQueryAllocationError, MemoryAllocation, NumBytes,
-    PrincipalId, QueryAllocation, SubnetId, Time,
+    CanisterId, CanisterTimer, ComputeAllocation, Cycles, ExecutionRound,
+    InvalidComputeAllocationError, InvalidMemoryAllocationError, InvalidQueryAllocationError,
+    MemoryAllocation, NumBytes, PrincipalId, QueryAllocation, SubnetId, Time,
 };
 use ic_wasm_types::CanisterModule;
 use num_traits::cast::ToPrimitive;
@@ -46,6 +49,16 @@ use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use std::{collections::BTreeSet, convert::TryFrom, str::FromStr, sync::Arc};
 
+/// The maximum number of snapshots a single canister can have.
+pub(crate) const MAX_SNAPSHOTS_PER_CANISTER: usize = 1;
+
+/// Returns the number of instructions charged for copying `copy_size` bytes of
+/// canister memory into or out of a snapshot: one instruction per byte, like
+/// writing the memory from Wasm.
+pub(crate) fn snapshot_copy_instructions(copy_size: NumBytes) -> NumInstructions {
+    NumInstructions::from(copy_size.get())
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub(crate) struct InstallCodeResult {
     pub heap_delta: NumBytes,
@@ -313,7 +326,8 @@ impl CanisterManager {
             | Ok(Ic00Method::BitcoinGetUtxos)
             | Ok(Ic00Method::BitcoinSendTransaction)
             | Ok(Ic00Method::BitcoinSendTransactionInternal)
-            | Ok(Ic00Method::BitcoinGetCurrentFeePercentiles) => Err(UserError::new(
+            | Ok(Ic00Method::BitcoinGetCurrentFeePercentiles)
+            | Ok(Ic00Method::BitcoinGetBlockHeaders) => Err(UserError::new(
                 ErrorCode::CanisterRejectedMessage,
                 format!("Only canisters can call ic00 method {}", method_name),
             )),
@@ -329,7 +343,11 @@ impl CanisterManager {
             | Ok(Ic00Method::DeleteCanister) |
             Ok(Ic00Method::UpdateSettings)|
             Ok(Ic00Method::InstallCode) |
-            Ok(Ic00Method::SetController) => {
+            Ok(Ic00Method::SetController) |
+            Ok(Ic00Method::TakeCanisterSnapshot) |
+            Ok(Ic00Method::LoadCanisterSnapshot) |
+            Ok(Ic00Method::ListCanisterSnapshots) |
+            Ok(Ic00Method::DeleteCanisterSnapshot) => {
                 match effective_canister_id {
                     Some(canister_id) => {
                         let canister = state.canister_state(&canister_id).ok_or_else(|| UserError::new(
@@ -354,6 +372,12 @@ impl CanisterManager {
                 }
             },
 
+            // Canister logs can only be fetched via a query call.
+            Ok(Ic00Method::FetchCanisterLogs) => Err(UserError::new(
+                ErrorCode::CanisterRejectedMessage,
+                format!("{} API is only accessible in non-replicated mode", method_name),
+            )),
+
             Ok(Ic00Method::ProvisionalCreateCanisterWithCycles)
             | Ok(Ic00Method::BitcoinGetSuccessors)
             | Ok(Ic00Method::ProvisionalTopUpCanister) => {
@@ -450,6 +474,9 @@ impl CanisterManager {
         if let Some(freezing_threshold) = settings.freezing_threshold {
             canister.system_state.freeze_threshold = freezing_threshold;
         }
+        if let Some(log_visibility) = settings.log_visibility {
+            canister.system_state.log_visibility = log_visibility;
+        }
     }
 
     /// Tries to apply the requested settings on the canister identified by
@@ -975,7 +1002,7 @@ impl CanisterManager {
             .canister_state_mut(&canister_id)
             .ok_or(CanisterManagerError::CanisterNotFound(canister_id))?;
 
-        let settings = CanisterSettings::new(Some(new_controller), None, None, None, None);
+        let settings = CanisterSettings::new(Some(new_controller), None, None, None, None, None);
         self.update_settings(sender, settings, canister, round_limits)
     }
 
@@ -1035,6 +1062,11 @@ impl CanisterManager {
             .subnet_metrics
             .consumed_cycles_by_deleted_canisters += consumed_cycles_by_canister_to_delete;
 
+        // Snapshots cannot outlive the canister they were taken of.
+        state
+            .canister_snapshots
+            .remove_canister_snapshots(canister_id_to_delete);
+
         // The canister has now been removed from `ReplicatedState` and is dropped
         // once the function is out of scope.
         Ok(())
@@ -1220,6 +1252,318 @@ impl CanisterManager {
         Ok(())
     }
 
+    /// Takes a snapshot of the canister's Wasm module, heap, stable memory,
+    /// exported globals and certified data.
+    ///
+    /// If `replace_snapshot` is given, the referenced snapshot is deleted once
+    /// the new one has been taken. Otherwise, the canister must have fewer than
+    /// `MAX_SNAPSHOTS_PER_CANISTER` snapshots.
+    ///
+    /// The size of the snapshot is counted against the subnet memory. Copying
+    /// the memories of the canister is charged to the canister, see
+    /// `snapshot_copy_instructions()`.
+    pub(crate) fn take_canister_snapshot(
+        &self,
+        sender: PrincipalId,
+        canister_id: CanisterId,
+        replace_snapshot: Option<Vec<u8>>,
+        state: &mut ReplicatedState,
+        round_limits: &mut RoundLimits,
+        subnet_size: usize,
+    ) -> Result<CanisterSnapshotResponse, CanisterManagerError> {
+        let time = state.time();
+        let canister = self.validate_canister_exists(state, canister_id)?;
+        validate_controller(canister, &sender)?;
+
+        let replace_snapshot = match replace_snapshot {
+            Some(snapshot_id) => {
+                let snapshot_id = self.validate_snapshot_id(state, canister_id, &snapshot_id)?;
+                Some(snapshot_id)
+            }
+            None => {
+                if state.canister_snapshots.list_snapshots(canister_id).count()
+                    >= MAX_SNAPSHOTS_PER_CANISTER
+                {
+                    return Err(CanisterManagerError::CanisterSnapshotLimitExceeded {
+                        canister_id,
+                        limit: MAX_SNAPSHOTS_PER_CANISTER,
+                    });
+                }
+                None
+            }
+        };
+
+        let execution_state = canister.execution_state.as_ref().ok_or(
+            CanisterManagerError::CanisterSnapshotWasmModuleNotFound(canister_id),
+        )?;
+        let copy_size = memory_copy_size(
+            &execution_state.wasm_memory.page_map,
+            &execution_state.stable_memory.page_map,
+        );
+        let copy_instructions = snapshot_copy_instructions(copy_size);
+        let copy_cost = self
+            .cycles_account_manager
+            .execution_cost(copy_instructions, subnet_size);
+        self.cycles_account_manager
+            .can_withdraw_cycles(
+                &canister.system_state,
+                copy_cost,
+                canister.memory_usage(self.config.own_subnet_type),
+                canister.scheduler_state.compute_allocation,
+                subnet_size,
+            )
+            .map_err(CanisterManagerError::CanisterSnapshotNotEnoughCycles)?;
+
+        let new_size =
+            CanisterSnapshot::size_of(execution_state, &canister.system_state.certified_data);
+        let old_size = replace_snapshot
+            .as_ref()
+            .and_then(|snapshot_id| state.canister_snapshots.get(snapshot_id))
+            .map(|snapshot| snapshot.size())
+            .unwrap_or_else(|| NumBytes::from(0));
+
+        if new_size > old_size {
+            let requested = new_size - old_size;
+            round_limits
+                .subnet_available_memory
+                .try_decrement(requested, NumBytes::from(0))
+                .map_err(
+                    |_| CanisterManagerError::CanisterSnapshotMemoryOverSubscribed {
+                        requested,
+                        available: NumBytes::from(
+                            round_limits
+                                .subnet_available_memory
+                                .get_total_memory()
+                                .max(0) as u64,
+                        ),
+                    },
+                )?;
+        } else {
+            round_limits
+                .subnet_available_memory
+                .increment(old_size - new_size, NumBytes::from(0));
+        }
+
+        if let Some(snapshot_id) = replace_snapshot {
+            state.canister_snapshots.remove(&snapshot_id);
+        }
+
+        // Safe to unwrap as the existence of the canister was validated above.
+        let canister = state.canister_state_mut(&canister_id).unwrap();
+        self.charge_for_snapshot_copy(canister, copy_cost, copy_instructions, round_limits);
+        // Safe to unwrap as the existence of the execution state was validated
+        // above.
+        let snapshot = CanisterSnapshot::from_canister(canister, time).unwrap();
+        let snapshot_id = SnapshotId::new(canister_id, canister.system_state.next_snapshot_id);
+        canister.system_state.next_snapshot_id += 1;
+
+        let response = CanisterSnapshotResponse::new(
+            snapshot_id.to_vec(),
+            time.as_nanos_since_unix_epoch(),
+            new_size.get(),
+        );
+        state.canister_snapshots.insert(snapshot_id, snapshot);
+        state.metadata.heap_delta_estimate += copy_size;
+        Ok(response)
+    }
+
+    /// Replaces the execution state and the certified data of the canister
+    /// with the contents of the given snapshot.
+    ///
+    /// The canister must be stopped, because open call contexts may refer to
+    /// callbacks that do not exist in the Wasm module of the snapshot.
+    ///
+    /// Copying the memories of the snapshot is charged to the canister, see
+    /// `snapshot_copy_instructions()`.
+    pub(crate) fn load_canister_snapshot(
+        &self,
+        sender: PrincipalId,
+        canister_id: CanisterId,
+        snapshot_id: &[u8],
+        state: &mut ReplicatedState,
+        round_limits: &mut RoundLimits,
+        subnet_size: usize,
+    ) -> Result<(), CanisterManagerError> {
+        let canister = self.validate_canister_exists(state, canister_id)?;
+        validate_controller(canister, &sender)?;
+        if canister.status() != CanisterStatusType::Stopped {
+            return Err(CanisterManagerError::LoadCanisterSnapshotNotStopped(
+                canister_id,
+            ));
+        }
+        let snapshot_id = self.validate_snapshot_id(state, canister_id, snapshot_id)?;
+        // Safe to unwrap as the snapshot id was validated above.
+        let snapshot = Arc::clone(state.canister_snapshots.get(&snapshot_id).unwrap());
+
+        let copy_size = memory_copy_size(&snapshot.wasm_memory, &snapshot.stable_memory);
+        let copy_instructions = snapshot_copy_instructions(copy_size);
+        let copy_cost = self
+            .cycles_account_manager
+            .execution_cost(copy_instructions, subnet_size);
+        self.cycles_account_manager
+            .can_withdraw_cycles(
+                &canister.system_state,
+                copy_cost,
+                canister.memory_usage(self.config.own_subnet_type),
+                canister.scheduler_state.compute_allocation,
+                subnet_size,
+            )
+            .map_err(CanisterManagerError::CanisterSnapshotNotEnoughCycles)?;
+
+        let own_subnet_type = self.config.own_subnet_type;
+        // Safe to unwrap as the existence of the canister was validated above.
+        let canister = state.canister_state_mut(&canister_id).unwrap();
+        let old_mem = canister
+            .memory_allocation()
+            .bytes()
+            .max(canister.memory_usage(own_subnet_type));
+
+        let last_executed_round = canister
+            .execution_state
+            .as_ref()
+            .map(|es| es.last_executed_round)
+            .unwrap_or_else(|| ExecutionRound::from(0));
+        let canister_root = canister_layout(&PathBuf::from("NOT_USED"), &canister_id).raw_path();
+        let old_execution_state = canister
+            .execution_state
+            .replace(snapshot.to_execution_state(canister_root, last_executed_round));
+
+        let new_usage = canister.memory_usage(own_subnet_type);
+        if let MemoryAllocation::Reserved(reserved) = canister.memory_allocation() {
+            if new_usage > reserved {
+                canister.execution_state = old_execution_state;
+                return Err(CanisterManagerError::NotEnoughMemoryAllocationGiven {
+                    canister_id,
+                    memory_allocation_given: canister.memory_allocation(),
+                    memory_usage_needed: new_usage,
+                });
+            }
+        }
+        let new_mem = canister.memory_allocation().bytes().max(new_usage);
+
+        if new_mem > old_mem {
+            if round_limits
+                .subnet_available_memory
+                .try_decrement(new_mem - old_mem, NumBytes::from(0))
+                .is_err()
+            {
+                canister.execution_state = old_execution_state;
+                return Err(CanisterManagerError::CanisterSnapshotMemoryOverSubscribed {
+                    requested: new_mem - old_mem,
+                    available: NumBytes::from(
+                        round_limits
+                            .subnet_available_memory
+                            .get_total_memory()
+                            .max(0) as u64,
+                    ),
+                });
+            }
+        } else {
+            round_limits
+                .subnet_available_memory
+                .increment(old_mem - new_mem, NumBytes::from(0));
+        }
+
+        self.charge_for_snapshot_copy(canister, copy_cost, copy_instructions, round_limits);
+        canister.system_state.certified_data = snapshot.certified_data.clone();
+        canister.system_state.canister_version += 1;
+        state.metadata.heap_delta_estimate += copy_size;
+        Ok(())
+    }
+
+    /// Consumes the cycles and round instructions for copying memories into
+    /// or out of a snapshot. The caller must have checked with
+    /// `can_withdraw_cycles()` that the canister can pay `cost`.
+    fn charge_for_snapshot_copy(
+        &self,
+        canister: &mut CanisterState,
+        cost: Cycles,
+        instructions: NumInstructions,
+        round_limits: &mut RoundLimits,
+    ) {
+        // The freezing threshold was checked before, so consuming the cycles
+        // cannot fail.
+        let threshold = Cycles::zero();
+        if let Err(err) = self.cycles_account_manager.consume_with_threshold(
+            &mut canister.system_state,
+            cost,
+            threshold,
+        ) {
+            fatal!(
+                self.log,
+                "Failed to charge canister {} for a snapshot copy: {}",
+                canister.canister_id(),
+                err
+            );
+        }
+        round_limits.instructions -= as_round_instructions(instructions);
+    }
+
+    /// Lists the snapshots of the canister.
+    pub(crate) fn list_canister_snapshots(
+        &self,
+        sender: PrincipalId,
+        canister_id: CanisterId,
+        state: &ReplicatedState,
+    ) -> Result<Vec<CanisterSnapshotResponse>, CanisterManagerError> {
+        let canister = self.validate_canister_exists(state, canister_id)?;
+        validate_controller(canister, &sender)?;
+
+        Ok(state
+            .canister_snapshots
+            .list_snapshots(canister_id)
+            .map(|(snapshot_id, snapshot)| {
+                CanisterSnapshotResponse::new(
+                    snapshot_id.to_vec(),
+                    snapshot.taken_at_timestamp.as_nanos_since_unix_epoch(),
+                    snapshot.size().get(),
+                )
+            })
+            .collect())
+    }
+
+    /// Deletes a snapshot of the canister and releases its memory.
+    pub(crate) fn delete_canister_snapshot(
+        &self,
+        sender: PrincipalId,
+        canister_id: CanisterId,
+        snapshot_id: &[u8],
+        state: &mut ReplicatedState,
+        round_limits: &mut RoundLimits,
+    ) -> Result<(), CanisterManagerError> {
+        let canister = self.validate_canister_exists(state, canister_id)?;
+        validate_controller(canister, &sender)?;
+        let snapshot_id = self.validate_snapshot_id(state, canister_id, snapshot_id)?;
+
+        if let Some(snapshot) = state.canister_snapshots.remove(&snapshot_id) {
+            round_limits
+                .subnet_available_memory
+                .increment(snapshot.size(), NumBytes::from(0));
+        }
+        Ok(())
+    }
+
+    /// Decodes the snapshot id and checks that the snapshot exists and belongs
+    /// to the given canister.
+    fn validate_snapshot_id(
+        &self,
+        state: &ReplicatedState,
+        canister_id: CanisterId,
+        snapshot_id: &[u8],
+    ) -> Result<SnapshotId, CanisterManagerError> {
+        let snapshot_id = SnapshotId::try_from(snapshot_id)
+            .map_err(|err| CanisterManagerError::InvalidSnapshotId(err.0))?;
+        if snapshot_id.canister_id() != canister_id
+            || state.canister_snapshots.get(&snapshot_id).is_none()
+        {
+            return Err(CanisterManagerError::CanisterSnapshotNotFound {
+                canister_id,
+                snapshot_id: snapshot_id.to_string(),
+            });
+        }
+        Ok(snapshot_id)
+    }
+
     fn validate_canister_is_stopped(
         &self,
         canister: &CanisterState,
@@ -1315,6 +1659,22 @@ pub(crate) enum CanisterManagerError {
     CanisterNotHostedBySubnet {
         message: String,
     },
+    InvalidSnapshotId(String),
+    CanisterSnapshotNotFound {
+        canister_id: CanisterId,
+        snapshot_id: String,
+    },
+    CanisterSnapshotLimitExceeded {
+        canister_id: CanisterId,
+        limit: usize,
+    },
+    CanisterSnapshotWasmModuleNotFound(CanisterId),
+    CanisterSnapshotNotEnoughCycles(CanisterOutOfCyclesError),
+    CanisterSnapshotMemoryOverSubscribed {
+        requested: NumBytes,
+        available: NumBytes,
+    },
+    LoadCanisterSnapshotNotStopped(CanisterId),
 }
 
 impl From<CanisterManagerError> for UserError {
@@ -1471,6 +1831,55 @@ impl From<CanisterManagerError> for UserError {
                     format!("Unsuccessful validation of specified ID: {}", message),
                 )
             }
+            InvalidSnapshotId(message) => {
+                Self::new(
+                    ErrorCode::InvalidManagementPayload,
+                    format!("Invalid snapshot id: {}", message),
+                )
+            }
+            CanisterSnapshotNotFound { canister_id, snapshot_id } => {
+                Self::new(
+                    ErrorCode::CanisterContractViolation,
+                    format!("Could not find the snapshot ID {} for canister {}.", snapshot_id, canister_id),
+                )
+            }
+            CanisterSnapshotLimitExceeded { canister_id, limit } => {
+                Self::new(
+                    ErrorCode::CanisterContractViolation,
+                    format!("Canister {} has reached the maximum number of {} snapshots. Delete or replace an existing snapshot.", canister_id, limit),
+                )
+            }
+            CanisterSnapshotWasmModuleNotFound(canister_id) => {
+                Self::new(
+                    ErrorCode::CanisterWasmModuleNotFound,
+                    format!("Cannot take a snapshot of canister {} because it has no Wasm module installed.", canister_id),
+                )
+            }
+            CanisterSnapshotNotEnoughCycles(err) => {
+                Self::new(
+                    ErrorCode::CanisterOutOfCycles,
+                    format!("Copying the canister memory for a snapshot failed with `{}`", err),
+                )
+            }
+            CanisterSnapshotMemoryOverSubscribed { requested, available } => {
+                Self::new(
+                    ErrorCode::SubnetOversubscribed,
+                    format!(
+                        "Canister snapshot requires {}MiB but the Subnet's remaining memory capacity is {}MiB",
+                        requested.get() / (1024 * 1024),
+                        available.get() / (1024 * 1024),
+                    )
+                )
+            }
+            LoadCanisterSnapshotNotStopped(canister_id) => {
+                Self::new(
+                    ErrorCode::CanisterNotStopped,
+                    format!(
+                        "Canister {} must be stopped before a snapshot is loaded.",
+                        canister_id,
+                    )
+                )
+            }
         }
     }
 }
@@ -1576,6 +1985,7 @@ struct ValidatedCanisterSettings {
     pub compute_allocation: Option<ComputeAllocation>,
     pub memory_allocation: Option<MemoryAllocation>,
     pub freezing_threshold: Option<NumSeconds>,
+    pub log_visibility: Option<LogVisibility>,
 }
 
 impl TryFrom<(CanisterSettings, usize)> for ValidatedCanisterSettings {
@@ -1611,6 +2021,7 @@ impl TryFrom<(CanisterSettings, usize)> for ValidatedCanisterSettings {
             compute_allocation: settings.compute_allocation(),
             memory_allocation: settings.memory_allocation(),
             freezing_threshold: settings.freezing_threshold(),
+            log_visibility: settings.log_visibility(),
         })
     }
 }
@@ -3454,6 +3454,7 @@ fn lower_memory_allocation_than_usage_fails() {
             None,
             Some(MemoryAllocation::try_from(NumBytes::from(2)).unwrap()),
             None,
+            None,
         );
 
         let canister = state.canister_state_mut(&canister_id).unwrap();
@@ -3484,6 +3485,7 @@ fn test_install_when_updating_memory_allocation_via_canister_settings() {
             None,
             Some(MemoryAllocation::try_from(NumBytes::from(2)).unwrap()),
             None,
+            None,
         );
         let canister_id = canister_manager
             .create_canister(
@@ -3529,6 +3531,7 @@ fn test_install_when_updating_memory_allocation_via_canister_settings() {
             None,
             Some(MemoryAllocation::try_from(NumBytes::from(MEMORY_CAPACITY.get() / 2)).unwrap()),
             None,
+            None,
         );
 
         let canister = state.canister_state_mut(&canister_id).unwrap();
@@ -3576,6 +3579,7 @@ fn test_upgrade_when_updating_memory_allocation_via_canister_settings() {
                 MemoryAllocation::try_from(NumBytes::from(WASM_PAGE_SIZE_IN_BYTES + 100)).unwrap(),
             ),
             None,
+            None,
         );
         let wat = r#"
         (module
@@ -3654,6 +3658,7 @@ fn test_upgrade_when_updating_memory_allocation_via_canister_settings() {
                     .unwrap(),
             ),
             None,
+            None,
         );
 
         let canister = state.canister_state_mut(&canister_id).unwrap();
@@ -3735,7 +3740,7 @@ fn test_install_when_setting_memory_allocation_to_zero() {
         let wasm = ic_test_utilities::universal_canister::UNIVERSAL_CANISTER_WASM.to_vec();
 
         let sender = canister_test_id(100).get();
-        let settings = CanisterSettings::new(None, None, None, None, None);
+        let settings = CanisterSettings::new(None, None, None, None, None, None);
         let canister_id = canister_manager
             .create_canister(
                 sender,
@@ -3757,6 +3762,7 @@ fn test_install_when_setting_memory_allocation_to_zero() {
             None,
             Some(MemoryAllocation::try_from(NumBytes::from(0)).unwrap()),
             None,
+            None,
         );
 
         let canister = state.canister_state_mut(&canister_id).unwrap();
@@ -3810,6 +3816,7 @@ fn test_upgrade_when_setting_memory_allocation_to_zero() {
             None,
             Some(MemoryAllocation::try_from(NumBytes::from(MEMORY_CAPACITY.get() / 2)).unwrap()),
             None,
+            None,
         );
         let canister_id = canister_manager
             .create_canister(
@@ -3850,6 +3857,7 @@ fn test_upgrade_when_setting_memory_allocation_to_zero() {
             None,
             Some(MemoryAllocation::try_from(NumBytes::from(0)).unwrap()),
             None,
+            None,
         );
 
         let canister = state.canister_state_mut(&canister_id).unwrap();
@@ -423,6 +423,11 @@ pub fn apply_canister_state_changes(
     subnet_id: SubnetId,
     log: &ReplicaLogger,
 ) {
+    // Log records are kept even if the execution failed, so that the canister
+    // owner can see why it trapped.
+    system_state
+        .canister_log
+        .append_delta(std::mem::take(&mut output.canister_log));
     if let Some(CanisterStateChanges {
         globals,
         wasm_memory,
@@ -464,6 +464,11 @@ impl InstallCodeHelper {
             }
         };
 
+        self.canister
+            .system_state
+            .canister_log
+            .append_delta(output.canister_log);
+
         if let Some(CanisterStateChanges {
             globals,
             wasm_memory,
@@ -15,7 +15,8 @@ use ic_types::ingress::WasmResult;
 use ic_types::methods::{FuncRef, WasmMethod};
 use ic_types::{Cycles, NumInstructions, Time};
 
-// Execute non replicated query.
+// Execute non replicated query. The returned flag tells whether the query
+// read the data certificate.
 #[allow(clippy::too_many_arguments)]
 pub fn execute_non_replicated_query(
     query_kind: NonReplicatedQueryKind,
@@ -32,6 +33,7 @@ pub fn execute_non_replicated_query(
     CanisterState,
     NumInstructions,
     Result<Option<WasmResult>, UserError>,
+    bool,
 ) {
     // Validate that the canister is running.
     if let Err(err) = validate_canister(&canister) {
@@ -39,6 +41,7 @@ pub fn execute_non_replicated_query(
             canister,
             execution_parameters.instruction_limits.message(),
             Err(err),
+            false,
         );
     }
 
@@ -51,6 +54,7 @@ pub fn execute_non_replicated_query(
             canister,
             execution_parameters.instruction_limits.message(),
             Err(err.into_user_error(&canister_id)),
+            false,
         );
     }
 
@@ -111,5 +115,10 @@ pub fn execute_non_replicated_query(
     let result = output
         .wasm_result
         .map_err(|err| err.into_user_error(&canister.canister_id()));
-    (canister, output.num_instructions_left, result)
+    (
+        canister,
+        output.num_instructions_left,
+        result,
+        output.data_certificate_accessed,
+    )
 }
@@ -2,8 +2,8 @@ use crate::execution::system_task::CanisterSystemTaskError;
 use crate::util::process_stopping_canisters;
 use crate::{
     execute_canister, CompilationCostHandling, ExecuteMessageResult, ExecutionEnvironment,
-    ExecutionResponse, Hypervisor, IngressHistoryWriterImpl, InternalHttpQueryHandler,
-    RoundInstructions, RoundLimits,
+    ExecutionResponse, ExecutionTracer, Hypervisor, IngressHistoryWriterImpl,
+    InternalHttpQueryHandler, RoundInstructions, RoundLimits,
 };
 use ic_base_types::{NumBytes, NumSeconds, PrincipalId, SubnetId};
 use ic_config::subnet_config::SchedulerConfig;
@@ -1281,6 +1281,7 @@ pub struct ExecutionTestBuilder {
     bitcoin_get_successors_follow_up_responses: BTreeMap<CanisterId, Vec<Vec<u8>>>,
     cost_to_compile_wasm_instruction: u64,
     max_instructions_per_composite_query_call: NumInstructions,
+    execution_tracer: Option<Arc<dyn ExecutionTracer>>,
 }
 
 impl Default for ExecutionTestBuilder {
@@ -1330,6 +1331,7 @@ impl Default for ExecutionTestBuilder {
                 .cost_to_compile_wasm_instruction
                 .get(),
             max_instructions_per_composite_query_call,
+            execution_tracer: None,
         }
     }
 }
@@ -1534,6 +1536,11 @@ impl ExecutionTestBuilder {
         self
     }
 
+    pub fn with_execution_tracer(mut self, execution_tracer: Arc<dyn ExecutionTracer>) -> Self {
+        self.execution_tracer = Some(execution_tracer);
+        self
+    }
+
     pub fn build_with_routing_table_for_specified_ids(self) -> ExecutionTest {
         let routing_table =
             get_routing_table_with_specified_ids_allocation_range(self.own_subnet_id).unwrap();
@@ -1690,7 +1697,8 @@ impl ExecutionTestBuilder {
             100,
             config.clone(),
             Arc::clone(&cycles_account_manager),
-        );
+        )
+        .with_execution_tracer(self.execution_tracer.clone());
         let query_handler = InternalHttpQueryHandler::new(
             self.log.clone(),
             hypervisor,
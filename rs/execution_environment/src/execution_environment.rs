@@ -15,6 +15,9 @@ use crate::{
     execution_environment_metrics::{
         ExecutionEnvironmentMetrics, SUBMITTED_OUTCOME_LABEL, SUCCESS_STATUS_LABEL,
     },
+    execution_trace::{
+        ExecutedMessage, ExecutionStart, ExecutionTracer, MessageExecutionTrace, MessageOutcome,
+    },
     hypervisor::Hypervisor,
     util::candid_error_to_user_error,
     NonReplicatedQueryKind,
@@ -29,10 +32,12 @@ use ic_cycles_account_manager::{CyclesAccountManager, IngressInductionCost};
 use ic_error_types::{ErrorCode, RejectCode, UserError};
 use ic_ic00_types::{
     CanisterHttpRequestArgs, CanisterIdRecord, CanisterSettingsArgs,
-    ComputeInitialEcdsaDealingsArgs, CreateCanisterArgs, ECDSAPublicKeyArgs,
-    ECDSAPublicKeyResponse, EcdsaKeyId, EmptyBlob, InstallCodeArgs, Method as Ic00Method,
+    ComputeInitialEcdsaDealingsArgs, CreateCanisterArgs, DeleteCanisterSnapshotArgs,
+    ECDSAPublicKeyArgs, ECDSAPublicKeyResponse, EcdsaKeyId, EmptyBlob, InstallCodeArgs,
+    ListCanisterSnapshotsResponse, LoadCanisterSnapshotArgs, Method as Ic00Method,
     Payload as Ic00Payload, ProvisionalCreateCanisterWithCyclesArgs, ProvisionalTopUpCanisterArgs,
-    SetControllerArgs, SetupInitialDKGArgs, SignWithECDSAArgs, UpdateSettingsArgs, IC_00,
+    SetControllerArgs, SetupInitialDKGArgs, SignWithECDSAArgs, TakeCanisterSnapshotArgs,
+    UpdateSettingsArgs, IC_00,
 };
 use ic_interfaces::{
     execution_environment::{
@@ -264,6 +269,7 @@ pub struct ExecutionEnvironment {
     own_subnet_id: SubnetId,
     own_subnet_type: SubnetType,
     paused_execution_registry: Arc<Mutex<PausedExecutionRegistry>>,
+    execution_tracer: Option<Arc<dyn ExecutionTracer>>,
 }
 
 /// This is a helper enum that indicates whether the current DTS execution of
@@ -321,9 +327,41 @@ impl ExecutionEnvironment {
             own_subnet_id,
             own_subnet_type,
             paused_execution_registry: Default::default(),
+            execution_tracer: None,
+        }
+    }
+
+    /// Reports every executed canister or subnet message to `execution_tracer`.
+    pub fn with_execution_tracer(
+        mut self,
+        execution_tracer: Option<Arc<dyn ExecutionTracer>>,
+    ) -> Self {
+        self.execution_tracer = execution_tracer;
+        self
+    }
+
+    pub(crate) fn execution_tracer(&self) -> Option<&Arc<dyn ExecutionTracer>> {
+        self.execution_tracer.as_ref()
+    }
+
+    /// Reports the trace built by `trace` if a tracer is installed.
+    fn trace(&self, trace: impl FnOnce() -> MessageExecutionTrace) {
+        if let Some(tracer) = &self.execution_tracer {
+            tracer.on_message_executed(&trace());
         }
     }
 
+    /// Starts measuring an execution on `canister` if a tracer is installed.
+    fn start_execution(
+        &self,
+        round_limits: &RoundLimits,
+        canister: &CanisterState,
+    ) -> Option<ExecutionStart> {
+        self.execution_tracer
+            .as_ref()
+            .map(|_| ExecutionStart::new(round_limits, canister))
+    }
+
     /// Look up the current amount of memory available on the subnet.
     pub fn subnet_available_memory(&self, state: &ReplicatedState) -> SubnetAvailableMemory {
         let (memory_taken, message_memory_taken) = state.total_and_message_memory_taken();
@@ -352,6 +390,16 @@ impl ExecutionEnvironment {
 
         let mut msg = match msg {
             CanisterInputMessage::Response(response) => {
+                self.trace(|| {
+                    MessageExecutionTrace::subnet_message(
+                        ExecutedMessage {
+                            kind: "response",
+                            method: None,
+                            caller: Some(response.respondent.get()),
+                        },
+                        MessageOutcome::from_payload(&response.response_payload),
+                    )
+                });
                 let context = state
                     .metadata
                     .subnet_call_context_manager
@@ -418,6 +466,15 @@ impl ExecutionEnvironment {
 
                     if !reject_message.is_empty() {
                         use ic_types::messages;
+                        self.trace(|| {
+                            MessageExecutionTrace::subnet_message(
+                                ExecutedMessage::from(&msg),
+                                MessageOutcome::Reject {
+                                    code: RejectCode::CanisterReject as u64,
+                                    message: reject_message.clone(),
+                                },
+                            )
+                        });
                         state.push_subnet_output_response(
                             Response {
                                 originator: request.sender,
@@ -806,6 +863,85 @@ impl ExecutionEnvironment {
                 }
             }
 
+            Ok(Ic00Method::TakeCanisterSnapshot) => {
+                let res = match TakeCanisterSnapshotArgs::decode(payload) {
+                    Err(err) => Err(candid_error_to_user_error(err)),
+                    Ok(args) => self
+                        .canister_manager
+                        .take_canister_snapshot(
+                            *msg.sender(),
+                            args.get_canister_id(),
+                            args.replace_snapshot,
+                            &mut state,
+                            round_limits,
+                            registry_settings.subnet_size,
+                        )
+                        .map(|response| response.encode())
+                        .map_err(|err| err.into()),
+                };
+                Some((res, msg.take_cycles()))
+            }
+
+            Ok(Ic00Method::LoadCanisterSnapshot) => {
+                let res = match LoadCanisterSnapshotArgs::decode(payload) {
+                    Err(err) => Err(candid_error_to_user_error(err)),
+                    Ok(args) => self
+                        .canister_manager
+                        .load_canister_snapshot(
+                            *msg.sender(),
+                            args.get_canister_id(),
+                            &args.snapshot_id,
+                            &mut state,
+                            round_limits,
+                            registry_settings.subnet_size,
+                        )
+                        .map(|()| EmptyBlob.encode())
+                        .map_err(|err| err.into()),
+                };
+                Some((res, msg.take_cycles()))
+            }
+
+            Ok(Ic00Method::ListCanisterSnapshots) => {
+                let res = match CanisterIdRecord::decode(payload) {
+                    Err(err) => Err(candid_error_to_user_error(err)),
+                    Ok(args) => self
+                        .canister_manager
+                        .list_canister_snapshots(*msg.sender(), args.get_canister_id(), &state)
+                        .map(|snapshots| ListCanisterSnapshotsResponse(snapshots).encode())
+                        .map_err(|err| err.into()),
+                };
+                Some((res, msg.take_cycles()))
+            }
+
+            Ok(Ic00Method::DeleteCanisterSnapshot) => {
+                let res = match DeleteCanisterSnapshotArgs::decode(payload) {
+                    Err(err) => Err(candid_error_to_user_error(err)),
+                    Ok(args) => self
+                        .canister_manager
+                        .delete_canister_snapshot(
+                            *msg.sender(),
+                            args.get_canister_id(),
+                            &args.snapshot_id,
+                            &mut state,
+                            round_limits,
+                        )
+                        .map(|()| EmptyBlob.encode())
+                        .map_err(|err| err.into()),
+                };
+                Some((res, msg.take_cycles()))
+            }
+
+            Ok(Ic00Method::FetchCanisterLogs) => Some((
+                Err(UserError::new(
+                    ErrorCode::CanisterRejectedMessage,
+                    format!(
+                        "{} API is only accessible in non-replicated mode",
+                        Ic00Method::FetchCanisterLogs
+                    ),
+                )),
+                msg.take_cycles(),
+            )),
+
             Ok(Ic00Method::ProvisionalCreateCanisterWithCycles) => {
                 let res = match ProvisionalCreateCanisterWithCyclesArgs::decode(payload) {
                     Err(err) => Err(candid_error_to_user_error(err)),
@@ -869,6 +1005,13 @@ impl ExecutionEnvironment {
                 Some(res)
             }
 
+            Ok(Ic00Method::BitcoinGetBlockHeaders) => {
+                let cycles = msg.take_cycles();
+                let res =
+                    crate::bitcoin::get_block_headers(msg.method_payload(), &mut state, cycles);
+                Some(res)
+            }
+
             Ok(Ic00Method::BitcoinSendTransaction) => {
                 let cycles = msg.take_cycles();
                 let res =
@@ -927,9 +1070,21 @@ impl ExecutionEnvironment {
         // these cases.
         let state = match result {
             Some((res, refund)) => {
+                self.trace(|| {
+                    MessageExecutionTrace::subnet_message(
+                        ExecutedMessage::from(&msg),
+                        MessageOutcome::from_subnet_result(&res),
+                    )
+                });
                 self.finish_subnet_message_execution(state, msg, res, refund, timer)
             }
             None => {
+                self.trace(|| {
+                    MessageExecutionTrace::subnet_message(
+                        ExecutedMessage::from(&msg),
+                        MessageOutcome::NoResponse,
+                    )
+                });
                 // This scenario happens when calling ic00::stop_canister on a
                 // canister that is already stopping. In this scenario, the
                 // request is not responded to until the canister has fully
@@ -1835,8 +1990,14 @@ impl ExecutionEnvironment {
             Ok(result) => result,
             Err(err) => {
                 let refund = msg.take_cycles();
-                let state =
-                    self.finish_subnet_message_execution(state, msg, Err(err), refund, timer);
+                let result = Err(err);
+                self.trace(|| {
+                    MessageExecutionTrace::subnet_message(
+                        ExecutedMessage::from(&msg),
+                        MessageOutcome::from_subnet_result(&result),
+                    )
+                });
+                let state = self.finish_subnet_message_execution(state, msg, result, refund, timer);
                 return (state, Some(NumInstructions::from(0)));
             }
         };
@@ -1870,6 +2031,9 @@ impl ExecutionEnvironment {
 
         let execution_parameters =
             self.execution_parameters(&old_canister, instruction_limits, ExecutionMode::Replicated);
+        let execution_start = self
+            .start_execution(round_limits, &old_canister)
+            .map(|start| (ExecutedMessage::from(&msg), start));
 
         let dts_result = self.canister_manager.install_code_dts(
             install_context,
@@ -1885,7 +2049,14 @@ impl ExecutionEnvironment {
             self.metrics.execution_cycles_refund_error_counter(),
             subnet_size,
         );
-        self.process_install_code_result(state, dts_result, dts_status, timer)
+        self.process_install_code_result(
+            state,
+            dts_result,
+            dts_status,
+            execution_start,
+            round_limits,
+            timer,
+        )
     }
 
     /// Processes the result of install code message that was executed using
@@ -1894,12 +2065,15 @@ impl ExecutionEnvironment {
     /// - If the execution is paused, then it enqueues it to the task queue of
     ///   the canister.
     /// In both cases, the functions gets the canister from the result and adds
-    /// it to the replicated state.
+    /// it to the replicated state. The execution is traced if it was started
+    /// with an `execution_start`.
     fn process_install_code_result(
         &self,
         mut state: ReplicatedState,
         dts_result: DtsInstallCodeResult,
         dts_status: DtsInstallCodeStatus,
+        execution_start: Option<(ExecutedMessage, ExecutionStart)>,
+        round_limits: &RoundLimits,
         timer: Timer,
     ) -> (ReplicatedState, Option<NumInstructions>) {
         let execution_duration = timer.elapsed();
@@ -1911,6 +2085,9 @@ impl ExecutionEnvironment {
                 result,
             } => {
                 let canister_id = canister.canister_id();
+                let heap_delta = result
+                    .as_ref()
+                    .map_or(NumBytes::from(0), |result| result.heap_delta);
                 let result = match result {
                     Ok(result) => {
                         state.metadata.heap_delta_estimate += result.heap_delta;
@@ -1940,6 +2117,17 @@ impl ExecutionEnvironment {
                         Err(err.into())
                     }
                 };
+                if let Some((executed_message, start)) = execution_start {
+                    self.trace(|| {
+                        start.finish(
+                            executed_message,
+                            &canister,
+                            round_limits,
+                            heap_delta,
+                            MessageOutcome::from_subnet_result(&result),
+                        )
+                    });
+                }
                 state.put_canister_state(canister);
                 let refund = message.take_cycles();
                 let state =
@@ -1971,6 +2159,17 @@ impl ExecutionEnvironment {
                     }
                 };
 
+                if let Some((executed_message, start)) = execution_start {
+                    self.trace(|| {
+                        start.finish(
+                            executed_message,
+                            &canister,
+                            round_limits,
+                            NumBytes::from(0),
+                            MessageOutcome::NoResponse,
+                        )
+                    });
+                }
                 state.put_canister_state(canister);
                 (state, None)
             }
@@ -2015,6 +2214,9 @@ impl ExecutionEnvironment {
                 let timer = Timer::start();
                 let paused = self.take_paused_install_code(id).unwrap();
                 let canister = state.take_canister_state(canister_id).unwrap();
+                let execution_start = self
+                    .start_execution(round_limits, &canister)
+                    .map(|start| (ExecutedMessage::task("paused install_code"), start));
                 let round = RoundContext {
                     network_topology: &state.metadata.network_topology,
                     hypervisor: &self.hypervisor,
@@ -2027,7 +2229,14 @@ impl ExecutionEnvironment {
                 };
                 let dts_result = paused.resume(canister, round, round_limits);
                 let dts_status = DtsInstallCodeStatus::ResumingPausedOrAbortedExecution;
-                self.process_install_code_result(state, dts_result, dts_status, timer)
+                self.process_install_code_result(
+                    state,
+                    dts_result,
+                    dts_status,
+                    execution_start,
+                    round_limits,
+                    timer,
+                )
             }
             ExecutionTask::AbortedInstallCode {
                 message,
@@ -2286,6 +2495,9 @@ fn execute_message(
     subnet_size: usize,
 ) -> ExecuteCanisterResult {
     let msg_info = message.to_string();
+    let execution_start = exec_env
+        .start_execution(round_limits, &canister)
+        .map(|start| (ExecutedMessage::from(&message), start));
     let result = exec_env.execute_canister_message(
         canister,
         instruction_limits,
@@ -2297,7 +2509,21 @@ fn execute_message(
         round_limits,
         subnet_size,
     );
+    let outcome = execution_start
+        .as_ref()
+        .map(|_| MessageOutcome::from_result(&result));
     let (canister, instructions_used, heap_delta, ingress_status) = exec_env.process_result(result);
+    if let (Some((executed_message, start)), Some(outcome)) = (execution_start, outcome) {
+        exec_env.trace(|| {
+            start.finish(
+                executed_message,
+                &canister,
+                round_limits,
+                heap_delta,
+                outcome,
+            )
+        });
+    }
     ExecuteCanisterResult {
         canister,
         instructions_used,
@@ -2341,6 +2567,7 @@ pub fn execute_canister(
                     max_instructions_per_message_without_dts,
                     max_instructions_per_message_without_dts,
                 );
+                let execution_start = exec_env.start_execution(round_limits, &canister);
                 let (canister, instructions_used, result) = exec_env.execute_canister_system_task(
                     canister,
                     SystemMethod::CanisterHeartbeat,
@@ -2351,6 +2578,20 @@ pub fn execute_canister(
                     subnet_size,
                     &exec_env.log,
                 );
+                if let Some(start) = execution_start {
+                    exec_env.trace(|| {
+                        start.finish(
+                            ExecutedMessage::task("heartbeat"),
+                            &canister,
+                            round_limits,
+                            *result.as_ref().unwrap_or(&NumBytes::from(0)),
+                            result.as_ref().err().map_or(
+                                MessageOutcome::NoResponse,
+                                MessageOutcome::from_task_error,
+                            ),
+                        )
+                    });
+                }
                 let heap_delta = result.unwrap_or_else(|_| NumBytes::from(0));
                 ExecuteCanisterResult {
                     canister,
@@ -2369,6 +2610,7 @@ pub fn execute_canister(
                 );
                 // The global timer is one-off
                 canister.system_state.global_timer = CanisterTimer::Inactive;
+                let execution_start = exec_env.start_execution(round_limits, &canister);
                 let (canister, instructions_used, result) = exec_env.execute_canister_system_task(
                     canister,
                     SystemMethod::CanisterGlobalTimer,
@@ -2379,6 +2621,20 @@ pub fn execute_canister(
                     subnet_size,
                     &exec_env.log,
                 );
+                if let Some(start) = execution_start {
+                    exec_env.trace(|| {
+                        start.finish(
+                            ExecutedMessage::task("global timer"),
+                            &canister,
+                            round_limits,
+                            *result.as_ref().unwrap_or(&NumBytes::from(0)),
+                            result.as_ref().err().map_or(
+                                MessageOutcome::NoResponse,
+                                MessageOutcome::from_task_error,
+                            ),
+                        )
+                    });
+                }
                 let heap_delta = result.unwrap_or_else(|_| NumBytes::from(0));
                 ExecuteCanisterResult {
                     canister,
@@ -2400,9 +2656,24 @@ pub fn execute_canister(
                     log: &exec_env.log,
                     time,
                 };
+                let execution_start = exec_env.start_execution(round_limits, &canister);
                 let result = paused.resume(canister, round_context, round_limits, subnet_size);
+                let outcome = execution_start
+                    .as_ref()
+                    .map(|_| MessageOutcome::from_result(&result));
                 let (canister, instructions_used, heap_delta, ingress_status) =
                     exec_env.process_result(result);
+                if let (Some(start), Some(outcome)) = (execution_start, outcome) {
+                    exec_env.trace(|| {
+                        start.finish(
+                            ExecutedMessage::task("paused execution"),
+                            &canister,
+                            round_limits,
+                            heap_delta,
+                            outcome,
+                        )
+                    });
+                }
                 ExecuteCanisterResult {
                     canister,
                     instructions_used,
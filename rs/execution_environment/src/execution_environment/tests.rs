@@ -1,21 +1,25 @@
 use assert_matches::assert_matches;
 use candid::{Decode, Encode};
 
+use crate::canister_manager::snapshot_copy_instructions;
 use crate::execution::test_utilities::{
     assert_empty_reply, check_ingress_status, get_reply, ExecutionTest, ExecutionTestBuilder,
 };
 use ic_base_types::{NumBytes, NumSeconds};
 use ic_error_types::{ErrorCode, RejectCode, UserError};
 use ic_ic00_types::{
-    self as ic00, CanisterHttpRequestArgs, CanisterIdRecord, CanisterStatusResultV2,
-    CanisterStatusType, EcdsaCurve, EcdsaKeyId, EmptyBlob, HttpMethod, Method,
-    Payload as Ic00Payload, ProvisionalCreateCanisterWithCyclesArgs, ProvisionalTopUpCanisterArgs,
-    TransformContext, TransformFunc, IC_00,
+    self as ic00, CanisterHttpRequestArgs, CanisterIdRecord, CanisterSettingsArgs,
+    CanisterSnapshotArgs, CanisterSnapshotResponse, CanisterStatusResultV2, CanisterStatusType,
+    EcdsaCurve, EcdsaKeyId, EmptyBlob, FetchCanisterLogsRequest, FetchCanisterLogsResponse,
+    HttpMethod, ListCanisterSnapshotsResponse, LogVisibility, Method, Payload as Ic00Payload,
+    ProvisionalCreateCanisterWithCyclesArgs, ProvisionalTopUpCanisterArgs,
+    TakeCanisterSnapshotArgs, TransformContext, TransformFunc, UpdateSettingsArgs, IC_00,
 };
 use ic_registry_routing_table::canister_id_into_u64;
 use ic_registry_routing_table::CanisterIdRange;
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::{
+    canister_snapshots::memory_copy_size,
     canister_state::{DEFAULT_QUEUE_CAPACITY, WASM_PAGE_SIZE_IN_BYTES},
     testing::{CanisterQueuesTesting, SystemStateTesting},
     CanisterStatus, SystemState,
@@ -27,16 +31,20 @@ use ic_types::{
     canister_http::CanisterHttpMethod,
     ingress::{IngressState, IngressStatus, WasmResult},
     messages::{
-        CallbackId, Payload, RejectContext, RequestOrResponse, Response, MAX_RESPONSE_COUNT_BYTES,
+        CallbackId, Payload, RejectContext, RequestOrResponse, Response, UserQuery,
+        MAX_RESPONSE_COUNT_BYTES,
     },
-    CanisterId, Cycles, PrincipalId, RegistryVersion,
+    CanisterId, Cycles, PrincipalId, RegistryVersion, UserId,
 };
 use ic_types_test_utils::ids::{canister_test_id, node_test_id, subnet_test_id, user_test_id};
 use ic_universal_canister::{call_args, wasm};
+use std::sync::Arc;
 
 #[cfg(test)]
 mod compilation;
 #[cfg(test)]
+mod execution_trace;
+#[cfg(test)]
 mod orthogonal_persistence;
 
 const BALANCE_EPSILON: Cycles = Cycles::new(10_000_000);
@@ -1477,6 +1485,114 @@ fn execute_canister_http_request() {
     );
 }
 
+#[test]
+fn execute_canister_http_request_with_body_methods() {
+    let own_subnet = subnet_test_id(1);
+    let caller_canister = canister_test_id(10);
+    let mut test = ExecutionTestBuilder::new()
+        .with_own_subnet_id(own_subnet)
+        .with_caller(own_subnet, caller_canister)
+        .build();
+    test.state_mut().metadata.own_subnet_features.http_requests = true;
+
+    let url = "https://example.com/resource/1".to_string();
+    let body = b"{\"name\":\"value\"}".to_vec();
+    let requests = [
+        (HttpMethod::PUT, CanisterHttpMethod::PUT, Some(body.clone())),
+        (
+            HttpMethod::PATCH,
+            CanisterHttpMethod::PATCH,
+            Some(body.clone()),
+        ),
+        (HttpMethod::DELETE, CanisterHttpMethod::DELETE, None),
+    ];
+    let response_size_limit = 1000u64;
+    let payment = Cycles::new(1_000_000_000);
+    for (method, _, body) in requests.iter() {
+        let args = CanisterHttpRequestArgs {
+            url: url.clone(),
+            max_response_bytes: Some(response_size_limit),
+            headers: Vec::new(),
+            body: body.clone(),
+            method: method.clone(),
+            transform: None,
+        };
+        test.inject_call_to_ic00(Method::HttpRequest, args.encode(), payment);
+    }
+    test.execute_all();
+
+    let canister_http_request_contexts = &test
+        .state()
+        .metadata
+        .subnet_call_context_manager
+        .canister_http_request_contexts;
+    assert_eq!(canister_http_request_contexts.len(), requests.len());
+
+    let body_less_fee = test.http_request_fee(
+        NumBytes::from(url.len() as u64),
+        Some(NumBytes::from(response_size_limit)),
+    );
+    for (i, (_, expected_method, body)) in requests.iter().enumerate() {
+        let http_request_context = canister_http_request_contexts
+            .get(&CallbackId::from(i as u64))
+            .unwrap();
+        assert_eq!(&http_request_context.http_method, expected_method);
+        // The url and the body are charged per byte, independently of the
+        // method.
+        let request_size = url.len() + body.as_ref().map_or(0, |body| body.len());
+        let expected_fee = test.http_request_fee(
+            NumBytes::from(request_size as u64),
+            Some(NumBytes::from(response_size_limit)),
+        );
+        assert_eq!(http_request_context.request.payment, payment - expected_fee);
+        match body {
+            Some(_) => assert!(expected_fee > body_less_fee),
+            None => assert_eq!(expected_fee, body_less_fee),
+        }
+    }
+}
+
+#[test]
+fn execute_canister_http_request_rejects_delete_with_body() {
+    let own_subnet = subnet_test_id(1);
+    let other_subnet = subnet_test_id(2);
+    let other_canister = canister_test_id(10);
+    let mut test = ExecutionTestBuilder::new()
+        .with_own_subnet_id(own_subnet)
+        .with_caller(other_subnet, other_canister)
+        .build();
+    test.state_mut().metadata.own_subnet_features.http_requests = true;
+
+    let args = CanisterHttpRequestArgs {
+        url: "https://example.com/resource/1".to_string(),
+        max_response_bytes: None,
+        headers: Vec::new(),
+        body: Some(b"{}".to_vec()),
+        method: HttpMethod::DELETE,
+        transform: None,
+    };
+    test.inject_call_to_ic00(
+        Method::HttpRequest,
+        args.encode(),
+        Cycles::new(1_000_000_000),
+    );
+    test.execute_all();
+
+    assert_eq!(
+        test.state()
+            .metadata
+            .subnet_call_context_manager
+            .canister_http_request_contexts
+            .len(),
+        0
+    );
+    let response = test.xnet_messages()[0].clone();
+    assert_eq!(
+        get_reject_message(response),
+        "http_request with method DELETE must not have a body"
+    );
+}
+
 #[test]
 fn execute_canister_http_request_disabled() {
     let own_subnet = subnet_test_id(1);
@@ -2127,3 +2243,297 @@ fn bitcoin_get_successors_cannot_be_called_by_non_bitcoin_canisters() {
     let result = test.ingress(uni, "update", call).unwrap();
     assert_eq!(result, WasmResult::Reject("Permission denied.".to_string()));
 }
+
+fn take_canister_snapshot(
+    test: &mut ExecutionTest,
+    canister_id: CanisterId,
+    replace_snapshot: Option<Vec<u8>>,
+) -> Result<CanisterSnapshotResponse, UserError> {
+    test.subnet_message(
+        Method::TakeCanisterSnapshot,
+        TakeCanisterSnapshotArgs::new(canister_id, replace_snapshot).encode(),
+    )
+    .map(|result| CanisterSnapshotResponse::decode(&get_reply(Ok(result))).unwrap())
+}
+
+#[test]
+fn take_and_load_canister_snapshot_restores_heap() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.universal_canister().unwrap();
+    test.ingress(
+        canister_id,
+        "update",
+        wasm().set_global_data(b"before").reply().build(),
+    )
+    .unwrap();
+
+    let snapshot = take_canister_snapshot(&mut test, canister_id, None).unwrap();
+    assert!(snapshot.total_size > 0);
+
+    test.ingress(
+        canister_id,
+        "update",
+        wasm().set_global_data(b"after").reply().build(),
+    )
+    .unwrap();
+
+    test.stop_canister(canister_id);
+    test.process_stopping_canisters();
+    let result = test.subnet_message(
+        Method::LoadCanisterSnapshot,
+        CanisterSnapshotArgs::new(canister_id, snapshot.id).encode(),
+    );
+    assert_eq!(get_reply(result), EmptyBlob.encode());
+    test.start_canister(canister_id).unwrap();
+
+    let result = test.ingress(
+        canister_id,
+        "update",
+        wasm().get_global_data().append_and_reply().build(),
+    );
+    assert_eq!(get_reply(result), b"before".to_vec());
+}
+
+#[test]
+fn load_canister_snapshot_fails_if_canister_is_running() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.universal_canister().unwrap();
+    let snapshot = take_canister_snapshot(&mut test, canister_id, None).unwrap();
+
+    let err = test
+        .subnet_message(
+            Method::LoadCanisterSnapshot,
+            CanisterSnapshotArgs::new(canister_id, snapshot.id).encode(),
+        )
+        .unwrap_err();
+    assert_eq!(ErrorCode::CanisterNotStopped, err.code());
+}
+
+#[test]
+fn take_canister_snapshot_respects_snapshot_limit() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.universal_canister().unwrap();
+    let first = take_canister_snapshot(&mut test, canister_id, None).unwrap();
+
+    let err = take_canister_snapshot(&mut test, canister_id, None).unwrap_err();
+    assert_eq!(ErrorCode::CanisterContractViolation, err.code());
+
+    // Replacing the existing snapshot is allowed and yields a new id.
+    let second = take_canister_snapshot(&mut test, canister_id, Some(first.id.clone())).unwrap();
+    assert_ne!(first.id, second.id);
+
+    let result = test.subnet_message(
+        Method::ListCanisterSnapshots,
+        CanisterIdRecord::from(canister_id).encode(),
+    );
+    let snapshots = ListCanisterSnapshotsResponse::decode(&get_reply(result)).unwrap();
+    assert_eq!(snapshots.0, vec![second]);
+}
+
+#[test]
+fn take_and_load_canister_snapshot_charge_for_copied_memory() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.universal_canister().unwrap();
+    let execution_state = test.execution_state(canister_id);
+    let copy_size = memory_copy_size(
+        &execution_state.wasm_memory.page_map,
+        &execution_state.stable_memory.page_map,
+    );
+    assert!(copy_size.get() > 0);
+    let copy_cost = test
+        .cycles_account_manager()
+        .execution_cost(snapshot_copy_instructions(copy_size), test.subnet_size());
+
+    let balance_before = test.canister_state(canister_id).system_state.balance();
+    let heap_delta_before = test.state().metadata.heap_delta_estimate;
+    let snapshot = take_canister_snapshot(&mut test, canister_id, None).unwrap();
+    assert_eq!(
+        test.canister_state(canister_id).system_state.balance(),
+        balance_before - copy_cost
+    );
+    assert_eq!(
+        test.state().metadata.heap_delta_estimate,
+        heap_delta_before + copy_size
+    );
+
+    test.stop_canister(canister_id);
+    test.process_stopping_canisters();
+    let balance_before = test.canister_state(canister_id).system_state.balance();
+    let heap_delta_before = test.state().metadata.heap_delta_estimate;
+    let result = test.subnet_message(
+        Method::LoadCanisterSnapshot,
+        CanisterSnapshotArgs::new(canister_id, snapshot.id).encode(),
+    );
+    assert_eq!(get_reply(result), EmptyBlob.encode());
+    assert_eq!(
+        test.canister_state(canister_id).system_state.balance(),
+        balance_before - copy_cost
+    );
+    assert_eq!(
+        test.state().metadata.heap_delta_estimate,
+        heap_delta_before + copy_size
+    );
+}
+
+#[test]
+fn take_canister_snapshot_fails_without_cycles_for_the_copy() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.universal_canister().unwrap();
+    *test
+        .canister_state_mut(canister_id)
+        .system_state
+        .balance_mut() = Cycles::zero();
+
+    let err = take_canister_snapshot(&mut test, canister_id, None).unwrap_err();
+    assert_eq!(ErrorCode::CanisterOutOfCycles, err.code());
+    assert!(test.state().canister_snapshots.is_empty());
+}
+
+#[test]
+fn delete_canister_snapshot_releases_subnet_memory() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.universal_canister().unwrap();
+    let memory_before = test.subnet_available_memory().get_total_memory();
+
+    let snapshot = take_canister_snapshot(&mut test, canister_id, None).unwrap();
+    assert_eq!(
+        test.subnet_available_memory().get_total_memory(),
+        memory_before - snapshot.total_size as i64
+    );
+
+    let result = test.subnet_message(
+        Method::DeleteCanisterSnapshot,
+        CanisterSnapshotArgs::new(canister_id, snapshot.id.clone()).encode(),
+    );
+    assert_eq!(get_reply(result), EmptyBlob.encode());
+    assert_eq!(
+        test.subnet_available_memory().get_total_memory(),
+        memory_before
+    );
+    assert!(test.state().canister_snapshots.is_empty());
+
+    let err = test
+        .subnet_message(
+            Method::DeleteCanisterSnapshot,
+            CanisterSnapshotArgs::new(canister_id, snapshot.id).encode(),
+        )
+        .unwrap_err();
+    assert_eq!(ErrorCode::CanisterContractViolation, err.code());
+}
+
+fn fetch_canister_logs(
+    test: &ExecutionTest,
+    sender: PrincipalId,
+    canister_id: CanisterId,
+) -> Result<FetchCanisterLogsResponse, UserError> {
+    let query = UserQuery {
+        source: UserId::from(sender),
+        receiver: IC_00,
+        method_name: Method::FetchCanisterLogs.to_string(),
+        method_payload: FetchCanisterLogsRequest::new(canister_id).encode(),
+        ingress_expiry: 0,
+        nonce: None,
+    };
+    test.query(query, Arc::new(test.state().clone()), vec![])
+        .map(|result| FetchCanisterLogsResponse::decode(&get_reply(Ok(result))).unwrap())
+}
+
+#[test]
+fn debug_print_is_recorded_in_canister_log() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.universal_canister().unwrap();
+    let result = test.ingress(
+        canister_id,
+        "update",
+        wasm().debug_print(b"hello").reply().build(),
+    );
+    assert_eq!(get_reply(result), Vec::<u8>::new());
+
+    let log = &test.canister_state(canister_id).system_state.canister_log;
+    let record = log.records().back().unwrap();
+    assert_eq!(record.content, b"hello".to_vec());
+    assert_eq!(
+        record.timestamp_nanos,
+        test.time().as_nanos_since_unix_epoch()
+    );
+}
+
+#[test]
+fn trap_is_recorded_in_canister_log() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.universal_canister().unwrap();
+    let err = test
+        .ingress(
+            canister_id,
+            "update",
+            wasm()
+                .debug_print(b"before trap")
+                .trap_with_blob(b"oops")
+                .build(),
+        )
+        .unwrap_err();
+    assert_eq!(ErrorCode::CanisterCalledTrap, err.code());
+
+    // The records are kept even though the execution failed.
+    let log = &test.canister_state(canister_id).system_state.canister_log;
+    let contents: Vec<_> = log
+        .records()
+        .iter()
+        .rev()
+        .take(2)
+        .map(|record| String::from_utf8_lossy(&record.content).to_string())
+        .collect();
+    assert!(contents[0].starts_with("[TRAP]: oops"), "{}", contents[0]);
+    assert_eq!(contents[1], "before trap");
+}
+
+#[test]
+fn fetch_canister_logs_respects_log_visibility() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.universal_canister().unwrap();
+    test.ingress(
+        canister_id,
+        "update",
+        wasm().debug_print(b"hello").reply().build(),
+    )
+    .unwrap();
+
+    let controller = test.user_id().get();
+    let response = fetch_canister_logs(&test, controller, canister_id).unwrap();
+    assert_eq!(
+        response.canister_log_records.last().unwrap().content,
+        b"hello".to_vec()
+    );
+
+    let err = fetch_canister_logs(&test, user_test_id(42).get(), canister_id).unwrap_err();
+    assert_eq!(ErrorCode::CanisterRejectedMessage, err.code());
+
+    let args = UpdateSettingsArgs {
+        canister_id: canister_id.get(),
+        settings: CanisterSettingsArgs {
+            log_visibility: Some(LogVisibility::Public),
+            ..Default::default()
+        },
+    };
+    let result = test.subnet_message(Method::UpdateSettings, args.encode());
+    assert_eq!(get_reply(result), EmptyBlob.encode());
+
+    let response = fetch_canister_logs(&test, user_test_id(42).get(), canister_id).unwrap();
+    assert_eq!(
+        response.canister_log_records.last().unwrap().content,
+        b"hello".to_vec()
+    );
+}
+
+#[test]
+fn fetch_canister_logs_is_rejected_in_replicated_mode() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.universal_canister().unwrap();
+    let err = test
+        .subnet_message(
+            Method::FetchCanisterLogs,
+            FetchCanisterLogsRequest::new(canister_id).encode(),
+        )
+        .unwrap_err();
+    assert_eq!(ErrorCode::CanisterRejectedMessage, err.code());
+}
@@ -0,0 +1,142 @@
+use std::sync::{Arc, Mutex};
+
+use crate::execution::test_utilities::ExecutionTestBuilder;
+use crate::{ExecutionTracer, MessageExecutionTrace, MessageOutcome};
+use ic_error_types::{ErrorCode, RejectCode};
+use ic_ic00_types::{CanisterIdRecord, Method, Payload};
+use ic_types::{CanisterId, Cycles, ExecutionRound, NumInstructions};
+use ic_universal_canister::{wasm, UNIVERSAL_CANISTER_WASM};
+
+#[derive(Default)]
+struct RecordingTracer {
+    traces: Mutex<Vec<MessageExecutionTrace>>,
+}
+
+impl RecordingTracer {
+    fn take(&self) -> Vec<MessageExecutionTrace> {
+        std::mem::take(&mut *self.traces.lock().unwrap())
+    }
+}
+
+impl ExecutionTracer for RecordingTracer {
+    fn on_round_started(&self, _round: ExecutionRound) {}
+
+    fn on_message_executed(&self, trace: &MessageExecutionTrace) {
+        self.traces.lock().unwrap().push(trace.clone());
+    }
+}
+
+#[test]
+fn rejected_ingress_message_is_traced() {
+    let tracer = Arc::new(RecordingTracer::default());
+    let mut test = ExecutionTestBuilder::new()
+        .with_execution_tracer(tracer.clone())
+        .build();
+    let canister_id = test.universal_canister().unwrap();
+    tracer.take();
+
+    let err = test
+        .ingress(canister_id, "update", wasm().trap().build())
+        .unwrap_err();
+    assert_eq!(ErrorCode::CanisterCalledTrap, err.code());
+
+    let traces = tracer.take();
+    assert_eq!(1, traces.len());
+    let trace = &traces[0];
+    assert_eq!(canister_id, trace.canister_id);
+    assert_eq!("ingress", trace.kind);
+    assert_eq!(Some("update".to_string()), trace.method);
+    assert!(trace.instructions_used > NumInstructions::from(0));
+    assert!(trace.cycles_charged > Cycles::zero());
+    assert_eq!(
+        MessageOutcome::Reject {
+            code: RejectCode::CanisterError as u64,
+            message: err.description().to_string(),
+        },
+        trace.outcome
+    );
+}
+
+#[test]
+fn replied_ingress_message_reports_charged_cycles() {
+    let tracer = Arc::new(RecordingTracer::default());
+    let mut test = ExecutionTestBuilder::new()
+        .with_execution_tracer(tracer.clone())
+        .build();
+    let canister_id = test.universal_canister().unwrap();
+    let execution_cost_before = test.canister_execution_cost(canister_id);
+    tracer.take();
+
+    test.ingress(canister_id, "update", wasm().reply().build())
+        .unwrap();
+
+    // Without deterministic time slicing the unused prepaid execution fee is
+    // refunded within the same execution, so only the net charge is traced.
+    let traces = tracer.take();
+    assert_eq!(1, traces.len());
+    assert_eq!(MessageOutcome::Reply, traces[0].outcome);
+    assert_eq!(
+        test.canister_execution_cost(canister_id) - execution_cost_before,
+        traces[0].cycles_charged
+    );
+    assert_eq!(Cycles::zero(), traces[0].cycles_refunded);
+}
+
+#[test]
+fn install_code_is_traced_with_the_installed_canister() {
+    let tracer = Arc::new(RecordingTracer::default());
+    let mut test = ExecutionTestBuilder::new()
+        .with_execution_tracer(tracer.clone())
+        .build();
+    let canister_id = test.create_canister(Cycles::new(1_000_000_000_000));
+    tracer.take();
+
+    test.install_canister(canister_id, UNIVERSAL_CANISTER_WASM.to_vec())
+        .unwrap();
+
+    let traces = tracer.take();
+    assert_eq!(1, traces.len());
+    let trace = &traces[0];
+    assert_eq!(canister_id, trace.canister_id);
+    assert_eq!(Some(Method::InstallCode.to_string()), trace.method);
+    assert!(trace.instructions_used > NumInstructions::from(0));
+    assert!(trace.cycles_charged > Cycles::zero());
+    assert_eq!(MessageOutcome::Reply, trace.outcome);
+}
+
+#[test]
+fn subnet_messages_are_traced_on_the_management_canister() {
+    let tracer = Arc::new(RecordingTracer::default());
+    let mut test = ExecutionTestBuilder::new()
+        .with_execution_tracer(tracer.clone())
+        .build();
+    let canister_id = test.create_canister(Cycles::new(1_000_000_000_000));
+    tracer.take();
+
+    test.subnet_message(
+        Method::CanisterStatus,
+        CanisterIdRecord::from(canister_id).encode(),
+    )
+    .unwrap();
+    let err = test
+        .subnet_message(
+            "no_such_method",
+            CanisterIdRecord::from(canister_id).encode(),
+        )
+        .unwrap_err();
+
+    let traces = tracer.take();
+    assert_eq!(2, traces.len());
+    assert_eq!(CanisterId::ic_00(), traces[0].canister_id);
+    assert_eq!(Some(Method::CanisterStatus.to_string()), traces[0].method);
+    assert_eq!(NumInstructions::from(0), traces[0].instructions_used);
+    assert_eq!(MessageOutcome::Reply, traces[0].outcome);
+    assert_eq!(CanisterId::ic_00(), traces[1].canister_id);
+    assert_eq!(
+        MessageOutcome::Reject {
+            code: err.reject_code() as u64,
+            message: err.description().to_string(),
+        },
+        traces[1].outcome
+    );
+}
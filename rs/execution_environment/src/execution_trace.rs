@@ -0,0 +1,276 @@
+//! Hooks to observe the execution of individual canister messages and tasks,
+//! e.g. to export a per-message trace while replaying blocks.
+
+use crate::execution_environment::{
+    as_num_instructions, ExecuteMessageResult, ExecutionResponse, RoundInstructions, RoundLimits,
+};
+use ic_error_types::{RejectCode, UserError};
+use ic_interfaces::messages::{CanisterInputMessage, RequestOrIngress};
+use ic_replicated_state::CanisterState;
+use ic_types::{
+    ingress::{IngressState, IngressStatus, WasmResult},
+    messages::Payload,
+    nominal_cycles::NominalCycles,
+    CanisterId, Cycles, ExecutionRound, NumBytes, NumInstructions, PrincipalId,
+};
+
+/// Receives a [MessageExecutionTrace] for every message or task that is
+/// executed, on canisters as well as on the management canister.
+///
+/// The tracer is called from the execution threads of the scheduler, so it
+/// must be cheap and must not block for long.
+pub trait ExecutionTracer: Send + Sync {
+    /// Called before any message of `round` is executed.
+    fn on_round_started(&self, round: ExecutionRound);
+
+    /// Called after every execution of a message or task in the current
+    /// round.
+    fn on_message_executed(&self, trace: &MessageExecutionTrace);
+}
+
+/// Describes a single execution (or execution slice, with deterministic time
+/// slicing) of a message or task.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MessageExecutionTrace {
+    /// The canister that executed the message: the installed canister for
+    /// `install_code` and the management canister for all other subnet
+    /// messages.
+    pub canister_id: CanisterId,
+    /// The kind of execution: `ingress`, `request`, `response`, `heartbeat`,
+    /// `global timer`, `paused execution` or `paused install_code`.
+    pub kind: &'static str,
+    /// The called method for ingress messages and requests.
+    pub method: Option<String>,
+    /// The sender of ingress messages and requests, and the respondent of
+    /// responses.
+    pub caller: Option<PrincipalId>,
+    /// The instructions executed in this execution (slice), including
+    /// `canister_init`, `canister_pre_upgrade` and `canister_post_upgrade`
+    /// for `install_code`.
+    pub instructions_used: NumInstructions,
+    /// The cycles consumed by the canister in this execution (slice): the
+    /// execution fee as well as fees for outgoing calls, but not cycles
+    /// transferred with messages.
+    pub cycles_charged: Cycles,
+    /// The cycles refunded to the canister in this execution (slice). With
+    /// deterministic time slicing, the execution fee of a message is prepaid
+    /// in its first slice and its unused part is refunded in its last one.
+    pub cycles_refunded: Cycles,
+    pub heap_delta: NumBytes,
+    pub outcome: MessageOutcome,
+}
+
+impl MessageExecutionTrace {
+    /// The trace of a subnet message that is handled by the management
+    /// canister without executing any canister code.
+    pub(crate) fn subnet_message(message: ExecutedMessage, outcome: MessageOutcome) -> Self {
+        Self {
+            canister_id: CanisterId::ic_00(),
+            kind: message.kind,
+            method: message.method,
+            caller: message.caller,
+            instructions_used: NumInstructions::from(0),
+            cycles_charged: Cycles::zero(),
+            cycles_refunded: Cycles::zero(),
+            heap_delta: NumBytes::from(0),
+            outcome,
+        }
+    }
+}
+
+/// The outcome of an execution, as far as it is known right after it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MessageOutcome {
+    /// The message was replied to.
+    Reply,
+    /// The message was rejected, failed or trapped with the given reject
+    /// code.
+    Reject { code: u64, message: String },
+    /// The execution did not produce a response, e.g. because the message
+    /// has outstanding calls, was a response or task, or was paused.
+    NoResponse,
+}
+
+/// The properties of an executed message that are known before execution.
+pub(crate) struct ExecutedMessage {
+    pub kind: &'static str,
+    pub method: Option<String>,
+    pub caller: Option<PrincipalId>,
+}
+
+impl ExecutedMessage {
+    pub fn task(kind: &'static str) -> Self {
+        Self {
+            kind,
+            method: None,
+            caller: None,
+        }
+    }
+}
+
+impl From<&CanisterInputMessage> for ExecutedMessage {
+    fn from(message: &CanisterInputMessage) -> Self {
+        match message {
+            CanisterInputMessage::Ingress(ingress) => Self {
+                kind: "ingress",
+                method: Some(ingress.method_name.clone()),
+                caller: Some(ingress.source.get()),
+            },
+            CanisterInputMessage::Request(request) => Self {
+                kind: "request",
+                method: Some(request.method_name.clone()),
+                caller: Some(request.sender.get()),
+            },
+            CanisterInputMessage::Response(response) => Self {
+                kind: "response",
+                method: None,
+                caller: Some(response.respondent.get()),
+            },
+        }
+    }
+}
+
+impl From<&RequestOrIngress> for ExecutedMessage {
+    fn from(message: &RequestOrIngress) -> Self {
+        Self {
+            kind: match message {
+                RequestOrIngress::Request(_) => "request",
+                RequestOrIngress::Ingress(_) => "ingress",
+            },
+            method: Some(message.method_name().to_string()),
+            caller: Some(*message.sender()),
+        }
+    }
+}
+
+/// The instructions and cycles consumed before an execution, to compute what
+/// the execution used once it is done.
+pub(crate) struct ExecutionStart {
+    instructions: RoundInstructions,
+    consumed_cycles: NominalCycles,
+}
+
+impl ExecutionStart {
+    pub fn new(round_limits: &RoundLimits, canister: &CanisterState) -> Self {
+        Self {
+            instructions: round_limits.instructions,
+            consumed_cycles: consumed_cycles(canister),
+        }
+    }
+
+    /// Builds the trace of the execution that started at `self` and left
+    /// `canister` and `round_limits` behind.
+    pub fn finish(
+        self,
+        message: ExecutedMessage,
+        canister: &CanisterState,
+        round_limits: &RoundLimits,
+        heap_delta: NumBytes,
+        outcome: MessageOutcome,
+    ) -> MessageExecutionTrace {
+        let consumed_cycles = consumed_cycles(canister);
+        let (cycles_charged, cycles_refunded) = if consumed_cycles >= self.consumed_cycles {
+            (
+                consumed_cycles - self.consumed_cycles,
+                NominalCycles::default(),
+            )
+        } else {
+            (
+                NominalCycles::default(),
+                self.consumed_cycles - consumed_cycles,
+            )
+        };
+        MessageExecutionTrace {
+            canister_id: canister.canister_id(),
+            kind: message.kind,
+            method: message.method,
+            caller: message.caller,
+            instructions_used: as_num_instructions(self.instructions - round_limits.instructions),
+            cycles_charged: Cycles::new(cycles_charged.get()),
+            cycles_refunded: Cycles::new(cycles_refunded.get()),
+            heap_delta,
+            outcome,
+        }
+    }
+}
+
+fn consumed_cycles(canister: &CanisterState) -> NominalCycles {
+    canister
+        .system_state
+        .canister_metrics
+        .consumed_cycles_since_replica_started
+}
+
+impl MessageOutcome {
+    pub(crate) fn from_result(result: &ExecuteMessageResult) -> Self {
+        match result {
+            ExecuteMessageResult::Finished { response, .. } => match response {
+                ExecutionResponse::Ingress((_, status)) => Self::from_ingress_status(status),
+                ExecutionResponse::Request(response) => match &response.response_payload {
+                    Payload::Data(_) => Self::Reply,
+                    Payload::Reject(context) => Self::Reject {
+                        code: context.code as u64,
+                        message: context.message.clone(),
+                    },
+                },
+                ExecutionResponse::Empty => Self::NoResponse,
+            },
+            ExecuteMessageResult::Paused { .. } => Self::NoResponse,
+        }
+    }
+
+    /// The outcome of a subnet message with the given response.
+    pub(crate) fn from_subnet_result(result: &Result<Vec<u8>, UserError>) -> Self {
+        match result {
+            Ok(_) => Self::Reply,
+            Err(err) => Self::from_user_error(err),
+        }
+    }
+
+    /// The outcome of a response that is delivered to the caller.
+    pub(crate) fn from_payload(payload: &Payload) -> Self {
+        match payload {
+            Payload::Data(_) => Self::Reply,
+            Payload::Reject(context) => Self::Reject {
+                code: context.code as u64,
+                message: context.message.clone(),
+            },
+        }
+    }
+
+    /// The outcome of a task that failed with the given error.
+    pub(crate) fn from_task_error(err: &impl std::fmt::Display) -> Self {
+        Self::Reject {
+            code: RejectCode::CanisterError as u64,
+            message: err.to_string(),
+        }
+    }
+
+    fn from_user_error(err: &UserError) -> Self {
+        Self::Reject {
+            code: err.reject_code() as u64,
+            message: err.description().to_string(),
+        }
+    }
+
+    fn from_ingress_status(status: &IngressStatus) -> Self {
+        match status {
+            IngressStatus::Known {
+                state: IngressState::Completed(WasmResult::Reply(_)),
+                ..
+            } => Self::Reply,
+            IngressStatus::Known {
+                state: IngressState::Completed(WasmResult::Reject(message)),
+                ..
+            } => Self::Reject {
+                code: RejectCode::CanisterReject as u64,
+                message: message.clone(),
+            },
+            IngressStatus::Known {
+                state: IngressState::Failed(error),
+                ..
+            } => Self::from_user_error(error),
+            _ => Self::NoResponse,
+        }
+    }
+}
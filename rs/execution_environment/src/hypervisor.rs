@@ -230,6 +230,14 @@ impl Hypervisor {
         embedder_config.feature_flags.rate_limiting_of_debug_prints =
             config.rate_limiting_of_debug_prints;
         embedder_config.cost_to_compile_wasm_instruction = config.cost_to_compile_wasm_instruction;
+        embedder_config.max_compilation_cache_size = config.max_compilation_cache_size;
+        embedder_config.max_compilation_cache_disk_size = config.max_compilation_cache_disk_size;
+        embedder_config.compilation_cache_dir = config.compilation_cache_dir.clone();
+        let compilation_cache = Arc::new(CompilationCache::new(
+            &embedder_config,
+            metrics_registry,
+            log.clone(),
+        ));
 
         let wasm_executor: Arc<dyn WasmExecutor> = match config.canister_sandboxing_flag {
             FlagStatus::Enabled => {
@@ -258,7 +266,7 @@ impl Hypervisor {
             own_subnet_type,
             log,
             cycles_account_manager,
-            compilation_cache: Arc::new(CompilationCache::new()),
+            compilation_cache,
             deterministic_time_slicing: config.deterministic_time_slicing,
             cost_to_compile_wasm_instruction: config.cost_to_compile_wasm_instruction,
             dirty_page_overhead,
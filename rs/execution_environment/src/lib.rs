@@ -5,6 +5,7 @@ mod canister_settings;
 pub mod execution;
 mod execution_environment;
 mod execution_environment_metrics;
+mod execution_trace;
 mod history;
 mod hypervisor;
 mod ingress_filter;
@@ -19,6 +20,7 @@ pub use execution_environment::{
     as_num_instructions, as_round_instructions, execute_canister, CompilationCostHandling,
     ExecuteMessageResult, ExecutionEnvironment, ExecutionResponse, RoundInstructions, RoundLimits,
 };
+pub use execution_trace::{ExecutionTracer, MessageExecutionTrace, MessageOutcome};
 pub use history::{IngressHistoryReaderImpl, IngressHistoryWriterImpl};
 pub use hypervisor::{Hypervisor, HypervisorMetrics};
 use ic_base_types::PrincipalId;
@@ -101,6 +103,33 @@ impl ExecutionServices {
         config: Config,
         cycles_account_manager: Arc<CyclesAccountManager>,
         state_reader: Arc<dyn StateReader<State = ReplicatedState>>,
+    ) -> ExecutionServices {
+        Self::setup_execution_with_tracer(
+            logger,
+            metrics_registry,
+            own_subnet_id,
+            own_subnet_type,
+            scheduler_config,
+            config,
+            cycles_account_manager,
+            state_reader,
+            None,
+        )
+    }
+
+    /// Same as `setup_execution()`, but reports every message executed by a
+    /// canister to the given `execution_tracer`.
+    #[allow(clippy::type_complexity, clippy::too_many_arguments)]
+    pub fn setup_execution_with_tracer(
+        logger: ReplicaLogger,
+        metrics_registry: &MetricsRegistry,
+        own_subnet_id: SubnetId,
+        own_subnet_type: SubnetType,
+        scheduler_config: SchedulerConfig,
+        config: Config,
+        cycles_account_manager: Arc<CyclesAccountManager>,
+        state_reader: Arc<dyn StateReader<State = ReplicatedState>>,
+        execution_tracer: Option<Arc<dyn ExecutionTracer>>,
     ) -> ExecutionServices {
         let hypervisor = Arc::new(Hypervisor::new(
             config.clone(),
@@ -120,17 +149,20 @@ impl ExecutionServices {
         let ingress_history_reader =
             Box::new(IngressHistoryReaderImpl::new(Arc::clone(&state_reader)));
 
-        let exec_env = Arc::new(ExecutionEnvironment::new(
-            logger.clone(),
-            Arc::clone(&hypervisor),
-            Arc::clone(&ingress_history_writer) as Arc<_>,
-            metrics_registry,
-            own_subnet_id,
-            own_subnet_type,
-            SchedulerImpl::compute_capacity(scheduler_config.scheduler_cores),
-            config.clone(),
-            Arc::clone(&cycles_account_manager),
-        ));
+        let exec_env = Arc::new(
+            ExecutionEnvironment::new(
+                logger.clone(),
+                Arc::clone(&hypervisor),
+                Arc::clone(&ingress_history_writer) as Arc<_>,
+                metrics_registry,
+                own_subnet_id,
+                own_subnet_type,
+                SchedulerImpl::compute_capacity(scheduler_config.scheduler_cores),
+                config.clone(),
+                Arc::clone(&cycles_account_manager),
+            )
+            .with_execution_tracer(execution_tracer),
+        );
         let sync_query_handler = Arc::new(InternalHttpQueryHandler::new(
             logger.clone(),
             hypervisor,
@@ -1,6 +1,7 @@
 //! This module implements the `QueryHandler` trait which is used to execute
 //! query methods via query calls.
 
+mod query_cache;
 mod query_context;
 #[cfg(test)]
 mod tests;
@@ -9,12 +10,17 @@ use crate::execution_environment::subnet_memory_capacity;
 use crate::{
     hypervisor::Hypervisor,
     metrics::{MeasurementScope, QueryHandlerMetrics},
+    util::candid_error_to_user_error,
 };
 use ic_config::execution_environment::Config;
 use ic_config::flag_status::FlagStatus;
 use ic_crypto_tree_hash::{flatmap, Label, LabeledTree, LabeledTree::SubTree};
 use ic_cycles_account_manager::CyclesAccountManager;
 use ic_error_types::{ErrorCode, RejectCode, UserError};
+use ic_ic00_types::{
+    FetchCanisterLogsRequest, FetchCanisterLogsResponse, LogVisibility, Method as Ic00Method,
+    Payload,
+};
 use ic_interfaces::execution_environment::{QueryExecutionService, QueryHandler};
 use ic_interfaces_state_manager::StateReader;
 use ic_logger::ReplicaLogger;
@@ -27,19 +33,24 @@ use ic_types::{
         Blob, Certificate, CertificateDelegation, HttpQueryResponse, HttpQueryResponseReply,
         UserQuery,
     },
-    CanisterId, NumInstructions,
+    CanisterId, NumInstructions, NumMessages, NumSlices, PrincipalId,
 };
 use serde::Serialize;
 use std::{
     convert::Infallible,
     future::Future,
     pin::Pin,
+    str::FromStr,
     sync::{Arc, Mutex},
     task::{Context, Poll},
 };
 use tokio::sync::oneshot;
 use tower::{limit::GlobalConcurrencyLimitLayer, util::BoxCloneService, Service, ServiceBuilder};
 
+/// The number of instructions accounted for a query answered from the query
+/// cache.
+const QUERY_CACHE_HIT_INSTRUCTIONS: NumInstructions = NumInstructions::new(1_000);
+
 /// Convert an object into CBOR binary.
 fn into_cbor<R: Serialize>(r: &R) -> Vec<u8> {
     let mut ser = serde_cbor::Serializer::new(Vec::new());
@@ -85,6 +96,50 @@ fn label<T: Into<Label>>(t: T) -> Label {
     t.into()
 }
 
+/// Handles a `fetch_canister_logs` query to the management canister. The logs
+/// are returned if they are public or if `sender` controls the canister.
+fn fetch_canister_logs(
+    sender: PrincipalId,
+    state: &ReplicatedState,
+    payload: &[u8],
+) -> Result<WasmResult, UserError> {
+    let args = FetchCanisterLogsRequest::decode(payload).map_err(candid_error_to_user_error)?;
+    let canister_id = args.get_canister_id();
+    let canister = state.canister_state(&canister_id).ok_or_else(|| {
+        UserError::new(
+            ErrorCode::CanisterNotFound,
+            format!("Canister {} not found", canister_id),
+        )
+    })?;
+
+    match canister.system_state.log_visibility {
+        LogVisibility::Public => {}
+        LogVisibility::Controllers => {
+            if !canister.controllers().contains(&sender) {
+                return Err(UserError::new(
+                    ErrorCode::CanisterRejectedMessage,
+                    format!(
+                        "Caller {} is not allowed to query ic00 method {}",
+                        sender,
+                        Ic00Method::FetchCanisterLogs
+                    ),
+                ));
+            }
+        }
+    }
+
+    let response = FetchCanisterLogsResponse {
+        canister_log_records: canister
+            .system_state
+            .canister_log
+            .records()
+            .iter()
+            .cloned()
+            .collect(),
+    };
+    Ok(WasmResult::Reply(response.encode()))
+}
+
 pub struct InternalHttpQueryHandler {
     log: ReplicaLogger,
     hypervisor: Arc<Hypervisor>,
@@ -94,6 +149,7 @@ pub struct InternalHttpQueryHandler {
     max_instructions_per_query: NumInstructions,
     cycles_account_manager: Arc<CyclesAccountManager>,
     composite_queries: FlagStatus,
+    query_cache: query_cache::QueryCache,
 }
 
 #[derive(Clone)]
@@ -115,6 +171,11 @@ impl InternalHttpQueryHandler {
         cycles_account_manager: Arc<CyclesAccountManager>,
         composite_queries: FlagStatus,
     ) -> Self {
+        let query_cache = query_cache::QueryCache::new(
+            metrics_registry,
+            config.query_cache_capacity,
+            config.query_cache_max_expiry_time,
+        );
         Self {
             log,
             hypervisor,
@@ -124,6 +185,7 @@ impl InternalHttpQueryHandler {
             max_instructions_per_query,
             cycles_account_manager,
             composite_queries,
+            query_cache,
         }
     }
 }
@@ -139,6 +201,38 @@ impl QueryHandler for InternalHttpQueryHandler {
     ) -> Result<WasmResult, UserError> {
         let measurement_scope = MeasurementScope::root(&self.metrics.query);
 
+        // The management canister does not have a Wasm module, so its query
+        // methods are handled here directly.
+        if query.receiver == CanisterId::ic_00() {
+            return match Ic00Method::from_str(&query.method_name) {
+                Ok(Ic00Method::FetchCanisterLogs) => {
+                    fetch_canister_logs(query.source.get(), state.as_ref(), &query.method_payload)
+                }
+                _ => Err(UserError::new(
+                    ErrorCode::CanisterMethodNotFound,
+                    format!("Query method {} not found.", query.method_name),
+                )),
+            };
+        }
+
+        let cache_entry = match self.config.query_caching {
+            FlagStatus::Enabled => {
+                query_cache::EntryEnv::new(&state, &query.receiver, &data_certificate)
+                    .map(|env| (query_cache::EntryKey::from(&query), env))
+            }
+            FlagStatus::Disabled => None,
+        };
+        if let Some((key, env)) = &cache_entry {
+            if let Some(result) = self.query_cache.get(key, env) {
+                measurement_scope.add(
+                    QUERY_CACHE_HIT_INSTRUCTIONS,
+                    NumSlices::from(0),
+                    NumMessages::from(0),
+                );
+                return Ok(result);
+            }
+        }
+
         // Letting the canister grow arbitrarily when executing the
         // query is fine as we do not persist state modifications.
         let subnet_available_memory = subnet_memory_capacity(&self.config);
@@ -158,12 +252,26 @@ impl QueryHandler for InternalHttpQueryHandler {
             self.config.instruction_overhead_per_query_call,
             self.composite_queries,
         );
-        context.run(
+        let result = context.run(
             query,
             &self.metrics,
             Arc::clone(&self.cycles_account_manager),
             &measurement_scope,
-        )
+        );
+
+        // The result of a query that called other canisters also depends on
+        // their states, so it is not cached.
+        if let (Some((key, env)), Ok(wasm_result)) = (cache_entry, &result) {
+            if !context.has_downstream_calls() {
+                self.query_cache.push(
+                    key,
+                    env,
+                    wasm_result.clone(),
+                    context.data_certificate_accessed(),
+                );
+            }
+        }
+        result
     }
 }
 
@@ -0,0 +1,373 @@
+//! A cache of the results of user queries.
+//!
+//! A cached result is reused for an identical query (same caller, canister,
+//! method and argument) as long as the canister has not changed since the
+//! result was computed: its version, its last executed round and its cycles
+//! balance must be the same. If the query read the data certificate, it must
+//! also run against the same certification, i.e. with the same data
+//! certificate, because the canister may have returned it. Results of queries
+//! that didn't read the certificate survive new certifications. A result is
+//! also discarded once the batch time of the state the query runs against is
+//! too far from the batch time at which it was computed, because the canister
+//! may have read the time.
+
+use ic_base_types::{CanisterId, NumBytes};
+use ic_metrics::MetricsRegistry;
+use ic_replicated_state::ReplicatedState;
+use ic_types::{ingress::WasmResult, messages::UserQuery, Cycles, ExecutionRound, Time, UserId};
+use lru::LruCache;
+use prometheus::{IntCounter, IntGauge};
+use std::{mem::size_of, sync::Mutex, time::Duration};
+
+pub(crate) struct QueryCacheMetrics {
+    pub hits: IntCounter,
+    pub misses: IntCounter,
+    pub evicted_entries: IntCounter,
+    pub invalidated_entries: IntCounter,
+    pub count_bytes: IntGauge,
+}
+
+impl QueryCacheMetrics {
+    fn new(metrics_registry: &MetricsRegistry) -> Self {
+        Self {
+            hits: metrics_registry.int_counter(
+                "execution_query_cache_hits_total",
+                "The number of user queries answered from the query cache",
+            ),
+            misses: metrics_registry.int_counter(
+                "execution_query_cache_misses_total",
+                "The number of user queries not found in the query cache",
+            ),
+            evicted_entries: metrics_registry.int_counter(
+                "execution_query_cache_evicted_entries_total",
+                "The number of query cache entries evicted to stay within the capacity",
+            ),
+            invalidated_entries: metrics_registry.int_counter(
+                "execution_query_cache_invalidated_entries_total",
+                "The number of query cache entries dropped because they became stale",
+            ),
+            count_bytes: metrics_registry.int_gauge(
+                "execution_query_cache_count_bytes",
+                "The total size of the query cache entries in bytes",
+            ),
+        }
+    }
+}
+
+/// Identifies a user query. Two queries with the same key are guaranteed to
+/// produce the same result when executed against the same canister state.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(super) struct EntryKey {
+    source: UserId,
+    receiver: CanisterId,
+    method_name: String,
+    method_payload: Vec<u8>,
+}
+
+impl EntryKey {
+    fn count_bytes(&self) -> usize {
+        size_of::<Self>() + self.method_name.len() + self.method_payload.len()
+    }
+}
+
+impl From<&UserQuery> for EntryKey {
+    fn from(query: &UserQuery) -> Self {
+        Self {
+            source: query.source,
+            receiver: query.receiver,
+            method_name: query.method_name.clone(),
+            method_payload: query.method_payload.clone(),
+        }
+    }
+}
+
+/// The parts of the replicated state a query result depends on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) struct EntryEnv {
+    batch_time: Time,
+    canister_version: u64,
+    last_executed_round: ExecutionRound,
+    canister_balance: Cycles,
+    /// The data certificate of the certified state the query runs against.
+    /// It changes with every new certification, so it is only compared for
+    /// queries that read it.
+    data_certificate: Vec<u8>,
+}
+
+impl EntryEnv {
+    /// Returns `None` if the queried canister doesn't exist or has no code.
+    pub(super) fn new(
+        state: &ReplicatedState,
+        canister_id: &CanisterId,
+        data_certificate: &[u8],
+    ) -> Option<Self> {
+        let canister = state.canister_state(canister_id)?;
+        let execution_state = canister.execution_state.as_ref()?;
+        Some(Self {
+            batch_time: state.metadata.batch_time,
+            canister_version: canister.system_state.canister_version,
+            last_executed_round: execution_state.last_executed_round,
+            canister_balance: canister.system_state.balance(),
+            data_certificate: data_certificate.to_vec(),
+        })
+    }
+}
+
+struct EntryValue {
+    env: EntryEnv,
+    result: WasmResult,
+    /// True if the query read the data certificate.
+    data_certificate_accessed: bool,
+}
+
+impl EntryValue {
+    fn new(mut env: EntryEnv, result: WasmResult, data_certificate_accessed: bool) -> Self {
+        // The certificate is never compared, there is no need to keep it.
+        if !data_certificate_accessed {
+            env.data_certificate = Vec::new();
+        }
+        Self {
+            env,
+            result,
+            data_certificate_accessed,
+        }
+    }
+
+    fn count_bytes(&self) -> usize {
+        size_of::<Self>()
+            + self.env.data_certificate.len()
+            + match &self.result {
+                WasmResult::Reply(data) => data.len(),
+                WasmResult::Reject(message) => message.len(),
+            }
+    }
+
+    /// Returns true if the result computed in `self.env` is still valid in
+    /// `env`.
+    fn is_valid(&self, env: &EntryEnv, max_expiry_time: Duration) -> bool {
+        self.env.canister_version == env.canister_version
+            && self.env.last_executed_round == env.last_executed_round
+            && self.env.canister_balance == env.canister_balance
+            && (!self.data_certificate_accessed
+                || self.env.data_certificate == env.data_certificate)
+            && self.env.batch_time <= env.batch_time
+            && env.batch_time <= self.env.batch_time + max_expiry_time
+    }
+}
+
+struct Entries {
+    cache: LruCache<EntryKey, EntryValue>,
+    count_bytes: usize,
+}
+
+/// Caches the results of user queries, see the module documentation.
+pub(crate) struct QueryCache {
+    entries: Mutex<Entries>,
+    capacity: NumBytes,
+    max_expiry_time: Duration,
+    pub(crate) metrics: QueryCacheMetrics,
+}
+
+impl QueryCache {
+    pub(crate) fn new(
+        metrics_registry: &MetricsRegistry,
+        capacity: NumBytes,
+        max_expiry_time: Duration,
+    ) -> Self {
+        Self {
+            entries: Mutex::new(Entries {
+                cache: LruCache::unbounded(),
+                count_bytes: 0,
+            }),
+            capacity,
+            max_expiry_time,
+            metrics: QueryCacheMetrics::new(metrics_registry),
+        }
+    }
+
+    /// Returns the cached result of the query with the given key if it is
+    /// still valid in `env`. A stale entry is removed.
+    pub(super) fn get(&self, key: &EntryKey, env: &EntryEnv) -> Option<WasmResult> {
+        let mut entries = self.entries.lock().unwrap();
+        let (valid, result) = match entries.cache.get(key) {
+            Some(value) => (
+                value.is_valid(env, self.max_expiry_time),
+                value.result.clone(),
+            ),
+            None => {
+                self.metrics.misses.inc();
+                return None;
+            }
+        };
+        if valid {
+            self.metrics.hits.inc();
+            return Some(result);
+        }
+        if let Some(value) = entries.cache.pop(key) {
+            entries.count_bytes -= key.count_bytes() + value.count_bytes();
+            self.metrics.invalidated_entries.inc();
+        }
+        self.metrics.count_bytes.set(entries.count_bytes as i64);
+        self.metrics.misses.inc();
+        None
+    }
+
+    /// Caches the result of the query computed in `env`, evicting the least
+    /// recently used entries if the cache is full. The result is tied to the
+    /// data certificate of `env` only if the query read the certificate.
+    pub(super) fn push(
+        &self,
+        key: EntryKey,
+        env: EntryEnv,
+        result: WasmResult,
+        data_certificate_accessed: bool,
+    ) {
+        let value = EntryValue::new(env, result, data_certificate_accessed);
+        let size = key.count_bytes() + value.count_bytes();
+        if size as u64 > self.capacity.get() {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(old_value) = entries.cache.pop(&key) {
+            entries.count_bytes -= key.count_bytes() + old_value.count_bytes();
+        }
+        while (entries.count_bytes + size) as u64 > self.capacity.get() {
+            match entries.cache.pop_lru() {
+                Some((evicted_key, evicted_value)) => {
+                    entries.count_bytes -= evicted_key.count_bytes() + evicted_value.count_bytes();
+                    self.metrics.evicted_entries.inc();
+                }
+                None => break,
+            }
+        }
+        entries.cache.put(key, value);
+        entries.count_bytes += size;
+        self.metrics.count_bytes.set(entries.count_bytes as i64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_test_utilities::types::ids::{canister_test_id, user_test_id};
+    use ic_types::time::UNIX_EPOCH;
+
+    fn key(payload: &[u8]) -> EntryKey {
+        EntryKey {
+            source: user_test_id(1),
+            receiver: canister_test_id(1),
+            method_name: "query".to_string(),
+            method_payload: payload.to_vec(),
+        }
+    }
+
+    fn env(batch_time_secs: u64, last_executed_round: u64, balance: u128) -> EntryEnv {
+        EntryEnv {
+            batch_time: UNIX_EPOCH + Duration::from_secs(batch_time_secs),
+            canister_version: 0,
+            last_executed_round: ExecutionRound::from(last_executed_round),
+            canister_balance: Cycles::new(balance),
+            data_certificate: vec![],
+        }
+    }
+
+    fn reply(data: &[u8]) -> WasmResult {
+        WasmResult::Reply(data.to_vec())
+    }
+
+    fn cache(capacity: u64) -> QueryCache {
+        QueryCache::new(
+            &MetricsRegistry::new(),
+            NumBytes::new(capacity),
+            Duration::from_secs(60),
+        )
+    }
+
+    #[test]
+    fn result_is_reused_in_the_same_env() {
+        let cache = cache(1 << 20);
+        assert_eq!(cache.get(&key(b"a"), &env(0, 1, 100)), None);
+        cache.push(key(b"a"), env(0, 1, 100), reply(b"x"), false);
+        assert_eq!(cache.get(&key(b"a"), &env(0, 1, 100)), Some(reply(b"x")));
+        assert_eq!(cache.get(&key(b"b"), &env(0, 1, 100)), None);
+        assert_eq!(cache.metrics.hits.get(), 1);
+        assert_eq!(cache.metrics.misses.get(), 2);
+    }
+
+    #[test]
+    fn result_is_invalidated_when_canister_changes() {
+        let cache = cache(1 << 20);
+        cache.push(key(b"a"), env(0, 1, 100), reply(b"x"), false);
+        assert_eq!(cache.get(&key(b"a"), &env(0, 2, 100)), None);
+        cache.push(key(b"a"), env(0, 1, 100), reply(b"x"), false);
+        assert_eq!(cache.get(&key(b"a"), &env(0, 1, 99)), None);
+        assert_eq!(cache.metrics.invalidated_entries.get(), 2);
+        assert_eq!(cache.metrics.count_bytes.get(), 0);
+    }
+
+    #[test]
+    fn result_is_invalidated_by_a_new_certification() {
+        let cache = cache(1 << 20);
+        let certified_env = |certificate: &[u8]| EntryEnv {
+            data_certificate: certificate.to_vec(),
+            ..env(0, 1, 100)
+        };
+        cache.push(
+            key(b"a"),
+            certified_env(b"certificate 1"),
+            reply(b"x"),
+            true,
+        );
+        assert_eq!(
+            cache.get(&key(b"a"), &certified_env(b"certificate 1")),
+            Some(reply(b"x"))
+        );
+        assert_eq!(
+            cache.get(&key(b"a"), &certified_env(b"certificate 2")),
+            None
+        );
+        assert_eq!(cache.metrics.invalidated_entries.get(), 1);
+    }
+
+    #[test]
+    fn result_survives_a_new_certification_if_certificate_not_read() {
+        let cache = cache(1 << 20);
+        let certified_env = |certificate: &[u8]| EntryEnv {
+            data_certificate: certificate.to_vec(),
+            ..env(0, 1, 100)
+        };
+        cache.push(
+            key(b"a"),
+            certified_env(b"certificate 1"),
+            reply(b"x"),
+            false,
+        );
+        assert_eq!(
+            cache.get(&key(b"a"), &certified_env(b"certificate 2")),
+            Some(reply(b"x"))
+        );
+        assert_eq!(cache.metrics.invalidated_entries.get(), 0);
+    }
+
+    #[test]
+    fn result_expires_after_max_expiry_time() {
+        let cache = cache(1 << 20);
+        cache.push(key(b"a"), env(10, 1, 100), reply(b"x"), false);
+        assert_eq!(cache.get(&key(b"a"), &env(70, 1, 100)), Some(reply(b"x")));
+        assert_eq!(cache.get(&key(b"a"), &env(71, 1, 100)), None);
+    }
+
+    #[test]
+    fn least_recently_used_entries_are_evicted() {
+        let entry_size = key(b"a").count_bytes()
+            + EntryValue::new(env(0, 1, 100), reply(b"x"), false).count_bytes();
+        let cache = cache(2 * entry_size as u64);
+        cache.push(key(b"a"), env(0, 1, 100), reply(b"x"), false);
+        cache.push(key(b"b"), env(0, 1, 100), reply(b"x"), false);
+        assert!(cache.get(&key(b"a"), &env(0, 1, 100)).is_some());
+        cache.push(key(b"c"), env(0, 1, 100), reply(b"x"), false);
+        assert!(cache.get(&key(b"a"), &env(0, 1, 100)).is_some());
+        assert!(cache.get(&key(b"b"), &env(0, 1, 100)).is_none());
+        assert_eq!(cache.metrics.evicted_entries.get(), 1);
+    }
+}
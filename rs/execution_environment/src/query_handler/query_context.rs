@@ -153,6 +153,11 @@ pub(super) struct QueryContext<'a> {
     instructions_per_composite_query_call: NumInstructions,
     round_limits: RoundLimits,
     composite_queries: FlagStatus,
+    // Set if the query called other canisters. The result of such a query
+    // depends on the state of canisters other than the one queried.
+    has_downstream_calls: bool,
+    // Set if any query executed in the context read the data certificate.
+    data_certificate_accessed: bool,
 }
 
 impl<'a> QueryContext<'a> {
@@ -195,6 +200,8 @@ impl<'a> QueryContext<'a> {
             instructions_per_composite_query_call,
             round_limits,
             composite_queries,
+            has_downstream_calls: false,
+            data_certificate_accessed: false,
         }
     }
 
@@ -334,6 +341,7 @@ impl<'a> QueryContext<'a> {
                         ),
                     )),
                     EnqueueRequestsResult::MessagesEnqueued => {
+                        self.has_downstream_calls = true;
                         self.call_stack.insert(canister.canister_id(), canister);
                         self.run_loop(canister_id, metrics, measurement_scope)
                     }
@@ -343,6 +351,16 @@ impl<'a> QueryContext<'a> {
         }
     }
 
+    /// Returns true if the executed query called other canisters.
+    pub(super) fn has_downstream_calls(&self) -> bool {
+        self.has_downstream_calls
+    }
+
+    /// Returns true if the executed query read the data certificate.
+    pub(super) fn data_certificate_accessed(&self) -> bool {
+        self.data_certificate_accessed
+    }
+
     // Keep processing the call graph till a result is achieved or no more
     // outstanding calls are left.
     fn run_loop<'b>(
@@ -495,18 +513,20 @@ impl<'a> QueryContext<'a> {
             InstructionLimits::new(FlagStatus::Disabled, instruction_limit, instruction_limit);
         let execution_parameters = self.execution_parameters(&canister, instruction_limits);
 
-        let (canister, instructions_left, result) = execute_non_replicated_query(
-            query_kind,
-            method_name,
-            method_payload,
-            canister,
-            Some(self.data_certificate.clone()),
-            self.state.time(),
-            execution_parameters,
-            &self.network_topology,
-            self.hypervisor,
-            &mut self.round_limits,
-        );
+        let (canister, instructions_left, result, data_certificate_accessed) =
+            execute_non_replicated_query(
+                query_kind,
+                method_name,
+                method_payload,
+                canister,
+                Some(self.data_certificate.clone()),
+                self.state.time(),
+                execution_parameters,
+                &self.network_topology,
+                self.hypervisor,
+                &mut self.round_limits,
+            );
+        self.data_certificate_accessed |= data_certificate_accessed;
         let instructions_executed = instruction_limit - instructions_left;
         self.remaining_instructions_for_composite_query = NumInstructions::from(
             self.remaining_instructions_for_composite_query
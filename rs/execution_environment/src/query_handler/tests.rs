@@ -809,3 +809,114 @@ fn composite_query_fails_in_replicated_mode() {
     // Verify that we consume some cycles.
     assert!(balance_before > balance_after);
 }
+
+#[test]
+fn query_results_are_cached_until_the_canister_changes() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.universal_canister_with_cycles(CYCLES_BALANCE).unwrap();
+    test.ingress(
+        canister_id,
+        "update",
+        wasm().set_global_data(b"a").reply().build(),
+    )
+    .unwrap();
+
+    let query = UserQuery {
+        source: user_test_id(2),
+        receiver: canister_id,
+        method_name: "query".to_string(),
+        method_payload: wasm().get_global_data().append_and_reply().build(),
+        ingress_expiry: 0,
+        nonce: None,
+    };
+    let run_query =
+        |test: &ExecutionTest| test.query(query.clone(), Arc::new(test.state().clone()), vec![]);
+
+    assert_eq!(run_query(&test), Ok(WasmResult::Reply(b"a".to_vec())));
+    assert_eq!(run_query(&test), Ok(WasmResult::Reply(b"a".to_vec())));
+    {
+        let query_handler = downcast_query_handler(test.query_handler());
+        assert_eq!(1, query_handler.query_cache.metrics.hits.get());
+        assert_eq!(1, query_handler.query_cache.metrics.misses.get());
+    }
+
+    // The query doesn't read the data certificate, so its cached result
+    // survives a new certification.
+    assert_eq!(
+        test.query(
+            query.clone(),
+            Arc::new(test.state().clone()),
+            b"new certificate".to_vec()
+        ),
+        Ok(WasmResult::Reply(b"a".to_vec()))
+    );
+    {
+        let query_handler = downcast_query_handler(test.query_handler());
+        assert_eq!(2, query_handler.query_cache.metrics.hits.get());
+        assert_eq!(
+            0,
+            query_handler.query_cache.metrics.invalidated_entries.get()
+        );
+    }
+
+    // A query that reads the data certificate is only reused within the
+    // same certification.
+    let certificate_query = UserQuery {
+        method_payload: wasm().data_certificate().append_and_reply().build(),
+        ..query.clone()
+    };
+    let run_certificate_query = |test: &ExecutionTest, certificate: &[u8]| {
+        test.query(
+            certificate_query.clone(),
+            Arc::new(test.state().clone()),
+            certificate.to_vec(),
+        )
+    };
+    assert_eq!(
+        run_certificate_query(&test, b"certificate 1"),
+        Ok(WasmResult::Reply(b"certificate 1".to_vec()))
+    );
+    assert_eq!(
+        run_certificate_query(&test, b"certificate 1"),
+        Ok(WasmResult::Reply(b"certificate 1".to_vec()))
+    );
+    assert_eq!(
+        run_certificate_query(&test, b"certificate 2"),
+        Ok(WasmResult::Reply(b"certificate 2".to_vec()))
+    );
+    {
+        let query_handler = downcast_query_handler(test.query_handler());
+        assert_eq!(3, query_handler.query_cache.metrics.hits.get());
+        assert_eq!(
+            1,
+            query_handler.query_cache.metrics.invalidated_entries.get()
+        );
+    }
+
+    // Executing an update changes the canister, so the cached result is
+    // dropped.
+    test.ingress(
+        canister_id,
+        "update",
+        wasm().set_global_data(b"b").reply().build(),
+    )
+    .unwrap();
+    assert_eq!(run_query(&test), Ok(WasmResult::Reply(b"b".to_vec())));
+    {
+        let query_handler = downcast_query_handler(test.query_handler());
+        assert_eq!(
+            2,
+            query_handler.query_cache.metrics.invalidated_entries.get()
+        );
+    }
+
+    // The cached result expires once the batch time moves too far.
+    test.state_mut().metadata.batch_time += std::time::Duration::from_secs(3600);
+    assert_eq!(run_query(&test), Ok(WasmResult::Reply(b"b".to_vec())));
+    let query_handler = downcast_query_handler(test.query_handler());
+    assert_eq!(3, query_handler.query_cache.metrics.hits.get());
+    assert_eq!(
+        3,
+        query_handler.query_cache.metrics.invalidated_entries.get()
+    );
+}
@@ -1143,6 +1143,9 @@ impl Scheduler for SchedulerImpl {
         registry_settings: &RegistryExecutionSettings,
     ) -> ReplicatedState {
         let measurement_scope = MeasurementScope::root(&self.metrics.round);
+        if let Some(tracer) = self.exec_env.execution_tracer() {
+            tracer.on_round_started(current_round);
+        }
 
         let mut cycles_in_sum = Cycles::zero();
         let round_log;
@@ -1934,11 +1937,17 @@ fn get_instructions_limits_for_subnet_message(
             | StopCanister
             | UninstallCode
             | UpdateSettings
+            | TakeCanisterSnapshot
+            | LoadCanisterSnapshot
+            | ListCanisterSnapshots
+            | DeleteCanisterSnapshot
+            | FetchCanisterLogs
             | BitcoinGetBalance
             | BitcoinGetUtxos
             | BitcoinSendTransaction
             | BitcoinSendTransactionInternal
             | BitcoinGetCurrentFeePercentiles
+            | BitcoinGetBlockHeaders
             | BitcoinGetSuccessors
             | ProvisionalCreateCanisterWithCycles
             | ProvisionalTopUpCanister => default_limits,
@@ -1964,7 +1973,8 @@ fn is_bitcoin_request(msg: &CanisterInputMessage) -> bool {
                 | BitcoinSendTransaction
                 | BitcoinSendTransactionInternal
                 | BitcoinGetSuccessors
-                | BitcoinGetCurrentFeePercentiles => true,
+                | BitcoinGetCurrentFeePercentiles
+                | BitcoinGetBlockHeaders => true,
                 CanisterStatus
                 | CreateCanister
                 | DeleteCanister
@@ -1980,6 +1990,11 @@ fn is_bitcoin_request(msg: &CanisterInputMessage) -> bool {
                 | StopCanister
                 | UninstallCode
                 | UpdateSettings
+                | TakeCanisterSnapshot
+                | LoadCanisterSnapshot
+                | ListCanisterSnapshots
+                | DeleteCanisterSnapshot
+                | FetchCanisterLogs
                 | ProvisionalCreateCanisterWithCycles
                 | ProvisionalTopUpCanister
                 | InstallCode => false,
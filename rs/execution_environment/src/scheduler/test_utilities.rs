@@ -47,6 +47,7 @@ use ic_test_utilities::{
     },
 };
 use ic_types::{
+    canister_log::CanisterLog,
     ingress::{IngressState, IngressStatus},
     messages::{CallContextId, Ingress, MessageId, Request, RequestOrResponse, Response},
     methods::{Callback, FuncRef, SystemMethod, WasmClosure, WasmMethod},
@@ -958,6 +959,8 @@ impl TestWasmExecutorCore {
                     accessed_pages: 0,
                     dirty_pages: 0,
                 },
+                canister_log: CanisterLog::default(),
+                data_certificate_accessed: false,
             };
             self.schedule
                 .push((self.round, canister_id, instructions_to_execute));
@@ -996,6 +999,8 @@ impl TestWasmExecutorCore {
             allocated_message_bytes: NumBytes::from(0),
             num_instructions_left: instructions_left,
             instance_stats,
+            canister_log: CanisterLog::default(),
+            data_certificate_accessed: false,
         };
         self.schedule
             .push((self.round, canister_id, instructions_to_execute));
@@ -230,6 +230,7 @@ fn setup_dts_install_code(
             compute_allocation: Some(1u32.into()),
             memory_allocation: None,
             freezing_threshold: Some(freezing_threshold_in_seconds.into()),
+            log_visibility: None,
         }),
     );
 
@@ -434,6 +435,7 @@ fn dts_pending_upgrade_with_heartbeat() {
         compute_allocation: None,
         memory_allocation: None,
         freezing_threshold: None,
+        log_visibility: None,
     });
 
     let canister = env
@@ -531,6 +533,7 @@ fn dts_scheduling_of_install_code() {
         compute_allocation: None,
         memory_allocation: None,
         freezing_threshold: None,
+        log_visibility: None,
     });
 
     let n = 10;
@@ -693,6 +696,7 @@ fn dts_pending_install_code_does_not_block_subnet_messages_of_other_canisters()
         compute_allocation: None,
         memory_allocation: None,
         freezing_threshold: None,
+        log_visibility: None,
     });
 
     let mut canister = vec![];
@@ -812,6 +816,7 @@ fn dts_pending_execution_blocks_subnet_messages_to_the_same_canister() {
         compute_allocation: None,
         memory_allocation: None,
         freezing_threshold: None,
+        log_visibility: None,
     });
 
     let canister = env
@@ -903,6 +908,7 @@ fn dts_pending_install_code_blocks_update_messages_to_the_same_canister() {
         compute_allocation: None,
         memory_allocation: None,
         freezing_threshold: None,
+        log_visibility: None,
     });
 
     let canister = env.create_canister_with_cycles(INITIAL_CYCLES_BALANCE, settings);
@@ -989,6 +995,7 @@ fn dts_long_running_install_and_update() {
             compute_allocation: None,
             memory_allocation: None,
             freezing_threshold: None,
+            log_visibility: None,
         });
 
         let id = env
@@ -1199,6 +1206,7 @@ fn dts_unrelated_subnet_messages_make_progress() {
         compute_allocation: None,
         memory_allocation: None,
         freezing_threshold: None,
+        log_visibility: None,
     });
 
     let canister = env
@@ -1607,6 +1615,7 @@ fn dts_canister_uninstalled_due_to_resource_charges_with_aborted_updrade() {
         compute_allocation: Some(1u32.into()),
         memory_allocation: None,
         freezing_threshold: None,
+        log_visibility: None,
     });
 
     let canister = env
@@ -1674,6 +1683,7 @@ fn dts_canister_uninstalled_due_resource_charges_with_aborted_update() {
             compute_allocation: Some(1u32.into()),
             memory_allocation: None,
             freezing_threshold: None,
+            log_visibility: None,
         });
 
         let id = env
@@ -1,15 +1,30 @@
+use ic_btc_types::NetworkSnakeCase;
+use ic_btc_types_internal::{BitcoinAdapterResponse, BitcoinAdapterResponseWrapper};
 use ic_config::{
-    execution_environment::Config as HypervisorConfig,
+    execution_environment::{BitcoinConfig, Config as HypervisorConfig},
     subnet_config::{CyclesAccountManagerConfig, SubnetConfigs},
 };
+use ic_ic00_types::{
+    self as ic00, BitcoinGetSuccessorsArgs, BitcoinGetSuccessorsRequestInitial,
+    BitcoinGetSuccessorsResponse, BitcoinGetSuccessorsResponseComplete, CanisterHttpRequestArgs,
+    ECDSAPublicKeyArgs, ECDSAPublicKeyResponse, EcdsaCurve, HttpMethod, Payload, SignWithECDSAArgs,
+    SignWithECDSAReply,
+};
+use ic_registry_subnet_features::SubnetFeatures;
 use ic_registry_subnet_type::SubnetType;
 use ic_state_machine_tests::{
-    CanisterSettingsArgs, ErrorCode, PrincipalId, StateMachine, StateMachineConfig, SubnetId,
-    UserError,
+    CanisterHttpResponsePayload, CanisterId, CanisterSettingsArgs, EcdsaKeyId, ErrorCode,
+    HttpHeader, MessageId, PayloadBuilder, PrincipalId, RejectCode, StateMachine,
+    StateMachineBuilder, StateMachineConfig, StateMachineEnv, SubnetId, UserError,
 };
 use ic_types::{ingress::WasmResult, Cycles, NumBytes};
-use ic_universal_canister::{wasm, UNIVERSAL_CANISTER_WASM};
-use std::{convert::TryInto, time::Duration};
+use ic_universal_canister::{call_args, wasm, UNIVERSAL_CANISTER_WASM};
+use k256::ecdsa::signature::hazmat::PrehashVerifier;
+use std::{
+    convert::{TryFrom, TryInto},
+    str::FromStr,
+    time::Duration,
+};
 
 const INITIAL_CYCLES_BALANCE: Cycles = Cycles::new(100_000_000_000_000);
 
@@ -321,6 +336,7 @@ fn test_canister_out_of_cycles() {
             compute_allocation: Some(candid::Nat::from(1)),
             memory_allocation: None,
             freezing_threshold: None,
+            log_visibility: None,
         }),
     );
 
@@ -384,6 +400,7 @@ fn canister_has_zero_balance_when_uninstalled_due_to_low_cycles() {
                 compute_allocation: Some(candid::Nat::from(1)),
                 memory_allocation: None,
                 freezing_threshold: None,
+                log_visibility: None,
             }),
             INITIAL_CYCLES_BALANCE,
         )
@@ -679,6 +696,7 @@ fn exceeding_memory_capacity_fails_when_memory_allocation_changes() {
                 compute_allocation: None,
                 memory_allocation: None,
                 freezing_threshold: None,
+                log_visibility: None,
             }),
             INITIAL_CYCLES_BALANCE,
         )
@@ -694,6 +712,7 @@ fn exceeding_memory_capacity_fails_when_memory_allocation_changes() {
                 compute_allocation: None,
                 memory_allocation: Some(candid::Nat::from(20u64 * 1024 * 1024 + 1)),
                 freezing_threshold: None,
+                log_visibility: None,
             },
         )
         .unwrap_err();
@@ -708,11 +727,237 @@ fn exceeding_memory_capacity_fails_when_memory_allocation_changes() {
             compute_allocation: None,
             memory_allocation: Some(candid::Nat::from(20u64 * 1024 * 1024)),
             freezing_threshold: None,
+            log_visibility: None,
         },
     )
     .unwrap();
 }
 
+#[test]
+fn inter_canister_call_across_subnets() {
+    let env = StateMachineEnv::new(2);
+    let caller = env
+        .subnet(0)
+        .install_canister(UNIVERSAL_CANISTER_WASM.into(), vec![], None)
+        .unwrap();
+    let callee = env
+        .subnet(1)
+        .install_canister(UNIVERSAL_CANISTER_WASM.into(), vec![], None)
+        .unwrap();
+    assert_eq!(
+        env.subnet_for_canister(callee).get_subnet_id(),
+        env.subnet(1).get_subnet_id()
+    );
+
+    // The caller forwards the reply of the callee on the other subnet.
+    let payload = wasm()
+        .inter_update(
+            callee.get(),
+            call_args()
+                .other_side(wasm().reply_data(b"pong"))
+                .on_reply(wasm().message_payload().append_and_reply()),
+        )
+        .build();
+    let result = env.execute_ingress(caller, "update", payload).unwrap();
+    assert_eq!(result, WasmResult::Reply(b"pong".to_vec()));
+
+    // The streams between the subnets are eventually garbage collected.
+    env.run_until_completion(10);
+}
+
+/// Signs a message hash with the test key of the given curve and returns the
+/// derived public key, the message hash and the signature.
+fn sign_with_ecdsa_test_key(curve: EcdsaCurve) -> (Vec<u8>, [u8; 32], Vec<u8>) {
+    let key_id = EcdsaKeyId {
+        curve,
+        name: "test_key".to_string(),
+    };
+    let env = StateMachineBuilder::new()
+        .with_ecdsa_key(key_id.clone())
+        .build();
+    let canister_id = env
+        .install_canister_with_cycles(
+            UNIVERSAL_CANISTER_WASM.into(),
+            vec![],
+            None,
+            INITIAL_CYCLES_BALANCE,
+        )
+        .unwrap();
+    let derivation_path = vec![b"path".to_vec()];
+
+    let public_key_call = wasm()
+        .call_simple(
+            ic00::IC_00,
+            ic00::Method::ECDSAPublicKey,
+            call_args().other_side(
+                ECDSAPublicKeyArgs {
+                    canister_id: None,
+                    derivation_path: derivation_path.clone(),
+                    key_id: key_id.clone(),
+                }
+                .encode(),
+            ),
+        )
+        .build();
+    let public_key = match env.execute_ingress(canister_id, "update", public_key_call) {
+        Ok(WasmResult::Reply(bytes)) => ECDSAPublicKeyResponse::decode(&bytes).unwrap(),
+        result => panic!("Unexpected result: {:?}", result),
+    };
+
+    let message_hash = [42; 32];
+    let sign_call = wasm()
+        .call_with_cycles(
+            ic00::IC_00,
+            ic00::Method::SignWithECDSA,
+            call_args().other_side(
+                SignWithECDSAArgs {
+                    message_hash,
+                    derivation_path,
+                    key_id,
+                }
+                .encode(),
+            ),
+            Cycles::new(100_000_000_000).into_parts(),
+        )
+        .build();
+    let msg_id = env.send_ingress(
+        PrincipalId::new_anonymous(),
+        canister_id,
+        "update",
+        sign_call,
+    );
+    env.tick();
+    assert_eq!(env.sign_with_ecdsa_contexts().len(), 1);
+
+    env.handle_sign_with_ecdsa_contexts();
+    assert!(env.sign_with_ecdsa_contexts().is_empty());
+    let signature = match env.await_ingress(msg_id, 10) {
+        Ok(WasmResult::Reply(bytes)) => SignWithECDSAReply::decode(&bytes).unwrap().signature,
+        result => panic!("Unexpected result: {:?}", result),
+    };
+
+    (public_key.public_key, message_hash, signature)
+}
+
+#[test]
+fn sign_with_ecdsa_is_answered_with_secp256k1_test_key() {
+    let (public_key, message_hash, signature) = sign_with_ecdsa_test_key(EcdsaCurve::Secp256k1);
+
+    let verifying_key = k256::ecdsa::VerifyingKey::from_sec1_bytes(&public_key).unwrap();
+    let signature = k256::ecdsa::Signature::try_from(signature.as_slice()).unwrap();
+    verifying_key
+        .verify_prehash(&message_hash, &signature)
+        .expect("the signature should be valid for the derived public key");
+}
+
+#[test]
+fn sign_with_ecdsa_is_answered_with_secp256r1_test_key() {
+    let (public_key, message_hash, signature) = sign_with_ecdsa_test_key(EcdsaCurve::Secp256r1);
+
+    let verifying_key = p256::ecdsa::VerifyingKey::from_sec1_bytes(&public_key).unwrap();
+    let signature = p256::ecdsa::Signature::try_from(signature.as_slice()).unwrap();
+    verifying_key
+        .verify_prehash(&message_hash, &signature)
+        .expect("the signature should be valid for the derived public key");
+}
+
+fn send_http_request(env: &StateMachine) -> MessageId {
+    let canister_id = env
+        .install_canister_with_cycles(
+            UNIVERSAL_CANISTER_WASM.into(),
+            vec![],
+            None,
+            INITIAL_CYCLES_BALANCE,
+        )
+        .unwrap();
+    let http_request = wasm()
+        .call_with_cycles(
+            ic00::IC_00,
+            ic00::Method::HttpRequest,
+            call_args()
+                .other_side(
+                    CanisterHttpRequestArgs {
+                        url: "https://example.com".to_string(),
+                        max_response_bytes: None,
+                        headers: Vec::new(),
+                        body: None,
+                        method: HttpMethod::GET,
+                        transform: None,
+                    }
+                    .encode(),
+                )
+                .on_reject(wasm().reject_message().reject()),
+            Cycles::new(100_000_000_000).into_parts(),
+        )
+        .build();
+    let msg_id = env.send_ingress(
+        PrincipalId::new_anonymous(),
+        canister_id,
+        "update",
+        http_request,
+    );
+    env.tick();
+    msg_id
+}
+
+#[test]
+fn canister_http_request_is_answered_with_injected_response() {
+    let env = StateMachineBuilder::new()
+        .with_features(SubnetFeatures::from_str("http_requests").unwrap())
+        .build();
+    let msg_id = send_http_request(&env);
+    let contexts = env.canister_http_request_contexts();
+    assert_eq!(contexts.len(), 1);
+    let (callback_id, _context) = contexts.into_iter().next().unwrap();
+
+    let response = CanisterHttpResponsePayload {
+        status: 200,
+        headers: vec![HttpHeader {
+            name: "content-type".to_string(),
+            value: "text/plain".to_string(),
+        }],
+        body: b"hello".to_vec(),
+    };
+    env.execute_payload(PayloadBuilder::new().with_canister_http_response(callback_id, &response));
+    assert!(env.canister_http_request_contexts().is_empty());
+    match env.await_ingress(msg_id, 10) {
+        Ok(WasmResult::Reply(bytes)) => {
+            assert_eq!(
+                CanisterHttpResponsePayload::decode(&bytes).unwrap(),
+                response
+            )
+        }
+        result => panic!("Unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn canister_http_request_can_be_rejected_or_timed_out() {
+    let env = StateMachineBuilder::new()
+        .with_features(SubnetFeatures::from_str("http_requests").unwrap())
+        .build();
+    let rejected = send_http_request(&env);
+    let timed_out = send_http_request(&env);
+    let callback_ids: Vec<_> = env.canister_http_request_contexts().into_keys().collect();
+    assert_eq!(callback_ids.len(), 2);
+
+    env.execute_payload(
+        PayloadBuilder::new()
+            .with_canister_http_reject(callback_ids[0], RejectCode::SysFatal, "connection refused")
+            .with_canister_http_timeout(callback_ids[1]),
+    );
+    assert_eq!(
+        env.await_ingress(rejected, 10),
+        Ok(WasmResult::Reject("connection refused".to_string()))
+    );
+    assert_eq!(
+        env.await_ingress(timed_out, 10),
+        Ok(WasmResult::Reject(
+            "Canister http request timed out".to_string()
+        ))
+    );
+}
+
 // Asserts that the canister replied with the given expected number.
 //
 // This function panics if there was an error executing the message or the
@@ -756,6 +1001,7 @@ fn exceeding_memory_capacity_fails_during_message_execution() {
                 compute_allocation: None,
                 memory_allocation: None,
                 freezing_threshold: None,
+                log_visibility: None,
             }),
             INITIAL_CYCLES_BALANCE,
         )
@@ -821,6 +1067,7 @@ fn max_canister_memory_respected_even_when_no_memory_allocation_is_set() {
                 compute_allocation: None,
                 memory_allocation: None,
                 freezing_threshold: None,
+                log_visibility: None,
             }),
             INITIAL_CYCLES_BALANCE,
         )
@@ -844,3 +1091,76 @@ fn max_canister_memory_respected_even_when_no_memory_allocation_is_set() {
     );
     assert_replied(res, 0);
 }
+
+#[test]
+fn bitcoin_get_successors_is_answered_with_adapter_response() {
+    // The first canister installed on a state machine gets the first id of
+    // the subnet's canister range.
+    let bitcoin_canister = CanisterId::from_u64(0);
+    let env = StateMachine::new_with_config(StateMachineConfig::new(
+        SubnetConfigs::default().own_subnet_config(SubnetType::Application),
+        HypervisorConfig {
+            bitcoin: BitcoinConfig {
+                privileged_access: vec![bitcoin_canister],
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    ));
+    let canister_id = env
+        .install_canister_with_cycles(
+            UNIVERSAL_CANISTER_WASM.into(),
+            vec![],
+            None,
+            INITIAL_CYCLES_BALANCE,
+        )
+        .unwrap();
+    assert_eq!(canister_id, bitcoin_canister);
+
+    let get_successors_call = wasm()
+        .call_simple(
+            ic00::IC_00,
+            ic00::Method::BitcoinGetSuccessors,
+            call_args().other_side(
+                BitcoinGetSuccessorsArgs::Initial(BitcoinGetSuccessorsRequestInitial {
+                    network: NetworkSnakeCase::Regtest,
+                    anchor: vec![1; 32],
+                    processed_block_hashes: vec![],
+                })
+                .encode(),
+            ),
+        )
+        .build();
+    let msg_id = env.send_ingress(
+        PrincipalId::new_anonymous(),
+        canister_id,
+        "update",
+        get_successors_call,
+    );
+    env.tick();
+    let contexts = env.bitcoin_get_successors_contexts();
+    assert_eq!(contexts.len(), 1);
+    let (callback_id, _context) = contexts.into_iter().next().unwrap();
+
+    let response = BitcoinGetSuccessorsResponseComplete {
+        blocks: vec![vec![2; 80]],
+        next: vec![vec![3; 80]],
+    };
+    env.execute_payload(PayloadBuilder::new().with_bitcoin_adapter_response(
+        BitcoinAdapterResponse {
+            response: BitcoinAdapterResponseWrapper::CanisterGetSuccessorsResponse(
+                response.clone(),
+            ),
+            callback_id: callback_id.get(),
+        },
+    ));
+    assert!(env.bitcoin_get_successors_contexts().is_empty());
+
+    match env.await_ingress(msg_id, 10) {
+        Ok(WasmResult::Reply(bytes)) => assert_eq!(
+            BitcoinGetSuccessorsResponse::decode(&bytes).unwrap(),
+            BitcoinGetSuccessorsResponse::Complete(response)
+        ),
+        result => panic!("Unexpected result: {:?}", result),
+    }
+}
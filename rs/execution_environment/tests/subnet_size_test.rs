@@ -194,6 +194,7 @@ fn simulate_one_gib_per_second_cost(
             compute_allocation: Some(candid::Nat::from(compute_allocation.as_percent())),
             memory_allocation: Some(candid::Nat::from(one_gib)),
             freezing_threshold: None,
+            log_visibility: None,
         }),
     );
 
@@ -389,7 +390,7 @@ fn simulate_sign_with_ecdsa_cost(subnet_type: SubnetType, subnet_size: usize) ->
         )
         .build();
     // Ignore ingress message response, since SignWithECDSA requires a response
-    // from consensus, which is not simulated in staet_machine_tests.
+    // from consensus, which is not needed to measure the fee.
     let _msg_id = env.send_ingress(
         PrincipalId::new_anonymous(),
         canister_id,
@@ -451,7 +452,7 @@ fn simulate_http_request_cost(subnet_type: SubnetType, subnet_size: usize) -> Cy
         )
         .build();
     // Ignore ingress message response, since HttpRequest requires a consensus response,
-    // which is not simulated in staet_machine_tests.
+    // which is not needed to measure the fee.
     let _msg_id = env.send_ingress(
         PrincipalId::new_anonymous(),
         canister_id,
@@ -1,13 +1,19 @@
-//! Module that deals with requests to /api/v2/canister/.../call
+//! Module that deals with requests to /api/v2/canister/.../call and
+//! /api/v3/canister/.../call
 
 use crate::{
     body::BodyReceiverLayer,
-    common::{get_cors_headers, make_plaintext_response, make_response, map_box_error_to_response},
+    common::{
+        cbor_response, get_cors_headers, into_cbor, make_plaintext_response, make_response,
+        map_box_error_to_response,
+    },
+    state_reader_executor::StateReaderExecutor,
     types::{to_legacy_request_type, ApiReqType},
     validator_executor::ValidatorExecutor,
     EndpointService, HttpError, HttpHandlerMetrics, IngressFilterService, UNKNOWN_LABEL,
 };
 use hyper::{Body, Response, StatusCode};
+use ic_crypto_tree_hash::{sparse_labeled_tree_from_paths, Label, Path};
 use ic_interfaces_p2p::{IngressError, IngressIngestionService};
 use ic_interfaces_registry::RegistryClient;
 use ic_logger::{error, info_sample, warn, ReplicaLogger};
@@ -17,17 +23,55 @@ use ic_registry_client_helpers::{
 };
 use ic_registry_provisional_whitelist::ProvisionalWhitelist;
 use ic_types::{
+    ingress::{IngressState, IngressStatus},
     malicious_flags::MaliciousFlags,
-    messages::{SignedIngress, SignedRequestBytes},
+    messages::{
+        Blob, Certificate, CertificateDelegation, HttpReadStateResponse, MessageId, SignedIngress,
+        SignedRequestBytes,
+    },
     CountBytes, RegistryVersion, SubnetId,
 };
 use std::convert::{Infallible, TryInto};
 use std::future::Future;
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::sync::Semaphore;
 use tower::{load_shed::LoadShed, util::BoxCloneService, Service, ServiceBuilder, ServiceExt};
 
+// How long a synchronous call waits for its reply to be certified before
+// falling back to an asynchronous `202 Accepted` response.
+const SYNC_CALL_TIMEOUT: Duration = Duration::from_secs(10);
+
+// The maximum number of synchronous calls held open at the same time. Calls
+// beyond that are answered with `202 Accepted` right after submission.
+const MAX_HELD_SYNC_CALLS: usize = 1000;
+
+/// What a call service needs to answer calls synchronously, with a
+/// certificate of the call's status in the same format `read_state` serves.
+#[derive(Clone)]
+pub(crate) struct SyncCallContext {
+    state_reader_executor: StateReaderExecutor,
+    delegation_from_nns: Arc<RwLock<Option<CertificateDelegation>>>,
+    held_calls: Arc<Semaphore>,
+    timeout: Duration,
+}
+
+impl SyncCallContext {
+    pub(crate) fn new(
+        state_reader_executor: StateReaderExecutor,
+        delegation_from_nns: Arc<RwLock<Option<CertificateDelegation>>>,
+    ) -> Self {
+        Self {
+            state_reader_executor,
+            delegation_from_nns,
+            held_calls: Arc::new(Semaphore::new(MAX_HELD_SYNC_CALLS)),
+            timeout: SYNC_CALL_TIMEOUT,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub(crate) struct CallService {
     log: ReplicaLogger,
@@ -38,6 +82,7 @@ pub(crate) struct CallService {
     ingress_sender: IngressIngestionService,
     ingress_filter: LoadShed<IngressFilterService>,
     malicious_flags: MaliciousFlags,
+    sync_call: Option<SyncCallContext>,
 }
 
 impl CallService {
@@ -51,6 +96,7 @@ impl CallService {
         ingress_sender: IngressIngestionService,
         ingress_filter: IngressFilterService,
         malicious_flags: MaliciousFlags,
+        sync_call: Option<SyncCallContext>,
     ) -> EndpointService {
         let base_service = BoxCloneService::new(ServiceBuilder::new().service(Self {
             log,
@@ -61,6 +107,7 @@ impl CallService {
             ingress_sender,
             ingress_filter: ServiceBuilder::new().load_shed().service(ingress_filter),
             malicious_flags,
+            sync_call,
         }));
         BoxCloneService::new(
             ServiceBuilder::new()
@@ -118,7 +165,65 @@ fn get_registry_data(
     Ok((settings, provisional_whitelist))
 }
 
-/// Handles a call to /api/v2/canister/../call
+pub(crate) fn is_terminal(status: &IngressStatus) -> bool {
+    match status {
+        IngressStatus::Known { state, .. } => matches!(
+            state,
+            IngressState::Completed(_) | IngressState::Failed(_) | IngressState::Done
+        ),
+        IngressStatus::Unknown => false,
+    }
+}
+
+/// Waits until the status of `message_id` is terminal in the certified state
+/// and returns a certificate of it, or `None` if that does not happen before
+/// the timeout of `sync_call` elapses.
+async fn wait_for_certified_status(
+    sync_call: &SyncCallContext,
+    message_id: &MessageId,
+) -> Option<Response<Body>> {
+    let deadline = tokio::time::Instant::now() + sync_call.timeout;
+    let mut certified_height = sync_call.state_reader_executor.subscribe_certified_height();
+    let mut paths = vec![
+        Path::new(vec![
+            Label::from("request_status"),
+            Label::from(message_id.as_bytes()),
+        ]),
+        Path::from(Label::from("time")),
+    ];
+    let labeled_tree = sparse_labeled_tree_from_paths(&mut paths);
+    loop {
+        // Mark the current height as seen before reading the state, so that a
+        // certification delivered in the meantime is not missed.
+        certified_height.borrow_and_update();
+        let (state, tree, certification) = match sync_call
+            .state_reader_executor
+            .read_certified_state(&labeled_tree)
+            .await
+        {
+            Ok(Some(certified_state)) => certified_state,
+            Ok(None) | Err(_) => return None,
+        };
+        if is_terminal(&state.get_ingress_status(message_id)) {
+            let signature = certification.signed.signature.signature.get().0;
+            let delegation = sync_call.delegation_from_nns.read().unwrap().clone();
+            return Some(cbor_response(&HttpReadStateResponse {
+                certificate: Blob(into_cbor(&Certificate {
+                    tree,
+                    signature: Blob(signature),
+                    delegation,
+                })),
+            }));
+        }
+        match tokio::time::timeout_at(deadline, certified_height.changed()).await {
+            Ok(Ok(())) => (),
+            // Timed out, or the state manager is gone.
+            Ok(Err(_)) | Err(_) => return None,
+        }
+    }
+}
+
+/// Handles a call to /api/v2/canister/../call and /api/v3/canister/../call
 impl Service<Vec<u8>> for CallService {
     type Response = Response<Body>;
     type Error = Infallible;
@@ -130,12 +235,17 @@ impl Service<Vec<u8>> for CallService {
     }
 
     fn call(&mut self, body: Vec<u8>) -> Self::Future {
+        let req_type = if self.sync_call.is_some() {
+            ApiReqType::SyncCall
+        } else {
+            ApiReqType::Call
+        };
         // Actual parsing.
         self.metrics
             .requests_body_size_bytes
             .with_label_values(&[
-                to_legacy_request_type(ApiReqType::Call),
-                ApiReqType::Call.into(),
+                to_legacy_request_type(req_type),
+                req_type.into(),
                 UNKNOWN_LABEL,
             ])
             .observe(body.len() as f64);
@@ -200,6 +310,7 @@ impl Service<Vec<u8>> for CallService {
         let log = self.log.clone();
         let validator_executor = self.validator_executor.clone();
         let malicious_flags = self.malicious_flags.clone();
+        let sync_call = self.sync_call.clone();
 
         Box::pin(async move {
             if let Err(http_err) = validator_executor
@@ -242,7 +353,18 @@ impl Service<Vec<u8>> for CallService {
                         "ingress_message_submit";
                         ingress_message => ingress_log_entry
                     );
-                    make_accepted_response()
+                    match sync_call {
+                        Some(sync_call) => {
+                            match Arc::clone(&sync_call.held_calls).try_acquire_owned() {
+                                Ok(_permit) => wait_for_certified_status(&sync_call, &message_id)
+                                    .await
+                                    .unwrap_or_else(make_accepted_response),
+                                // Too many calls are held open already.
+                                Err(_) => make_accepted_response(),
+                            }
+                        }
+                        None => make_accepted_response(),
+                    }
                 }
             };
             Ok(response)
@@ -260,11 +382,151 @@ fn make_accepted_response() -> Response<Body> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::common::test::state_reader_executor_with_certified_height;
+    use ic_crypto_tree_hash::MixedHashTree;
+    use ic_error_types::{ErrorCode, UserError};
+    use ic_replicated_state::ReplicatedState;
+    use ic_test_utilities::{
+        mock_time,
+        state::ReplicatedStateBuilder,
+        types::ids::{message_test_id, user_test_id},
+    };
     use ic_types::{
+        ingress::WasmResult,
         messages::{Blob, HttpCallContent, HttpCanisterUpdate, HttpRequestEnvelope},
         time::current_time_and_expiry_time,
+        Height, NumBytes,
     };
     use std::convert::TryFrom;
+    use std::sync::Mutex;
+    use tokio::sync::watch;
+
+    fn status(state: IngressState) -> IngressStatus {
+        IngressStatus::Known {
+            receiver: user_test_id(1).get(),
+            user_id: user_test_id(1),
+            time: mock_time(),
+            state,
+        }
+    }
+
+    fn state_with_status(message_id: &MessageId, state: IngressState) -> Arc<ReplicatedState> {
+        let mut replicated_state = ReplicatedStateBuilder::new().build();
+        replicated_state.set_ingress_status(
+            message_id.clone(),
+            status(state),
+            NumBytes::from(u64::MAX),
+        );
+        Arc::new(replicated_state)
+    }
+
+    // Returns a context whose certified state is whatever `state` holds when
+    // it is read, and whose certified height is driven by the returned sender.
+    fn sync_call_context(
+        state: Arc<Mutex<Arc<ReplicatedState>>>,
+        timeout: Duration,
+    ) -> (SyncCallContext, watch::Sender<Height>) {
+        let (state_reader_executor, height_sender) =
+            state_reader_executor_with_certified_height(move || {
+                (Arc::clone(&state.lock().unwrap()), MixedHashTree::Empty)
+            });
+        let sync_call = SyncCallContext {
+            state_reader_executor,
+            delegation_from_nns: Arc::new(RwLock::new(None)),
+            held_calls: Arc::new(Semaphore::new(1)),
+            timeout,
+        };
+        (sync_call, height_sender)
+    }
+
+    #[test]
+    fn only_replied_rejected_and_done_statuses_are_terminal() {
+        assert!(!is_terminal(&IngressStatus::Unknown));
+        assert!(!is_terminal(&status(IngressState::Received)));
+        assert!(!is_terminal(&status(IngressState::Processing)));
+        assert!(is_terminal(&status(IngressState::Completed(
+            WasmResult::Reply(vec![])
+        ))));
+        assert!(is_terminal(&status(IngressState::Completed(
+            WasmResult::Reject("rejected".to_string())
+        ))));
+        assert!(is_terminal(&status(IngressState::Failed(UserError::new(
+            ErrorCode::CanisterTrapped,
+            "trapped"
+        )))));
+        assert!(is_terminal(&status(IngressState::Done)));
+    }
+
+    #[tokio::test]
+    async fn sync_call_returns_certificate_of_terminal_status() {
+        let message_id = message_test_id(1);
+        let state = Arc::new(Mutex::new(state_with_status(
+            &message_id,
+            IngressState::Failed(UserError::new(ErrorCode::CanisterTrapped, "trapped")),
+        )));
+        let (sync_call, _height_sender) = sync_call_context(state, Duration::from_secs(60));
+
+        let response = wait_for_certified_status(&sync_call, &message_id)
+            .await
+            .expect("a failed call is answered right away");
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn sync_call_waits_for_a_certified_height_with_terminal_status() {
+        let message_id = message_test_id(1);
+        let state = Arc::new(Mutex::new(state_with_status(
+            &message_id,
+            IngressState::Processing,
+        )));
+        let (sync_call, height_sender) =
+            sync_call_context(Arc::clone(&state), Duration::from_secs(60));
+
+        let wait = tokio::spawn({
+            let message_id = message_id.clone();
+            async move { wait_for_certified_status(&sync_call, &message_id).await }
+        });
+        *state.lock().unwrap() = state_with_status(
+            &message_id,
+            IngressState::Completed(WasmResult::Reply(vec![])),
+        );
+        height_sender.send(Height::from(2)).unwrap();
+
+        let response = wait
+            .await
+            .unwrap()
+            .expect("the call is answered once its reply is certified");
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn sync_call_gives_up_after_timeout() {
+        let message_id = message_test_id(1);
+        let state = Arc::new(Mutex::new(state_with_status(
+            &message_id,
+            IngressState::Processing,
+        )));
+        let (sync_call, _height_sender) = sync_call_context(state, Duration::from_millis(10));
+
+        assert!(wait_for_certified_status(&sync_call, &message_id)
+            .await
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn sync_call_gives_up_when_certified_height_is_no_longer_updated() {
+        let message_id = message_test_id(1);
+        let state = Arc::new(Mutex::new(state_with_status(
+            &message_id,
+            IngressState::Processing,
+        )));
+        let (sync_call, height_sender) = sync_call_context(state, Duration::from_secs(60));
+        drop(height_sender);
+
+        assert!(wait_for_certified_status(&sync_call, &message_id)
+            .await
+            .is_none());
+    }
 
     #[test]
     fn check_request_id() {
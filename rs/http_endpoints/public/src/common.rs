@@ -51,7 +51,7 @@ pub(crate) fn get_root_threshold_public_key(
     }
 }
 
-pub(crate) fn make_plaintext_response(status: StatusCode, message: String) -> Response<Body> {
+pub fn make_plaintext_response(status: StatusCode, message: String) -> Response<Body> {
     let mut resp = Response::new(Body::from(message));
     *resp.status_mut() = status;
     *resp.headers_mut() = get_cors_headers();
@@ -72,7 +72,7 @@ pub(crate) fn make_plaintext_response(status: StatusCode, message: String) -> Re
 ///      Users can fetch the details via the read_state endpoint.
 ///
 /// make_response conversion applies the first case.
-pub(crate) fn make_response(user_error: UserError) -> Response<Body> {
+pub fn make_response(user_error: UserError) -> Response<Body> {
     use ic_error_types::ErrorCode as C;
 
     let status = match user_error.code() {
@@ -139,7 +139,7 @@ pub(crate) fn map_box_error_to_response(err: BoxError) -> Response<Body> {
 /// Add CORS headers to provided Response. In particular we allow
 /// wildcard origin, POST and GET and allow Accept, Authorization and
 /// Content Type headers.
-pub(crate) fn get_cors_headers() -> HeaderMap {
+pub fn get_cors_headers() -> HeaderMap {
     use hyper::header;
     let mut headers = HeaderMap::new();
     headers.insert(
@@ -158,7 +158,7 @@ pub(crate) fn get_cors_headers() -> HeaderMap {
 }
 
 /// Convert an object into CBOR binary.
-pub(crate) fn into_cbor<R: Serialize>(r: &R) -> Vec<u8> {
+pub fn into_cbor<R: Serialize>(r: &R) -> Vec<u8> {
     let mut ser = serde_cbor::Serializer::new(Vec::new());
     ser.self_describe().expect("Could not write magic tag.");
     r.serialize(&mut ser).expect("Serialization failed.");
@@ -166,7 +166,7 @@ pub(crate) fn into_cbor<R: Serialize>(r: &R) -> Vec<u8> {
 }
 
 /// Write the "self describing" CBOR tag and serialize the response
-pub(crate) fn cbor_response<R: Serialize>(r: &R) -> Response<Body> {
+pub fn cbor_response<R: Serialize>(r: &R) -> Response<Body> {
     use hyper::header;
     let mut response = Response::new(Body::from(into_cbor(r)));
     *response.status_mut() = StatusCode::OK;
@@ -185,7 +185,7 @@ pub(crate) fn empty_response() -> Response<Body> {
     response
 }
 
-pub(crate) fn validation_error_to_http_error(
+pub fn validation_error_to_http_error(
     message_id: MessageId,
     err: RequestValidationError,
     log: &ReplicaLogger,
@@ -228,11 +228,24 @@ pub(crate) async fn get_latest_certified_state(
 pub(crate) mod test {
     use super::*;
     use hyper::header;
-    use ic_types::messages::{Blob, CertificateDelegation};
+    use ic_crypto_tree_hash::MixedHashTree;
+    use ic_interfaces_state_manager_mocks::MockStateManager;
+    use ic_test_utilities::types::ids::subnet_test_id;
+    use ic_types::{
+        consensus::certification::{Certification, CertificationContent},
+        crypto::{
+            threshold_sig::ni_dkg::{NiDkgId, NiDkgTag, NiDkgTargetSubnet},
+            CombinedThresholdSig, CombinedThresholdSigOf, CryptoHash, Signed,
+        },
+        messages::{Blob, CertificateDelegation},
+        signature::ThresholdSignature,
+        CryptoHashOfPartialState, Height,
+    };
     use maplit::btreemap;
     use pretty_assertions::assert_eq;
     use serde::Serialize;
     use serde_cbor::Value;
+    use tokio::sync::watch;
 
     fn check_cors_headers(hm: &HeaderMap) {
         let acl_headers = hm.get_all(header::ACCESS_CONTROL_ALLOW_HEADERS).iter();
@@ -291,6 +304,49 @@ pub(crate) mod test {
         Value::Array(values)
     }
 
+    fn certification() -> Certification {
+        Certification {
+            height: Height::from(1),
+            signed: Signed {
+                signature: ThresholdSignature {
+                    signer: NiDkgId {
+                        start_block_height: Height::from(0),
+                        dealer_subnet: subnet_test_id(0),
+                        dkg_tag: NiDkgTag::HighThreshold,
+                        target_subnet: NiDkgTargetSubnet::Local,
+                    },
+                    signature: CombinedThresholdSigOf::new(CombinedThresholdSig(vec![])),
+                },
+                content: CertificationContent::new(CryptoHashOfPartialState::from(CryptoHash(
+                    vec![],
+                ))),
+            },
+        }
+    }
+
+    /// Returns a state reader executor whose certified state is whatever
+    /// `certified_state` returns when it is read, and the sender driving the
+    /// certified height its subscribers are notified of.
+    pub(crate) fn state_reader_executor_with_certified_height(
+        certified_state: impl Fn() -> (Arc<ReplicatedState>, MixedHashTree) + Send + Sync + 'static,
+    ) -> (StateReaderExecutor, watch::Sender<Height>) {
+        let (height_sender, height_receiver) = watch::channel(Height::from(1));
+        let mut state_manager = MockStateManager::new();
+        state_manager
+            .expect_subscribe_certified_height()
+            .returning(move || height_receiver.clone());
+        state_manager
+            .expect_read_certified_state()
+            .returning(move |_| {
+                let (state, tree) = certified_state();
+                Some((state, tree, certification()))
+            });
+        (
+            StateReaderExecutor::new(Arc::new(state_manager)),
+            height_sender,
+        )
+    }
+
     #[test]
     fn encoding_delegation() {
         let delegation = CertificateDelegation {
@@ -17,16 +17,23 @@ mod query;
 mod read_state;
 mod state_reader_executor;
 mod status;
+mod subscribe;
 mod types;
 mod validator_executor;
 
+// Building blocks of the endpoints that are shared with other implementations
+// of the public HTTP API, such as the HTTP gateway of state machine tests.
+pub use common::{
+    cbor_response, get_cors_headers, into_cbor, make_plaintext_response, make_response,
+    validation_error_to_http_error,
+};
+pub use read_state::verify_read_state_paths;
+pub use status::IC_API_VERSION;
+
 use crate::{
-    call::CallService,
+    call::{CallService, SyncCallContext},
     catch_up_package::CatchUpPackageService,
-    common::{
-        get_cors_headers, get_root_threshold_public_key, make_plaintext_response,
-        map_box_error_to_response,
-    },
+    common::{get_root_threshold_public_key, map_box_error_to_response},
     dashboard::DashboardService,
     health_status_refresher::HealthStatusRefreshLayer,
     metrics::{
@@ -36,6 +43,7 @@ use crate::{
     read_state::ReadStateService,
     state_reader_executor::StateReaderExecutor,
     status::StatusService,
+    subscribe::SubscribeService,
     types::*,
     validator_executor::ValidatorExecutor,
 };
@@ -148,7 +156,7 @@ const CONTENT_TYPE_CBOR: &str = "application/cbor";
 const UNKNOWN_LABEL: &str = "unknown";
 
 #[derive(Debug, Clone, PartialEq)]
-pub(crate) struct HttpError {
+pub struct HttpError {
     pub status: StatusCode,
     pub message: String,
 }
@@ -161,11 +169,13 @@ pub(crate) type EndpointService = BoxCloneService<Body, Response<Body>, BoxError
 struct HttpHandler {
     registry_client: Arc<dyn RegistryClient>,
     call_service: EndpointService,
+    sync_call_service: EndpointService,
     query_service: EndpointService,
     catchup_service: EndpointService,
     dashboard_service: EndpointService,
     status_service: EndpointService,
     read_state_service: EndpointService,
+    subscribe_service: EndpointService,
     health_status_refresher: HealthStatusRefreshLayer,
 }
 
@@ -312,6 +322,17 @@ pub fn start_server(
         }
 
         let call_service = CallService::new_service(
+            log.clone(),
+            metrics.clone(),
+            subnet_id,
+            Arc::clone(&registry_client),
+            validator_executor.clone(),
+            ingress_sender.clone(),
+            ingress_filter.clone(),
+            malicious_flags.clone(),
+            None,
+        );
+        let sync_call_service = CallService::new_service(
             log.clone(),
             metrics.clone(),
             subnet_id,
@@ -320,6 +341,10 @@ pub fn start_server(
             ingress_sender,
             ingress_filter,
             malicious_flags.clone(),
+            Some(SyncCallContext::new(
+                state_reader_executor.clone(),
+                Arc::clone(&delegation_from_nns),
+            )),
         );
         let query_service = QueryService::new_service(
             log.clone(),
@@ -332,6 +357,16 @@ pub fn start_server(
             malicious_flags.clone(),
         );
         let read_state_service = ReadStateService::new_service(
+            log.clone(),
+            metrics.clone(),
+            Arc::clone(&health_status),
+            Arc::clone(&delegation_from_nns),
+            state_reader_executor.clone(),
+            validator_executor.clone(),
+            Arc::clone(&registry_client),
+            malicious_flags.clone(),
+        );
+        let subscribe_service = SubscribeService::new_service(
             log.clone(),
             metrics.clone(),
             Arc::clone(&health_status),
@@ -382,11 +417,13 @@ pub fn start_server(
         let http_handler = HttpHandler {
             registry_client,
             call_service,
+            sync_call_service,
             query_service,
             status_service,
             catchup_service,
             dashboard_service,
             read_state_service,
+            subscribe_service,
             health_status_refresher,
         };
         let main_service = create_main_service(metrics.clone(), http_handler.clone());
@@ -596,11 +633,13 @@ async fn make_router(
     (req, mut timer): RequestWithTimer,
 ) -> ResponseWithTimer {
     let call_service = http_handler.call_service.clone();
+    let sync_call_service = http_handler.sync_call_service.clone();
     let query_service = http_handler.query_service.clone();
     let status_service = http_handler.status_service.clone();
     let catch_up_package_service = http_handler.catchup_service.clone();
     let dashboard_service = http_handler.dashboard_service.clone();
     let read_state_service = http_handler.read_state_service.clone();
+    let subscribe_service = http_handler.subscribe_service.clone();
 
     let svc = match req.method().clone() {
         Method::POST => {
@@ -633,6 +672,10 @@ async fn make_router(
                     set_timer_labels(&mut timer, ApiReqType::Call);
                     call_service
                 }
+                ["", "api", "v3", "canister", _, "call"] => {
+                    set_timer_labels(&mut timer, ApiReqType::SyncCall);
+                    sync_call_service
+                }
                 ["", "api", "v2", "canister", _, "query"] => {
                     set_timer_labels(&mut timer, ApiReqType::Query);
                     query_service
@@ -641,6 +684,10 @@ async fn make_router(
                     set_timer_labels(&mut timer, ApiReqType::ReadState);
                     read_state_service
                 }
+                ["", "api", "v2", "canister", _, "subscribe"] => {
+                    set_timer_labels(&mut timer, ApiReqType::Subscribe);
+                    subscribe_service
+                }
                 ["", "_", "catch_up_package"] => {
                     set_timer_labels(&mut timer, ApiReqType::CatchUpPackage);
                     catch_up_package_service
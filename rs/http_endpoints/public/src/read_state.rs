@@ -216,6 +216,18 @@ async fn verify_paths(
     targets: &CanisterIdSet,
 ) -> Result<(), HttpError> {
     let state = state_reader_executor.get_latest_state().await?.take();
+    verify_read_state_paths(&state, user, paths, targets)
+}
+
+/// Verifies that the `user` is authorized to retrieve the `paths` requested
+/// from the given `state`, where `targets` are the canisters the request is
+/// authorized for.
+pub fn verify_read_state_paths(
+    state: &ReplicatedState,
+    user: &UserId,
+    paths: &[Path],
+    targets: &CanisterIdSet,
+) -> Result<(), HttpError> {
     let mut num_request_ids = 0;
 
     // Convert the paths to slices to make it easier to match below.
@@ -238,7 +250,7 @@ async fn verify_paths(
 
                 match CanisterId::try_from(*canister_id) {
                     Ok(canister_id) => {
-                        can_read_canister_metadata(user, &canister_id, &name, &state)?
+                        can_read_canister_metadata(user, &canister_id, &name, state)?
                     }
                     Err(err) => {
                         return Err(HttpError {
@@ -4,7 +4,7 @@
 use crate::HttpError;
 use hyper::StatusCode;
 use ic_crypto_tree_hash::{LabeledTree, MixedHashTree};
-use ic_interfaces_state_manager::{Labeled, StateReader};
+use ic_interfaces_state_manager::{CertifiedHeightReceiver, Labeled, StateReader};
 use ic_replicated_state::ReplicatedState;
 use ic_types::{consensus::certification::Certification, Height};
 use std::sync::{Arc, Mutex};
@@ -33,6 +33,11 @@ impl StateReaderExecutor {
         self.state_reader.latest_certified_height()
     }
 
+    /// Subscribes to changes of the latest certified height, which is also non-blocking.
+    pub fn subscribe_certified_height(&self) -> CertifiedHeightReceiver {
+        self.state_reader.subscribe_certified_height()
+    }
+
     pub async fn get_latest_state(&self) -> Result<Labeled<Arc<ReplicatedState>>, HttpError> {
         let (tx, rx) = oneshot::channel();
         let state = self.state_reader.clone();
@@ -22,7 +22,7 @@ use tower::{
 
 // TODO(NET-776)
 // The IC API version reported on status requests.
-const IC_API_VERSION: &str = "0.18.0";
+pub const IC_API_VERSION: &str = "0.18.0";
 const MAX_STATUS_CONCURRENT_REQUESTS: usize = 100;
 
 #[derive(Clone)]
@@ -0,0 +1,601 @@
+//! Module that deals with requests to /api/v2/canister/.../subscribe
+//!
+//! A subscription is a signed `read_state` request whose paths are
+//! `request_status/<request_id>` or `canister/<canister_id>/certified_data`.
+//! Instead of a single certificate, the response is a stream of server-sent
+//! events. Every time the certified values under the requested paths change,
+//! a `certificate` event carrying the hex encoded CBOR certificate, in the
+//! same format `read_state` serves, is pushed to the subscriber.
+//!
+//! The stream ends when the `ingress_expiry` of the subscription request
+//! passes, or once all subscribed requests reached a terminal status.
+
+use crate::{
+    body::BodyReceiverLayer,
+    call::is_terminal,
+    common::{get_cors_headers, into_cbor, make_plaintext_response},
+    read_state::verify_read_state_paths,
+    state_reader_executor::StateReaderExecutor,
+    types::{to_legacy_request_type, ApiReqType},
+    validator_executor::ValidatorExecutor,
+    EndpointService, HttpError, HttpHandlerMetrics, ReplicaHealthStatus, UNKNOWN_LABEL,
+};
+use crossbeam::atomic::AtomicCell;
+use hyper::{body::Bytes, Body, Response, StatusCode};
+use ic_crypto_tree_hash::{
+    sparse_labeled_tree_from_paths, Label, LabeledTree, MixedHashTree, Path,
+};
+use ic_interfaces_registry::RegistryClient;
+use ic_logger::{trace, ReplicaLogger};
+use ic_replicated_state::ReplicatedState;
+use ic_types::{
+    malicious_flags::MaliciousFlags,
+    messages::{
+        Blob, Certificate, CertificateDelegation, HttpReadStateContent, HttpRequest,
+        HttpRequestEnvelope, MessageId, ReadState, SignedRequestBytes,
+    },
+    time::current_time,
+    UserId,
+};
+use ic_validator::CanisterIdSet;
+use std::convert::{Infallible, TryFrom};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tower::{util::BoxCloneService, Service, ServiceBuilder};
+
+const MAX_CONCURRENT_SUBSCRIPTIONS: usize = 1000;
+const CONTENT_TYPE_EVENT_STREAM: &str = "text/event-stream";
+
+#[derive(Clone)]
+pub(crate) struct SubscribeService {
+    log: ReplicaLogger,
+    metrics: HttpHandlerMetrics,
+    health_status: Arc<AtomicCell<ReplicaHealthStatus>>,
+    delegation_from_nns: Arc<RwLock<Option<CertificateDelegation>>>,
+    state_reader_executor: StateReaderExecutor,
+    validator_executor: ValidatorExecutor,
+    registry_client: Arc<dyn RegistryClient>,
+    malicious_flags: MaliciousFlags,
+    // Subscriptions outlive the requests that opened them, so they are
+    // limited here rather than by a concurrency limit layer.
+    subscriptions: Arc<Semaphore>,
+}
+
+impl SubscribeService {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new_service(
+        log: ReplicaLogger,
+        metrics: HttpHandlerMetrics,
+        health_status: Arc<AtomicCell<ReplicaHealthStatus>>,
+        delegation_from_nns: Arc<RwLock<Option<CertificateDelegation>>>,
+        state_reader_executor: StateReaderExecutor,
+        validator_executor: ValidatorExecutor,
+        registry_client: Arc<dyn RegistryClient>,
+        malicious_flags: MaliciousFlags,
+    ) -> EndpointService {
+        let base_service = BoxCloneService::new(ServiceBuilder::new().service(Self {
+            log,
+            metrics,
+            health_status,
+            delegation_from_nns,
+            state_reader_executor,
+            validator_executor,
+            registry_client,
+            malicious_flags,
+            subscriptions: Arc::new(Semaphore::new(MAX_CONCURRENT_SUBSCRIPTIONS)),
+        }));
+        BoxCloneService::new(
+            ServiceBuilder::new()
+                .layer(BodyReceiverLayer::default())
+                .service(base_service),
+        )
+    }
+}
+
+/// The paths of a subscription, split by what they observe.
+#[derive(Debug, PartialEq)]
+struct Subscription {
+    request_ids: Vec<MessageId>,
+    certified_data: bool,
+}
+
+impl Subscription {
+    fn parse(paths: &[Path]) -> Result<Self, HttpError> {
+        if paths.is_empty() {
+            return Err(HttpError {
+                status: StatusCode::BAD_REQUEST,
+                message: "A subscription needs at least one path.".to_string(),
+            });
+        }
+        let mut subscription = Subscription {
+            request_ids: vec![],
+            certified_data: false,
+        };
+        for path in paths {
+            let path: Vec<&[u8]> = path.iter().map(|label| label.as_bytes()).collect();
+            match path.as_slice() {
+                [b"canister", _canister_id, b"certified_data"] => {
+                    subscription.certified_data = true
+                }
+                [b"request_status", request_id] | [b"request_status", request_id, ..] => {
+                    // Malformed request IDs are rejected by `verify_read_state_paths`.
+                    if let Ok(message_id) = MessageId::try_from(*request_id) {
+                        subscription.request_ids.push(message_id);
+                    }
+                }
+                _ => {
+                    return Err(HttpError {
+                        status: StatusCode::NOT_FOUND,
+                        message:
+                            "Only request_status and certified_data paths can be subscribed to."
+                                .to_string(),
+                    })
+                }
+            }
+        }
+        Ok(subscription)
+    }
+
+    /// Whether nothing observed by the subscription can change anymore.
+    fn is_done(&self, state: &ReplicatedState) -> bool {
+        !self.certified_data
+            && self
+                .request_ids
+                .iter()
+                .all(|message_id| is_terminal(&state.get_ingress_status(message_id)))
+    }
+}
+
+// Verifies that the `user` is authorized to subscribe to the `paths` in the
+// given `state`. `certified_data` is public, and requests statuses have the
+// same restrictions as in `read_state`.
+fn verify_subscription_paths(
+    state: &ReplicatedState,
+    user: &UserId,
+    paths: &[Path],
+    targets: &CanisterIdSet,
+) -> Result<(), HttpError> {
+    let request_status_paths: Vec<Path> = paths
+        .iter()
+        .filter(|path| path.first().map(|label| label.as_bytes()) == Some(&b"request_status"[..]))
+        .cloned()
+        .collect();
+    verify_read_state_paths(state, user, &request_status_paths, targets)
+}
+
+// The certified values witnessed by `tree`, except for the time, which
+// changes with every certified height.
+fn witnessed_values(tree: &MixedHashTree) -> Option<LabeledTree<Vec<u8>>> {
+    let mut values = LabeledTree::<Vec<u8>>::try_from(tree.clone()).ok()?;
+    if let LabeledTree::SubTree(children) = &mut values {
+        children.remove(&Label::from("time"));
+    }
+    Some(values)
+}
+
+fn certificate_event(
+    tree: MixedHashTree,
+    signature: Vec<u8>,
+    delegation: Option<CertificateDelegation>,
+) -> Bytes {
+    let certificate = into_cbor(&Certificate {
+        tree,
+        signature: Blob(signature),
+        delegation,
+    });
+    Bytes::from(format!(
+        "event: certificate\ndata: {}\n\n",
+        hex::encode(certificate)
+    ))
+}
+
+/// Everything a subscription needs once the response headers are sent.
+struct SubscriptionStream {
+    log: ReplicaLogger,
+    state_reader_executor: StateReaderExecutor,
+    delegation_from_nns: Arc<RwLock<Option<CertificateDelegation>>>,
+    user: UserId,
+    paths: Vec<Path>,
+    targets: CanisterIdSet,
+    subscription: Subscription,
+    expires_in: Duration,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl SubscriptionStream {
+    async fn run(self, mut sender: hyper::body::Sender) {
+        let deadline = tokio::time::Instant::now() + self.expires_in;
+        let mut certified_height = self.state_reader_executor.subscribe_certified_height();
+        let mut paths = self.paths.clone();
+        paths.push(Path::from(Label::from("time")));
+        let labeled_tree = sparse_labeled_tree_from_paths(&mut paths);
+        let mut last_pushed = None;
+        loop {
+            // Mark the current height as seen before reading the state, so
+            // that a certification delivered in the meantime is not missed.
+            certified_height.borrow_and_update();
+            let certified_state = match self
+                .state_reader_executor
+                .read_certified_state(&labeled_tree)
+                .await
+            {
+                Ok(certified_state) => certified_state,
+                Err(_) => return,
+            };
+            if let Some((state, tree, certification)) = certified_state {
+                // Messages may have been inducted since the subscription was
+                // opened, so authorization is checked against every state.
+                if verify_subscription_paths(&state, &self.user, &self.paths, &self.targets)
+                    .is_err()
+                {
+                    return;
+                }
+                let values = witnessed_values(&tree);
+                if values != last_pushed {
+                    let signature = certification.signed.signature.signature.get().0;
+                    let delegation = self.delegation_from_nns.read().unwrap().clone();
+                    if sender
+                        .send_data(certificate_event(tree, signature, delegation))
+                        .await
+                        .is_err()
+                    {
+                        trace!(self.log, "subscriber disconnected");
+                        return;
+                    }
+                    last_pushed = values;
+                }
+                if self.subscription.is_done(&state) {
+                    return;
+                }
+            }
+            match tokio::time::timeout_at(deadline, certified_height.changed()).await {
+                Ok(Ok(())) => (),
+                // The subscription expired, or the state manager is gone.
+                Ok(Err(_)) | Err(_) => return,
+            }
+        }
+    }
+}
+
+/// Handles a call to /api/v2/canister/../subscribe
+impl Service<Vec<u8>> for SubscribeService {
+    type Response = Response<Body>;
+    type Error = Infallible;
+    #[allow(clippy::type_complexity)]
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, body: Vec<u8>) -> Self::Future {
+        trace!(self.log, "in handle subscribe");
+        self.metrics
+            .requests_body_size_bytes
+            .with_label_values(&[
+                to_legacy_request_type(ApiReqType::Subscribe),
+                ApiReqType::Subscribe.into(),
+                UNKNOWN_LABEL,
+            ])
+            .observe(body.len() as f64);
+
+        if self.health_status.load() != ReplicaHealthStatus::Healthy {
+            let res = make_plaintext_response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                format!(
+                    "Replica is unhealthy: {}. Check the /api/v2/status for more information.",
+                    self.health_status.load(),
+                ),
+            );
+            return Box::pin(async move { Ok(res) });
+        }
+
+        let permit = match Arc::clone(&self.subscriptions).try_acquire_owned() {
+            Ok(permit) => permit,
+            Err(_) => {
+                let res = make_plaintext_response(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "Too many subscriptions, try again later.".to_string(),
+                );
+                return Box::pin(async move { Ok(res) });
+            }
+        };
+
+        let request = match <HttpRequestEnvelope<HttpReadStateContent>>::try_from(
+            &SignedRequestBytes::from(body),
+        ) {
+            Ok(request) => request,
+            Err(e) => {
+                let res = make_plaintext_response(
+                    StatusCode::BAD_REQUEST,
+                    format!("Could not parse body as subscribe request: {}", e),
+                );
+                return Box::pin(async move { Ok(res) });
+            }
+        };
+
+        // Convert the message to a strongly-typed struct.
+        let request = match HttpRequest::<ReadState>::try_from(request) {
+            Ok(request) => request,
+            Err(e) => {
+                let res = make_plaintext_response(
+                    StatusCode::BAD_REQUEST,
+                    format!("Malformed request: {:?}", e),
+                );
+                return Box::pin(async move { Ok(res) });
+            }
+        };
+        let paths = request.content().paths.clone();
+        let subscription = match Subscription::parse(&paths) {
+            Ok(subscription) => subscription,
+            Err(HttpError { status, message }) => {
+                return Box::pin(async move { Ok(make_plaintext_response(status, message)) });
+            }
+        };
+
+        let log = self.log.clone();
+        let registry_version = self.registry_client.get_latest_version();
+        let malicious_flags = self.malicious_flags.clone();
+        let state_reader_executor = self.state_reader_executor.clone();
+        let validator_executor = self.validator_executor.clone();
+        let delegation_from_nns = Arc::clone(&self.delegation_from_nns);
+        Box::pin(async move {
+            let targets = match validator_executor
+                .get_authorized_canisters(&request, registry_version, &malicious_flags)
+                .await
+            {
+                Ok(targets) => targets,
+                Err(http_err) => {
+                    let res = make_plaintext_response(http_err.status, http_err.message);
+                    return Ok(res);
+                }
+            };
+            let user = request.sender();
+            let state = match state_reader_executor.get_latest_state().await {
+                Ok(state) => state.take(),
+                Err(HttpError { status, message }) => {
+                    return Ok(make_plaintext_response(status, message))
+                }
+            };
+            if let Err(HttpError { status, message }) =
+                verify_subscription_paths(&state, &user, &paths, &targets)
+            {
+                return Ok(make_plaintext_response(status, message));
+            }
+
+            let expires_in = Duration::from_nanos(
+                request
+                    .ingress_expiry()
+                    .saturating_sub(current_time().as_nanos_since_unix_epoch()),
+            );
+            let stream = SubscriptionStream {
+                log,
+                state_reader_executor,
+                delegation_from_nns,
+                user,
+                paths,
+                targets,
+                subscription,
+                expires_in,
+                _permit: permit,
+            };
+            let (sender, body) = Body::channel();
+            tokio::spawn(stream.run(sender));
+
+            let mut response = Response::new(body);
+            *response.status_mut() = StatusCode::OK;
+            *response.headers_mut() = get_cors_headers();
+            response.headers_mut().insert(
+                hyper::header::CONTENT_TYPE,
+                hyper::header::HeaderValue::from_static(CONTENT_TYPE_EVENT_STREAM),
+            );
+            response.headers_mut().insert(
+                hyper::header::CACHE_CONTROL,
+                hyper::header::HeaderValue::from_static("no-cache"),
+            );
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::common::test::state_reader_executor_with_certified_height;
+    use hyper::body::HttpBody;
+    use ic_crypto_tree_hash::{flatmap, Digest};
+    use ic_logger::replica_logger::no_op_logger;
+    use ic_test_utilities::{
+        mock_time,
+        state::ReplicatedStateBuilder,
+        types::ids::{canister_test_id, message_test_id, user_test_id},
+    };
+    use ic_types::{
+        ingress::{IngressState, IngressStatus, WasmResult},
+        Height, NumBytes,
+    };
+    use std::sync::Mutex;
+    use tokio::sync::watch;
+
+    // A certified state in which `message_id` was sent by `user`, or `None`
+    // if the message is not known yet.
+    fn certified_state(
+        message_id: &MessageId,
+        status: Option<(UserId, IngressState)>,
+        tree: MixedHashTree,
+    ) -> (Arc<ReplicatedState>, MixedHashTree) {
+        let mut state = ReplicatedStateBuilder::new().build();
+        if let Some((user_id, ingress_state)) = status {
+            state.set_ingress_status(
+                message_id.clone(),
+                IngressStatus::Known {
+                    receiver: canister_test_id(1).get(),
+                    user_id,
+                    time: mock_time(),
+                    state: ingress_state,
+                },
+                NumBytes::from(u64::MAX),
+            );
+        }
+        (Arc::new(state), tree)
+    }
+
+    // Runs a subscription of `user_test_id(1)` to the status of `message_id`
+    // against whatever `certified` holds whenever the certified height
+    // advances, and returns the response body and the height sender.
+    fn subscribe(
+        message_id: &MessageId,
+        certified: Arc<Mutex<(Arc<ReplicatedState>, MixedHashTree)>>,
+    ) -> (Body, watch::Sender<Height>) {
+        let (state_reader_executor, height_sender) =
+            state_reader_executor_with_certified_height(move || certified.lock().unwrap().clone());
+        let paths = vec![Path::new(vec![
+            Label::from("request_status"),
+            Label::from(message_id.as_bytes()),
+        ])];
+        let stream = SubscriptionStream {
+            log: no_op_logger(),
+            state_reader_executor,
+            delegation_from_nns: Arc::new(RwLock::new(None)),
+            user: user_test_id(1),
+            subscription: Subscription::parse(&paths).unwrap(),
+            paths,
+            targets: CanisterIdSet::All,
+            expires_in: Duration::from_secs(60),
+            _permit: Arc::new(Semaphore::new(1)).try_acquire_owned().unwrap(),
+        };
+        let (sender, body) = Body::channel();
+        tokio::spawn(stream.run(sender));
+        (body, height_sender)
+    }
+
+    #[tokio::test]
+    async fn subscription_pushes_changes_until_the_request_is_done() {
+        let message_id = message_test_id(1);
+        let certified = Arc::new(Mutex::new(certified_state(
+            &message_id,
+            Some((user_test_id(1), IngressState::Processing)),
+            MixedHashTree::Leaf(vec![1]),
+        )));
+        let (mut body, height_sender) = subscribe(&message_id, Arc::clone(&certified));
+
+        assert_eq!(
+            body.data().await.unwrap().unwrap(),
+            certificate_event(MixedHashTree::Leaf(vec![1]), vec![], None)
+        );
+
+        *certified.lock().unwrap() = certified_state(
+            &message_id,
+            Some((
+                user_test_id(1),
+                IngressState::Completed(WasmResult::Reply(vec![])),
+            )),
+            MixedHashTree::Leaf(vec![2]),
+        );
+        height_sender.send(Height::from(2)).unwrap();
+        assert_eq!(
+            body.data().await.unwrap().unwrap(),
+            certificate_event(MixedHashTree::Leaf(vec![2]), vec![], None)
+        );
+
+        // The request reached a terminal status, so the stream ends.
+        assert!(body.data().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn subscription_ends_once_the_request_is_inducted_for_another_user() {
+        let message_id = message_test_id(1);
+        let certified = Arc::new(Mutex::new(certified_state(
+            &message_id,
+            None,
+            MixedHashTree::Leaf(vec![1]),
+        )));
+        let (mut body, height_sender) = subscribe(&message_id, Arc::clone(&certified));
+
+        // An unknown request can be subscribed to by anyone.
+        assert_eq!(
+            body.data().await.unwrap().unwrap(),
+            certificate_event(MixedHashTree::Leaf(vec![1]), vec![], None)
+        );
+
+        *certified.lock().unwrap() = certified_state(
+            &message_id,
+            Some((user_test_id(2), IngressState::Processing)),
+            MixedHashTree::Leaf(vec![2]),
+        );
+        height_sender.send(Height::from(2)).unwrap();
+
+        // The status of another user's request is never pushed.
+        assert!(body.data().await.is_none());
+    }
+
+    #[test]
+    fn only_request_status_and_certified_data_paths_can_be_subscribed_to() {
+        let message_id = MessageId::from([1; 32]);
+        let subscription = Subscription::parse(&[
+            Path::new(vec![
+                Label::from("request_status"),
+                Label::from(message_id.as_bytes()),
+            ]),
+            Path::new(vec![
+                Label::from("canister"),
+                Label::from([0u8; 10]),
+                Label::from("certified_data"),
+            ]),
+        ])
+        .unwrap();
+        assert_eq!(
+            subscription,
+            Subscription {
+                request_ids: vec![message_id],
+                certified_data: true,
+            }
+        );
+
+        assert_eq!(
+            Subscription::parse(&[]).unwrap_err().status,
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            Subscription::parse(&[Path::from(Label::from("subnet"))])
+                .unwrap_err()
+                .status,
+            StatusCode::NOT_FOUND
+        );
+    }
+
+    #[test]
+    fn witnessed_values_ignore_time_and_pruned_subtrees() {
+        let tree = |time: u8, pruned: u8| {
+            MixedHashTree::Fork(Box::new((
+                MixedHashTree::Labeled(
+                    Label::from("request_status"),
+                    Box::new(MixedHashTree::Fork(Box::new((
+                        MixedHashTree::Pruned(Digest([pruned; 32])),
+                        MixedHashTree::Labeled(
+                            Label::from("status"),
+                            Box::new(MixedHashTree::Leaf(b"replied".to_vec())),
+                        ),
+                    )))),
+                ),
+                MixedHashTree::Labeled(
+                    Label::from("time"),
+                    Box::new(MixedHashTree::Leaf(vec![time])),
+                ),
+            )))
+        };
+        assert_eq!(witnessed_values(&tree(1, 1)), witnessed_values(&tree(2, 2)));
+        assert_eq!(
+            witnessed_values(&tree(1, 1)),
+            Some(LabeledTree::SubTree(flatmap! {
+                Label::from("request_status") => LabeledTree::SubTree(flatmap! {
+                    Label::from("status") => LabeledTree::Leaf(b"replied".to_vec())
+                })
+            }))
+        );
+    }
+}
@@ -27,7 +27,10 @@ use ic_types::{
     CanisterId, CountBytes, Cycles, Height, NumBytes, Time,
 };
 use ic_validator::{validate_request, RequestValidationError};
-use std::{collections::BTreeMap, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashSet, VecDeque},
+    sync::Arc,
+};
 
 impl<'a> IngressSelector for IngressManager {
     fn get_ingress_payload(
@@ -75,17 +78,93 @@ impl<'a> IngressSelector for IngressManager {
             .get_ingress_message_settings(context.registry_version)
             .expect("Couldn't fetch ingress message parameters from the registry.");
 
-        // Select valid ingress messages and stop once the total size
-        // becomes greater than byte_limit.
+        // Group the candidate messages by destination canister, keeping the
+        // order in which the pool yields them. Messages that are already part
+        // of past payloads are left out, and a canister's queue is capped at
+        // what could fill the payload on its own. Only the message ids are
+        // collected, the messages themselves are not copied.
+        let mut queues: BTreeMap<CanisterId, CanisterQueue> = BTreeMap::new();
+        self.ingress_pool.select_validated(
+            expiry_range.clone(),
+            Box::new(|ingress_obj| {
+                let ingress_id = IngressMessageId::from(ingress_obj);
+                if past_ingress_set.contains(&ingress_id) {
+                    return SelectResult::Skip;
+                }
+                let queue = queues
+                    .entry(ingress_obj.signed_ingress.canister_id())
+                    .or_default();
+                if queue.bytes <= byte_limit.get() as usize
+                    && queue.messages.len() < settings.max_ingress_messages_per_block
+                {
+                    let size = ingress_obj.signed_ingress.count_bytes();
+                    queue.bytes += size;
+                    queue.messages.push_back((ingress_id, size));
+                }
+                SelectResult::Skip
+            }),
+        );
+
+        // Choose the messages from the canisters in round-robin fashion, so
+        // that a canister flooding the pool cannot crowd out the others. In
+        // every round each canister contributes up to its selection weight in
+        // messages. The canister going first rotates with the certified
+        // height, so that no canister is favoured when the payload fills up in
+        // the middle of a round.
+        let mut queues: Vec<(usize, VecDeque<(IngressMessageId, usize)>)> = queues
+            .into_iter()
+            .map(|(canister_id, queue)| (selection_weight(&state, &canister_id), queue.messages))
+            .collect();
+        if !queues.is_empty() {
+            let first = certified_height.get() as usize % queues.len();
+            queues.rotate_left(first);
+        }
+        let mut chosen = HashSet::new();
+        let mut chosen_size = 0;
+        'rounds: while !queues.is_empty() {
+            for (weight, queue) in queues.iter_mut() {
+                for _ in 0..*weight {
+                    if chosen.len() >= settings.max_ingress_messages_per_block {
+                        break 'rounds;
+                    }
+                    let (ingress_id, size) = match queue.pop_front() {
+                        Some(message) => message,
+                        None => break,
+                    };
+                    // Stop choosing from this canister once its next message
+                    // does not fit anymore; the messages of others might.
+                    if chosen_size + size > byte_limit.get() as usize {
+                        queue.clear();
+                        break;
+                    }
+                    chosen_size += size;
+                    chosen.insert(ingress_id);
+                }
+            }
+            queues.retain(|(_, queue)| !queue.is_empty());
+        }
+
+        // Select the valid ones among the chosen messages and stop once all of
+        // them have been seen or the total size becomes greater than
+        // byte_limit.
         let mut accumulated_size = 0;
         let mut cycles_needed: BTreeMap<CanisterId, Cycles> = BTreeMap::new();
         let mut num_messages = 0;
+        let mut remaining = chosen.len();
 
         let mut messages_in_payload = self.ingress_pool.select_validated(
             expiry_range,
             Box::new(move |ingress_obj| {
+                if remaining == 0 {
+                    return SelectResult::Abort;
+                }
+                let ingress_id = IngressMessageId::from(ingress_obj);
+                if !chosen.contains(&ingress_id) {
+                    return SelectResult::Skip;
+                }
+                remaining -= 1;
                 let result = self.validate_ingress(
-                    IngressMessageId::from(ingress_obj),
+                    ingress_id,
                     &ingress_obj.signed_ingress,
                     &state,
                     context,
@@ -399,6 +478,23 @@ impl IngressManager {
     }
 }
 
+/// The messages of a single canister that are candidates for a payload.
+#[derive(Default)]
+struct CanisterQueue {
+    messages: VecDeque<(IngressMessageId, usize)>,
+    bytes: usize,
+}
+
+/// The number of messages a canister may contribute to a payload in each round
+/// of the selection: its compute allocation in percent, but at least one.
+fn selection_weight(state: &ReplicatedState, canister_id: &CanisterId) -> usize {
+    state
+        .canister_state(canister_id)
+        .map(|canister| canister.scheduler_state.compute_allocation.as_percent() as usize)
+        .unwrap_or_default()
+        .max(1)
+}
+
 /// An IngressSetQuery implementation based on IngressHistoryReader.
 struct IngressHistorySet {
     get_status: Box<dyn Fn(&MessageId) -> IngressStatus>,
@@ -12,6 +12,10 @@
 //! be impossible to cover in any reasonable time.
 //! We therefore build multiple proptests, where we keep most properties fixed and only leave a
 //! small number of values variable.
+//!
+//! Besides validity, the ingress selector has to be fair: a canister flooding the pool must not
+//! starve the other canisters that have messages in the pool, and messages that are already part
+//! of past payloads must not take up a canister's share.
 
 use crate::tests::{access_ingress_pool, setup_with_params};
 use ic_constants::MAX_INGRESS_TTL;
@@ -38,10 +42,19 @@ use ic_types::{
     CountBytes, Height, NumBytes, RegistryVersion,
 };
 use proptest::prelude::*;
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
+use std::convert::TryInto;
 
 const MAX_BLOCK_SIZE: u64 = 4 * 1024 * 1024;
 
+/// Block size of the fairness test, small enough for the flooding canister
+/// alone to fill it many times over.
+const FAIRNESS_BLOCK_SIZE: u64 = 64 * 1024;
+
+/// Number of canisters sending a few messages in the fairness test, next to
+/// the flooding canister 0.
+const FAIRNESS_MAX_CANISTERS: u64 = 16;
+
 proptest! {
     #![proptest_config(ProptestConfig {
         cases: 64,
@@ -117,7 +130,73 @@ proptest! {
         )
     }
 
+    #[test]
+    fn proptest_ingress_payload_builder_fairness(
+        (signed_ingress_vec, past_ingress) in prop_signed_ingress_vec_for_fairness_test(),
+        certified_height in 0..100u64,
+    ) {
+        let mut state = ReplicatedStateBuilder::default();
+        for i in 0..=FAIRNESS_MAX_CANISTERS {
+            state = state.with_canister(
+                CanisterStateBuilder::default()
+                    .with_canister_id(canister_test_id(i))
+                    .build(),
+            );
+        }
+        setup_with_params(
+            None,
+            None,
+            None,
+            Some(state.build()),
+            |ingress_manager, ingress_pool| {
+                let time_source = FastForwardTimeSource::new();
+                let validation_context = ValidationContext {
+                    time: mock_time(),
+                    registry_version: RegistryVersion::from(1),
+                    certified_height: Height::from(certified_height),
+                };
+
+                for m in signed_ingress_vec.iter() {
+                    let message_id = IngressMessageId::from(m);
+                    let attribute = IngressMessageAttribute::new(m);
+                    access_ingress_pool(&ingress_pool, |mut ingress_pool| {
+                        ingress_pool.insert(UnvalidatedArtifact {
+                            message: m.clone(),
+                            peer_id: node_test_id(0),
+                            timestamp: time_source.get_relative_time(),
+                        });
+                        ingress_pool.apply_changeset(vec![ChangeAction::MoveToValidated((
+                            message_id.clone(),
+                            node_test_id(0),
+                            m.count_bytes(),
+                            attribute,
+                            crypto_hash(m.binary()).get(),
+                        ))]);
+                    });
+                }
+
+                let payload = ingress_manager.get_ingress_payload(
+                    &past_ingress,
+                    &validation_context,
+                    NumBytes::new(FAIRNESS_BLOCK_SIZE),
+                );
+                assert!((payload.count_bytes() as u64) < FAIRNESS_BLOCK_SIZE);
+                assert!(ingress_manager.validate_ingress_payload(&payload, &past_ingress, &validation_context).is_ok());
 
+                // Every canister with messages in the pool that are not part of past payloads made
+                // it into the payload.
+                let in_pool: BTreeSet<_> = signed_ingress_vec
+                    .iter()
+                    .filter(|m| !past_ingress.contains(&IngressMessageId::from(*m)))
+                    .map(|m| m.canister_id())
+                    .collect();
+                let messages: Vec<SignedIngress> = payload.try_into().unwrap();
+                assert!(messages.iter().all(|m| !past_ingress.contains(&IngressMessageId::from(m))));
+                let in_payload: BTreeSet<_> = messages.iter().map(|m| m.canister_id()).collect();
+                assert_eq!(in_pool, in_payload);
+            },
+        )
+    }
 }
 
 /// Props up a mock ingress message, which varies in size.
@@ -140,3 +219,54 @@ fn prop_signed_ingress_for_size_test(
 fn prop_signed_ingress_vec_for_size_test() -> impl Strategy<Value = Vec<SignedIngress>> {
     prop::collection::vec(prop_signed_ingress_for_size_test(0, 1024), 1..6000)
 }
+
+/// Props up the pool contents of the fairness test along with the messages of past payloads: many
+/// messages to canister 0 that expire first, and thus come first out of the pool, a prefix of which
+/// is part of past payloads, and a few messages to each of a number of other canisters, the first of
+/// which may be part of past payloads.
+fn prop_signed_ingress_vec_for_fairness_test(
+) -> impl Strategy<Value = (Vec<SignedIngress>, HashSet<IngressMessageId>)> {
+    (
+        500..1500usize,
+        0..=100usize,
+        prop::collection::vec(
+            (1..5usize, any::<bool>()),
+            1..=FAIRNESS_MAX_CANISTERS as usize,
+        ),
+    )
+        .prop_map(|(flood, flood_past_percent, others)| {
+            let mut nonce = 0;
+            let mut messages = vec![];
+            let mut past_ingress = HashSet::new();
+            let mut push = |canister, expiry_time, in_past| {
+                let message = SignedIngressBuilder::new()
+                    .canister_id(canister_test_id(canister))
+                    .method_name("Fairness proptest")
+                    .nonce(nonce)
+                    .expiry_time(expiry_time)
+                    .build();
+                if in_past {
+                    past_ingress.insert(IngressMessageId::from(&message));
+                }
+                messages.push(message);
+                nonce += 1;
+            };
+            for i in 0..flood {
+                push(
+                    0,
+                    mock_time() + MAX_INGRESS_TTL / 2,
+                    i * 100 < flood * flood_past_percent,
+                );
+            }
+            for (i, (count, first_in_past)) in others.into_iter().enumerate() {
+                for j in 0..count {
+                    push(
+                        i as u64 + 1,
+                        mock_time() + MAX_INGRESS_TTL,
+                        first_in_past && j == 0,
+                    );
+                }
+            }
+            (messages, past_ingress)
+        })
+}
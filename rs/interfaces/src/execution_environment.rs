@@ -9,6 +9,7 @@ use ic_registry_provisional_whitelist::ProvisionalWhitelist;
 use ic_registry_subnet_type::SubnetType;
 use ic_sys::{PageBytes, PageIndex};
 use ic_types::{
+    canister_log::CanisterLog,
     crypto::canister_threshold_sig::MasterEcdsaPublicKey,
     ingress::{IngressStatus, WasmResult},
     messages::{
@@ -313,6 +314,20 @@ pub trait SystemApi {
     /// Returns the reference to the execution error.
     fn get_execution_error(&self) -> Option<&HypervisorError>;
 
+    /// Appends a record with the given content to the canister log.
+    fn append_canister_log(&mut self, content: Vec<u8>);
+
+    /// Stores the specified bytes on the heap in the canister log without
+    /// printing them. Used for debug prints that are rate-limited.
+    fn save_log_message(&mut self, src: u32, size: u32, heap: &[u8]);
+
+    /// Returns the canister log records produced during the execution and
+    /// clears them.
+    fn take_canister_log(&mut self) -> CanisterLog;
+
+    /// Returns true if the execution read the data certificate.
+    fn data_certificate_accessed(&self) -> bool;
+
     /// Returns the amount of instructions needed to copy `num_bytes`.
     fn get_num_instructions_from_bytes(&self, num_bytes: NumBytes) -> NumInstructions;
 
@@ -458,8 +473,9 @@ pub trait SystemApi {
         heap: &mut [u8],
     ) -> HypervisorResult<()>;
 
-    /// Outputs the specified bytes on the heap as a string on STDOUT.
-    fn ic0_debug_print(&self, src: u32, size: u32, heap: &[u8]) -> HypervisorResult<()>;
+    /// Outputs the specified bytes on the heap as a string on STDOUT and
+    /// stores them in the canister log.
+    fn ic0_debug_print(&mut self, src: u32, size: u32, heap: &[u8]) -> HypervisorResult<()>;
 
     /// Traps, with a possibly helpful message
     fn ic0_trap(&self, src: u32, size: u32, heap: &[u8]) -> HypervisorResult<()>;
@@ -789,18 +805,18 @@ pub trait SystemApi {
     /// returns 1 if the data certificate is present, 0 otherwise.
     /// If run in replicated execution (i.e. an update call or a certified
     /// query), returns 0.
-    fn ic0_data_certificate_present(&self) -> HypervisorResult<i32>;
+    fn ic0_data_certificate_present(&mut self) -> HypervisorResult<i32>;
 
     /// Returns the size of the data certificate if it is present
     /// (i.e. data_certificate_present returns 1).
     /// Traps if data_certificate_present returns 0.
-    fn ic0_data_certificate_size(&self) -> HypervisorResult<i32>;
+    fn ic0_data_certificate_size(&mut self) -> HypervisorResult<i32>;
 
     /// Copies the data certificate into the heap if it is present
     /// (i.e. data_certificate_present returns 1).
     /// Traps if data_certificate_present returns 0.
     fn ic0_data_certificate_copy(
-        &self,
+        &mut self,
         dst: u32,
         offset: u32,
         size: u32,
@@ -903,6 +919,12 @@ pub struct WasmExecutionOutput {
     pub allocated_bytes: NumBytes,
     pub allocated_message_bytes: NumBytes,
     pub instance_stats: InstanceStats,
+    /// Canister log records produced during the execution. They are appended
+    /// to the canister log regardless of whether the execution succeeded.
+    pub canister_log: CanisterLog,
+    /// True if the execution read the data certificate, i.e. its result may
+    /// depend on the certificate.
+    pub data_certificate_accessed: bool,
 }
 
 impl fmt::Display for WasmExecutionOutput {
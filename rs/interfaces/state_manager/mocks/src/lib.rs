@@ -1,7 +1,7 @@
 use ic_crypto_tree_hash::{LabeledTree, MixedHashTree};
 use ic_interfaces_state_manager::{
-    CertificationMask, CertificationScope, Labeled, StateHashError, StateManager,
-    StateManagerResult, StateReader,
+    CertificationMask, CertificationScope, CertifiedHeightReceiver, Labeled, StateHashError,
+    StateManager, StateManagerResult, StateReader,
 };
 use ic_replicated_state::ReplicatedState;
 use ic_types::{
@@ -24,6 +24,8 @@ mock! {
 
         fn latest_certified_height(&self) -> Height;
 
+        fn subscribe_certified_height(&self) -> CertifiedHeightReceiver;
+
         fn read_certified_state(
             &self,
             _paths: &LabeledTree<()>
@@ -7,6 +7,10 @@ use phantom_newtype::BitMask;
 use std::sync::Arc;
 use thiserror::Error;
 
+/// Observes the height of the latest certified state. See
+/// `StateReader::subscribe_certified_height`.
+pub type CertifiedHeightReceiver = tokio::sync::watch::Receiver<Height>;
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum StateManagerError {
     /// The state at the specified height was removed and cannot be recovered
@@ -365,6 +369,11 @@ pub trait StateReader: Send + Sync {
     /// Returns the height of the latest certified state available.
     fn latest_certified_height(&self) -> Height;
 
+    /// Returns a receiver of the height of the latest certified state
+    /// available, which is notified every time a newer state gets certified.
+    /// This allows waiting for the certification of a state without polling.
+    fn subscribe_certified_height(&self) -> CertifiedHeightReceiver;
+
     /// Reads part of the certified state tree specified by the shape of
     /// `paths`.  Path can reference either a leaf or a subtree.  E.g.,
     ///  if the tree looks like this:
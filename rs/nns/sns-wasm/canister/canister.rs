@@ -143,6 +143,7 @@ impl CanisterApi for CanisterApiImpl {
                 compute_allocation: None,
                 memory_allocation: None,
                 freezing_threshold: None,
+                log_visibility: None,
             },
         };
 
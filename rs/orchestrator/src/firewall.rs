@@ -185,6 +185,22 @@ impl Firewall {
         // Insert the whitelisting rule at the top of the list (highest priority)
         rules.insert(0, node_whitelisting_rule);
 
+        // The transport may run over QUIC, so nodes are also whitelisted on its
+        // UDP port. Rules from the registry and the config file only apply to
+        // TCP, so this is the only UDP rule.
+        let mut udp_rules = Vec::new();
+        if !self.configuration.udp_ports_for_node_whitelist.is_empty() {
+            udp_rules.push(FirewallRule {
+                ipv4_prefixes: node_ipv4s.clone(),
+                ipv6_prefixes: node_ipv6s.clone(),
+                ports: self.configuration.udp_ports_for_node_whitelist.clone(),
+                action: FirewallAction::Allow as i32,
+                comment: "Automatic node whitelisting (UDP)".to_string(),
+                user: None,
+                direction: Some(FirewallRuleDirection::Inbound as i32),
+            });
+        }
+
         // Blacklisting for Canister HTTP requests
         // In addition to any explicit firewall rules we might apply, we also ALWAYS blacklist the ic-http-adapter used from accessing
         // all nodes in the registry on specific ports defined in the config file.
@@ -206,7 +222,8 @@ impl Firewall {
         rules.insert(0, ic_http_adapter_rule);
 
         // Generate the firewall file content
-        let content = Self::generate_firewall_file_content_full(&self.configuration, rules);
+        let content =
+            Self::generate_firewall_file_content_full(&self.configuration, rules, udp_rules);
 
         let changed = content.ne(&self.compiled_config);
         if changed {
@@ -256,6 +273,7 @@ impl Firewall {
     fn generate_firewall_file_content_full(
         config: &FirewallConfig,
         rules: Vec<FirewallRule>,
+        udp_rules: Vec<FirewallRule>,
     ) -> String {
         config
             .file_template
@@ -281,6 +299,22 @@ impl Firewall {
                     ],
                 ),
             )
+            .replace(
+                "<<IPv4_UDP_RULES>>",
+                &Self::compile_rules(
+                    &config.ipv4_udp_rule_template,
+                    &udp_rules,
+                    vec![FirewallRuleDirection::Inbound],
+                ),
+            )
+            .replace(
+                "<<IPv6_UDP_RULES>>",
+                &Self::compile_rules(
+                    &config.ipv6_udp_rule_template,
+                    &udp_rules,
+                    vec![FirewallRuleDirection::Inbound],
+                ),
+            )
             .replace(
                 "<<IPv4_OUTBOUND_RULES>>",
                 &Self::compile_rules(
@@ -412,7 +446,18 @@ fn test_firewall_rule_compilation() {
         "{} {} {} {}",
         "<<IPv6_PREFIXES>>", "<<PORTS>>", "<<ACTION>>", "<<COMMENT>>"
     );
-    let file_template = format!("{} {}", "<<IPv4_RULES>>", "<<IPv6_RULES>>");
+    let ipv4_udp_rule_template = format!(
+        "udp {} {} {} {}",
+        "<<IPv4_PREFIXES>>", "<<PORTS>>", "<<ACTION>>", "<<COMMENT>>"
+    );
+    let ipv6_udp_rule_template = format!(
+        "udp {} {} {} {}",
+        "<<IPv6_PREFIXES>>", "<<PORTS>>", "<<ACTION>>", "<<COMMENT>>"
+    );
+    let file_template = format!(
+        "{} {} {} {}",
+        "<<IPv4_RULES>>", "<<IPv4_UDP_RULES>>", "<<IPv6_RULES>>", "<<IPv6_UDP_RULES>>"
+    );
 
     let rules = vec![
         FirewallRule {
@@ -453,6 +498,16 @@ fn test_firewall_rule_compilation() {
         },
     ];
 
+    let udp_rules = vec![FirewallRule {
+        ipv4_prefixes: vec!["test_ipv4_5".to_string()],
+        ipv6_prefixes: vec!["test_ipv6_5".to_string()],
+        ports: vec![13],
+        action: 1,
+        comment: "comment5".to_string(),
+        user: None,
+        direction: Some(FirewallRuleDirection::Inbound as i32),
+    }];
+
     let expected_rules_compiled_v4 = vec![
         format!("{} {} {} {}", "test_ipv4_1", "1,2,3", "accept", "comment1"),
         format!("{} {} {} {}", "test_ipv4_2", "4,5,6", "drop", "comment2"),
@@ -462,9 +517,11 @@ fn test_firewall_rule_compilation() {
         format!("{} {} {} {}", "test_ipv6_3", "7,8,9", "drop", "comment3"),
     ];
     let expected_file_content = format!(
-        "{} {}",
+        "{} {} {} {}",
         expected_rules_compiled_v4.join("\n"),
-        expected_rules_compiled_v6.join("\n")
+        format!("udp {} {} {} {}", "test_ipv4_5", "13", "accept", "comment5"),
+        expected_rules_compiled_v6.join("\n"),
+        format!("udp {} {} {} {}", "test_ipv6_5", "13", "accept", "comment5"),
     );
 
     let config = FirewallConfig {
@@ -474,13 +531,16 @@ fn test_firewall_rule_compilation() {
         ipv6_rule_template,
         ipv4_user_output_rule_template: "".to_string(),
         ipv6_user_output_rule_template: "".to_string(),
+        ipv4_udp_rule_template,
+        ipv6_udp_rule_template,
         default_rules: vec![],
         ports_for_node_whitelist: vec![],
+        udp_ports_for_node_whitelist: vec![],
         ports_for_http_adapter_blacklist: vec![],
     };
 
     assert_eq!(
         expected_file_content,
-        Firewall::generate_firewall_file_content_full(&config, rules)
+        Firewall::generate_firewall_file_content_full(&config, rules, udp_rules)
     );
 }
@@ -24,6 +24,12 @@
 //!    index orders the downloads in increasing order of their
 //!    expiry-instant. Note: This index may contain multiple downloads
 //!    expiring at a given expiry-instant.
+//!
+//! Artifacts are downloaded using the chunk tracker provided by the artifact
+//! manager, except for large artifacts that the artifact manager would track
+//! as a single chunk. These are downloaded in multiple chunks (see
+//! [`multi_chunked`](ic_types::multi_chunked)), so that the chunks can be
+//! requested from several peers in parallel.
 
 use ic_interfaces::artifact_manager::ArtifactManager;
 use ic_logger::{replica_logger::ReplicaLogger, warn};
@@ -32,11 +38,12 @@ use ic_types::{
     artifact::ArtifactId,
     chunkable::Chunkable,
     crypto::CryptoHash,
+    multi_chunked::{is_multi_chunked, Manifest, MultiChunked},
     p2p::{GossipAdvert, MAX_ARTIFACT_TIMEOUT},
     NodeId,
 };
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, BTreeSet, HashMap},
     time::{Duration, Instant},
 };
 
@@ -109,6 +116,15 @@ pub(crate) struct ArtifactTracker {
     pub chunkable: Box<dyn Chunkable + Send + Sync>,
     /// The ID of the node whose quota is charged for this artifact.
     pub peer_id: NodeId,
+    /// True if the artifact is downloaded in multiple chunks although its
+    /// artifact type is single-chunked.
+    pub multi_chunked: bool,
+    /// The manifest of the chunks received from each peer for a
+    /// multi-chunked artifact.
+    pub manifests: BTreeMap<NodeId, Manifest>,
+    /// The manifests of a multi-chunked artifact whose slices do not form the
+    /// advertised artifact.
+    pub rejected_manifests: BTreeSet<Manifest>,
     // Stores the e2e duration of downloading the artifact.
     duration: Instant,
 }
@@ -119,12 +135,16 @@ impl ArtifactTracker {
         expiry_instant: Instant,
         chunkable: Box<dyn Chunkable + Send + Sync>,
         peer_id: NodeId,
+        multi_chunked: bool,
     ) -> Self {
         ArtifactTracker {
             artifact_id,
             expiry_instant,
             chunkable,
             peer_id,
+            multi_chunked,
+            manifests: BTreeMap::new(),
+            rejected_manifests: BTreeSet::new(),
             duration: Instant::now(),
         }
     }
@@ -178,7 +198,15 @@ impl ArtifactDownloadList for ArtifactDownloadListImpl {
                 Some(_) => { /* enough quota remaining */ }
             }
 
-            if let Some(chunk_tracker) = artifact_manager.get_chunk_tracker(&advert.artifact_id) {
+            // Large artifacts of single-chunked types are downloaded in multiple
+            // chunks instead.
+            let multi_chunked = is_multi_chunked(advert);
+            let chunk_tracker = if multi_chunked {
+                Some(Box::new(MultiChunked::new(advert.size)) as Box<dyn Chunkable + Send + Sync>)
+            } else {
+                artifact_manager.get_chunk_tracker(&advert.artifact_id)
+            };
+            if let Some(chunk_tracker) = chunk_tracker {
                 let requested_instant = Instant::now();
                 // Calculate the worst-case time estimate for the artifact download, which
                 // assumes that all chunks for the artifact will time out for
@@ -200,6 +228,7 @@ impl ArtifactDownloadList for ArtifactDownloadListImpl {
                         expiry_instant,
                         chunk_tracker,
                         peer_id,
+                        multi_chunked,
                     ),
                 );
                 self.expiry_index
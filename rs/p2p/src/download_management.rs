@@ -90,6 +90,7 @@ use ic_types::{
     artifact::{Artifact, ArtifactFilter, ArtifactId, ArtifactTag},
     chunkable::{ArtifactErrorCode, ChunkId},
     crypto::CryptoHash,
+    multi_chunked::{chunk_manifest, is_oversized, is_slice_chunk, Manifest},
     p2p::GossipAdvert,
     NodeId, RegistryVersion,
 };
@@ -137,6 +138,20 @@ impl GossipImpl {
             return;
         }
 
+        // Adverts of artifacts larger than any valid artifact are ignored, as
+        // downloading them would only waste bandwidth and memory.
+        if is_oversized(&gossip_advert) {
+            warn!(
+                every_n_seconds => 30,
+                self.log,
+                "Dropping advert of oversized artifact {:?} from node {:?}",
+                gossip_advert.artifact_id,
+                peer_id
+            );
+            self.metrics.adverts_dropped.inc();
+            return;
+        }
+
         let mut current_peers = self.current_peers.lock();
         if let Some(_peer_context) = current_peers.get_mut(&peer_id) {
             let _ = self.prioritizer.add_advert(gossip_advert, peer_id);
@@ -268,15 +283,28 @@ impl GossipImpl {
                 peer_id
             );
             if let P2PErrorCode::NotFound = error.p2p_error_code {
-                // If the artifact is not found on the sender's side, drop the
-                // advert from the context for this peer to prevent it from
-                // being requested again from this peer.
-                self.delete_advert_from_peer(
-                    peer_id,
-                    &gossip_chunk.artifact_id,
-                    &gossip_chunk.integrity_hash,
-                    self.artifacts_under_construction.write().deref_mut(),
-                )
+                let mut artifacts_under_construction = self.artifacts_under_construction.write();
+                if is_slice_chunk(&gossip_chunk.artifact_id, gossip_chunk.chunk_id) {
+                    // Peers running a replica version without multi-chunked
+                    // artifacts do not serve slices. The download is continued
+                    // as a single chunk, which may still be requested from
+                    // this peer.
+                    self.fall_back_to_single_chunk(
+                        &gossip_chunk.artifact_id,
+                        &gossip_chunk.integrity_hash,
+                        artifacts_under_construction.deref_mut(),
+                    );
+                } else {
+                    // If the artifact is not found on the sender's side, drop the
+                    // advert from the context for this peer to prevent it from
+                    // being requested again from this peer.
+                    self.delete_advert_from_peer(
+                        peer_id,
+                        &gossip_chunk.artifact_id,
+                        &gossip_chunk.integrity_hash,
+                        artifacts_under_construction.deref_mut(),
+                    )
+                }
             }
             return;
         }
@@ -308,12 +336,49 @@ impl GossipImpl {
             return;
         }
         let artifact_tracker = artifact_tracker.unwrap();
+        let artifact_chunk = gossip_chunk.artifact_chunk.unwrap();
+
+        // Honest peers serve a single manifest for a multi-chunked artifact. A
+        // chunk is faulty if it does not match its own manifest, if its manifest
+        // differs from the one of earlier chunks of the same peer, or if its
+        // manifest was rejected.
+        let manifest = if artifact_tracker.multi_chunked {
+            match chunk_manifest(&artifact_chunk) {
+                Ok(manifest)
+                    if !artifact_tracker.rejected_manifests.contains(&manifest)
+                        && artifact_tracker
+                            .manifests
+                            .get(&peer_id)
+                            .map_or(true, |peer_manifest| *peer_manifest == manifest) =>
+                {
+                    artifact_tracker.manifests.insert(peer_id, manifest.clone());
+                    Some(manifest)
+                }
+                _ => {
+                    trace!(
+                        self.log,
+                        "Chunk verification failed for artifact{:?} chunk {:?} from peer {:?}",
+                        gossip_chunk.artifact_id,
+                        gossip_chunk.chunk_id,
+                        peer_id
+                    );
+                    self.metrics.chunks_verification_failed.inc();
+                    // Faulty peers are no longer asked for this artifact.
+                    self.delete_advert_from_peer(
+                        peer_id,
+                        &gossip_chunk.artifact_id,
+                        &gossip_chunk.integrity_hash,
+                        artifacts_under_construction.deref_mut(),
+                    );
+                    return;
+                }
+            }
+        } else {
+            None
+        };
 
         // Feed the chunk to the tracker.
-        let completed_artifact = match artifact_tracker
-            .chunkable
-            .add_chunk(gossip_chunk.artifact_chunk.unwrap())
-        {
+        let completed_artifact = match artifact_tracker.chunkable.add_chunk(artifact_chunk) {
             // Artifact assembly is complete.
             Ok(artifact) => Some(artifact),
             Err(ArtifactErrorCode::ChunksMoreNeeded) => None,
@@ -326,11 +391,21 @@ impl GossipImpl {
                     peer_id
                 );
                 self.metrics.chunks_verification_failed.inc();
+                // The chunk matches its manifest, so the manifest itself is
+                // wrong.
+                if let Some(manifest) = &manifest {
+                    self.reject_manifest(
+                        &gossip_chunk.artifact_id,
+                        &gossip_chunk.integrity_hash,
+                        manifest,
+                        artifacts_under_construction.deref_mut(),
+                    );
+                }
                 None
             }
         };
 
-        // Return if the artifact is complete.
+        // Return if the artifact is not complete.
         if completed_artifact.is_none() {
             return;
         }
@@ -389,14 +464,26 @@ impl GossipImpl {
             );
             self.metrics.integrity_hash_check_failed.inc();
 
-            // The advert is deleted from this particular peer. Gossip may fetch the
-            // artifact again from another peer.
-            let _ = self.prioritizer.delete_advert_from_peer(
-                &gossip_chunk.artifact_id,
-                &gossip_chunk.integrity_hash,
-                peer_id,
-                AdvertTrackerFinalAction::Abort,
-            );
+            match &manifest {
+                // The slices of a multi-chunked artifact match its manifest, so
+                // the manifest is wrong.
+                Some(manifest) => self.reject_manifest(
+                    &gossip_chunk.artifact_id,
+                    &gossip_chunk.integrity_hash,
+                    manifest,
+                    artifacts_under_construction.deref_mut(),
+                ),
+                // The advert is deleted from this particular peer. Gossip may fetch the
+                // artifact again from another peer.
+                None => {
+                    let _ = self.prioritizer.delete_advert_from_peer(
+                        &gossip_chunk.artifact_id,
+                        &gossip_chunk.integrity_hash,
+                        peer_id,
+                        AdvertTrackerFinalAction::Abort,
+                    );
+                }
+            }
             return;
         }
 
@@ -1045,6 +1132,29 @@ impl GossipImpl {
                 current_peers.len() as u32,
                 self.artifact_manager.as_ref(),
             ) {
+                // The chunks of a multi-chunked artifact are spread over the peers
+                // that advertised it, so that a slow peer only holds back its share
+                // of the chunks.
+                let max_chunk_requests = if artifact_tracker.multi_chunked {
+                    let num_chunks = artifact_tracker.chunkable.chunks_to_download().count();
+                    let num_peers = std::cmp::max(advert_tracker.peers.len(), 1);
+                    let in_flight = current_peers
+                        .get(&peer_id)
+                        .map(|peer_context| {
+                            peer_context
+                                .requested
+                                .keys()
+                                .filter(|key| {
+                                    key.integrity_hash == advert_tracker.advert.integrity_hash
+                                })
+                                .count()
+                        })
+                        .unwrap_or(0);
+                    ((num_chunks + num_peers - 1) / num_peers).saturating_sub(in_flight)
+                } else {
+                    usize::MAX
+                };
+
                 // Collect gossip requests that can be initiated for this artifact.
                 // The function get_chunk_request() returns requests for chunks that satisfy
                 // chunk download constraints. These requests are collected and download
@@ -1059,7 +1169,10 @@ impl GossipImpl {
                                 req
                             })
                     })
-                    .take(num_downloadable_chunks - requests.len());
+                    .take(std::cmp::min(
+                        num_downloadable_chunks - requests.len(),
+                        max_chunk_requests,
+                    ));
 
                 // Extend the requests to be send out to this peer
                 requests.extend(new_chunk_requests);
@@ -1112,6 +1225,67 @@ impl GossipImpl {
         }
     }
 
+    /// The method continues the download of a multi-chunked artifact as a
+    /// single chunk.
+    ///
+    /// The method does nothing if the artifact is not downloaded in multiple
+    /// chunks (anymore).
+    fn fall_back_to_single_chunk(
+        &self,
+        artifact_id: &ArtifactId,
+        integrity_hash: &CryptoHash,
+        artifacts_under_construction: &mut dyn ArtifactDownloadList,
+    ) {
+        let artifact_tracker = match artifacts_under_construction.get_tracker(integrity_hash) {
+            Some(artifact_tracker) if artifact_tracker.multi_chunked => artifact_tracker,
+            _ => return,
+        };
+        if let Some(chunk_tracker) = self.artifact_manager.get_chunk_tracker(artifact_id) {
+            artifact_tracker.chunkable = chunk_tracker;
+            artifact_tracker.multi_chunked = false;
+            artifact_tracker.manifests.clear();
+            artifact_tracker.rejected_manifests.clear();
+            self.metrics.multi_chunked_downloads_fallen_back.inc();
+        }
+    }
+
+    /// The method rejects a manifest of a multi-chunked artifact whose slices
+    /// do not form the advertised artifact.
+    ///
+    /// The peers that served the manifest are no longer asked for the
+    /// artifact, and later chunks with this manifest are faulty. The download
+    /// continues with the manifests of the other peers.
+    fn reject_manifest(
+        &self,
+        artifact_id: &ArtifactId,
+        integrity_hash: &CryptoHash,
+        manifest: &Manifest,
+        artifacts_under_construction: &mut dyn ArtifactDownloadList,
+    ) {
+        let faulty_peers: Vec<NodeId> =
+            match artifacts_under_construction.get_tracker(integrity_hash) {
+                Some(artifact_tracker) => {
+                    artifact_tracker.rejected_manifests.insert(manifest.clone());
+                    artifact_tracker
+                        .manifests
+                        .iter()
+                        .filter(|(_, peer_manifest)| *peer_manifest == manifest)
+                        .map(|(peer_id, _)| *peer_id)
+                        .collect()
+                }
+                None => return,
+            };
+        self.metrics.multi_chunked_manifests_rejected.inc();
+        for peer_id in faulty_peers {
+            self.delete_advert_from_peer(
+                peer_id,
+                artifact_id,
+                integrity_hash,
+                artifacts_under_construction,
+            );
+        }
+    }
+
     /// The method processes timed-out artifacts.
     ///
     /// This method is called by the method on_timer(). It checks if there are
@@ -1274,6 +1448,7 @@ pub mod tests {
         artifact,
         artifact::{Artifact, ArtifactAttribute, ArtifactPriorityFn, Priority},
         chunkable::{ArtifactChunk, ArtifactChunkData, Chunkable, ChunkableArtifact},
+        multi_chunked::{SlicedArtifact, CHUNK_SIZE, MAX_ARTIFACT_SIZE},
         Height, NodeId, PrincipalId,
     };
     use std::collections::HashSet;
@@ -2034,6 +2209,277 @@ pub mod tests {
             gossip.metrics.integrity_hash_check_failed.get() as usize
         );
     }
+    /// The function returns the adverts of the given number of artifacts that
+    /// are large enough to be downloaded in multiple chunks.
+    fn multi_chunked_test_create_adverts(
+        range: Range<u32>,
+        num_chunks: usize,
+    ) -> Vec<GossipAdvert> {
+        receive_check_test_create_adverts(range)
+            .into_iter()
+            .map(|mut advert| {
+                advert.size = num_chunks * CHUNK_SIZE;
+                advert
+            })
+            .collect()
+    }
+
+    /// The function returns the given chunk of the multi-chunked message.
+    fn multi_chunked_test_create_chunk(
+        chunk_id: ChunkId,
+        advert: &GossipAdvert,
+        number: u32,
+    ) -> GossipChunk {
+        let artifact_chunk =
+            SlicedArtifact::new(Box::new(receive_check_test_create_message(number)))
+                .unwrap()
+                .get_chunk(chunk_id)
+                .unwrap();
+        GossipChunk {
+            artifact_id: advert.artifact_id.clone(),
+            integrity_hash: advert.integrity_hash.clone(),
+            chunk_id,
+            artifact_chunk: Ok(artifact_chunk),
+        }
+    }
+
+    /// The function tests that the chunks of a multi-chunked artifact are
+    /// requested from all peers that advertised the artifact.
+    #[tokio::test]
+    async fn download_manager_multi_chunked_artifacts_are_spread_over_peers() {
+        // There are 4 replicas in total. 3 peers advertise an artifact with 6
+        // chunks, so each peer is requested 2 of the chunks.
+        let num_replicas = 4;
+        let logger = p2p_test_setup_logger();
+        let gossip = new_test_gossip(num_replicas, &logger, tokio::runtime::Handle::current());
+        let advert = multi_chunked_test_create_adverts(0..1, 6).remove(0);
+        for peer_id in 1..num_replicas {
+            gossip.on_advert(advert.clone(), node_test_id(peer_id as u64));
+        }
+
+        let mut requested_chunks = HashSet::new();
+        for peer_id in 1..num_replicas {
+            let chunks_to_be_downloaded = gossip
+                .download_next_compute_work(node_test_id(peer_id as u64))
+                .unwrap();
+            assert_eq!(chunks_to_be_downloaded.len(), 2);
+            for chunk_req in chunks_to_be_downloaded {
+                assert_eq!(chunk_req.artifact_id, advert.artifact_id);
+                assert_ne!(chunk_req.chunk_id, ChunkId::from(0));
+                assert!(requested_chunks.insert(chunk_req.chunk_id));
+            }
+        }
+        assert_eq!(requested_chunks.len(), 6);
+    }
+
+    /// The function tests that a multi-chunked artifact is assembled from its
+    /// chunks and delivered.
+    #[tokio::test]
+    async fn multi_chunked_receive_test() {
+        let logger = p2p_test_setup_logger();
+        let gossip = new_test_gossip(2, &logger, tokio::runtime::Handle::current());
+        let node_id = node_test_id(1);
+        let advert = multi_chunked_test_create_adverts(0..1, 2).remove(0);
+        gossip.on_advert(advert.clone(), node_id);
+
+        let chunks_to_be_downloaded = gossip.download_next_compute_work(node_id).unwrap();
+        assert_eq!(chunks_to_be_downloaded.len(), 2);
+
+        // The message fits into a single slice, which completes the artifact.
+        let gossip_chunk =
+            multi_chunked_test_create_chunk(chunks_to_be_downloaded[0].chunk_id, &advert, 0);
+        gossip.on_chunk(gossip_chunk, node_id);
+
+        assert!(gossip
+            .artifacts_under_construction
+            .write()
+            .get_tracker(&advert.integrity_hash)
+            .is_none());
+        let receive_check_caches = gossip.receive_check_caches.read();
+        let cache = &receive_check_caches.get(&node_id).unwrap();
+        assert!(cache.contains(&advert.integrity_hash));
+    }
+
+    /// The function tests that the manifest of a multi-chunked artifact with an
+    /// incorrect integrity hash is rejected, and that the download continues
+    /// without the peer that provided the manifest.
+    #[tokio::test]
+    async fn multi_chunked_integrity_hash_test() {
+        let logger = p2p_test_setup_logger();
+        let gossip = new_test_gossip(3, &logger, tokio::runtime::Handle::current());
+        let advert = multi_chunked_test_create_adverts(0..1, 2).remove(0);
+        for peer_id in 1..3 {
+            gossip.on_advert(advert.clone(), node_test_id(peer_id));
+        }
+
+        let node_id = node_test_id(1);
+        let chunks_to_be_downloaded = gossip.download_next_compute_work(node_id).unwrap();
+        assert_eq!(chunks_to_be_downloaded.len(), 1);
+
+        // Provide a chunk of a different message.
+        let gossip_chunk =
+            multi_chunked_test_create_chunk(chunks_to_be_downloaded[0].chunk_id, &advert, 1);
+        gossip.on_chunk(gossip_chunk, node_id);
+
+        assert_eq!(gossip.metrics.integrity_hash_check_failed.get(), 1);
+        assert_eq!(gossip.metrics.multi_chunked_manifests_rejected.get(), 1);
+        assert_eq!(
+            gossip
+                .artifacts_under_construction
+                .write()
+                .get_tracker(&advert.integrity_hash)
+                .unwrap()
+                .rejected_manifests
+                .len(),
+            1
+        );
+        let advert_tracker = gossip
+            .prioritizer
+            .get_advert_tracker(&advert.artifact_id, &advert.integrity_hash)
+            .unwrap();
+        assert_eq!(advert_tracker.read().unwrap().peers, vec![node_test_id(2)]);
+
+        // The artifact can be downloaded from the remaining peer.
+        let chunks_to_be_downloaded = gossip.download_next_compute_work(node_test_id(2)).unwrap();
+        assert_eq!(chunks_to_be_downloaded.len(), 2);
+    }
+
+    /// The function tests that a chunk of a multi-chunked artifact that does
+    /// not match its own manifest is attributed to the peer that sent it.
+    #[tokio::test]
+    async fn multi_chunked_faulty_chunk_test() {
+        let logger = p2p_test_setup_logger();
+        let gossip = new_test_gossip(3, &logger, tokio::runtime::Handle::current());
+        let advert = multi_chunked_test_create_adverts(0..1, 2).remove(0);
+        for peer_id in 1..3 {
+            gossip.on_advert(advert.clone(), node_test_id(peer_id));
+        }
+
+        let node_id = node_test_id(1);
+        let chunks_to_be_downloaded = gossip.download_next_compute_work(node_id).unwrap();
+        assert_eq!(chunks_to_be_downloaded.len(), 1);
+
+        // Provide a chunk with a manifest of another message.
+        let mut gossip_chunk =
+            multi_chunked_test_create_chunk(chunks_to_be_downloaded[0].chunk_id, &advert, 0);
+        if let Ok(artifact_chunk) = &mut gossip_chunk.artifact_chunk {
+            artifact_chunk.witness =
+                SlicedArtifact::new(Box::new(receive_check_test_create_message(1)))
+                    .unwrap()
+                    .get_chunk(artifact_chunk.chunk_id)
+                    .unwrap()
+                    .witness;
+        }
+        gossip.on_chunk(gossip_chunk, node_id);
+
+        assert_eq!(gossip.metrics.chunks_verification_failed.get(), 1);
+        assert_eq!(gossip.metrics.multi_chunked_manifests_rejected.get(), 0);
+        let advert_tracker = gossip
+            .prioritizer
+            .get_advert_tracker(&advert.artifact_id, &advert.integrity_hash)
+            .unwrap();
+        assert_eq!(advert_tracker.read().unwrap().peers, vec![node_test_id(2)]);
+
+        // The download continues with the remaining peer.
+        let mut artifacts_under_construction = gossip.artifacts_under_construction.write();
+        let artifact_tracker = artifacts_under_construction
+            .get_tracker(&advert.integrity_hash)
+            .unwrap();
+        assert!(artifact_tracker.multi_chunked);
+        assert!(artifact_tracker.manifests.is_empty());
+    }
+
+    /// The function tests that a peer serving a wrong manifest first does not
+    /// hold back the download of a multi-chunked artifact from other peers.
+    #[tokio::test]
+    async fn multi_chunked_wrong_manifest_first_test() {
+        let logger = p2p_test_setup_logger();
+        let gossip = new_test_gossip(3, &logger, tokio::runtime::Handle::current());
+        let advert = multi_chunked_test_create_adverts(0..1, 2).remove(0);
+        for peer_id in 1..3 {
+            gossip.on_advert(advert.clone(), node_test_id(peer_id));
+        }
+        let node_id = node_test_id(1);
+        gossip.download_next_compute_work(node_id).unwrap();
+
+        // The first chunk received carries the manifest of another message,
+        // which is consistent with the chunk but never completed.
+        let gossip_chunk = multi_chunked_test_create_chunk(ChunkId::from(2), &advert, 1);
+        gossip.on_chunk(gossip_chunk, node_id);
+        assert_eq!(gossip.metrics.chunks_verification_failed.get(), 0);
+
+        // The chunk of the right manifest completes the artifact.
+        let gossip_chunk = multi_chunked_test_create_chunk(ChunkId::from(1), &advert, 0);
+        gossip.on_chunk(gossip_chunk, node_test_id(2));
+
+        assert!(gossip
+            .artifacts_under_construction
+            .write()
+            .get_tracker(&advert.integrity_hash)
+            .is_none());
+        let receive_check_caches = gossip.receive_check_caches.read();
+        let cache = &receive_check_caches.get(&node_id).unwrap();
+        assert!(cache.contains(&advert.integrity_hash));
+    }
+
+    /// The function tests that the download of a multi-chunked artifact is
+    /// continued as a single chunk if a peer does not serve its slices.
+    #[tokio::test]
+    async fn multi_chunked_fallback_test() {
+        let logger = p2p_test_setup_logger();
+        let gossip = new_test_gossip(2, &logger, tokio::runtime::Handle::current());
+        let node_id = node_test_id(1);
+        let advert = multi_chunked_test_create_adverts(0..1, 2).remove(0);
+        gossip.on_advert(advert.clone(), node_id);
+
+        let chunks_to_be_downloaded = gossip.download_next_compute_work(node_id).unwrap();
+        assert_eq!(chunks_to_be_downloaded.len(), 2);
+
+        // The peer does not serve the slices, like peers running an older
+        // replica version.
+        for chunk_req in chunks_to_be_downloaded {
+            let gossip_chunk = GossipChunk {
+                artifact_id: advert.artifact_id.clone(),
+                integrity_hash: advert.integrity_hash.clone(),
+                chunk_id: chunk_req.chunk_id,
+                artifact_chunk: Err(P2PError {
+                    p2p_error_code: P2PErrorCode::NotFound,
+                }),
+            };
+            gossip.on_chunk(gossip_chunk, node_id);
+        }
+        assert_eq!(gossip.metrics.multi_chunked_downloads_fallen_back.get(), 1);
+        assert!(
+            !gossip
+                .artifacts_under_construction
+                .write()
+                .get_tracker(&advert.integrity_hash)
+                .unwrap()
+                .multi_chunked
+        );
+
+        // The artifact is requested from the same peer as a single chunk.
+        let chunks_to_be_downloaded = gossip.download_next_compute_work(node_id).unwrap();
+        assert_eq!(chunks_to_be_downloaded.len(), 1);
+        assert_eq!(chunks_to_be_downloaded[0].chunk_id, ChunkId::from(0));
+    }
+
+    /// The function tests that adverts of oversized artifacts are ignored.
+    #[tokio::test]
+    async fn oversized_advert_test() {
+        let logger = p2p_test_setup_logger();
+        let gossip = new_test_gossip(2, &logger, tokio::runtime::Handle::current());
+        let mut advert = multi_chunked_test_create_adverts(0..1, 2).remove(0);
+        advert.size = MAX_ARTIFACT_SIZE + 1;
+        gossip.on_advert(advert.clone(), node_test_id(1));
+
+        assert_eq!(gossip.metrics.adverts_dropped.get(), 1);
+        assert!(gossip
+            .prioritizer
+            .get_advert_tracker(&advert.artifact_id, &advert.integrity_hash)
+            .is_err());
+    }
+
     #[test]
     fn test_get_random_subset_of_peers() {
         let mut current_peers = vec![];
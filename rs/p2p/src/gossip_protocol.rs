@@ -68,7 +68,11 @@ use ic_logger::{info, replica_logger::ReplicaLogger};
 use ic_metrics::MetricsRegistry;
 use ic_protobuf::registry::subnet::v1::GossipConfig;
 use ic_types::{
-    artifact::ArtifactFilter, chunkable::ArtifactChunk, crypto::CryptoHash, p2p::GossipAdvert,
+    artifact::{ArtifactFilter, ArtifactId},
+    chunkable::ArtifactChunk,
+    crypto::CryptoHash,
+    multi_chunked::{is_slice_chunk, SlicedArtifact},
+    p2p::GossipAdvert,
     NodeId, SubnetId,
 };
 use lru::LruCache;
@@ -159,6 +163,13 @@ pub(crate) enum GossipAdvertAction {
 /// The cache used to check if a certain artifact has been received recently.
 pub(crate) type ReceiveCheckCache = LruCache<CryptoHash, ()>;
 
+/// The cache of serialized artifacts whose slices are served to peers
+/// downloading them in multiple chunks.
+pub(crate) type SlicedArtifactCache = LruCache<ArtifactId, Arc<SlicedArtifact>>;
+
+/// The number of artifacts in the sliced artifact cache.
+const SLICED_ARTIFACT_CACHE_SIZE: usize = 4;
+
 /// The canonical implementation of the `GossipMessage` trait.
 pub(crate) struct GossipImpl {
     /// The artifact manager used to handle received artifacts.
@@ -199,6 +210,9 @@ pub(crate) struct GossipImpl {
     pub registry_refresh_instant: Mutex<Instant>,
     /// The last retransmission request time.
     pub retransmission_request_instant: Mutex<Instant>,
+    /// The artifacts recently served in multiple chunks, so that they are
+    /// serialized once rather than for every chunk.
+    pub sliced_artifacts: Mutex<SlicedArtifactCache>,
 }
 
 impl GossipImpl {
@@ -243,6 +257,7 @@ impl GossipImpl {
             pfn_invocation_instant: Mutex::new(Instant::now()),
             registry_refresh_instant: Mutex::new(Instant::now()),
             retransmission_request_instant: Mutex::new(Instant::now()),
+            sliced_artifacts: Mutex::new(SlicedArtifactCache::new(SLICED_ARTIFACT_CACHE_SIZE)),
         };
         gossip.refresh_registry();
         gossip
@@ -251,6 +266,17 @@ impl GossipImpl {
     /// The method returns the artifact chunk matching the given chunk request
     /// (if available).
     fn serve_chunk(&self, gossip_request: &GossipChunkRequest) -> P2PResult<ArtifactChunk> {
+        if is_slice_chunk(&gossip_request.artifact_id, gossip_request.chunk_id) {
+            return self
+                .get_sliced_artifact(&gossip_request.artifact_id)
+                .and_then(|sliced_artifact| sliced_artifact.get_chunk(gossip_request.chunk_id))
+                .ok_or_else(|| {
+                    self.gossip_metrics.chunk_req_not_found.inc();
+                    P2PError {
+                        p2p_error_code: P2PErrorCode::NotFound,
+                    }
+                });
+        }
         self.artifact_manager
             .get_validated_by_identifier(&gossip_request.artifact_id)
             .ok_or_else(|| {
@@ -267,6 +293,24 @@ impl GossipImpl {
                 }
             })
     }
+
+    /// The method returns the serialized artifact with the given ID, from the
+    /// cache if it has been served recently.
+    fn get_sliced_artifact(&self, artifact_id: &ArtifactId) -> Option<Arc<SlicedArtifact>> {
+        if let Some(sliced_artifact) = self.sliced_artifacts.lock().get(artifact_id) {
+            return Some(sliced_artifact.clone());
+        }
+        // The artifact is serialized without holding the lock, so that other
+        // chunk requests are not blocked.
+        let sliced_artifact = Arc::new(SlicedArtifact::new(
+            self.artifact_manager
+                .get_validated_by_identifier(artifact_id)?,
+        )?);
+        self.sliced_artifacts
+            .lock()
+            .put(artifact_id.clone(), sliced_artifact.clone());
+        Some(sliced_artifact)
+    }
 }
 
 /// Canonical Implementation for the *Gossip* trait.
@@ -81,6 +81,11 @@ pub struct DownloadManagementMetrics {
     pub chunks_redundant_residue: IntCounter,
     /// The number of failures to verify a chunk.
     pub chunks_verification_failed: IntCounter,
+    /// The number of rejected manifests of multi-chunked artifacts.
+    pub multi_chunked_manifests_rejected: IntCounter,
+    /// The number of multi-chunked artifact downloads continued as a single
+    /// chunk.
+    pub multi_chunked_downloads_fallen_back: IntCounter,
 
     // Advert fields.
     /// The number of sent adverts(total).
@@ -218,6 +223,14 @@ impl DownloadManagementMetrics {
                 "gossip_chunk_verification_failed",
                 "Number of chunks that failed verification",
             ),
+            multi_chunked_manifests_rejected: metrics_registry.int_counter(
+                "gossip_multi_chunked_manifests_rejected",
+                "Number of manifests of multi-chunked artifacts rejected after a failed verification",
+            ),
+            multi_chunked_downloads_fallen_back: metrics_registry.int_counter(
+                "gossip_multi_chunked_downloads_fallen_back",
+                "Number of multi-chunked artifact downloads continued as a single chunk",
+            ),
 
             // Adverts fields.
             adverts_sent: metrics_registry.int_counter(
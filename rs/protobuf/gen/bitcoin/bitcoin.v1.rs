@@ -93,10 +93,24 @@ pub struct CanisterSendTransactionRequest {
 }
 #[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq, ::prost::Message)]
 pub struct SendTransactionResponse {}
+#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq, ::prost::Message)]
+pub struct GetHeadersRequest {
+    /// The hash of the block whose header is requested. The adapter responds with
+    /// this header followed by the headers of its ancestors.
+    #[prost(bytes = "vec", tag = "1")]
+    pub tip: ::prost::alloc::vec::Vec<u8>,
+}
+#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq, ::prost::Message)]
+pub struct GetHeadersResponse {
+    /// The header of the requested block followed by the headers of its
+    /// ancestors, in descending order of height.
+    #[prost(message, repeated, tag = "1")]
+    pub headers: ::prost::alloc::vec::Vec<BlockHeader>,
+}
 /// Wraps the different types of requests to the Bitcoin Adapter.
 #[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq, ::prost::Message)]
 pub struct BitcoinAdapterRequestWrapper {
-    #[prost(oneof = "bitcoin_adapter_request_wrapper::R", tags = "1, 2, 3, 4")]
+    #[prost(oneof = "bitcoin_adapter_request_wrapper::R", tags = "1, 2, 3, 4, 5")]
     pub r: ::core::option::Option<bitcoin_adapter_request_wrapper::R>,
 }
 /// Nested message and enum types in `BitcoinAdapterRequestWrapper`.
@@ -111,12 +125,14 @@ pub mod bitcoin_adapter_request_wrapper {
         CanisterGetSuccessorsRequest(super::CanisterGetSuccessorsRequestInitial),
         #[prost(message, tag = "4")]
         CanisterSendTransactionRequest(super::CanisterSendTransactionRequest),
+        #[prost(message, tag = "5")]
+        GetHeadersRequest(super::GetHeadersRequest),
     }
 }
 /// Wraps the different types of responses from the Bitcoin Adapter.
 #[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq, ::prost::Message)]
 pub struct BitcoinAdapterResponseWrapper {
-    #[prost(oneof = "bitcoin_adapter_response_wrapper::R", tags = "1, 2, 3, 4")]
+    #[prost(oneof = "bitcoin_adapter_response_wrapper::R", tags = "1, 2, 3, 4, 5")]
     pub r: ::core::option::Option<bitcoin_adapter_response_wrapper::R>,
 }
 /// Nested message and enum types in `BitcoinAdapterResponseWrapper`.
@@ -131,6 +147,8 @@ pub mod bitcoin_adapter_response_wrapper {
         CanisterGetSuccessorsResponse(super::CanisterGetSuccessorsResponseComplete),
         #[prost(message, tag = "4")]
         CanisterSendTransactionResponse(super::SendTransactionResponse),
+        #[prost(message, tag = "5")]
+        GetHeadersResponse(super::GetHeadersResponse),
     }
 }
 /// A Bitcoin Adapter request, used to store the requests in the
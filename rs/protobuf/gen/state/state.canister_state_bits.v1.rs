@@ -307,6 +307,15 @@ pub mod execution_task {
     }
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CanisterLogRecord {
+    #[prost(uint64, tag = "1")]
+    pub idx: u64,
+    #[prost(uint64, tag = "2")]
+    pub timestamp_nanos: u64,
+    #[prost(bytes = "vec", tag = "3")]
+    pub content: ::prost::alloc::vec::Vec<u8>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct CanisterStateBits {
     #[prost(uint64, tag = "2")]
     pub last_full_execution_round: u64,
@@ -367,6 +376,18 @@ pub struct CanisterStateBits {
     /// Canister version.
     #[prost(uint64, tag = "34")]
     pub canister_version: u64,
+    /// The local id of the next snapshot taken of this canister.
+    #[prost(uint64, tag = "35")]
+    pub next_snapshot_id: u64,
+    /// Log records produced by the canister, oldest first.
+    #[prost(message, repeated, tag = "36")]
+    pub canister_log_records: ::prost::alloc::vec::Vec<CanisterLogRecord>,
+    /// The index assigned to the next log record of the canister.
+    #[prost(uint64, tag = "37")]
+    pub next_canister_log_record_idx: u64,
+    /// Determines who can fetch the canister log.
+    #[prost(enumeration = "LogVisibility", tag = "38")]
+    pub log_visibility: i32,
     #[prost(oneof = "canister_state_bits::CanisterStatus", tags = "11, 12, 13")]
     pub canister_status: ::core::option::Option<canister_state_bits::CanisterStatus>,
 }
@@ -382,6 +403,27 @@ pub mod canister_state_bits {
         Stopped(super::CanisterStatusStopped),
     }
 }
+/// A snapshot of a canister taken via `take_canister_snapshot`. The heap, the
+/// stable memory and the Wasm module are stored next to this message in the
+/// snapshot directory.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CanisterSnapshotBits {
+    #[prost(message, optional, tag = "1")]
+    pub canister_id: ::core::option::Option<super::super::super::types::v1::CanisterId>,
+    #[prost(uint64, tag = "2")]
+    pub local_id: u64,
+    #[prost(uint64, tag = "3")]
+    pub taken_at_timestamp_nanos: u64,
+    #[prost(uint64, tag = "4")]
+    pub canister_version: u64,
+    #[prost(bytes = "vec", tag = "5")]
+    pub certified_data: ::prost::alloc::vec::Vec<u8>,
+    #[prost(message, optional, tag = "6")]
+    pub execution_state_bits: ::core::option::Option<ExecutionStateBits>,
+    /// The size of the snapshot's stable memory in wasm pages.
+    #[prost(uint64, tag = "7")]
+    pub stable_memory_size: u64,
+}
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
 pub enum CustomSectionType {
@@ -402,3 +444,23 @@ impl CustomSectionType {
         }
     }
 }
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum LogVisibility {
+    Unspecified = 0,
+    Controllers = 1,
+    Public = 2,
+}
+impl LogVisibility {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            LogVisibility::Unspecified => "LOG_VISIBILITY_UNSPECIFIED",
+            LogVisibility::Controllers => "LOG_VISIBILITY_CONTROLLERS",
+            LogVisibility::Public => "LOG_VISIBILITY_PUBLIC",
+        }
+    }
+}
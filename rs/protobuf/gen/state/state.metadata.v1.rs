@@ -296,6 +296,9 @@ pub enum HttpMethod {
     Get = 1,
     Post = 2,
     Head = 3,
+    Put = 4,
+    Delete = 5,
+    Patch = 6,
 }
 impl HttpMethod {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -308,6 +311,9 @@ impl HttpMethod {
             HttpMethod::Get => "HTTP_METHOD_GET",
             HttpMethod::Post => "HTTP_METHOD_POST",
             HttpMethod::Head => "HTTP_METHOD_HEAD",
+            HttpMethod::Put => "HTTP_METHOD_PUT",
+            HttpMethod::Delete => "HTTP_METHOD_DELETE",
+            HttpMethod::Patch => "HTTP_METHOD_PATCH",
         }
     }
 }
@@ -324,6 +324,10 @@ pub struct SubnetFeatures {
     /// supported.
     #[prost(message, optional, tag = "6")]
     pub bitcoin: ::core::option::Option<BitcoinFeatureInfo>,
+    /// Whether the nodes of the subnet connect to each other with QUIC instead
+    /// of TLS over TCP. It is disabled by default.
+    #[prost(bool, tag = "7")]
+    pub quic_transport: bool,
 }
 /// Per subnet ECDSA configuration
 #[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq, ::prost::Message)]
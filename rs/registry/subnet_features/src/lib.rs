@@ -19,6 +19,12 @@ pub struct SubnetFeatures {
 
     /// Determines whether or not the bitcoin feature is enabled on the subnet.
     pub bitcoin: Option<BitcoinFeature>,
+
+    /// Whether the nodes of the subnet connect to each other with QUIC instead
+    /// of TLS over TCP. It is disabled by default. Nodes read it at the
+    /// registry version of the CUP they start from, so a change takes effect
+    /// at the next upgrade of the subnet.
+    pub quic_transport: bool,
 }
 
 impl SubnetFeatures {
@@ -46,6 +52,7 @@ impl From<SubnetFeatures> for pb::SubnetFeatures {
                     },
                     status: bitcoin_feature.status.into(),
                 }),
+            quic_transport: features.quic_transport,
         }
     }
 }
@@ -78,6 +85,7 @@ impl From<pb::SubnetFeatures> for SubnetFeatures {
                         })
                 }
             },
+            quic_transport: features.quic_transport,
         }
     }
 }
@@ -97,6 +105,7 @@ impl FromStr for SubnetFeatures {
             match feature {
                 "canister_sandboxing" => features.canister_sandboxing = true,
                 "http_requests" => features.http_requests = true,
+                "quic_transport" => features.quic_transport = true,
                 "bitcoin_testnet" => {
                     if features.bitcoin.is_some() {
                         // Feature was already set. Return an error.
@@ -275,8 +284,10 @@ mod tests {
 
     #[test]
     fn test_all_can_be_set_true() {
-        let result =
-            SubnetFeatures::from_str("canister_sandboxing,http_requests,bitcoin_testnet").unwrap();
+        let result = SubnetFeatures::from_str(
+            "canister_sandboxing,http_requests,bitcoin_testnet,quic_transport",
+        )
+        .unwrap();
         assert_eq!(
             result,
             SubnetFeatures {
@@ -285,7 +296,8 @@ mod tests {
                 bitcoin: Some(BitcoinFeature {
                     network: BitcoinNetwork::Testnet,
                     status: BitcoinFeatureStatus::Enabled
-                })
+                }),
+                quic_transport: true,
             }
         );
     }
@@ -303,7 +315,8 @@ mod tests {
                 bitcoin: Some(BitcoinFeature {
                     network: BitcoinNetwork::Mainnet,
                     status: BitcoinFeatureStatus::Paused
-                })
+                }),
+                quic_transport: false,
             }
         );
     }
@@ -320,7 +333,8 @@ mod tests {
                 bitcoin: Some(BitcoinFeature {
                     network: BitcoinNetwork::Mainnet,
                     status: BitcoinFeatureStatus::Enabled
-                })
+                }),
+                quic_transport: false,
             }
         );
     }
@@ -344,6 +358,16 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_quic_transport_to_from_proto() {
+        let subnet_feature = SubnetFeatures::from_str("quic_transport").unwrap();
+        assert!(subnet_feature.quic_transport);
+        assert_eq!(
+            subnet_feature,
+            SubnetFeatures::from(pb::SubnetFeatures::from(subnet_feature))
+        );
+    }
+
     #[test]
     fn test_bitcoin_to_from_proto() {
         for feature in [
@@ -39,6 +39,19 @@ pub struct ReplayToolArgs {
     #[clap(long)]
     /// The replay will stop at this height and make a checkpoint.
     pub replay_until_height: Option<u64>,
+
+    /// Write a trace of every executed message as JSON lines to this file.
+    #[clap(long)]
+    pub trace_file: Option<PathBuf>,
+
+    /// Only trace messages executed on this canister. Can be given multiple
+    /// times; all canisters are traced if omitted.
+    #[clap(long)]
+    pub trace_canister_id: Vec<CanisterId>,
+
+    /// Only trace messages executed at or above this height.
+    #[clap(long)]
+    pub trace_from_height: Option<u64>,
 }
 
 #[derive(Clone, Parser)]
@@ -80,6 +93,32 @@ pub enum SubCommand {
 
     /// Verify the signature of a CUP from a subnet
     VerifySubnetCUP(VerifySubnetCUPCmd),
+
+    /// Replay the blocks up to the given height and run a query against the
+    /// state at that height. The replay starts from a temporary copy of the
+    /// latest checkpoint at or below the height, so the replica state is not
+    /// modified.
+    Query(QueryCmd),
+}
+
+#[derive(Clone, Parser)]
+pub struct QueryCmd {
+    /// Height of the state to run the query against.
+    #[clap(long)]
+    pub height: u64,
+    /// The queried canister.
+    #[clap(long)]
+    pub canister: CanisterId,
+    /// The query method to call.
+    #[clap(long)]
+    pub method: String,
+    /// Candid text arguments of the query, e.g. `'(record { owner = principal "aaaaa-aa" })'`.
+    /// Defaults to `()`.
+    #[clap(long)]
+    pub arg: Option<String>,
+    /// Sender of the query. Defaults to the anonymous principal.
+    #[clap(long)]
+    pub sender: Option<PrincipalId>,
 }
 
 #[derive(Clone, Parser)]
@@ -17,6 +17,7 @@
 use crate::cmd::{ReplayToolArgs, SubCommand};
 use crate::ingress::*;
 use crate::player::{Player, ReplayResult};
+use crate::trace::ExecutionTrace;
 
 use cmd::RestoreFromBackupCmd;
 use ic_canister_client::{Agent, Sender};
@@ -24,7 +25,7 @@ use ic_config::registry_client::DataProviderConfig;
 use ic_config::{Config, ConfigSource};
 use ic_nns_constants::GOVERNANCE_CANISTER_ID;
 use ic_protobuf::registry::subnet::v1::InitialNiDkgTranscriptRecord;
-use ic_types::ReplicaVersion;
+use ic_types::{messages::UserQuery, Height, PrincipalId, ReplicaVersion, UserId};
 use prost::Message;
 use std::cell::RefCell;
 use std::convert::{TryFrom, TryInto};
@@ -33,12 +34,14 @@ use std::fs::File;
 use std::io::Read;
 use std::path::Path;
 use std::rc::Rc;
+use std::sync::Arc;
 
 mod backup;
 pub mod cmd;
 pub mod ingress;
 mod mocks;
 pub mod player;
+pub mod trace;
 mod validator;
 
 /// Replays the past blocks and creates a checkpoint of the latest state.
@@ -60,6 +63,9 @@ mod validator;
 ///     canister_caller_id: None,
 ///     replay_until_height: None,
 ///     data_root: None,
+///     trace_file: None,
+///     trace_canister_id: Vec::new(),
+///     trace_from_height: None,
 ///     subcmd: Some(SubCommand::RestoreFromBackup(RestoreFromBackupCmd {
 ///         registry_local_store_path: PathBuf::from("/path/to/ic_registry_local_store"),
 ///         backup_spool_path: PathBuf::from("/path/to/spool"),
@@ -125,6 +131,33 @@ pub fn replay(args: ReplayToolArgs) -> ReplayResult {
             }
         }
 
+        let execution_trace = args.trace_file.map(|path| {
+            let trace = ExecutionTrace::new(&path, args.trace_canister_id, args.trace_from_height)
+                .unwrap_or_else(|err| {
+                    println!("Failed to create trace file {}: {}", path.display(), err);
+                    std::process::exit(1);
+                });
+            println!("Writing the execution trace to {}", path.display());
+            Arc::new(trace)
+        });
+
+        if let Some(SubCommand::Query(cmd)) = subcmd {
+            if target_height.is_some() {
+                panic!("Target height cannot be used with the query sub-command.");
+            }
+            let _enter_guard = rt.enter();
+            let player =
+                Player::new_for_query(cfg, subnet_id, Height::from(cmd.height), execution_trace);
+            *res_clone.borrow_mut() = player.replay(|_, _| Vec::new()).map(|state_params| {
+                if let Err(err) = cmd_query(&player, cmd) {
+                    println!("Query failed: {}", err);
+                    std::process::exit(1);
+                }
+                state_params
+            });
+            return;
+        }
+
         if let (Some(cmd), is_new) = match subcmd {
             Some(SubCommand::RestoreFromBackup(cmd)) => (Some(cmd.clone()), false),
             Some(SubCommand::RestoreFromBackup2(cmd2)) => {
@@ -149,6 +182,7 @@ pub fn replay(args: ReplayToolArgs) -> ReplayResult {
                 subnet_id,
                 cmd.start_height,
                 is_new,
+                execution_trace,
             )
             .with_replay_target_height(target_height);
             *res_clone.borrow_mut() = player.restore(cmd.start_height + 1);
@@ -163,9 +197,8 @@ pub fn replay(args: ReplayToolArgs) -> ReplayResult {
                     "Target height cannot be used with any sub-command in subnet-recovery mode."
                 );
                 }
-                (_, target_height) => {
-                    Player::new(cfg, subnet_id).with_replay_target_height(target_height)
-                }
+                (_, target_height) => Player::new(cfg, subnet_id, execution_trace)
+                    .with_replay_target_height(target_height),
             };
 
             if let Some(SubCommand::GetRecoveryCup(cmd)) = subcmd {
@@ -326,6 +359,39 @@ fn cmd_get_recovery_cup(
     Ok(())
 }
 
+// Runs the query against the state at the height of the command and prints
+// the reply.
+fn cmd_query(player: &Player, cmd: &crate::cmd::QueryCmd) -> Result<(), String> {
+    use candid::IDLArgs;
+    use ic_types::ingress::WasmResult;
+
+    let arg = cmd
+        .arg
+        .as_deref()
+        .unwrap_or("()")
+        .parse::<IDLArgs>()
+        .map_err(|err| format!("Failed to parse Candid arguments: {}", err))?
+        .to_bytes()
+        .map_err(|err| format!("Failed to encode Candid arguments: {}", err))?;
+    let ingress_expiry = ic_types::time::current_time() + std::time::Duration::from_secs(60);
+    let query = UserQuery {
+        source: UserId::from(cmd.sender.unwrap_or_else(PrincipalId::new_anonymous)),
+        receiver: cmd.canister,
+        method_name: cmd.method.clone(),
+        method_payload: arg,
+        ingress_expiry: ingress_expiry.as_nanos_since_unix_epoch(),
+        nonce: None,
+    };
+    match player.query_at_height(Height::from(cmd.height), query)? {
+        WasmResult::Reply(bytes) => match IDLArgs::from_bytes(&bytes) {
+            Ok(args) => println!("Reply at height {}: {}", cmd.height, args),
+            Err(_) => println!("Reply at height {}: 0x{}", cmd.height, hex::encode(bytes)),
+        },
+        WasmResult::Reject(reject) => println!("Rejected at height {}: {}", cmd.height, reject),
+    }
+    Ok(())
+}
+
 fn verify_cup_signature(cup_file: &Path, public_key_file: &Path) -> Result<(), Box<dyn Error>> {
     let mut file = File::open(cup_file)?;
     let mut buffer = Vec::new();
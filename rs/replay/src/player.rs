@@ -1,6 +1,7 @@
 use crate::ingress::IngressWithPrinter;
 use crate::{
     backup,
+    trace::ExecutionTrace,
     validator::{InvalidArtifact, ReplayValidator},
 };
 use ic_artifact_pool::{
@@ -9,7 +10,7 @@ use ic_artifact_pool::{
 };
 use ic_config::{
     artifact_pool::ArtifactPoolConfig, registry_client::DataProviderConfig,
-    subnet_config::SubnetConfigs, Config,
+    state_manager::Config as StateManagerConfig, subnet_config::SubnetConfigs, Config,
 };
 use ic_consensus::{
     certification::VerifierImpl,
@@ -18,7 +19,7 @@ use ic_consensus::{
     },
 };
 use ic_cycles_account_manager::CyclesAccountManager;
-use ic_execution_environment::ExecutionServices;
+use ic_execution_environment::{ExecutionServices, ExecutionTracer};
 use ic_interfaces::crypto::ThresholdSigVerifierByPublicKey;
 use ic_interfaces::{
     certification::CertificationPool,
@@ -50,6 +51,7 @@ use ic_registry_transport::{
 };
 use ic_replica::setup::get_subnet_type;
 use ic_replicated_state::ReplicatedState;
+use ic_state_layout::utils::do_copy;
 use ic_state_manager::StateManagerImpl;
 use ic_types::{
     batch::{Batch, BatchPayload, IngressPayload},
@@ -120,11 +122,15 @@ pub struct Player {
     // The target height until which the state will be replayed.
     // None means finalized height.
     replay_target_height: Option<u64>,
+    // The trace of all executed messages, if enabled.
+    execution_trace: Option<Arc<ExecutionTrace>>,
 }
 
 impl Player {
     /// Create and return a `Player` from a replica configuration object for
-    /// restoring states from backups.
+    /// restoring states from backups. If `execution_trace` is given, every
+    /// executed message is written to it.
+    #[allow(clippy::too_many_arguments)]
     pub fn new_for_backup(
         mut cfg: Config,
         replica_version: ReplicaVersion,
@@ -133,6 +139,7 @@ impl Player {
         subnet_id: SubnetId,
         start_height: u64,
         is_new: bool,
+        execution_trace: Option<Arc<ExecutionTrace>>,
     ) -> Self {
         let (log, _async_log_guard) = new_replica_logger_from_config(&cfg.logger);
         let DataProviderConfig::LocalStore(local_store_from_config) = cfg
@@ -202,14 +209,73 @@ impl Player {
             replica_version,
             log,
             _async_log_guard,
+            None,
+            execution_trace,
         );
         player.tmp_dir = Some(tmp_dir);
         player
     }
 
     /// Create and return a `Player` from a replica configuration object for
-    /// subnet recovery.
-    pub fn new(cfg: Config, subnet_id: SubnetId) -> Self {
+    /// subnet recovery. If `execution_trace` is given, every executed message
+    /// is written to it.
+    pub fn new(
+        cfg: Config,
+        subnet_id: SubnetId,
+        execution_trace: Option<Arc<ExecutionTrace>>,
+    ) -> Self {
+        Player::new_from_checkpoint(cfg, subnet_id, None, execution_trace)
+    }
+
+    /// Create and return a `Player` that replays the blocks up to exactly
+    /// `height`, starting from the latest checkpoint at or below `height`,
+    /// so that queries can be run against the state at `height`.
+    ///
+    /// Starting from an older checkpoint would archive all newer ones, so the
+    /// checkpoint is copied to a temporary state directory next to the
+    /// original one and the original state is left untouched.
+    pub fn new_for_query(
+        mut cfg: Config,
+        subnet_id: SubnetId,
+        height: Height,
+        execution_trace: Option<Arc<ExecutionTrace>>,
+    ) -> Self {
+        let state_root = cfg.state_manager.state_root();
+        let tmp_dir = tempfile::Builder::new()
+            .prefix("replay_query_state_")
+            .tempdir_in(state_root.parent().unwrap_or(&state_root))
+            .expect("Couldn't create a temporary directory");
+        let checkpoint_height = copy_checkpoint_at_or_below(
+            &ic_logger::replica_logger::no_op_logger(),
+            &state_root,
+            height,
+            tmp_dir.path(),
+        )
+        .unwrap_or_else(|err| {
+            panic!(
+                "Couldn't copy a checkpoint at or below height {}: {}",
+                height, err
+            )
+        });
+        println!(
+            "Using a copy of the checkpoint at height {} in {:?} for the query...",
+            checkpoint_height,
+            tmp_dir.path()
+        );
+        cfg.state_manager = StateManagerConfig::new(tmp_dir.path().into());
+
+        let mut player = Player::new_from_checkpoint(cfg, subnet_id, Some(height), execution_trace)
+            .with_replay_target_height(Some(height.get()));
+        player.tmp_dir = Some(tmp_dir);
+        player
+    }
+
+    fn new_from_checkpoint(
+        cfg: Config,
+        subnet_id: SubnetId,
+        starting_height: Option<Height>,
+        execution_trace: Option<Arc<ExecutionTrace>>,
+    ) -> Self {
         let (log, _async_log_guard) = new_replica_logger_from_config(&cfg.logger);
         let metrics_registry = MetricsRegistry::new();
         let registry = setup_registry(cfg.clone(), Some(&metrics_registry));
@@ -244,6 +310,8 @@ impl Player {
             replica_version,
             log,
             _async_log_guard,
+            starting_height,
+            execution_trace,
         )
     }
 
@@ -257,6 +325,8 @@ impl Player {
         replica_version: ReplicaVersion,
         log: ReplicaLogger,
         _async_log_guard: AsyncGuard,
+        starting_height: Option<Height>,
+        execution_trace: Option<Arc<ExecutionTrace>>,
     ) -> Self {
         println!("Setting default replica version {}", replica_version);
         if ReplicaVersion::set_default_version(replica_version.clone()).is_err() {
@@ -297,10 +367,10 @@ impl Player {
             log.clone(),
             &metrics_registry,
             &cfg.state_manager,
-            None,
+            starting_height,
             ic_types::malicious_flags::MaliciousFlags::default(),
         ));
-        let execution_service = ExecutionServices::setup_execution(
+        let execution_service = ExecutionServices::setup_execution_with_tracer(
             log.clone(),
             &metrics_registry,
             subnet_id,
@@ -309,6 +379,9 @@ impl Player {
             cfg.hypervisor.clone(),
             Arc::clone(&cycles_account_manager),
             Arc::clone(&state_manager) as Arc<_>,
+            execution_trace
+                .clone()
+                .map(|trace| trace as Arc<dyn ExecutionTracer>),
         );
         let message_routing = Arc::new(MessageRoutingImpl::new(
             state_manager.clone(),
@@ -362,6 +435,7 @@ impl Player {
             _async_log_guard,
             tmp_dir: None,
             replay_target_height: None,
+            execution_trace,
         }
     }
 
@@ -488,6 +562,10 @@ impl Player {
             }
             std::thread::sleep(WAIT_DURATION);
         }
+        // All messages up to `height` were executed at this point.
+        if let Some(execution_trace) = &self.execution_trace {
+            execution_trace.flush();
+        }
         println!(
             "Latest state height is {}",
             self.state_manager.latest_state_height()
@@ -733,6 +811,21 @@ impl Player {
         }
     }
 
+    /// Run a non-replicated query against the state at the given height.
+    pub fn query_at_height(&self, height: Height, query: UserQuery) -> Result<WasmResult, String> {
+        let state = self.state_manager.get_state_at(height).map_err(|err| {
+            format!(
+                "The state at height {} is not available: {:?} (latest state height is {})",
+                height,
+                err,
+                self.state_manager.latest_state_height()
+            )
+        })?;
+        self.http_query_handler
+            .query(query, state.take(), Vec::new())
+            .map_err(|err| format!("Failed to run query: {}", err))
+    }
+
     /// Return the highest CatchUpPackage
     pub fn get_highest_catch_up_package(&self) -> CatchUpPackage {
         PoolReader::new(self.consensus_pool.as_ref().unwrap()).get_highest_catch_up_package()
@@ -1079,3 +1172,182 @@ fn get_state_hash<T>(
         std::thread::sleep(WAIT_DURATION);
     }
 }
+
+// Copies the latest checkpoint at or below `height` from the state directory
+// `state_root` to the state directory `dst_root`, together with the states
+// metadata if present. Returns the height of the copied checkpoint.
+//
+// Files are reflinked where the file system supports it, like the state
+// layout does when it creates tip from a checkpoint, so that copying large
+// canister states is cheap.
+fn copy_checkpoint_at_or_below(
+    log: &ReplicaLogger,
+    state_root: &Path,
+    height: Height,
+    dst_root: &Path,
+) -> std::io::Result<Height> {
+    let checkpoints = state_root.join("checkpoints");
+    let mut checkpoint_heights = Vec::new();
+    for entry in std::fs::read_dir(&checkpoints)? {
+        let name = entry?.file_name();
+        if let Some(h) = name
+            .to_str()
+            .and_then(|name| u64::from_str_radix(name, 16).ok())
+        {
+            checkpoint_heights.push(h);
+        }
+    }
+    let checkpoint_height = checkpoint_heights
+        .into_iter()
+        .filter(|h| *h <= height.get())
+        .max()
+        .ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("no checkpoint in {}", checkpoints.display()),
+            )
+        })?;
+
+    let name = format!("{:016x}", checkpoint_height);
+    copy_dir_recursively(
+        log,
+        &checkpoints.join(&name),
+        &dst_root.join("checkpoints").join(&name),
+    )?;
+    let states_metadata = state_root.join("states_metadata.pbuf");
+    if states_metadata.exists() {
+        do_copy(
+            log,
+            &states_metadata,
+            &dst_root.join("states_metadata.pbuf"),
+        )?;
+    }
+    Ok(Height::from(checkpoint_height))
+}
+
+fn copy_dir_recursively(log: &ReplicaLogger, src: &Path, dst: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dst)?;
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        let dst = dst.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir_recursively(log, &entry.path(), &dst)?;
+        } else {
+            do_copy(log, &entry.path(), &dst)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_logger::replica_logger::no_op_logger;
+
+    fn write_checkpoint(state_root: &Path, height: u64) {
+        let checkpoint = state_root
+            .join("checkpoints")
+            .join(format!("{:016x}", height))
+            .join("canister_states")
+            .join("00000000000000010101");
+        std::fs::create_dir_all(&checkpoint).unwrap();
+        std::fs::write(checkpoint.join("canister.pbuf"), height.to_le_bytes()).unwrap();
+    }
+
+    fn checkpoint_names(state_root: &Path) -> Vec<String> {
+        let mut names: Vec<_> = std::fs::read_dir(state_root.join("checkpoints"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn copies_latest_checkpoint_at_or_below_height() {
+        let state_root = tempfile::tempdir().unwrap();
+        let dst_root = tempfile::tempdir().unwrap();
+        for height in [100, 200, 300] {
+            write_checkpoint(state_root.path(), height);
+        }
+        std::fs::write(state_root.path().join("states_metadata.pbuf"), b"metadata").unwrap();
+
+        let copied = copy_checkpoint_at_or_below(
+            &no_op_logger(),
+            state_root.path(),
+            Height::from(250),
+            dst_root.path(),
+        )
+        .unwrap();
+
+        assert_eq!(Height::from(200), copied);
+        assert_eq!(
+            vec![format!("{:016x}", 200)],
+            checkpoint_names(dst_root.path())
+        );
+        assert_eq!(
+            200u64.to_le_bytes().to_vec(),
+            std::fs::read(
+                dst_root
+                    .path()
+                    .join("checkpoints")
+                    .join(format!("{:016x}", 200))
+                    .join("canister_states/00000000000000010101/canister.pbuf")
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            b"metadata".to_vec(),
+            std::fs::read(dst_root.path().join("states_metadata.pbuf")).unwrap()
+        );
+        // The original state is left untouched.
+        assert_eq!(
+            vec![
+                format!("{:016x}", 100),
+                format!("{:016x}", 200),
+                format!("{:016x}", 300)
+            ],
+            checkpoint_names(state_root.path())
+        );
+    }
+
+    #[test]
+    fn copies_checkpoint_at_exact_height() {
+        let state_root = tempfile::tempdir().unwrap();
+        let dst_root = tempfile::tempdir().unwrap();
+        write_checkpoint(state_root.path(), 100);
+        write_checkpoint(state_root.path(), 200);
+
+        let copied = copy_checkpoint_at_or_below(
+            &no_op_logger(),
+            state_root.path(),
+            Height::from(100),
+            dst_root.path(),
+        )
+        .unwrap();
+
+        assert_eq!(Height::from(100), copied);
+        assert_eq!(
+            vec![format!("{:016x}", 100)],
+            checkpoint_names(dst_root.path())
+        );
+        assert!(!dst_root.path().join("states_metadata.pbuf").exists());
+    }
+
+    #[test]
+    fn fails_without_checkpoint_at_or_below_height() {
+        let state_root = tempfile::tempdir().unwrap();
+        let dst_root = tempfile::tempdir().unwrap();
+        write_checkpoint(state_root.path(), 200);
+
+        let err = copy_checkpoint_at_or_below(
+            &no_op_logger(),
+            state_root.path(),
+            Height::from(100),
+            dst_root.path(),
+        )
+        .unwrap_err();
+
+        assert_eq!(std::io::ErrorKind::NotFound, err.kind());
+    }
+}
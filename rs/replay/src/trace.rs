@@ -0,0 +1,114 @@
+//! Export of a per-message execution trace while replaying blocks.
+//!
+//! Every message or task executed on a canister, as well as every subnet
+//! message, is written as a single JSON object on its own line (JSONL).
+//! Canisters are executed on several threads, so the lines of a single height
+//! are not ordered by canister.
+
+use ic_execution_environment::{ExecutionTracer, MessageExecutionTrace, MessageOutcome};
+use ic_types::{CanisterId, ExecutionRound, Height};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Mutex,
+};
+
+/// A single line of the trace.
+#[derive(Serialize)]
+struct TraceEntry<'a> {
+    height: u64,
+    canister_id: String,
+    kind: &'a str,
+    method: Option<&'a str>,
+    caller: Option<String>,
+    instructions_used: u64,
+    cycles_charged: u128,
+    cycles_refunded: u128,
+    heap_delta: u64,
+    result: &'static str,
+    reject_code: Option<u64>,
+    reject_message: Option<&'a str>,
+}
+
+/// Writes the executions of the replayed heights to a JSONL file.
+pub struct ExecutionTrace {
+    writer: Mutex<BufWriter<File>>,
+    /// Only executions on these canisters are traced. All canisters are traced
+    /// if empty.
+    canister_ids: BTreeSet<CanisterId>,
+    /// Executions below this height are not traced.
+    from_height: Height,
+    /// The height of the round that is currently executed.
+    current_height: AtomicU64,
+}
+
+impl ExecutionTrace {
+    /// Creates (or truncates) the trace file at `path`.
+    pub fn new(
+        path: &Path,
+        canister_ids: Vec<CanisterId>,
+        from_height: Option<u64>,
+    ) -> std::io::Result<Self> {
+        Ok(Self {
+            writer: Mutex::new(BufWriter::new(File::create(path)?)),
+            canister_ids: canister_ids.into_iter().collect(),
+            from_height: Height::from(from_height.unwrap_or(0)),
+            current_height: AtomicU64::new(0),
+        })
+    }
+
+    /// Writes the buffered lines to the trace file.
+    pub fn flush(&self) {
+        if let Err(err) = self.writer.lock().unwrap().flush() {
+            eprintln!("Failed to flush the execution trace: {}", err);
+        }
+    }
+
+    fn is_traced(&self, height: Height, canister_id: &CanisterId) -> bool {
+        height >= self.from_height
+            && (self.canister_ids.is_empty() || self.canister_ids.contains(canister_id))
+    }
+}
+
+impl ExecutionTracer for ExecutionTrace {
+    fn on_round_started(&self, round: ExecutionRound) {
+        // The execution round is always the height of the executed batch.
+        self.current_height.store(round.get(), Ordering::Relaxed);
+    }
+
+    fn on_message_executed(&self, trace: &MessageExecutionTrace) {
+        let height = Height::from(self.current_height.load(Ordering::Relaxed));
+        if !self.is_traced(height, &trace.canister_id) {
+            return;
+        }
+        let (result, reject_code, reject_message) = match &trace.outcome {
+            MessageOutcome::Reply => ("reply", None, None),
+            MessageOutcome::Reject { code, message } => {
+                ("reject", Some(*code), Some(message.as_str()))
+            }
+            MessageOutcome::NoResponse => ("no_response", None, None),
+        };
+        let entry = TraceEntry {
+            height: height.get(),
+            canister_id: trace.canister_id.to_string(),
+            kind: trace.kind,
+            method: trace.method.as_deref(),
+            caller: trace.caller.map(|caller| caller.to_string()),
+            instructions_used: trace.instructions_used.get(),
+            cycles_charged: trace.cycles_charged.get(),
+            cycles_refunded: trace.cycles_refunded.get(),
+            heap_delta: trace.heap_delta.get(),
+            result,
+            reject_code,
+            reject_message,
+        };
+        let line = serde_json::to_string(&entry).expect("Failed to serialize trace entry");
+        if let Err(err) = writeln!(self.writer.lock().unwrap(), "{}", line) {
+            eprintln!("Failed to write to the execution trace: {}", err);
+        }
+    }
+}
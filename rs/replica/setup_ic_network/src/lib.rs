@@ -12,7 +12,9 @@ use ic_artifact_pool::{
     ensure_persistent_pool_replica_version_compatibility, ingress_pool::IngressPoolImpl,
 };
 use ic_config::{
-    artifact_pool::ArtifactPoolConfig, consensus::ConsensusConfig, transport::TransportConfig,
+    artifact_pool::ArtifactPoolConfig,
+    consensus::ConsensusConfig,
+    transport::{TransportConfig, TransportProtocol},
 };
 use ic_consensus::{
     canister_http, certification,
@@ -50,7 +52,7 @@ use ic_types::{
     filetree_sync::{FileTreeSyncArtifact, FileTreeSyncId},
     malicious_flags::MaliciousFlags,
     replica_config::ReplicaConfig,
-    NodeId, SubnetId,
+    NodeId, RegistryVersion, SubnetId,
 };
 use std::sync::{Arc, Mutex, RwLock};
 
@@ -118,6 +120,17 @@ pub fn create_networking_stack(
         ic_interfaces_canister_http_adapter_client::CanisterHttpAdapterClient,
     registry_poll_delay_duration_ms: u64,
 ) -> (IngressIngestionService, P2PThreadJoiner) {
+    // Read before consensus gets to make progress, i.e. from the CUP the
+    // replica starts from.
+    let transport_protocol = fetch_transport_protocol(
+        registry_client.as_ref(),
+        subnet_id,
+        artifact_pools
+            .consensus_pool_cache
+            .catch_up_package()
+            .content
+            .registry_version(),
+    );
     let gossip_config = fetch_gossip_config(registry_client.clone(), subnet_id);
     let advert_subscriber =
         AdvertBroadcaster::new(log.clone(), &metrics_registry, gossip_config.clone());
@@ -159,6 +172,7 @@ pub fn create_networking_stack(
             tls_handshake,
             rt_handle.clone(),
             log.clone(),
+            transport_protocol,
             false,
         )
     });
@@ -189,6 +203,26 @@ pub fn create_networking_stack(
     (ingress_event_handler, p2p_thread)
 }
 
+/// Returns the transport protocol of the subnet at the registry version of the
+/// CUP the replica starts from.
+///
+/// The latest registry version differs between nodes, depending on how far
+/// their local copies of the registry are, so nodes starting together could
+/// pick different protocols and fail to talk to each other. At a subnet
+/// upgrade, all nodes restart from the same CUP and thus switch protocols
+/// together, so a change of the `quic_transport` subnet feature should be
+/// rolled out with an upgrade of the subnet.
+fn fetch_transport_protocol(
+    registry_client: &dyn RegistryClient,
+    subnet_id: SubnetId,
+    cup_registry_version: RegistryVersion,
+) -> TransportProtocol {
+    match registry_client.get_features(subnet_id, cup_registry_version) {
+        Ok(Some(features)) if features.quic_transport => TransportProtocol::Quic,
+        _ => TransportProtocol::Tcp,
+    }
+}
+
 /// The function sets up and returns the Artifact Manager and Consensus Pool.
 ///
 /// The Artifact Manager runs all artifact clients as separate actors.
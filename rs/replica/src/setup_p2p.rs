@@ -135,13 +135,18 @@ pub fn construct_ic_stack(
         Some(artifact_pools.consensus_pool_cache.starting_height()),
         config.malicious_behaviour.malicious_flags.clone(),
     ));
+    let mut hypervisor_config = config.hypervisor.clone();
+    if hypervisor_config.compilation_cache_dir.is_none() {
+        hypervisor_config.compilation_cache_dir =
+            Some(config.state_manager.state_root().join("compilation_cache"));
+    }
     let execution_services = ExecutionServices::setup_execution(
         replica_logger.clone(),
         &metrics_registry,
         subnet_id,
         subnet_type,
         subnet_config.scheduler_config,
-        config.hypervisor.clone(),
+        hypervisor_config,
         Arc::clone(&cycles_account_manager),
         Arc::clone(&state_manager) as Arc<_>,
     );
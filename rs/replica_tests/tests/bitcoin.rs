@@ -2,8 +2,9 @@ use ic_async_utils::incoming_from_path;
 use ic_base_types::CanisterId;
 use ic_btc_service::{
     btc_service_server::{BtcService, BtcServiceServer},
-    BtcServiceGetSuccessorsRequest, BtcServiceGetSuccessorsResponse,
-    BtcServiceSendTransactionRequest, BtcServiceSendTransactionResponse,
+    BtcServiceGetHeadersRequest, BtcServiceGetHeadersResponse, BtcServiceGetSuccessorsRequest,
+    BtcServiceGetSuccessorsResponse, BtcServiceSendTransactionRequest,
+    BtcServiceSendTransactionResponse,
 };
 use ic_btc_types::NetworkInRequest as BitcoinNetwork;
 use ic_btc_types_internal::{
@@ -47,6 +48,15 @@ impl BtcService for MockBitcoinAdapter {
     ) -> Result<tonic::Response<BtcServiceSendTransactionResponse>, tonic::Status> {
         Ok(tonic::Response::new(BtcServiceSendTransactionResponse {}))
     }
+
+    async fn get_headers(
+        &self,
+        _request: tonic::Request<BtcServiceGetHeadersRequest>,
+    ) -> Result<tonic::Response<BtcServiceGetHeadersResponse>, tonic::Status> {
+        Ok(tonic::Response::new(BtcServiceGetHeadersResponse {
+            headers: vec![],
+        }))
+    }
 }
 
 fn spawn_mock_bitcoin_adapter(
@@ -69,7 +69,8 @@ pub fn push_response(
             Ok(())
         }
         BitcoinAdapterResponseWrapper::GetSuccessorsResponse(_)
-        | BitcoinAdapterResponseWrapper::SendTransactionResponse(_) => {
+        | BitcoinAdapterResponseWrapper::SendTransactionResponse(_)
+        | BitcoinAdapterResponseWrapper::GetHeadersResponse(_) => {
             match state.metadata.own_subnet_features.bitcoin().status {
                 BitcoinFeatureStatus::Enabled
                 | BitcoinFeatureStatus::Syncing
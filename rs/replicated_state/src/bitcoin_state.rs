@@ -70,6 +70,7 @@ pub struct AdapterQueues {
     responses: VecDeque<BitcoinAdapterResponse>,
     requests_queue_capacity: u32,
     in_flight_get_successors_requests_num: u32,
+    in_flight_get_headers_requests_num: u32,
 }
 
 impl Default for AdapterQueues {
@@ -86,6 +87,7 @@ impl AdapterQueues {
             responses: VecDeque::new(),
             requests_queue_capacity,
             in_flight_get_successors_requests_num: 0,
+            in_flight_get_headers_requests_num: 0,
         }
     }
 
@@ -94,6 +96,11 @@ impl AdapterQueues {
         self.in_flight_get_successors_requests_num > 0
     }
 
+    /// Returns true iff there's at least an in-flight `GetHeadersRequest`.
+    pub fn has_in_flight_get_headers_requests(&self) -> bool {
+        self.in_flight_get_headers_requests_num > 0
+    }
+
     /// Pushes a `BitcoinAdapterRequestWrapper` to the `BitcoinState`.
     ///
     /// Returns a `BitcoinStateError` if there's no room left in the queue for new requests.
@@ -107,8 +114,14 @@ impl AdapterQueues {
             });
         }
 
-        if let BitcoinAdapterRequestWrapper::GetSuccessorsRequest(_) = request {
-            self.in_flight_get_successors_requests_num += 1;
+        match request {
+            BitcoinAdapterRequestWrapper::GetSuccessorsRequest(_) => {
+                self.in_flight_get_successors_requests_num += 1;
+            }
+            BitcoinAdapterRequestWrapper::GetHeadersRequest(_) => {
+                self.in_flight_get_headers_requests_num += 1;
+            }
+            _ => {}
         }
         self.requests.insert(
             self.next_callback_id,
@@ -152,6 +165,9 @@ pub struct UtxoSet {
     /// PageMap storing an index mapping a Bitcoin address to its UTXOs.
     pub address_outpoints: PageMap,
 
+    /// PageMap storing the headers of all stable blocks, indexed by height.
+    pub block_headers: PageMap,
+
     /// The bitcoin network that this UtxoSet belongs to.
     pub network: Network,
 }
@@ -168,6 +184,7 @@ impl UtxoSet {
             utxos_medium: PageMap::default(),
             utxos_large: BTreeMap::default(),
             address_outpoints: PageMap::default(),
+            block_headers: PageMap::default(),
         }
     }
 }
@@ -292,8 +309,14 @@ impl BitcoinState {
                 callback_id: response.callback_id,
             }),
             Some(r) => {
-                if let BitcoinAdapterRequestWrapper::GetSuccessorsRequest(_) = r.request {
-                    self.adapter_queues.in_flight_get_successors_requests_num -= 1;
+                match r.request {
+                    BitcoinAdapterRequestWrapper::GetSuccessorsRequest(_) => {
+                        self.adapter_queues.in_flight_get_successors_requests_num -= 1;
+                    }
+                    BitcoinAdapterRequestWrapper::GetHeadersRequest(_) => {
+                        self.adapter_queues.in_flight_get_headers_requests_num -= 1;
+                    }
+                    _ => {}
                 }
                 self.adapter_queues.responses.push_back(response);
                 Ok(())
@@ -319,12 +342,17 @@ impl TryFrom<pb_bitcoin::AdapterQueues> for AdapterQueues {
     fn try_from(queues: pb_bitcoin::AdapterQueues) -> Result<Self, Self::Error> {
         let mut requests = BTreeMap::new();
         let mut in_flight_get_successors_requests_num = 0;
+        let mut in_flight_get_headers_requests_num = 0;
         for r in queues.requests.into_iter() {
             let bitcoin_adapter_request = BitcoinAdapterRequest::try_from(r)?;
-            if let BitcoinAdapterRequestWrapper::GetSuccessorsRequest(_) =
-                bitcoin_adapter_request.request
-            {
-                in_flight_get_successors_requests_num += 1;
+            match bitcoin_adapter_request.request {
+                BitcoinAdapterRequestWrapper::GetSuccessorsRequest(_) => {
+                    in_flight_get_successors_requests_num += 1;
+                }
+                BitcoinAdapterRequestWrapper::GetHeadersRequest(_) => {
+                    in_flight_get_headers_requests_num += 1;
+                }
+                _ => {}
             }
             requests.insert(bitcoin_adapter_request.callback_id, bitcoin_adapter_request);
         }
@@ -340,6 +368,7 @@ impl TryFrom<pb_bitcoin::AdapterQueues> for AdapterQueues {
             responses,
             requests_queue_capacity: queues.requests_queue_capacity,
             in_flight_get_successors_requests_num,
+            in_flight_get_headers_requests_num,
         })
     }
 }
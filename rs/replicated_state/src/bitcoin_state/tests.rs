@@ -1,9 +1,12 @@
-use crate::{BitcoinState, BitcoinStateError};
+use crate::{bitcoin_state::AdapterQueues, BitcoinState, BitcoinStateError};
 use ic_btc_types::Network;
 use ic_btc_types_internal::{
     BitcoinAdapterRequestWrapper, BitcoinAdapterResponse, BitcoinAdapterResponseWrapper,
-    GetSuccessorsRequest, GetSuccessorsResponse, SendTransactionRequest, SendTransactionResponse,
+    GetHeadersRequest, GetHeadersResponse, GetSuccessorsRequest, GetSuccessorsResponse,
+    SendTransactionRequest, SendTransactionResponse,
 };
+use ic_protobuf::bitcoin::v1 as pb_bitcoin;
+use std::convert::TryFrom;
 
 #[test]
 fn can_push_requests_until_capacity_reached() {
@@ -126,6 +129,58 @@ fn can_detect_in_flight_get_successors_requests() {
         .has_in_flight_get_successors_requests());
 }
 
+#[test]
+fn can_detect_in_flight_get_headers_requests() {
+    let mut bitcoin_state = BitcoinState::default();
+    assert!(!bitcoin_state
+        .adapter_queues
+        .has_in_flight_get_headers_requests());
+
+    // Enqueue a `GetSuccessorsRequest` -- should not affect the in flight `GetHeadersRequest`s.
+    let request = BitcoinAdapterRequestWrapper::GetSuccessorsRequest(GetSuccessorsRequest {
+        processed_block_hashes: vec![vec![10; 32]],
+        anchor: vec![10; 32],
+    });
+    bitcoin_state.adapter_queues.push_request(request).unwrap();
+    assert!(!bitcoin_state
+        .adapter_queues
+        .has_in_flight_get_headers_requests());
+
+    // Enqueue a `GetHeadersRequest` -- should see the effect on in flight `GetHeadersRequest`s.
+    let request =
+        BitcoinAdapterRequestWrapper::GetHeadersRequest(GetHeadersRequest { tip: vec![5; 32] });
+    bitcoin_state.adapter_queues.push_request(request).unwrap();
+    assert!(bitcoin_state
+        .adapter_queues
+        .has_in_flight_get_headers_requests());
+    assert!(bitcoin_state
+        .adapter_queues
+        .has_in_flight_get_successors_requests());
+
+    // The in flight requests are restored when the queues are deserialized.
+    let queues = AdapterQueues::try_from(pb_bitcoin::AdapterQueues::from(
+        &bitcoin_state.adapter_queues,
+    ))
+    .unwrap();
+    assert_eq!(queues, bitcoin_state.adapter_queues);
+
+    // Clear the `GetHeadersRequest` -- should affect in flight `GetHeadersRequest`s only.
+    bitcoin_state
+        .push_response(BitcoinAdapterResponse {
+            response: BitcoinAdapterResponseWrapper::GetHeadersResponse(
+                GetHeadersResponse::default(),
+            ),
+            callback_id: 1,
+        })
+        .unwrap();
+    assert!(!bitcoin_state
+        .adapter_queues
+        .has_in_flight_get_headers_requests());
+    assert!(bitcoin_state
+        .adapter_queues
+        .has_in_flight_get_successors_requests());
+}
+
 #[test]
 fn can_pop_responses_in_the_correct_order() {
     let mut bitcoin_state = BitcoinState::default();
@@ -0,0 +1,336 @@
+use crate::{
+    canister_state::execution_state::{WasmBinary, WasmMetadata},
+    num_bytes_try_from, CanisterState, ExecutionState, ExportedFunctions, Global, Memory,
+    NumWasmPages, PageMap,
+};
+use ic_sys::PAGE_SIZE;
+use ic_types::{CanisterId, ExecutionRound, NumBytes, PrincipalId, Time};
+use ic_wasm_types::CanisterModule;
+use std::{
+    collections::BTreeMap,
+    convert::{TryFrom, TryInto},
+    fmt,
+    path::PathBuf,
+    sync::Arc,
+};
+
+/// Identifies a canister snapshot on the subnet.
+///
+/// The id consists of the id of the canister the snapshot belongs to and a
+/// local id that is unique among all snapshots ever taken of that canister.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SnapshotId {
+    canister_id: CanisterId,
+    local_id: u64,
+}
+
+impl SnapshotId {
+    pub fn new(canister_id: CanisterId, local_id: u64) -> Self {
+        Self {
+            canister_id,
+            local_id,
+        }
+    }
+
+    pub fn canister_id(&self) -> CanisterId {
+        self.canister_id
+    }
+
+    pub fn local_id(&self) -> u64 {
+        self.local_id
+    }
+
+    /// Encodes the id as the blob exposed through the management canister:
+    /// the big-endian local id followed by the canister id bytes.
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut bytes = self.local_id.to_be_bytes().to_vec();
+        bytes.extend_from_slice(self.canister_id.get_ref().as_slice());
+        bytes
+    }
+}
+
+impl fmt::Display for SnapshotId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.canister_id, self.local_id)
+    }
+}
+
+/// Error returned when a blob cannot be decoded into a `SnapshotId`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidSnapshotIdError(pub String);
+
+impl TryFrom<&[u8]> for SnapshotId {
+    type Error = InvalidSnapshotIdError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        const LOCAL_ID_LEN: usize = std::mem::size_of::<u64>();
+        if bytes.len() <= LOCAL_ID_LEN {
+            return Err(InvalidSnapshotIdError(format!(
+                "Snapshot id must be longer than {} bytes, got {}",
+                LOCAL_ID_LEN,
+                bytes.len()
+            )));
+        }
+        let (local_id, canister_id) = bytes.split_at(LOCAL_ID_LEN);
+        let local_id = u64::from_be_bytes(local_id.try_into().unwrap());
+        let canister_id = PrincipalId::try_from(canister_id)
+            .map_err(|err| InvalidSnapshotIdError(err.to_string()))
+            .and_then(|principal| {
+                CanisterId::new(principal).map_err(|err| InvalidSnapshotIdError(err.to_string()))
+            })?;
+        Ok(Self {
+            canister_id,
+            local_id,
+        })
+    }
+}
+
+/// A copy of the execution-relevant parts of a canister taken at a given
+/// point in time: the Wasm module, the heap, the stable memory, the exported
+/// globals and the certified data.
+///
+/// Snapshots are immutable once taken. Their memories are stored as
+/// `PageMap`s, so they are persisted in checkpoints the same way as the memories
+/// of canisters are.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CanisterSnapshot {
+    pub canister_id: CanisterId,
+    pub taken_at_timestamp: Time,
+    pub canister_version: u64,
+    pub certified_data: Vec<u8>,
+    pub wasm_binary: CanisterModule,
+    pub exported_globals: Vec<Global>,
+    pub exports: ExportedFunctions,
+    pub metadata: WasmMetadata,
+    pub wasm_memory: PageMap,
+    pub wasm_memory_size: NumWasmPages,
+    pub stable_memory: PageMap,
+    pub stable_memory_size: NumWasmPages,
+}
+
+impl CanisterSnapshot {
+    /// Takes a snapshot of the given canister. Returns `None` if the canister
+    /// has no Wasm module installed.
+    ///
+    /// The memories are copied into fresh `PageMap`s so that the snapshot does
+    /// not share checkpoint files with the canister. The copied pages become
+    /// part of the heap delta, see `memory_copy_size()`.
+    pub fn from_canister(canister: &CanisterState, taken_at_timestamp: Time) -> Option<Self> {
+        let execution_state = canister.execution_state.as_ref()?;
+        Some(Self {
+            canister_id: canister.canister_id(),
+            taken_at_timestamp,
+            canister_version: canister.system_state.canister_version,
+            certified_data: canister.system_state.certified_data.clone(),
+            wasm_binary: execution_state.wasm_binary.binary.clone(),
+            exported_globals: execution_state.exported_globals.clone(),
+            exports: execution_state.exports.clone(),
+            metadata: execution_state.metadata.clone(),
+            wasm_memory: copy_page_map(&execution_state.wasm_memory.page_map),
+            wasm_memory_size: execution_state.wasm_memory.size,
+            stable_memory: copy_page_map(&execution_state.stable_memory.page_map),
+            stable_memory_size: execution_state.stable_memory.size,
+        })
+    }
+
+    /// Creates a new execution state from this snapshot. The memories are
+    /// copied, so that the canister can modify them without affecting the
+    /// snapshot. The copied pages become part of the heap delta, see
+    /// `memory_copy_size()`.
+    pub fn to_execution_state(
+        &self,
+        canister_root: PathBuf,
+        last_executed_round: ExecutionRound,
+    ) -> ExecutionState {
+        let mut execution_state = ExecutionState::new(
+            canister_root,
+            WasmBinary::new(self.wasm_binary.clone()),
+            self.exports.clone(),
+            Memory::new(copy_page_map(&self.wasm_memory), self.wasm_memory_size),
+            Memory::new(copy_page_map(&self.stable_memory), self.stable_memory_size),
+            self.exported_globals.clone(),
+            self.metadata.clone(),
+        );
+        execution_state.last_executed_round = last_executed_round;
+        execution_state
+    }
+
+    /// Returns the number of bytes this snapshot occupies. It is counted
+    /// against the subnet memory capacity.
+    pub fn size(&self) -> NumBytes {
+        snapshot_size(
+            self.wasm_memory_size,
+            self.stable_memory_size,
+            self.exported_globals.len(),
+            self.wasm_binary.len(),
+            self.certified_data.len(),
+        )
+    }
+
+    /// Returns the size that a snapshot of a canister with the given execution
+    /// state and certified data would have, without taking the snapshot.
+    pub fn size_of(execution_state: &ExecutionState, certified_data: &[u8]) -> NumBytes {
+        snapshot_size(
+            execution_state.wasm_memory.size,
+            execution_state.stable_memory.size,
+            execution_state.exported_globals.len(),
+            execution_state.wasm_binary.binary.len(),
+            certified_data.len(),
+        )
+    }
+}
+
+fn snapshot_size(
+    wasm_memory_size: NumWasmPages,
+    stable_memory_size: NumWasmPages,
+    num_globals: usize,
+    wasm_binary_len: usize,
+    certified_data_len: usize,
+) -> NumBytes {
+    // We use 8 bytes per global, same as for execution states.
+    let globals_size_bytes = 8 * num_globals as u64;
+    num_bytes_try_from(wasm_memory_size)
+        .expect("could not convert from wasm memory number of pages to bytes")
+        + num_bytes_try_from(stable_memory_size)
+            .expect("could not convert from stable memory number of pages to bytes")
+        + NumBytes::from(globals_size_bytes)
+        + NumBytes::from(wasm_binary_len as u64)
+        + NumBytes::from(certified_data_len as u64)
+}
+
+/// Returns the number of bytes copied into the heap delta when the given
+/// memories are copied into or out of a snapshot.
+pub fn memory_copy_size(wasm_memory: &PageMap, stable_memory: &PageMap) -> NumBytes {
+    let num_pages = wasm_memory.num_host_pages() + stable_memory.num_host_pages();
+    NumBytes::from((num_pages * PAGE_SIZE) as u64)
+}
+
+/// Copies all pages of `page_map` into a new `PageMap` that is not backed by
+/// any checkpoint file.
+fn copy_page_map(page_map: &PageMap) -> PageMap {
+    let pages: Vec<_> = page_map.host_pages_iter().collect();
+    let mut copy = PageMap::default();
+    copy.update(&pages);
+    copy
+}
+
+/// All snapshots of all canisters on the subnet.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CanisterSnapshots {
+    snapshots: BTreeMap<SnapshotId, Arc<CanisterSnapshot>>,
+}
+
+impl CanisterSnapshots {
+    pub fn new(snapshots: BTreeMap<SnapshotId, Arc<CanisterSnapshot>>) -> Self {
+        Self { snapshots }
+    }
+
+    pub fn get(&self, snapshot_id: &SnapshotId) -> Option<&Arc<CanisterSnapshot>> {
+        self.snapshots.get(snapshot_id)
+    }
+
+    /// Returns a mutable reference to the snapshot. Only meant to be used by
+    /// the state manager to manage the `PageMap`s of the snapshot.
+    pub fn get_mut(&mut self, snapshot_id: &SnapshotId) -> Option<&mut CanisterSnapshot> {
+        self.snapshots.get_mut(snapshot_id).map(Arc::make_mut)
+    }
+
+    pub fn insert(&mut self, snapshot_id: SnapshotId, snapshot: CanisterSnapshot) {
+        self.snapshots.insert(snapshot_id, Arc::new(snapshot));
+    }
+
+    pub fn remove(&mut self, snapshot_id: &SnapshotId) -> Option<Arc<CanisterSnapshot>> {
+        self.snapshots.remove(snapshot_id)
+    }
+
+    /// Removes all snapshots of the given canister.
+    pub fn remove_canister_snapshots(&mut self, canister_id: CanisterId) {
+        self.snapshots
+            .retain(|id, _| id.canister_id() != canister_id);
+    }
+
+    /// Returns the snapshots of the given canister, ordered by their ids.
+    pub fn list_snapshots(
+        &self,
+        canister_id: CanisterId,
+    ) -> impl Iterator<Item = (&SnapshotId, &Arc<CanisterSnapshot>)> {
+        self.snapshots
+            .range(SnapshotId::new(canister_id, 0)..=SnapshotId::new(canister_id, u64::MAX))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&SnapshotId, &Arc<CanisterSnapshot>)> {
+        self.snapshots.iter()
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = &SnapshotId> {
+        self.snapshots.keys()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    /// Returns the total memory taken by the snapshots of the given canister.
+    pub fn canister_memory_taken(&self, canister_id: CanisterId) -> NumBytes {
+        self.list_snapshots(canister_id)
+            .map(|(_, snapshot)| snapshot.size())
+            .sum()
+    }
+
+    /// Returns the total memory taken by all snapshots on the subnet.
+    pub fn memory_taken(&self) -> NumBytes {
+        self.snapshots
+            .values()
+            .map(|snapshot| snapshot.size())
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_test_utilities::types::ids::canister_test_id;
+
+    #[test]
+    fn snapshot_id_round_trip() {
+        let id = SnapshotId::new(canister_test_id(42), 7);
+        assert_eq!(SnapshotId::try_from(id.to_vec().as_slice()), Ok(id));
+    }
+
+    #[test]
+    fn snapshot_id_rejects_short_blobs() {
+        assert!(SnapshotId::try_from(&[0u8; 8][..]).is_err());
+    }
+
+    #[test]
+    fn list_snapshots_only_returns_snapshots_of_canister() {
+        let mut snapshots = BTreeMap::new();
+        for (canister, local_id) in [(1, 0), (2, 0), (2, 1), (3, 5)] {
+            let id = SnapshotId::new(canister_test_id(canister), local_id);
+            snapshots.insert(id, Arc::new(test_snapshot(canister_test_id(canister))));
+        }
+        let snapshots = CanisterSnapshots::new(snapshots);
+        let ids: Vec<_> = snapshots
+            .list_snapshots(canister_test_id(2))
+            .map(|(id, _)| id.local_id())
+            .collect();
+        assert_eq!(ids, vec![0, 1]);
+    }
+
+    fn test_snapshot(canister_id: CanisterId) -> CanisterSnapshot {
+        CanisterSnapshot {
+            canister_id,
+            taken_at_timestamp: Time::from_nanos_since_unix_epoch(0),
+            canister_version: 0,
+            certified_data: vec![],
+            wasm_binary: CanisterModule::new(vec![]),
+            exported_globals: vec![],
+            exports: ExportedFunctions::new(Default::default()),
+            metadata: WasmMetadata::default(),
+            wasm_memory: PageMap::default(),
+            wasm_memory_size: NumWasmPages::from(0),
+            stable_memory: PageMap::default(),
+            stable_memory_size: NumWasmPages::from(0),
+        }
+    }
+}
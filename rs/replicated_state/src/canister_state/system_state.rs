@@ -6,6 +6,7 @@ pub use crate::canister_state::queues::CanisterOutputQueuesIterator;
 use crate::{CanisterQueues, CanisterState, InputQueueType, StateError};
 pub use call_context_manager::{CallContext, CallContextAction, CallContextManager, CallOrigin};
 use ic_base_types::NumSeconds;
+use ic_ic00_types::LogVisibility;
 use ic_interfaces::messages::{CanisterInputMessage, RequestOrIngress};
 use ic_logger::{error, ReplicaLogger};
 use ic_protobuf::{
@@ -14,6 +15,7 @@ use ic_protobuf::{
 };
 use ic_registry_subnet_type::SubnetType;
 use ic_types::{
+    canister_log::CanisterLog,
     messages::{Ingress, RejectContext, Request, RequestOrResponse, Response, StopCanisterContext},
     nominal_cycles::NominalCycles,
     CanisterId, CanisterTimer, Cycles, MemoryAllocation, NumBytes, PrincipalId, Time,
@@ -112,6 +114,16 @@ pub struct SystemState {
 
     /// Canister version.
     pub canister_version: u64,
+
+    /// The local id that the next snapshot taken of this canister will get.
+    /// It is never decremented, so snapshot ids are never reused.
+    pub next_snapshot_id: u64,
+
+    /// Log records produced by `ic0.debug_print` and by traps.
+    pub canister_log: CanisterLog,
+
+    /// Determines who can fetch the canister log.
+    pub log_visibility: LogVisibility,
 }
 
 /// A wrapper around the different canister statuses.
@@ -416,6 +428,9 @@ impl SystemState {
             task_queue: Default::default(),
             global_timer: CanisterTimer::Inactive,
             canister_version: 0,
+            next_snapshot_id: 0,
+            canister_log: Default::default(),
+            log_visibility: Default::default(),
         }
     }
 
@@ -450,6 +465,9 @@ impl SystemState {
         task_queue: VecDeque<ExecutionTask>,
         global_timer: CanisterTimer,
         canister_version: u64,
+        next_snapshot_id: u64,
+        canister_log: CanisterLog,
+        log_visibility: LogVisibility,
     ) -> Self {
         Self {
             controllers,
@@ -465,6 +483,9 @@ impl SystemState {
             task_queue,
             global_timer,
             canister_version,
+            next_snapshot_id,
+            canister_log,
+            log_visibility,
         }
     }
 
@@ -1,5 +1,6 @@
 mod bitcoin;
 pub mod bitcoin_state;
+pub mod canister_snapshots;
 pub mod canister_state;
 pub mod metadata_state;
 pub mod page_map;
@@ -11,6 +12,7 @@ pub mod testing {
     pub use super::replicated_state::testing::ReplicatedStateTesting;
 }
 pub use bitcoin_state::{BitcoinState, BitcoinStateError};
+pub use canister_snapshots::{CanisterSnapshot, CanisterSnapshots, SnapshotId};
 pub use canister_state::{
     execution_state::Memory,
     num_bytes_try_from,
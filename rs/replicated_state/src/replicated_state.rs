@@ -7,7 +7,7 @@ use crate::{
     canister_state::queues::CanisterQueuesLoopDetector,
     canister_state::system_state::{push_input, CanisterOutputQueuesIterator},
     metadata_state::StreamMap,
-    CanisterQueues,
+    CanisterQueues, CanisterSnapshots,
 };
 use ic_base_types::PrincipalId;
 use ic_btc_types_internal::{BitcoinAdapterRequestWrapper, BitcoinAdapterResponse};
@@ -331,6 +331,9 @@ pub struct ReplicatedState {
     pub consensus_queue: Vec<Response>,
 
     bitcoin: BitcoinState,
+
+    /// Snapshots of canisters taken via `take_canister_snapshot`.
+    pub canister_snapshots: CanisterSnapshots,
 }
 
 impl ReplicatedState {
@@ -342,6 +345,7 @@ impl ReplicatedState {
             subnet_queues: CanisterQueues::default(),
             consensus_queue: Vec::new(),
             bitcoin: BitcoinState::default(),
+            canister_snapshots: CanisterSnapshots::default(),
         }
     }
 
@@ -358,6 +362,7 @@ impl ReplicatedState {
             subnet_queues,
             consensus_queue,
             bitcoin,
+            canister_snapshots: CanisterSnapshots::default(),
         };
         res.update_stream_responses_size_bytes();
         res
@@ -530,10 +535,11 @@ impl ReplicatedState {
 
     /// Returns:
     ///   * the raw total memory taken by canisters in bytes, i.e. without
-    ///     including memory taken by canister messages
+    ///     including memory taken by canister messages, but including the
+    ///     memory taken by canister snapshots
     ///   * the memory taken by canister messages in bytes
     pub fn raw_total_and_message_memory_taken(&self) -> (NumBytes, NumBytes) {
-        let (raw_memory_taken, mut message_memory_taken) = self
+        let (mut raw_memory_taken, mut message_memory_taken) = self
             .canisters_iter()
             .map(|canister| {
                 (
@@ -548,6 +554,7 @@ impl ReplicatedState {
             .unwrap_or_default();
 
         message_memory_taken += (self.subnet_queues.memory_usage() as u64).into();
+        raw_memory_taken += self.canister_snapshots.memory_taken();
 
         (raw_memory_taken, message_memory_taken)
     }
@@ -1,5 +1,6 @@
 use dfn_core::api::now;
 use ic_base_types::{CanisterId, PrincipalId};
+use ic_ledger_canister_core::approvals::AllowanceTable;
 use ic_ledger_canister_core::archive::ArchiveCanisterWasm;
 use ic_ledger_canister_core::blockchain::Blockchain;
 use ic_ledger_canister_core::ledger::{self as core_ledger, LedgerData, TransactionInfo};
@@ -97,6 +98,10 @@ pub struct Ledger {
     /// Token name
     #[serde(default = "unknown_token")]
     pub token_name: String,
+
+    /// The ICP ledger does not support approvals, the table is always empty.
+    #[serde(default)]
+    approvals: AllowanceTable<AccountIdentifier>,
 }
 
 impl LedgerData for Ledger {
@@ -126,6 +131,10 @@ impl LedgerData for Ledger {
         self.accounts_overflow_trim_quantity
     }
 
+    fn max_number_of_approvals(&self) -> usize {
+        0
+    }
+
     fn token_name(&self) -> &str {
         &self.token_name
     }
@@ -142,6 +151,14 @@ impl LedgerData for Ledger {
         &mut self.balances
     }
 
+    fn approvals(&self) -> &AllowanceTable<Self::AccountId> {
+        &self.approvals
+    }
+
+    fn approvals_mut(&mut self) -> &mut AllowanceTable<Self::AccountId> {
+        &mut self.approvals
+    }
+
     fn blockchain(&self) -> &Blockchain<Self::Runtime, Self::ArchiveWasm> {
         &self.blockchain
     }
@@ -188,6 +205,7 @@ impl Default for Ledger {
             transfer_fee: DEFAULT_TRANSFER_FEE,
             token_symbol: unknown_token(),
             token_name: unknown_token(),
+            approvals: AllowanceTable::default(),
         }
     }
 }
@@ -245,6 +263,13 @@ impl Ledger {
                 }),
                 CTE::TxCreatedInFuture { .. } => PTE(TE::TxCreatedInFuture),
                 CTE::TxDuplicate { duplicate_of } => PTE(TE::TxDuplicate { duplicate_of }),
+                CTE::InsufficientAllowance { .. }
+                | CTE::AllowanceChanged { .. }
+                | CTE::ExpiredApproval { .. }
+                | CTE::SelfApproval => PaymentError::Reject(format!(
+                    "the ICP ledger does not support approvals: {:?}",
+                    e
+                )),
                 CTE::TxThrottled => PaymentError::Reject(
                     concat!(
                         "Too many transactions in replay prevention window, ",
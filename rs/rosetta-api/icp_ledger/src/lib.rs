@@ -3,7 +3,10 @@ use dfn_protobuf::ProtoBuf;
 use ic_base_types::{CanisterId, PrincipalId};
 use ic_crypto_sha::Sha256;
 pub use ic_ledger_canister_core::archive::ArchiveOptions;
-use ic_ledger_canister_core::ledger::LedgerTransaction;
+use ic_ledger_canister_core::{
+    approvals::AllowanceTable,
+    ledger::{LedgerTransaction, TxApplyError},
+};
 use ic_ledger_core::{
     balances::{BalanceError, Balances, BalancesStore},
     block::{BlockType, EncodedBlock, HashOf, HASH_LENGTH},
@@ -146,11 +149,17 @@ impl LedgerTransaction for Transaction {
         HashOf::new(state.finish())
     }
 
-    fn apply<S>(&self, balances: &mut Balances<Self::AccountId, S>) -> Result<(), BalanceError>
+    fn apply<S>(
+        &self,
+        balances: &mut Balances<Self::AccountId, S>,
+        _approvals: &mut AllowanceTable<Self::AccountId>,
+        _now: TimeStamp,
+    ) -> Result<(), TxApplyError>
     where
         S: Default + BalancesStore<Self::AccountId>,
     {
-        apply_operation(balances, &self.operation)
+        apply_operation(balances, &self.operation)?;
+        Ok(())
     }
 }
 
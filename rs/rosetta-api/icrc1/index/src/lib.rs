@@ -6,7 +6,7 @@ use ic_base_types::{CanisterId, PrincipalId};
 use ic_cdk::api::stable::{StableReader, StableWriter};
 use ic_icrc1::endpoints::{ArchivedTransactionRange, TransactionRange};
 use ic_icrc1::{
-    endpoints::{GetTransactionsRequest, GetTransactionsResponse, Transaction, Transfer},
+    endpoints::{Approve, GetTransactionsRequest, GetTransactionsResponse, Transaction, Transfer},
     Account, Subaccount,
 };
 use num_traits::cast::ToPrimitive;
@@ -270,6 +270,14 @@ fn index_transaction(txid: u64, transaction: Transaction) -> Result<(), String>
             add_tx(txid, to);
             Ok(())
         }
+        "approve" => {
+            let Approve { from, spender, .. } = transaction
+                .approve
+                .ok_or("Got a transaction with kind 'approve' but the approve field was None")?;
+            add_tx(txid, from);
+            add_tx(txid, spender);
+            Ok(())
+        }
         kind => Err(format!("Found transaction of unknown kind {}", kind)),
     }
 }
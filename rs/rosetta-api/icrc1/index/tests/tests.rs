@@ -3,7 +3,7 @@ use std::path::PathBuf;
 use candid::{Decode, Encode, Nat};
 use ic_base_types::PrincipalId;
 use ic_icrc1::{
-    endpoints::{ArchiveInfo, TransferArg, TransferError, Value},
+    endpoints::{ApproveArgs, ApproveError, ArchiveInfo, TransferArg, TransferError, Value},
     Account, Block, Memo, Operation, Subaccount, Transaction,
 };
 use ic_icrc1_index::{
@@ -201,6 +201,39 @@ fn transfer(
     .unwrap()
 }
 
+fn approve(
+    env: &StateMachine,
+    ledger: CanisterId,
+    from: Account,
+    spender: Account,
+    amount: u64,
+) -> BlockIndex {
+    Decode!(
+        &env.execute_ingress_as(
+            from.owner,
+            ledger,
+            "icrc2_approve",
+            Encode!(&ApproveArgs {
+                from_subaccount: from.subaccount,
+                spender,
+                amount: Nat::from(amount),
+                expected_allowance: None,
+                expires_at: None,
+                fee: None,
+                memo: None,
+                created_at_time: None,
+            })
+            .unwrap()
+        )
+        .expect("failed to approve")
+        .bytes(),
+        Result<Nat, ApproveError>
+    )
+    .expect("failed to decode approve response")
+    .map(|n| n.0.to_u64().unwrap())
+    .unwrap()
+}
+
 fn burn(env: &StateMachine, ledger: CanisterId, from: Account, amount: u64) -> BlockIndex {
     transfer(env, ledger, from, MINTER, amount)
 }
@@ -395,6 +428,30 @@ fn test_upgrade() {
     check_transfer(1, account(1), account(2), 1, txs.get(0).unwrap());
 }
 
+#[test]
+fn test_index_approvals() {
+    let env = StateMachine::new();
+    let ledger_id = install_ledger(&env, vec![], default_archive_options());
+    let index_id = install_index(&env, ledger_id);
+
+    mint(&env, ledger_id, account(1), 100000); // block=0
+    approve(&env, ledger_id, account(1), account(2), 500); // block=1
+
+    env.tick(); // trigger index heartbeat
+
+    for spender_or_approver in [account(1), account(2)] {
+        let txs = get_account_transactions(&env, index_id, spender_or_approver, None, u64::MAX);
+        let tx = &txs.transactions[0];
+        assert_eq!(tx.id, Nat::from(1));
+        assert_eq!("approve".to_string(), tx.transaction.kind);
+        let approve = tx.transaction.approve.as_ref().unwrap();
+        assert_eq!(
+            (&approve.from, &approve.spender, &approve.amount),
+            (&account(1), &account(2), &Nat::from(500))
+        );
+    }
+}
+
 #[test]
 fn test_index_archived_txs() {
     let env = StateMachine::new();
@@ -10,6 +10,7 @@ use ic_icrc1::endpoints::{
 };
 use ic_icrc1::{Account, Block, LedgerBalances, Transaction};
 use ic_ledger_canister_core::{
+    approvals::AllowanceTable,
     archive::{ArchiveCanisterWasm, ArchiveOptions},
     blockchain::Blockchain,
     ledger::{apply_transaction, block_locations, LedgerData, TransactionInfo},
@@ -35,6 +36,7 @@ const MAX_TRANSACTIONS_PER_REQUEST: usize = 2_000;
 const ACCOUNTS_OVERFLOW_TRIM_QUANTITY: usize = 100_000;
 const MAX_TRANSACTIONS_IN_WINDOW: usize = 3_000_000;
 const MAX_TRANSACTIONS_TO_PURGE: usize = 100_000;
+const MAX_APPROVALS: usize = 500_000;
 
 #[derive(Debug, Clone)]
 pub struct Icrc1ArchiveWasm;
@@ -104,6 +106,8 @@ pub struct InitArgs {
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Ledger {
     balances: LedgerBalances,
+    #[serde(default)]
+    approvals: AllowanceTable<Account>,
     blockchain: Blockchain<CdkRuntime, Icrc1ArchiveWasm>,
 
     minting_account: Account,
@@ -132,6 +136,7 @@ impl Ledger {
     ) -> Self {
         let mut ledger = Self {
             balances: LedgerBalances::default(),
+            approvals: AllowanceTable::default(),
             blockchain: Blockchain::new_with_archive(archive_options),
             transactions_by_hash: BTreeMap::new(),
             transactions_by_height: VecDeque::new(),
@@ -187,6 +192,10 @@ impl LedgerData for Ledger {
         ACCOUNTS_OVERFLOW_TRIM_QUANTITY
     }
 
+    fn max_number_of_approvals(&self) -> usize {
+        MAX_APPROVALS
+    }
+
     fn token_name(&self) -> &str {
         &self.token_name
     }
@@ -203,6 +212,14 @@ impl LedgerData for Ledger {
         &mut self.balances
     }
 
+    fn approvals(&self) -> &AllowanceTable<Self::AccountId> {
+        &self.approvals
+    }
+
+    fn approvals_mut(&mut self) -> &mut AllowanceTable<Self::AccountId> {
+        &mut self.approvals
+    }
+
     fn blockchain(&self) -> &Blockchain<Self::Runtime, Self::ArchiveWasm> {
         &self.blockchain
     }
@@ -3,8 +3,10 @@ use candid::{CandidType, Decode, Encode};
 use ic_base_types::PrincipalId;
 use ic_icrc1::{
     endpoints::{
-        ArchiveInfo, GetTransactionsRequest, GetTransactionsResponse, StandardRecord,
-        Transaction as Tx, TransactionRange, Transfer, TransferArg, TransferError, Value,
+        Allowance, AllowanceArgs, Approve, ApproveArgs, ApproveError, ArchiveInfo,
+        GetTransactionsRequest, GetTransactionsResponse, StandardRecord, Transaction as Tx,
+        TransactionRange, Transfer, TransferArg, TransferError, TransferFromArgs,
+        TransferFromError, Value,
     },
     Account, Block, Memo, Operation, Transaction,
 };
@@ -128,6 +130,109 @@ fn transfer(
     )
 }
 
+fn send_approval(
+    env: &StateMachine,
+    ledger: CanisterId,
+    from: PrincipalId,
+    arg: &ApproveArgs,
+) -> Result<BlockIndex, ApproveError> {
+    Decode!(
+        &env.execute_ingress_as(
+            from,
+            ledger,
+            "icrc2_approve",
+            Encode!(arg)
+            .unwrap()
+        )
+        .expect("failed to approve")
+        .bytes(),
+        Result<Nat, ApproveError>
+    )
+    .expect("failed to decode approve response")
+    .map(|n| n.0.to_u64().unwrap())
+}
+
+fn approve(
+    env: &StateMachine,
+    ledger: CanisterId,
+    from: impl Into<Account>,
+    spender: impl Into<Account>,
+    amount: u64,
+) -> Result<BlockIndex, ApproveError> {
+    let from = from.into();
+    send_approval(
+        env,
+        ledger,
+        from.owner,
+        &ApproveArgs {
+            from_subaccount: from.subaccount,
+            spender: spender.into(),
+            amount: Nat::from(amount),
+            expected_allowance: None,
+            expires_at: None,
+            fee: None,
+            memo: None,
+            created_at_time: None,
+        },
+    )
+}
+
+fn transfer_from(
+    env: &StateMachine,
+    ledger: CanisterId,
+    spender: impl Into<Account>,
+    from: impl Into<Account>,
+    to: impl Into<Account>,
+    amount: u64,
+) -> Result<BlockIndex, TransferFromError> {
+    let spender = spender.into();
+    Decode!(
+        &env.execute_ingress_as(
+            spender.owner,
+            ledger,
+            "icrc2_transfer_from",
+            Encode!(&TransferFromArgs {
+                spender_subaccount: spender.subaccount,
+                from: from.into(),
+                to: to.into(),
+                amount: Nat::from(amount),
+                fee: None,
+                memo: None,
+                created_at_time: None,
+            })
+            .unwrap()
+        )
+        .expect("failed to transfer funds")
+        .bytes(),
+        Result<Nat, TransferFromError>
+    )
+    .expect("failed to decode transfer_from response")
+    .map(|n| n.0.to_u64().unwrap())
+}
+
+fn allowance(
+    env: &StateMachine,
+    ledger: CanisterId,
+    account: impl Into<Account>,
+    spender: impl Into<Account>,
+) -> Allowance {
+    Decode!(
+        &env.query(
+            ledger,
+            "icrc2_allowance",
+            Encode!(&AllowanceArgs {
+                account: account.into(),
+                spender: spender.into(),
+            })
+            .unwrap()
+        )
+        .expect("failed to query allowance")
+        .bytes(),
+        Allowance
+    )
+    .expect("failed to decode allowance response")
+}
+
 fn list_archives(env: &StateMachine, ledger: CanisterId) -> Vec<ArchiveInfo> {
     Decode!(
         &env.query(ledger, "archives", Encode!().unwrap())
@@ -273,10 +378,16 @@ fn test_metadata() {
     let standards = supported_standards(&env, canister_id);
     assert_eq!(
         standards,
-        vec![StandardRecord {
-            name: "ICRC-1".to_string(),
-            url: "https://github.com/dfinity/ICRC-1".to_string(),
-        }]
+        vec![
+            StandardRecord {
+                name: "ICRC-1".to_string(),
+                url: "https://github.com/dfinity/ICRC-1".to_string(),
+            },
+            StandardRecord {
+                name: "ICRC-2".to_string(),
+                url: "https://github.com/dfinity/ICRC-1/tree/main/standards/ICRC-2".to_string(),
+            },
+        ]
     );
 }
 
@@ -664,6 +775,177 @@ fn test_tx_time_bounds() {
     assert_eq!(0u64, balance_of(&env, canister_id, p2));
 }
 
+#[test]
+fn test_approve_and_transfer_from() {
+    let env = StateMachine::new();
+    let p1 = PrincipalId::new_user_test_id(1);
+    let p2 = PrincipalId::new_user_test_id(2);
+    let p3 = PrincipalId::new_user_test_id(3);
+    let canister_id = install_ledger(&env, vec![(Account::from(p1), 10_000_000)]);
+
+    assert_eq!(
+        Err(TransferFromError::InsufficientAllowance {
+            allowance: Nat::from(0u64)
+        }),
+        transfer_from(&env, canister_id, p2, p1, p3, 1_000_000),
+    );
+
+    let block_idx = approve(&env, canister_id, p1, p2, 3_000_000).expect("approve failed");
+    assert_eq!(
+        allowance(&env, canister_id, p1, p2),
+        Allowance {
+            allowance: Nat::from(3_000_000u64),
+            expires_at: None,
+        }
+    );
+    // The approval fee is charged from the approver.
+    assert_eq!(10_000_000 - FEE, balance_of(&env, canister_id, p1));
+
+    let tx = get_transactions(&env, canister_id, block_idx, 1).transactions[0].clone();
+    assert_eq!(tx.kind, "approve");
+    assert_eq!(
+        tx.approve,
+        Some(Approve {
+            from: p1.into(),
+            spender: p2.into(),
+            amount: Nat::from(3_000_000u64),
+            expected_allowance: None,
+            expires_at: None,
+            memo: None,
+            fee: Some(Nat::from(FEE)),
+            created_at_time: None,
+        })
+    );
+
+    let block_idx =
+        transfer_from(&env, canister_id, p2, p1, p3, 1_000_000).expect("transfer_from failed");
+    assert_eq!(
+        10_000_000 - 2 * FEE - 1_000_000,
+        balance_of(&env, canister_id, p1)
+    );
+    assert_eq!(0, balance_of(&env, canister_id, p2));
+    assert_eq!(1_000_000, balance_of(&env, canister_id, p3));
+    // The spender pays both the amount and the fee out of the allowance.
+    assert_eq!(
+        allowance(&env, canister_id, p1, p2).allowance,
+        Nat::from(2_000_000 - FEE)
+    );
+
+    let tx = get_transactions(&env, canister_id, block_idx, 1).transactions[0].clone();
+    assert_eq!(tx.transfer.unwrap().spender, Some(p2.into()));
+
+    assert_eq!(
+        Err(TransferFromError::InsufficientAllowance {
+            allowance: Nat::from(2_000_000 - FEE)
+        }),
+        transfer_from(&env, canister_id, p2, p1, p3, 2_000_000),
+    );
+    // Other accounts cannot use the allowance of the spender.
+    assert_eq!(
+        Err(TransferFromError::InsufficientAllowance {
+            allowance: Nat::from(0u64)
+        }),
+        transfer_from(&env, canister_id, p3, p1, p3, 1),
+    );
+}
+
+#[test]
+fn test_approve_expected_allowance() {
+    let env = StateMachine::new();
+    let p1 = PrincipalId::new_user_test_id(1);
+    let p2 = PrincipalId::new_user_test_id(2);
+    let canister_id = install_ledger(&env, vec![(Account::from(p1), 10_000_000)]);
+
+    approve(&env, canister_id, p1, p2, 1_000_000).expect("approve failed");
+
+    let approve_args = |expected_allowance: u64, amount: u64| ApproveArgs {
+        from_subaccount: None,
+        spender: p2.into(),
+        amount: Nat::from(amount),
+        expected_allowance: Some(Nat::from(expected_allowance)),
+        expires_at: None,
+        fee: None,
+        memo: None,
+        created_at_time: None,
+    };
+
+    assert_eq!(
+        Err(ApproveError::AllowanceChanged {
+            current_allowance: Nat::from(1_000_000u64)
+        }),
+        send_approval(&env, canister_id, p1, &approve_args(500_000, 2_000_000)),
+    );
+    send_approval(&env, canister_id, p1, &approve_args(1_000_000, 2_000_000))
+        .expect("approve failed");
+    assert_eq!(
+        allowance(&env, canister_id, p1, p2).allowance,
+        Nat::from(2_000_000u64)
+    );
+
+    // Approving yourself is not allowed.
+    assert!(matches!(
+        approve(&env, canister_id, p1, p1, 1_000_000),
+        Err(ApproveError::GenericError { .. })
+    ));
+}
+
+#[test]
+fn test_approval_expiration() {
+    let env = StateMachine::new();
+    let p1 = PrincipalId::new_user_test_id(1);
+    let p2 = PrincipalId::new_user_test_id(2);
+    let p3 = PrincipalId::new_user_test_id(3);
+    let canister_id = install_ledger(&env, vec![(Account::from(p1), 10_000_000)]);
+
+    let now = system_time_to_nanos(env.time());
+    let approve_args = |expires_at: u64| ApproveArgs {
+        from_subaccount: None,
+        spender: p2.into(),
+        amount: Nat::from(1_000_000u64),
+        expected_allowance: None,
+        expires_at: Some(expires_at),
+        fee: None,
+        memo: None,
+        created_at_time: None,
+    };
+
+    assert_eq!(
+        Err(ApproveError::Expired { ledger_time: now }),
+        send_approval(&env, canister_id, p1, &approve_args(now - 1)),
+    );
+    assert_eq!(
+        Err(ApproveError::Expired { ledger_time: now }),
+        send_approval(&env, canister_id, p1, &approve_args(now)),
+    );
+
+    let expires_at = now + Duration::from_secs(3600).as_nanos() as u64;
+    send_approval(&env, canister_id, p1, &approve_args(expires_at)).expect("approve failed");
+    assert_eq!(
+        allowance(&env, canister_id, p1, p2),
+        Allowance {
+            allowance: Nat::from(1_000_000u64),
+            expires_at: Some(expires_at),
+        }
+    );
+
+    env.advance_time(Duration::from_secs(2 * 3600));
+    env.tick();
+
+    assert_eq!(
+        allowance(&env, canister_id, p1, p2),
+        Allowance {
+            allowance: Nat::from(0u64),
+            expires_at: None,
+        }
+    );
+    assert_eq!(
+        Err(TransferFromError::InsufficientAllowance {
+            allowance: Nat::from(0u64)
+        }),
+        transfer_from(&env, canister_id, p2, p1, p3, 1),
+    );
+}
+
 #[test]
 fn test_archiving() {
     let env = StateMachine::new();
@@ -706,6 +988,7 @@ fn test_archiving() {
         let expected_tx = Transfer {
             from: p1.into(),
             to: p2.into(),
+            spender: None,
             amount: Nat::from(10_000 + i - 1),
             fee: Some(Nat::from(FEE)),
             memo: None,
@@ -742,6 +1025,7 @@ fn test_archiving() {
             Some(Transfer {
                 from: p1.into(),
                 to: p2.into(),
+                spender: None,
                 amount: Nat::from(10_000 + i - 1),
                 fee: Some(Nat::from(FEE)),
                 memo: None,
@@ -797,14 +1081,41 @@ fn arb_account() -> impl Strategy<Value = Account> {
 }
 
 fn arb_transfer() -> impl Strategy<Value = Operation> {
-    (arb_account(), arb_account(), arb_amount(), arb_amount()).prop_map(
-        |(from, to, amount, fee)| Operation::Transfer {
+    (
+        arb_account(),
+        arb_account(),
+        proptest::option::of(arb_account()),
+        arb_amount(),
+        arb_amount(),
+    )
+        .prop_map(|(from, to, spender, amount, fee)| Operation::Transfer {
             from,
             to,
+            spender,
             amount,
             fee,
-        },
+        })
+}
+
+fn arb_approve() -> impl Strategy<Value = Operation> {
+    (
+        arb_account(),
+        arb_account(),
+        arb_amount(),
+        any::<Option<u64>>(),
+        any::<Option<u64>>(),
+        arb_amount(),
     )
+        .prop_map(
+            |(from, spender, amount, expected_allowance, expires_at, fee)| Operation::Approve {
+                from,
+                spender,
+                amount,
+                expected_allowance,
+                expires_at,
+                fee,
+            },
+        )
 }
 
 fn arb_mint() -> impl Strategy<Value = Operation> {
@@ -816,7 +1127,7 @@ fn arb_burn() -> impl Strategy<Value = Operation> {
 }
 
 fn arb_operation() -> impl Strategy<Value = Operation> {
-    prop_oneof![arb_transfer(), arb_mint(), arb_burn()]
+    prop_oneof![arb_transfer(), arb_mint(), arb_burn(), arb_approve()]
 }
 
 fn arb_transaction() -> impl Strategy<Value = Transaction> {
@@ -10,6 +10,9 @@ use std::convert::TryFrom;
 pub type NumTokens = Nat;
 pub type BlockIndex = Nat;
 
+/// The error code of the `GenericError` variants returned by the ledger.
+pub const GENERIC_ERROR_CODE: u64 = 0;
+
 #[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
 pub enum TransferError {
     BadFee { expected_fee: NumTokens },
@@ -42,6 +45,13 @@ impl From<CoreTransferError> for TransferError {
             LTE::TxDuplicate { duplicate_of } => TE::Duplicate {
                 duplicate_of: Nat::from(duplicate_of),
             },
+            LTE::InsufficientAllowance { .. }
+            | LTE::AllowanceChanged { .. }
+            | LTE::ExpiredApproval { .. }
+            | LTE::SelfApproval => TE::GenericError {
+                error_code: Nat::from(GENERIC_ERROR_CODE),
+                message: format!("unexpected error for a transfer: {:?}", err),
+            },
         }
     }
 }
@@ -60,6 +70,149 @@ pub struct TransferArg {
     pub amount: NumTokens,
 }
 
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct ApproveArgs {
+    #[serde(default)]
+    pub from_subaccount: Option<Subaccount>,
+    pub spender: Account,
+    pub amount: NumTokens,
+    #[serde(default)]
+    pub expected_allowance: Option<NumTokens>,
+    #[serde(default)]
+    pub expires_at: Option<u64>,
+    #[serde(default)]
+    pub fee: Option<NumTokens>,
+    #[serde(default)]
+    pub memo: Option<Memo>,
+    #[serde(default)]
+    pub created_at_time: Option<u64>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum ApproveError {
+    BadFee { expected_fee: NumTokens },
+    InsufficientFunds { balance: NumTokens },
+    AllowanceChanged { current_allowance: NumTokens },
+    Expired { ledger_time: u64 },
+    TooOld,
+    CreatedInFuture { ledger_time: u64 },
+    Duplicate { duplicate_of: BlockIndex },
+    TemporarilyUnavailable,
+    GenericError { error_code: Nat, message: String },
+}
+
+impl From<CoreTransferError> for ApproveError {
+    fn from(err: CoreTransferError) -> Self {
+        use ic_ledger_canister_core::ledger::TransferError as LTE;
+        use ApproveError as AE;
+
+        match err {
+            LTE::BadFee { expected_fee } => AE::BadFee {
+                expected_fee: Nat::from(expected_fee.get_e8s()),
+            },
+            LTE::InsufficientFunds { balance } => AE::InsufficientFunds {
+                balance: Nat::from(balance.get_e8s()),
+            },
+            LTE::AllowanceChanged { current_allowance } => AE::AllowanceChanged {
+                current_allowance: Nat::from(current_allowance.get_e8s()),
+            },
+            LTE::ExpiredApproval { ledger_time } => AE::Expired {
+                ledger_time: ledger_time.as_nanos_since_unix_epoch(),
+            },
+            LTE::TxTooOld { .. } => AE::TooOld,
+            LTE::TxCreatedInFuture { ledger_time } => AE::CreatedInFuture {
+                ledger_time: ledger_time.as_nanos_since_unix_epoch(),
+            },
+            LTE::TxThrottled => AE::TemporarilyUnavailable,
+            LTE::TxDuplicate { duplicate_of } => AE::Duplicate {
+                duplicate_of: Nat::from(duplicate_of),
+            },
+            LTE::SelfApproval => AE::GenericError {
+                error_code: Nat::from(GENERIC_ERROR_CODE),
+                message: "self approval is not allowed".to_string(),
+            },
+            LTE::InsufficientAllowance { .. } => AE::GenericError {
+                error_code: Nat::from(GENERIC_ERROR_CODE),
+                message: format!("unexpected error for an approval: {:?}", err),
+            },
+        }
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct TransferFromArgs {
+    #[serde(default)]
+    pub spender_subaccount: Option<Subaccount>,
+    pub from: Account,
+    pub to: Account,
+    pub amount: NumTokens,
+    #[serde(default)]
+    pub fee: Option<NumTokens>,
+    #[serde(default)]
+    pub memo: Option<Memo>,
+    #[serde(default)]
+    pub created_at_time: Option<u64>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum TransferFromError {
+    BadFee { expected_fee: NumTokens },
+    BadBurn { min_burn_amount: NumTokens },
+    InsufficientFunds { balance: NumTokens },
+    InsufficientAllowance { allowance: NumTokens },
+    TooOld,
+    CreatedInFuture { ledger_time: u64 },
+    Duplicate { duplicate_of: BlockIndex },
+    TemporarilyUnavailable,
+    GenericError { error_code: Nat, message: String },
+}
+
+impl From<CoreTransferError> for TransferFromError {
+    fn from(err: CoreTransferError) -> Self {
+        use ic_ledger_canister_core::ledger::TransferError as LTE;
+        use TransferFromError as TFE;
+
+        match err {
+            LTE::BadFee { expected_fee } => TFE::BadFee {
+                expected_fee: Nat::from(expected_fee.get_e8s()),
+            },
+            LTE::InsufficientFunds { balance } => TFE::InsufficientFunds {
+                balance: Nat::from(balance.get_e8s()),
+            },
+            LTE::InsufficientAllowance { allowance } => TFE::InsufficientAllowance {
+                allowance: Nat::from(allowance.get_e8s()),
+            },
+            LTE::TxTooOld { .. } => TFE::TooOld,
+            LTE::TxCreatedInFuture { ledger_time } => TFE::CreatedInFuture {
+                ledger_time: ledger_time.as_nanos_since_unix_epoch(),
+            },
+            LTE::TxThrottled => TFE::TemporarilyUnavailable,
+            LTE::TxDuplicate { duplicate_of } => TFE::Duplicate {
+                duplicate_of: Nat::from(duplicate_of),
+            },
+            LTE::AllowanceChanged { .. } | LTE::ExpiredApproval { .. } | LTE::SelfApproval => {
+                TFE::GenericError {
+                    error_code: Nat::from(GENERIC_ERROR_CODE),
+                    message: format!("unexpected error for a transfer: {:?}", err),
+                }
+            }
+        }
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct AllowanceArgs {
+    pub account: Account,
+    pub spender: Account,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct Allowance {
+    pub allowance: NumTokens,
+    #[serde(default)]
+    pub expires_at: Option<u64>,
+}
+
 /// Variant type for the `metadata` endpoint values.
 #[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
 pub enum Value {
@@ -187,6 +340,19 @@ pub struct Transfer {
     pub amount: Nat,
     pub from: Account,
     pub to: Account,
+    pub spender: Option<Account>,
+    pub memo: Option<Memo>,
+    pub fee: Option<Nat>,
+    pub created_at_time: Option<u64>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct Approve {
+    pub from: Account,
+    pub spender: Account,
+    pub amount: Nat,
+    pub expected_allowance: Option<Nat>,
+    pub expires_at: Option<u64>,
     pub memo: Option<Memo>,
     pub fee: Option<Nat>,
     pub created_at_time: Option<u64>,
@@ -198,6 +364,7 @@ pub struct Transaction {
     pub mint: Option<Mint>,
     pub burn: Option<Burn>,
     pub transfer: Option<Transfer>,
+    pub approve: Option<Approve>,
     pub timestamp: u64,
 }
 
@@ -276,6 +443,7 @@ impl From<Block> for Transaction {
             mint: None,
             burn: None,
             transfer: None,
+            approve: None,
             timestamp: b.timestamp,
         };
         let created_at_time = b.transaction.created_at_time;
@@ -303,6 +471,7 @@ impl From<Block> for Transaction {
             Operation::Transfer {
                 from,
                 to,
+                spender,
                 amount,
                 fee,
             } => {
@@ -310,7 +479,28 @@ impl From<Block> for Transaction {
                 tx.transfer = Some(Transfer {
                     from,
                     to,
+                    spender,
+                    amount: Nat::from(amount),
+                    fee: Some(Nat::from(fee)),
+                    created_at_time,
+                    memo,
+                });
+            }
+            Operation::Approve {
+                from,
+                spender,
+                amount,
+                expected_allowance,
+                expires_at,
+                fee,
+            } => {
+                tx.kind = "approve".to_string();
+                tx.approve = Some(Approve {
+                    from,
+                    spender,
                     amount: Nat::from(amount),
+                    expected_allowance: expected_allowance.map(Nat::from),
+                    expires_at,
                     fee: Some(Nat::from(fee)),
                     created_at_time,
                     memo,
@@ -4,9 +4,12 @@ pub mod hash;
 use candid::CandidType;
 use ciborium::tag::Required;
 use ic_base_types::PrincipalId;
-use ic_ledger_canister_core::ledger::LedgerTransaction;
+use ic_ledger_canister_core::{
+    approvals::AllowanceTable,
+    ledger::{LedgerTransaction, TxApplyError},
+};
 use ic_ledger_core::{
-    balances::{BalanceError, Balances, BalancesStore},
+    balances::{Balances, BalancesStore},
     block::{BlockType, EncodedBlock, HashOf},
     timestamp::TimeStamp,
     tokens::Tokens,
@@ -99,6 +102,24 @@ where
     Account::try_from(compact_account).map_err(D::Error::custom)
 }
 
+fn ser_opt_compact_account<S>(acc: &Option<Account>, s: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::ser::Serializer,
+{
+    acc.clone().map(CompactAccount::from).serialize(s)
+}
+
+fn de_opt_compact_account<'de, D>(d: D) -> Result<Option<Account>, D::Error>
+where
+    D: serde::de::Deserializer<'de>,
+{
+    use serde::de::Error;
+    Option::<CompactAccount>::deserialize(d)?
+        .map(Account::try_from)
+        .transpose()
+        .map_err(D::Error::custom)
+}
+
 /// A compact representation of an Account.
 ///
 /// Instead of encoding accounts as structs with named fields,
@@ -174,6 +195,13 @@ pub enum Operation {
         #[serde(serialize_with = "ser_compact_account")]
         #[serde(deserialize_with = "de_compact_account")]
         to: Account,
+        /// The account that spent the allowance of `from`, if the transfer
+        /// was made with `icrc2_transfer_from`.
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        #[serde(serialize_with = "ser_opt_compact_account")]
+        #[serde(deserialize_with = "de_opt_compact_account")]
+        spender: Option<Account>,
         #[serde(rename = "amt")]
         amount: u64,
         fee: u64,
@@ -186,6 +214,24 @@ pub enum Operation {
         #[serde(rename = "amt")]
         amount: u64,
     },
+    #[serde(rename = "approve")]
+    Approve {
+        #[serde(serialize_with = "ser_compact_account")]
+        #[serde(deserialize_with = "de_compact_account")]
+        from: Account,
+        #[serde(serialize_with = "ser_compact_account")]
+        #[serde(deserialize_with = "de_compact_account")]
+        spender: Account,
+        #[serde(rename = "amt")]
+        amount: u64,
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        expected_allowance: Option<u64>,
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        expires_at: Option<u64>,
+        fee: u64,
+    },
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -308,7 +354,12 @@ impl LedgerTransaction for Transaction {
             })
     }
 
-    fn apply<S>(&self, balances: &mut Balances<Self::AccountId, S>) -> Result<(), BalanceError>
+    fn apply<S>(
+        &self,
+        balances: &mut Balances<Self::AccountId, S>,
+        approvals: &mut AllowanceTable<Self::AccountId>,
+        now: TimeStamp,
+    ) -> Result<(), TxApplyError>
     where
         S: Default + BalancesStore<Self::AccountId>,
     {
@@ -316,12 +367,59 @@ impl LedgerTransaction for Transaction {
             Operation::Transfer {
                 from,
                 to,
+                spender,
                 amount,
                 fee,
-            } => balances.transfer(from, to, Tokens::from_e8s(*amount), Tokens::from_e8s(*fee)),
-            Operation::Burn { from, amount } => balances.burn(from, Tokens::from_e8s(*amount)),
-            Operation::Mint { to, amount } => balances.mint(to, Tokens::from_e8s(*amount)),
+            } => {
+                let amount = Tokens::from_e8s(*amount);
+                let fee = Tokens::from_e8s(*fee);
+                if let Some(spender) = spender {
+                    // The spender must be allowed to spend both the amount and the fee.
+                    let (allowance, _) = approvals.allowance(from, spender, now);
+                    let debit_amount = (amount + fee)
+                        .map_err(|_| TxApplyError::InsufficientAllowance { allowance })?;
+                    if allowance < debit_amount {
+                        return Err(TxApplyError::InsufficientAllowance { allowance });
+                    }
+                    balances.transfer(from, to, amount, fee)?;
+                    approvals
+                        .use_allowance(from, spender, debit_amount, now)
+                        .expect("bug: allowance checked above");
+                } else {
+                    balances.transfer(from, to, amount, fee)?;
+                }
+            }
+            Operation::Burn { from, amount } => balances.burn(from, Tokens::from_e8s(*amount))?,
+            Operation::Mint { to, amount } => balances.mint(to, Tokens::from_e8s(*amount))?,
+            Operation::Approve {
+                from,
+                spender,
+                amount,
+                expected_allowance,
+                expires_at,
+                fee,
+            } => {
+                // Check the balance first so that a failed approval leaves
+                // the allowance table unchanged.
+                let fee = Tokens::from_e8s(*fee);
+                let balance = balances.account_balance(from);
+                if balance < fee {
+                    return Err(TxApplyError::InsufficientFunds { balance });
+                }
+                approvals.approve(
+                    from,
+                    spender,
+                    Tokens::from_e8s(*amount),
+                    expires_at.map(TimeStamp::from_nanos_since_unix_epoch),
+                    now,
+                    expected_allowance.map(Tokens::from_e8s),
+                )?;
+                balances
+                    .burn(from, fee)
+                    .expect("bug: failed to burn the approval fee after checking the balance");
+            }
         }
+        Ok(())
     }
 }
 
@@ -354,7 +452,55 @@ impl Transaction {
             operation: Operation::Transfer {
                 from,
                 to,
+                spender: None,
+                amount: amount.get_e8s(),
+                fee: fee.get_e8s(),
+            },
+            created_at_time: created_at_time.map(|t| t.as_nanos_since_unix_epoch()),
+            memo,
+        }
+    }
+
+    pub fn transfer_from(
+        from: Account,
+        to: Account,
+        spender: Account,
+        amount: Tokens,
+        fee: Tokens,
+        created_at_time: Option<TimeStamp>,
+        memo: Option<Memo>,
+    ) -> Self {
+        Self {
+            operation: Operation::Transfer {
+                from,
+                to,
+                spender: Some(spender),
+                amount: amount.get_e8s(),
+                fee: fee.get_e8s(),
+            },
+            created_at_time: created_at_time.map(|t| t.as_nanos_since_unix_epoch()),
+            memo,
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn approve(
+        from: Account,
+        spender: Account,
+        amount: Tokens,
+        expected_allowance: Option<Tokens>,
+        expires_at: Option<TimeStamp>,
+        fee: Tokens,
+        created_at_time: Option<TimeStamp>,
+        memo: Option<Memo>,
+    ) -> Self {
+        Self {
+            operation: Operation::Approve {
+                from,
+                spender,
                 amount: amount.get_e8s(),
+                expected_allowance: expected_allowance.map(|t| t.get_e8s()),
+                expires_at: expires_at.map(|t| t.as_nanos_since_unix_epoch()),
                 fee: fee.get_e8s(),
             },
             created_at_time: created_at_time.map(|t| t.as_nanos_since_unix_epoch()),
@@ -0,0 +1,312 @@
+use ic_ledger_core::timestamp::TimeStamp;
+use ic_ledger_core::tokens::Tokens;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The amount of tokens a spender is allowed to transfer from an account.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Allowance {
+    pub amount: Tokens,
+    pub expires_at: Option<TimeStamp>,
+    /// The time at which the allowance was last modified. Used to evict the
+    /// oldest allowances when the table is full.
+    pub arrived_at: TimeStamp,
+}
+
+/// An error returned by [AllowanceTable::approve].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApproveError {
+    /// The caller expected a different current allowance.
+    AllowanceChanged { current_allowance: Tokens },
+    /// The approval would expire before or when it is created.
+    ExpiredApproval { now: TimeStamp },
+    /// An account cannot approve itself as a spender.
+    SelfApproval,
+}
+
+/// An error returned by [AllowanceTable::use_allowance] if the spender is
+/// not allowed to transfer the requested amount.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InsufficientAllowance(pub Tokens);
+
+/// The table of allowances granted by account owners to spenders.
+///
+/// Expired allowances are removed lazily: they are never returned to the
+/// callers and are pruned by [AllowanceTable::prune]. If the table grows
+/// beyond its capacity, the allowances modified least recently are evicted
+/// by [AllowanceTable::select_approvals_to_trim].
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct AllowanceTable<AccountId: Ord> {
+    allowances: BTreeMap<(AccountId, AccountId), Allowance>,
+    /// Allowances that have an expiration time, ordered by that time.
+    expiration_queue: BTreeSet<(TimeStamp, (AccountId, AccountId))>,
+    /// All allowances, ordered by the time they were last modified.
+    arrival_queue: BTreeSet<(TimeStamp, (AccountId, AccountId))>,
+}
+
+impl<AccountId: Ord> Default for AllowanceTable<AccountId> {
+    fn default() -> Self {
+        Self {
+            allowances: BTreeMap::new(),
+            expiration_queue: BTreeSet::new(),
+            arrival_queue: BTreeSet::new(),
+        }
+    }
+}
+
+impl<AccountId> AllowanceTable<AccountId>
+where
+    AccountId: Ord + Clone,
+{
+    /// Returns the amount `spender` is allowed to transfer from `account` at
+    /// time `now`, and the expiration time of the allowance.
+    pub fn allowance(
+        &self,
+        account: &AccountId,
+        spender: &AccountId,
+        now: TimeStamp,
+    ) -> (Tokens, Option<TimeStamp>) {
+        match self.allowances.get(&(account.clone(), spender.clone())) {
+            Some(allowance) if !is_expired(allowance, now) => {
+                (allowance.amount, allowance.expires_at)
+            }
+            _ => (Tokens::ZERO, None),
+        }
+    }
+
+    /// Sets the amount `spender` is allowed to transfer from `account`,
+    /// replacing the previous allowance. Setting the amount to zero removes
+    /// the allowance.
+    ///
+    /// If `expected_allowance` is set, the approval only succeeds if the
+    /// current allowance is equal to it.
+    pub fn approve(
+        &mut self,
+        account: &AccountId,
+        spender: &AccountId,
+        amount: Tokens,
+        expires_at: Option<TimeStamp>,
+        now: TimeStamp,
+        expected_allowance: Option<Tokens>,
+    ) -> Result<(), ApproveError> {
+        if account == spender {
+            return Err(ApproveError::SelfApproval);
+        }
+        if let Some(expires_at) = expires_at {
+            if expires_at <= now {
+                return Err(ApproveError::ExpiredApproval { now });
+            }
+        }
+        if let Some(expected_allowance) = expected_allowance {
+            let (current_allowance, _) = self.allowance(account, spender, now);
+            if current_allowance != expected_allowance {
+                return Err(ApproveError::AllowanceChanged { current_allowance });
+            }
+        }
+
+        let key = (account.clone(), spender.clone());
+        self.remove(&key);
+        if amount == Tokens::ZERO {
+            return Ok(());
+        }
+        if let Some(expires_at) = expires_at {
+            self.expiration_queue.insert((expires_at, key.clone()));
+        }
+        self.arrival_queue.insert((now, key.clone()));
+        self.allowances.insert(
+            key,
+            Allowance {
+                amount,
+                expires_at,
+                arrived_at: now,
+            },
+        );
+        Ok(())
+    }
+
+    /// Decreases the allowance of `spender` on `account` by `amount`.
+    /// Returns the remaining allowance.
+    pub fn use_allowance(
+        &mut self,
+        account: &AccountId,
+        spender: &AccountId,
+        amount: Tokens,
+        now: TimeStamp,
+    ) -> Result<Tokens, InsufficientAllowance> {
+        let key = (account.clone(), spender.clone());
+        let allowance = match self.allowances.get_mut(&key) {
+            Some(allowance) if !is_expired(allowance, now) => allowance,
+            _ => return Err(InsufficientAllowance(Tokens::ZERO)),
+        };
+        let remaining =
+            (allowance.amount - amount).map_err(|_| InsufficientAllowance(allowance.amount))?;
+        allowance.amount = remaining;
+        if remaining == Tokens::ZERO {
+            self.remove(&key);
+        }
+        Ok(remaining)
+    }
+
+    /// Removes at most `limit` allowances that expired at or before `now` and
+    /// returns the number of removed allowances.
+    pub fn prune(&mut self, now: TimeStamp, limit: usize) -> usize {
+        let mut num_pruned = 0;
+        while num_pruned < limit {
+            let key = match self.expiration_queue.iter().next() {
+                Some((expires_at, key)) if *expires_at <= now => key.clone(),
+                _ => break,
+            };
+            self.remove(&key);
+            num_pruned += 1;
+        }
+        num_pruned
+    }
+
+    /// Returns the `num_approvals` allowances that were modified least
+    /// recently.
+    pub fn select_approvals_to_trim(&self, num_approvals: usize) -> Vec<(AccountId, AccountId)> {
+        self.arrival_queue
+            .iter()
+            .take(num_approvals)
+            .map(|(_, key)| key.clone())
+            .collect()
+    }
+
+    /// Removes the allowance `spender` has on `account`.
+    pub fn remove_approval(&mut self, account: &AccountId, spender: &AccountId) {
+        self.remove(&(account.clone(), spender.clone()));
+    }
+
+    /// Returns the number of allowances in the table, including the expired
+    /// allowances that have not been pruned yet.
+    pub fn len(&self) -> usize {
+        self.allowances.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.allowances.is_empty()
+    }
+
+    fn remove(&mut self, key: &(AccountId, AccountId)) {
+        if let Some(allowance) = self.allowances.remove(key) {
+            if let Some(expires_at) = allowance.expires_at {
+                self.expiration_queue.remove(&(expires_at, key.clone()));
+            }
+            self.arrival_queue
+                .remove(&(allowance.arrived_at, key.clone()));
+        }
+    }
+}
+
+fn is_expired(allowance: &Allowance, now: TimeStamp) -> bool {
+    allowance
+        .expires_at
+        .map(|expires_at| expires_at <= now)
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ts(nanos: u64) -> TimeStamp {
+        TimeStamp::from_nanos_since_unix_epoch(nanos)
+    }
+
+    fn tokens(e8s: u64) -> Tokens {
+        Tokens::from_e8s(e8s)
+    }
+
+    #[test]
+    fn approve_and_use_allowance() {
+        let mut table = AllowanceTable::default();
+        table
+            .approve(&1, &2, tokens(100), None, ts(0), None)
+            .unwrap();
+        assert_eq!(table.allowance(&1, &2, ts(0)), (tokens(100), None));
+        assert_eq!(table.allowance(&2, &1, ts(0)), (Tokens::ZERO, None));
+
+        assert_eq!(
+            table.use_allowance(&1, &2, tokens(40), ts(1)),
+            Ok(tokens(60))
+        );
+        assert_eq!(
+            table.use_allowance(&1, &2, tokens(61), ts(1)),
+            Err(InsufficientAllowance(tokens(60)))
+        );
+        assert_eq!(
+            table.use_allowance(&1, &2, tokens(60), ts(1)),
+            Ok(Tokens::ZERO)
+        );
+        assert!(table.is_empty());
+    }
+
+    #[test]
+    fn approve_checks_expected_allowance() {
+        let mut table = AllowanceTable::default();
+        table
+            .approve(&1, &2, tokens(100), None, ts(0), None)
+            .unwrap();
+        assert_eq!(
+            table.approve(&1, &2, tokens(5), None, ts(0), Some(tokens(99))),
+            Err(ApproveError::AllowanceChanged {
+                current_allowance: tokens(100)
+            })
+        );
+        table
+            .approve(&1, &2, tokens(5), None, ts(0), Some(tokens(100)))
+            .unwrap();
+        assert_eq!(table.allowance(&1, &2, ts(0)), (tokens(5), None));
+    }
+
+    #[test]
+    fn approve_rejects_self_approval_and_past_expiration() {
+        let mut table = AllowanceTable::default();
+        assert_eq!(
+            table.approve(&1, &1, tokens(1), None, ts(0), None),
+            Err(ApproveError::SelfApproval)
+        );
+        assert_eq!(
+            table.approve(&1, &2, tokens(1), Some(ts(5)), ts(10), None),
+            Err(ApproveError::ExpiredApproval { now: ts(10) })
+        );
+        assert_eq!(
+            table.approve(&1, &2, tokens(1), Some(ts(10)), ts(10), None),
+            Err(ApproveError::ExpiredApproval { now: ts(10) })
+        );
+        assert!(table.is_empty());
+    }
+
+    #[test]
+    fn expired_allowances_are_ignored_and_pruned() {
+        let mut table = AllowanceTable::default();
+        table
+            .approve(&1, &2, tokens(100), Some(ts(10)), ts(0), None)
+            .unwrap();
+        table
+            .approve(&1, &3, tokens(100), Some(ts(20)), ts(0), None)
+            .unwrap();
+        assert_eq!(table.allowance(&1, &2, ts(9)), (tokens(100), Some(ts(10))));
+        // An allowance is expired from its expiration time on.
+        assert_eq!(table.allowance(&1, &2, ts(10)), (Tokens::ZERO, None));
+        assert_eq!(
+            table.use_allowance(&1, &2, tokens(1), ts(10)),
+            Err(InsufficientAllowance(Tokens::ZERO))
+        );
+
+        assert_eq!(table.prune(ts(10), 10), 1);
+        assert_eq!(table.len(), 1);
+        assert_eq!(table.allowance(&1, &3, ts(10)), (tokens(100), Some(ts(20))));
+    }
+
+    #[test]
+    fn oldest_approvals_are_selected_for_trimming() {
+        let mut table = AllowanceTable::default();
+        table.approve(&1, &2, tokens(1), None, ts(0), None).unwrap();
+        table.approve(&1, &3, tokens(1), None, ts(1), None).unwrap();
+        table.approve(&1, &4, tokens(1), None, ts(2), None).unwrap();
+        // Updating an allowance moves it to the back of the queue.
+        table.approve(&1, &2, tokens(2), None, ts(3), None).unwrap();
+        assert_eq!(table.select_approvals_to_trim(2), vec![(1, 3), (1, 4)]);
+    }
+}
@@ -1,4 +1,10 @@
-use crate::{archive::ArchiveCanisterWasm, blockchain::Blockchain, range_utils, runtime::Runtime};
+use crate::{
+    approvals::{AllowanceTable, ApproveError, InsufficientAllowance},
+    archive::ArchiveCanisterWasm,
+    blockchain::Blockchain,
+    range_utils,
+    runtime::Runtime,
+};
 use ic_base_types::CanisterId;
 use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, HashMap, VecDeque};
@@ -19,8 +25,44 @@ pub struct TransactionInfo<TransactionType> {
     pub transaction_hash: HashOf<TransactionType>,
 }
 
+/// An error returned by [LedgerTransaction::apply].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TxApplyError {
+    InsufficientFunds { balance: Tokens },
+    InsufficientAllowance { allowance: Tokens },
+    AllowanceChanged { current_allowance: Tokens },
+    ExpiredApproval { now: TimeStamp },
+    SelfApproval,
+}
+
+impl From<BalanceError> for TxApplyError {
+    fn from(err: BalanceError) -> Self {
+        match err {
+            BalanceError::InsufficientFunds { balance } => Self::InsufficientFunds { balance },
+        }
+    }
+}
+
+impl From<ApproveError> for TxApplyError {
+    fn from(err: ApproveError) -> Self {
+        match err {
+            ApproveError::AllowanceChanged { current_allowance } => {
+                Self::AllowanceChanged { current_allowance }
+            }
+            ApproveError::ExpiredApproval { now } => Self::ExpiredApproval { now },
+            ApproveError::SelfApproval => Self::SelfApproval,
+        }
+    }
+}
+
+impl From<InsufficientAllowance> for TxApplyError {
+    fn from(InsufficientAllowance(allowance): InsufficientAllowance) -> Self {
+        Self::InsufficientAllowance { allowance }
+    }
+}
+
 pub trait LedgerTransaction: Sized {
-    type AccountId: std::hash::Hash + Eq;
+    type AccountId: std::hash::Hash + Ord + Eq + Clone;
 
     /// Constructs a new "burn" transaction that removes the specified `amount` of tokens from the
     /// `from` account.
@@ -37,8 +79,14 @@ pub trait LedgerTransaction: Sized {
     /// Returns the hash of this transaction.
     fn hash(&self) -> HashOf<Self>;
 
-    /// Applies this transaction to the balance book.
-    fn apply<S>(&self, balances: &mut Balances<Self::AccountId, S>) -> Result<(), BalanceError>
+    /// Applies this transaction to the balance book and the allowance table.
+    /// Leaves both unchanged if the transaction cannot be applied.
+    fn apply<S>(
+        &self,
+        balances: &mut Balances<Self::AccountId, S>,
+        approvals: &mut AllowanceTable<Self::AccountId>,
+        now: TimeStamp,
+    ) -> Result<(), TxApplyError>
     where
         S: Default + BalancesStore<Self::AccountId>;
 }
@@ -87,6 +135,10 @@ pub trait LedgerData {
     /// [LedgerData::max_number_of_accounts].
     fn accounts_overflow_trim_quantity(&self) -> usize;
 
+    /// The maximum size of the allowance table. The allowances modified least
+    /// recently are removed when the table grows beyond this size.
+    fn max_number_of_approvals(&self) -> usize;
+
     // Token configuration
 
     /// Token name (e.g., Bitcoin).
@@ -100,6 +152,9 @@ pub trait LedgerData {
     fn balances(&self) -> &Balances<Self::AccountId, HashMap<Self::AccountId, Tokens>>;
     fn balances_mut(&mut self) -> &mut Balances<Self::AccountId, HashMap<Self::AccountId, Tokens>>;
 
+    fn approvals(&self) -> &AllowanceTable<Self::AccountId>;
+    fn approvals_mut(&mut self) -> &mut AllowanceTable<Self::AccountId>;
+
     fn blockchain(&self) -> &Blockchain<Self::Runtime, Self::ArchiveWasm>;
     fn blockchain_mut(&mut self) -> &mut Blockchain<Self::Runtime, Self::ArchiveWasm>;
 
@@ -121,6 +176,26 @@ pub enum TransferError {
     TxCreatedInFuture { ledger_time: TimeStamp },
     TxThrottled,
     TxDuplicate { duplicate_of: BlockIndex },
+    InsufficientAllowance { allowance: Tokens },
+    AllowanceChanged { current_allowance: Tokens },
+    ExpiredApproval { ledger_time: TimeStamp },
+    SelfApproval,
+}
+
+impl From<TxApplyError> for TransferError {
+    fn from(err: TxApplyError) -> Self {
+        match err {
+            TxApplyError::InsufficientFunds { balance } => Self::InsufficientFunds { balance },
+            TxApplyError::InsufficientAllowance { allowance } => {
+                Self::InsufficientAllowance { allowance }
+            }
+            TxApplyError::AllowanceChanged { current_allowance } => {
+                Self::AllowanceChanged { current_allowance }
+            }
+            TxApplyError::ExpiredApproval { now } => Self::ExpiredApproval { ledger_time: now },
+            TxApplyError::SelfApproval => Self::SelfApproval,
+        }
+    }
 }
 
 /// Adds a new block with the specified transaction to the ledger.
@@ -131,6 +206,9 @@ pub fn apply_transaction<L: LedgerData>(
 ) -> Result<(BlockIndex, HashOf<EncodedBlock>), TransferError> {
     let num_pruned = purge_old_transactions(ledger, now);
 
+    let max_approvals_to_prune = ledger.max_transactions_to_purge();
+    ledger.approvals_mut().prune(now, max_approvals_to_prune);
+
     // If we pruned some transactions, let this one through
     // otherwise throttle if there are too many
     if num_pruned == 0 && throttle(ledger, now) {
@@ -160,13 +238,7 @@ pub fn apply_transaction<L: LedgerData>(
         }
     }
 
-    transaction
-        .apply(ledger.balances_mut())
-        .map_err(|e| match e {
-            BalanceError::InsufficientFunds { balance } => {
-                TransferError::InsufficientFunds { balance }
-            }
-        })?;
+    apply_to_ledger(ledger, &transaction, now)?;
 
     let block = L::Block::from_transaction(ledger.blockchain().last_hash, transaction, now);
     let block_timestamp = block.timestamp();
@@ -200,8 +272,7 @@ pub fn apply_transaction<L: LedgerData>(
     for (balance, account) in to_trim {
         let burn_tx = L::Transaction::burn(account, balance, Some(now), Some(TRIMMED_MEMO));
 
-        burn_tx
-            .apply(ledger.balances_mut())
+        apply_to_ledger(ledger, &burn_tx, now)
             .expect("failed to burn funds that must have existed");
 
         let parent_hash = ledger.blockchain().last_hash;
@@ -212,9 +283,32 @@ pub fn apply_transaction<L: LedgerData>(
             .unwrap();
     }
 
+    let num_approvals = ledger.approvals().len();
+    if num_approvals > ledger.max_number_of_approvals() {
+        let num_to_trim = num_approvals - ledger.max_number_of_approvals();
+        for (account, spender) in ledger.approvals().select_approvals_to_trim(num_to_trim) {
+            ledger.approvals_mut().remove_approval(&account, &spender);
+        }
+    }
+
     Ok((height, ledger.blockchain().last_hash.unwrap()))
 }
 
+/// Applies the transaction to the balances and the allowance table of the
+/// ledger.
+fn apply_to_ledger<L: LedgerData>(
+    ledger: &mut L,
+    transaction: &L::Transaction,
+    now: TimeStamp,
+) -> Result<(), TxApplyError> {
+    // The allowance table is moved out of the ledger for the duration of the
+    // call so that the transaction can borrow it along with the balances.
+    let mut approvals = std::mem::take(ledger.approvals_mut());
+    let result = transaction.apply(ledger.balances_mut(), &mut approvals, now);
+    *ledger.approvals_mut() = approvals;
+    result
+}
+
 /// Finds the archive canister that contains the block with the specified height.
 pub fn find_block_in_archive<L: LedgerData>(ledger: &L, block_height: u64) -> Option<CanisterId> {
     let index = ledger
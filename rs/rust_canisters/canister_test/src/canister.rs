@@ -651,6 +651,7 @@ impl<'a> Canister<'a> {
                         compute_allocation: None,
                         memory_allocation: None,
                         freezing_threshold: None,
+                        log_visibility: None,
                     },
                 },),
             )
@@ -323,6 +323,7 @@ struct CliArgs {
             "bitcoin_regtest",
             "bitcoin_regtest_syncing",
             "bitcoin_regtest_paused",
+            "quic_transport",
         ],
         multiple_values(true))]
     subnet_features: Vec<String>,
@@ -559,6 +560,7 @@ impl CliArgs {
 fn to_subnet_features(features: &[String]) -> SubnetFeatures {
     let canister_sandboxing = features.iter().any(|s| s.as_str() == "canister_sandboxing");
     let http_requests = features.iter().any(|s| s.as_str() == "http_requests");
+    let quic_transport = features.iter().any(|s| s.as_str() == "quic_transport");
     let bitcoin = if features.iter().any(|s| s.as_str() == "bitcoin_testnet") {
         Some(BitcoinFeatureInfo {
             network: BitcoinNetwork::Testnet.into(),
@@ -631,6 +633,7 @@ fn to_subnet_features(features: &[String]) -> SubnetFeatures {
         http_requests,
         bitcoin_testnet_feature: None,
         bitcoin,
+        quic_transport,
     }
 }
 
@@ -3,6 +3,7 @@ use crate::utils::do_copy;
 
 use bitcoin::{hashes::Hash, Network, OutPoint, Script, TxOut, Txid};
 use ic_base_types::{NumBytes, NumSeconds};
+use ic_ic00_types::LogVisibility;
 use ic_logger::{error, ReplicaLogger};
 use ic_protobuf::{
     bitcoin::v1 as pb_bitcoin,
@@ -14,12 +15,13 @@ use ic_protobuf::{
 };
 use ic_replicated_state::{
     bitcoin_state, canister_state::execution_state::WasmMetadata, CallContextManager,
-    CanisterStatus, ExecutionTask, ExportedFunctions, Global, NumWasmPages,
+    CanisterStatus, ExecutionTask, ExportedFunctions, Global, NumWasmPages, SnapshotId,
 };
 use ic_sys::mmap::ScopedMmap;
 use ic_types::{
-    nominal_cycles::NominalCycles, AccumulatedPriority, CanisterId, ComputeAllocation, Cycles,
-    ExecutionRound, Height, MemoryAllocation, NumInstructions, PrincipalId,
+    canister_log::CanisterLog, nominal_cycles::NominalCycles, AccumulatedPriority, CanisterId,
+    ComputeAllocation, Cycles, ExecutionRound, Height, MemoryAllocation, NumInstructions,
+    PrincipalId, Time,
 };
 use ic_utils::fs::sync_path;
 use ic_utils::thread::parallel_map;
@@ -131,6 +133,21 @@ pub struct CanisterStateBits {
     pub time_of_last_allocation_charge_nanos: u64,
     pub global_timer_nanos: Option<u64>,
     pub canister_version: u64,
+    pub next_snapshot_id: u64,
+    pub canister_log: CanisterLog,
+    pub log_visibility: LogVisibility,
+}
+
+/// This struct contains bits of a `CanisterSnapshot` that are not already
+/// covered somewhere else and are too small to be serialized separately.
+#[derive(Debug)]
+pub struct CanisterSnapshotBits {
+    pub snapshot_id: SnapshotId,
+    pub taken_at_timestamp: Time,
+    pub canister_version: u64,
+    pub certified_data: Vec<u8>,
+    pub execution_state_bits: ExecutionStateBits,
+    pub stable_memory_size: NumWasmPages,
 }
 
 /// This struct contains bits of the `BitcoinState` that are not already
@@ -178,12 +195,19 @@ impl Default for BitcoinStateBits {
 /// |   |       └── utxos_small.bin
 /// |   |       └── utxos_medium.bin
 /// |   |       └── address_outpoints.bin
-/// │   └── canister_states
-/// │       └── <hex(canister_id)>
-/// │           ├── queues.pbuf
+/// |   |       └── block_headers.bin
+/// │   ├── canister_states
+/// │   │   └── <hex(canister_id)>
+/// │   │       ├── queues.pbuf
+/// │   │       ├── vmemory_0.bin
+/// │   │       ├── canister.pbuf
+/// │   │       ├── stable_memory.(pbuf|bin)
+/// │   │       └── software.wasm
+/// │   └── snapshots
+/// │       └── <hex(snapshot_id)>
+/// │           ├── snapshot.pbuf
 /// │           ├── vmemory_0.bin
-/// │           ├── canister.pbuf
-/// │           ├── stable_memory.(pbuf|bin)
+/// │           ├── stable_memory.bin
 /// │           └── software.wasm
 /// │
 /// ├── [checkpoints, backups, diverged_checkpoints]
@@ -196,12 +220,19 @@ impl Default for BitcoinStateBits {
 /// |      |       └── utxos_small.bin
 /// |      |       └── utxos_medium.bin
 /// |      |       └── address_outpoints.bin
-/// │      └── canister_states
-/// │          └── <hex(canister_id)>
-/// │              ├── queues.pbuf
+/// |      |       └── block_headers.bin
+/// │      ├── canister_states
+/// │      │   └── <hex(canister_id)>
+/// │      │       ├── queues.pbuf
+/// │      │       ├── vmemory_0.bin
+/// │      │       ├── canister.pbuf
+/// │      │       ├── stable_memory.(pbuf|bin)
+/// │      │       └── software.wasm
+/// │      └── snapshots
+/// │          └── <hex(snapshot_id)>
+/// │              ├── snapshot.pbuf
 /// │              ├── vmemory_0.bin
-/// │              ├── canister.pbuf
-/// │              ├── stable_memory.(pbuf|bin)
+/// │              ├── stable_memory.bin
 /// │              └── software.wasm
 /// │
 /// └── diverged_state_markers
@@ -365,6 +396,27 @@ impl TipHandler {
         }
         Ok(())
     }
+
+    /// Deletes canister snapshots from tip if they are not in ids.
+    pub fn filter_tip_snapshots(
+        &mut self,
+        height: Height,
+        ids: &BTreeSet<&SnapshotId>,
+    ) -> Result<(), LayoutError> {
+        let tip = self.tip(height)?;
+        let snapshots_on_disk = tip.snapshot_ids()?;
+        for id in snapshots_on_disk {
+            if !ids.contains(&id) {
+                let snapshot_path = tip.snapshot(&id)?.raw_path();
+                std::fs::remove_dir_all(&snapshot_path).map_err(|err| LayoutError::IoError {
+                    path: snapshot_path,
+                    message: "Cannot remove canister snapshot.".to_string(),
+                    io_err: err,
+                })?;
+            }
+        }
+        Ok(())
+    }
 }
 
 impl StateLayout {
@@ -1007,6 +1059,33 @@ impl<Permissions: AccessPolicy> CheckpointLayout<Permissions> {
         )
     }
 
+    pub fn snapshot_ids(&self) -> Result<Vec<SnapshotId>, LayoutError> {
+        // Unlike `canister_states`, the `snapshots` directory is only created
+        // once the first snapshot is written, so we do not check it here.
+        let snapshots_dir = self.root.join("snapshots");
+        collect_subdirs(snapshots_dir.as_path(), |p| {
+            let blob = hex::decode(p).unwrap_or_else(|err| {
+                panic!(
+                    "Failed to convert directory name {} into a snapshot id: {}",
+                    p, err
+                )
+            });
+
+            SnapshotId::try_from(&blob[..]).expect("failed to parse snapshot id")
+        })
+    }
+
+    pub fn snapshot(
+        &self,
+        snapshot_id: &SnapshotId,
+    ) -> Result<SnapshotLayout<Permissions>, LayoutError> {
+        SnapshotLayout::new(
+            self.root
+                .join("snapshots")
+                .join(hex::encode(snapshot_id.to_vec())),
+        )
+    }
+
     pub fn bitcoin(&self) -> Result<BitcoinStateLayout<Permissions>, LayoutError> {
         // TODO(EXC-1113): Rename this path to "bitcoin", as it stores data for either network.
         BitcoinStateLayout::new(self.root.join("bitcoin").join("testnet"))
@@ -1062,6 +1141,43 @@ impl<Permissions: AccessPolicy> CanisterLayout<Permissions> {
     }
 }
 
+pub struct SnapshotLayout<Permissions: AccessPolicy> {
+    snapshot_root: PathBuf,
+    permissions_tag: PhantomData<Permissions>,
+}
+
+impl<Permissions: AccessPolicy> SnapshotLayout<Permissions> {
+    pub fn new(snapshot_root: PathBuf) -> Result<Self, LayoutError> {
+        Permissions::check_dir(&snapshot_root)?;
+        Ok(Self {
+            snapshot_root,
+            permissions_tag: PhantomData,
+        })
+    }
+
+    pub fn raw_path(&self) -> PathBuf {
+        self.snapshot_root.clone()
+    }
+
+    pub fn snapshot(
+        &self,
+    ) -> ProtoFileWith<pb_canister_state_bits::CanisterSnapshotBits, Permissions> {
+        self.snapshot_root.join("snapshot.pbuf").into()
+    }
+
+    pub fn wasm(&self) -> WasmFile<Permissions> {
+        self.snapshot_root.join("software.wasm").into()
+    }
+
+    pub fn vmemory_0(&self) -> PathBuf {
+        self.snapshot_root.join("vmemory_0.bin")
+    }
+
+    pub fn stable_memory_blob(&self) -> PathBuf {
+        self.snapshot_root.join("stable_memory.bin")
+    }
+}
+
 pub struct BitcoinStateLayout<Permissions: AccessPolicy> {
     bitcoin_root: PathBuf,
     permissions_tag: PhantomData<Permissions>,
@@ -1095,6 +1211,10 @@ impl<Permissions: AccessPolicy> BitcoinStateLayout<Permissions> {
     pub fn address_outpoints(&self) -> PathBuf {
         self.bitcoin_root.join("address_outpoints.bin")
     }
+
+    pub fn block_headers(&self) -> PathBuf {
+        self.bitcoin_root.join("block_headers.bin")
+    }
 }
 
 fn open_for_write(path: &Path) -> Result<std::fs::File, LayoutError> {
@@ -1324,6 +1444,15 @@ impl From<CanisterStateBits> for pb_canister_state_bits::CanisterStateBits {
             task_queue: item.task_queue.iter().map(|v| v.into()).collect(),
             global_timer_nanos: item.global_timer_nanos,
             canister_version: item.canister_version,
+            next_snapshot_id: item.next_snapshot_id,
+            canister_log_records: item
+                .canister_log
+                .records()
+                .iter()
+                .map(|record| record.into())
+                .collect(),
+            next_canister_log_record_idx: item.canister_log.next_idx(),
+            log_visibility: pb_canister_state_bits::LogVisibility::from(item.log_visibility) as i32,
         }
     }
 }
@@ -1369,6 +1498,16 @@ impl TryFrom<pb_canister_state_bits::CanisterStateBits> for CanisterStateBits {
             .map(|v| v.try_into())
             .collect::<Result<_, _>>()?;
 
+        let log_visibility = pb_canister_state_bits::LogVisibility::from_i32(value.log_visibility)
+            .ok_or(ProxyDecodeError::ValueOutOfRange {
+                typ: "LogVisibility",
+                err: format!(
+                    "Unable to convert {} to a LogVisibility",
+                    value.log_visibility
+                ),
+            })?
+            .into();
+
         Ok(Self {
             controllers,
             last_full_execution_round: value.last_full_execution_round.into(),
@@ -1409,6 +1548,50 @@ impl TryFrom<pb_canister_state_bits::CanisterStateBits> for CanisterStateBits {
             task_queue,
             global_timer_nanos: value.global_timer_nanos,
             canister_version: value.canister_version,
+            next_snapshot_id: value.next_snapshot_id,
+            canister_log: CanisterLog::new(
+                value.next_canister_log_record_idx,
+                value
+                    .canister_log_records
+                    .into_iter()
+                    .map(|record| record.into())
+                    .collect(),
+            ),
+            log_visibility,
+        })
+    }
+}
+
+impl From<CanisterSnapshotBits> for pb_canister_state_bits::CanisterSnapshotBits {
+    fn from(item: CanisterSnapshotBits) -> Self {
+        Self {
+            canister_id: Some(item.snapshot_id.canister_id().into()),
+            local_id: item.snapshot_id.local_id(),
+            taken_at_timestamp_nanos: item.taken_at_timestamp.as_nanos_since_unix_epoch(),
+            canister_version: item.canister_version,
+            certified_data: item.certified_data,
+            execution_state_bits: Some((&item.execution_state_bits).into()),
+            stable_memory_size: item.stable_memory_size.get() as u64,
+        }
+    }
+}
+
+impl TryFrom<pb_canister_state_bits::CanisterSnapshotBits> for CanisterSnapshotBits {
+    type Error = ProxyDecodeError;
+
+    fn try_from(value: pb_canister_state_bits::CanisterSnapshotBits) -> Result<Self, Self::Error> {
+        let canister_id: CanisterId =
+            try_from_option_field(value.canister_id, "CanisterSnapshotBits::canister_id")?;
+        Ok(Self {
+            snapshot_id: SnapshotId::new(canister_id, value.local_id),
+            taken_at_timestamp: Time::from_nanos_since_unix_epoch(value.taken_at_timestamp_nanos),
+            canister_version: value.canister_version,
+            certified_data: value.certified_data,
+            execution_state_bits: try_from_option_field(
+                value.execution_state_bits,
+                "CanisterSnapshotBits::execution_state_bits",
+            )?,
+            stable_memory_size: NumWasmPages::from(value.stable_memory_size as usize),
         })
     }
 }
@@ -1839,6 +2022,9 @@ mod test {
             task_queue: vec![],
             global_timer_nanos: None,
             canister_version: 0,
+            next_snapshot_id: 0,
+            canister_log: CanisterLog::default(),
+            log_visibility: LogVisibility::default(),
         }
     }
 
@@ -1938,4 +2124,36 @@ mod test {
         let canister_state_bits = CanisterStateBits::try_from(pb_bits).unwrap();
         assert_eq!(canister_state_bits.task_queue, task_queue);
     }
+
+    #[test]
+    fn test_encode_decode_canister_snapshot_bits() {
+        let snapshot_id = SnapshotId::new(canister_test_id(3), 5);
+        let snapshot_bits = CanisterSnapshotBits {
+            snapshot_id,
+            taken_at_timestamp: mock_time(),
+            canister_version: 7,
+            certified_data: vec![1, 2, 3],
+            execution_state_bits: ExecutionStateBits {
+                exported_globals: vec![Global::I64(42)],
+                heap_size: NumWasmPages::from(2),
+                exports: ExportedFunctions::new(BTreeSet::new()),
+                last_executed_round: ExecutionRound::from(0),
+                metadata: WasmMetadata::default(),
+                binary_hash: None,
+            },
+            stable_memory_size: NumWasmPages::from(3),
+        };
+
+        let pb_bits = pb_canister_state_bits::CanisterSnapshotBits::from(snapshot_bits);
+        let snapshot_bits = CanisterSnapshotBits::try_from(pb_bits).unwrap();
+        assert_eq!(snapshot_bits.snapshot_id, snapshot_id);
+        assert_eq!(snapshot_bits.taken_at_timestamp, mock_time());
+        assert_eq!(snapshot_bits.canister_version, 7);
+        assert_eq!(snapshot_bits.certified_data, vec![1, 2, 3]);
+        assert_eq!(
+            snapshot_bits.execution_state_bits.heap_size,
+            NumWasmPages::from(2)
+        );
+        assert_eq!(snapshot_bits.stable_memory_size, NumWasmPages::from(3));
+    }
 }
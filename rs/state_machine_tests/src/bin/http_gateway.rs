@@ -0,0 +1,48 @@
+//! Runs a fresh state machine behind the public HTTP API, so that test suites
+//! written in other languages can use it as a lightweight local replica.
+//!
+//! Usage:
+//!
+//! http-gateway --listen-addr 127.0.0.1:8080 --port-file /tmp/port
+//!
+//! The root key used to verify certificates is returned by `/api/v2/status`.
+
+use clap::Parser;
+use ic_state_machine_tests::{HttpGateway, StateMachine};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+#[derive(Parser)]
+#[clap(version = "0.1.0", author = "DFINITY team <team@dfinity.org>")]
+struct Opts {
+    #[clap(
+        long,
+        help = "The address to listen on. Use port 0 to pick a free port.",
+        default_value = "127.0.0.1:8080"
+    )]
+    listen_addr: SocketAddr,
+
+    #[clap(
+        long,
+        help = "A file to write the port to once the gateway accepts connections."
+    )]
+    port_file: Option<PathBuf>,
+}
+
+fn main() -> std::io::Result<()> {
+    let opts = Opts::parse();
+
+    let env = StateMachine::new();
+    env.set_time(SystemTime::now());
+    let gateway = HttpGateway::start(env, opts.listen_addr)?;
+    let addr = gateway.local_addr();
+    println!("Listening on http://{}", addr);
+    if let Some(port_file) = opts.port_file {
+        std::fs::write(port_file, addr.port().to_string())?;
+    }
+
+    loop {
+        std::thread::park();
+    }
+}
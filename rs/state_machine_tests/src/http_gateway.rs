@@ -0,0 +1,588 @@
+//! An HTTP server that exposes the public HTTP API of the Internet Computer
+//! (`/api/v2/status`, `/call`, `/query` and `/read_state`) on top of a
+//! [StateMachine]. It lets agents written in any language, e.g. the ones of
+//! JS frontends, use a state machine as a lightweight local replica.
+
+use crate::{has_pending_messages, StateMachine};
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use ic_crypto_tree_hash::{sparse_labeled_tree_from_paths, Label, Path};
+use ic_crypto_utils_threshold_sig_der::public_key_to_der;
+use ic_error_types::{ErrorCode, RejectCode};
+use ic_http_endpoints_public::{
+    cbor_response, get_cors_headers, into_cbor, make_plaintext_response,
+    validation_error_to_http_error, verify_read_state_paths, HttpError, IC_API_VERSION,
+};
+use ic_interfaces::crypto::IngressSigVerifier;
+use ic_interfaces_registry::RegistryClient;
+use ic_interfaces_state_manager::StateReader;
+use ic_logger::{replica_logger::no_op_logger, ReplicaLogger};
+use ic_types::{
+    ingress::WasmResult,
+    malicious_flags::MaliciousFlags,
+    messages::{
+        Blob, Certificate, HttpQueryContent, HttpQueryResponse, HttpQueryResponseReply,
+        HttpReadStateContent, HttpReadStateResponse, HttpRequest, HttpRequestEnvelope,
+        HttpStatusResponse, ReadState, ReplicaHealthStatus, SignedIngress, SignedRequestBytes,
+        UserQuery,
+    },
+};
+use ic_validator::{get_authorized_canisters, validate_request, CanisterIdSet};
+use std::convert::{Infallible, TryFrom, TryInto};
+use std::net::{SocketAddr, TcpListener};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::{runtime::Runtime, sync::oneshot, task::JoinHandle};
+
+/// How often the gateway checks whether the state machine needs to execute a
+/// round, e.g. to deliver responses to inter-canister calls.
+const TICK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The maximum time between two rounds when there are no messages in flight,
+/// so that heartbeats and timers keep running as on a replica.
+const IDLE_ROUND_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long stopping the gateway waits for requests that are in progress.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Serves the public HTTP API over a [StateMachine].
+///
+/// Requests are validated like on a replica, including the signatures of
+/// non-anonymous senders. Certificates are signed with the root key of the
+/// state machine, which is returned by `/api/v2/status`, so agents have to
+/// fetch the root key before verifying responses.
+///
+/// Updates are executed in a round of their own as soon as they are received.
+/// While there are messages in flight, the gateway keeps executing rounds in
+/// the background; the time of the state machine follows the system time.
+pub struct HttpGateway {
+    local_addr: SocketAddr,
+    gateway: Option<Arc<Gateway>>,
+    runtime: Option<Runtime>,
+    shutdown: Option<oneshot::Sender<()>>,
+    server: Option<JoinHandle<Result<(), hyper::Error>>>,
+}
+
+impl HttpGateway {
+    /// Starts serving the API of `env` on `addr`. Use port 0 to listen on
+    /// a free port, which is then returned by [HttpGateway::local_addr].
+    pub fn start(env: StateMachine, addr: SocketAddr) -> std::io::Result<Self> {
+        let verifier = Arc::new(ic_crypto_for_verification_only::new(Arc::clone(
+            &env.registry_client,
+        )
+            as Arc<dyn RegistryClient>));
+        let gateway = Arc::new(Gateway {
+            env: Mutex::new(env),
+            verifier,
+            log: no_op_logger(),
+        });
+
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()?;
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let local_addr = listener.local_addr()?;
+
+        let (shutdown, shutdown_rx) = oneshot::channel();
+        let server = {
+            let _guard = runtime.enter();
+            let gateway = Arc::clone(&gateway);
+            Server::from_tcp(listener)
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?
+                .serve(make_service_fn(move |_conn| {
+                    let gateway = Arc::clone(&gateway);
+                    async move {
+                        Ok::<_, Infallible>(service_fn(move |request| {
+                            Arc::clone(&gateway).handle(request)
+                        }))
+                    }
+                }))
+                .with_graceful_shutdown(async {
+                    shutdown_rx.await.ok();
+                })
+        };
+        let server = runtime.spawn(server);
+
+        let ticker = Arc::clone(&gateway);
+        runtime.spawn(async move {
+            let mut interval = tokio::time::interval(TICK_INTERVAL);
+            let mut last_round = Instant::now();
+            loop {
+                interval.tick().await;
+                let ticker = Arc::clone(&ticker);
+                let idle_for = last_round.elapsed();
+                let executed = tokio::task::spawn_blocking(move || ticker.tick(idle_for))
+                    .await
+                    .expect("Executing a round failed");
+                if executed {
+                    last_round = Instant::now();
+                }
+            }
+        });
+
+        Ok(Self {
+            local_addr,
+            gateway: Some(gateway),
+            runtime: Some(runtime),
+            shutdown: Some(shutdown),
+            server: Some(server),
+        })
+    }
+
+    /// Returns the address the gateway listens on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Runs `f` with exclusive access to the state machine, e.g. to install
+    /// canisters before a test suite starts.
+    pub fn with_state_machine<R>(&self, f: impl FnOnce(&StateMachine) -> R) -> R {
+        let gateway = self.gateway.as_ref().expect("The gateway has been stopped");
+        f(&gateway.env.lock().unwrap())
+    }
+
+    /// Stops serving requests and returns the state machine.
+    pub fn stop(mut self) -> StateMachine {
+        self.shut_down();
+        let gateway = self.gateway.take().expect("The gateway has been stopped");
+        match Arc::try_unwrap(gateway) {
+            Ok(gateway) => gateway.env.into_inner().unwrap(),
+            Err(_) => panic!("The state machine is still used by a request handler"),
+        }
+    }
+
+    fn shut_down(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            shutdown.send(()).ok();
+        }
+        if let Some(runtime) = self.runtime.take() {
+            if let Some(server) = self.server.take() {
+                runtime.block_on(server).ok();
+            }
+            runtime.shutdown_timeout(SHUTDOWN_TIMEOUT);
+        }
+    }
+}
+
+impl Drop for HttpGateway {
+    fn drop(&mut self) {
+        self.shut_down();
+    }
+}
+
+/// The state shared by the request handlers of an [HttpGateway].
+struct Gateway {
+    env: Mutex<StateMachine>,
+    verifier: Arc<dyn IngressSigVerifier + Send + Sync>,
+    log: ReplicaLogger,
+}
+
+impl Gateway {
+    async fn handle(self: Arc<Self>, request: Request<Body>) -> Result<Response<Body>, Infallible> {
+        let method = request.method().clone();
+        let path = request.uri().path().to_string();
+        let body = match hyper::body::to_bytes(request.into_body()).await {
+            Ok(body) => body.to_vec(),
+            Err(err) => {
+                return Ok(make_plaintext_response(
+                    StatusCode::BAD_REQUEST,
+                    format!("Failed to read the request body: {}", err),
+                ))
+            }
+        };
+        // Executing rounds and queries blocks, so the handlers must not run on
+        // the threads that serve connections.
+        let response = tokio::task::spawn_blocking(move || {
+            let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+            match (method, segments.as_slice()) {
+                (Method::GET, ["api", "v2", "status"]) => self.status(),
+                (Method::POST, ["api", "v2", "canister", _, "call"]) => self.call(body),
+                (Method::POST, ["api", "v2", "canister", _, "query"]) => self.query(body),
+                (Method::POST, ["api", "v2", "canister", _, "read_state"]) => self.read_state(body),
+                _ => make_plaintext_response(
+                    StatusCode::NOT_FOUND,
+                    format!("Unsupported endpoint {}", path),
+                ),
+            }
+        })
+        .await
+        .unwrap_or_else(|err| {
+            make_plaintext_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Internal error: {}", err),
+            )
+        });
+        Ok(response)
+    }
+
+    /// Executes a round if there are messages in flight or if the state
+    /// machine has been idle for a while. Returns `true` if it did.
+    fn tick(&self, idle_for: Duration) -> bool {
+        let env = self.env.lock().unwrap();
+        let state = env.state_manager.get_latest_state().take();
+        if !has_pending_messages(&state) && idle_for < IDLE_ROUND_INTERVAL {
+            return false;
+        }
+        sync_time(&env);
+        env.tick();
+        true
+    }
+
+    fn status(&self) -> Response<Body> {
+        let root_key = self.env.lock().unwrap().root_key();
+        cbor_response(&HttpStatusResponse {
+            ic_api_version: IC_API_VERSION.to_string(),
+            root_key: public_key_to_der(&root_key.into_bytes()).ok().map(Blob),
+            impl_version: None,
+            impl_hash: None,
+            replica_health_status: Some(ReplicaHealthStatus::Healthy),
+        })
+    }
+
+    fn call(&self, body: Vec<u8>) -> Response<Body> {
+        let msg: SignedIngress = match SignedRequestBytes::from(body).try_into() {
+            Ok(msg) => msg,
+            Err(e) => {
+                return make_plaintext_response(
+                    StatusCode::BAD_REQUEST,
+                    format!("Could not parse body as call message: {}", e),
+                )
+            }
+        };
+
+        let env = self.env.lock().unwrap();
+        sync_time(&env);
+        if let Err(err) = validate_request(
+            msg.as_ref(),
+            self.verifier.as_ref(),
+            env.time.get(),
+            env.registry_client.get_latest_version(),
+            &MaliciousFlags::default(),
+        ) {
+            let HttpError { status, message } =
+                validation_error_to_http_error(msg.id(), err, &self.log);
+            return make_plaintext_response(status, message);
+        }
+        env.send_signed_ingress(msg);
+
+        let mut response = Response::new(Body::from(""));
+        *response.status_mut() = StatusCode::ACCEPTED;
+        *response.headers_mut() = get_cors_headers();
+        response
+    }
+
+    fn query(&self, body: Vec<u8>) -> Response<Body> {
+        let request = match <HttpRequestEnvelope<HttpQueryContent>>::try_from(
+            &SignedRequestBytes::from(body),
+        ) {
+            Ok(request) => request,
+            Err(e) => {
+                return make_plaintext_response(
+                    StatusCode::BAD_REQUEST,
+                    format!("Could not parse body as read request: {}", e),
+                )
+            }
+        };
+        let request = match HttpRequest::<UserQuery>::try_from(request) {
+            Ok(request) => request,
+            Err(e) => {
+                return make_plaintext_response(
+                    StatusCode::BAD_REQUEST,
+                    format!("Malformed request: {:?}", e),
+                )
+            }
+        };
+
+        let env = self.env.lock().unwrap();
+        match self.authorized_canisters(&env, &request) {
+            Ok(targets) if targets.contains(&request.content().receiver) => (),
+            Ok(_) => return make_plaintext_response(StatusCode::FORBIDDEN, "".to_string()),
+            Err(HttpError { status, message }) => return make_plaintext_response(status, message),
+        }
+
+        let response = match env.execute_query(request.take_content()) {
+            Ok(WasmResult::Reply(vec)) => HttpQueryResponse::Replied {
+                reply: HttpQueryResponseReply { arg: Blob(vec) },
+            },
+            Ok(WasmResult::Reject(message)) => HttpQueryResponse::Rejected {
+                error_code: ErrorCode::CanisterRejectedMessage.to_string(),
+                reject_code: RejectCode::CanisterReject as u64,
+                reject_message: message,
+            },
+            Err(user_error) => HttpQueryResponse::Rejected {
+                error_code: user_error.code().to_string(),
+                reject_code: user_error.reject_code() as u64,
+                reject_message: user_error.to_string(),
+            },
+        };
+        cbor_response(&response)
+    }
+
+    fn read_state(&self, body: Vec<u8>) -> Response<Body> {
+        let request = match <HttpRequestEnvelope<HttpReadStateContent>>::try_from(
+            &SignedRequestBytes::from(body),
+        ) {
+            Ok(request) => request,
+            Err(e) => {
+                return make_plaintext_response(
+                    StatusCode::BAD_REQUEST,
+                    format!("Could not parse body as read request: {}", e),
+                )
+            }
+        };
+        let request = match HttpRequest::<ReadState>::try_from(request) {
+            Ok(request) => request,
+            Err(e) => {
+                return make_plaintext_response(
+                    StatusCode::BAD_REQUEST,
+                    format!("Malformed request: {:?}", e),
+                )
+            }
+        };
+
+        let env = self.env.lock().unwrap();
+        let targets = match self.authorized_canisters(&env, &request) {
+            Ok(targets) => targets,
+            Err(HttpError { status, message }) => return make_plaintext_response(status, message),
+        };
+        env.certify_latest_state();
+        let read_state = request.content();
+        let state = env.state_manager.get_latest_state().take();
+        if let Err(HttpError { status, message }) =
+            verify_read_state_paths(&state, &read_state.source, &read_state.paths, &targets)
+        {
+            return make_plaintext_response(status, message);
+        }
+
+        // Always add "time" to the paths even if not explicitly requested.
+        let mut paths: Vec<Path> = read_state.paths.clone();
+        paths.push(Path::from(Label::from("time")));
+        let labeled_tree = sparse_labeled_tree_from_paths(&mut paths);
+        match env.state_manager.read_certified_state(&labeled_tree) {
+            Some((_state, tree, certification)) => cbor_response(&HttpReadStateResponse {
+                certificate: Blob(into_cbor(&Certificate {
+                    tree,
+                    signature: Blob(certification.signed.signature.signature.get().0),
+                    delegation: None,
+                })),
+            }),
+            None => make_plaintext_response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Certified state is not available yet. Please try again...".to_string(),
+            ),
+        }
+    }
+
+    fn authorized_canisters<C: ic_types::messages::HttpRequestContent>(
+        &self,
+        env: &StateMachine,
+        request: &HttpRequest<C>,
+    ) -> Result<CanisterIdSet, HttpError> {
+        sync_time(env);
+        get_authorized_canisters(
+            request,
+            self.verifier.as_ref(),
+            env.time.get(),
+            env.registry_client.get_latest_version(),
+            &MaliciousFlags::default(),
+        )
+        .map_err(|err| validation_error_to_http_error(request.id(), err, &self.log))
+    }
+}
+
+/// Moves the time of the state machine forward to the system time, so that
+/// the ingress expiry set by agents is valid.
+fn sync_time(env: &StateMachine) {
+    let now = SystemTime::now();
+    if now > env.time() {
+        env.set_time(now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CanisterId;
+    use ic_crypto_tree_hash::{lookup_path, LabeledTree};
+    use ic_types::messages::{HttpCallContent, HttpCanisterUpdate, HttpReadState, HttpUserQuery};
+    use ic_types::time::current_time_and_expiry_time;
+
+    const ANONYMOUS_SENDER: u8 = 0x04;
+
+    // A canister that replies to `hello` with "world".
+    const HELLO_WAT: &str = r#"
+        (module
+          (import "ic0" "msg_reply" (func $msg_reply))
+          (import "ic0" "msg_reply_data_append"
+            (func $msg_reply_data_append (param i32 i32)))
+          (func $hello
+            (call $msg_reply_data_append (i32.const 0) (i32.const 5))
+            (call $msg_reply))
+          (memory $memory 1)
+          (data (i32.const 0) "world")
+          (export "canister_query hello" (func $hello))
+          (export "canister_update hello" (func $hello)))"#;
+
+    fn post<T: serde::Serialize>(
+        runtime: &Runtime,
+        gateway: &HttpGateway,
+        path: &str,
+        body: &T,
+    ) -> (StatusCode, Vec<u8>) {
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(format!("http://{}{}", gateway.local_addr(), path))
+            .header(hyper::header::CONTENT_TYPE, "application/cbor")
+            .body(Body::from(into_cbor(body)))
+            .unwrap();
+        send(runtime, request)
+    }
+
+    fn send(runtime: &Runtime, request: Request<Body>) -> (StatusCode, Vec<u8>) {
+        runtime.block_on(async {
+            let response = hyper::Client::new().request(request).await.unwrap();
+            let status = response.status();
+            let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+            (status, body.to_vec())
+        })
+    }
+
+    fn expiry() -> u64 {
+        current_time_and_expiry_time().1.as_nanos_since_unix_epoch()
+    }
+
+    fn start_gateway() -> (HttpGateway, CanisterId) {
+        let env = StateMachine::new();
+        env.set_time(SystemTime::now());
+        let canister_id = env.install_canister_wat(HELLO_WAT, vec![], None);
+        let gateway = HttpGateway::start(env, "127.0.0.1:0".parse().unwrap()).unwrap();
+        (gateway, canister_id)
+    }
+
+    #[test]
+    fn status_returns_root_key() {
+        let runtime = Runtime::new().unwrap();
+        let (gateway, _canister_id) = start_gateway();
+        let request = Request::builder()
+            .uri(format!("http://{}/api/v2/status", gateway.local_addr()))
+            .body(Body::empty())
+            .unwrap();
+        let (status, body) = send(&runtime, request);
+        assert_eq!(status, StatusCode::OK);
+
+        let response: HttpStatusResponse = serde_cbor::from_slice(&body).unwrap();
+        let root_key = gateway.with_state_machine(|env| env.root_key());
+        assert_eq!(
+            response.root_key,
+            Some(Blob(public_key_to_der(&root_key.into_bytes()).unwrap()))
+        );
+        assert_eq!(
+            response.replica_health_status,
+            Some(ReplicaHealthStatus::Healthy)
+        );
+    }
+
+    #[test]
+    fn query_is_answered() {
+        let runtime = Runtime::new().unwrap();
+        let (gateway, canister_id) = start_gateway();
+        let envelope = HttpRequestEnvelope {
+            content: HttpQueryContent::Query {
+                query: HttpUserQuery {
+                    canister_id: Blob(canister_id.get().to_vec()),
+                    method_name: "hello".to_string(),
+                    arg: Blob(vec![]),
+                    sender: Blob(vec![ANONYMOUS_SENDER]),
+                    nonce: None,
+                    ingress_expiry: expiry(),
+                },
+            },
+            sender_pubkey: None,
+            sender_sig: None,
+            sender_delegation: None,
+        };
+        let (status, body) = post(
+            &runtime,
+            &gateway,
+            &format!("/api/v2/canister/{}/query", canister_id),
+            &envelope,
+        );
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            serde_cbor::from_slice::<HttpQueryResponse>(&body).unwrap(),
+            HttpQueryResponse::Replied {
+                reply: HttpQueryResponseReply {
+                    arg: Blob(b"world".to_vec())
+                }
+            }
+        );
+    }
+
+    #[test]
+    fn call_is_executed_and_status_can_be_read() {
+        let runtime = Runtime::new().unwrap();
+        let (gateway, canister_id) = start_gateway();
+        let envelope = HttpRequestEnvelope {
+            content: HttpCallContent::Call {
+                update: HttpCanisterUpdate {
+                    canister_id: Blob(canister_id.get().to_vec()),
+                    method_name: "hello".to_string(),
+                    arg: Blob(vec![]),
+                    sender: Blob(vec![ANONYMOUS_SENDER]),
+                    nonce: None,
+                    ingress_expiry: expiry(),
+                },
+            },
+            sender_pubkey: None,
+            sender_sig: None,
+            sender_delegation: None,
+        };
+        let message_id = SignedIngress::try_from(envelope.clone()).unwrap().id();
+        let (status, _body) = post(
+            &runtime,
+            &gateway,
+            &format!("/api/v2/canister/{}/call", canister_id),
+            &envelope,
+        );
+        assert_eq!(status, StatusCode::ACCEPTED);
+
+        let envelope = HttpRequestEnvelope {
+            content: HttpReadStateContent::ReadState {
+                read_state: HttpReadState {
+                    sender: Blob(vec![ANONYMOUS_SENDER]),
+                    paths: vec![Path::new(vec![
+                        Label::from("request_status"),
+                        Label::from(message_id.as_bytes().to_vec()),
+                        Label::from("reply"),
+                    ])],
+                    nonce: None,
+                    ingress_expiry: expiry(),
+                },
+            },
+            sender_pubkey: None,
+            sender_sig: None,
+            sender_delegation: None,
+        };
+        let (status, body) = post(
+            &runtime,
+            &gateway,
+            &format!("/api/v2/canister/{}/read_state", canister_id),
+            &envelope,
+        );
+        assert_eq!(status, StatusCode::OK);
+        let response: HttpReadStateResponse = serde_cbor::from_slice(&body).unwrap();
+        let certificate: Certificate = serde_cbor::from_slice(&response.certificate.0).unwrap();
+        let tree: LabeledTree<Vec<u8>> = certificate.tree.try_into().unwrap();
+        let path: [&[u8]; 3] = [b"request_status", message_id.as_bytes(), b"reply"];
+        assert_eq!(
+            lookup_path(&tree, &path),
+            Some(&LabeledTree::Leaf(b"world".to_vec()))
+        );
+
+        let env = gateway.stop();
+        assert!(env.canister_exists(canister_id));
+    }
+}
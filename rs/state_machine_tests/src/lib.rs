@@ -1,3 +1,7 @@
+mod http_gateway;
+
+pub use http_gateway::HttpGateway;
+use ic_btc_types_internal::BitcoinAdapterResponse;
 use ic_config::flag_status::FlagStatus;
 use ic_config::{
     execution_environment::Config as HypervisorConfig,
@@ -9,14 +13,21 @@ use ic_crypto_internal_threshold_sig_bls12381::api::{
     combine_signatures, combined_public_key, keygen, sign_message,
 };
 use ic_crypto_internal_threshold_sig_bls12381::types::SecretKeyBytes;
+use ic_crypto_internal_threshold_sig_ecdsa::{
+    DerivationPath, EccCurveType, EccPoint, EccScalar, ThresholdEcdsaResult,
+};
 use ic_crypto_internal_types::sign::threshold_sig::public_key::CspThresholdSigPublicKey;
 use ic_crypto_tree_hash::{flatmap, Label, LabeledTree, LabeledTree::SubTree};
 use ic_cycles_account_manager::CyclesAccountManager;
-pub use ic_error_types::{ErrorCode, UserError};
+pub use ic_error_types::{ErrorCode, RejectCode, UserError};
 use ic_execution_environment::ExecutionServices;
-use ic_ic00_types::{self as ic00, CanisterIdRecord, InstallCodeArgs, Method, Payload};
+use ic_ic00_types::{
+    self as ic00, CanisterIdRecord, EcdsaCurve, FetchCanisterLogsRequest, InstallCodeArgs, Method,
+    Payload, SignWithECDSAReply,
+};
 pub use ic_ic00_types::{
-    CanisterInstallMode, CanisterSettingsArgs, EcdsaKeyId, UpdateSettingsArgs,
+    CanisterHttpResponsePayload, CanisterInstallMode, CanisterSettingsArgs, EcdsaKeyId, HttpHeader,
+    UpdateSettingsArgs,
 };
 use ic_interfaces::{
     certification::{Verifier, VerifierError},
@@ -24,6 +35,7 @@ use ic_interfaces::{
     messaging::MessageRouting,
     validation::ValidationResult,
 };
+use ic_interfaces_certified_stream_store::{CertifiedStreamStore, EncodeStreamError};
 use ic_interfaces_registry::RegistryClient;
 use ic_interfaces_state_manager::{CertificationScope, StateHashError, StateManager, StateReader};
 use ic_logger::ReplicaLogger;
@@ -35,6 +47,7 @@ use ic_protobuf::registry::{
     provisional_whitelist::v1::ProvisionalWhitelist as PbProvisionalWhitelist,
     routing_table::v1::CanisterMigrations as PbCanisterMigrations,
     routing_table::v1::RoutingTable as PbRoutingTable,
+    subnet::v1::SubnetListRecord,
 };
 use ic_protobuf::types::v1::PrincipalId as PrincipalIdIdProto;
 use ic_protobuf::types::v1::SubnetId as SubnetIdProto;
@@ -42,7 +55,8 @@ use ic_registry_client_fake::FakeRegistryClient;
 use ic_registry_client_helpers::subnet::SubnetListRegistry;
 use ic_registry_keys::{
     make_canister_migrations_record_key, make_ecdsa_signing_subnet_list_key, make_node_record_key,
-    make_provisional_whitelist_record_key, make_routing_table_record_key, ROOT_SUBNET_ID_KEY,
+    make_provisional_whitelist_record_key, make_routing_table_record_key,
+    make_subnet_list_record_key, make_subnet_record_key, ROOT_SUBNET_ID_KEY,
 };
 use ic_registry_proto_data_provider::ProtoRegistryDataProvider;
 use ic_registry_provisional_whitelist::ProvisionalWhitelist;
@@ -51,7 +65,9 @@ use ic_registry_routing_table::{
 };
 use ic_registry_subnet_features::{EcdsaConfig, SubnetFeatures, DEFAULT_ECDSA_MAX_QUEUE_SIZE};
 use ic_registry_subnet_type::SubnetType;
-use ic_replicated_state::metadata_state::subnet_call_context_manager::SignWithEcdsaContext;
+use ic_replicated_state::metadata_state::subnet_call_context_manager::{
+    BitcoinGetSuccessorsContext, BitcoinSendTransactionInternalContext, SignWithEcdsaContext,
+};
 use ic_replicated_state::page_map::Buffer;
 use ic_replicated_state::{
     canister_state::{NumWasmPages, WASM_PAGE_SIZE_IN_BYTES},
@@ -59,20 +75,21 @@ use ic_replicated_state::{
 };
 use ic_state_manager::StateManagerImpl;
 use ic_test_utilities_metrics::{fetch_histogram_stats, fetch_int_counter};
-use ic_test_utilities_registry::{
-    add_subnet_record, insert_initial_dkg_transcript, SubnetRecordBuilder,
-};
+use ic_test_utilities_registry::{insert_initial_dkg_transcript, SubnetRecordBuilder};
+pub use ic_types::canister_log::CanisterLog;
 use ic_types::consensus::certification::CertificationContent;
 use ic_types::crypto::threshold_sig::ni_dkg::{NiDkgId, NiDkgTag, NiDkgTargetSubnet};
 pub use ic_types::crypto::threshold_sig::ThresholdSigPublicKey;
 use ic_types::crypto::{
-    canister_threshold_sig::MasterEcdsaPublicKey, AlgorithmId, CombinedThresholdSig,
-    CombinedThresholdSigOf, Signable, Signed,
+    canister_threshold_sig::{ExtendedDerivationPath, MasterEcdsaPublicKey},
+    AlgorithmId, CombinedThresholdSig, CombinedThresholdSigOf, Signable, Signed,
+};
+use ic_types::messages::{
+    CallbackId, Certificate, Payload as ResponsePayload, RejectContext, Response,
 };
-use ic_types::messages::{CallbackId, Certificate};
 use ic_types::signature::ThresholdSignature;
 use ic_types::{
-    batch::{Batch, BatchPayload, IngressPayload},
+    batch::{Batch, BatchPayload, IngressPayload, SelfValidatingPayload, XNetPayload},
     canister_http::CanisterHttpRequestContext,
     consensus::certification::Certification,
     messages::{
@@ -113,15 +130,20 @@ impl Verifier for FakeVerifier {
 
 const GENESIS: Time = Time::from_nanos_since_unix_epoch(1_620_328_630_000_000_000);
 
-/// Constructs the initial version of the registry containing a subnet with the
-/// specified SUBNET_ID, with the nodes with the specified NODE_IDs.
-fn make_nodes_registry(
-    nns_subnet_id: SubnetId,
+/// Describes a subnet to be added to the initial version of the registry.
+struct SubnetRegistrySpec {
     subnet_id: SubnetId,
     subnet_type: SubnetType,
-    node_ids: &[NodeId],
-    ecdsa_keys: &[EcdsaKeyId],
+    node_ids: Vec<NodeId>,
+    ecdsa_keys: Vec<EcdsaKeyId>,
     features: SubnetFeatures,
+}
+
+/// Constructs the initial version of the registry containing the specified
+/// subnets, in the order given, each with its NODE_IDs.
+fn make_nodes_registry(
+    nns_subnet_id: SubnetId,
+    subnets: &[SubnetRegistrySpec],
 ) -> (Arc<ProtoRegistryDataProvider>, Arc<FakeRegistryClient>) {
     let registry_version = RegistryVersion::from(1);
     let data_provider = Arc::new(ProtoRegistryDataProvider::new());
@@ -141,26 +163,34 @@ fn make_nodes_registry(
 
     // ECDSA subnet_id must be different from nns_subnet_id, otherwise
     // `sign_with_ecdsa` won't be charged.
-    let subnet_id_proto = SubnetIdProto {
-        principal_id: Some(PrincipalIdIdProto {
-            raw: subnet_id.get_ref().to_vec(),
-        }),
-    };
-    for key_id in ecdsa_keys {
-        let id = make_ecdsa_signing_subnet_list_key(key_id);
+    let mut ecdsa_signing_subnets: BTreeMap<&EcdsaKeyId, Vec<SubnetIdProto>> = BTreeMap::new();
+    for subnet in subnets {
+        let subnet_id_proto = SubnetIdProto {
+            principal_id: Some(PrincipalIdIdProto {
+                raw: subnet.subnet_id.get_ref().to_vec(),
+            }),
+        };
+        for key_id in &subnet.ecdsa_keys {
+            ecdsa_signing_subnets
+                .entry(key_id)
+                .or_default()
+                .push(subnet_id_proto.clone());
+        }
+    }
+    for (key_id, subnets) in ecdsa_signing_subnets {
         data_provider
             .add(
-                &id.clone(),
+                &make_ecdsa_signing_subnet_list_key(key_id),
                 registry_version,
-                Some(EcdsaSigningSubnetList {
-                    subnets: vec![subnet_id_proto.clone()],
-                }),
+                Some(EcdsaSigningSubnetList { subnets }),
             )
             .unwrap();
     }
 
     let mut routing_table = RoutingTable::new();
-    routing_table_insert_subnet(&mut routing_table, subnet_id).unwrap();
+    for subnet in subnets {
+        routing_table_insert_subnet(&mut routing_table, subnet.subnet_id).unwrap();
+    }
     let pb_routing_table = PbRoutingTable::from(routing_table);
     data_provider
         .add(
@@ -178,7 +208,7 @@ fn make_nodes_registry(
         )
         .unwrap();
 
-    for node_id in node_ids {
+    for node_id in subnets.iter().flat_map(|subnet| subnet.node_ids.iter()) {
         let node_record = NodeRecord {
             node_operator_id: vec![0],
             xnet: None,
@@ -203,27 +233,60 @@ fn make_nodes_registry(
             .unwrap();
     }
 
-    // Set subnetwork list(needed for filling network_topology.nns_subnet_id)
-    let record = SubnetRecordBuilder::from(node_ids)
-        .with_subnet_type(subnet_type)
-        .with_ecdsa_config(EcdsaConfig {
-            quadruples_to_create_in_advance: 1,
-            key_ids: ecdsa_keys.to_vec(),
-            max_queue_size: Some(DEFAULT_ECDSA_MAX_QUEUE_SIZE),
-            signature_request_timeout_ns: None,
-            idkg_key_rotation_period_ms: None,
-        })
-        .with_features(features.into())
-        .build();
+    for subnet in subnets {
+        let record = SubnetRecordBuilder::from(&subnet.node_ids)
+            .with_subnet_type(subnet.subnet_type)
+            .with_ecdsa_config(EcdsaConfig {
+                quadruples_to_create_in_advance: 1,
+                key_ids: subnet.ecdsa_keys.clone(),
+                max_queue_size: Some(DEFAULT_ECDSA_MAX_QUEUE_SIZE),
+                signature_request_timeout_ns: None,
+                idkg_key_rotation_period_ms: None,
+            })
+            .with_features(subnet.features.into())
+            .build();
+
+        insert_initial_dkg_transcript(
+            registry_version.get(),
+            subnet.subnet_id,
+            &record,
+            &data_provider,
+        );
+        data_provider
+            .add(
+                &make_subnet_record_key(subnet.subnet_id),
+                registry_version,
+                Some(record),
+            )
+            .unwrap();
+    }
 
-    insert_initial_dkg_transcript(registry_version.get(), subnet_id, &record, &data_provider);
-    add_subnet_record(&data_provider, registry_version.get(), subnet_id, record);
+    // Set subnetwork list(needed for filling network_topology.nns_subnet_id)
+    data_provider
+        .add(
+            &make_subnet_list_record_key(),
+            registry_version,
+            Some(SubnetListRecord {
+                subnets: subnets
+                    .iter()
+                    .map(|subnet| subnet.subnet_id.get().into_vec())
+                    .collect(),
+            }),
+        )
+        .unwrap();
 
     let registry_client = Arc::new(FakeRegistryClient::new(Arc::clone(&data_provider) as _));
     registry_client.update_to_latest_version();
     (data_provider, registry_client)
 }
 
+/// Returns `count` node ids, starting at the test node id `first`.
+fn make_node_ids(first: u64, count: usize) -> Vec<NodeId> {
+    (first..first + count as u64)
+        .map(|id| NodeId::from(PrincipalId::new_node_test_id(id)))
+        .collect()
+}
+
 /// Convert an object into CBOR binary.
 fn into_cbor<R: Serialize>(r: &R) -> Vec<u8> {
     let mut ser = serde_cbor::Serializer::new(Vec::new());
@@ -294,6 +357,8 @@ pub struct StateMachineBuilder {
     use_cost_scaling_flag: bool,
     ecdsa_keys: Vec<EcdsaKeyId>,
     features: SubnetFeatures,
+    subnet_id: SubnetId,
+    registry: Option<(Arc<ProtoRegistryDataProvider>, Arc<FakeRegistryClient>)>,
 }
 
 impl StateMachineBuilder {
@@ -309,6 +374,8 @@ impl StateMachineBuilder {
             subnet_size: SMALL_APP_SUBNET_MAX_SIZE,
             ecdsa_keys: Vec::new(),
             features: SubnetFeatures::default(),
+            subnet_id: SubnetId::from(PrincipalId::new_subnet_test_id(2)),
+            registry: None,
         }
     }
 
@@ -366,6 +433,21 @@ impl StateMachineBuilder {
         Self { features, ..self }
     }
 
+    /// Makes the state machine use an existing registry in which the subnet
+    /// with the given id is already registered.
+    fn with_registry(
+        self,
+        subnet_id: SubnetId,
+        registry_data_provider: Arc<ProtoRegistryDataProvider>,
+        registry_client: Arc<FakeRegistryClient>,
+    ) -> Self {
+        Self {
+            subnet_id,
+            registry: Some((registry_data_provider, registry_client)),
+            ..self
+        }
+    }
+
     pub fn build(self) -> StateMachine {
         StateMachine::setup_from_dir(
             self.state_dir,
@@ -378,6 +460,8 @@ impl StateMachineBuilder {
             self.use_cost_scaling_flag,
             self.ecdsa_keys,
             self.features,
+            self.subnet_id,
+            self.registry,
         )
     }
 }
@@ -415,6 +499,8 @@ impl StateMachine {
         use_cost_scaling_flag: bool,
         ecdsa_keys: Vec<EcdsaKeyId>,
         features: SubnetFeatures,
+        subnet_id: SubnetId,
+        registry: Option<(Arc<ProtoRegistryDataProvider>, Arc<FakeRegistryClient>)>,
     ) -> Self {
         use slog::Drain;
 
@@ -430,11 +516,6 @@ impl StateMachine {
         let logger = slog::Logger::root(drain, slog::o!());
         let replica_logger: ReplicaLogger = logger.into();
 
-        let mut node_ids = vec![];
-        for id in 0..subnet_size {
-            let node_id = NodeId::from(PrincipalId::new_node_test_id(id as u64));
-            node_ids.push(node_id);
-        }
         let metrics_registry = MetricsRegistry::new();
 
         let (subnet_config, mut hypervisor_config) = match config {
@@ -445,16 +526,19 @@ impl StateMachine {
             ),
         };
 
-        let nns_subnet_id = SubnetId::from(PrincipalId::new_subnet_test_id(1));
-        let subnet_id = SubnetId::from(PrincipalId::new_subnet_test_id(2));
-        let (registry_data_provider, registry_client) = make_nodes_registry(
-            nns_subnet_id,
-            subnet_id,
-            subnet_type,
-            &node_ids,
-            &ecdsa_keys,
-            features,
-        );
+        let (registry_data_provider, registry_client) = match registry {
+            Some(registry) => registry,
+            None => make_nodes_registry(
+                SubnetId::from(PrincipalId::new_subnet_test_id(1)),
+                &[SubnetRegistrySpec {
+                    subnet_id,
+                    subnet_type,
+                    node_ids: make_node_ids(0, subnet_size),
+                    ecdsa_keys: ecdsa_keys.clone(),
+                    features,
+                }],
+            ),
+        };
 
         let sm_config = ic_config::state_manager::Config::new(state_dir.path().to_path_buf());
 
@@ -534,10 +618,12 @@ impl StateMachine {
         let mut ecdsa_subnet_public_keys = BTreeMap::new();
         for ecdsa_key in ecdsa_keys {
             ecdsa_subnet_public_keys.insert(
-                ecdsa_key,
+                ecdsa_key.clone(),
                 MasterEcdsaPublicKey {
-                    algorithm_id: AlgorithmId::EcdsaSecp256k1,
-                    public_key: b"master_ecdsa_public_key".to_vec(),
+                    algorithm_id: ecdsa_test_algorithm_id(&ecdsa_key),
+                    public_key: EccPoint::mul_by_g(&ecdsa_test_secret_key(&ecdsa_key))
+                        .expect("failed to compute the master ECDSA public key")
+                        .serialize(),
                 },
             );
         }
@@ -637,11 +723,7 @@ impl StateMachine {
         let mut reached_completion = false;
         for _tick in 0..max_ticks {
             let state = self.state_manager.get_latest_state().take();
-            reached_completion = !state
-                .canisters_iter()
-                .any(|canister| canister.has_input() || canister.has_output())
-                && !state.subnet_queues().has_input()
-                && !state.subnet_queues().has_output();
+            reached_completion = !has_pending_messages(&state);
             if reached_completion {
                 break;
             }
@@ -656,6 +738,29 @@ impl StateMachine {
     }
 
     fn execute_block_with_ingress_payload(&self, ingress: IngressPayload) {
+        self.execute_block(
+            BatchPayload {
+                ingress,
+                ..BatchPayload::default()
+            },
+            vec![],
+        )
+    }
+
+    /// Executes a single round with the inputs collected by the given
+    /// [PayloadBuilder], e.g. responses to pending ECDSA signing, canister
+    /// HTTP and Bitcoin adapter requests.
+    pub fn execute_payload(&self, payload: PayloadBuilder) {
+        self.execute_block(
+            BatchPayload {
+                self_validating: SelfValidatingPayload::new(payload.bitcoin_adapter_responses),
+                ..BatchPayload::default()
+            },
+            payload.consensus_responses,
+        )
+    }
+
+    fn execute_block(&self, payload: BatchPayload, consensus_responses: Vec<Response>) {
         let batch_number = self.message_routing.expected_batch_height();
 
         let mut seed = [0u8; 32];
@@ -665,15 +770,12 @@ impl StateMachine {
         let batch = Batch {
             batch_number,
             requires_full_state_hash: self.checkpoints_enabled.get(),
-            payload: BatchPayload {
-                ingress,
-                ..BatchPayload::default()
-            },
+            payload,
             randomness: Randomness::from(seed),
             ecdsa_subnet_public_keys: self.ecdsa_subnet_public_keys.clone(),
             registry_version: self.registry_client.get_latest_version(),
             time: self.time.get(),
-            consensus_responses: vec![],
+            consensus_responses,
         };
         self.message_routing
             .deliver_batch(batch)
@@ -835,6 +937,9 @@ impl StateMachine {
     /// After you import the canister, you can execute methods on it and upgrade it.
     /// The original directory is not modified.
     ///
+    /// The `canister` directory of a bundle created with
+    /// `state_tool export_canister` can be imported this way.
+    ///
     /// The function is currently not used in code, but it is useful for local
     /// testing and debugging. Do not remove it.
     ///
@@ -1110,17 +1215,25 @@ impl StateMachine {
         method: impl ToString,
         method_payload: Vec<u8>,
     ) -> Result<WasmResult, UserError> {
-        if self.state_manager.latest_state_height() > self.state_manager.latest_certified_height() {
-            let state_hashes = self.state_manager.list_state_hashes_to_certify();
-            let (height, hash) = state_hashes.last().unwrap();
-            self.state_manager
-                .deliver_state_certification(self.certify_hash(height, hash));
-        }
+        self.execute_query(UserQuery {
+            receiver,
+            source: UserId::from(sender),
+            method_name: method.to_string(),
+            method_payload,
+            ingress_expiry: 0,
+            nonce: None,
+        })
+    }
+
+    /// Executes the query on the latest state, which is certified first so
+    /// that the canister can access its data certificate.
+    fn execute_query(&self, query: UserQuery) -> Result<WasmResult, UserError> {
+        self.certify_latest_state();
 
         let path = SubTree(flatmap! {
             Label::from("canister") => SubTree(
                 flatmap! {
-                    Label::from(receiver) => SubTree(
+                    Label::from(query.receiver) => SubTree(
                         flatmap!(Label::from("certified_data") => LabeledTree::Leaf(()))
                     )
                 }),
@@ -1132,18 +1245,17 @@ impl StateMachine {
             signature: Blob(certification.signed.signature.signature.get().0),
             delegation: None,
         });
-        self.query_handler.query(
-            UserQuery {
-                receiver,
-                source: UserId::from(sender),
-                method_name: method.to_string(),
-                method_payload,
-                ingress_expiry: 0,
-                nonce: None,
-            },
-            state,
-            data_certificate,
-        )
+        self.query_handler.query(query, state, data_certificate)
+    }
+
+    /// Certifies the latest state, if it is not certified yet.
+    fn certify_latest_state(&self) {
+        if self.state_manager.latest_state_height() > self.state_manager.latest_certified_height() {
+            let state_hashes = self.state_manager.list_state_hashes_to_certify();
+            let (height, hash) = state_hashes.last().unwrap();
+            self.state_manager
+                .deliver_state_certification(self.certify_hash(height, hash));
+        }
     }
 
     fn certify_hash(&self, height: &Height, hash: &CryptoHashOfPartialState) -> Certification {
@@ -1486,6 +1598,36 @@ impl StateMachine {
             .get()
     }
 
+    /// Returns the log of the specified canister.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the specified canister does not exist.
+    pub fn canister_log(&self, canister_id: CanisterId) -> CanisterLog {
+        let state = self.state_manager.get_latest_state().take();
+        state
+            .canister_state(&canister_id)
+            .unwrap_or_else(|| panic!("Canister {} not found", canister_id))
+            .system_state
+            .canister_log
+            .clone()
+    }
+
+    /// Fetches the log of the specified canister via the `fetch_canister_logs`
+    /// query of the management canister.
+    pub fn fetch_canister_logs(
+        &self,
+        sender: PrincipalId,
+        canister_id: CanisterId,
+    ) -> Result<WasmResult, UserError> {
+        self.query_as(
+            sender,
+            CanisterId::ic_00(),
+            Method::FetchCanisterLogs,
+            FetchCanisterLogsRequest::new(canister_id).encode(),
+        )
+    }
+
     /// Tops up the specified canister with cycle amount and returns the resulting cycle balance.
     ///
     /// # Panics
@@ -1524,4 +1666,508 @@ impl StateMachine {
             .canister_http_request_contexts
             .clone()
     }
+
+    /// Returns Bitcoin `get_successors` contexts from internal subnet call
+    /// context manager.
+    pub fn bitcoin_get_successors_contexts(
+        &self,
+    ) -> BTreeMap<CallbackId, BitcoinGetSuccessorsContext> {
+        let state = self.state_manager.get_latest_state().take();
+        state
+            .metadata
+            .subnet_call_context_manager
+            .bitcoin_get_successors_contexts
+            .clone()
+    }
+
+    /// Returns Bitcoin `send_transaction_internal` contexts from internal
+    /// subnet call context manager.
+    pub fn bitcoin_send_transaction_internal_contexts(
+        &self,
+    ) -> BTreeMap<CallbackId, BitcoinSendTransactionInternalContext> {
+        let state = self.state_manager.get_latest_state().take();
+        state
+            .metadata
+            .subnet_call_context_manager
+            .bitcoin_send_transaction_internal_contexts
+            .clone()
+    }
+
+    /// Signs the message hash of the given context with the test key of this
+    /// state machine, derived for the caller and derivation path of the
+    /// request. Returns the signature in the format of `sign_with_ecdsa`
+    /// replies, i.e. the 64-byte concatenation of `r` and `s`.
+    ///
+    /// The test keys are deterministic: the same key id always maps to the
+    /// same master key, and the master public keys returned by
+    /// `ecdsa_public_key` are consistent with the signatures.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the key of the context is not enabled on this
+    /// subnet.
+    pub fn sign_with_ecdsa_test_key(&self, context: &SignWithEcdsaContext) -> Vec<u8> {
+        let master_public_key = self
+            .ecdsa_subnet_public_keys
+            .get(&context.key_id)
+            .unwrap_or_else(|| panic!("ECDSA key {} is not enabled", context.key_id));
+        let master_public_key = EccPoint::deserialize(
+            ecdsa_test_curve(&context.key_id),
+            &master_public_key.public_key,
+        )
+        .expect("failed to deserialize the master ECDSA public key");
+        let path = DerivationPath::from(&ExtendedDerivationPath {
+            caller: context.request.sender.get(),
+            derivation_path: context.derivation_path.clone(),
+        });
+        let (tweak, _chain_code) = path
+            .derive_tweak(&master_public_key)
+            .expect("failed to derive the ECDSA key tweak");
+        let secret_key = ecdsa_test_secret_key(&context.key_id)
+            .add(&tweak)
+            .expect("failed to derive the ECDSA secret key");
+        ecdsa_sign_prehash(&secret_key, &context.message_hash)
+            .expect("failed to sign the message hash")
+    }
+
+    /// Replies to all pending `sign_with_ecdsa` requests with signatures
+    /// computed by [StateMachine::sign_with_ecdsa_test_key] and executes a
+    /// round delivering the replies.
+    pub fn handle_sign_with_ecdsa_contexts(&self) {
+        let payload = self.sign_with_ecdsa_contexts().iter().fold(
+            PayloadBuilder::new(),
+            |payload, (callback_id, context)| {
+                payload.with_consensus_response(Response {
+                    originator: context.request.sender,
+                    respondent: CanisterId::ic_00(),
+                    originator_reply_callback: *callback_id,
+                    refund: context.request.payment,
+                    response_payload: ResponsePayload::Data(
+                        SignWithECDSAReply {
+                            signature: self.sign_with_ecdsa_test_key(context),
+                        }
+                        .encode(),
+                    ),
+                })
+            },
+        );
+        self.execute_payload(payload);
+    }
+}
+
+/// Returns the deterministic master secret key used by state machines to
+/// sign with the given ECDSA key.
+fn ecdsa_test_secret_key(key_id: &EcdsaKeyId) -> EccScalar {
+    EccScalar::from_seed(
+        ecdsa_test_curve(key_id),
+        Seed::from_bytes(format!("state_machine_tests:{}", key_id).as_bytes()),
+    )
+}
+
+fn ecdsa_test_curve(key_id: &EcdsaKeyId) -> EccCurveType {
+    match key_id.curve {
+        EcdsaCurve::Secp256k1 => EccCurveType::K256,
+        EcdsaCurve::Secp256r1 => EccCurveType::P256,
+    }
+}
+
+fn ecdsa_test_algorithm_id(key_id: &EcdsaKeyId) -> AlgorithmId {
+    match key_id.curve {
+        EcdsaCurve::Secp256k1 => AlgorithmId::EcdsaSecp256k1,
+        EcdsaCurve::Secp256r1 => AlgorithmId::EcdsaP256,
+    }
+}
+
+/// Computes a plain (non-threshold) ECDSA signature of `message_hash` on the
+/// curve of `secret_key`, encoded as `r || s` with a low `s`, i.e. in the
+/// format returned by `sign_with_ecdsa`.
+///
+/// The nonce is derived from the secret key and the message hash, so the
+/// signatures are reproducible.
+fn ecdsa_sign_prehash(
+    secret_key: &EccScalar,
+    message_hash: &[u8],
+) -> ThresholdEcdsaResult<Vec<u8>> {
+    let curve = secret_key.curve_type();
+    let nonce = EccScalar::from_seed(
+        curve,
+        Seed::from_bytes(&[secret_key.serialize(), message_hash.to_vec()].concat()),
+    );
+    let r = EccScalar::from_bytes_wide(curve, &EccPoint::mul_by_g(&nonce)?.affine_x()?.as_bytes())?;
+    let e = EccScalar::from_bytes_wide(curve, message_hash)?;
+    let s = nonce.invert()?.mul(&e.add(&r.mul(secret_key)?)?)?;
+    let s = if s.is_high() { s.negate() } else { s };
+    Ok([r.serialize(), s.serialize()].concat())
+}
+
+/// Collects the inputs of a round that consensus would otherwise provide:
+/// responses to management canister requests that are answered outside of
+/// execution (threshold ECDSA signatures and canister HTTP outcalls) and
+/// responses of the Bitcoin adapter.
+///
+/// The payload is executed by [StateMachine::execute_payload].
+#[derive(Clone, Debug, Default)]
+pub struct PayloadBuilder {
+    consensus_responses: Vec<Response>,
+    bitcoin_adapter_responses: Vec<BitcoinAdapterResponse>,
+}
+
+impl PayloadBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a response to a subnet call that is delivered through the
+    /// consensus queue.
+    pub fn with_consensus_response(mut self, response: Response) -> Self {
+        self.consensus_responses.push(response);
+        self
+    }
+
+    /// Replies to the canister HTTP request with the given callback id, as
+    /// if all replicas had agreed on `response` after applying the transform.
+    pub fn with_canister_http_response(
+        self,
+        callback_id: CallbackId,
+        response: &CanisterHttpResponsePayload,
+    ) -> Self {
+        self.with_canister_http_payload(callback_id, ResponsePayload::Data(response.encode()))
+    }
+
+    /// Rejects the canister HTTP request with the given callback id, as if
+    /// the adapter had failed to perform the request.
+    pub fn with_canister_http_reject(
+        self,
+        callback_id: CallbackId,
+        code: RejectCode,
+        message: impl ToString,
+    ) -> Self {
+        self.with_canister_http_payload(
+            callback_id,
+            ResponsePayload::Reject(RejectContext {
+                code,
+                message: message.to_string(),
+            }),
+        )
+    }
+
+    /// Times out the canister HTTP request with the given callback id.
+    pub fn with_canister_http_timeout(self, callback_id: CallbackId) -> Self {
+        self.with_canister_http_reject(
+            callback_id,
+            RejectCode::SysTransient,
+            "Canister http request timed out",
+        )
+    }
+
+    /// Adds a response of the Bitcoin adapter, e.g. to a pending
+    /// `bitcoin_get_successors` request.
+    pub fn with_bitcoin_adapter_response(mut self, response: BitcoinAdapterResponse) -> Self {
+        self.bitcoin_adapter_responses.push(response);
+        self
+    }
+
+    fn with_canister_http_payload(
+        self,
+        callback_id: CallbackId,
+        response_payload: ResponsePayload,
+    ) -> Self {
+        // Consensus does not set the originator, respondent and refund of
+        // canister HTTP responses; execution looks up the request context.
+        self.with_consensus_response(Response {
+            originator: CanisterId::ic_00(),
+            respondent: CanisterId::ic_00(),
+            originator_reply_callback: callback_id,
+            refund: Cycles::zero(),
+            response_payload,
+        })
+    }
+}
+
+/// The number of test node ids reserved for each subnet of a
+/// [StateMachineEnv].
+const NODE_IDS_PER_SUBNET: u64 = 1_000;
+
+/// Builds a [StateMachineEnv].
+pub struct StateMachineEnvBuilder {
+    subnets: Vec<StateMachineBuilder>,
+}
+
+impl StateMachineEnvBuilder {
+    pub fn new() -> Self {
+        Self { subnets: vec![] }
+    }
+
+    /// Adds a subnet configured by the given builder.
+    ///
+    /// Subnets are assigned consecutive canister id ranges in the order in
+    /// which they are added.
+    pub fn with_subnet(mut self, subnet: StateMachineBuilder) -> Self {
+        self.subnets.push(subnet);
+        self
+    }
+
+    pub fn build(self) -> StateMachineEnv {
+        let subnet_ids: Vec<_> = (0..self.subnets.len())
+            .map(|i| SubnetId::from(PrincipalId::new_subnet_test_id(2 + i as u64)))
+            .collect();
+        let specs: Vec<_> = self
+            .subnets
+            .iter()
+            .zip(subnet_ids.iter())
+            .enumerate()
+            .map(|(i, (builder, subnet_id))| SubnetRegistrySpec {
+                subnet_id: *subnet_id,
+                subnet_type: builder.subnet_type,
+                node_ids: make_node_ids(i as u64 * NODE_IDS_PER_SUBNET, builder.subnet_size),
+                ecdsa_keys: builder.ecdsa_keys.clone(),
+                features: builder.features,
+            })
+            .collect();
+        let (registry_data_provider, registry_client) =
+            make_nodes_registry(SubnetId::from(PrincipalId::new_subnet_test_id(1)), &specs);
+
+        let subnets = self
+            .subnets
+            .into_iter()
+            .zip(subnet_ids.into_iter())
+            .map(|(builder, subnet_id)| {
+                builder
+                    .with_registry(
+                        subnet_id,
+                        Arc::clone(&registry_data_provider),
+                        Arc::clone(&registry_client),
+                    )
+                    .build()
+            })
+            .collect();
+        StateMachineEnv { subnets }
+    }
+}
+
+impl Default for StateMachineEnvBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Hosts several [StateMachine]s, one per subnet, that share a registry and
+/// exchange XNet messages.
+///
+/// Each [StateMachineEnv::tick] includes the certified streams of every
+/// subnet in the next batch of the respective destination subnet, so that
+/// inter-canister calls across subnets make progress deterministically.
+pub struct StateMachineEnv {
+    subnets: Vec<StateMachine>,
+}
+
+impl fmt::Debug for StateMachineEnv {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StateMachineEnv")
+            .field("subnets", &self.subnets)
+            .finish()
+    }
+}
+
+impl StateMachineEnv {
+    /// Constructs an environment with `num_subnets` subnets in the default
+    /// configuration.
+    pub fn new(num_subnets: usize) -> Self {
+        (0..num_subnets)
+            .fold(StateMachineEnvBuilder::new(), |builder, _| {
+                builder.with_subnet(StateMachineBuilder::new())
+            })
+            .build()
+    }
+
+    /// Returns all subnets, in the order they were added.
+    pub fn subnets(&self) -> &[StateMachine] {
+        &self.subnets
+    }
+
+    /// Returns the subnet with the given index.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if there is no subnet with the given index.
+    pub fn subnet(&self, index: usize) -> &StateMachine {
+        &self.subnets[index]
+    }
+
+    /// Returns the subnet that hosts the given canister according to the
+    /// routing table.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the canister is not routed to any subnet of
+    /// the environment.
+    pub fn subnet_for_canister(&self, canister_id: CanisterId) -> &StateMachine {
+        use ic_registry_client_helpers::routing_table::RoutingTableRegistry;
+
+        let registry_client = &self.subnets[0].registry_client;
+        let subnet_id = registry_client
+            .get_routing_table(registry_client.get_latest_version())
+            .expect("malformed routing table")
+            .expect("missing routing table")
+            .route(canister_id.get())
+            .unwrap_or_else(|| panic!("Canister {} is not routed to any subnet", canister_id));
+        self.subnets
+            .iter()
+            .find(|subnet| subnet.subnet_id == subnet_id)
+            .unwrap_or_else(|| panic!("Subnet {} is not part of the environment", subnet_id))
+    }
+
+    /// Executes a single round on every subnet.
+    ///
+    /// The batch of each subnet includes the slices of all streams addressed
+    /// to it, starting at the first message it has not inducted yet.
+    pub fn tick(&self) {
+        let payloads: Vec<_> = self
+            .subnets
+            .iter()
+            .map(|subnet| self.xnet_payload(subnet))
+            .collect();
+        for (subnet, xnet) in self.subnets.iter().zip(payloads.into_iter()) {
+            subnet.execute_block(
+                BatchPayload {
+                    xnet,
+                    ..BatchPayload::default()
+                },
+                vec![],
+            );
+        }
+    }
+
+    /// Builds an XNet payload for `destination` from the latest certified
+    /// states of all other subnets.
+    fn xnet_payload(&self, destination: &StateMachine) -> XNetPayload {
+        let state = destination.state_manager.get_latest_state().take();
+        let stream_slices = self
+            .subnets
+            .iter()
+            .filter(|source| source.subnet_id != destination.subnet_id)
+            .filter_map(|source| {
+                source.certify_latest_state();
+                // The signals of the reverse stream tell which messages have
+                // already been inducted by the destination.
+                let begin = state
+                    .metadata
+                    .streams()
+                    .get(&source.subnet_id)
+                    .map(|stream| stream.signals_end());
+                match source.state_manager.encode_certified_stream_slice(
+                    destination.subnet_id,
+                    begin,
+                    begin,
+                    None,
+                    None,
+                ) {
+                    Ok(slice) => Some((source.subnet_id, slice)),
+                    Err(EncodeStreamError::NoStreamForSubnet(_)) => None,
+                    Err(err) => panic!(
+                        "Failed to encode stream from {} to {}: {}",
+                        source.subnet_id, destination.subnet_id, err
+                    ),
+                }
+            })
+            .collect();
+        XNetPayload { stream_slices }
+    }
+
+    /// Makes all subnets tick until there are no more messages in the
+    /// system, including messages in flight between subnets.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the subnets did not process all messages within
+    /// the `max_ticks` iterations.
+    pub fn run_until_completion(&self, max_ticks: usize) {
+        for _tick in 0..max_ticks {
+            if self.subnets.iter().all(|subnet| {
+                let state = subnet.state_manager.get_latest_state().take();
+                !has_pending_messages(&state)
+                    && state
+                        .metadata
+                        .streams()
+                        .iter()
+                        .all(|(_, stream)| stream.messages().is_empty())
+            }) {
+                return;
+            }
+            self.tick();
+        }
+        panic!(
+            "The state machine environment did not reach completion after {} ticks",
+            max_ticks
+        );
+    }
+
+    /// Blocks until the result of the ingress message with the specified ID,
+    /// submitted to the subnet with the specified ID, is available.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the result doesn't become available after the
+    /// specified number of ticks.
+    pub fn await_ingress(
+        &self,
+        subnet_id: SubnetId,
+        msg_id: MessageId,
+        max_ticks: usize,
+    ) -> Result<WasmResult, UserError> {
+        let subnet = self
+            .subnets
+            .iter()
+            .find(|subnet| subnet.subnet_id == subnet_id)
+            .unwrap_or_else(|| panic!("Subnet {} is not part of the environment", subnet_id));
+        for _tick in 0..max_ticks {
+            match subnet.ingress_status(&msg_id) {
+                IngressStatus::Known {
+                    state: IngressState::Completed(result),
+                    ..
+                } => return Ok(result),
+                IngressStatus::Known {
+                    state: IngressState::Failed(error),
+                    ..
+                } => return Err(error),
+                _ => self.tick(),
+            }
+        }
+        panic!(
+            "Did not get answer to ingress {} after {} ticks",
+            msg_id, max_ticks
+        )
+    }
+
+    /// Sends an ingress message to the subnet hosting the specified canister
+    /// and ticks all subnets until the message completes.
+    pub fn execute_ingress(
+        &self,
+        canister_id: CanisterId,
+        method: impl ToString,
+        payload: Vec<u8>,
+    ) -> Result<WasmResult, UserError> {
+        const MAX_TICKS: usize = 100;
+        let subnet = self.subnet_for_canister(canister_id);
+        let msg_id =
+            subnet.send_ingress(PrincipalId::new_anonymous(), canister_id, method, payload);
+        self.await_ingress(subnet.subnet_id, msg_id, MAX_TICKS)
+    }
+
+    /// Advances the time of all subnets by the given amount.
+    pub fn advance_time(&self, amount: Duration) {
+        for subnet in &self.subnets {
+            subnet.advance_time(amount);
+        }
+    }
+}
+
+/// Returns `true` if any canister or the subnet still has messages to process
+/// or to route.
+fn has_pending_messages(state: &ReplicatedState) -> bool {
+    state
+        .canisters_iter()
+        .any(|canister| canister.has_input() || canister.has_output())
+        || state.subnet_queues().has_input()
+        || state.subnet_queues().has_output()
 }
@@ -10,12 +10,12 @@ use ic_replicated_state::{
     bitcoin_state::{BitcoinState, UtxoSet},
     canister_state::execution_state::WasmBinary,
     page_map::PageMap,
-    CanisterMetrics, CanisterState, ExecutionState, NumWasmPages, ReplicatedState, SchedulerState,
-    SystemState,
+    CanisterMetrics, CanisterSnapshot, CanisterSnapshots, CanisterState, ExecutionState,
+    NumWasmPages, ReplicatedState, SchedulerState, SnapshotId, SystemState,
 };
 use ic_state_layout::{
-    BitcoinStateBits, BitcoinStateLayout, CanisterLayout, CanisterStateBits, CheckpointLayout,
-    ExecutionStateBits, ReadOnly, ReadPolicy, RwPolicy, StateLayout, TipHandler,
+    BitcoinStateBits, BitcoinStateLayout, CanisterLayout, CanisterSnapshotBits, CanisterStateBits,
+    CheckpointLayout, ExecutionStateBits, ReadOnly, ReadPolicy, RwPolicy, StateLayout, TipHandler,
 };
 use ic_types::{CanisterTimer, Height, LongExecutionMode, Time};
 use ic_utils::fs::defrag_file_partially;
@@ -25,6 +25,7 @@ use rand::{seq::IteratorRandom, Rng, SeedableRng};
 use rand_chacha::ChaChaRng;
 use std::collections::BTreeMap;
 use std::os::unix::prelude::MetadataExt;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use std::{
     convert::{From, TryFrom},
@@ -85,6 +86,14 @@ pub fn make_checkpoint(
         tip_handler.filter_tip_canisters(height, &state.canister_states.keys().collect())?;
     }
 
+    {
+        let _timer = metrics
+            .make_checkpoint_step_duration
+            .with_label_values(&["filter_snapshots"])
+            .start_timer();
+        tip_handler.filter_tip_snapshots(height, &state.canister_snapshots.ids().collect())?;
+    }
+
     let cp = {
         let _timer = metrics
             .make_checkpoint_step_duration
@@ -129,6 +138,16 @@ fn serialize_to_tip(
         result?;
     }
 
+    let results = parallel_map(
+        thread_pool,
+        state.canister_snapshots.iter(),
+        |(snapshot_id, snapshot)| serialize_snapshot_to_tip(log, snapshot_id, snapshot, tip),
+    );
+
+    for result in results.into_iter() {
+        result?;
+    }
+
     serialize_bitcoin_state_to_tip(state.bitcoin(), &tip.bitcoin()?)?;
 
     Ok(())
@@ -248,6 +267,63 @@ fn serialize_canister_to_tip(
                     .global_timer
                     .to_nanos_since_unix_epoch(),
                 canister_version: canister_state.system_state.canister_version,
+                next_snapshot_id: canister_state.system_state.next_snapshot_id,
+                canister_log: canister_state.system_state.canister_log.clone(),
+                log_visibility: canister_state.system_state.log_visibility,
+            }
+            .into(),
+        )
+        .map_err(CheckpointError::from)
+}
+
+fn serialize_snapshot_to_tip(
+    log: &ReplicaLogger,
+    snapshot_id: &SnapshotId,
+    snapshot: &CanisterSnapshot,
+    tip: &CheckpointLayout<RwPolicy>,
+) -> Result<(), CheckpointError> {
+    let snapshot_layout = tip.snapshot(snapshot_id)?;
+
+    // Snapshots are immutable, so the Wasm binary only needs to be written once.
+    let wasm = snapshot_layout.wasm();
+    if !wasm.raw_path().exists() {
+        match snapshot.wasm_binary.file() {
+            Some(path) => {
+                ic_state_layout::utils::do_copy(log, path, wasm.raw_path()).map_err(|io_err| {
+                    CheckpointError::IoError {
+                        path: path.to_path_buf(),
+                        message: "failed to copy Wasm file".to_string(),
+                        io_err: io_err.to_string(),
+                    }
+                })?;
+            }
+            None => wasm.serialize(&snapshot.wasm_binary)?,
+        }
+    }
+    snapshot
+        .wasm_memory
+        .persist_delta(&snapshot_layout.vmemory_0())?;
+    snapshot
+        .stable_memory
+        .persist_delta(&snapshot_layout.stable_memory_blob())?;
+
+    snapshot_layout
+        .snapshot()
+        .serialize(
+            CanisterSnapshotBits {
+                snapshot_id: *snapshot_id,
+                taken_at_timestamp: snapshot.taken_at_timestamp,
+                canister_version: snapshot.canister_version,
+                certified_data: snapshot.certified_data.clone(),
+                execution_state_bits: ExecutionStateBits {
+                    exported_globals: snapshot.exported_globals.clone(),
+                    heap_size: snapshot.wasm_memory_size,
+                    exports: snapshot.exports.clone(),
+                    last_executed_round: 0.into(),
+                    metadata: snapshot.metadata.clone(),
+                    binary_hash: Some(snapshot.wasm_binary.module_hash().into()),
+                },
+                stable_memory_size: snapshot.stable_memory_size,
             }
             .into(),
         )
@@ -273,6 +349,11 @@ fn serialize_bitcoin_state_to_tip(
         .address_outpoints
         .persist_delta(&layout.address_outpoints())?;
 
+    state
+        .utxo_set
+        .block_headers
+        .persist_delta(&layout.block_headers())?;
+
     layout
         .bitcoin_state()
         .serialize(
@@ -458,7 +539,21 @@ pub fn load_checkpoint<P: ReadPolicy + Send + Sync>(
         load_bitcoin_state(checkpoint_layout)?
     };
 
-    let state = ReplicatedState::new_from_checkpoint(
+    let canister_snapshots = {
+        let _timer = metrics
+            .load_checkpoint_step_duration
+            .with_label_values(&["canister_snapshots"])
+            .start_timer();
+
+        let mut snapshots = BTreeMap::new();
+        for snapshot_id in checkpoint_layout.snapshot_ids()? {
+            let snapshot = load_snapshot(checkpoint_layout, &snapshot_id)?;
+            snapshots.insert(snapshot_id, Arc::new(snapshot));
+        }
+        CanisterSnapshots::new(snapshots)
+    };
+
+    let mut state = ReplicatedState::new_from_checkpoint(
         canister_states,
         metadata,
         subnet_queues,
@@ -466,6 +561,7 @@ pub fn load_checkpoint<P: ReadPolicy + Send + Sync>(
         Vec::new(),
         bitcoin,
     );
+    state.canister_snapshots = canister_snapshots;
 
     Ok(state)
 }
@@ -587,6 +683,9 @@ pub fn load_canister_state<P: ReadPolicy>(
         canister_state_bits.task_queue.into_iter().collect(),
         CanisterTimer::from_nanos_since_unix_epoch(canister_state_bits.global_timer_nanos),
         canister_state_bits.canister_version,
+        canister_state_bits.next_snapshot_id,
+        canister_state_bits.canister_log,
+        canister_state_bits.log_visibility,
     );
 
     let canister_state = CanisterState {
@@ -621,6 +720,39 @@ fn load_canister_state_from_checkpoint<P: ReadPolicy>(
     load_canister_state::<P>(&canister_layout, canister_id, checkpoint_layout.height())
 }
 
+fn load_snapshot<P: ReadPolicy>(
+    checkpoint_layout: &CheckpointLayout<P>,
+    snapshot_id: &SnapshotId,
+) -> Result<CanisterSnapshot, CheckpointError> {
+    let snapshot_layout = checkpoint_layout.snapshot(snapshot_id)?;
+    let height = checkpoint_layout.height();
+
+    let snapshot_bits = CanisterSnapshotBits::try_from(snapshot_layout.snapshot().deserialize()?)
+        .map_err(|err| CheckpointError::ProtoError {
+        path: snapshot_layout.raw_path(),
+        field: format!("snapshots[{}]::snapshot_bits", snapshot_id),
+        proto_err: err.to_string(),
+    })?;
+    let execution_state_bits = snapshot_bits.execution_state_bits;
+
+    Ok(CanisterSnapshot {
+        canister_id: snapshot_id.canister_id(),
+        taken_at_timestamp: snapshot_bits.taken_at_timestamp,
+        canister_version: snapshot_bits.canister_version,
+        certified_data: snapshot_bits.certified_data,
+        wasm_binary: snapshot_layout
+            .wasm()
+            .deserialize(execution_state_bits.binary_hash)?,
+        exported_globals: execution_state_bits.exported_globals,
+        exports: execution_state_bits.exports,
+        metadata: execution_state_bits.metadata,
+        wasm_memory: PageMap::open(&snapshot_layout.vmemory_0(), height)?,
+        wasm_memory_size: execution_state_bits.heap_size,
+        stable_memory: PageMap::open(&snapshot_layout.stable_memory_blob(), height)?,
+        stable_memory_size: snapshot_bits.stable_memory_size,
+    })
+}
+
 fn load_bitcoin_state<P: ReadPolicy>(
     checkpoint_layout: &CheckpointLayout<P>,
 ) -> Result<BitcoinState, CheckpointError> {
@@ -643,6 +775,7 @@ fn load_bitcoin_state<P: ReadPolicy>(
     let utxos_small = load_or_create_pagemap(&layout.utxos_small(), height)?;
     let utxos_medium = load_or_create_pagemap(&layout.utxos_medium(), height)?;
     let address_outpoints = load_or_create_pagemap(&layout.address_outpoints(), height)?;
+    let block_headers = load_or_create_pagemap(&layout.block_headers(), height)?;
 
     Ok(BitcoinState {
         adapter_queues: bitcoin_state_bits.adapter_queues,
@@ -654,6 +787,7 @@ fn load_bitcoin_state<P: ReadPolicy>(
             utxos_medium,
             utxos_large: bitcoin_state_bits.utxos_large,
             address_outpoints,
+            block_headers,
         },
         fee_percentiles_cache: None,
     })
@@ -681,6 +815,7 @@ mod tests {
     };
     use ic_sys::PAGE_SIZE;
     use ic_test_utilities::{
+        mock_time,
         state::{canister_ids, new_canister_state},
         types::{
             ids::{canister_test_id, message_test_id, subnet_test_id, user_test_id},
@@ -937,6 +1072,83 @@ mod tests {
         });
     }
 
+    #[test]
+    fn can_recover_canister_snapshots() {
+        with_test_replica_logger(|log| {
+            let tmp = tmpdir("checkpoint");
+            let root = tmp.path().to_path_buf();
+            let layout = StateLayout::try_new(log.clone(), root).unwrap();
+            let mut tip_handler = layout.capture_tip_handler();
+
+            const HEIGHT: Height = Height::new(42);
+            let canister_id: CanisterId = canister_test_id(10);
+
+            let mut canister_state = new_canister_state(
+                canister_id,
+                user_test_id(24).get(),
+                INITIAL_CYCLES,
+                NumSeconds::from(100_000),
+            );
+            canister_state.execution_state = Some(ExecutionState {
+                canister_root: "NOT_USED".into(),
+                session_nonce: None,
+                wasm_binary: WasmBinary::new(empty_wasm()),
+                wasm_memory: one_page_of(1),
+                stable_memory: one_page_of(2),
+                exported_globals: vec![],
+                exports: ExportedFunctions::new(BTreeSet::new()),
+                metadata: WasmMetadata::default(),
+                last_executed_round: ExecutionRound::from(0),
+            });
+            canister_state.system_state.certified_data = vec![3; 32];
+            let snapshot = CanisterSnapshot::from_canister(&canister_state, mock_time()).unwrap();
+
+            // Modify the canister after taking the snapshot.
+            canister_state.execution_state.as_mut().unwrap().wasm_memory = one_page_of(4);
+
+            let own_subnet_type = SubnetType::Application;
+            let mut state = ReplicatedState::new(subnet_test_id(1), own_subnet_type);
+            state.put_canister_state(canister_state);
+            let snapshot_id = SnapshotId::new(canister_id, 0);
+            state
+                .canister_snapshots
+                .insert(snapshot_id, snapshot.clone());
+            let _state =
+                make_checkpoint_and_get_state(&log, &state, HEIGHT, &layout, &mut tip_handler);
+
+            let recovered_state = load_checkpoint(
+                &layout.checkpoint(HEIGHT).unwrap(),
+                own_subnet_type,
+                &checkpoint_metrics(),
+                Some(&mut thread_pool()),
+            )
+            .unwrap();
+
+            let recovered = recovered_state
+                .canister_snapshots
+                .get(&snapshot_id)
+                .unwrap();
+            assert_eq!(recovered.canister_id, canister_id);
+            assert_eq!(recovered.taken_at_timestamp, snapshot.taken_at_timestamp);
+            assert_eq!(recovered.certified_data, vec![3; 32]);
+            assert_eq!(
+                recovered.wasm_binary.as_slice(),
+                snapshot.wasm_binary.as_slice()
+            );
+            assert_eq!(recovered.wasm_memory, one_page_of(1).page_map);
+            assert_eq!(recovered.wasm_memory_size, NumWasmPages::from(1));
+            assert_eq!(recovered.stable_memory, one_page_of(2).page_map);
+            assert_eq!(recovered.stable_memory_size, NumWasmPages::from(1));
+            assert_eq!(recovered.size(), snapshot.size());
+
+            let canister = recovered_state.canister_state(&canister_id).unwrap();
+            assert_eq!(
+                canister.execution_state.as_ref().unwrap().wasm_memory,
+                one_page_of(4)
+            );
+        });
+    }
+
     #[test]
     fn can_recover_an_empty_state() {
         with_test_replica_logger(|log| {
@@ -1269,6 +1481,7 @@ mod tests {
             state.bitcoin_mut().utxo_set.utxos_small = PageMap::from(&[1, 2, 3, 4][..]);
             state.bitcoin_mut().utxo_set.utxos_medium = PageMap::from(&[5, 6, 7, 8][..]);
             state.bitcoin_mut().utxo_set.address_outpoints = PageMap::from(&[9, 10, 11, 12][..]);
+            state.bitcoin_mut().utxo_set.block_headers = PageMap::from(&[13, 14, 15, 16][..]);
 
             let original_state = state.clone();
             let _state =
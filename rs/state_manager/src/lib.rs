@@ -22,9 +22,9 @@ use ic_interfaces_certified_stream_store::{
     CertifiedStreamStore, DecodeStreamError, EncodeStreamError,
 };
 use ic_interfaces_state_manager::{
-    CertificationMask, CertificationScope, Labeled, PermanentStateHashError::*, StateHashError,
-    StateManager, StateManagerError, StateManagerResult, StateReader, TransientStateHashError::*,
-    CERT_CERTIFIED, CERT_UNCERTIFIED,
+    CertificationMask, CertificationScope, CertifiedHeightReceiver, Labeled,
+    PermanentStateHashError::*, StateHashError, StateManager, StateManagerError,
+    StateManagerResult, StateReader, TransientStateHashError::*, CERT_CERTIFIED, CERT_UNCERTIFIED,
 };
 use ic_logger::{debug, error, fatal, info, warn, ReplicaLogger};
 use ic_metrics::{buckets::decimal_buckets, MetricsRegistry};
@@ -33,7 +33,7 @@ use ic_protobuf::{messaging::xnet::v1, state::v1 as pb};
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::{
     canister_state::execution_state::SandboxMemory, page_map::PersistenceError, PageIndex, PageMap,
-    ReplicatedState,
+    ReplicatedState, SnapshotId,
 };
 use ic_state_layout::{
     error::LayoutError, AccessPolicy, CheckpointLayout, StateLayout, TipHandler,
@@ -674,6 +674,8 @@ pub struct StateManagerImpl {
     // requested quite often and this causes high contention on the lock.
     latest_state_height: AtomicU64,
     latest_certified_height: AtomicU64,
+    // Notifies the subscribers of `latest_certified_height` of its changes.
+    certified_height_sender: tokio::sync::watch::Sender<Height>,
     state_sync_refs: StateSyncRefs,
     _state_hasher_handle: JoinOnDrop<()>,
     _deallocation_handle: JoinOnDrop<()>,
@@ -905,6 +907,8 @@ struct PopulatedMetadata {
 pub enum PageMapType {
     WasmMemory(CanisterId),
     StableMemory(CanisterId),
+    SnapshotWasmMemory(SnapshotId),
+    SnapshotStableMemory(SnapshotId),
     Bitcoin(BitcoinPageMap),
 }
 
@@ -914,6 +918,7 @@ pub enum BitcoinPageMap {
     UtxosSmall,
     UtxosMedium,
     AddressOutpoints,
+    BlockHeaders,
 }
 
 impl PageMapType {
@@ -927,9 +932,15 @@ impl PageMapType {
             }
         }
 
+        for id in state.canister_snapshots.ids() {
+            result.push(Self::SnapshotWasmMemory(id.to_owned()));
+            result.push(Self::SnapshotStableMemory(id.to_owned()));
+        }
+
         result.push(Self::Bitcoin(BitcoinPageMap::UtxosSmall));
         result.push(Self::Bitcoin(BitcoinPageMap::UtxosMedium));
         result.push(Self::Bitcoin(BitcoinPageMap::AddressOutpoints));
+        result.push(Self::Bitcoin(BitcoinPageMap::BlockHeaders));
 
         result
     }
@@ -942,6 +953,8 @@ impl PageMapType {
         match &self {
             PageMapType::WasmMemory(id) => Ok(layout.canister(id)?.vmemory_0()),
             PageMapType::StableMemory(id) => Ok(layout.canister(id)?.stable_memory_blob()),
+            PageMapType::SnapshotWasmMemory(id) => Ok(layout.snapshot(id)?.vmemory_0()),
+            PageMapType::SnapshotStableMemory(id) => Ok(layout.snapshot(id)?.stable_memory_blob()),
             PageMapType::Bitcoin(BitcoinPageMap::UtxosSmall) => Ok(layout.bitcoin()?.utxos_small()),
             PageMapType::Bitcoin(BitcoinPageMap::UtxosMedium) => {
                 Ok(layout.bitcoin()?.utxos_medium())
@@ -949,6 +962,9 @@ impl PageMapType {
             PageMapType::Bitcoin(BitcoinPageMap::AddressOutpoints) => {
                 Ok(layout.bitcoin()?.address_outpoints())
             }
+            PageMapType::Bitcoin(BitcoinPageMap::BlockHeaders) => {
+                Ok(layout.bitcoin()?.block_headers())
+            }
         }
     }
 
@@ -965,6 +981,14 @@ impl PageMapType {
                     .as_ref()
                     .map(|ex| &ex.stable_memory.page_map)
             }),
+            PageMapType::SnapshotWasmMemory(id) => state
+                .canister_snapshots
+                .get(id)
+                .map(|snapshot| &snapshot.wasm_memory),
+            PageMapType::SnapshotStableMemory(id) => state
+                .canister_snapshots
+                .get(id)
+                .map(|snapshot| &snapshot.stable_memory),
             PageMapType::Bitcoin(BitcoinPageMap::UtxosSmall) => {
                 Some(&state.bitcoin().utxo_set.utxos_small)
             }
@@ -974,6 +998,9 @@ impl PageMapType {
             PageMapType::Bitcoin(BitcoinPageMap::AddressOutpoints) => {
                 Some(&state.bitcoin().utxo_set.address_outpoints)
             }
+            PageMapType::Bitcoin(BitcoinPageMap::BlockHeaders) => {
+                Some(&state.bitcoin().utxo_set.block_headers)
+            }
         }
     }
 
@@ -990,6 +1017,14 @@ impl PageMapType {
                     .as_mut()
                     .map(|ex| &mut ex.stable_memory.page_map)
             }),
+            PageMapType::SnapshotWasmMemory(id) => state
+                .canister_snapshots
+                .get_mut(id)
+                .map(|snapshot| &mut snapshot.wasm_memory),
+            PageMapType::SnapshotStableMemory(id) => state
+                .canister_snapshots
+                .get_mut(id)
+                .map(|snapshot| &mut snapshot.stable_memory),
             PageMapType::Bitcoin(BitcoinPageMap::UtxosSmall) => {
                 Some(&mut state.bitcoin_mut().utxo_set.utxos_small)
             }
@@ -999,6 +1034,9 @@ impl PageMapType {
             PageMapType::Bitcoin(BitcoinPageMap::AddressOutpoints) => {
                 Some(&mut state.bitcoin_mut().utxo_set.address_outpoints)
             }
+            PageMapType::Bitcoin(BitcoinPageMap::BlockHeaders) => {
+                Some(&mut state.bitcoin_mut().utxo_set.block_headers)
+            }
         }
     }
 }
@@ -1246,6 +1284,15 @@ impl StateManagerImpl {
     /// Height for the initial default state.
     const INITIAL_STATE_HEIGHT: Height = Height::new(0);
 
+    /// Publishes the latest certified height to the subscribers if it changed.
+    fn notify_certified_height(&self, height: Height) {
+        self.certified_height_sender.send_if_modified(|latest| {
+            let modified = *latest != height;
+            *latest = height;
+            modified
+        });
+    }
+
     pub fn new(
         verifier: Arc<dyn Verifier>,
         own_subnet_id: SubnetId,
@@ -1384,6 +1431,7 @@ impl StateManagerImpl {
 
         let latest_state_height = AtomicU64::new(0);
         let latest_certified_height = AtomicU64::new(0);
+        let (certified_height_sender, _) = tokio::sync::watch::channel(Height::new(0));
 
         let initial_snapshot = Snapshot {
             height: Self::INITIAL_STATE_HEIGHT,
@@ -1515,6 +1563,7 @@ impl StateManagerImpl {
             deallocation_sender,
             latest_state_height,
             latest_certified_height,
+            certified_height_sender,
             state_sync_refs: StateSyncRefs::new(log),
             _state_hasher_handle,
             _deallocation_handle,
@@ -2235,6 +2284,7 @@ impl StateManagerImpl {
 
         self.latest_certified_height
             .store(latest_certified_height.get(), Ordering::Relaxed);
+        self.notify_certified_height(latest_certified_height);
 
         self.metrics
             .latest_certified_height
@@ -2639,6 +2689,7 @@ impl StateManager for StateManagerImpl {
             self.metrics
                 .latest_certified_height
                 .set(latest_certified as i64);
+            self.notify_certified_height(Height::new(latest_certified));
 
             metadata.certification = Some(certification);
 
@@ -3087,6 +3138,10 @@ impl StateReader for StateManagerImpl {
         Height::new(self.latest_certified_height.load(Ordering::Relaxed))
     }
 
+    fn subscribe_certified_height(&self) -> CertifiedHeightReceiver {
+        self.certified_height_sender.subscribe()
+    }
+
     fn get_latest_state(&self) -> Labeled<Arc<Self::State>> {
         let _timer = self
             .metrics
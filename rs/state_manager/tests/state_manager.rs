@@ -1538,7 +1538,7 @@ fn state_sync_message_contains_manifest() {
             .get_validated_by_identifier(&id)
             .expect("failed to get state sync messages");
 
-        // Expecting 6 files, as we don't have canisters in the default state.
+        // Expecting 7 files, as we don't have canisters in the default state.
         //
         // 1. "system_metadata.pbuf"
         // 2. "subnet_queues.pbuf"
@@ -1546,7 +1546,8 @@ fn state_sync_message_contains_manifest() {
         // 4. "bitcoin/testnet/utxos_small.pbuf"
         // 5. "bitcoin/testnet/utxos_medium.pbuf"
         // 6. "bitcoin/testnet/address_outpoints.pbuf"
-        assert_eq!(6, msg.manifest.file_table.len());
+        // 7. "bitcoin/testnet/block_headers.pbuf"
+        assert_eq!(7, msg.manifest.file_table.len());
 
         // Check that all the files are accessible
         for file_info in msg.manifest.file_table.iter() {
@@ -2418,6 +2419,11 @@ fn can_get_dirty_pages() {
             (PageIndex::new(3), &[99u8; PAGE_SIZE]),
             (PageIndex::new(300), &[99u8; PAGE_SIZE]),
         ]);
+
+        state.bitcoin_mut().utxo_set.block_headers.update(&[
+            (PageIndex::new(4), &[99u8; PAGE_SIZE]),
+            (PageIndex::new(400), &[99u8; PAGE_SIZE]),
+        ]);
     }
 
     fn drop_page_map(state: &mut ReplicatedState, canister_id: CanisterId) {
@@ -2501,6 +2507,11 @@ fn can_get_dirty_pages() {
                 )),
                 page_delta_indices: vec![PageIndex::new(3), PageIndex::new(300)],
             },
+            DirtyPageMap {
+                height: height(1),
+                file_type: FileType::PageMap(PageMapType::Bitcoin(BitcoinPageMap::BlockHeaders)),
+                page_delta_indices: vec![PageIndex::new(4), PageIndex::new(400)],
+            },
             DirtyPageMap {
                 height: height(1),
                 file_type: FileType::WasmBinary(canister_test_id(80)),
@@ -2579,6 +2590,11 @@ fn can_get_dirty_pages() {
                 )),
                 page_delta_indices: vec![],
             },
+            DirtyPageMap {
+                height: height(2),
+                file_type: FileType::PageMap(PageMapType::Bitcoin(BitcoinPageMap::BlockHeaders)),
+                page_delta_indices: vec![],
+            },
             DirtyPageMap {
                 height: height(2),
                 file_type: FileType::WasmBinary(canister_test_id(80)),
@@ -1,4 +1,5 @@
 //! Command implementations.
+pub mod canister_bundle;
 pub mod cdiff;
 pub mod chash;
 pub mod convert_ids;
@@ -6,5 +7,6 @@ pub mod decode;
 pub mod import_state;
 pub mod list;
 pub mod manifest;
+pub mod stats;
 mod utils;
 pub mod verify_manifest;
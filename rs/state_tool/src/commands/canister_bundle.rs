@@ -0,0 +1,342 @@
+//! Exports the state of a single canister from a checkpoint into a
+//! self-contained bundle and imports such bundles into other checkpoints.
+//!
+//! A bundle is a directory with the following structure:
+//!
+//! ```text
+//! <bundle>/
+//! ├── canister     The canister directory copied verbatim from the checkpoint
+//! │   ├── canister.pbuf
+//! │   ├── queues.pbuf
+//! │   ├── software.wasm
+//! │   ├── stable_memory.bin
+//! │   └── vmemory_0.bin
+//! ├── manifest     The manifest of `canister/` in the format of the `manifest` command
+//! └── metadata     The original canister ID and the state sync version of the manifest
+//! ```
+//!
+//! The `canister` directory can also be loaded into a state machine with
+//! `StateMachine::import_canister_state`.
+
+use crate::commands::utils;
+use ic_logger::replica_logger::no_op_logger;
+use ic_metrics::MetricsRegistry;
+use ic_state_layout::{CheckpointLayout, ReadOnly};
+use ic_state_manager::{
+    manifest::{compute_manifest, manifest_hash, DEFAULT_CHUNK_SIZE},
+    ManifestMetrics,
+};
+use ic_types::{state_sync::Manifest, CanisterId, Height};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+const CANISTER_DIR: &str = "canister";
+const MANIFEST_FILE: &str = "manifest";
+const METADATA_FILE: &str = "metadata";
+
+const CANISTER_ID_PREFIX: &str = "CANISTER ID: ";
+const STATE_SYNC_VERSION_PREFIX: &str = "STATE SYNC VERSION: ";
+const ROOT_HASH_PREFIX: &str = "ROOT HASH: ";
+
+/// Copies the state of `canister` in the checkpoint at `state` into a new
+/// bundle directory `out`.
+pub fn do_export_canister(state: PathBuf, canister: String, out: PathBuf) -> Result<(), String> {
+    let canister_id = CanisterId::from_str(&canister).map_err(|e| e.to_string())?;
+    let cp_layout = CheckpointLayout::<ReadOnly>::new(state, Height::new(0))
+        .map_err(|e| format!("Failed to create checkpoint layout: {}", e))?;
+    let canister_layout = cp_layout
+        .canister(&canister_id)
+        .map_err(|e| format!("Failed to create canister layout: {}", e))?;
+    if !canister_layout.raw_path().is_dir() {
+        return Err(format!(
+            "Canister {} does not exist in checkpoint {}",
+            canister_id,
+            cp_layout.raw_path().display()
+        ));
+    }
+    if out.exists() {
+        return Err(format!("Bundle {} already exists", out.display()));
+    }
+
+    let state_sync_version = cp_layout
+        .system_metadata()
+        .deserialize()
+        .map_err(|e| {
+            format!(
+                "Failed to deserialize system metadata to determine the manifest version: {}",
+                e
+            )
+        })?
+        .state_sync_version;
+
+    let canister_dir = out.join(CANISTER_DIR);
+    utils::copy_recursively(&canister_layout.raw_path(), &canister_dir)?;
+
+    let manifest = compute_bundle_manifest(&canister_dir, state_sync_version)?;
+    let root_hash = hex::encode(manifest_hash(&manifest));
+    write_file(
+        &out.join(MANIFEST_FILE),
+        format!("{}\n{}{}\n", manifest, ROOT_HASH_PREFIX, root_hash),
+    )?;
+    write_file(
+        &out.join(METADATA_FILE),
+        format!(
+            "{}{}\n{}{}\n",
+            CANISTER_ID_PREFIX, canister_id, STATE_SYNC_VERSION_PREFIX, state_sync_version
+        ),
+    )?;
+
+    println!(
+        "Successfully exported canister {} to {}",
+        canister_id,
+        out.display()
+    );
+    println!("{}{}", ROOT_HASH_PREFIX, root_hash);
+
+    Ok(())
+}
+
+/// Verifies the bundle at `bundle` against its manifest and inserts the
+/// canister into the checkpoint at `state`, either under its original ID or
+/// under `canister` if specified.
+///
+/// Only the canister directory is added: the caller is responsible for the
+/// canister ID being part of the subnet's routing table, and messages in the
+/// canister's queues still refer to the original canister ID.
+///
+/// Function is not crash-safe. Caller is responsible to follow guidelines
+/// regarding crash-safe I/O.
+pub fn do_import_canister(
+    bundle: PathBuf,
+    state: PathBuf,
+    canister: Option<String>,
+) -> Result<(), String> {
+    let metadata = read_file(&bundle.join(METADATA_FILE))?;
+    let original_canister_id = CanisterId::from_str(field(&metadata, CANISTER_ID_PREFIX)?)
+        .map_err(|e| format!("Malformed canister ID in bundle metadata: {}", e))?;
+    let state_sync_version: u32 = field(&metadata, STATE_SYNC_VERSION_PREFIX)?
+        .parse()
+        .map_err(|e| format!("Malformed state sync version in bundle metadata: {}", e))?;
+
+    let canister_dir = bundle.join(CANISTER_DIR);
+    let expected_root_hash =
+        field(&read_file(&bundle.join(MANIFEST_FILE))?, ROOT_HASH_PREFIX)?.to_string();
+    let root_hash = hex::encode(manifest_hash(&compute_bundle_manifest(
+        &canister_dir,
+        state_sync_version,
+    )?));
+    if root_hash != expected_root_hash {
+        return Err(format!(
+            "Bundle {} is corrupted: expected root hash {}, computed {}",
+            bundle.display(),
+            expected_root_hash,
+            root_hash
+        ));
+    }
+
+    let canister_id = match canister {
+        Some(canister) => CanisterId::from_str(&canister).map_err(|e| e.to_string())?,
+        None => original_canister_id,
+    };
+    let cp_layout = CheckpointLayout::<ReadOnly>::new(state, Height::new(0))
+        .map_err(|e| format!("Failed to create checkpoint layout: {}", e))?;
+    let canister_ids = cp_layout
+        .canister_ids()
+        .map_err(|e| format!("Failed to list canisters in checkpoint: {}", e))?;
+    if canister_ids.contains(&canister_id) {
+        return Err(format!(
+            "Canister {} already exists in checkpoint {}",
+            canister_id,
+            cp_layout.raw_path().display()
+        ));
+    }
+    let canister_layout = cp_layout
+        .canister(&canister_id)
+        .map_err(|e| format!("Failed to create canister layout: {}", e))?;
+
+    utils::copy_recursively(&canister_dir, &canister_layout.raw_path())?;
+    mark_files_readonly(&canister_layout.raw_path())?;
+
+    println!(
+        "Successfully imported canister {} as {} into checkpoint {}",
+        original_canister_id,
+        canister_id,
+        cp_layout.raw_path().display()
+    );
+
+    Ok(())
+}
+
+fn compute_bundle_manifest(
+    canister_dir: &Path,
+    state_sync_version: u32,
+) -> Result<Manifest, String> {
+    let mut thread_pool =
+        scoped_threadpool::Pool::new(ic_state_manager::NUMBER_OF_CHECKPOINT_THREADS);
+    let metrics_registry = MetricsRegistry::new();
+    let manifest_metrics = ManifestMetrics::new(&metrics_registry);
+    compute_manifest(
+        &mut thread_pool,
+        &manifest_metrics,
+        &no_op_logger(),
+        state_sync_version,
+        canister_dir,
+        DEFAULT_CHUNK_SIZE,
+        None,
+    )
+    .map_err(|e| {
+        format!(
+            "Failed to compute manifest of {}: {}",
+            canister_dir.display(),
+            e
+        )
+    })
+}
+
+/// Returns the value of the line starting with `prefix`.
+fn field<'a>(content: &'a str, prefix: &str) -> Result<&'a str, String> {
+    content
+        .lines()
+        .find_map(|line| line.strip_prefix(prefix))
+        .map(str::trim)
+        .ok_or_else(|| {
+            format!(
+                "Missing field `{}` in bundle",
+                prefix.trim_end_matches(": ")
+            )
+        })
+}
+
+fn read_file(path: &Path) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+}
+
+fn write_file(path: &Path, content: String) -> Result<(), String> {
+    fs::write(path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Marks all files below `path` as readonly, like all other files of a
+/// checkpoint.
+fn mark_files_readonly(path: &Path) -> Result<(), String> {
+    let metadata = path
+        .metadata()
+        .map_err(|e| format!("failed to get metadata of path {}: {}", path.display(), e))?;
+    if metadata.is_dir() {
+        let entries = path
+            .read_dir()
+            .map_err(|e| format!("failed to read directory {}: {}", path.display(), e))?;
+        for entry in entries {
+            let entry = entry.map_err(|e| {
+                format!(
+                    "failed to read entry of directory {}: {}",
+                    path.display(),
+                    e
+                )
+            })?;
+            mark_files_readonly(&entry.path())?;
+        }
+    } else {
+        let mut permissions = metadata.permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(path, permissions).map_err(|e| {
+            format!(
+                "failed to set readonly permissions for file {}: {}",
+                path.display(),
+                e
+            )
+        })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CANISTER_FILES: &[&str] = &[
+        "canister.pbuf",
+        "queues.pbuf",
+        "software.wasm",
+        "stable_memory.bin",
+        "vmemory_0.bin",
+    ];
+
+    fn canister_dir(checkpoint: &Path, canister_id: CanisterId) -> PathBuf {
+        checkpoint
+            .join("canister_states")
+            .join(hex::encode(canister_id.get_ref().as_slice()))
+    }
+
+    /// Creates a checkpoint with the system metadata and a single canister
+    /// with dummy files.
+    fn make_checkpoint(root: &Path, canister_id: CanisterId) -> PathBuf {
+        let checkpoint = root.join("checkpoint");
+        let cp_layout =
+            CheckpointLayout::<ic_state_layout::RwPolicy>::new(checkpoint.clone(), Height::new(0))
+                .unwrap();
+        cp_layout
+            .system_metadata()
+            .serialize(ic_protobuf::state::system_metadata::v1::SystemMetadata {
+                state_sync_version: ic_state_manager::manifest::CURRENT_STATE_SYNC_VERSION,
+                ..Default::default()
+            })
+            .unwrap();
+        let dir = canister_dir(&checkpoint, canister_id);
+        fs::create_dir_all(&dir).unwrap();
+        for (i, file) in CANISTER_FILES.iter().enumerate() {
+            fs::write(dir.join(file), vec![i as u8; 1024 * (i + 1)]).unwrap();
+        }
+        checkpoint
+    }
+
+    #[test]
+    fn exported_canister_can_be_imported_under_another_id() {
+        let tmp = tempfile::tempdir().unwrap();
+        let canister_id = CanisterId::from_u64(1);
+        let other_canister_id = CanisterId::from_u64(2);
+        let source = make_checkpoint(&tmp.path().join("source"), canister_id);
+        let target = make_checkpoint(&tmp.path().join("target"), canister_id);
+        let bundle = tmp.path().join("bundle");
+
+        do_export_canister(source.clone(), canister_id.to_string(), bundle.clone()).unwrap();
+        do_import_canister(
+            bundle.clone(),
+            target.clone(),
+            Some(other_canister_id.to_string()),
+        )
+        .unwrap();
+
+        for file in CANISTER_FILES {
+            let imported = canister_dir(&target, other_canister_id).join(file);
+            assert_eq!(
+                fs::read(canister_dir(&source, canister_id).join(file)).unwrap(),
+                fs::read(&imported).unwrap()
+            );
+            assert!(imported.metadata().unwrap().permissions().readonly());
+        }
+
+        // The canister already exists under its original ID.
+        assert!(do_import_canister(bundle, target, None).is_err());
+    }
+
+    #[test]
+    fn corrupted_bundle_is_rejected() {
+        let tmp = tempfile::tempdir().unwrap();
+        let canister_id = CanisterId::from_u64(1);
+        let source = make_checkpoint(&tmp.path().join("source"), canister_id);
+        let target = make_checkpoint(&tmp.path().join("target"), CanisterId::from_u64(2));
+        let bundle = tmp.path().join("bundle");
+
+        do_export_canister(source, canister_id.to_string(), bundle.clone()).unwrap();
+        let heap = bundle.join(CANISTER_DIR).join("vmemory_0.bin");
+        let mut permissions = heap.metadata().unwrap().permissions();
+        permissions.set_readonly(false);
+        fs::set_permissions(&heap, permissions).unwrap();
+        fs::write(&heap, vec![42; 1024]).unwrap();
+
+        let err = do_import_canister(bundle, target.clone(), None).unwrap_err();
+        assert!(err.contains("is corrupted"), "{}", err);
+        assert!(!canister_dir(&target, canister_id).exists());
+    }
+}
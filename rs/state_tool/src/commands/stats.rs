@@ -0,0 +1,545 @@
+//! Reports per-canister memory, cycles and queue statistics of a checkpoint.
+
+use clap::ArgEnum;
+use ic_config::{
+    config_parser::ConfigSource, execution_environment::Config as ExecutionConfig, ConfigOptional,
+};
+use ic_ic00_types::CanisterStatusType;
+use ic_registry_subnet_type::SubnetType;
+use ic_replicated_state::{canister_state::WASM_PAGE_SIZE_IN_BYTES, CanisterState};
+use ic_state_layout::CompleteCheckpointLayout;
+use ic_state_manager::checkpoint::load_canister_state;
+use ic_types::{CanisterId, Height, MemoryAllocation, PrincipalId};
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// The format in which the statistics are printed.
+#[derive(Clone, Copy, Debug, ArgEnum)]
+pub enum OutputFormat {
+    Table,
+    Json,
+    Csv,
+}
+
+/// The column by which canisters are sorted.
+#[derive(Clone, Copy, Debug, ArgEnum)]
+#[clap(rename_all = "snake_case")]
+pub enum SortBy {
+    CanisterId,
+    Memory,
+    Cycles,
+    Wasm,
+    Heap,
+    Stable,
+    Messages,
+}
+
+/// The status of a canister.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ArgEnum)]
+pub enum Status {
+    Running,
+    Stopping,
+    Stopped,
+}
+
+impl From<CanisterStatusType> for Status {
+    fn from(status: CanisterStatusType) -> Self {
+        match status {
+            CanisterStatusType::Running => Status::Running,
+            CanisterStatusType::Stopping => Status::Stopping,
+            CanisterStatusType::Stopped => Status::Stopped,
+        }
+    }
+}
+
+/// Conditions that canisters must satisfy to be included in the report.
+#[derive(Debug, Default)]
+pub struct Filter {
+    /// Only include canisters with the given status.
+    pub status: Option<Status>,
+    /// Only include canisters controlled by the given principal.
+    pub controller: Option<PrincipalId>,
+    /// Only include canisters using at least that many bytes of memory.
+    pub min_memory_bytes: Option<u64>,
+}
+
+impl Filter {
+    fn matches(&self, canister: &CanisterState, stats: &CanisterStats) -> bool {
+        self.status
+            .map_or(true, |status| Status::from(canister.status()) == status)
+            && self.controller.map_or(true, |controller| {
+                canister.controllers().contains(&controller)
+            })
+            && stats.total_memory_bytes >= self.min_memory_bytes.unwrap_or(0)
+    }
+}
+
+/// The statistics of a single canister.
+#[derive(Debug, Serialize)]
+struct CanisterStats {
+    #[serde(skip)]
+    id: CanisterId,
+    canister_id: String,
+    status: String,
+    controllers: Vec<String>,
+    cycles_balance: u128,
+    memory_allocation_bytes: u64,
+    compute_allocation_percent: u64,
+    wasm_size_bytes: u64,
+    heap_pages: u64,
+    heap_size_bytes: u64,
+    stable_memory_pages: u64,
+    stable_memory_size_bytes: u64,
+    ingress_messages: usize,
+    input_messages: usize,
+    output_messages: usize,
+    /// Memory used by the Wasm module, heap, stable memory and globals.
+    execution_memory_bytes: u64,
+    /// Memory used by canister messages.
+    message_memory_bytes: u64,
+    /// Memory usage as accounted on the subnet, i.e. including message
+    /// memory everywhere but on system subnets.
+    total_memory_bytes: u64,
+    /// Memory taken from the subnet capacity: the memory allocation if one
+    /// is reserved, the execution memory otherwise, plus message memory
+    /// everywhere but on system subnets.
+    memory_taken_bytes: u64,
+}
+
+impl CanisterStats {
+    fn new(canister: &CanisterState, subnet_type: SubnetType) -> Self {
+        let system_state = &canister.system_state;
+        let queues = system_state.queues();
+        let (wasm_size_bytes, heap_pages, stable_memory_pages, execution_memory_bytes) =
+            match &canister.execution_state {
+                Some(es) => (
+                    es.wasm_binary.binary.len() as u64,
+                    es.wasm_memory.size.get() as u64,
+                    es.stable_memory.size.get() as u64,
+                    es.memory_usage().get(),
+                ),
+                None => (0, 0, 0, 0),
+            };
+        let message_memory_bytes = system_state.memory_usage().get();
+        let subnet_message_memory_bytes = if subnet_type == SubnetType::System {
+            0
+        } else {
+            message_memory_bytes
+        };
+        let reserved_memory_bytes = match canister.memory_allocation() {
+            MemoryAllocation::Reserved(bytes) => bytes.get(),
+            MemoryAllocation::BestEffort => execution_memory_bytes,
+        };
+
+        Self {
+            id: canister.canister_id(),
+            canister_id: canister.canister_id().to_string(),
+            status: canister.status().to_string(),
+            controllers: canister
+                .controllers()
+                .iter()
+                .map(|controller| controller.to_string())
+                .collect(),
+            cycles_balance: system_state.balance().get(),
+            memory_allocation_bytes: canister.memory_allocation().bytes().get(),
+            compute_allocation_percent: canister.compute_allocation().as_percent(),
+            wasm_size_bytes,
+            heap_pages,
+            heap_size_bytes: heap_pages * WASM_PAGE_SIZE_IN_BYTES as u64,
+            stable_memory_pages,
+            stable_memory_size_bytes: stable_memory_pages * WASM_PAGE_SIZE_IN_BYTES as u64,
+            ingress_messages: queues.ingress_queue_message_count(),
+            input_messages: queues.input_queues_message_count(),
+            output_messages: queues.output_queues_message_count(),
+            execution_memory_bytes,
+            message_memory_bytes,
+            total_memory_bytes: canister.memory_usage(subnet_type).get(),
+            memory_taken_bytes: reserved_memory_bytes + subnet_message_memory_bytes,
+        }
+    }
+
+    fn messages(&self) -> usize {
+        self.ingress_messages + self.input_messages + self.output_messages
+    }
+}
+
+/// The totals over all canisters of a checkpoint.
+#[derive(Debug, Default, Serialize)]
+struct SubnetTotals {
+    canisters: usize,
+    cycles_balance: u128,
+    memory_allocation_bytes: u64,
+    compute_allocation_percent: u64,
+    execution_memory_bytes: u64,
+    message_memory_bytes: u64,
+    total_memory_bytes: u64,
+    memory_taken_bytes: u64,
+    /// The `subnet_memory_capacity` of the replica configuration.
+    subnet_memory_capacity_bytes: u64,
+    /// The `subnet_message_memory_capacity` of the replica configuration.
+    subnet_message_memory_capacity_bytes: u64,
+}
+
+impl SubnetTotals {
+    fn new(canisters: &[CanisterStats], config: &ExecutionConfig) -> Self {
+        let mut totals = Self {
+            canisters: canisters.len(),
+            subnet_memory_capacity_bytes: config.subnet_memory_capacity.get(),
+            subnet_message_memory_capacity_bytes: config.subnet_message_memory_capacity.get(),
+            ..Default::default()
+        };
+        for canister in canisters {
+            totals.cycles_balance += canister.cycles_balance;
+            totals.memory_allocation_bytes += canister.memory_allocation_bytes;
+            totals.compute_allocation_percent += canister.compute_allocation_percent;
+            totals.execution_memory_bytes += canister.execution_memory_bytes;
+            totals.message_memory_bytes += canister.message_memory_bytes;
+            totals.total_memory_bytes += canister.total_memory_bytes;
+            totals.memory_taken_bytes += canister.memory_taken_bytes;
+        }
+        totals
+    }
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    canisters: &'a [CanisterStats],
+    totals: &'a SubnetTotals,
+}
+
+/// Loads all canisters of the checkpoint at `path` and prints their
+/// statistics along with subnet totals.
+///
+/// Memory is accounted as on a subnet of type `subnet_type` and compared
+/// against the subnet capacities of the replica configuration at `config`.
+/// Totals only include the canisters that pass the `filter`.
+pub fn do_stats(
+    path: PathBuf,
+    config: PathBuf,
+    subnet_type: SubnetType,
+    format: OutputFormat,
+    sort_by: SortBy,
+    filter: Filter,
+) -> Result<(), String> {
+    let config = load_execution_config(config)?;
+    let cp_layout = CompleteCheckpointLayout::new(path.clone(), Height::new(0))
+        .map_err(|e| format!("failed to create checkpoint layout: {}", e))?;
+
+    let mut canisters = Vec::new();
+    for canister_id in cp_layout
+        .canister_ids()
+        .map_err(|e| format!("failed to list canisters at {}: {}", path.display(), e))?
+    {
+        let canister_layout = cp_layout
+            .canister(&canister_id)
+            .map_err(|e| format!("failed to create layout of canister {}: {}", canister_id, e))?;
+        let (canister, _metrics) =
+            load_canister_state(&canister_layout, &canister_id, cp_layout.height())
+                .map_err(|e| format!("failed to load canister {}: {}", canister_id, e))?;
+
+        let stats = CanisterStats::new(&canister, subnet_type);
+        if filter.matches(&canister, &stats) {
+            canisters.push(stats);
+        }
+    }
+
+    sort(&mut canisters, sort_by);
+    let totals = SubnetTotals::new(&canisters, &config);
+
+    match format {
+        OutputFormat::Table => print_table(&canisters, &totals),
+        OutputFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&Report {
+                canisters: &canisters,
+                totals: &totals,
+            })
+            .map_err(|e| format!("failed to serialize statistics: {}", e))?
+        ),
+        OutputFormat::Csv => print_csv(&canisters),
+    }
+
+    Ok(())
+}
+
+/// Loads the execution environment configuration of the given `replica`
+/// configuration file, falling back to the replica defaults like the replica
+/// does.
+fn load_execution_config(config_path: PathBuf) -> Result<ExecutionConfig, String> {
+    let config: ConfigOptional = ConfigSource::File(config_path.clone())
+        .load()
+        .map_err(|e| format!("failed to load {}: {}", config_path.display(), e))?;
+    Ok(config.hypervisor.unwrap_or_default())
+}
+
+/// Sorts canisters by canister ID in ascending order, and by all other
+/// columns in descending order, so that the biggest canisters come first.
+fn sort(canisters: &mut [CanisterStats], sort_by: SortBy) {
+    match sort_by {
+        SortBy::CanisterId => canisters.sort_by_key(|c| c.id),
+        SortBy::Memory => canisters.sort_by_key(|c| std::cmp::Reverse(c.total_memory_bytes)),
+        SortBy::Cycles => canisters.sort_by_key(|c| std::cmp::Reverse(c.cycles_balance)),
+        SortBy::Wasm => canisters.sort_by_key(|c| std::cmp::Reverse(c.wasm_size_bytes)),
+        SortBy::Heap => canisters.sort_by_key(|c| std::cmp::Reverse(c.heap_size_bytes)),
+        SortBy::Stable => canisters.sort_by_key(|c| std::cmp::Reverse(c.stable_memory_size_bytes)),
+        SortBy::Messages => canisters.sort_by_key(|c| std::cmp::Reverse(c.messages())),
+    }
+}
+
+const TABLE_HEADER: &[&str] = &[
+    "canister_id",
+    "status",
+    "cycles",
+    "mem_alloc",
+    "compute_alloc",
+    "wasm",
+    "heap_pages",
+    "stable_pages",
+    "ingress",
+    "input",
+    "output",
+    "memory",
+    "controllers",
+];
+
+fn table_row(c: &CanisterStats) -> Vec<String> {
+    vec![
+        c.canister_id.clone(),
+        c.status.clone(),
+        c.cycles_balance.to_string(),
+        c.memory_allocation_bytes.to_string(),
+        format!("{}%", c.compute_allocation_percent),
+        c.wasm_size_bytes.to_string(),
+        c.heap_pages.to_string(),
+        c.stable_memory_pages.to_string(),
+        c.ingress_messages.to_string(),
+        c.input_messages.to_string(),
+        c.output_messages.to_string(),
+        c.total_memory_bytes.to_string(),
+        c.controllers.join(","),
+    ]
+}
+
+fn print_table(canisters: &[CanisterStats], totals: &SubnetTotals) {
+    let rows: Vec<Vec<String>> = canisters.iter().map(table_row).collect();
+    let widths: Vec<usize> = TABLE_HEADER
+        .iter()
+        .enumerate()
+        .map(|(i, header)| {
+            rows.iter()
+                .map(|row| row[i].len())
+                .chain(std::iter::once(header.len()))
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+
+    let print_row = |row: Vec<&str>| {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect();
+        println!("{}", cells.join(" | ").trim_end());
+    };
+    print_row(TABLE_HEADER.to_vec());
+    println!(
+        "{}",
+        widths
+            .iter()
+            .map(|width| "-".repeat(*width))
+            .collect::<Vec<_>>()
+            .join("-+-")
+    );
+    for row in rows.iter() {
+        print_row(row.iter().map(String::as_str).collect());
+    }
+
+    println!();
+    println!("CANISTERS: {}", totals.canisters);
+    println!("TOTAL CYCLES: {}", totals.cycles_balance);
+    println!(
+        "TOTAL MEMORY ALLOCATION: {} bytes",
+        totals.memory_allocation_bytes
+    );
+    println!(
+        "TOTAL COMPUTE ALLOCATION: {}%",
+        totals.compute_allocation_percent
+    );
+    println!(
+        "TOTAL EXECUTION MEMORY: {} bytes",
+        totals.execution_memory_bytes
+    );
+    println!(
+        "TOTAL MESSAGE MEMORY: {} bytes",
+        totals.message_memory_bytes
+    );
+    println!(
+        "TOTAL MESSAGE MEMORY TAKEN: {:.2}% of the subnet message memory capacity of {} bytes",
+        percent(
+            totals.message_memory_bytes,
+            totals.subnet_message_memory_capacity_bytes
+        ),
+        totals.subnet_message_memory_capacity_bytes
+    );
+    println!("TOTAL MEMORY: {} bytes", totals.total_memory_bytes);
+    println!(
+        "TOTAL MEMORY TAKEN: {} bytes ({:.2}% of the subnet memory capacity of {} bytes)",
+        totals.memory_taken_bytes,
+        percent(
+            totals.memory_taken_bytes,
+            totals.subnet_memory_capacity_bytes
+        ),
+        totals.subnet_memory_capacity_bytes
+    );
+}
+
+fn percent(part: u64, whole: u64) -> f64 {
+    100.0 * part as f64 / whole as f64
+}
+
+fn print_csv(canisters: &[CanisterStats]) {
+    println!(
+        "canister_id,status,controllers,cycles_balance,memory_allocation_bytes,\
+         compute_allocation_percent,wasm_size_bytes,heap_pages,heap_size_bytes,\
+         stable_memory_pages,stable_memory_size_bytes,ingress_messages,input_messages,\
+         output_messages,execution_memory_bytes,message_memory_bytes,total_memory_bytes,\
+         memory_taken_bytes"
+    );
+    for c in canisters {
+        println!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            c.canister_id,
+            c.status,
+            // Principals never contain spaces, so they can be separated by
+            // spaces without quoting the field.
+            c.controllers.join(" "),
+            c.cycles_balance,
+            c.memory_allocation_bytes,
+            c.compute_allocation_percent,
+            c.wasm_size_bytes,
+            c.heap_pages,
+            c.heap_size_bytes,
+            c.stable_memory_pages,
+            c.stable_memory_size_bytes,
+            c.ingress_messages,
+            c.input_messages,
+            c.output_messages,
+            c.execution_memory_bytes,
+            c.message_memory_bytes,
+            c.total_memory_bytes,
+            c.memory_taken_bytes,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_test_utilities::{
+        state::CanisterStateBuilder,
+        types::{
+            ids::{canister_test_id, user_test_id},
+            messages::RequestBuilder,
+        },
+    };
+    use ic_types::NumBytes;
+
+    #[test]
+    fn filter_matches_status_controller_and_memory() {
+        let canister = CanisterStateBuilder::new()
+            .with_canister_id(canister_test_id(1))
+            .with_controller(user_test_id(1).get())
+            .with_status(CanisterStatusType::Stopped)
+            .with_stable_memory(vec![1; 1024])
+            .build();
+        let stats = CanisterStats::new(&canister, SubnetType::Application);
+        let matches = |filter: Filter| filter.matches(&canister, &stats);
+
+        assert!(matches(Filter::default()));
+        assert!(matches(Filter {
+            status: Some(Status::Stopped),
+            controller: Some(user_test_id(1).get()),
+            min_memory_bytes: Some(stats.total_memory_bytes),
+        }));
+        assert!(!matches(Filter {
+            status: Some(Status::Running),
+            ..Default::default()
+        }));
+        assert!(!matches(Filter {
+            controller: Some(user_test_id(2).get()),
+            ..Default::default()
+        }));
+        assert!(!matches(Filter {
+            min_memory_bytes: Some(stats.total_memory_bytes + 1),
+            ..Default::default()
+        }));
+    }
+
+    #[test]
+    fn canister_ids_are_sorted_ascending_and_other_columns_descending() {
+        let mut canisters: Vec<_> = [(1, 20_u128), (3, 30), (2, 10)]
+            .iter()
+            .map(|(id, cycles)| {
+                let canister = CanisterStateBuilder::new()
+                    .with_canister_id(canister_test_id(*id))
+                    .with_cycles(*cycles)
+                    .build();
+                CanisterStats::new(&canister, SubnetType::Application)
+            })
+            .collect();
+        let ids = |canisters: &[CanisterStats]| canisters.iter().map(|c| c.id).collect::<Vec<_>>();
+
+        sort(&mut canisters, SortBy::CanisterId);
+        assert_eq!(
+            ids(&canisters),
+            vec![
+                canister_test_id(1),
+                canister_test_id(2),
+                canister_test_id(3)
+            ]
+        );
+
+        sort(&mut canisters, SortBy::Cycles);
+        assert_eq!(
+            ids(&canisters),
+            vec![
+                canister_test_id(3),
+                canister_test_id(1),
+                canister_test_id(2)
+            ]
+        );
+    }
+
+    #[test]
+    fn totals_follow_the_subnet_type_and_configured_capacities() {
+        let canister_id = canister_test_id(1);
+        let canister = CanisterStateBuilder::new()
+            .with_canister_id(canister_id)
+            .with_memory_allocation(NumBytes::new(1 << 20))
+            .with_canister_request(RequestBuilder::new().receiver(canister_id).build())
+            .build();
+        let config = ExecutionConfig {
+            subnet_memory_capacity: NumBytes::new(1 << 30),
+            subnet_message_memory_capacity: NumBytes::new(1 << 25),
+            ..Default::default()
+        };
+
+        let application = CanisterStats::new(&canister, SubnetType::Application);
+        assert!(application.message_memory_bytes > 0);
+        assert_eq!(
+            application.memory_taken_bytes,
+            (1 << 20) + application.message_memory_bytes
+        );
+        // Message memory does not count against the memory capacity of
+        // system subnets.
+        let system = CanisterStats::new(&canister, SubnetType::System);
+        assert_eq!(system.memory_taken_bytes, 1 << 20);
+
+        let totals = SubnetTotals::new(&[application, system], &config);
+        assert_eq!(totals.canisters, 2);
+        assert_eq!(totals.memory_allocation_bytes, 2 << 20);
+        assert_eq!(totals.subnet_memory_capacity_bytes, 1 << 30);
+        assert_eq!(totals.subnet_message_memory_capacity_bytes, 1 << 25);
+    }
+}
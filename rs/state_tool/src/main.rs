@@ -2,7 +2,8 @@
 //!
 //! A command-line tool to manage Internet Computer replicated states (decode
 //! persisted state files, diff checkpoints, compute partial state hashes and
-//! checkpoint manifests, import state trees).
+//! checkpoint manifests, import state trees, move single canisters between
+//! checkpoints, report canister statistics).
 
 use clap::Parser;
 use std::path::PathBuf;
@@ -41,6 +42,40 @@ enum Opt {
         height: u64,
     },
 
+    /// Exports the state of a single canister from a checkpoint into a
+    /// self-contained bundle directory.
+    #[clap(name = "export_canister")]
+    ExportCanister {
+        /// Path to a checkpoint.
+        #[clap(long = "state")]
+        state: PathBuf,
+
+        /// The textual ID of the canister to export.
+        #[clap(long = "canister")]
+        canister: String,
+
+        /// Path of the bundle directory to create.
+        #[clap(long = "out")]
+        out: PathBuf,
+    },
+
+    /// Imports a canister bundle created by `export_canister` into a
+    /// checkpoint.
+    #[clap(name = "import_canister")]
+    ImportCanister {
+        /// Path to the bundle.
+        #[clap(long = "bundle")]
+        bundle: PathBuf,
+
+        /// Path to the checkpoint to insert the canister into.
+        #[clap(long = "state")]
+        state: PathBuf,
+
+        /// The textual ID to give the canister; defaults to its original ID.
+        #[clap(long = "canister")]
+        canister: Option<String>,
+    },
+
     /// Computes manifest of a checkpoint.
     #[clap(name = "manifest")]
     Manifest {
@@ -49,6 +84,47 @@ enum Opt {
         path: PathBuf,
     },
 
+    /// Reports the memory usage, cycles balance, queue sizes and settings of
+    /// all canisters in a checkpoint, along with subnet totals.
+    #[clap(name = "stats")]
+    Stats {
+        /// Path to a checkpoint.
+        #[clap(long = "state")]
+        path: PathBuf,
+
+        /// Path to the replica configuration (ic.json) that defines the
+        /// subnet memory capacities.
+        #[clap(long = "config")]
+        config: PathBuf,
+
+        /// The type of the subnet (application, verified_application or
+        /// system), which determines whether message memory counts against
+        /// the subnet memory capacity.
+        #[clap(long = "subnet_type", default_value = "application")]
+        subnet_type: ic_registry_subnet_type::SubnetType,
+
+        /// The output format.
+        #[clap(long = "format", arg_enum, default_value = "table")]
+        format: commands::stats::OutputFormat,
+
+        /// The column to sort by. Canister IDs are sorted in ascending order,
+        /// all other columns in descending order.
+        #[clap(long = "sort_by", arg_enum, default_value = "memory")]
+        sort_by: commands::stats::SortBy,
+
+        /// Only report canisters with this status.
+        #[clap(long = "status", arg_enum)]
+        status: Option<commands::stats::Status>,
+
+        /// Only report canisters controlled by this principal.
+        #[clap(long = "controller")]
+        controller: Option<ic_types::PrincipalId>,
+
+        /// Only report canisters using at least this many bytes of memory.
+        #[clap(long = "min_memory_bytes")]
+        min_memory_bytes: Option<u64>,
+    },
+
     /// Verifies whether the textual representation
     /// of a manifest matches its root hash.
     #[clap(name = "verify_manifest")]
@@ -154,7 +230,38 @@ fn main() {
             config,
             height,
         } => commands::import_state::do_import(state, config, height),
+        Opt::ExportCanister {
+            state,
+            canister,
+            out,
+        } => commands::canister_bundle::do_export_canister(state, canister, out),
+        Opt::ImportCanister {
+            bundle,
+            state,
+            canister,
+        } => commands::canister_bundle::do_import_canister(bundle, state, canister),
         Opt::Manifest { path } => commands::manifest::do_compute_manifest(path),
+        Opt::Stats {
+            path,
+            config,
+            subnet_type,
+            format,
+            sort_by,
+            status,
+            controller,
+            min_memory_bytes,
+        } => commands::stats::do_stats(
+            path,
+            config,
+            subnet_type,
+            format,
+            sort_by,
+            commands::stats::Filter {
+                status,
+                controller,
+                min_memory_bytes,
+            },
+        ),
         Opt::VerifyManifest { file, version } => {
             commands::verify_manifest::do_verify_manifest(&file, version)
         }
@@ -19,6 +19,7 @@ use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::{memory_required_to_push_request, Memory, NumWasmPages, PageIndex};
 use ic_sys::PageBytes;
 use ic_types::{
+    canister_log::CanisterLog,
     ingress::WasmResult,
     messages::{CallContextId, RejectContext, Request, MAX_INTER_CANISTER_PAYLOAD_IN_BYTES},
     methods::{Callback, SystemMethod, WasmClosure},
@@ -38,6 +39,7 @@ use std::{
 const MULTIPLIER_MAX_SIZE_LOCAL_SUBNET: u64 = 5;
 const MAX_NON_REPLICATED_QUERY_REPLY_SIZE: NumBytes = NumBytes::new(3 << 20);
 const CERTIFIED_DATA_MAX_LENGTH: u32 = 32;
+const MAX_DEBUG_MESSAGE_SIZE: u32 = 32 * 1024;
 
 // Enables tracing of system calls for local debugging.
 const TRACE_SYSCALLS: bool = false;
@@ -729,6 +731,12 @@ pub struct SystemApiImpl {
 
     /// Tracks the total execution complexity.
     total_execution_complexity: ExecutionComplexity,
+
+    /// Canister log records produced during the execution.
+    canister_log: CanisterLog,
+
+    /// Set when a query reads the data certificate.
+    data_certificate_accessed: bool,
 }
 
 impl SystemApiImpl {
@@ -764,6 +772,8 @@ impl SystemApiImpl {
             current_slice_instruction_limit: i64::try_from(slice_limit).unwrap_or(i64::MAX),
             instructions_executed_before_current_slice: 0,
             total_execution_complexity: ExecutionComplexity::new(),
+            canister_log: CanisterLog::default(),
+            data_certificate_accessed: false,
         }
     }
 
@@ -1182,6 +1192,41 @@ impl SystemApi for SystemApiImpl {
         self.execution_error.as_ref()
     }
 
+    fn append_canister_log(&mut self, content: Vec<u8>) {
+        // The `start` method has no access to the time.
+        let timestamp_nanos = match &self.api_type {
+            ApiType::Start { .. } => 0,
+            ApiType::Init { time, .. }
+            | ApiType::SystemTask { time, .. }
+            | ApiType::Update { time, .. }
+            | ApiType::Cleanup { time, .. }
+            | ApiType::NonReplicatedQuery { time, .. }
+            | ApiType::ReplicatedQuery { time, .. }
+            | ApiType::PreUpgrade { time, .. }
+            | ApiType::ReplyCallback { time, .. }
+            | ApiType::RejectCallback { time, .. }
+            | ApiType::InspectMessage { time, .. } => time.as_nanos_since_unix_epoch(),
+        };
+        self.canister_log.add_record(timestamp_nanos, content);
+    }
+
+    fn save_log_message(&mut self, src: u32, size: u32, heap: &[u8]) {
+        let size = size.min(MAX_DEBUG_MESSAGE_SIZE);
+        let content = match valid_subslice("ic0.debug_print", src, size, heap) {
+            Ok(bytes) => bytes.to_vec(),
+            Err(_) => b"(debug message out of memory bounds)".to_vec(),
+        };
+        self.append_canister_log(content);
+    }
+
+    fn take_canister_log(&mut self) -> CanisterLog {
+        std::mem::take(&mut self.canister_log)
+    }
+
+    fn data_certificate_accessed(&self) -> bool {
+        self.data_certificate_accessed
+    }
+
     fn get_num_instructions_from_bytes(&self, num_bytes: NumBytes) -> NumInstructions {
         match self.sandbox_safe_system_state.subnet_type {
             SubnetType::System => NumInstructions::from(0),
@@ -2570,7 +2615,7 @@ impl SystemApi for SystemApiImpl {
         result
     }
 
-    fn ic0_data_certificate_present(&self) -> HypervisorResult<i32> {
+    fn ic0_data_certificate_present(&mut self) -> HypervisorResult<i32> {
         let result = match &self.api_type {
             ApiType::Start { .. } => Err(self.error_for("ic0_data_certificate_present")),
             ApiType::Init { .. }
@@ -2586,16 +2631,19 @@ impl SystemApi for SystemApiImpl {
             }
             | ApiType::NonReplicatedQuery {
                 data_certificate, ..
-            } => match data_certificate {
-                Some(_) => Ok(1),
-                None => Ok(0),
-            },
+            } => {
+                self.data_certificate_accessed = true;
+                match data_certificate {
+                    Some(_) => Ok(1),
+                    None => Ok(0),
+                }
+            }
         };
         trace_syscall!(self, ic0_data_certificate_present, result);
         result
     }
 
-    fn ic0_data_certificate_size(&self) -> HypervisorResult<i32> {
+    fn ic0_data_certificate_size(&mut self) -> HypervisorResult<i32> {
         let result = match &self.api_type {
             ApiType::Start { .. }
             | ApiType::Init { .. }
@@ -2611,17 +2659,20 @@ impl SystemApi for SystemApiImpl {
             }
             | ApiType::NonReplicatedQuery {
                 data_certificate, ..
-            } => match data_certificate {
-                Some(data_certificate) => Ok(data_certificate.len() as i32),
-                None => Err(self.error_for("ic0_data_certificate_size")),
-            },
+            } => {
+                self.data_certificate_accessed = true;
+                match data_certificate {
+                    Some(data_certificate) => Ok(data_certificate.len() as i32),
+                    None => Err(self.error_for("ic0_data_certificate_size")),
+                }
+            }
         };
         trace_syscall!(self, ic0_data_certificate_size, result);
         result
     }
 
     fn ic0_data_certificate_copy(
-        &self,
+        &mut self,
         dst: u32,
         offset: u32,
         size: u32,
@@ -2642,43 +2693,46 @@ impl SystemApi for SystemApiImpl {
             }
             | ApiType::NonReplicatedQuery {
                 data_certificate, ..
-            } => match data_certificate {
-                Some(data_certificate) => {
-                    let (dst, offset, size) = (dst as usize, offset as usize, size as usize);
-
-                    let (upper_bound, overflow) = offset.overflowing_add(size);
-                    if overflow || upper_bound > data_certificate.len() {
-                        return Err(ContractViolation(format!(
-                            "ic0_data_certificate_copy failed because offset + size is out \
+            } => {
+                self.data_certificate_accessed = true;
+                match data_certificate {
+                    Some(data_certificate) => {
+                        let (dst, offset, size) = (dst as usize, offset as usize, size as usize);
+
+                        let (upper_bound, overflow) = offset.overflowing_add(size);
+                        if overflow || upper_bound > data_certificate.len() {
+                            return Err(ContractViolation(format!(
+                                "ic0_data_certificate_copy failed because offset + size is out \
                                  of bounds. Found offset = {} and size = {} while offset + size \
                                  must be <= {}",
-                            offset,
-                            size,
-                            data_certificate.len(),
-                        )));
-                    }
+                                offset,
+                                size,
+                                data_certificate.len(),
+                            )));
+                        }
 
-                    let (upper_bound, overflow) = dst.overflowing_add(size);
-                    if overflow || upper_bound > heap.len() {
-                        return Err(ContractViolation(format!(
-                            "ic0_data_certificate_copy failed because dst + size is out \
+                        let (upper_bound, overflow) = dst.overflowing_add(size);
+                        if overflow || upper_bound > heap.len() {
+                            return Err(ContractViolation(format!(
+                                "ic0_data_certificate_copy failed because dst + size is out \
                                  of bounds. Found dst = {} and size = {} while dst + size \
                                  must be <= {}",
-                            dst,
-                            size,
-                            heap.len(),
-                        )));
-                    }
+                                dst,
+                                size,
+                                heap.len(),
+                            )));
+                        }
 
-                    // Copy the certificate into the canister.
-                    deterministic_copy_from_slice(
-                        &mut heap[dst..dst + size],
-                        &data_certificate[offset..offset + size],
-                    );
-                    Ok(())
+                        // Copy the certificate into the canister.
+                        deterministic_copy_from_slice(
+                            &mut heap[dst..dst + size],
+                            &data_certificate[offset..offset + size],
+                        );
+                        Ok(())
+                    }
+                    None => Err(self.error_for("ic0_data_certificate_size")),
                 }
-                None => Err(self.error_for("ic0_data_certificate_size")),
-            },
+            }
         };
         trace_syscall!(
             self,
@@ -2787,8 +2841,8 @@ impl SystemApi for SystemApiImpl {
         result
     }
 
-    fn ic0_debug_print(&self, src: u32, size: u32, heap: &[u8]) -> HypervisorResult<()> {
-        const MAX_DEBUG_MESSAGE_SIZE: u32 = 32 * 1024;
+    fn ic0_debug_print(&mut self, src: u32, size: u32, heap: &[u8]) -> HypervisorResult<()> {
+        self.save_log_message(src, size, heap);
         let size = size.min(MAX_DEBUG_MESSAGE_SIZE);
         let msg = match valid_subslice("ic0.debug_print", src, size, heap) {
             Ok(bytes) => String::from_utf8_lossy(bytes).to_string(),
@@ -5,10 +5,11 @@ use candid::Decode;
 use ic_base_types::{CanisterId, PrincipalId, SubnetId};
 use ic_btc_types::NetworkInRequest as BitcoinNetwork;
 use ic_ic00_types::{
-    BitcoinGetBalanceArgs, BitcoinGetCurrentFeePercentilesArgs, BitcoinGetUtxosArgs,
-    BitcoinSendTransactionArgs, CanisterIdRecord, ComputeInitialEcdsaDealingsArgs,
-    ECDSAPublicKeyArgs, EcdsaKeyId, InstallCodeArgs, Method as Ic00Method, Payload,
-    ProvisionalTopUpCanisterArgs, SetControllerArgs, SignWithECDSAArgs, UpdateSettingsArgs,
+    BitcoinGetBalanceArgs, BitcoinGetBlockHeadersArgs, BitcoinGetCurrentFeePercentilesArgs,
+    BitcoinGetUtxosArgs, BitcoinSendTransactionArgs, CanisterIdRecord, CanisterSnapshotArgs,
+    ComputeInitialEcdsaDealingsArgs, ECDSAPublicKeyArgs, EcdsaKeyId, FetchCanisterLogsRequest,
+    InstallCodeArgs, Method as Ic00Method, Payload, ProvisionalTopUpCanisterArgs,
+    SetControllerArgs, SignWithECDSAArgs, TakeCanisterSnapshotArgs, UpdateSettingsArgs,
 };
 use ic_replicated_state::NetworkTopology;
 
@@ -92,7 +93,8 @@ pub(super) fn resolve_destination(
         | Ok(Ic00Method::StopCanister)
         | Ok(Ic00Method::DeleteCanister)
         | Ok(Ic00Method::UninstallCode)
-        | Ok(Ic00Method::DepositCycles) => {
+        | Ok(Ic00Method::DepositCycles)
+        | Ok(Ic00Method::ListCanisterSnapshots) => {
             let args = Decode!(payload, CanisterIdRecord)?;
             let canister_id = args.get_canister_id();
             network_topology
@@ -103,6 +105,45 @@ pub(super) fn resolve_destination(
                     ResolveDestinationError::SubnetNotFound(canister_id, method.unwrap())
                 })
         }
+        Ok(Ic00Method::TakeCanisterSnapshot) => {
+            let args = TakeCanisterSnapshotArgs::decode(payload)?;
+            let canister_id = args.get_canister_id();
+            network_topology
+                .routing_table
+                .route(canister_id.get())
+                .map(|subnet_id| subnet_id.get())
+                .ok_or_else(|| {
+                    ResolveDestinationError::SubnetNotFound(
+                        canister_id,
+                        Ic00Method::TakeCanisterSnapshot,
+                    )
+                })
+        }
+        Ok(Ic00Method::LoadCanisterSnapshot) | Ok(Ic00Method::DeleteCanisterSnapshot) => {
+            let args = CanisterSnapshotArgs::decode(payload)?;
+            let canister_id = args.get_canister_id();
+            network_topology
+                .routing_table
+                .route(canister_id.get())
+                .map(|subnet_id| subnet_id.get())
+                .ok_or_else(|| {
+                    ResolveDestinationError::SubnetNotFound(canister_id, method.unwrap())
+                })
+        }
+        Ok(Ic00Method::FetchCanisterLogs) => {
+            let args = FetchCanisterLogsRequest::decode(payload)?;
+            let canister_id = args.get_canister_id();
+            network_topology
+                .routing_table
+                .route(canister_id.get())
+                .map(|subnet_id| subnet_id.get())
+                .ok_or({
+                    ResolveDestinationError::SubnetNotFound(
+                        canister_id,
+                        Ic00Method::FetchCanisterLogs,
+                    )
+                })
+        }
         Ok(Ic00Method::ProvisionalTopUpCanister) => {
             let args = ProvisionalTopUpCanisterArgs::decode(payload)?;
             let canister_id = args.get_canister_id();
@@ -150,6 +191,14 @@ pub(super) fn resolve_destination(
                 own_subnet,
             ))
         }
+        Ok(Ic00Method::BitcoinGetBlockHeaders) => {
+            let args = Decode!(payload, BitcoinGetBlockHeadersArgs)?;
+            Ok(route_bitcoin_message(
+                args.network,
+                network_topology,
+                own_subnet,
+            ))
+        }
         Ok(Ic00Method::ECDSAPublicKey) => {
             let key_id = Decode!(payload, ECDSAPublicKeyArgs)?.key_id;
             route_ecdsa_message(
@@ -6,7 +6,7 @@ use ic_interfaces::execution_environment::{
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::PageIndex;
 use ic_sys::PageBytes;
-use ic_types::{Cycles, NumBytes, NumInstructions, NumPages, Time};
+use ic_types::{canister_log::CanisterLog, Cycles, NumBytes, NumInstructions, NumPages, Time};
 
 const MESSAGE_UNIMPLEMENTED: &str =
     "Empty System API should not be called. Only used by the embedder to create an ExecutionState instance";
@@ -28,6 +28,18 @@ impl SystemApi for SystemApiEmpty {
     fn get_execution_error(&self) -> Option<&HypervisorError> {
         unimplemented!("{}", MESSAGE_UNIMPLEMENTED)
     }
+    fn append_canister_log(&mut self, _content: Vec<u8>) {
+        unimplemented!("{}", MESSAGE_UNIMPLEMENTED)
+    }
+    fn save_log_message(&mut self, _src: u32, _size: u32, _heap: &[u8]) {
+        unimplemented!("{}", MESSAGE_UNIMPLEMENTED)
+    }
+    fn take_canister_log(&mut self) -> CanisterLog {
+        unimplemented!("{}", MESSAGE_UNIMPLEMENTED)
+    }
+    fn data_certificate_accessed(&self) -> bool {
+        unimplemented!("{}", MESSAGE_UNIMPLEMENTED)
+    }
     fn get_num_instructions_from_bytes(&self, _num_bytes: NumBytes) -> NumInstructions {
         unimplemented!("{}", MESSAGE_UNIMPLEMENTED)
     }
@@ -127,7 +139,7 @@ impl SystemApi for SystemApiEmpty {
     ) -> HypervisorResult<()> {
         unimplemented!("{}", MESSAGE_UNIMPLEMENTED)
     }
-    fn ic0_debug_print(&self, _: u32, _: u32, _: &[u8]) -> HypervisorResult<()> {
+    fn ic0_debug_print(&mut self, _: u32, _: u32, _: &[u8]) -> HypervisorResult<()> {
         unimplemented!("{}", MESSAGE_UNIMPLEMENTED)
     }
     fn ic0_trap(&self, _: u32, _: u32, _: &[u8]) -> HypervisorResult<()> {
@@ -255,14 +267,14 @@ impl SystemApi for SystemApiEmpty {
     fn ic0_certified_data_set(&mut self, _: u32, _: u32, _: &[u8]) -> HypervisorResult<()> {
         unimplemented!("{}", MESSAGE_UNIMPLEMENTED)
     }
-    fn ic0_data_certificate_present(&self) -> HypervisorResult<i32> {
+    fn ic0_data_certificate_present(&mut self) -> HypervisorResult<i32> {
         unimplemented!("{}", MESSAGE_UNIMPLEMENTED)
     }
-    fn ic0_data_certificate_size(&self) -> HypervisorResult<i32> {
+    fn ic0_data_certificate_size(&mut self) -> HypervisorResult<i32> {
         unimplemented!("{}", MESSAGE_UNIMPLEMENTED)
     }
     fn ic0_data_certificate_copy(
-        &self,
+        &mut self,
         _: u32,
         _: u32,
         _: u32,
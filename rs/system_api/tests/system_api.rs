@@ -1354,7 +1354,7 @@ fn certified_data_set() {
 fn data_certificate_copy() {
     let cycles_account_manager = CyclesAccountManagerBuilder::new().build();
     let system_state = SystemStateBuilder::default().build();
-    let api = get_system_api(
+    let mut api = get_system_api(
         ApiType::replicated_query(
             mock_time(),
             vec![],
@@ -1365,6 +1365,7 @@ fn data_certificate_copy() {
         cycles_account_manager,
     );
     let mut heap = vec![0; 10];
+    assert!(!api.data_certificate_accessed());
 
     // Copying with out of bounds offset + size fails.
     assert!(api.ic0_data_certificate_copy(0, 0, 10, &mut heap).is_err());
@@ -1381,6 +1382,7 @@ fn data_certificate_copy() {
     // Copying part of the data certificate.
     api.ic0_data_certificate_copy(6, 2, 4, &mut heap).unwrap();
     assert_eq!(heap, vec![1, 2, 3, 4, 5, 6, 3, 4, 5, 6]);
+    assert!(api.data_certificate_accessed());
 }
 
 #[test]
@@ -26,14 +26,19 @@ pub fn get_pair(private_key: Option<&str>) -> ic_canister_client_sender::Ed25519
     }
 }
 
+// A keypair deterministically derived from `seed`. Seed 1 yields
+// `TEST_IDENTITY_KEYPAIR`.
+pub fn test_identity_keypair(seed: u64) -> ic_canister_client_sender::Ed25519KeyPair {
+    let mut rng = ChaChaRng::seed_from_u64(seed);
+    ic_canister_client_sender::Ed25519KeyPair::generate(&mut rng)
+}
+
 lazy_static! {
     // A keypair meant to be used in various test setups, including
     // but (not limited) to scenario tests, end-to-end tests and the
     // workload generator.
-    pub static ref TEST_IDENTITY_KEYPAIR: ic_canister_client_sender::Ed25519KeyPair = {
-        let mut rng = ChaChaRng::seed_from_u64(1_u64);
-        ic_canister_client_sender::Ed25519KeyPair::generate(&mut rng)
-    };
+    pub static ref TEST_IDENTITY_KEYPAIR: ic_canister_client_sender::Ed25519KeyPair =
+        test_identity_keypair(1);
 
     // a dedicated identity for when we use --principal-id in the
     // workload generator
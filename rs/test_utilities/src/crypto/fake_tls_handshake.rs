@@ -1,4 +1,5 @@
 use async_trait::async_trait;
+use ic_crypto_tls_interfaces::rustls::{Certificate, ClientConfig, ServerConfig};
 use ic_crypto_tls_interfaces::{
     AllowedClients, AuthenticatedPeer, TlsClientHandshakeError, TlsHandshake,
     TlsServerHandshakeError, TlsStream,
@@ -49,4 +50,23 @@ impl TlsHandshake for FakeTlsHandshake {
     ) -> Result<Box<dyn TlsStream>, TlsClientHandshakeError> {
         unimplemented!()
     }
+
+    fn quic_server_config(&self) -> Result<ServerConfig, TlsServerHandshakeError> {
+        unimplemented!()
+    }
+
+    fn quic_authenticated_peer(
+        &self,
+        _peer_certificates: &[Certificate],
+    ) -> Result<AuthenticatedPeer, TlsServerHandshakeError> {
+        unimplemented!()
+    }
+
+    fn quic_client_config(
+        &self,
+        _server: NodeId,
+        _registry_version: RegistryVersion,
+    ) -> Result<ClientConfig, TlsClientHandshakeError> {
+        unimplemented!()
+    }
 }
@@ -5,9 +5,10 @@ use ic_interfaces_certified_stream_store::{
     CertifiedStreamStore, DecodeStreamError, EncodeStreamError,
 };
 use ic_interfaces_state_manager::{
-    CertificationMask, CertificationScope, Labeled, PermanentStateHashError::*, StateHashError,
-    StateManager, StateManagerError, StateManagerResult, StateReader, TransientStateHashError::*,
-    CERT_ANY, CERT_CERTIFIED, CERT_UNCERTIFIED,
+    CertificationMask, CertificationScope, CertifiedHeightReceiver, Labeled,
+    PermanentStateHashError::*, StateHashError, StateManager, StateManagerError,
+    StateManagerResult, StateReader, TransientStateHashError::*, CERT_ANY, CERT_CERTIFIED,
+    CERT_UNCERTIFIED,
 };
 use ic_interfaces_state_manager_mocks::MockStateManager;
 use ic_registry_subnet_type::SubnetType;
@@ -54,6 +55,7 @@ impl Snapshot {
 pub struct FakeStateManager {
     states: Arc<RwLock<Vec<Snapshot>>>,
     tip: Arc<RwLock<Option<(Height, ReplicatedState)>>>,
+    certified_height_sender: Arc<tokio::sync::watch::Sender<Height>>,
     _tempdir: Arc<tempfile::TempDir>,
     /// Size 1 by default (no op).
     pub encode_certified_stream_slice_barrier: Arc<RwLock<Barrier>>,
@@ -85,6 +87,7 @@ impl FakeStateManager {
                 height,
                 ReplicatedState::new(subnet_test_id(1), SubnetType::Application),
             )))),
+            certified_height_sender: Arc::new(tokio::sync::watch::channel(height).0),
             _tempdir: Arc::new(tmpdir),
             encode_certified_stream_slice_barrier: Arc::new(RwLock::new(Barrier::new(1))),
         }
@@ -188,6 +191,9 @@ impl StateManager for FakeStateManager {
             .find(|s| s.height == certification.height && s.certification.is_none())
         {
             snapshot.certification = Some(certification);
+            drop(snapshots);
+            self.certified_height_sender
+                .send_replace(self.latest_certified_height());
         }
     }
 
@@ -270,6 +276,10 @@ impl StateReader for FakeStateManager {
             .unwrap_or_else(|| Height::from(0))
     }
 
+    fn subscribe_certified_height(&self) -> CertifiedHeightReceiver {
+        self.certified_height_sender.subscribe()
+    }
+
     fn get_latest_state(&self) -> Labeled<Arc<Self::State>> {
         self.states
             .read()
@@ -589,6 +599,10 @@ impl StateReader for RefMockStateManager {
         self.mock.read().unwrap().latest_certified_height()
     }
 
+    fn subscribe_certified_height(&self) -> CertifiedHeightReceiver {
+        self.mock.read().unwrap().subscribe_certified_height()
+    }
+
     fn get_latest_state(&self) -> Labeled<Arc<Self::State>> {
         self.mock.read().unwrap().get_latest_state()
     }
@@ -141,6 +141,7 @@ pub fn test(env: TestEnv) {
         disk_threshold_warn: 75,
         slack_token: "NO_TOKEN_IN_TESTING".to_string(),
         subnets: vec![subnet],
+        content_store: None,
     };
     let config_str =
         serde_json::to_string(&config).expect("Config structure can't be converted to json");
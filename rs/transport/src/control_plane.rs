@@ -38,14 +38,14 @@ enum TransportTlsHandshakeError {
 }
 
 /// Time to wait before retrying an unsuccessful connection attempt
-const CONNECT_RETRY_SECONDS: u64 = 3;
+pub(crate) const CONNECT_RETRY_SECONDS: u64 = 3;
 
 /// Time to wait for the TLS handshake (for both client/server sides)
-const TLS_HANDSHAKE_TIMEOUT_SECONDS: u64 = 30;
+pub(crate) const TLS_HANDSHAKE_TIMEOUT_SECONDS: u64 = 30;
 
-const CONNECT_TASK_NAME: &str = "connect";
-const ACCEPT_TASK_NAME: &str = "accept";
-const TRANSITION_FROM_ACCEPT_TASK_NAME: &str = "transition_from_accept";
+pub(crate) const CONNECT_TASK_NAME: &str = "connect";
+pub(crate) const ACCEPT_TASK_NAME: &str = "accept";
+pub(crate) const TRANSITION_FROM_ACCEPT_TASK_NAME: &str = "transition_from_accept";
 
 /// Implementation for the transport control plane
 impl TransportImpl {
@@ -526,7 +526,7 @@ async fn connect_to_server(
 }
 
 /// Returns our role wrt the peer connection
-fn connection_role(my_id: &NodeId, peer: &NodeId) -> ConnectionRole {
+pub(crate) fn connection_role(my_id: &NodeId, peer: &NodeId) -> ConnectionRole {
     assert!(*my_id != *peer);
     if *my_id > *peer {
         ConnectionRole::Server
@@ -0,0 +1,493 @@
+//! Control plane - QUIC transport connection management.
+//!
+//! A node has a single QUIC endpoint, bound to the transport's listening
+//! port. The endpoint accepts the connections from the peers we are the server
+//! for, and establishes the connections to the peers we are the client for.
+//! The roles are assigned as in the TCP transport. Both sides authenticate
+//! with their node TLS certificates, see `TlsHandshake::quic_server_config`
+//! and `TlsHandshake::quic_client_config`. The component also manages the
+//! re-establishment of severed connections.
+
+use crate::{
+    control_plane::{
+        connection_role, ACCEPT_TASK_NAME, CONNECT_RETRY_SECONDS, CONNECT_TASK_NAME,
+        TLS_HANDSHAKE_TIMEOUT_SECONDS, TRANSITION_FROM_ACCEPT_TASK_NAME,
+    },
+    data_plane_quic::{create_connected_state, spawn_write_task},
+    metrics::{IntGaugeResource, QUIC_CONNECTION_FLOW_TAG, QUIC_TRANSPORT_API, STATUS_SUCCESS},
+    types::{
+        Connecting, ConnectionRole, ConnectionState, PeerStateQuic, QueueSize, SendQueue,
+        ServerPortState, TransportImplQuic,
+    },
+    utils::{get_peer_label, SendQueueImpl},
+};
+use futures::StreamExt;
+use ic_base_types::{NodeId, RegistryVersion};
+use ic_crypto_tls_interfaces::AuthenticatedPeer;
+use ic_interfaces_transport::{
+    TransportChannelId, TransportError, TransportEvent, TransportEventHandler,
+};
+use ic_logger::{info, warn};
+use quinn::{ClientConfig, Endpoint, Incoming, NewConnection, ServerConfig, VarInt};
+use std::{
+    net::SocketAddr,
+    sync::{atomic::Ordering, Arc},
+    time::Duration,
+};
+use strum::AsRefStr;
+use tokio::{sync::RwLock, task::JoinHandle, time::sleep};
+use tower::Service;
+
+#[derive(Debug, AsRefStr)]
+#[strum(serialize_all = "snake_case")]
+enum QuicHandshakeError {
+    DeadlineExceeded,
+    Connection(String),
+    Crypto(String),
+    NotAllowed(NodeId),
+}
+
+/// Interval of the QUIC keep-alive packets, which take the place of the
+/// heartbeats of the TCP transport
+const KEEP_ALIVE_INTERVAL_MS: u64 = 200;
+
+/// Time without packets from the peer after which a connection is closed
+const IDLE_TIMEOUT_MS: u64 = 5000;
+
+/// The server name of the handshake. Servers are authenticated by their
+/// node ID, so the name is not verified.
+const SERVER_NAME: &str = "domain.is-irrelevant-as-hostname-verification-is.disabled";
+
+/// The QUIC application error code used when closing a connection
+const CLOSE_ERROR_CODE: u32 = 0;
+
+/// Implementation for the QUIC transport control plane
+impl TransportImplQuic {
+    /// Stops connection to a peer
+    pub(crate) fn stop_peer_connection(&self, peer_id: &NodeId) {
+        self.allowed_clients.blocking_write().remove(peer_id);
+        self.peer_map.blocking_write().remove(peer_id);
+    }
+
+    /// Starts connection to a peer and initializes the corresponding data
+    /// structures and tasks
+    pub(crate) fn start_peer_connection(
+        &self,
+        peer_id: &NodeId,
+        peer_addr: SocketAddr,
+        registry_version: RegistryVersion,
+    ) -> Result<(), TransportError> {
+        let role = connection_role(&self.node_id, peer_id);
+        // If we are the server, we should add the peer to the allowed_clients.
+        if role == ConnectionRole::Server {
+            self.allowed_clients.blocking_write().insert(*peer_id);
+        }
+        *self.registry_version.blocking_write() = registry_version;
+        let mut peer_map = self.peer_map.blocking_write();
+        if peer_map.get(peer_id).is_some() {
+            return Err(TransportError::AlreadyExists);
+        }
+
+        let peer_label = get_peer_label(&peer_addr.ip().to_string(), peer_id);
+        let connection_state = if role == ConnectionRole::Server {
+            ConnectionState::Listening
+        } else {
+            let connecting_task = self.spawn_connect_task(*peer_id, peer_addr);
+            ConnectionState::Connecting(Connecting {
+                peer_addr,
+                connecting_task,
+            })
+        };
+        let peer_state = PeerStateQuic::new(
+            self.log.clone(),
+            peer_label,
+            connection_state,
+            self.control_plane_metrics.clone(),
+        );
+        peer_map.insert(*peer_id, RwLock::new(peer_state));
+        Ok(())
+    }
+
+    /// Returns the send queue of a flow with the peer, creating the queue
+    /// when the flow is first used. If the peer is connected, the write task
+    /// of the new flow is started as well.
+    pub(crate) fn get_or_create_send_queue<'a>(
+        &self,
+        peer_id: &NodeId,
+        peer_state: &'a mut PeerStateQuic,
+        channel_id: TransportChannelId,
+    ) -> &'a (dyn SendQueue + Send + Sync) {
+        if !peer_state.send_queues.contains_key(&channel_id) {
+            let mut send_queue = Box::new(SendQueueImpl::new(
+                peer_state.peer_label.clone(),
+                channel_id,
+                QueueSize::from(self.config.send_queue_size),
+                self.send_queue_metrics.clone(),
+            ));
+            if let Some(connected) = peer_state.get_connected_mut() {
+                let write_task = spawn_write_task(
+                    *peer_id,
+                    connected.connection_id,
+                    channel_id,
+                    send_queue.get_reader(),
+                    connected.connection.clone(),
+                    self.data_plane_metrics.clone(),
+                    self.weak_self.read().unwrap().clone(),
+                    self.rt_handle.clone(),
+                );
+                connected.write_tasks.insert(channel_id, write_task);
+            }
+            peer_state.send_queues.insert(channel_id, send_queue);
+        }
+        peer_state.send_queues[&channel_id].as_ref()
+    }
+
+    /// Starts the async task to accept the incoming connections in server mode.
+    fn spawn_accept_task(&self, mut incoming: Incoming) -> JoinHandle<()> {
+        let weak_self = self.weak_self.read().unwrap().clone();
+        let rt_handle = self.rt_handle.clone();
+        let async_tasks_gauge_vec = self.control_plane_metrics.async_tasks.clone();
+        self.rt_handle.spawn(async move {
+            let task_gauge = async_tasks_gauge_vec.with_label_values(&[ACCEPT_TASK_NAME]);
+            let _gauge_guard = IntGaugeResource::new(task_gauge);
+            while let Some(connecting) = incoming.next().await {
+                // If the TransportImplQuic has been deleted, abort.
+                let arc_self = match weak_self.upgrade() {
+                    Some(arc_self) => arc_self,
+                    _ => return,
+                };
+                rt_handle.spawn(async move {
+                    let task_gauge = arc_self
+                        .control_plane_metrics
+                        .async_tasks
+                        .with_label_values(&[TRANSITION_FROM_ACCEPT_TASK_NAME]);
+                    let _gauge_guard = IntGaugeResource::new(task_gauge);
+                    match arc_self.quic_server_handshake(connecting).await {
+                        Ok((peer_id, new_connection)) => {
+                            arc_self
+                                .control_plane_metrics
+                                .tls_handshakes
+                                .with_label_values(&[
+                                    ConnectionRole::Server.as_ref(),
+                                    STATUS_SUCCESS,
+                                ])
+                                .inc();
+                            arc_self
+                                .on_connect(peer_id, ConnectionRole::Server, new_connection)
+                                .await;
+                        }
+                        Err(err) => {
+                            arc_self
+                                .control_plane_metrics
+                                .tls_handshakes
+                                .with_label_values(&[ConnectionRole::Server.as_ref(), err.as_ref()])
+                                .inc();
+                            warn!(
+                                arc_self.log,
+                                "ControlPlaneQuic::spawn_accept_task(): quic_server_handshake failed: error = {:?}",
+                                err,
+                            );
+                        }
+                    }
+                });
+            }
+        })
+    }
+
+    /// Spawn a task that tries to connect to a peer (forever, or until
+    /// connection is established or peer is removed)
+    fn spawn_connect_task(&self, peer_id: NodeId, peer_addr: SocketAddr) -> JoinHandle<()> {
+        let weak_self = self.weak_self.read().unwrap().clone();
+        let async_tasks_gauge_vec = self.control_plane_metrics.async_tasks.clone();
+        self.rt_handle.spawn(async move {
+            let gauge = async_tasks_gauge_vec.with_label_values(&[CONNECT_TASK_NAME]);
+            let _raii_gauge_vec = IntGaugeResource::new(gauge);
+
+            // Loop till connection is established
+            let mut retries: u32 = 0;
+            loop {
+                retries += 1;
+                // If the TransportImplQuic has been deleted, abort.
+                let arc_self = match weak_self.upgrade() {
+                    Some(arc_self) => arc_self,
+                    _ => return,
+                };
+                // The endpoint is set up when the transport-client is initialized.
+                let endpoint = arc_self.endpoint.read().unwrap().clone();
+                if let Some(endpoint) = endpoint {
+                    match arc_self
+                        .quic_client_handshake(&endpoint, peer_id, peer_addr)
+                        .await
+                    {
+                        Ok(new_connection) => {
+                            arc_self
+                                .control_plane_metrics
+                                .tls_handshakes
+                                .with_label_values(&[
+                                    ConnectionRole::Client.as_ref(),
+                                    STATUS_SUCCESS,
+                                ])
+                                .inc();
+                            if arc_self
+                                .on_connect(peer_id, ConnectionRole::Client, new_connection)
+                                .await
+                            {
+                                // Stop this task since we successfully spawned the data plane tasks
+                                return;
+                            }
+                        }
+                        Err(err) => {
+                            arc_self
+                                .control_plane_metrics
+                                .tls_handshakes
+                                .with_label_values(&[ConnectionRole::Client.as_ref(), err.as_ref()])
+                                .inc();
+                            warn!(
+                                arc_self.log,
+                                "ControlPlaneQuic::spawn_connect_task(): quic_client_handshake failed: error = {:?}, \
+                                peer = {:?}/{:?}, retries = {}",
+                                err,
+                                peer_id,
+                                peer_addr,
+                                retries,
+                            );
+                        }
+                    }
+                }
+                sleep(Duration::from_secs(CONNECT_RETRY_SECONDS)).await;
+            }
+        })
+    }
+
+    /// Hands an established connection over to the data plane. Returns false
+    /// if the connection was not needed and has been closed.
+    async fn on_connect(
+        &self,
+        peer_id: NodeId,
+        role: ConnectionRole,
+        new_connection: NewConnection,
+    ) -> bool {
+        let NewConnection {
+            connection,
+            uni_streams,
+            ..
+        } = new_connection;
+        let peer_map = self.peer_map.read().await;
+        let peer_state_mu = match peer_map.get(&peer_id) {
+            Some(peer_state) => peer_state,
+            None => {
+                connection.close(VarInt::from_u32(CLOSE_ERROR_CODE), b"unknown peer");
+                return false;
+            }
+        };
+        let mut peer_state = peer_state_mu.write().await;
+        if peer_state.get_connected().is_some() {
+            // TODO: P2P-516
+            connection.close(VarInt::from_u32(CLOSE_ERROR_CODE), b"already connected");
+            return false;
+        }
+        let mut event_handler = match self.event_handler.lock().await.as_ref() {
+            Some(event_handler) => event_handler.clone(),
+            None => {
+                connection.close(VarInt::from_u32(CLOSE_ERROR_CODE), b"not ready");
+                return false;
+            }
+        };
+
+        let connection_id = self.next_connection_id.fetch_add(1, Ordering::Relaxed);
+        let peer_addr = connection.remote_address();
+        let connected_state = create_connected_state(
+            peer_id,
+            connection_id,
+            &mut peer_state.send_queues,
+            role,
+            peer_addr,
+            connection,
+            uni_streams,
+            event_handler.clone(),
+            self.data_plane_metrics.clone(),
+            self.weak_self.read().unwrap().clone(),
+            self.rt_handle.clone(),
+        );
+        event_handler
+            .call(TransportEvent::PeerUp(peer_id))
+            .await
+            .expect("Can't panic on infallible");
+        peer_state.update(ConnectionState::ConnectedQuic(connected_state));
+        true
+    }
+
+    /// Retries to establish a connection, unless the failed connection has
+    /// already been replaced
+    pub(crate) async fn on_disconnect(&self, peer_id: NodeId, connection_id: u64) {
+        warn!(
+            self.log,
+            "ControlPlaneQuic::retry_connection(): node_id = {:?}, peer_id = {:?}, connection_id = {:?}",
+            self.node_id,
+            peer_id,
+            connection_id
+        );
+        let peer_map = self.peer_map.read().await;
+        let peer_state_mu = match peer_map.get(&peer_id) {
+            Some(peer_state) => peer_state,
+            None => return,
+        };
+        let mut peer_state = peer_state_mu.write().await;
+        let connected = match peer_state.get_connected() {
+            Some(connected) if connected.connection_id == connection_id => connected,
+            // Connection is already disconnected/reconnecting, skip reconnect processing
+            _ => return,
+        };
+        let mut event_handler = match self.event_handler.lock().await.as_ref() {
+            Some(event_handler) => event_handler.clone(),
+            None => return,
+        };
+        self.control_plane_metrics
+            .retry_connection
+            .with_label_values(&[
+                &peer_id.to_string(),
+                QUIC_CONNECTION_FLOW_TAG,
+                QUIC_TRANSPORT_API,
+            ])
+            .inc();
+
+        let peer_addr = connected.peer_addr;
+        let connection_state = if connected.role == ConnectionRole::Server {
+            // We are the server, wait for the peer to connect
+            info!(
+                self.log,
+                "ControlPlaneQuic::on_disconnect(): waiting for peer to reconnect: \
+                 node_id = {:?}, peer_id = {:?}",
+                self.node_id,
+                peer_id
+            );
+            ConnectionState::Listening
+        } else {
+            info!(
+                self.log,
+                "ControlPlaneQuic::on_disconnect(): spawning reconnect task: node = {:?}/{:?}, \
+                peer = {:?}/{:?}",
+                self.node_id,
+                self.node_ip,
+                peer_id,
+                peer_addr,
+            );
+            let connecting_task = self.spawn_connect_task(peer_id, peer_addr);
+            ConnectionState::Connecting(Connecting {
+                peer_addr,
+                connecting_task,
+            })
+        };
+        event_handler
+            .call(TransportEvent::PeerDown(peer_id))
+            .await
+            .expect("Can't panic on infallible");
+        peer_state.update(connection_state);
+    }
+
+    /// Completes the server side of a handshake, and verifies that the
+    /// authenticated peer is an allowed client
+    async fn quic_server_handshake(
+        &self,
+        connecting: quinn::Connecting,
+    ) -> Result<(NodeId, NewConnection), QuicHandshakeError> {
+        let new_connection = match tokio::time::timeout(
+            Duration::from_secs(TLS_HANDSHAKE_TIMEOUT_SECONDS),
+            connecting,
+        )
+        .await
+        {
+            Err(_) => Err(QuicHandshakeError::DeadlineExceeded),
+            Ok(Ok(new_connection)) => Ok(new_connection),
+            Ok(Err(err)) => Err(QuicHandshakeError::Connection(format!("{:?}", err))),
+        }?;
+        let connection = &new_connection.connection;
+        let peer_certificates: Vec<_> = connection
+            .authentication_data()
+            .peer_certificates
+            .map(|certificates| certificates.iter().cloned().collect())
+            .unwrap_or_default();
+        let peer_id = match self.crypto.quic_authenticated_peer(&peer_certificates) {
+            Ok(AuthenticatedPeer::Node(peer_id)) => peer_id,
+            Err(err) => {
+                connection.close(VarInt::from_u32(CLOSE_ERROR_CODE), b"unauthenticated");
+                return Err(QuicHandshakeError::Crypto(format!("{:?}", err)));
+            }
+        };
+        if !self.allowed_clients.read().await.contains(&peer_id) {
+            connection.close(VarInt::from_u32(CLOSE_ERROR_CODE), b"not allowed");
+            return Err(QuicHandshakeError::NotAllowed(peer_id));
+        }
+        Ok((peer_id, new_connection))
+    }
+
+    /// Performs the client side of a handshake with the peer
+    async fn quic_client_handshake(
+        &self,
+        endpoint: &Endpoint,
+        peer_id: NodeId,
+        peer_addr: SocketAddr,
+    ) -> Result<NewConnection, QuicHandshakeError> {
+        let registry_version = *self.registry_version.read().await;
+        let tls_config = self
+            .crypto
+            .quic_client_config(peer_id, registry_version)
+            .map_err(|err| QuicHandshakeError::Crypto(format!("{:?}", err)))?;
+        let mut client_config = ClientConfig::default();
+        client_config.crypto = Arc::new(tls_config);
+        client_config.transport = Arc::new(quic_transport_config());
+        let connecting = endpoint
+            .connect_with(client_config, &peer_addr, SERVER_NAME)
+            .map_err(|err| QuicHandshakeError::Connection(format!("{:?}", err)))?;
+        match tokio::time::timeout(
+            Duration::from_secs(TLS_HANDSHAKE_TIMEOUT_SECONDS),
+            connecting,
+        )
+        .await
+        {
+            Err(_) => Err(QuicHandshakeError::DeadlineExceeded),
+            Ok(Ok(new_connection)) => Ok(new_connection),
+            Ok(Err(err)) => Err(QuicHandshakeError::Connection(format!("{:?}", err))),
+        }
+    }
+
+    /// Initializes a client: sets up the endpoint and starts accepting
+    /// connections
+    pub(crate) fn init_client(&self, event_handler: TransportEventHandler) {
+        // Binding the endpoint requires that we are within a tokio runtime context.
+        let _rt_enter_guard = self.rt_handle.enter();
+        let server_addr = SocketAddr::new(self.node_ip, self.config.listening_port);
+        let tls_config = self
+            .crypto
+            .quic_server_config()
+            .unwrap_or_else(|err| panic!("Failed to create the QUIC server config: {:?}", err));
+        let mut server_config = ServerConfig::default();
+        server_config.crypto = Arc::new(tls_config);
+        server_config.transport = Arc::new(quic_transport_config());
+
+        let mut endpoint_builder = Endpoint::builder();
+        endpoint_builder.listen(server_config);
+        let (endpoint, incoming) = endpoint_builder.bind(&server_addr).unwrap_or_else(|err| {
+            panic!(
+                "Failed to init endpoint: local_addr = {:?}, error = {:?}",
+                server_addr, err
+            )
+        });
+        *self.endpoint.write().unwrap() = Some(endpoint);
+
+        let accept_task = self.spawn_accept_task(incoming);
+        *self.accept_port.blocking_lock() = Some(ServerPortState { accept_task });
+        *self.event_handler.blocking_lock() = Some(event_handler);
+    }
+}
+
+/// Returns the QUIC transport parameters, used for both client and server
+/// connections
+fn quic_transport_config() -> quinn::TransportConfig {
+    let mut config = quinn::TransportConfig::default();
+    config.keep_alive_interval(Some(Duration::from_millis(KEEP_ALIVE_INTERVAL_MS)));
+    config
+        .max_idle_timeout(Some(Duration::from_millis(IDLE_TIMEOUT_MS)))
+        .expect("The idle timeout is within the bounds of QUIC");
+    config
+}
@@ -53,7 +53,7 @@ use tower::Service;
 // larger queue size.
 /// The number of bytes which will be attempted to dequeue and aggregate before
 /// sending to the network
-const DEQUEUE_BYTES: usize = 100 * 4 * 1490;
+pub(crate) const DEQUEUE_BYTES: usize = 100 * 4 * 1490;
 
 // Payloads are received/collected in units of SOCKET_READ_CHUNK_SIZE
 /// Size of read chunks
@@ -69,7 +69,7 @@ const READ_RESULT_HEARTBEAT: &str = "heartbeat";
 const READ_RESULT_MESSAGE: &str = "message";
 
 /// Create header bytes to send with payload.
-fn pack_header(payload: Option<&TransportPayload>, heartbeat: bool) -> Vec<u8> {
+pub(crate) fn pack_header(payload: Option<&TransportPayload>, heartbeat: bool) -> Vec<u8> {
     let mut result = Vec::<u8>::new();
     let mut header = TransportHeader {
         version: 0,
@@ -94,7 +94,7 @@ fn pack_header(payload: Option<&TransportPayload>, heartbeat: bool) -> Vec<u8> {
 }
 
 /// Read header bytes received in payload.
-fn unpack_header(data: Vec<u8>) -> TransportHeader {
+pub(crate) fn unpack_header(data: Vec<u8>) -> TransportHeader {
     let mut header = TransportHeader {
         version: 0,
         flags: 0,
@@ -0,0 +1,340 @@
+//! Data plane - QUIC transport data path
+//!
+//! Unlike the TCP data plane, which multiplexes all flows with a peer onto a
+//! single TLS stream, the QUIC data plane sends every flow on its own
+//! unidirectional stream of the peer connection. A stream starts with the
+//! (little endian) ID of its flow, followed by the messages of the flow, each
+//! prefixed with the transport header. As QUIC delivers and flow controls
+//! every stream separately, a lost packet or a slow receiver on one flow does
+//! not hold back the other flows.
+//!
+//! Per connection, there is one write task for each flow, and one task that
+//! accepts the streams opened by the peer and spawns a read task for each of
+//! them. Liveness of the connection is tracked with QUIC keep-alives instead
+//! of heartbeat messages. A connection error detected by a write task or the
+//! accept task is raised to the control plane via `on_disconnect()`.
+//!
+//! The data plane module implements data plane functionality for
+//! [`TransportImplQuic`](../types/struct.TransportImplQuic.html).
+
+use crate::{
+    data_plane::{pack_header, unpack_header, DEQUEUE_BYTES},
+    metrics::{DataPlaneMetrics, IntGaugeResource, QUIC_TRANSPORT_API},
+    types::{
+        ConnectedQuic, ConnectionRole, SendQueue, SendQueueReader, StreamReadError,
+        TransportImplQuic, TRANSPORT_HEADER_SIZE,
+    },
+};
+use futures::StreamExt;
+use ic_base_types::NodeId;
+use ic_interfaces_transport::{
+    TransportChannelId, TransportEvent, TransportEventHandler, TransportMessage, TransportPayload,
+};
+use ic_logger::{info, warn};
+use quinn::{Connection, IncomingUniStreams, RecvStream, SendStream};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Weak;
+use tokio::task::JoinHandle;
+use tokio::time::{Duration, Instant};
+use tower::Service;
+
+/// Time to wait for messages in the send queue before checking if the
+/// transport still exists
+const DEQUEUE_TIMEOUT_MS: u64 = 200;
+
+/// The size (in bytes) of the flow ID that starts a stream
+const CHANNEL_ID_SIZE: usize = 4;
+
+const READ_RESULT_ERROR: &str = "error";
+const READ_RESULT_MESSAGE: &str = "message";
+
+/// Per-flow send task. Opens the stream of the flow, then reads the requests
+/// from the send queue and writes them to the stream.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn spawn_write_task(
+    peer_id: NodeId,
+    connection_id: u64,
+    channel_id: TransportChannelId,
+    mut send_queue_reader: Box<dyn SendQueueReader + Send + Sync>,
+    connection: Connection,
+    data_plane_metrics: DataPlaneMetrics,
+    weak_self: Weak<TransportImplQuic>,
+    rt_handle: tokio::runtime::Handle,
+) -> JoinHandle<()> {
+    let channel_id_str = channel_id.to_string();
+    rt_handle.spawn(async move {
+        let _raii_gauge = IntGaugeResource::new(data_plane_metrics.write_tasks.clone());
+        let mut stream = match open_stream(&connection, channel_id).await {
+            Ok(stream) => stream,
+            Err(err) => {
+                if let Some(arc_self) = weak_self.upgrade() {
+                    warn!(
+                        arc_self.log,
+                        "DataPlaneQuic::spawn_write_task(): failed to open stream: peer_id = {:?}, channel_id = {:?}, error = {:?}",
+                        peer_id,
+                        channel_id,
+                        err,
+                    );
+                    arc_self.on_disconnect(peer_id, connection_id).await;
+                }
+                return;
+            }
+        };
+        loop {
+            // If the TransportImplQuic has been deleted, abort.
+            let arc_self = match weak_self.upgrade() {
+                Some(arc_self) => arc_self,
+                _ => return,
+            };
+            // Wait for the send requests
+            let dequeued = send_queue_reader
+                .dequeue(DEQUEUE_BYTES, Duration::from_millis(DEQUEUE_TIMEOUT_MS))
+                .await;
+            if dequeued.is_empty() {
+                continue;
+            }
+
+            let mut bytes_to_send = Vec::<u8>::new();
+            for mut payload in dequeued {
+                bytes_to_send.append(&mut pack_header(Some(&payload), false));
+                bytes_to_send.append(&mut payload.0);
+            }
+            // Send the payload
+            let start_time = Instant::now();
+            let message_len = bytes_to_send.len();
+            if let Err(err) = stream.write_all(&bytes_to_send).await {
+                warn!(
+                    arc_self.log,
+                    "DataPlaneQuic::spawn_write_task(): failed to write payload: peer_id = {:?}, channel_id = {:?}, error = {:?}",
+                    peer_id,
+                    channel_id,
+                    err,
+                );
+                arc_self.on_disconnect(peer_id, connection_id).await;
+                return;
+            }
+            arc_self
+                .data_plane_metrics
+                .send_message_duration
+                .with_label_values(&[&channel_id_str, QUIC_TRANSPORT_API])
+                .observe(start_time.elapsed().as_secs() as f64);
+            arc_self
+                .data_plane_metrics
+                .write_bytes_total
+                .with_label_values(&[&channel_id_str, QUIC_TRANSPORT_API])
+                .inc_by(message_len as u64);
+        }
+    })
+}
+
+/// Opens the stream of a flow and announces the flow to the peer.
+async fn open_stream(
+    connection: &Connection,
+    channel_id: TransportChannelId,
+) -> Result<SendStream, String> {
+    let mut stream = connection
+        .open_uni()
+        .await
+        .map_err(|err| format!("{:?}", err))?;
+    stream
+        .write_all(&channel_id.get().to_le_bytes())
+        .await
+        .map_err(|err| format!("{:?}", err))?;
+    Ok(stream)
+}
+
+/// Per-connection task. Accepts the streams opened by the peer and spawns a
+/// read task for each of them.
+fn spawn_accept_streams_task(
+    peer_id: NodeId,
+    connection_id: u64,
+    mut uni_streams: IncomingUniStreams,
+    event_handler: TransportEventHandler,
+    weak_self: Weak<TransportImplQuic>,
+    rt_handle: tokio::runtime::Handle,
+) -> JoinHandle<()> {
+    let read_rt_handle = rt_handle.clone();
+    rt_handle.spawn(async move {
+        while let Some(stream) = uni_streams.next().await {
+            // If the TransportImplQuic has been deleted, abort.
+            let arc_self = match weak_self.upgrade() {
+                Some(arc_self) => arc_self,
+                _ => return,
+            };
+            match stream {
+                Ok(stream) => {
+                    spawn_read_task(
+                        peer_id,
+                        stream,
+                        event_handler.clone(),
+                        arc_self.data_plane_metrics.clone(),
+                        weak_self.clone(),
+                        read_rt_handle.clone(),
+                    );
+                }
+                Err(err) => {
+                    info!(
+                        arc_self.log,
+                        "DataPlaneQuic::spawn_accept_streams_task(): connection failed: peer_id = {:?}, error = {:?}",
+                        peer_id,
+                        err,
+                    );
+                    break;
+                }
+            }
+        }
+        if let Some(arc_self) = weak_self.upgrade() {
+            arc_self.on_disconnect(peer_id, connection_id).await;
+        }
+    })
+}
+
+/// Per-flow receive task. Reads the messages from the stream and passes them
+/// to the client. Errors of the connection are detected and handled by the
+/// accept task of the connection.
+fn spawn_read_task(
+    peer_id: NodeId,
+    mut stream: RecvStream,
+    mut event_handler: TransportEventHandler,
+    data_plane_metrics: DataPlaneMetrics,
+    weak_self: Weak<TransportImplQuic>,
+    rt_handle: tokio::runtime::Handle,
+) {
+    rt_handle.spawn(async move {
+        let _raii_gauge = IntGaugeResource::new(data_plane_metrics.read_tasks.clone());
+        let mut channel_id_buffer = [0u8; CHANNEL_ID_SIZE];
+        if let Err(err) = read_into_buffer(&mut stream, &mut channel_id_buffer).await {
+            if let Some(arc_self) = weak_self.upgrade() {
+                info!(
+                    arc_self.log,
+                    "DataPlaneQuic::spawn_read_task(): failed to receive the flow of a stream: peer_id = {:?}, error = {:?}",
+                    peer_id,
+                    err,
+                );
+            }
+            return;
+        }
+        let channel_id = TransportChannelId::from(u32::from_le_bytes(channel_id_buffer));
+        let channel_id_str = channel_id.to_string();
+        loop {
+            // If the TransportImplQuic has been deleted, abort.
+            let arc_self = match weak_self.upgrade() {
+                Some(arc_self) => arc_self,
+                _ => return,
+            };
+            // Read the next message from the stream
+            let read_message_start = Instant::now();
+            match read_one_message(&mut stream).await {
+                Err(err) => {
+                    info!(
+                        arc_self.log,
+                        "DataPlaneQuic::spawn_read_task(): failed to receive a single message: peer_id = {:?}, channel_id = {:?}, error = {:?}",
+                        peer_id,
+                        channel_id,
+                        err,
+                    );
+                    arc_self.data_plane_metrics
+                        .read_message_duration
+                        .with_label_values(&[&channel_id_str, READ_RESULT_ERROR, QUIC_TRANSPORT_API])
+                        .observe(read_message_start.elapsed().as_secs() as f64);
+                    arc_self.data_plane_metrics
+                        .message_read_errors_total
+                        .with_label_values(&[&channel_id_str, err.into(), QUIC_TRANSPORT_API])
+                        .inc();
+                    return;
+                }
+                Ok(payload) => {
+                    arc_self.data_plane_metrics
+                        .read_message_duration
+                        .with_label_values(&[&channel_id_str, READ_RESULT_MESSAGE, QUIC_TRANSPORT_API])
+                        .observe(read_message_start.elapsed().as_secs() as f64);
+                    arc_self.data_plane_metrics
+                        .read_bytes_total
+                        .with_label_values(&[&channel_id_str, QUIC_TRANSPORT_API])
+                        .inc_by(payload.0.len() as u64);
+                    let _callback_start_time = arc_self.data_plane_metrics
+                        .event_handler_message_duration
+                        .with_label_values(&[&channel_id_str, QUIC_TRANSPORT_API]).start_timer();
+                    event_handler
+                        .call(TransportEvent::Message(TransportMessage {
+                            peer_id,
+                            payload,
+                        }))
+                        .await
+                        .expect("Can't panic on infallible");
+                }
+            }
+        }
+    });
+}
+
+/// Reads and returns the next message payload from the stream.
+async fn read_one_message(stream: &mut RecvStream) -> Result<TransportPayload, StreamReadError> {
+    let mut header_buffer = vec![0u8; TRANSPORT_HEADER_SIZE];
+    read_into_buffer(stream, &mut header_buffer).await?;
+    let header = unpack_header(header_buffer);
+
+    let mut payload_buffer = vec![0u8; header.payload_length as usize];
+    read_into_buffer(stream, &mut payload_buffer).await?;
+    Ok(TransportPayload(payload_buffer))
+}
+
+/// Reads the requested bytes from the stream
+async fn read_into_buffer(stream: &mut RecvStream, buf: &mut [u8]) -> Result<(), StreamReadError> {
+    stream
+        .read_exact(buf)
+        .await
+        .map_err(|err| StreamReadError::QuicReceiveStreamFailure(format!("{:?}", err)))
+}
+
+/// Handle connection setup. Starts the task accepting the streams of the peer,
+/// and a write task for each flow that has a send queue.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn create_connected_state(
+    peer_id: NodeId,
+    connection_id: u64,
+    send_queues: &mut HashMap<TransportChannelId, Box<dyn SendQueue + Send + Sync>>,
+    role: ConnectionRole,
+    peer_addr: SocketAddr,
+    connection: Connection,
+    uni_streams: IncomingUniStreams,
+    event_handler: TransportEventHandler,
+    data_plane_metrics: DataPlaneMetrics,
+    weak_self: Weak<TransportImplQuic>,
+    rt_handle: tokio::runtime::Handle,
+) -> ConnectedQuic {
+    let read_task = spawn_accept_streams_task(
+        peer_id,
+        connection_id,
+        uni_streams,
+        event_handler,
+        weak_self.clone(),
+        rt_handle.clone(),
+    );
+    let write_tasks = send_queues
+        .iter_mut()
+        .map(|(channel_id, send_queue)| {
+            let write_task = spawn_write_task(
+                peer_id,
+                connection_id,
+                *channel_id,
+                send_queue.get_reader(),
+                connection.clone(),
+                data_plane_metrics.clone(),
+                weak_self.clone(),
+                rt_handle.clone(),
+            );
+            (*channel_id, write_task)
+        })
+        .collect();
+
+    ConnectedQuic {
+        peer_addr,
+        connection_id,
+        connection,
+        read_task,
+        write_tasks,
+        role,
+    }
+}
@@ -34,10 +34,13 @@
 
 mod control_plane;
 mod control_plane_h2;
+mod control_plane_quic;
 mod data_plane;
 mod data_plane_h2;
+mod data_plane_quic;
 mod metrics;
 pub mod transport;
 pub mod transport_h2;
+pub mod transport_quic;
 mod types;
 mod utils;
@@ -8,6 +8,11 @@ pub(crate) const STATUS_ERROR: &str = "error";
 pub(crate) const LABEL_DETAIL: &str = "detail";
 pub(crate) const LABEL_CHANNEL_ID: &str = "channel_id";
 pub(crate) const TRANSPORT_API: &str = "transport_api";
+/// The `TRANSPORT_API` label value of the QUIC transport
+pub(crate) const QUIC_TRANSPORT_API: &str = "quic";
+/// The flow tag label value of per-peer metrics of the QUIC transport, as a
+/// QUIC connection is shared by all flows with a peer
+pub(crate) const QUIC_CONNECTION_FLOW_TAG: &str = "connection";
 
 /// This is intended to be used as RAII type that will increment the gauge
 /// at construction and decrease the gauge on Drop.
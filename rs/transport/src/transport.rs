@@ -67,9 +67,10 @@
 //! ```
 
 use crate::metrics::{ControlPlaneMetrics, DataPlaneMetrics, SendQueueMetrics};
+use crate::transport_quic::create_transport_quic;
 use crate::types::TransportImpl;
 use ic_base_types::{NodeId, RegistryVersion};
-use ic_config::transport::TransportConfig;
+use ic_config::transport::{TransportConfig, TransportProtocol};
 use ic_crypto_tls_interfaces::TlsHandshake;
 use ic_interfaces_transport::{
     Transport, TransportChannelId, TransportError, TransportEventHandler, TransportPayload,
@@ -123,7 +124,8 @@ impl TransportImpl {
     }
 }
 
-/// Returns the production implementation of the `Transport` interfaces.
+/// Returns the production implementation of the `Transport` interfaces,
+/// using the given `protocol`. `use_h2` only applies to the TCP transport.
 pub fn create_transport(
     node_id: NodeId,
     transport_config: TransportConfig,
@@ -132,18 +134,30 @@ pub fn create_transport(
     crypto: Arc<dyn TlsHandshake + Send + Sync>,
     rt_handle: Handle,
     log: ReplicaLogger,
+    protocol: TransportProtocol,
     use_h2: bool,
 ) -> Arc<dyn Transport> {
-    TransportImpl::new(
-        node_id,
-        transport_config,
-        registry_version,
-        metrics_registry,
-        crypto,
-        rt_handle,
-        log,
-        use_h2,
-    )
+    match protocol {
+        TransportProtocol::Tcp => TransportImpl::new(
+            node_id,
+            transport_config,
+            registry_version,
+            metrics_registry,
+            crypto,
+            rt_handle,
+            log,
+            use_h2,
+        ),
+        TransportProtocol::Quic => create_transport_quic(
+            node_id,
+            transport_config,
+            registry_version,
+            metrics_registry,
+            crypto,
+            rt_handle,
+            log,
+        ),
+    }
 }
 
 /// Trait implementation for
@@ -0,0 +1,185 @@
+//! QUIC transport client interface.
+//!
+//! An alternative to the TLS over TCP transport, selected for the nodes of a
+//! subnet by the `quic_transport` subnet feature in the registry.
+//!
+//! The TCP transport sends all flows with a peer over a single TLS stream, so
+//! a lost packet delays the messages of every flow until it is retransmitted
+//! (head-of-line blocking). The QUIC transport also uses a single connection
+//! per peer, but sends every flow on its own QUIC stream, which are
+//! retransmitted and flow controlled independently of each other.
+//!
+//! The connection set up follows the TCP transport: transport clients invoke
+//! start_connection(peer_id) to set up the connection with a valid peer. If
+//! we are the client, the control plane connects to the peer's QUIC endpoint.
+//! If we are the server, we wait for the peer to connect. Connections are
+//! authenticated with the node TLS certificates in the registry. Once a
+//! connection is established, it is handed to the data plane. If the data
+//! plane detects that the connection is broken, the control plane is notified
+//! via on_disconnect() and re-establishes the connection.
+//!
+//! The send data path has two hops:
+//!
+//! Transport client calls send(flow_id, message). The message is en-queued to
+//! the send queue of the flow, which is created when the flow is first used.
+//! The send task of the flow then dequeues the messages and writes them to
+//! the QUIC stream of the flow.
+//!
+//! The receive data path has one hop:
+//!
+//! The receive task of a stream reads complete messages from the stream and
+//! invokes the client's on_message(flow_id, message) callback.
+
+use crate::metrics::{ControlPlaneMetrics, DataPlaneMetrics, SendQueueMetrics};
+use crate::types::TransportImplQuic;
+use ic_base_types::{NodeId, RegistryVersion};
+use ic_config::transport::TransportConfig;
+use ic_crypto_tls_interfaces::TlsHandshake;
+use ic_interfaces_transport::{
+    Transport, TransportChannelId, TransportError, TransportEventHandler, TransportPayload,
+};
+use ic_logger::{info, ReplicaLogger};
+use ic_metrics::MetricsRegistry;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::sync::atomic::AtomicU64;
+use std::sync::{Arc, Weak};
+use tokio::{
+    runtime::Handle,
+    sync::{Mutex, RwLock},
+};
+
+impl TransportImplQuic {
+    /// Creates a new Transport instance
+    fn new(
+        node_id: NodeId,
+        config: TransportConfig,
+        registry_version: RegistryVersion,
+        metrics_registry: MetricsRegistry,
+        crypto: Arc<dyn TlsHandshake + Send + Sync>,
+        rt_handle: Handle,
+        log: ReplicaLogger,
+    ) -> Arc<Self> {
+        let node_ip = IpAddr::from_str(&config.node_ip)
+            .unwrap_or_else(|_| panic!("Invalid node IP: {}", &config.node_ip));
+        let arc = Arc::new(Self {
+            node_id,
+            node_ip,
+            config,
+            allowed_clients: Arc::new(RwLock::new(BTreeSet::<NodeId>::new())),
+            crypto,
+            registry_version: Arc::new(RwLock::new(registry_version)),
+            rt_handle,
+            data_plane_metrics: DataPlaneMetrics::new(metrics_registry.clone()),
+            control_plane_metrics: ControlPlaneMetrics::new(metrics_registry.clone()),
+            send_queue_metrics: SendQueueMetrics::new(metrics_registry),
+            log,
+            endpoint: std::sync::RwLock::new(None),
+            peer_map: tokio::sync::RwLock::new(HashMap::new()),
+            accept_port: Mutex::new(None),
+            event_handler: Mutex::new(None),
+            next_connection_id: AtomicU64::new(0),
+            weak_self: std::sync::RwLock::new(Weak::new()),
+        });
+        *arc.weak_self.write().unwrap() = Arc::downgrade(&arc);
+        arc
+    }
+}
+
+/// Returns the QUIC implementation of the `Transport` interfaces.
+pub fn create_transport_quic(
+    node_id: NodeId,
+    transport_config: TransportConfig,
+    registry_version: RegistryVersion,
+    metrics_registry: MetricsRegistry,
+    crypto: Arc<dyn TlsHandshake + Send + Sync>,
+    rt_handle: Handle,
+    log: ReplicaLogger,
+) -> Arc<dyn Transport> {
+    TransportImplQuic::new(
+        node_id,
+        transport_config,
+        registry_version,
+        metrics_registry,
+        crypto,
+        rt_handle,
+        log,
+    )
+}
+
+/// Trait implementation for
+/// [`Transport`](../../ic_interfaces/transport/trait.Transport.html).
+impl Transport for TransportImplQuic {
+    fn set_event_handler(&self, event_handler: TransportEventHandler) {
+        self.init_client(event_handler)
+    }
+
+    /// Mark the peer as valid neighbor, and set up the transport layer to
+    /// exchange messages with the peer. If the peer is the server, connect
+    /// to it. If the peer is the client, accept its connection.
+    fn start_connection(
+        &self,
+        peer_id: &NodeId,
+        peer_addr: SocketAddr,
+        registry_version: RegistryVersion,
+    ) -> Result<(), TransportError> {
+        info!(
+            self.log,
+            "TransportQuic::start_connection(): peer_id = {:?}", peer_id
+        );
+        self.start_peer_connection(peer_id, peer_addr, registry_version)
+    }
+
+    /// Remove the peer from the set of valid neighbors, and tear down the
+    /// queues and connection for the peer. Any messages in the Tx and Rx
+    /// queues for the peer will be discarded.
+    /// It is fine to call the function on non-existing connection(s).
+    fn stop_connection(&self, peer_id: &NodeId) {
+        info!(
+            self.log,
+            "TransportQuic::stop_connection(): peer_id = {:?}", peer_id,
+        );
+        self.stop_peer_connection(peer_id);
+    }
+
+    fn send(
+        &self,
+        peer_id: &NodeId,
+        channel_id: TransportChannelId,
+        message: TransportPayload,
+    ) -> Result<(), TransportError> {
+        let peer_map = self.peer_map.blocking_read();
+        let peer_state_mu = match peer_map.get(peer_id) {
+            Some(peer_state) => peer_state,
+            None => return Err(TransportError::NotFound),
+        };
+        {
+            let peer_state = peer_state_mu.blocking_read();
+            if let Some(send_queue) = peer_state.send_queues.get(&channel_id) {
+                return match send_queue.enqueue(message) {
+                    Some(unsent) => Err(TransportError::SendQueueFull(unsent)),
+                    None => Ok(()),
+                };
+            }
+        }
+        // The flow is used for the first time
+        let mut peer_state = peer_state_mu.blocking_write();
+        let send_queue = self.get_or_create_send_queue(peer_id, &mut peer_state, channel_id);
+        match send_queue.enqueue(message) {
+            Some(unsent) => Err(TransportError::SendQueueFull(unsent)),
+            None => Ok(()),
+        }
+    }
+
+    fn clear_send_queues(&self, peer_id: &NodeId) {
+        let mut peer_map = self.peer_map.blocking_write();
+        let peer_state = peer_map
+            .get_mut(peer_id)
+            .expect("Transport client not found");
+        for send_queue in peer_state.blocking_write().send_queues.values_mut() {
+            send_queue.clear();
+        }
+    }
+}
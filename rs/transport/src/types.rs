@@ -1,6 +1,8 @@
 //! Shared types internal to transport crate
 
-use crate::metrics::{ControlPlaneMetrics, DataPlaneMetrics, SendQueueMetrics};
+use crate::metrics::{
+    ControlPlaneMetrics, DataPlaneMetrics, SendQueueMetrics, QUIC_CONNECTION_FLOW_TAG,
+};
 use crate::utils::SendQueueImpl;
 use async_trait::async_trait;
 use bytes::Bytes;
@@ -18,6 +20,7 @@ use std::collections::{BTreeSet, HashMap};
 use std::fmt::{self, Debug, Formatter};
 use std::net::IpAddr;
 use std::net::SocketAddr;
+use std::sync::atomic::AtomicU64;
 use std::sync::{Arc, Weak};
 use strum::AsRefStr;
 use strum::IntoStaticStr;
@@ -158,6 +161,7 @@ pub enum StreamReadError {
     Failed(std::io::Error),
     TimeOut,
     H2ReceiveStreamFailure(String),
+    QuicReceiveStreamFailure(String),
 }
 
 // Wrapper around SendStream to ensure that we only send data if there is available capacity,
@@ -289,6 +293,49 @@ pub(crate) struct TransportImplH2 {
     pub weak_self: std::sync::RwLock<Weak<TransportImplH2>>,
 }
 
+pub(crate) struct TransportImplQuic {
+    /// The node ID of this replica
+    pub node_id: NodeId,
+    /// The IP address of this node
+    pub node_ip: IpAddr,
+    /// Configuration
+    pub config: TransportConfig,
+
+    /// The QUIC endpoint for both incoming and outgoing connections, set up
+    /// when the transport-client is initialized
+    pub endpoint: std::sync::RwLock<Option<quinn::Endpoint>>,
+    /// Port used to accept connections for this transport-client
+    pub accept_port: Mutex<Option<ServerPortState>>,
+    /// Mapping of peers to their corresponding state
+    pub peer_map: RwLock<HashMap<NodeId, RwLock<PeerStateQuic>>>,
+    /// Event handler to report back to the transport client
+    pub event_handler: Mutex<Option<TransportEventHandler>>,
+    /// Source of the IDs that tell apart successive connections to a peer
+    pub next_connection_id: AtomicU64,
+
+    // Crypto and data required for TLS handshakes
+    /// Clients that are allowed to connect to this node
+    pub allowed_clients: Arc<RwLock<BTreeSet<NodeId>>>,
+    /// The registry version that is used
+    pub registry_version: Arc<RwLock<RegistryVersion>>,
+    /// Reference to the crypto component
+    pub crypto: Arc<dyn TlsHandshake + Send + Sync>,
+
+    /// Data plane metrics
+    pub data_plane_metrics: DataPlaneMetrics,
+    /// Control plane metrics
+    pub control_plane_metrics: ControlPlaneMetrics,
+    /// Send queue metrics
+    pub send_queue_metrics: SendQueueMetrics,
+
+    /// The tokio runtime
+    pub rt_handle: Handle,
+    /// Logger
+    pub log: ReplicaLogger,
+    /// Guarded self weak-reference
+    pub weak_self: std::sync::RwLock<Weak<TransportImplQuic>>,
+}
+
 /// Our role in a connection
 #[derive(Debug, PartialEq, Eq, Copy, Clone, AsRefStr)]
 #[strum(serialize_all = "snake_case")]
@@ -481,6 +528,83 @@ impl Drop for PeerStateH2 {
     }
 }
 
+/// Per-peer state of the QUIC transport. Unlike `PeerState`, the connection
+/// is shared by all flows with the peer, and each flow has its own send queue.
+pub(crate) struct PeerStateQuic {
+    log: ReplicaLogger,
+    /// Peer label, used for metrics
+    pub peer_label: String,
+    /// Connection state
+    connection_state: ConnectionState,
+    /// The send queues of the flows, created when a flow is first used
+    pub send_queues: HashMap<TransportChannelId, Box<dyn SendQueue + Send + Sync>>,
+    /// Metrics
+    control_plane_metrics: ControlPlaneMetrics,
+}
+
+impl PeerStateQuic {
+    pub(crate) fn new(
+        log: ReplicaLogger,
+        peer_label: String,
+        connection_state: ConnectionState,
+        control_plane_metrics: ControlPlaneMetrics,
+    ) -> Self {
+        let ret = Self {
+            log,
+            peer_label,
+            connection_state,
+            send_queues: HashMap::new(),
+            control_plane_metrics,
+        };
+        ret.report_connection_state();
+        ret
+    }
+
+    /// Updates the state of the connection
+    pub(crate) fn update(&mut self, connection_state: ConnectionState) {
+        self.connection_state.update(connection_state);
+        self.report_connection_state();
+    }
+
+    /// Reports the state of the connection to metrics
+    fn report_connection_state(&self) {
+        self.control_plane_metrics
+            .flow_state
+            .with_label_values(&[&self.peer_label, QUIC_CONNECTION_FLOW_TAG])
+            .set(self.connection_state.idx());
+    }
+
+    pub(crate) fn get_connected(&self) -> Option<&ConnectedQuic> {
+        if let ConnectionState::ConnectedQuic(connected) = &self.connection_state {
+            return Some(connected);
+        }
+        None
+    }
+
+    pub(crate) fn get_connected_mut(&mut self) -> Option<&mut ConnectedQuic> {
+        if let ConnectionState::ConnectedQuic(connected) = &mut self.connection_state {
+            return Some(connected);
+        }
+        None
+    }
+}
+
+impl Drop for PeerStateQuic {
+    fn drop(&mut self) {
+        if self
+            .control_plane_metrics
+            .flow_state
+            .remove_label_values(&[&self.peer_label, QUIC_CONNECTION_FLOW_TAG])
+            .is_err()
+        {
+            warn!(
+                self.log,
+                "Transport:PeerStateQuic drop: Could not remove peer metric {:?}", self.peer_label
+            )
+        }
+    }
+}
+
 /// The connection state machine for a flow with a peer
 pub(crate) enum ConnectionState {
     /// We are the server, waiting for peer to connect
@@ -491,6 +615,8 @@ pub(crate) enum ConnectionState {
     Connected(Connected),
     /// Connection established (H2)
     ConnectedH2(ConnectedH2),
+    /// Connection established (QUIC)
+    ConnectedQuic(ConnectedQuic),
 }
 
 /// Info about a flow in ConnectionState::Connecting
@@ -529,6 +655,27 @@ pub(crate) struct ConnectedH2 {
     pub role: ConnectionRole,
 }
 
+/// Info about a QUIC connection in ConnectionState::ConnectedQuic
+pub(crate) struct ConnectedQuic {
+    /// Peer node
+    pub peer_addr: SocketAddr,
+
+    /// Tells this connection apart from earlier connections to the peer
+    pub connection_id: u64,
+
+    /// The connection, shared by all flows with the peer
+    pub connection: quinn::Connection,
+
+    /// The task accepting the streams opened by the peer
+    pub read_task: JoinHandle<()>,
+
+    /// The write task handles, one per flow
+    pub write_tasks: HashMap<TransportChannelId, JoinHandle<()>>,
+
+    /// Our role
+    pub role: ConnectionRole,
+}
+
 impl ConnectionState {
     /// Validates/updates the state transition
     fn update(&mut self, next_state: Self) {
@@ -558,6 +705,11 @@ impl ConnectionState {
                         valid = true;
                     }
                 }
+                if let Self::ConnectedQuic(s) = next_state {
+                    if s.role == ConnectionRole::Server {
+                        valid = true;
+                    }
+                }
             }
             Self::Connecting(_) => {
                 if let Self::Connected(s) = next_state {
@@ -570,6 +722,11 @@ impl ConnectionState {
                         valid = true;
                     }
                 }
+                if let Self::ConnectedQuic(s) = next_state {
+                    if s.role == ConnectionRole::Client {
+                        valid = true;
+                    }
+                }
             }
             Self::Connected(s) => match next_state {
                 Self::Listening => {
@@ -597,6 +754,19 @@ impl ConnectionState {
                 }
                 _ => (),
             },
+            Self::ConnectedQuic(s) => match next_state {
+                Self::Listening => {
+                    if s.role == ConnectionRole::Server {
+                        valid = true;
+                    }
+                }
+                Self::Connecting(_) => {
+                    if s.role == ConnectionRole::Client {
+                        valid = true;
+                    }
+                }
+                _ => (),
+            },
         }
         valid
     }
@@ -607,6 +777,7 @@ impl ConnectionState {
             Self::Connecting(_) => 2,
             Self::Connected(_) => 3,
             Self::ConnectedH2(_) => 4,
+            Self::ConnectedQuic(_) => 5,
         }
     }
 }
@@ -638,6 +809,13 @@ impl Debug for ConnectionState {
                     state.peer_addr, state.role
                 )
             }
+            Self::ConnectedQuic(state) => {
+                write!(
+                    f,
+                    "ConnectionState::ConnectedQuic(peer = {:?}, role = {:?})",
+                    state.peer_addr, state.role
+                )
+            }
         }
     }
 }
@@ -650,6 +828,14 @@ impl Drop for ConnectionState {
                 state.read_task.abort();
                 state.write_task.abort();
             }
+            Self::ConnectedQuic(state) => {
+                state.read_task.abort();
+                for write_task in state.write_tasks.values() {
+                    write_task.abort();
+                }
+                // Also ends the streams opened by the peer, and thus their read tasks
+                state.connection.close(0u32.into(), b"");
+            }
             _ => (),
         }
     }
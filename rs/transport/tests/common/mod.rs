@@ -1,5 +1,5 @@
 use ic_base_types::{NodeId, RegistryVersion};
-use ic_config::transport::TransportConfig;
+use ic_config::transport::{TransportConfig, TransportProtocol};
 use ic_crypto_temp_crypto::{NodeKeysToGenerate, TempCryptoComponent};
 use ic_crypto_tls_interfaces::TlsHandshake;
 use ic_interfaces_transport::Transport;
@@ -64,6 +64,7 @@ pub(crate) fn setup_test_peer<F>(
     registry_and_data: &mut RegistryAndDataProvider,
     mut crypto_factory: F,
     event_handler: TransportEventHandler,
+    protocol: TransportProtocol,
     use_h2: bool,
 ) -> (Arc<dyn Transport>, SocketAddr)
 where
@@ -84,6 +85,7 @@ where
         crypto,
         rt_handle,
         log,
+        protocol,
         use_h2,
     );
     let addr = SocketAddr::from_str(&format!("127.0.0.1:{}", port)).unwrap();
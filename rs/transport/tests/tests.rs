@@ -6,7 +6,7 @@ use common::{
     REG_V1,
 };
 use ic_base_types::{NodeId, RegistryVersion};
-use ic_config::transport::TransportConfig;
+use ic_config::transport::{TransportConfig, TransportProtocol};
 use ic_crypto_tls_interfaces::TlsHandshake;
 use ic_interfaces_transport::{
     Transport, TransportChannelId, TransportError, TransportEvent, TransportEventHandler,
@@ -35,11 +35,12 @@ const TRANSPORT_CHANNEL_ID: u32 = 1234;
 
 #[test]
 fn test_start_connection_between_two_peers() {
-    test_start_connection_between_two_peers_impl(false);
-    test_start_connection_between_two_peers_impl(true);
+    test_start_connection_between_two_peers_impl(TransportProtocol::Tcp, false);
+    test_start_connection_between_two_peers_impl(TransportProtocol::Tcp, true);
+    test_start_connection_between_two_peers_impl(TransportProtocol::Quic, false);
 }
 
-fn test_start_connection_between_two_peers_impl(use_h2: bool) {
+fn test_start_connection_between_two_peers_impl(protocol: TransportProtocol, use_h2: bool) {
     with_test_replica_logger(|logger| {
         let registry_version = REG_V1;
 
@@ -58,6 +59,7 @@ fn test_start_connection_between_two_peers_impl(use_h2: bool) {
             10,
             event_handler_1,
             event_handler_2,
+            protocol,
             use_h2,
         );
 
@@ -66,6 +68,83 @@ fn test_start_connection_between_two_peers_impl(use_h2: bool) {
     });
 }
 
+/*
+Verifies that a server only accepts a peer once it started a connection to it, i.e. once the peer
+is one of its allowed clients.
+- A (the client) starts a connection to B (the server), which B rejects.
+- B starts a connection to A, and accepts A when it retries.
+*/
+#[test]
+fn test_peer_not_in_allowed_clients_is_rejected() {
+    test_peer_not_in_allowed_clients_is_rejected_impl(TransportProtocol::Tcp, false);
+    test_peer_not_in_allowed_clients_is_rejected_impl(TransportProtocol::Tcp, true);
+    test_peer_not_in_allowed_clients_is_rejected_impl(TransportProtocol::Quic, false);
+}
+
+fn test_peer_not_in_allowed_clients_is_rejected_impl(protocol: TransportProtocol, use_h2: bool) {
+    with_test_replica_logger(|logger| {
+        let mut registry_and_data = RegistryAndDataProvider::new();
+        let rt = tokio::runtime::Runtime::new().unwrap();
+
+        let crypto_factory = |registry_and_data: &mut RegistryAndDataProvider, node_id: NodeId| {
+            Arc::new(temp_crypto_component_with_tls_keys_in_registry(
+                registry_and_data,
+                node_id,
+            )) as Arc<dyn TlsHandshake + Send + Sync>
+        };
+
+        // Only the events of the server are checked, as a QUIC client may see the server go up
+        // before the server rejects it.
+        let (peer_a_sender, _peer_a_receiver) = channel(1);
+        let peer_a_event_handler =
+            setup_message_ack_event_handler(rt.handle().clone(), peer_a_sender);
+        let (peer_a, peer_a_addr) = setup_test_peer(
+            logger.clone(),
+            rt.handle().clone(),
+            NODE_ID_1,
+            get_free_localhost_port().expect("Failed to get free localhost port"),
+            REG_V1,
+            &mut registry_and_data,
+            crypto_factory,
+            peer_a_event_handler,
+            protocol,
+            use_h2,
+        );
+
+        let (peer_b_sender, mut peer_b_receiver) = channel(1);
+        let peer_b_event_handler =
+            setup_peer_up_ack_event_handler(rt.handle().clone(), peer_b_sender);
+        let (peer_b, peer_b_addr) = setup_test_peer(
+            logger,
+            rt.handle().clone(),
+            NODE_ID_2,
+            get_free_localhost_port().expect("Failed to get free localhost port"),
+            REG_V1,
+            &mut registry_and_data,
+            crypto_factory,
+            peer_b_event_handler,
+            protocol,
+            use_h2,
+        );
+        registry_and_data.registry.update_to_latest_version();
+
+        peer_a
+            .start_connection(&NODE_ID_2, peer_b_addr, REG_V1)
+            .expect("start_connection");
+        assert!(rt
+            .block_on(tokio::time::timeout(
+                Duration::from_secs(5),
+                peer_b_receiver.recv()
+            ))
+            .is_err());
+
+        peer_b
+            .start_connection(&NODE_ID_1, peer_a_addr, REG_V1)
+            .expect("start_connection");
+        assert_eq!(peer_b_receiver.blocking_recv(), Some(true));
+    });
+}
+
 /*
 Verifies that transport suffers "head of line problem" when peer is slow to consume messages.
 - Peer A sends Peer B message, which will work fine.
@@ -118,13 +197,14 @@ Establish connection with 2 peers, A and B.  Send message from A->B and B->A and
 */
 #[test]
 fn test_basic_message_send() {
-    test_send_big_message_succeeds(false);
-    test_send_big_message_succeeds(true);
+    test_send_big_message_succeeds(TransportProtocol::Tcp, false);
+    test_send_big_message_succeeds(TransportProtocol::Tcp, true);
+    test_send_big_message_succeeds(TransportProtocol::Quic, false);
 }
 
 // StateSync may send chunks that are 30MB big so we want to make sure a message of this size can be sent and received
 // in both directions
-fn test_send_big_message_succeeds(use_h2: bool) {
+fn test_send_big_message_succeeds(protocol: TransportProtocol, use_h2: bool) {
     let registry_version = REG_V1;
     with_test_replica_logger(|logger| {
         let rt = tokio::runtime::Runtime::new().unwrap();
@@ -144,6 +224,7 @@ fn test_send_big_message_succeeds(use_h2: bool) {
             1,
             peer_a_event_handler,
             peer_b_event_handler,
+            protocol,
             use_h2,
         );
 
@@ -170,11 +251,12 @@ no messages are being sent. (In current implementation, this is ensured by heart
 */
 #[test]
 fn test_idle_connection_active() {
-    test_idle_connection_active_impl(false);
-    test_idle_connection_active_impl(true);
+    test_idle_connection_active_impl(TransportProtocol::Tcp, false);
+    test_idle_connection_active_impl(TransportProtocol::Tcp, true);
+    test_idle_connection_active_impl(TransportProtocol::Quic, false);
 }
 
-fn test_idle_connection_active_impl(use_h2: bool) {
+fn test_idle_connection_active_impl(protocol: TransportProtocol, use_h2: bool) {
     let registry_version = REG_V1;
     with_test_replica_logger(|logger| {
         let rt = tokio::runtime::Runtime::new().unwrap();
@@ -194,6 +276,7 @@ fn test_idle_connection_active_impl(use_h2: bool) {
             1,
             peer_a_event_handler,
             peer_b_event_handler,
+            protocol,
             use_h2,
         );
         std::thread::sleep(Duration::from_secs(20));
@@ -409,6 +492,7 @@ fn create_n_peers(
             &mut registry_and_data,
             crypto_factory,
             event_handler_i,
+            TransportProtocol::Tcp,
             use_h2,
         );
         nodes.push((peer, addr, peer_id, peer_i_receiver));
@@ -496,6 +580,7 @@ fn start_connection_between_two_peers(
     send_queue_size: usize,
     event_handler_1: TransportEventHandler,
     event_handler_2: TransportEventHandler,
+    protocol: TransportProtocol,
     use_h2: bool,
 ) -> (Arc<dyn Transport>, Arc<dyn Transport>) {
     // Setup registry and crypto component
@@ -520,6 +605,7 @@ fn start_connection_between_two_peers(
         Arc::new(crypto_1),
         rt_handle.clone(),
         logger.clone(),
+        protocol,
         use_h2,
     );
 
@@ -541,6 +627,7 @@ fn start_connection_between_two_peers(
         Arc::new(crypto_2),
         rt_handle,
         logger,
+        protocol,
         use_h2,
     );
     peer_b.set_event_handler(event_handler_2);
@@ -575,6 +662,7 @@ fn trigger_and_test_send_queue_full(
         send_queue_size,
         event_handler_a,
         event_handler_b,
+        TransportProtocol::Tcp,
         use_h2,
     );
     let channel_id = TransportChannelId::from(TRANSPORT_CHANNEL_ID);
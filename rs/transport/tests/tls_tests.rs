@@ -5,8 +5,10 @@ use common::{
     temp_crypto_component_with_tls_keys_in_registry, RegistryAndDataProvider, REG_V1,
 };
 use ic_base_types::{NodeId, RegistryVersion};
+use ic_config::transport::TransportProtocol;
 use ic_crypto_tls_interfaces::{
-    AllowedClients, TlsClientHandshakeError, TlsHandshake, TlsServerHandshakeError,
+    rustls::Certificate, AllowedClients, TlsClientHandshakeError, TlsHandshake,
+    TlsServerHandshakeError,
 };
 use ic_crypto_tls_interfaces_mocks::MockTlsHandshake;
 use ic_test_utilities_logger::with_test_replica_logger;
@@ -98,6 +100,7 @@ fn test_single_transient_failure_of_tls_client_handshake_impl(use_h2: bool) {
             &mut registry_and_data,
             crypto_factory_with_single_tls_handshake_client_failures,
             event_handler_1,
+            TransportProtocol::Tcp,
             use_h2,
         );
         let peer2_port = get_free_localhost_port().expect("Failed to get free localhost port");
@@ -113,6 +116,7 @@ fn test_single_transient_failure_of_tls_client_handshake_impl(use_h2: bool) {
             &mut registry_and_data,
             crypto_factory,
             event_handler_2,
+            TransportProtocol::Tcp,
             use_h2,
         );
         registry_and_data.registry.update_to_latest_version();
@@ -212,6 +216,7 @@ fn test_single_transient_failure_of_tls_server_handshake_impl(use_h2: bool) {
             &mut registry_and_data,
             crypto_factory,
             event_handler_1,
+            TransportProtocol::Tcp,
             use_h2,
         );
         let peer2_port = get_free_localhost_port().expect("Failed to get free localhost port");
@@ -226,6 +231,7 @@ fn test_single_transient_failure_of_tls_server_handshake_impl(use_h2: bool) {
             &mut registry_and_data,
             crypto_factory_with_single_tls_handshake_server_failures,
             event_handler_2,
+            TransportProtocol::Tcp,
             use_h2,
         );
         registry_and_data.registry.update_to_latest_version();
@@ -241,3 +247,189 @@ fn test_single_transient_failure_of_tls_server_handshake_impl(use_h2: bool) {
         assert_eq!(peer_2_receiver.blocking_recv(), Some(true));
     });
 }
+
+#[test]
+fn test_single_transient_failure_of_quic_client_handshake() {
+    with_test_replica_logger(|log| {
+        let mut registry_and_data = RegistryAndDataProvider::new();
+        let rt = tokio::runtime::Runtime::new().unwrap();
+
+        let crypto_factory_with_single_quic_client_config_failures =
+            |registry_and_data: &mut RegistryAndDataProvider, node_id: NodeId| {
+                let mut mock_client_tls_handshake = MockTlsHandshake::new();
+
+                let crypto = Arc::new(temp_crypto_component_with_tls_keys_in_registry(
+                    registry_and_data,
+                    node_id,
+                ));
+
+                mock_client_tls_handshake
+                    .expect_quic_server_config()
+                    .returning({
+                        let crypto = crypto.clone();
+                        move || crypto.quic_server_config()
+                    });
+
+                mock_client_tls_handshake
+                    .expect_quic_client_config()
+                    .times(1)
+                    .returning(|_server: NodeId, _registry_version: RegistryVersion| {
+                        Err(TlsClientHandshakeError::HandshakeError {
+                            internal_error: "transient".to_string(),
+                        })
+                    });
+
+                mock_client_tls_handshake
+                    .expect_quic_client_config()
+                    .returning(move |server: NodeId, registry_version: RegistryVersion| {
+                        crypto.quic_client_config(server, registry_version)
+                    });
+
+                Arc::new(mock_client_tls_handshake) as Arc<dyn TlsHandshake + Send + Sync>
+            };
+
+        let crypto_factory = |registry_and_data: &mut RegistryAndDataProvider, node_id: NodeId| {
+            Arc::new(temp_crypto_component_with_tls_keys_in_registry(
+                registry_and_data,
+                node_id,
+            )) as Arc<dyn TlsHandshake + Send + Sync>
+        };
+
+        let peer1_port = get_free_localhost_port().expect("Failed to get free localhost port");
+        let (peer_1_sender, mut peer_1_receiver) = channel(1);
+        let event_handler_1 = setup_peer_up_ack_event_handler(rt.handle().clone(), peer_1_sender);
+
+        let (peer_1, peer_1_addr) = setup_test_peer(
+            log.clone(),
+            rt.handle().clone(),
+            NODE_1,
+            peer1_port,
+            REG_V1,
+            &mut registry_and_data,
+            crypto_factory_with_single_quic_client_config_failures,
+            event_handler_1,
+            TransportProtocol::Quic,
+            false,
+        );
+        let peer2_port = get_free_localhost_port().expect("Failed to get free localhost port");
+        let (peer_2_sender, mut peer_2_receiver) = channel(1);
+        let event_handler_2 = setup_peer_up_ack_event_handler(rt.handle().clone(), peer_2_sender);
+
+        let (peer_2, peer_2_addr) = setup_test_peer(
+            log,
+            rt.handle().clone(),
+            NODE_2,
+            peer2_port,
+            REG_V1,
+            &mut registry_and_data,
+            crypto_factory,
+            event_handler_2,
+            TransportProtocol::Quic,
+            false,
+        );
+        registry_and_data.registry.update_to_latest_version();
+
+        assert!(peer_1
+            .start_connection(&NODE_2, peer_2_addr, REG_V1)
+            .is_ok());
+
+        assert!(peer_2
+            .start_connection(&NODE_1, peer_1_addr, REG_V1)
+            .is_ok());
+        assert_eq!(peer_1_receiver.blocking_recv(), Some(true));
+        assert_eq!(peer_2_receiver.blocking_recv(), Some(true));
+    });
+}
+
+#[test]
+fn test_single_transient_failure_of_quic_server_handshake() {
+    with_test_replica_logger(|log| {
+        let mut registry_and_data = RegistryAndDataProvider::new();
+        let rt = tokio::runtime::Runtime::new().unwrap();
+
+        let crypto_factory_with_single_quic_server_authentication_failures =
+            |registry_and_data: &mut RegistryAndDataProvider, node_id: NodeId| {
+                let mut mock_server_tls_handshake = MockTlsHandshake::new();
+
+                let crypto = Arc::new(temp_crypto_component_with_tls_keys_in_registry(
+                    registry_and_data,
+                    node_id,
+                ));
+
+                mock_server_tls_handshake
+                    .expect_quic_server_config()
+                    .returning({
+                        let crypto = crypto.clone();
+                        move || crypto.quic_server_config()
+                    });
+
+                mock_server_tls_handshake
+                    .expect_quic_authenticated_peer()
+                    .times(1)
+                    .returning(|_peer_certificates: &[Certificate]| {
+                        Err(TlsServerHandshakeError::HandshakeError {
+                            internal_error: "transient".to_string(),
+                        })
+                    });
+
+                mock_server_tls_handshake
+                    .expect_quic_authenticated_peer()
+                    .returning(move |peer_certificates: &[Certificate]| {
+                        crypto.quic_authenticated_peer(peer_certificates)
+                    });
+
+                Arc::new(mock_server_tls_handshake) as Arc<dyn TlsHandshake + Send + Sync>
+            };
+
+        let crypto_factory = |registry_and_data: &mut RegistryAndDataProvider, node_id: NodeId| {
+            Arc::new(temp_crypto_component_with_tls_keys_in_registry(
+                registry_and_data,
+                node_id,
+            )) as Arc<dyn TlsHandshake + Send + Sync>
+        };
+
+        let peer1_port = get_free_localhost_port().expect("Failed to get free localhost port");
+        // The client completes its side of the handshake before the server
+        // rejects it, so it sees the peer go up twice.
+        let (peer_1_sender, mut peer_1_receiver) = channel(2);
+        let event_handler_1 = setup_peer_up_ack_event_handler(rt.handle().clone(), peer_1_sender);
+        let (peer_1, peer_1_addr) = setup_test_peer(
+            log.clone(),
+            rt.handle().clone(),
+            NODE_1,
+            peer1_port,
+            REG_V1,
+            &mut registry_and_data,
+            crypto_factory,
+            event_handler_1,
+            TransportProtocol::Quic,
+            false,
+        );
+        let peer2_port = get_free_localhost_port().expect("Failed to get free localhost port");
+        let (peer_2_sender, mut peer_2_receiver) = channel(1);
+        let event_handler_2 = setup_peer_up_ack_event_handler(rt.handle().clone(), peer_2_sender);
+        let (peer_2, peer_2_addr) = setup_test_peer(
+            log,
+            rt.handle().clone(),
+            NODE_2,
+            peer2_port,
+            REG_V1,
+            &mut registry_and_data,
+            crypto_factory_with_single_quic_server_authentication_failures,
+            event_handler_2,
+            TransportProtocol::Quic,
+            false,
+        );
+        registry_and_data.registry.update_to_latest_version();
+
+        assert!(peer_1
+            .start_connection(&NODE_2, peer_2_addr, REG_V1)
+            .is_ok());
+
+        assert!(peer_2
+            .start_connection(&NODE_1, peer_1_addr, REG_V1)
+            .is_ok());
+        assert_eq!(peer_1_receiver.blocking_recv(), Some(true));
+        assert_eq!(peer_2_receiver.blocking_recv(), Some(true));
+    });
+}
@@ -17,9 +17,10 @@
 /// cargo run --bin transport_client --
 ///     --node <node_id>
 ///     --message_count <count>
+///     --protocol <tcp|quic>
 ///
 /// If not specified, message_count = 100 (default, applies only for the source
-/// node)
+/// node) and protocol = tcp
 use clap::{Arg, ArgMatches, Command};
 use crossbeam_channel::{self, Receiver, RecvTimeoutError, Sender};
 use rand::Rng;
@@ -37,7 +38,7 @@ mod utils;
 
 use ic_config::{
     logger::{Config as LoggerConfig, LogTarget},
-    transport::TransportConfig,
+    transport::{TransportConfig, TransportProtocol},
 };
 use ic_interfaces_transport::{
     Transport, TransportChannelId, TransportError, TransportEvent, TransportPayload,
@@ -65,6 +66,7 @@ type MpscReceiver = Receiver<TestMessage>;
 
 const ARG_NODE_ID: &str = "node";
 const ARG_MSG_COUNT: &str = "count";
+const ARG_PROTOCOL: &str = "protocol";
 
 const REG_V1: RegistryVersion = RegistryVersion::new(1);
 const SUBNET_ID: u8 = 100;
@@ -413,6 +415,14 @@ fn cmd_line_matches() -> ArgMatches {
                 .default_value("100")
                 .takes_value(true),
         )
+        .arg(
+            Arg::new(ARG_PROTOCOL)
+                .long("protocol")
+                .help("Transport protocol [tcp|quic]")
+                .possible_values(&["tcp", "quic"])
+                .default_value("tcp")
+                .takes_value(true),
+        )
         .get_matches()
 }
 
@@ -498,6 +508,7 @@ fn do_work_source(
 fn task_main(
     node_id_val: u8,
     message_count: usize,
+    protocol: TransportProtocol,
     active_flag: Arc<AtomicBool>,
 ) -> Result<(), TestClientErrorCode> {
     let v: Vec<u8> = vec![SUBNET_ID];
@@ -546,6 +557,7 @@ fn task_main(
         crypto,
         rt.handle().clone(),
         log.clone(),
+        protocol,
         false,
     );
 
@@ -600,7 +612,17 @@ fn main() {
         .unwrap()
         .parse::<usize>()
         .unwrap();
-    task_main(node_id_val, message_count, Arc::new(AtomicBool::new(true))).unwrap()
+    let protocol = match matches.value_of(ARG_PROTOCOL).unwrap() {
+        "quic" => TransportProtocol::Quic,
+        _ => TransportProtocol::Tcp,
+    };
+    task_main(
+        node_id_val,
+        message_count,
+        protocol,
+        Arc::new(AtomicBool::new(true)),
+    )
+    .unwrap()
 }
 
 #[cfg(test)]
@@ -616,7 +638,29 @@ async fn test_transport_spawn_tasks() {
     // Spawn tokio tasks
     for node_id in 1..(TEST_NODE_COUNT + 1) {
         let flag = active_flag.clone();
-        let handle = std::thread::spawn(move || task_main(node_id, TEST_MESSAGE_COUNT, flag));
+        let handle = std::thread::spawn(move || {
+            task_main(node_id, TEST_MESSAGE_COUNT, TransportProtocol::Tcp, flag)
+        });
+        handles.push(handle);
+    }
+
+    for x in handles {
+        let res = x.join().unwrap();
+        assert!(res.is_ok());
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_transport_spawn_tasks_quic() {
+    let active_flag = Arc::new(AtomicBool::new(true));
+    let mut handles = Vec::new();
+
+    // Spawn tokio tasks
+    for node_id in 1..(TEST_NODE_COUNT + 1) {
+        let flag = active_flag.clone();
+        let handle = std::thread::spawn(move || {
+            task_main(node_id, TEST_MESSAGE_COUNT, TransportProtocol::Quic, flag)
+        });
         handles.push(handle);
     }
 
@@ -0,0 +1,157 @@
+use crate::Payload;
+use candid::{CandidType, Deserialize};
+use ic_base_types::{CanisterId, PrincipalId};
+use ic_protobuf::state::canister_state_bits::v1 as pb_canister_state_bits;
+use serde::Serialize;
+
+/// Determines who is allowed to read the logs of a canister.
+///
+/// `(variant {
+///     controllers;
+///     public;
+/// })`
+#[derive(Clone, Copy, CandidType, Deserialize, Debug, PartialEq, Eq, Serialize)]
+pub enum LogVisibility {
+    #[serde(rename = "controllers")]
+    Controllers,
+    #[serde(rename = "public")]
+    Public,
+}
+
+impl Default for LogVisibility {
+    fn default() -> Self {
+        LogVisibility::Controllers
+    }
+}
+
+impl Payload<'_> for LogVisibility {}
+
+impl From<LogVisibility> for pb_canister_state_bits::LogVisibility {
+    fn from(item: LogVisibility) -> Self {
+        match item {
+            LogVisibility::Controllers => pb_canister_state_bits::LogVisibility::Controllers,
+            LogVisibility::Public => pb_canister_state_bits::LogVisibility::Public,
+        }
+    }
+}
+
+impl From<pb_canister_state_bits::LogVisibility> for LogVisibility {
+    fn from(item: pb_canister_state_bits::LogVisibility) -> Self {
+        match item {
+            // Checkpoints written before log visibility existed default to
+            // controllers-only access.
+            pb_canister_state_bits::LogVisibility::Unspecified
+            | pb_canister_state_bits::LogVisibility::Controllers => LogVisibility::Controllers,
+            pb_canister_state_bits::LogVisibility::Public => LogVisibility::Public,
+        }
+    }
+}
+
+/// A single record of a canister log.
+///
+/// `(record {
+///     idx : nat64;
+///     timestamp_nanos : nat64;
+///     content : blob;
+/// })`
+#[derive(Clone, CandidType, Deserialize, Debug, PartialEq, Eq, Serialize)]
+pub struct CanisterLogRecord {
+    pub idx: u64,
+    pub timestamp_nanos: u64,
+    #[serde(with = "serde_bytes")]
+    pub content: Vec<u8>,
+}
+
+impl CanisterLogRecord {
+    /// Returns the number of bytes the record occupies in the log buffer.
+    pub fn data_size(&self) -> usize {
+        std::mem::size_of::<u64>() * 2 + self.content.len()
+    }
+}
+
+impl Payload<'_> for CanisterLogRecord {}
+
+impl From<&CanisterLogRecord> for pb_canister_state_bits::CanisterLogRecord {
+    fn from(item: &CanisterLogRecord) -> Self {
+        Self {
+            idx: item.idx,
+            timestamp_nanos: item.timestamp_nanos,
+            content: item.content.clone(),
+        }
+    }
+}
+
+impl From<pb_canister_state_bits::CanisterLogRecord> for CanisterLogRecord {
+    fn from(item: pb_canister_state_bits::CanisterLogRecord) -> Self {
+        Self {
+            idx: item.idx,
+            timestamp_nanos: item.timestamp_nanos,
+            content: item.content,
+        }
+    }
+}
+
+/// Struct used for encoding/decoding
+/// `(record {
+///     canister_id : principal;
+/// })`
+#[derive(Clone, CandidType, Deserialize, Debug, PartialEq, Eq)]
+pub struct FetchCanisterLogsRequest {
+    canister_id: PrincipalId,
+}
+
+impl FetchCanisterLogsRequest {
+    pub fn new(canister_id: CanisterId) -> Self {
+        Self {
+            canister_id: canister_id.get(),
+        }
+    }
+
+    pub fn get_canister_id(&self) -> CanisterId {
+        // Safe as this was converted from CanisterId when Self was constructed.
+        CanisterId::new(self.canister_id).unwrap()
+    }
+}
+
+impl Payload<'_> for FetchCanisterLogsRequest {}
+
+/// Struct used for encoding/decoding
+/// `(record {
+///     canister_log_records : vec canister_log_record;
+/// })`
+#[derive(Clone, CandidType, Deserialize, Debug, Default, PartialEq, Eq)]
+pub struct FetchCanisterLogsResponse {
+    pub canister_log_records: Vec<CanisterLogRecord>,
+}
+
+impl Payload<'_> for FetchCanisterLogsResponse {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fetch_canister_logs_response_round_trip() {
+        let response = FetchCanisterLogsResponse {
+            canister_log_records: vec![CanisterLogRecord {
+                idx: 3,
+                timestamp_nanos: 42,
+                content: b"hello".to_vec(),
+            }],
+        };
+        assert_eq!(
+            FetchCanisterLogsResponse::decode(&response.encode()).unwrap(),
+            response
+        );
+    }
+
+    #[test]
+    fn log_visibility_round_trip() {
+        for visibility in [LogVisibility::Controllers, LogVisibility::Public] {
+            assert_eq!(
+                LogVisibility::decode(&visibility.encode()).unwrap(),
+                visibility
+            );
+        }
+    }
+}
@@ -57,7 +57,7 @@ pub struct TransformContext {
 //     url : text;
 //     max_response_bytes: opt nat64;
 //     headers : vec http_header;
-//     method : variant { get; head; post };
+//     method : variant { get; head; post; put; delete; patch };
 //     body : opt blob;
 //     transform : opt record {
 //       function : func (record {response : http_response; context : blob}) -> (http_response) query;
@@ -107,6 +107,12 @@ pub enum HttpMethod {
     POST,
     #[serde(rename = "head")]
     HEAD,
+    #[serde(rename = "put")]
+    PUT,
+    #[serde(rename = "delete")]
+    DELETE,
+    #[serde(rename = "patch")]
+    PATCH,
 }
 
 /// Represents the response for a canister http request.
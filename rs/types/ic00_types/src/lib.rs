@@ -1,6 +1,8 @@
 //! Data types used for encoding/decoding the Candid payloads of ic:00.
+mod canister_log;
 mod http;
 mod provisional;
+mod snapshot;
 
 use candid::{CandidType, Decode, Deserialize, Encode};
 use ic_base_types::{CanisterId, NodeId, NumBytes, PrincipalId, RegistryVersion, SubnetId};
@@ -16,11 +18,18 @@ use strum_macros::{Display, EnumIter, EnumString};
 /// The id of the management canister.
 pub const IC_00: CanisterId = CanisterId::ic_00();
 pub const MAX_CONTROLLERS: usize = 10;
+pub use canister_log::{
+    CanisterLogRecord, FetchCanisterLogsRequest, FetchCanisterLogsResponse, LogVisibility,
+};
 pub use http::{
     CanisterHttpRequestArgs, CanisterHttpResponsePayload, HttpHeader, HttpMethod, TransformArgs,
     TransformContext, TransformFunc,
 };
 pub use provisional::{ProvisionalCreateCanisterWithCyclesArgs, ProvisionalTopUpCanisterArgs};
+pub use snapshot::{
+    CanisterSnapshotArgs, CanisterSnapshotResponse, DeleteCanisterSnapshotArgs,
+    ListCanisterSnapshotsResponse, LoadCanisterSnapshotArgs, TakeCanisterSnapshotArgs,
+};
 
 /// Methods exported by ic:00.
 #[derive(Debug, EnumString, EnumIter, Display, Copy, Clone)]
@@ -43,11 +52,21 @@ pub enum Method {
     UpdateSettings,
     ComputeInitialEcdsaDealings,
 
+    // Canister snapshots.
+    TakeCanisterSnapshot,
+    LoadCanisterSnapshot,
+    ListCanisterSnapshots,
+    DeleteCanisterSnapshot,
+
+    // Canister logs.
+    FetchCanisterLogs,
+
     // Bitcoin Interface.
     BitcoinGetBalance,
     BitcoinGetUtxos,
     BitcoinSendTransaction,
     BitcoinGetCurrentFeePercentiles,
+    BitcoinGetBlockHeaders,
     // Private APIs used exclusively by the bitcoin canisters.
     BitcoinSendTransactionInternal, // API for sending transactions to the network.
     BitcoinGetSuccessors,           // API for fetching blocks from the network.
@@ -503,6 +522,8 @@ impl Payload<'_> for UpdateSettingsArgs {}
 ///     controllers: opt vec principal;
 ///     compute_allocation: opt nat;
 ///     memory_allocation: opt nat;
+///     freezing_threshold: opt nat;
+///     log_visibility: opt log_visibility;
 /// })`
 #[derive(Default, Clone, CandidType, Deserialize, Debug)]
 pub struct CanisterSettingsArgs {
@@ -511,6 +532,7 @@ pub struct CanisterSettingsArgs {
     pub compute_allocation: Option<candid::Nat>,
     pub memory_allocation: Option<candid::Nat>,
     pub freezing_threshold: Option<candid::Nat>,
+    pub log_visibility: Option<LogVisibility>,
 }
 
 impl Payload<'_> for CanisterSettingsArgs {}
@@ -529,6 +551,7 @@ impl CanisterSettingsArgs {
             compute_allocation: compute_allocation.map(candid::Nat::from),
             memory_allocation: memory_allocation.map(candid::Nat::from),
             freezing_threshold: freezing_threshold.map(candid::Nat::from),
+            log_visibility: None,
         }
     }
 }
@@ -696,7 +719,7 @@ impl SetupInitialDKGResponse {
 
 /// Types of curves that can be used for ECDSA signing.
 /// ```text
-/// (variant { secp256k1; })
+/// (variant { secp256k1; secp256r1; })
 /// ```
 #[derive(
     CandidType, Copy, Clone, Debug, PartialOrd, Ord, PartialEq, Eq, Serialize, Deserialize, Hash,
@@ -704,6 +727,8 @@ impl SetupInitialDKGResponse {
 pub enum EcdsaCurve {
     #[serde(rename = "secp256k1")]
     Secp256k1,
+    #[serde(rename = "secp256r1")]
+    Secp256r1,
 }
 
 impl TryFrom<pb_registry_crypto::EcdsaCurve> for EcdsaCurve {
@@ -712,6 +737,7 @@ impl TryFrom<pb_registry_crypto::EcdsaCurve> for EcdsaCurve {
     fn try_from(item: pb_registry_crypto::EcdsaCurve) -> Result<Self, Self::Error> {
         match item {
             pb_registry_crypto::EcdsaCurve::Secp256k1 => Ok(EcdsaCurve::Secp256k1),
+            pb_registry_crypto::EcdsaCurve::Secp256r1 => Ok(EcdsaCurve::Secp256r1),
             pb_registry_crypto::EcdsaCurve::Unspecified => Err(ProxyDecodeError::ValueOutOfRange {
                 typ: "EcdsaCurve",
                 err: format!("Unable to convert {:?} to an EcdsaCurve", item),
@@ -724,6 +750,7 @@ impl From<EcdsaCurve> for pb_registry_crypto::EcdsaCurve {
     fn from(item: EcdsaCurve) -> Self {
         match item {
             EcdsaCurve::Secp256k1 => pb_registry_crypto::EcdsaCurve::Secp256k1,
+            EcdsaCurve::Secp256r1 => pb_registry_crypto::EcdsaCurve::Secp256r1,
         }
     }
 }
@@ -740,6 +767,7 @@ impl FromStr for EcdsaCurve {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "Secp256k1" => Ok(Self::Secp256k1),
+            "Secp256r1" => Ok(Self::Secp256r1),
             _ => Err(format!("{} is not a recognized ECDSA curve", s)),
         }
     }
@@ -747,12 +775,9 @@ impl FromStr for EcdsaCurve {
 
 #[test]
 fn ecdsa_curve_round_trip() {
-    assert_eq!(
-        format!("{}", EcdsaCurve::Secp256k1)
-            .parse::<EcdsaCurve>()
-            .unwrap(),
-        EcdsaCurve::Secp256k1
-    );
+    for curve in [EcdsaCurve::Secp256k1, EcdsaCurve::Secp256r1] {
+        assert_eq!(format!("{}", curve).parse::<EcdsaCurve>().unwrap(), curve);
+    }
 }
 
 /// Unique identifier for a key that can be used for ECDSA signatures. The name
@@ -974,6 +999,7 @@ impl ComputeInitialEcdsaDealingsResponse {
 // Export the bitcoin types.
 pub use ic_btc_types::{
     GetBalanceRequest as BitcoinGetBalanceArgs,
+    GetBlockHeadersRequest as BitcoinGetBlockHeadersArgs,
     GetCurrentFeePercentilesRequest as BitcoinGetCurrentFeePercentilesArgs,
     GetUtxosRequest as BitcoinGetUtxosArgs, Network as BitcoinNetwork,
     SendTransactionRequest as BitcoinSendTransactionArgs,
@@ -990,6 +1016,7 @@ impl Payload<'_> for BitcoinGetBalanceArgs {}
 impl Payload<'_> for BitcoinGetUtxosArgs {}
 impl Payload<'_> for BitcoinSendTransactionArgs {}
 impl Payload<'_> for BitcoinGetCurrentFeePercentilesArgs {}
+impl Payload<'_> for BitcoinGetBlockHeadersArgs {}
 impl Payload<'_> for BitcoinGetSuccessorsArgs {}
 impl Payload<'_> for BitcoinGetSuccessorsResponse {}
 impl Payload<'_> for BitcoinSendTransactionInternalArgs {}
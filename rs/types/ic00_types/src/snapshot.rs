@@ -0,0 +1,127 @@
+use crate::Payload;
+use candid::{CandidType, Deserialize};
+use ic_base_types::{CanisterId, PrincipalId};
+
+/// Struct used for encoding/decoding
+/// `(record {
+///     canister_id : principal;
+///     replace_snapshot : opt blob;
+/// })`
+#[derive(Clone, CandidType, Deserialize, Debug, PartialEq, Eq)]
+pub struct TakeCanisterSnapshotArgs {
+    canister_id: PrincipalId,
+    pub replace_snapshot: Option<Vec<u8>>,
+}
+
+impl TakeCanisterSnapshotArgs {
+    pub fn new(canister_id: CanisterId, replace_snapshot: Option<Vec<u8>>) -> Self {
+        Self {
+            canister_id: canister_id.get(),
+            replace_snapshot,
+        }
+    }
+
+    pub fn get_canister_id(&self) -> CanisterId {
+        // Safe as this was converted from CanisterId when Self was constructed.
+        CanisterId::new(self.canister_id).unwrap()
+    }
+}
+
+impl Payload<'_> for TakeCanisterSnapshotArgs {}
+
+/// Struct used for encoding/decoding
+/// `(record {
+///     canister_id : principal;
+///     snapshot_id : blob;
+/// })`
+///
+/// It is used by both `load_canister_snapshot` and `delete_canister_snapshot`.
+#[derive(Clone, CandidType, Deserialize, Debug, PartialEq, Eq)]
+pub struct CanisterSnapshotArgs {
+    canister_id: PrincipalId,
+    #[serde(with = "serde_bytes")]
+    pub snapshot_id: Vec<u8>,
+}
+
+impl CanisterSnapshotArgs {
+    pub fn new(canister_id: CanisterId, snapshot_id: Vec<u8>) -> Self {
+        Self {
+            canister_id: canister_id.get(),
+            snapshot_id,
+        }
+    }
+
+    pub fn get_canister_id(&self) -> CanisterId {
+        // Safe as this was converted from CanisterId when Self was constructed.
+        CanisterId::new(self.canister_id).unwrap()
+    }
+}
+
+impl Payload<'_> for CanisterSnapshotArgs {}
+
+pub type LoadCanisterSnapshotArgs = CanisterSnapshotArgs;
+pub type DeleteCanisterSnapshotArgs = CanisterSnapshotArgs;
+
+/// Struct used for encoding/decoding
+/// `(record {
+///     id : blob;
+///     taken_at_timestamp : nat64;
+///     total_size : nat64;
+/// })`
+#[derive(Clone, CandidType, Deserialize, Debug, PartialEq, Eq)]
+pub struct CanisterSnapshotResponse {
+    #[serde(with = "serde_bytes")]
+    pub id: Vec<u8>,
+    pub taken_at_timestamp: u64,
+    pub total_size: u64,
+}
+
+impl CanisterSnapshotResponse {
+    pub fn new(id: Vec<u8>, taken_at_timestamp: u64, total_size: u64) -> Self {
+        Self {
+            id,
+            taken_at_timestamp,
+            total_size,
+        }
+    }
+}
+
+impl Payload<'_> for CanisterSnapshotResponse {}
+
+/// Struct used for encoding/decoding the reply of `list_canister_snapshots`
+/// `(vec record {
+///     id : blob;
+///     taken_at_timestamp : nat64;
+///     total_size : nat64;
+/// })`
+#[derive(Clone, CandidType, Deserialize, Debug, Default, PartialEq, Eq)]
+pub struct ListCanisterSnapshotsResponse(pub Vec<CanisterSnapshotResponse>);
+
+impl Payload<'_> for ListCanisterSnapshotsResponse {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn take_canister_snapshot_args_round_trip() {
+        let args = TakeCanisterSnapshotArgs::new(CanisterId::from_u64(7), Some(vec![1, 2, 3]));
+        assert_eq!(
+            TakeCanisterSnapshotArgs::decode(&args.encode()).unwrap(),
+            args
+        );
+    }
+
+    #[test]
+    fn list_canister_snapshots_response_round_trip() {
+        let response = ListCanisterSnapshotsResponse(vec![CanisterSnapshotResponse::new(
+            vec![0; 10],
+            42,
+            1 << 20,
+        )]);
+        assert_eq!(
+            ListCanisterSnapshotsResponse::decode(&response.encode()).unwrap(),
+            response
+        );
+    }
+}
@@ -214,6 +214,16 @@ impl TryFrom<(Time, &Request, CanisterHttpRequestArgs)> for CanisterHttpRequestC
             None => Ok(None),
         }?;
 
+        // The content of a DELETE request has no defined semantics and is
+        // rejected by many servers, so it is not accepted (and charged for).
+        if args.method == HttpMethod::DELETE && args.body.is_some() {
+            return Err(CanisterHttpRequestContextError::RequestBody(
+                InvalidRequestBody {
+                    http_method: CanisterHttpMethod::DELETE,
+                },
+            ));
+        }
+
         Ok(CanisterHttpRequestContext {
             request: request.clone(),
             url: args.url,
@@ -232,6 +242,9 @@ impl TryFrom<(Time, &Request, CanisterHttpRequestArgs)> for CanisterHttpRequestC
                 HttpMethod::GET => CanisterHttpMethod::GET,
                 HttpMethod::POST => CanisterHttpMethod::POST,
                 HttpMethod::HEAD => CanisterHttpMethod::HEAD,
+                HttpMethod::PUT => CanisterHttpMethod::PUT,
+                HttpMethod::DELETE => CanisterHttpMethod::DELETE,
+                HttpMethod::PATCH => CanisterHttpMethod::PATCH,
             },
             transform: args.transform.map(From::from),
             time,
@@ -271,12 +284,20 @@ pub struct InvalidTransformPrincipalId {
     actual_principal_id: PrincipalId,
 }
 
+/// The error that occurs when an end-user specifies a body for an HTTP method
+/// that doesn't take one.
+#[derive(Debug)]
+pub struct InvalidRequestBody {
+    http_method: CanisterHttpMethod,
+}
+
 /// Errors that can occur when converting from (time, request, [`CanisterHttpRequestArgs`]) to
 /// an [`CanisterHttpRequestContext`].
 #[derive(Debug)]
 pub enum CanisterHttpRequestContextError {
     MaxResponseBytes(InvalidMaxResponseBytes),
     TransformPrincipalId(InvalidTransformPrincipalId),
+    RequestBody(InvalidRequestBody),
 }
 
 impl From<CanisterHttpRequestContextError> for UserError {
@@ -296,6 +317,13 @@ impl From<CanisterHttpRequestContextError> for UserError {
                     err.expected_principal_id, err.actual_principal_id,
                 ),
             ),
+            CanisterHttpRequestContextError::RequestBody(err) => UserError::new(
+                ErrorCode::CanisterRejectedMessage,
+                format!(
+                    "http_request with method {:?} must not have a body",
+                    err.http_method
+                ),
+            ),
         }
     }
 }
@@ -366,6 +394,9 @@ pub enum CanisterHttpMethod {
     GET,
     POST,
     HEAD,
+    PUT,
+    DELETE,
+    PATCH,
 }
 
 impl From<&CanisterHttpMethod> for pb_metadata::HttpMethod {
@@ -374,6 +405,9 @@ impl From<&CanisterHttpMethod> for pb_metadata::HttpMethod {
             CanisterHttpMethod::GET => pb_metadata::HttpMethod::Get,
             CanisterHttpMethod::POST => pb_metadata::HttpMethod::Post,
             CanisterHttpMethod::HEAD => pb_metadata::HttpMethod::Head,
+            CanisterHttpMethod::PUT => pb_metadata::HttpMethod::Put,
+            CanisterHttpMethod::DELETE => pb_metadata::HttpMethod::Delete,
+            CanisterHttpMethod::PATCH => pb_metadata::HttpMethod::Patch,
         }
     }
 }
@@ -386,6 +420,9 @@ impl TryFrom<pb_metadata::HttpMethod> for CanisterHttpMethod {
             pb_metadata::HttpMethod::Get => Ok(CanisterHttpMethod::GET),
             pb_metadata::HttpMethod::Post => Ok(CanisterHttpMethod::POST),
             pb_metadata::HttpMethod::Head => Ok(CanisterHttpMethod::HEAD),
+            pb_metadata::HttpMethod::Put => Ok(CanisterHttpMethod::PUT),
+            pb_metadata::HttpMethod::Delete => Ok(CanisterHttpMethod::DELETE),
+            pb_metadata::HttpMethod::Patch => Ok(CanisterHttpMethod::PATCH),
             pb_metadata::HttpMethod::Unspecified => Err(ProxyDecodeError::ValueOutOfRange {
                 typ: "ic_protobuf::state::system_metadata::v1::HttpMethod",
                 err: "Unspecified HttpMethod".to_string(),
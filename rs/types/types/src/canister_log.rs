@@ -0,0 +1,137 @@
+//! A bounded buffer of canister log records.
+use ic_ic00_types::CanisterLogRecord;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// The maximum total size in bytes of the records kept in a canister log.
+/// Older records are evicted once the limit is reached.
+pub const MAX_ALLOWED_CANISTER_LOG_BUFFER_SIZE: usize = 4 * 1024;
+
+/// A ring buffer of log records produced by a canister via `ic0.debug_print`
+/// and by traps.
+///
+/// Records are assigned consecutive indices. An index is never reused, even
+/// after the record has been evicted from the buffer.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CanisterLog {
+    /// The index assigned to the next record.
+    next_idx: u64,
+    records: VecDeque<CanisterLogRecord>,
+    /// The total `data_size()` of all `records`.
+    records_size: usize,
+}
+
+impl CanisterLog {
+    /// Creates a log from records restored from a checkpoint.
+    pub fn new(next_idx: u64, records: Vec<CanisterLogRecord>) -> Self {
+        let mut log = Self {
+            next_idx,
+            records: VecDeque::new(),
+            records_size: 0,
+        };
+        for record in records {
+            log.push_record(record);
+        }
+        log
+    }
+
+    /// Returns the index that will be assigned to the next record.
+    pub fn next_idx(&self) -> u64 {
+        self.next_idx
+    }
+
+    /// Returns the records currently kept in the log, oldest first.
+    pub fn records(&self) -> &VecDeque<CanisterLogRecord> {
+        &self.records
+    }
+
+    /// Returns the total size of the records currently kept in the log.
+    pub fn used_space(&self) -> usize {
+        self.records_size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Appends a record with the given timestamp and content, evicting the
+    /// oldest records if the log is full. Content that does not fit into an
+    /// empty log is truncated.
+    pub fn add_record(&mut self, timestamp_nanos: u64, mut content: Vec<u8>) {
+        let max_content_size =
+            MAX_ALLOWED_CANISTER_LOG_BUFFER_SIZE - 2 * std::mem::size_of::<u64>();
+        content.truncate(max_content_size);
+        let record = CanisterLogRecord {
+            idx: self.next_idx,
+            timestamp_nanos,
+            content,
+        };
+        self.next_idx += 1;
+        self.push_record(record);
+    }
+
+    /// Appends the records of `delta` to this log, assigning them new
+    /// indices. Used to merge the records produced during a message
+    /// execution into the log of the canister.
+    pub fn append_delta(&mut self, delta: CanisterLog) {
+        for record in delta.records {
+            self.add_record(record.timestamp_nanos, record.content);
+        }
+    }
+
+    fn push_record(&mut self, record: CanisterLogRecord) {
+        self.records_size += record.data_size();
+        self.records.push_back(record);
+        while self.records_size > MAX_ALLOWED_CANISTER_LOG_BUFFER_SIZE {
+            match self.records.pop_front() {
+                Some(evicted) => self.records_size -= evicted.data_size(),
+                None => break,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_get_consecutive_indices() {
+        let mut log = CanisterLog::default();
+        log.add_record(1, b"a".to_vec());
+        log.add_record(2, b"b".to_vec());
+        let indices: Vec<_> = log.records().iter().map(|r| r.idx).collect();
+        assert_eq!(indices, vec![0, 1]);
+        assert_eq!(log.next_idx(), 2);
+    }
+
+    #[test]
+    fn oldest_records_are_evicted_when_full() {
+        let mut log = CanisterLog::default();
+        let content = vec![0; 1000];
+        for i in 0..10 {
+            log.add_record(i, content.clone());
+        }
+        assert!(log.used_space() <= MAX_ALLOWED_CANISTER_LOG_BUFFER_SIZE);
+        assert_eq!(log.records().back().unwrap().idx, 9);
+        assert_eq!(log.records().front().unwrap().idx, 6);
+    }
+
+    #[test]
+    fn oversized_record_is_truncated() {
+        let mut log = CanisterLog::default();
+        log.add_record(0, vec![0; 2 * MAX_ALLOWED_CANISTER_LOG_BUFFER_SIZE]);
+        assert_eq!(log.records().len(), 1);
+        assert_eq!(log.used_space(), MAX_ALLOWED_CANISTER_LOG_BUFFER_SIZE);
+    }
+
+    #[test]
+    fn append_delta_reindexes_records() {
+        let mut log = CanisterLog::new(5, vec![]);
+        let mut delta = CanisterLog::default();
+        delta.add_record(7, b"x".to_vec());
+        log.append_delta(delta);
+        assert_eq!(log.records()[0].idx, 5);
+        assert_eq!(log.records()[0].timestamp_nanos, 7);
+    }
+}
@@ -53,7 +53,8 @@ pub trait ChunkableArtifact {
     /// Retrieves the artifact chunk with the given ID.
     ///
     /// The chunk ID for single-chunked artifacts must be
-    /// [`CHUNKID_UNIT_CHUNK`].
+    /// [`CHUNKID_UNIT_CHUNK`]. The slices of multi-chunked artifacts are
+    /// served from a [`SlicedArtifact`](crate::multi_chunked::SlicedArtifact).
     fn get_chunk(self: Box<Self>, chunk_id: ChunkId) -> Option<ArtifactChunk>;
 }
 
@@ -422,7 +422,7 @@ impl ThresholdEcdsaSigInputs {
         algorithm_id: AlgorithmId,
     ) -> Result<(), error::ThresholdEcdsaSigInputsCreationError> {
         match algorithm_id {
-            AlgorithmId::ThresholdEcdsaSecp256k1 => {
+            AlgorithmId::ThresholdEcdsaSecp256k1 | AlgorithmId::ThresholdEcdsaSecp256r1 => {
                 if hashed_message.len() != ECDSA_SECP256K1_HASH_BYTE_LENGTH {
                     return Err(error::ThresholdEcdsaSigInputsCreationError::InvalidHashLength);
                 }
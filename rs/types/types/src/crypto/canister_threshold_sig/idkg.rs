@@ -300,8 +300,8 @@ impl IDkgTranscriptParams {
     ///   and `ReceiversEmpty`)
     /// * |dealers| >= self.collection_threshold + faults_tolerated(|dealers|)
     ///   (error: `UnsatisfiedCollectionThreshold`)
-    /// * algorithm_id is of type `ThresholdEcdsaSecp256k1` (error:
-    ///   `UnsupportedAlgorithmId`)
+    /// * algorithm_id is of type `ThresholdEcdsaSecp256k1` or
+    ///   `ThresholdEcdsaSecp256r1` (error: `UnsupportedAlgorithmId`)
     /// * If `operation_type` is:
     ///   - ReshareOfMasked(t):
     ///     - t is of type Masked(_)
@@ -470,7 +470,7 @@ impl IDkgTranscriptParams {
 
     fn ensure_algorithm_id_supported(&self) -> Result<(), IDkgParamsValidationError> {
         match self.algorithm_id {
-            AlgorithmId::ThresholdEcdsaSecp256k1 => Ok(()),
+            AlgorithmId::ThresholdEcdsaSecp256k1 | AlgorithmId::ThresholdEcdsaSecp256r1 => Ok(()),
             _ => Err(IDkgParamsValidationError::UnsupportedAlgorithmId {
                 algorithm_id: self.algorithm_id,
             }),
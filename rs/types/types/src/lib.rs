@@ -66,6 +66,7 @@
 pub mod artifact;
 pub mod batch;
 pub mod canister_http;
+pub mod canister_log;
 pub mod chunkable;
 pub mod consensus;
 pub mod crypto;
@@ -76,6 +77,7 @@ pub mod malicious_behaviour;
 pub mod malicious_flags;
 pub mod messages;
 pub mod methods;
+pub mod multi_chunked;
 pub mod nominal_cycles;
 pub mod p2p;
 pub mod registry;
@@ -0,0 +1,528 @@
+//! Chunked transfer of large [`SingleChunked`](crate::single_chunked::SingleChunked)
+//! artifacts.
+//!
+//! Artifacts other than state sync are delivered as a single unit chunk
+//! ([`CHUNKID_UNIT_CHUNK`]). For artifacts larger than [`CHUNK_SIZE`], such as
+//! blocks with payloads close to the maximum ingress and xnet sizes, P2P
+//! instead requests the artifact in multiple chunks, which can be downloaded
+//! from several peers in parallel.
+//!
+//! A multi-chunked artifact is the bincode serialization of the [`Artifact`],
+//! split into slices of [`CHUNK_SIZE`] bytes. Slice `i` is served as chunk
+//! `i + 1`, so that the unit chunk remains available to peers that download
+//! the artifact as a single chunk. Each chunk carries the length of the
+//! serialized artifact (8 bytes, little endian), followed by the slice. Its
+//! witness is the manifest of the artifact: the SHA-256 hashes of all slices.
+//!
+//! A chunk whose slice does not match its own manifest is faulty, and so is
+//! its sender. As long as it is unknown which manifest is the right one, the
+//! slices are collected separately for each manifest received, so that a peer
+//! serving a wrong manifest cannot hold back the download of the right one.
+//! Once all slices of a manifest are received, the artifact is assembled and
+//! verified against the integrity hash of the advert by P2P, like
+//! single-chunked artifacts. If the slices do not form an artifact, or if
+//! this check fails, the manifest was wrong and so are the peers serving it.
+use crate::{
+    artifact::{Artifact, ArtifactId},
+    chunkable::{
+        ArtifactChunk, ArtifactChunkData, ArtifactErrorCode, ChunkId, Chunkable, ChunkableArtifact,
+        CHUNKID_UNIT_CHUNK,
+    },
+    crypto::CryptoHash,
+    p2p::GossipAdvert,
+};
+use bincode::{deserialize, serialize};
+use ic_crypto_sha::Sha256;
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::{TryFrom, TryInto};
+
+/// Size in bytes of the slices of a multi-chunked artifact. Artifacts with an
+/// advertised size of at most this size are transferred as a single chunk.
+pub const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Maximum advertised size in bytes of an artifact that is not state sync.
+///
+/// This is well above the size of the largest blocks, so that adverts of
+/// larger artifacts can be dropped without downloading them.
+pub const MAX_ARTIFACT_SIZE: usize = 64 * CHUNK_SIZE;
+
+/// The ID of the chunk containing the first slice of an artifact.
+const FIRST_SLICE_CHUNK_ID: u32 = CHUNKID_UNIT_CHUNK + 1;
+
+/// The size in bytes of the length prefix of a chunk.
+const LENGTH_PREFIX_SIZE: usize = 8;
+
+/// Returns true if artifacts with the given ID can be downloaded in multiple
+/// chunks.
+///
+/// State sync (and file tree sync) artifacts implement their own chunking and
+/// are never multi-chunked.
+fn is_sliceable(artifact_id: &ArtifactId) -> bool {
+    !matches!(
+        artifact_id,
+        ArtifactId::StateSync(_) | ArtifactId::FileTreeSync(_)
+    )
+}
+
+/// Returns true if the artifact of the advert is to be downloaded in
+/// multiple chunks.
+pub fn is_multi_chunked(advert: &GossipAdvert) -> bool {
+    is_sliceable(&advert.artifact_id) && advert.size > CHUNK_SIZE
+}
+
+/// Returns true if the advertised size of the artifact exceeds
+/// [`MAX_ARTIFACT_SIZE`]. Such adverts are not downloaded.
+pub fn is_oversized(advert: &GossipAdvert) -> bool {
+    is_sliceable(&advert.artifact_id) && advert.size > MAX_ARTIFACT_SIZE
+}
+
+/// Returns true if the chunk with the given ID is a slice of a multi-chunked
+/// artifact, which is served from a [`SlicedArtifact`].
+pub fn is_slice_chunk(artifact_id: &ArtifactId, chunk_id: ChunkId) -> bool {
+    is_sliceable(artifact_id) && chunk_id != ChunkId::from(CHUNKID_UNIT_CHUNK)
+}
+
+/// Returns the number of slices of a serialized artifact of the given length.
+fn num_slices(length: usize) -> usize {
+    std::cmp::max(
+        1,
+        length / CHUNK_SIZE + usize::from(length % CHUNK_SIZE != 0),
+    )
+}
+
+/// Returns the ID of the chunk containing the slice with the given index, or
+/// `None` if the index exceeds the range of chunk IDs.
+fn slice_chunk_id(index: usize) -> Option<ChunkId> {
+    u32::try_from(index)
+        .ok()
+        .and_then(|index| FIRST_SLICE_CHUNK_ID.checked_add(index))
+        .map(ChunkId::from)
+}
+
+/// Returns the index of the slice contained in the chunk with the given ID.
+fn slice_index(chunk_id: ChunkId) -> Option<usize> {
+    chunk_id
+        .get()
+        .checked_sub(FIRST_SLICE_CHUNK_ID)
+        .and_then(|index| usize::try_from(index).ok())
+}
+
+/// Returns the range of the slice with the given index within a serialized
+/// artifact of the given length. Slices past the end of the artifact are
+/// empty.
+fn slice_range(index: usize, length: usize) -> std::ops::Range<usize> {
+    let start = index.saturating_mul(CHUNK_SIZE).min(length);
+    let end = start.saturating_add(CHUNK_SIZE).min(length);
+    start..end
+}
+
+/// Returns the manifest of a serialized artifact.
+fn manifest(binary: &[u8]) -> Vec<CryptoHash> {
+    (0..num_slices(binary.len()))
+        .map(|index| CryptoHash(Sha256::hash(&binary[slice_range(index, binary.len())]).to_vec()))
+        .collect()
+}
+
+/// The manifest of a multi-chunked artifact: the length of the serialized
+/// artifact and the hashes of its slices.
+///
+/// Honest peers serve the same serialization of an artifact, so all chunks
+/// of a peer must carry the same manifest.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Manifest {
+    length: usize,
+    hashes: Vec<CryptoHash>,
+}
+
+impl Manifest {
+    /// Returns the number of slices of the artifact.
+    fn num_slices(&self) -> usize {
+        self.hashes.len()
+    }
+}
+
+/// Returns the manifest of the given chunk, after checking that the chunk
+/// matches it.
+///
+/// Returns `ChunkVerificationFailed` if the chunk is not a slice chunk or if
+/// it does not match its own manifest, in which case its sender is faulty.
+pub fn chunk_manifest(artifact_chunk: &ArtifactChunk) -> Result<Manifest, ArtifactErrorCode> {
+    parse_chunk(artifact_chunk).map(|(_, manifest, _)| manifest)
+}
+
+/// Splits the given chunk into the index of its slice, its manifest and the
+/// slice, after checking that the slice matches the manifest.
+fn parse_chunk(
+    artifact_chunk: &ArtifactChunk,
+) -> Result<(usize, Manifest, &[u8]), ArtifactErrorCode> {
+    let payload = match &artifact_chunk.artifact_chunk_data {
+        ArtifactChunkData::SemiStructuredChunkData(payload)
+            if payload.len() >= LENGTH_PREFIX_SIZE =>
+        {
+            payload
+        }
+        _ => return Err(ArtifactErrorCode::ChunkVerificationFailed),
+    };
+    let index =
+        slice_index(artifact_chunk.chunk_id).ok_or(ArtifactErrorCode::ChunkVerificationFailed)?;
+    let length = usize::try_from(u64::from_le_bytes(
+        payload[..LENGTH_PREFIX_SIZE].try_into().unwrap(),
+    ))
+    .map_err(|_| ArtifactErrorCode::ChunkVerificationFailed)?;
+    let slice = &payload[LENGTH_PREFIX_SIZE..];
+    let hashes = &artifact_chunk.witness;
+
+    if hashes.len() != num_slices(length) || slice.len() != slice_range(index, length).len() {
+        return Err(ArtifactErrorCode::ChunkVerificationFailed);
+    }
+    if let Some(expected_hash) = hashes.get(index) {
+        if Sha256::hash(slice)[..] != expected_hash.0[..] {
+            return Err(ArtifactErrorCode::ChunkVerificationFailed);
+        }
+    }
+
+    let manifest = Manifest {
+        length,
+        hashes: hashes.clone(),
+    };
+    Ok((index, manifest, slice))
+}
+
+/// A serialized artifact, from which the chunks of a multi-chunked download
+/// are served.
+///
+/// The artifact is serialized once, so that the peers serving its chunks can
+/// keep it instead of serializing the artifact for every chunk.
+pub struct SlicedArtifact {
+    binary: Vec<u8>,
+    manifest: Vec<CryptoHash>,
+}
+
+impl SlicedArtifact {
+    /// Serializes the given single-chunked artifact. Returns `None` if the
+    /// artifact has no unit chunk.
+    pub fn new(artifact: Box<dyn ChunkableArtifact + '_>) -> Option<Self> {
+        let artifact = match artifact
+            .get_chunk(ChunkId::from(CHUNKID_UNIT_CHUNK))?
+            .artifact_chunk_data
+        {
+            ArtifactChunkData::UnitChunkData(artifact) => artifact,
+            _ => return None,
+        };
+        Self::from_artifact(&artifact)
+    }
+
+    fn from_artifact(artifact: &Artifact) -> Option<Self> {
+        let binary = serialize(artifact).ok()?;
+        let manifest = manifest(&binary);
+        Some(Self { binary, manifest })
+    }
+
+    /// Returns the chunk with the given ID. Chunks past the end of the
+    /// artifact have an empty slice.
+    pub fn get_chunk(&self, chunk_id: ChunkId) -> Option<ArtifactChunk> {
+        let range = slice_range(slice_index(chunk_id)?, self.binary.len());
+        let mut payload = Vec::with_capacity(LENGTH_PREFIX_SIZE + range.len());
+        payload.extend_from_slice(&(self.binary.len() as u64).to_le_bytes());
+        payload.extend_from_slice(&self.binary[range]);
+        Some(ArtifactChunk {
+            chunk_id,
+            witness: self.manifest.clone(),
+            artifact_chunk_data: ArtifactChunkData::SemiStructuredChunkData(payload),
+        })
+    }
+}
+
+/// Chunk tracker assembling a multi-chunked artifact.
+pub struct MultiChunked {
+    /// Upper bound for the length of the serialized artifact.
+    max_length: usize,
+    /// The number of slices of the artifact, estimated from the advertised
+    /// size. Only used while no manifest is pending.
+    estimated_num_slices: usize,
+    /// The slices received so far, by slice index, for each manifest.
+    candidates: BTreeMap<Manifest, BTreeMap<usize, Vec<u8>>>,
+}
+
+impl MultiChunked {
+    /// Creates a tracker for an artifact with the given advertised size.
+    ///
+    /// The advertised size is an estimate of the length of the serialized
+    /// artifact, so chunks announcing a length of up to one chunk more are
+    /// accepted.
+    pub fn new(advertised_size: usize) -> Self {
+        Self {
+            max_length: advertised_size.saturating_add(CHUNK_SIZE),
+            estimated_num_slices: num_slices(advertised_size),
+            candidates: BTreeMap::new(),
+        }
+    }
+}
+
+impl Chunkable for MultiChunked {
+    /// Returns the chunks that are missing for any of the manifests received.
+    fn chunks_to_download(&self) -> Box<dyn Iterator<Item = ChunkId>> {
+        if self.candidates.is_empty() {
+            return Box::new((0..self.estimated_num_slices).map_while(slice_chunk_id));
+        }
+
+        let candidates: Vec<(usize, BTreeSet<usize>)> = self
+            .candidates
+            .iter()
+            .map(|(manifest, slices)| (manifest.num_slices(), slices.keys().copied().collect()))
+            .collect();
+        let num_slices = candidates
+            .iter()
+            .map(|(num_slices, _)| *num_slices)
+            .max()
+            .unwrap_or_default();
+        Box::new(
+            (0..num_slices)
+                .filter(move |index| {
+                    candidates.iter().any(|(num_slices, received)| {
+                        index < num_slices && !received.contains(index)
+                    })
+                })
+                .map_while(slice_chunk_id),
+        )
+    }
+
+    /// Adds the chunk to the slices of its manifest.
+    ///
+    /// Returns `ChunkVerificationFailed` if the chunk does not match its own
+    /// manifest, or if the manifest is wrong: it announces a length larger
+    /// than the advertised size allows, or its slices do not form an
+    /// artifact. The slices of a wrong manifest are dropped.
+    fn add_chunk(&mut self, artifact_chunk: ArtifactChunk) -> Result<Artifact, ArtifactErrorCode> {
+        let (index, manifest, slice) = parse_chunk(&artifact_chunk)?;
+        if manifest.length > self.max_length {
+            return Err(ArtifactErrorCode::ChunkVerificationFailed);
+        }
+
+        let slices = self.candidates.entry(manifest.clone()).or_default();
+        if index >= manifest.num_slices() {
+            // An empty slice past the end of an artifact whose size was
+            // overestimated.
+            return Err(ArtifactErrorCode::ChunksMoreNeeded);
+        }
+        slices.insert(index, slice.to_vec());
+        if slices.len() < manifest.num_slices() {
+            return Err(ArtifactErrorCode::ChunksMoreNeeded);
+        }
+
+        // The manifest is complete. If the artifact turns out to be wrong, the
+        // download continues with the other manifests.
+        let slices = self.candidates.remove(&manifest).unwrap_or_default();
+        let mut binary = Vec::with_capacity(manifest.length);
+        for slice in slices.values() {
+            binary.extend_from_slice(slice);
+        }
+        deserialize(&binary).map_err(|_| ArtifactErrorCode::ChunkVerificationFailed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filetree_sync::FileTreeSyncArtifact;
+    use std::path::PathBuf;
+
+    fn artifact_of_size(size: usize) -> Artifact {
+        Artifact::FileTreeSync(FileTreeSyncArtifact {
+            absolute_path: PathBuf::from("a".repeat(size)),
+            id: "id".to_string(),
+        })
+    }
+
+    fn slice_chunk(artifact: &Artifact, index: u32) -> ArtifactChunk {
+        SlicedArtifact::from_artifact(artifact)
+            .unwrap()
+            .get_chunk(ChunkId::from(FIRST_SLICE_CHUNK_ID + index))
+            .unwrap()
+    }
+
+    fn download(
+        artifact: &Artifact,
+        tracker: &mut MultiChunked,
+    ) -> Result<Artifact, ArtifactErrorCode> {
+        let sliced = SlicedArtifact::from_artifact(artifact).unwrap();
+        let mut result = Err(ArtifactErrorCode::ChunksMoreNeeded);
+        while let Some(chunk_id) = tracker.chunks_to_download().next() {
+            result = tracker.add_chunk(sliced.get_chunk(chunk_id).unwrap());
+            if result.is_ok() {
+                break;
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn artifact_is_assembled_from_slices() {
+        let artifact = artifact_of_size(3 * CHUNK_SIZE + 17);
+        let size = serialize(&artifact).unwrap().len();
+        let mut tracker = MultiChunked::new(size);
+        assert_eq!(tracker.chunks_to_download().count(), 4);
+        assert_eq!(download(&artifact, &mut tracker), Ok(artifact));
+    }
+
+    #[test]
+    fn number_of_slices_follows_the_announced_length() {
+        let artifact = artifact_of_size(2 * CHUNK_SIZE + 17);
+
+        // Underestimated size: the missing slices are requested once the
+        // length is known.
+        let mut tracker = MultiChunked::new(CHUNK_SIZE + 1);
+        assert_eq!(tracker.chunks_to_download().count(), 2);
+        assert_eq!(download(&artifact, &mut tracker), Ok(artifact.clone()));
+
+        // Overestimated size: slices past the end are no longer requested.
+        let mut tracker = MultiChunked::new(3 * CHUNK_SIZE + 1);
+        assert_eq!(tracker.chunks_to_download().count(), 4);
+        assert_eq!(
+            tracker.add_chunk(slice_chunk(&artifact, 3)),
+            Err(ArtifactErrorCode::ChunksMoreNeeded)
+        );
+        assert_eq!(tracker.chunks_to_download().count(), 3);
+        assert_eq!(download(&artifact, &mut tracker), Ok(artifact));
+    }
+
+    #[test]
+    fn chunk_ids_are_computed_lazily_and_without_overflow() {
+        let tracker = MultiChunked::new(usize::MAX);
+        let mut chunks = tracker.chunks_to_download();
+        assert_eq!(chunks.next(), Some(ChunkId::from(FIRST_SLICE_CHUNK_ID)));
+        assert_eq!(chunks.next(), Some(ChunkId::from(FIRST_SLICE_CHUNK_ID + 1)));
+
+        assert_eq!(slice_chunk_id(u32::MAX as usize), None);
+        assert_eq!(
+            slice_chunk_id(u32::MAX as usize - 1),
+            Some(ChunkId::from(u32::MAX))
+        );
+        assert_eq!(slice_index(ChunkId::from(CHUNKID_UNIT_CHUNK)), None);
+    }
+
+    #[test]
+    fn chunks_of_different_manifests_are_collected_separately() {
+        let artifact = artifact_of_size(2 * CHUNK_SIZE);
+        let other_artifact = artifact_of_size(2 * CHUNK_SIZE + 1);
+        let size = serialize(&artifact).unwrap().len();
+        let mut tracker = MultiChunked::new(size);
+
+        assert_eq!(
+            tracker.add_chunk(slice_chunk(&other_artifact, 0)),
+            Err(ArtifactErrorCode::ChunksMoreNeeded)
+        );
+        // The chunk of the other manifest does not hold back the artifact.
+        assert_eq!(
+            tracker.add_chunk(slice_chunk(&artifact, 1)),
+            Err(ArtifactErrorCode::ChunksMoreNeeded)
+        );
+        // The chunks missing for either manifest are requested.
+        assert_eq!(
+            tracker.chunks_to_download().collect::<Vec<_>>(),
+            vec![
+                ChunkId::from(FIRST_SLICE_CHUNK_ID),
+                ChunkId::from(FIRST_SLICE_CHUNK_ID + 1),
+                ChunkId::from(FIRST_SLICE_CHUNK_ID + 2),
+            ]
+        );
+        assert_eq!(
+            tracker.add_chunk(slice_chunk(&artifact, 0)),
+            Err(ArtifactErrorCode::ChunksMoreNeeded)
+        );
+        assert_eq!(tracker.add_chunk(slice_chunk(&artifact, 2)), Ok(artifact));
+    }
+
+    #[test]
+    fn manifests_whose_slices_do_not_form_an_artifact_are_dropped() {
+        let artifact = artifact_of_size(CHUNK_SIZE);
+        let size = serialize(&artifact).unwrap().len();
+        let mut tracker = MultiChunked::new(size);
+
+        // Slices with a consistent manifest, which do not form an artifact.
+        let binary = vec![0xff; CHUNK_SIZE + 1];
+        let garbage = SlicedArtifact {
+            manifest: manifest(&binary),
+            binary,
+        };
+        assert_eq!(
+            tracker.add_chunk(
+                garbage
+                    .get_chunk(ChunkId::from(FIRST_SLICE_CHUNK_ID))
+                    .unwrap()
+            ),
+            Err(ArtifactErrorCode::ChunksMoreNeeded)
+        );
+        assert_eq!(
+            tracker.add_chunk(
+                garbage
+                    .get_chunk(ChunkId::from(FIRST_SLICE_CHUNK_ID + 1))
+                    .unwrap()
+            ),
+            Err(ArtifactErrorCode::ChunkVerificationFailed)
+        );
+
+        // The slices of the wrong manifest are dropped.
+        assert_eq!(tracker.chunks_to_download().count(), 2);
+        assert_eq!(download(&artifact, &mut tracker), Ok(artifact));
+    }
+
+    #[test]
+    fn chunks_inconsistent_with_their_manifest_are_rejected() {
+        let artifact = artifact_of_size(2 * CHUNK_SIZE);
+        let size = serialize(&artifact).unwrap().len();
+        let mut tracker = MultiChunked::new(size);
+
+        // A modified slice.
+        let mut chunk = slice_chunk(&artifact, 1);
+        if let ArtifactChunkData::SemiStructuredChunkData(payload) = &mut chunk.artifact_chunk_data
+        {
+            *payload.last_mut().unwrap() ^= 1;
+        }
+        assert_eq!(
+            tracker.add_chunk(chunk),
+            Err(ArtifactErrorCode::ChunkVerificationFailed)
+        );
+
+        // A manifest of the wrong length.
+        let mut chunk = slice_chunk(&artifact, 0);
+        chunk.witness.pop();
+        assert_eq!(
+            tracker.add_chunk(chunk),
+            Err(ArtifactErrorCode::ChunkVerificationFailed)
+        );
+
+        // A slice past the end of the artifact that is not empty.
+        let mut chunk = slice_chunk(&artifact, 0);
+        chunk.chunk_id = ChunkId::from(FIRST_SLICE_CHUNK_ID + 5);
+        assert_eq!(
+            tracker.add_chunk(chunk),
+            Err(ArtifactErrorCode::ChunkVerificationFailed)
+        );
+
+        assert_eq!(download(&artifact, &mut tracker), Ok(artifact));
+    }
+
+    #[test]
+    fn unit_and_oversized_chunks_are_rejected() {
+        let artifact = artifact_of_size(2 * CHUNK_SIZE);
+        let mut tracker = MultiChunked::new(CHUNK_SIZE + 1);
+
+        let unit_chunk = ArtifactChunk {
+            chunk_id: ChunkId::from(CHUNKID_UNIT_CHUNK),
+            witness: Vec::new(),
+            artifact_chunk_data: ArtifactChunkData::UnitChunkData(artifact),
+        };
+        assert_eq!(
+            tracker.add_chunk(unit_chunk),
+            Err(ArtifactErrorCode::ChunkVerificationFailed)
+        );
+
+        // The announced length exceeds the advertised size by more than a chunk.
+        let oversized = artifact_of_size(4 * CHUNK_SIZE);
+        assert_eq!(
+            tracker.add_chunk(slice_chunk(&oversized, 0)),
+            Err(ArtifactErrorCode::ChunkVerificationFailed)
+        );
+    }
+}
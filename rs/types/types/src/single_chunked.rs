@@ -54,7 +54,7 @@ macro_rules! chunkable_artifact_impl {
                     Some(ArtifactChunk {
                         chunk_id,
                         witness: Vec::new(),
-                        artifact_chunk_data: $v,
+                        artifact_chunk_data: ArtifactChunkData::UnitChunkData($v),
                     })
                 }
             }
@@ -63,25 +63,26 @@ macro_rules! chunkable_artifact_impl {
 }
 
 chunkable_artifact_impl! {ConsensusMessage, |self|
-    ArtifactChunkData::UnitChunkData(Artifact::ConsensusMessage(*self))
+    Artifact::ConsensusMessage(*self)
 }
 chunkable_artifact_impl! {SignedIngress, |self|
-    ArtifactChunkData::UnitChunkData(Artifact::IngressMessage(*self))
+    Artifact::IngressMessage(*self)
 }
 chunkable_artifact_impl! {CertificationMessage, |self|
-    ArtifactChunkData::UnitChunkData(Artifact::CertificationMessage(*self))
+    Artifact::CertificationMessage(*self)
 }
 chunkable_artifact_impl! {DkgMessage, |self|
-    ArtifactChunkData::UnitChunkData(Artifact::DkgMessage(*self))
+    Artifact::DkgMessage(*self)
 }
 chunkable_artifact_impl! {EcdsaMessage, |self|
-    ArtifactChunkData::UnitChunkData(Artifact::EcdsaMessage(*self))
+    Artifact::EcdsaMessage(*self)
 }
 chunkable_artifact_impl! {CanisterHttpResponseShare, |self|
-    ArtifactChunkData::UnitChunkData(Artifact::CanisterHttpMessage(*self))
+    Artifact::CanisterHttpMessage(*self)
 }
 
-// Basic chunking impl for [`SingleChunked`] object tracking
+// Basic chunking impl for [`SingleChunked`] object tracking. Large artifacts
+// are tracked by [`MultiChunked`](crate::multi_chunked::MultiChunked) instead.
 impl Chunkable for SingleChunked {
     fn chunks_to_download(&self) -> Box<dyn Iterator<Item = ChunkId>> {
         let v: Vec<ChunkId> = vec![ChunkId::from(CHUNKID_UNIT_CHUNK)];
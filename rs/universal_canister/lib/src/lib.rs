@@ -311,6 +311,11 @@ impl PayloadBuilder {
         self
     }
 
+    pub fn data_certificate(mut self) -> Self {
+        self.0.push(Ops::DataCertificate as u8);
+        self
+    }
+
     /// Store data (in a global variable) on the heap.
     /// NOTE: This does _not_ correspond to a Wasm global.
     pub fn set_global_data(mut self, data: &[u8]) -> Self {
@@ -35,6 +35,12 @@ where
     T: 'static + Send + RequestInfo,
 {
     let num_expected = plan.requests;
+    // Prefix of the output lines, naming the plan if it has a name.
+    let label = plan
+        .name
+        .as_ref()
+        .map(|name| format!("[{}] ", name))
+        .unwrap_or_default();
     let mut eof_received = false;
     let mut messages: Vec<T> = Vec::with_capacity(plan.requests);
 
@@ -46,7 +52,7 @@ where
             ProgressStyle::default_bar()
                 .template("{msg} {percent:>3}% {per_sec:>20} {bar:40.green} {pos:5}/{len:7}"),
         );
-        pb_success.set_message("Completed successfully");
+        pb_success.set_message(&format!("{}Completed successfully", label));
         pb_success.set_position(0);
         Some(pb_success)
     } else {
@@ -59,7 +65,7 @@ where
             ProgressStyle::default_bar()
                 .template("{msg} {percent:>3}% {per_sec:>20} {bar:40.red} {pos:5}/{len:7}"),
         );
-        pb_fail.set_message("Completed with failure");
+        pb_fail.set_message(&format!("{}Completed with failure", label));
         pb_fail.set_position(0);
         Some(pb_fail)
     } else {
@@ -101,7 +107,8 @@ where
                         let total_elapsed = time_start.elapsed().unwrap();
                         let delta_succ = num_succ - last_num_succ;
                         println!(
-                            "Progress {:?}: success = {}, failed = {}, current RPS = {}, effective RPS = {}",
+                            "{}Progress {:?}: success = {}, failed = {}, current RPS = {}, effective RPS = {}",
+                            label,
                             total_elapsed,
                             num_succ,
                             num_fail,
@@ -132,7 +139,8 @@ where
     if periodic_output {
         let elapsed = time_start.elapsed().unwrap();
         println!(
-            "Summary {:?}: success = {}, failed = {}, effective RPS = {}",
+            "{}Summary {:?}: success = {}, failed = {}, effective RPS = {}",
+            label,
             elapsed,
             num_succ,
             num_fail,
@@ -4,6 +4,7 @@ use crate::{
     message::Message,
     metrics::{FUTURE_STARTED, REQUEST_STARTING},
     plan::{EngineCall, Plan},
+    scenario::Scenario,
     stats::Fact,
     RequestType,
 };
@@ -16,6 +17,7 @@ use ic_types::{
 };
 
 use byte_unit::Byte;
+use futures::future::join_all;
 use itertools::Either;
 use serde::{Deserialize, Serialize};
 use std::{
@@ -69,48 +71,51 @@ pub struct Engine {
 }
 
 impl Engine {
-    /// Creates a new engine
+    /// Creates a new engine with one agent per sender and url.
     pub fn new(
-        agent_sender: AgentSender,
-        sender_field: Blob,
+        senders: Vec<(AgentSender, Blob)>,
         urls: &[String],
         http_client_config: HttpClientConfig,
         host: Option<String>,
         query_timeout: Option<Duration>,
         ingress_timeout: Option<Duration>,
     ) -> Engine {
-        let mut agents = Vec::with_capacity(urls.len());
-        let current_batch = urls.iter().map(|url| {
-            let mut url = Url::parse(url.as_str()).unwrap();
-            let mut http_client_config = http_client_config.clone();
-            if let Some(new_host) = host.as_ref() {
-                http_client_config.overrides.insert(
-                    new_host.clone(),
-                    match url.host() {
-                        None => panic!("no host found in {}", url),
-                        Some(Host::Domain(host)) => Either::Right(
-                            FromStr::from_str(host).expect("failed to convert host to dns name"),
-                        ),
-                        Some(Host::Ipv4(host)) => {
-                            Either::Left((host, url.port_or_known_default().unwrap()).into())
-                        }
-                        Some(Host::Ipv6(host)) => {
-                            Either::Left((host, url.port_or_known_default().unwrap()).into())
-                        }
-                    },
-                );
-                url.set_host(Some(new_host.as_str()))
-                    .expect("failed to set host");
-            }
-            let mut agent = Agent::new_with_http_client_config(
-                url,
-                agent_sender.clone(),
-                http_client_config.clone(),
-            )
-            .with_query_timeout(query_timeout.unwrap_or(QUERY_TIMEOUT))
-            .with_ingress_timeout(ingress_timeout.unwrap_or(INGRESS_TIMEOUT));
-            agent.sender_field = sender_field.clone();
-            agent
+        let mut agents = Vec::with_capacity(senders.len() * urls.len());
+        let (host, http_client_config) = (&host, &http_client_config);
+        let current_batch = senders.iter().flat_map(|(agent_sender, sender_field)| {
+            urls.iter().map(move |url| {
+                let mut url = Url::parse(url.as_str()).unwrap();
+                let mut http_client_config = http_client_config.clone();
+                if let Some(new_host) = host.as_ref() {
+                    http_client_config.overrides.insert(
+                        new_host.clone(),
+                        match url.host() {
+                            None => panic!("no host found in {}", url),
+                            Some(Host::Domain(host)) => Either::Right(
+                                FromStr::from_str(host)
+                                    .expect("failed to convert host to dns name"),
+                            ),
+                            Some(Host::Ipv4(host)) => {
+                                Either::Left((host, url.port_or_known_default().unwrap()).into())
+                            }
+                            Some(Host::Ipv6(host)) => {
+                                Either::Left((host, url.port_or_known_default().unwrap()).into())
+                            }
+                        },
+                    );
+                    url.set_host(Some(new_host.as_str()))
+                        .expect("failed to set host");
+                }
+                let mut agent = Agent::new_with_http_client_config(
+                    url,
+                    agent_sender.clone(),
+                    http_client_config.clone(),
+                )
+                .with_query_timeout(query_timeout.unwrap_or(QUERY_TIMEOUT))
+                .with_ingress_timeout(ingress_timeout.unwrap_or(INGRESS_TIMEOUT));
+                agent.sender_field = sender_field.clone();
+                agent
+            })
         });
 
         agents.extend(current_batch);
@@ -166,30 +171,75 @@ impl Engine {
             nonce,
             call_payload_size,
             call_payload,
-            *canister_id,
+            vec![*canister_id],
             request_type,
             canister_method_name,
         );
+
+        // Time between each two consecutive requests
+        let inter_arrival_time = 1000. / rpms as f64;
+        let send_times = (0..requests)
+            .map(|n| Duration::from_secs_f64(inter_arrival_time * n as f64))
+            .collect();
+
+        self.execute_plan(plan, send_times, Some(rpms), periodic_output)
+            .await
+    }
+
+    /// Execute the streams of a scenario concurrently. Returns the facts of
+    /// each stream along with its name.
+    pub async fn execute_scenario(
+        &self,
+        scenario: &Scenario,
+        nonce: String,
+        periodic_output: bool,
+    ) -> Vec<(String, Vec<Fact>)> {
+        // Progress bars of concurrent streams would overwrite each other.
+        let periodic_output = periodic_output || scenario.streams.len() > 1;
+        let streams = scenario
+            .plans(&nonce)
+            .into_iter()
+            .map(|(plan, send_times)| {
+                let name = plan.name.clone().unwrap_or_default();
+                debug!(
+                    "⏱️  Executing {} requests of stream {}",
+                    send_times.len(),
+                    name
+                );
+                async move {
+                    (
+                        name,
+                        self.execute_plan(plan, send_times, None, periodic_output)
+                            .await,
+                    )
+                }
+            });
+        join_all(streams).await
+    }
+
+    /// Execute the requests of `plan`, the `n`-th of which is sent at
+    /// `send_times[n]` after the start.
+    async fn execute_plan(
+        &self,
+        plan: Plan,
+        send_times: Vec<Duration>,
+        rpms: Option<usize>,
+        periodic_output: bool,
+    ) -> Vec<Fact> {
+        if send_times.is_empty() {
+            return vec![];
+        }
         let (collector, rec_handle) = collector::start::<Fact>(plan.clone(), periodic_output);
 
-        let (tx, rx) = channel(requests);
+        let (tx, rx) = channel(send_times.len());
         let time_origin = Instant::now();
 
-        let rx_handle = tokio::task::spawn(Engine::evaluate_requests(
-            rx,
-            collector,
-            Some(rpms),
-            time_origin,
-        ));
+        let rx_handle =
+            tokio::task::spawn(Engine::evaluate_requests(rx, collector, rpms, time_origin));
 
-        // Time between each two consecutive requests
-        let inter_arrival_time = 1000. / rpms as f64;
         let mut tx_handles = vec![];
-        for n in 0..requests {
-            // Calculate the time at which the request should be running from start time
-            // and inter arrival time.
-            let target_instant =
-                time_origin + START_OFFSET + Duration::from_secs_f64(inter_arrival_time * n as f64);
+        for (n, send_time) in send_times.into_iter().enumerate() {
+            let target_instant = time_origin + START_OFFSET + send_time;
             sleep_until(tokio::time::Instant::from_std(target_instant)).await;
             let tx = tx.clone();
             let plan = plan.clone();
@@ -244,7 +294,9 @@ impl Engine {
         n: usize,
     ) -> Option<u32> {
         let time_query_start = Instant::now();
-        let response = agent.execute_query(&plan.canister_id, &*method, arg).await;
+        let response = agent
+            .execute_query(&plan.canister_id(n), &*method, arg)
+            .await;
         let time_query_end = Instant::now();
         debug!("Sent query ({}). Response was: {:?}", n, response);
 
@@ -302,10 +354,11 @@ impl Engine {
         n: usize,
     ) -> bool {
         let nonce = plan.nonce.clone();
+        let canister_id = plan.canister_id(n);
         let deadline = Instant::now() + agent.ingress_timeout;
         let (request, request_id) = agent
             .prepare_update_raw(
-                &plan.canister_id,
+                &canister_id,
                 method,
                 arg,
                 format!("inc {} {}", nonce, n).into_bytes(),
@@ -319,7 +372,7 @@ impl Engine {
         );
 
         let content = SignedRequestBytes::try_from(request).unwrap().into();
-        let path = update_path(plan.canister_id);
+        let path = update_path(canister_id);
         let time_start = std::time::Instant::now();
         debug!(
             "Sending update() call ({}) after {}ms since origin",
@@ -429,7 +482,7 @@ impl Engine {
                     let wait = Engine::wait_ingress_for_counter_canister(
                         agent,
                         request_id.clone(),
-                        &plan.canister_id,
+                        &canister_id,
                         deadline,
                     )
                     .await;
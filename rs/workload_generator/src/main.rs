@@ -27,6 +27,7 @@ mod engine;
 mod message;
 mod metrics;
 mod plan;
+mod scenario;
 mod stats;
 
 use ic_canister_client::{HttpClient, HttpClientConfig, Sender as AgentSender};
@@ -34,6 +35,8 @@ use ic_canister_client_sender::ed25519_public_key_to_der;
 use ic_config::metrics::{Config as MetricsConfig, Exporter};
 use ic_test_identity::{get_pair, TEST_IDENTITY_KEYPAIR, TEST_IDENTITY_KEYPAIR_HARD_CODED};
 use ic_types::{messages::Blob, CanisterId, PrincipalId, UserId};
+use scenario::Scenario;
+use serde::Deserialize;
 use stats::Summary;
 
 #[cfg(build = "debug")]
@@ -62,8 +65,9 @@ fn write_output_json(filename: &str, summaries: &[Summary]) -> io::Result<()> {
     Ok(())
 }
 
-#[derive(Clone, Copy, Debug, ArgEnum)]
+#[derive(Clone, Copy, Debug, ArgEnum, Deserialize)]
 #[clap(rename_all = "camel")]
+#[serde(rename_all = "camelCase")]
 pub enum RequestType {
     // Needs to expose "read"
     QueryCounter,
@@ -114,10 +118,18 @@ async fn main() {
         .arg(
             Arg::new("rps")
                 .short('r')
-                .required(true)
+                .required_unless_present("scenario")
                 .takes_value(true)
                 .help("Requests per second to generate. Accepts fractional values, e.g. 1.5 rps."),
         )
+        .arg(
+            Arg::new("scenario")
+                .long("scenario")
+                .value_name("FILE")
+                .takes_value(true)
+                .conflicts_with_all(&["rps", "canister-id", "canister", "updates"])
+                .help("Run the scenario described in the given JSON file instead of a single request type at a fixed rate. The scenario defines its own duration, rate profile, request streams and canisters, so -n, -r, --method and --canister-id are not used."),
+        )
         .arg(
             Arg::new("evaluate-max-rps")
                 .long("evaluate-max-rps")
@@ -305,7 +317,10 @@ async fn main() {
         .unwrap()
         .parse::<usize>()
         .unwrap();
-    let rps = matches.value_of("rps").unwrap().parse::<f64>().unwrap();
+    let rps = matches
+        .value_of("rps")
+        .map(|rps| rps.parse::<f64>().unwrap())
+        .unwrap_or_default();
     let rpms = (rps * 1000f64).floor() as usize;
 
     let principal_id = matches
@@ -368,6 +383,12 @@ async fn main() {
         }
     };
 
+    let scenario = matches.value_of("scenario").map(|f| {
+        Scenario::load(Path::new(f)).unwrap_or_else(|err| {
+            panic!("{}", err);
+        })
+    });
+
     let mut exit_code_success = true;
 
     let mut http_client_config = HttpClientConfig::default();
@@ -444,9 +465,12 @@ async fn main() {
                 }
                 _ => {}
             }
+            let senders = match scenario.as_ref() {
+                Some(scenario) => scenario.senders(),
+                None => vec![(sender.clone(), sender_field)],
+            };
             let eng = engine::Engine::new(
-                sender.clone(),
-                sender_field,
+                senders,
                 &url,
                 http_client_config,
                 host,
@@ -458,50 +482,6 @@ async fn main() {
                 eng.wait_for_all_agents_to_be_healthy().await;
             }
 
-            // use id of install canister if no id specified
-            let canister_id = if let Some(s) = matches.value_of("canister-id") {
-                let canister_id =
-                    CanisterId::try_from(PrincipalId::from_str(s).unwrap_or_else(|_| {
-                        panic!("Illegal value for option --canister-id: '{}'", s);
-                    }))
-                    .unwrap();
-                if let Some(wasm_file_path) = matches.value_of_os("canister").map(Path::new) {
-                    let mut install_succeeded = false;
-                    for url in install_endpoint {
-                        match canister::install_canister(
-                            http_client.clone(),
-                            sender.clone(),
-                            url,
-                            canister_id,
-                            Some(wasm_file_path),
-                        )
-                        .await
-                        {
-                            Ok(()) => {
-                                install_succeeded = true;
-                                break;
-                            }
-                            Err(err) => println!(
-                                "⚠️  Could not install canister at replica url {}. {}",
-                                url, err
-                            ),
-                        }
-                    }
-
-                    if !install_succeeded {
-                        panic!("Failed to install wasm to existing canister");
-                    }
-                }
-                canister_id
-            } else {
-                let wasm_file_path = matches.value_of_os("canister").map(Path::new);
-                canister::setup_canister(http_client, sender, install_endpoint, wasm_file_path)
-                    .await
-                    .unwrap_or_else(|err| {
-                        panic!("Failed to create canister: {}", err);
-                    })
-            };
-
             // case insensitive
             let chart_size = ChartSize::from_str(
                 matches
@@ -514,35 +494,102 @@ async fn main() {
             // Hold all summaries so we can serialize them later if needed
             let mut summaries: Vec<Summary> = Vec::new();
 
-            // Make sure to save the guard, see documentation for more information
-            println!(
-                "Running {:?} rps for {} seconds, req_type = {:?}",
-                rps, duration, request_type
-            );
+            if let Some(scenario) = scenario.as_ref() {
+                println!(
+                    "Running scenario with {} streams for {} seconds",
+                    scenario.streams.len(),
+                    scenario.duration_secs
+                );
+                let results = eng
+                    .execute_scenario(scenario, nonce.clone(), periodic_output)
+                    .await;
+                std::mem::drop(eng);
+
+                let mut all_facts = vec![];
+                for (name, facts) in results {
+                    let summary = Summary::from_facts(&facts).with_stream(name);
+                    summaries.push(summary.clone());
+                    println!("{}", summary.with_chart_size(chart_size));
+                    all_facts.extend(facts);
+                }
+                let summary = Summary::from_facts(&all_facts);
+                summaries.push(summary.clone());
+                println!("{}", summary.with_chart_size(chart_size));
+            } else {
+                // use id of install canister if no id specified
+                let canister_id = if let Some(s) = matches.value_of("canister-id") {
+                    let canister_id =
+                        CanisterId::try_from(PrincipalId::from_str(s).unwrap_or_else(|_| {
+                            panic!("Illegal value for option --canister-id: '{}'", s);
+                        }))
+                        .unwrap();
+                    if let Some(wasm_file_path) = matches.value_of_os("canister").map(Path::new) {
+                        let mut install_succeeded = false;
+                        for url in install_endpoint {
+                            match canister::install_canister(
+                                http_client.clone(),
+                                sender.clone(),
+                                url,
+                                canister_id,
+                                Some(wasm_file_path),
+                            )
+                            .await
+                            {
+                                Ok(()) => {
+                                    install_succeeded = true;
+                                    break;
+                                }
+                                Err(err) => println!(
+                                    "⚠️  Could not install canister at replica url {}. {}",
+                                    url, err
+                                ),
+                            }
+                        }
 
-            let facts = eng
-                .execute_rps(
-                    rpms,
-                    request_type,
-                    canister_method_name,
-                    duration,
-                    nonce.clone(),
-                    call_payload_size,
-                    call_payload,
-                    &canister_id,
-                    periodic_output,
-                )
-                .await;
-
-            // Drop the engine with the hope that all client connections will be closed.
-            // Sometimes we may end up in situation where all file decriptors
-            // are consumed by the number of connections. We need a more
-            // sustainable solution where the file decriptors
-            // are not a bottleneck.
-            std::mem::drop(eng);
-            let summary = Summary::from_facts(&facts);
-            summaries.push(summary.clone());
-            println!("{}", summary.with_chart_size(chart_size));
+                        if !install_succeeded {
+                            panic!("Failed to install wasm to existing canister");
+                        }
+                    }
+                    canister_id
+                } else {
+                    let wasm_file_path = matches.value_of_os("canister").map(Path::new);
+                    canister::setup_canister(http_client, sender, install_endpoint, wasm_file_path)
+                        .await
+                        .unwrap_or_else(|err| {
+                            panic!("Failed to create canister: {}", err);
+                        })
+                };
+
+                // Make sure to save the guard, see documentation for more information
+                println!(
+                    "Running {:?} rps for {} seconds, req_type = {:?}",
+                    rps, duration, request_type
+                );
+
+                let facts = eng
+                    .execute_rps(
+                        rpms,
+                        request_type,
+                        canister_method_name,
+                        duration,
+                        nonce.clone(),
+                        call_payload_size,
+                        call_payload,
+                        &canister_id,
+                        periodic_output,
+                    )
+                    .await;
+
+                // Drop the engine with the hope that all client connections will be closed.
+                // Sometimes we may end up in situation where all file decriptors
+                // are consumed by the number of connections. We need a more
+                // sustainable solution where the file decriptors
+                // are not a bottleneck.
+                std::mem::drop(eng);
+                let summary = Summary::from_facts(&facts);
+                summaries.push(summary.clone());
+                println!("{}", summary.with_chart_size(chart_size));
+            }
 
             if let Some(metrics) = metrics_runtime.take() {
                 std::mem::drop(metrics);
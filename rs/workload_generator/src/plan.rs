@@ -1,4 +1,4 @@
-use crate::RequestType;
+use crate::{scenario::render_arg, RequestType};
 use byte_unit::Byte;
 use candid::Encode;
 use ic_types::CanisterId;
@@ -9,9 +9,15 @@ pub struct Plan {
     pub nonce: String,
     pub call_payload_size: Byte,
     pub call_payload: Vec<u8>,
-    pub canister_id: CanisterId,
+    /// Requests are sent to these canisters in round-robin fashion.
+    pub canister_ids: Vec<CanisterId>,
     pub request_type: RequestType,
     pub canister_method_name: String,
+    /// Label of the plan in progress output and summaries.
+    pub name: Option<String>,
+    /// Candid text template of the argument of custom calls, rendered for
+    /// every request. Takes precedence over `call_payload`.
+    pub arg_template: Option<String>,
 }
 
 pub enum EngineCall {
@@ -25,7 +31,7 @@ impl Plan {
         nonce: String,
         call_payload_size: Byte,
         call_payload: Vec<u8>,
-        canister_id: CanisterId,
+        canister_ids: Vec<CanisterId>,
         request_type: RequestType,
         canister_method_name: String,
     ) -> Self {
@@ -34,9 +40,33 @@ impl Plan {
             nonce,
             call_payload_size,
             call_payload,
-            canister_id,
+            canister_ids,
             request_type,
             canister_method_name,
+            name: None,
+            arg_template: None,
+        }
+    }
+
+    pub fn with_name(mut self, name: String) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    pub fn with_arg_template(mut self, arg_template: Option<String>) -> Self {
+        self.arg_template = arg_template;
+        self
+    }
+
+    /// The canister the `n`-th request is sent to.
+    pub fn canister_id(&self, n: usize) -> CanisterId {
+        self.canister_ids[n % self.canister_ids.len()]
+    }
+
+    fn custom_arg(&self, n: usize) -> Vec<u8> {
+        match &self.arg_template {
+            Some(template) => render_arg(template, n).expect("Failed to render call argument"),
+            None => self.call_payload.clone(),
         }
     }
 
@@ -81,11 +111,11 @@ impl Plan {
 
             RequestType::Update => EngineCall::Write {
                 method: self.canister_method_name.clone(),
-                arg: self.call_payload.clone(),
+                arg: self.custom_arg(n),
             },
             RequestType::Query => EngineCall::Read {
                 method: self.canister_method_name.clone(),
-                arg: self.call_payload.clone(),
+                arg: self.custom_arg(n),
             },
         }
     }
@@ -0,0 +1,507 @@
+//! Declarative workload scenarios.
+//!
+//! A scenario describes a mix of request streams that are run concurrently,
+//! each sending a weighted share of an overall request rate that follows a
+//! rate profile. Scenarios are read from JSON files, e.g.:
+//!
+//! ```json
+//! {
+//!   "duration_secs": 300,
+//!   "identities": 4,
+//!   "rate": { "ramp": { "from_rps": 10, "to_rps": 200, "secs": 120 } },
+//!   "streams": [
+//!     {
+//!       "name": "balance",
+//!       "request_type": "query",
+//!       "canisters": ["rwlgt-iiaaa-aaaaa-aaaaa-cai", "rrkah-fqaaa-aaaaa-aaaaq-cai"],
+//!       "method": "balance",
+//!       "arg": "(record { account = {{rand:1000}} })",
+//!       "weight": 9
+//!     },
+//!     {
+//!       "name": "transfer",
+//!       "request_type": "update",
+//!       "canisters": ["rwlgt-iiaaa-aaaaa-aaaaa-cai"],
+//!       "method": "transfer",
+//!       "arg": "(record { to = {{rand:1000}}; nonce = {{n}} })",
+//!       "weight": 1
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! Candid argument templates are rendered for each request: `{{n}}` is
+//! replaced by the index of the request within its stream and `{{rand:N}}` by
+//! a number in `0..N` derived from that index, so that runs are reproducible.
+use crate::{plan::Plan, RequestType};
+use byte_unit::Byte;
+use candid::IDLArgs;
+use ic_canister_client::Sender as AgentSender;
+use ic_canister_client_sender::ed25519_public_key_to_der;
+use ic_test_identity::test_identity_keypair;
+use ic_types::{messages::Blob, CanisterId, PrincipalId, UserId};
+use serde::Deserialize;
+use std::{convert::TryFrom, fs, path::Path, str::FromStr, time::Duration};
+
+// Granularity at which the rate profile is integrated into send times.
+const RATE_RESOLUTION: Duration = Duration::from_millis(1);
+
+/// The overall request rate of a scenario over time.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateProfile {
+    /// A fixed rate for the whole run.
+    Constant { rps: f64 },
+    /// A linear ramp from `from_rps` to `to_rps` over `secs` seconds, after
+    /// which `to_rps` is kept.
+    Ramp {
+        from_rps: f64,
+        to_rps: f64,
+        secs: f64,
+    },
+    /// A sequence of fixed rates. The last step is kept until the end of the
+    /// run.
+    Step { steps: Vec<RateStep> },
+    /// `base_rps`, except for `secs` seconds starting at `at_secs` during which
+    /// `spike_rps` is sent.
+    Spike {
+        base_rps: f64,
+        spike_rps: f64,
+        at_secs: f64,
+        secs: f64,
+    },
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct RateStep {
+    pub rps: f64,
+    pub secs: f64,
+}
+
+impl RateProfile {
+    /// The requests per second to send `t` seconds into the run.
+    pub fn rps_at(&self, t: f64) -> f64 {
+        match self {
+            RateProfile::Constant { rps } => *rps,
+            RateProfile::Ramp {
+                from_rps,
+                to_rps,
+                secs,
+            } => {
+                if t >= *secs {
+                    *to_rps
+                } else {
+                    from_rps + (to_rps - from_rps) * t / secs
+                }
+            }
+            RateProfile::Step { steps } => {
+                let mut end = 0.;
+                for step in steps {
+                    end += step.secs;
+                    if t < end {
+                        return step.rps;
+                    }
+                }
+                steps.last().map(|step| step.rps).unwrap_or(0.)
+            }
+            RateProfile::Spike {
+                base_rps,
+                spike_rps,
+                at_secs,
+                secs,
+            } => {
+                if t >= *at_secs && t < at_secs + secs {
+                    *spike_rps
+                } else {
+                    *base_rps
+                }
+            }
+        }
+    }
+
+    /// Offsets from the start of the run at which the requests of a stream
+    /// receiving `share` of the rate are to be sent.
+    pub fn send_times(&self, share: f64, duration: Duration) -> Vec<Duration> {
+        let mut times = vec![];
+        // Number of requests owed so far, accumulated over the rate profile.
+        let mut owed = 0.;
+        let mut t = Duration::ZERO;
+        while t < duration {
+            owed += self.rps_at(t.as_secs_f64()) * share * RATE_RESOLUTION.as_secs_f64();
+            while owed >= 1. {
+                times.push(t);
+                owed -= 1.;
+            }
+            t += RATE_RESOLUTION;
+        }
+        times
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let rates = match self {
+            RateProfile::Constant { rps } => vec![*rps],
+            RateProfile::Ramp {
+                from_rps,
+                to_rps,
+                secs,
+            } => {
+                if *secs <= 0. {
+                    return Err("the ramp duration must be positive".to_string());
+                }
+                vec![*from_rps, *to_rps]
+            }
+            RateProfile::Step { steps } => {
+                if steps.is_empty() {
+                    return Err("a step profile needs at least one step".to_string());
+                }
+                steps.iter().map(|step| step.rps).collect()
+            }
+            RateProfile::Spike {
+                base_rps,
+                spike_rps,
+                ..
+            } => vec![*base_rps, *spike_rps],
+        };
+        if rates.iter().any(|rps| !rps.is_finite() || *rps < 0.) {
+            return Err("rates must be non-negative numbers".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// A stream of requests of a single type and method.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Stream {
+    pub name: String,
+    pub request_type: RequestType,
+    /// Requests are spread round-robin across these canisters.
+    pub canisters: Vec<String>,
+    /// The method to call for `query` and `update` streams.
+    #[serde(default)]
+    pub method: String,
+    /// Candid text template of the argument for `query` and `update` streams.
+    #[serde(default)]
+    pub arg: Option<String>,
+    /// Payload size for the built-in request types.
+    #[serde(default)]
+    pub payload_size: Option<String>,
+    /// Relative share of the overall rate.
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Scenario {
+    pub duration_secs: u64,
+    /// The number of caller identities requests are spread across.
+    #[serde(default = "default_identities")]
+    pub identities: u64,
+    pub rate: RateProfile,
+    pub streams: Vec<Stream>,
+}
+
+fn default_identities() -> u64 {
+    1
+}
+
+impl Scenario {
+    /// Reads and validates the scenario file at `path`.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read scenario file {}: {}", path.display(), e))?;
+        let scenario: Scenario = serde_json::from_str(&contents)
+            .map_err(|e| format!("Failed to parse scenario file {}: {}", path.display(), e))?;
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.streams.is_empty() {
+            return Err("The scenario has no streams".to_string());
+        }
+        if self.identities == 0 {
+            return Err("The scenario needs at least one identity".to_string());
+        }
+        if self.streams.iter().all(|stream| stream.weight == 0) {
+            return Err("At least one stream needs a positive weight".to_string());
+        }
+        self.rate
+            .validate()
+            .map_err(|e| format!("Invalid rate profile: {}", e))?;
+        for stream in &self.streams {
+            if stream.canisters.is_empty() {
+                return Err(format!("Stream {} has no canisters", stream.name));
+            }
+            for canister in &stream.canisters {
+                parse_canister_id(canister)
+                    .map_err(|e| format!("Stream {}: {}", stream.name, e))?;
+            }
+            if let RequestType::Update | RequestType::Query = stream.request_type {
+                if stream.method.is_empty() {
+                    return Err(format!("Stream {} needs a method", stream.name));
+                }
+            }
+            if let Some(template) = &stream.arg {
+                render_arg(template, 0).map_err(|e| format!("Stream {}: {}", stream.name, e))?;
+            }
+            if let Some(size) = &stream.payload_size {
+                Byte::from_str(size.trim()).map_err(|e| {
+                    format!("Stream {}: invalid payload size: {:?}", stream.name, e)
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs(self.duration_secs)
+    }
+
+    /// The fraction of the overall rate that `stream` sends.
+    pub fn share(&self, stream: &Stream) -> f64 {
+        let total: u32 = self.streams.iter().map(|stream| stream.weight).sum();
+        stream.weight as f64 / total as f64
+    }
+
+    /// Builds the plan and the send times of each stream.
+    pub fn plans(&self, nonce: &str) -> Vec<(Plan, Vec<Duration>)> {
+        self.streams
+            .iter()
+            .map(|stream| {
+                let send_times = self.rate.send_times(self.share(stream), self.duration());
+                let canister_ids = stream
+                    .canisters
+                    .iter()
+                    .map(|canister| parse_canister_id(canister).unwrap())
+                    .collect();
+                let payload_size = stream
+                    .payload_size
+                    .as_ref()
+                    .map(|size| Byte::from_str(size.trim()).unwrap())
+                    .unwrap_or_else(|| Byte::from_bytes(0));
+                let plan = Plan::new(
+                    send_times.len(),
+                    format!("{} {}", nonce, stream.name),
+                    payload_size,
+                    vec![],
+                    canister_ids,
+                    stream.request_type,
+                    stream.method.clone(),
+                )
+                .with_name(stream.name.clone())
+                .with_arg_template(stream.arg.clone());
+                (plan, send_times)
+            })
+            .collect()
+    }
+
+    /// The senders of the scenario's caller identities, along with their
+    /// sender field.
+    pub fn senders(&self) -> Vec<(AgentSender, Blob)> {
+        (1..=self.identities)
+            .map(|seed| {
+                let keypair = test_identity_keypair(seed);
+                let sender_field = Blob(
+                    UserId::from(PrincipalId::new_self_authenticating(
+                        &ed25519_public_key_to_der(keypair.public_key.to_vec()),
+                    ))
+                    .get()
+                    .into_vec(),
+                );
+                (AgentSender::from_keypair(&keypair), sender_field)
+            })
+            .collect()
+    }
+}
+
+fn parse_canister_id(canister: &str) -> Result<CanisterId, String> {
+    PrincipalId::from_str(canister)
+        .map_err(|e| e.to_string())
+        .and_then(|principal| CanisterId::try_from(principal).map_err(|e| e.to_string()))
+        .map_err(|e| format!("invalid canister id '{}': {}", canister, e))
+}
+
+/// Renders the Candid argument template for the `n`-th request and encodes it.
+pub fn render_arg(template: &str, n: usize) -> Result<Vec<u8>, String> {
+    let mut text = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        text.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find("}}")
+            .ok_or_else(|| format!("unterminated placeholder in '{}'", template))?
+            + start;
+        let placeholder = rest[start + 2..end].trim();
+        if placeholder == "n" {
+            text.push_str(&n.to_string());
+        } else if let Some(max) = placeholder.strip_prefix("rand:") {
+            let max = max
+                .trim()
+                .parse::<u64>()
+                .ok()
+                .filter(|max| *max > 0)
+                .ok_or_else(|| format!("invalid placeholder '{{{{{}}}}}'", placeholder))?;
+            text.push_str(&(splitmix64(n as u64) % max).to_string());
+        } else {
+            return Err(format!("unknown placeholder '{{{{{}}}}}'", placeholder));
+        }
+        rest = &rest[end + 2..];
+    }
+    text.push_str(rest);
+
+    let args: IDLArgs = text
+        .parse()
+        .map_err(|e| format!("invalid Candid argument '{}': {}", text, e))?;
+    args.to_bytes()
+        .map_err(|e| format!("failed to encode Candid argument '{}': {}", text, e))
+}
+
+// A cheap, well-mixing hash, so that `{{rand:N}}` is reproducible across runs.
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(text: &str) -> Vec<u8> {
+        text.parse::<IDLArgs>().unwrap().to_bytes().unwrap()
+    }
+
+    #[test]
+    fn rps_at_follows_the_profile() {
+        let constant = RateProfile::Constant { rps: 5. };
+        assert_eq!(constant.rps_at(0.), 5.);
+        assert_eq!(constant.rps_at(1000.), 5.);
+
+        let ramp = RateProfile::Ramp {
+            from_rps: 10.,
+            to_rps: 110.,
+            secs: 100.,
+        };
+        assert_eq!(ramp.rps_at(0.), 10.);
+        assert_eq!(ramp.rps_at(50.), 60.);
+        assert_eq!(ramp.rps_at(100.), 110.);
+        assert_eq!(ramp.rps_at(200.), 110.);
+
+        let step = RateProfile::Step {
+            steps: vec![
+                RateStep { rps: 1., secs: 10. },
+                RateStep { rps: 2., secs: 5. },
+            ],
+        };
+        assert_eq!(step.rps_at(0.), 1.);
+        assert_eq!(step.rps_at(9.99), 1.);
+        assert_eq!(step.rps_at(10.), 2.);
+        assert_eq!(step.rps_at(14.99), 2.);
+        // The last step is kept until the end of the run.
+        assert_eq!(step.rps_at(100.), 2.);
+        assert_eq!(RateProfile::Step { steps: vec![] }.rps_at(0.), 0.);
+
+        let spike = RateProfile::Spike {
+            base_rps: 1.,
+            spike_rps: 100.,
+            at_secs: 10.,
+            secs: 5.,
+        };
+        assert_eq!(spike.rps_at(9.99), 1.);
+        assert_eq!(spike.rps_at(10.), 100.);
+        assert_eq!(spike.rps_at(14.99), 100.);
+        assert_eq!(spike.rps_at(15.), 1.);
+    }
+
+    #[test]
+    fn send_times_are_spaced_by_the_rate() {
+        let profile = RateProfile::Constant { rps: 1000. };
+        let times = profile.send_times(1., Duration::from_secs(1));
+        assert_eq!(times.len(), 1000);
+        for (i, t) in times.iter().enumerate() {
+            assert_eq!(*t, Duration::from_millis(i as u64));
+        }
+    }
+
+    #[test]
+    fn send_times_integrate_the_share_of_the_rate() {
+        let duration = Duration::from_secs(10);
+        let profile = RateProfile::Ramp {
+            from_rps: 0.,
+            to_rps: 100.,
+            secs: 10.,
+        };
+        let times = profile.send_times(0.5, duration);
+        // Half of the integral of the ramp: 0.5 * 100 * 10 / 2.
+        assert!((times.len() as i64 - 250).abs() <= 1, "{}", times.len());
+        assert!(times.windows(2).all(|w| w[0] <= w[1]));
+        assert!(times.iter().all(|t| *t < duration));
+        let first_half = times
+            .iter()
+            .filter(|t| **t < Duration::from_secs(5))
+            .count();
+        assert!(first_half < times.len() - first_half);
+
+        assert!(profile.send_times(0., duration).is_empty());
+    }
+
+    #[test]
+    fn send_times_skip_steps_without_requests() {
+        let profile = RateProfile::Step {
+            steps: vec![
+                RateStep { rps: 10., secs: 1. },
+                RateStep { rps: 0., secs: 1. },
+                RateStep { rps: 10., secs: 1. },
+            ],
+        };
+        let times = profile.send_times(1., Duration::from_secs(3));
+        assert!((times.len() as i64 - 20).abs() <= 1, "{}", times.len());
+        assert!(!times
+            .iter()
+            .any(|t| *t >= Duration::from_secs(1) && *t < Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn render_arg_replaces_placeholders() {
+        assert_eq!(render_arg("()", 3).unwrap(), encode("()"));
+        assert_eq!(
+            render_arg("(record { to = {{n}}; nonce = {{ n }} })", 7).unwrap(),
+            encode("(record { to = 7; nonce = 7 })")
+        );
+        for n in 0..100 {
+            assert_eq!(
+                render_arg("({{rand:10}})", n).unwrap(),
+                encode(&format!("({})", splitmix64(n as u64) % 10))
+            );
+        }
+        // The same request index always renders the same argument.
+        assert_eq!(
+            render_arg("({{rand:1000}}, {{n}})", 42).unwrap(),
+            render_arg("({{rand:1000}}, {{n}})", 42).unwrap()
+        );
+    }
+
+    #[test]
+    fn render_arg_rejects_invalid_templates() {
+        assert!(render_arg("({{n)", 0)
+            .unwrap_err()
+            .contains("unterminated placeholder"));
+        assert!(render_arg("({{rand:0}})", 0)
+            .unwrap_err()
+            .contains("invalid placeholder"));
+        assert!(render_arg("({{rand:x}})", 0)
+            .unwrap_err()
+            .contains("invalid placeholder"));
+        assert!(render_arg("({{foo}})", 0)
+            .unwrap_err()
+            .contains("unknown placeholder"));
+        assert!(render_arg("(record {", 0)
+            .unwrap_err()
+            .contains("invalid Candid argument"));
+    }
+}
@@ -170,6 +170,8 @@ impl DurationStats {
 /// Represents the statistics around a given set of facts.
 #[derive(Debug, Clone, Serialize)]
 pub struct Summary {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream: Option<String>,
     average: Duration,
     median: Duration,
     max: Duration,
@@ -225,6 +227,12 @@ impl Summary {
         self
     }
 
+    /// Names the scenario stream the summarized facts belong to.
+    pub fn with_stream(mut self, name: String) -> Self {
+        self.stream = Some(name);
+        self
+    }
+
     fn get_succ_rate_histogram(facts: &[Fact]) -> HashMap<usize, u32> {
         let end_times = facts.iter().map(|f| (f.time_request_end, f.is_succ()));
 
@@ -290,6 +298,7 @@ impl Summary {
 
     fn zero() -> Summary {
         Summary {
+            stream: None,
             average: Duration::new(0, 0),
             stddev: Duration::new(0, 0),
             median: Duration::new(0, 0),
@@ -338,7 +347,10 @@ where
 
 impl fmt::Display for Summary {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        writeln!(f, "Summary")?;
+        match &self.stream {
+            Some(name) => writeln!(f, "Summary of stream {}", name)?,
+            None => writeln!(f, "Summary")?,
+        }
         writeln!(
             f,
             "  Average:   {} ms (std: {} ms)",